@@ -0,0 +1,188 @@
+/*!
+Agent Artifacts - Agent 运行产物
+================================
+
+Agent 的结果不只限于聊天文本和工作区文件写入：通过 `agent_emit_artifact` 工具
+登记分析报告、Mermaid 图、CSV 导出等非代码产物。
+
+- 内容存入 blob 存储，引用者为运行通道（`agent_{id}`），删除运行记录时一并释放
+- 产物清单写入 `.ifai/agent_runs/{channel}.artifacts.json`
+- 运行结束时清单随 `agent:result` 事件发送，之后可通过 `get_agent_artifacts` 读取
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// 单个产物的大小上限
+const MAX_ARTIFACT_BYTES: usize = 5 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    /// Markdown 报告
+    Report,
+    /// Mermaid 图（文本）
+    Mermaid,
+    Csv,
+    Json,
+    Text,
+}
+
+impl ArtifactKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "report" | "markdown" | "md" => Some(Self::Report),
+            "mermaid" | "diagram" => Some(Self::Mermaid),
+            "csv" => Some(Self::Csv),
+            "json" => Some(Self::Json),
+            "text" | "txt" => Some(Self::Text),
+            _ => None,
+        }
+    }
+
+    pub fn media_type(self) -> &'static str {
+        match self {
+            Self::Report => "text/markdown",
+            Self::Mermaid => "text/vnd.mermaid",
+            Self::Csv => "text/csv",
+            Self::Json => "application/json",
+            Self::Text => "text/plain",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentArtifact {
+    pub id: String,
+    pub name: String,
+    pub kind: ArtifactKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub blob: crate::blob_store::BlobRef,
+    pub created_at: i64,
+}
+
+/// 清单读写锁（同一进程内串行化）
+static MANIFEST_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+
+fn manifest_lock() -> &'static Mutex<()> {
+    MANIFEST_LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn manifest_path(project_root: &str, channel: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("agent_runs").join(format!("{}.artifacts.json", channel))
+}
+
+fn load_manifest(path: &Path) -> Vec<AgentArtifact> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// 读取运行已登记的产物
+pub fn list(project_root: &str, channel: &str) -> Vec<AgentArtifact> {
+    load_manifest(&manifest_path(project_root, channel))
+}
+
+/// 存储产物内容并登记到运行清单
+pub fn emit(
+    project_root: &str,
+    channel: &str,
+    name: &str,
+    kind: ArtifactKind,
+    content: &str,
+    description: Option<String>,
+) -> Result<AgentArtifact, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Artifact name must not be empty".to_string());
+    }
+    if content.trim().is_empty() {
+        return Err("Artifact content must not be empty".to_string());
+    }
+    if content.len() > MAX_ARTIFACT_BYTES {
+        return Err(format!("Artifact too large: {} bytes (max {})", content.len(), MAX_ARTIFACT_BYTES));
+    }
+
+    let blob = crate::blob_store::put(project_root, content.as_bytes(), kind.media_type(), channel)?;
+
+    let _guard = manifest_lock().lock().map_err(|e| format!("Lock error: {}", e))?;
+    let path = manifest_path(project_root, channel);
+    let mut artifacts = load_manifest(&path);
+    let artifact = AgentArtifact {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        kind,
+        description: description.filter(|d| !d.trim().is_empty()),
+        blob,
+        created_at: chrono::Utc::now().timestamp(),
+    };
+    artifacts.push(artifact.clone());
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create agent_runs dir: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&artifacts).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write artifact manifest: {}", e))?;
+
+    println!("[AgentArtifacts] {} registered {:?} artifact '{}' ({} bytes)", channel, kind, artifact.name, artifact.blob.size);
+    Ok(artifact)
+}
+
+/// 删除运行的产物清单（blob 由调用方按运行通道释放）
+pub fn remove(project_root: &str, channel: &str) -> Result<(), String> {
+    let path = manifest_path(project_root, channel);
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to delete artifact manifest: {}", e))?;
+    }
+    Ok(())
+}
+
+/// 工具执行结果（返回给模型）
+pub fn format_for_model(artifact: &AgentArtifact) -> String {
+    format!(
+        "Artifact '{}' saved ({:?}, {} bytes, id: {}). It will be attached to the run result.",
+        artifact.name, artifact.kind, artifact.blob.size, artifact.id
+    )
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 读取 Agent 运行的产物清单；内容通过 `blob_read` 按 `blob.id` 获取
+#[tauri::command]
+pub fn get_agent_artifacts(project_root: String, run_id: String) -> Result<Vec<AgentArtifact>, String> {
+    let channel = if run_id.starts_with("agent_") { run_id } else { format!("agent_{}", run_id) };
+    Ok(list(&project_root, &channel))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit_and_list() {
+        let dir = std::env::temp_dir().join(format!("ifai_artifacts_{}", uuid::Uuid::new_v4()));
+        let root = dir.to_str().unwrap();
+
+        let report = emit(root, "agent_1", "Summary", ArtifactKind::Report, "# Findings\n", None).unwrap();
+        emit(root, "agent_1", "Flow", ArtifactKind::parse("diagram").unwrap(), "graph TD; A-->B", Some(" ".into())).unwrap();
+        assert!(emit(root, "agent_1", " ", ArtifactKind::Csv, "a,b", None).is_err());
+        assert!(emit(root, "agent_1", "empty", ArtifactKind::Csv, "", None).is_err());
+
+        let artifacts = get_agent_artifacts(root.to_string(), "1".to_string()).unwrap();
+        assert_eq!(artifacts.len(), 2);
+        assert_eq!(artifacts[0], report);
+        assert_eq!(artifacts[1].blob.media_type, "text/vnd.mermaid");
+        assert_eq!(artifacts[1].description, None);
+        assert_eq!(crate::blob_store::get_text(root, &report.blob.id).unwrap(), "# Findings\n");
+
+        remove(root, "agent_1").unwrap();
+        assert!(list(root, "agent_1").is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
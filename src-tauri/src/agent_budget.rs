@@ -0,0 +1,355 @@
+/*!
+Agent Budget - Agent 运行预算
+=============================
+
+每次运行的循环次数、Token 用量与时长上限（`AgentContext.limits`），以及按轮统计的 Token 用量：
+
+- 用量优先取自接口返回的 usage 字段（OpenAI `usage`、Anthropic `message_start` / `message_delta`、
+  Gemini `usageMetadata`），流式请求结束后按 Agent 记录，runner 每轮取走
+- 接口未返回 usage 时按模型分词器估算，并在报告中标记 `estimated`
+- 超出任一预算时 runner 在下一轮开始前停止，带着已有结果正常结束，
+  预算报告随 `agent:result` 一起发送
+- 用量首次达到已配置预算的 `BUDGET_WARNING_RATIO` 时，runner 发送 `BudgetThreshold` 系统通知
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// 未配置时的最大循环次数（与原先固定的 MAX_LOOPS 一致）
+pub const DEFAULT_MAX_ITERATIONS: usize = 12;
+/// 用量达到预算的该比例时发送提醒
+pub const BUDGET_WARNING_RATIO: f64 = 0.8;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AgentLimits {
+    /// 最大循环次数，默认 12
+    pub max_iterations: Option<usize>,
+    /// 输入 + 输出 Token 总量上限
+    pub max_tokens: Option<u64>,
+    /// 运行时长上限（秒）
+    pub max_duration_secs: Option<u64>,
+}
+
+impl AgentLimits {
+    pub fn iterations(&self) -> usize {
+        self.max_iterations.filter(|n| *n > 0).unwrap_or(DEFAULT_MAX_ITERATIONS)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn total(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// OpenAI 兼容接口的 `usage` 字段
+    pub fn from_openai(usage: &Value) -> Option<Self> {
+        Some(Self {
+            prompt_tokens: usage["prompt_tokens"].as_u64()?,
+            completion_tokens: usage["completion_tokens"].as_u64().unwrap_or(0),
+        })
+    }
+
+    /// 合并 Anthropic 流式事件中的用量（`message_start` 给出输入，`message_delta` 给出累计输出）
+    pub fn merge_anthropic_event(&mut self, event: &Value) -> bool {
+        let usage = match event["type"].as_str() {
+            Some("message_start") => &event["message"]["usage"],
+            Some("message_delta") => &event["usage"],
+            _ => return false,
+        };
+        let mut found = false;
+        if let Some(input) = usage["input_tokens"].as_u64() {
+            let cached = usage["cache_read_input_tokens"].as_u64().unwrap_or(0) + usage["cache_creation_input_tokens"].as_u64().unwrap_or(0);
+            self.prompt_tokens = input + cached;
+            found = true;
+        }
+        if let Some(output) = usage["output_tokens"].as_u64() {
+            self.completion_tokens = output;
+            found = true;
+        }
+        found
+    }
+
+    /// Gemini 的 `usageMetadata`（流式响应中为累计值）
+    pub fn from_gemini(chunk: &Value) -> Option<Self> {
+        let usage = &chunk["usageMetadata"];
+        Some(Self {
+            prompt_tokens: usage["promptTokenCount"].as_u64()?,
+            completion_tokens: usage["candidatesTokenCount"].as_u64().unwrap_or(0),
+        })
+    }
+}
+
+// ============================================================================
+// 接口用量（ai_utils 记录，runner 取走）
+// ============================================================================
+
+fn reported() -> &'static Mutex<HashMap<String, TokenUsage>> {
+    static REPORTED: OnceLock<Mutex<HashMap<String, TokenUsage>>> = OnceLock::new();
+    REPORTED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 记录 Agent 最近一次请求的接口用量
+pub fn record_usage(agent_id: &str, usage: TokenUsage) {
+    if let Ok(mut map) = reported().lock() {
+        map.insert(agent_id.to_string(), usage);
+    }
+}
+
+pub fn take_usage(agent_id: &str) -> Option<TokenUsage> {
+    reported().lock().ok().and_then(|mut map| map.remove(agent_id))
+}
+
+// ============================================================================
+// 预算跟踪
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BudgetLimit {
+    Iterations,
+    Tokens,
+    Duration,
+}
+
+impl BudgetLimit {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BudgetLimit::Iterations => "iteration limit",
+            BudgetLimit::Tokens => "token budget",
+            BudgetLimit::Duration => "time limit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LoopUsage {
+    pub iteration: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// 接口未返回 usage，按分词器估算
+    pub estimated: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BudgetReport {
+    pub iterations: usize,
+    pub max_iterations: usize,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u64>,
+    pub elapsed_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_duration_secs: Option<u64>,
+    /// 提前结束运行的预算（未超出时为空）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exceeded: Option<BudgetLimit>,
+    pub loops: Vec<LoopUsage>,
+}
+
+pub struct BudgetTracker {
+    limits: AgentLimits,
+    started: Instant,
+    loops: Vec<LoopUsage>,
+    exceeded: Option<BudgetLimit>,
+    /// 已提醒过的预算
+    warned: Vec<BudgetLimit>,
+}
+
+impl BudgetTracker {
+    pub fn new(limits: AgentLimits) -> Self {
+        Self { limits, started: Instant::now(), loops: Vec::new(), exceeded: None, warned: Vec::new() }
+    }
+
+    /// 从暂停点恢复：沿用已完成的循环（时长从恢复时重新计算）
+    pub fn resumed(limits: AgentLimits, loops: Vec<LoopUsage>) -> Self {
+        Self { loops, ..Self::new(limits) }
+    }
+
+    pub fn loops(&self) -> &[LoopUsage] {
+        &self.loops
+    }
+
+    pub fn usage(&self) -> TokenUsage {
+        self.loops.iter().fold(TokenUsage::default(), |acc, l| TokenUsage {
+            prompt_tokens: acc.prompt_tokens + l.prompt_tokens,
+            completion_tokens: acc.completion_tokens + l.completion_tokens,
+        })
+    }
+
+    fn limit_reached(&self, elapsed: Duration) -> Option<BudgetLimit> {
+        if self.loops.len() >= self.limits.iterations() {
+            return Some(BudgetLimit::Iterations);
+        }
+        if self.limits.max_tokens.is_some_and(|max| self.usage().total() >= max) {
+            return Some(BudgetLimit::Tokens);
+        }
+        if self.limits.max_duration_secs.is_some_and(|max| elapsed >= Duration::from_secs(max)) {
+            return Some(BudgetLimit::Duration);
+        }
+        None
+    }
+
+    /// 开始新一轮前检查预算；超出时记录并返回超出的预算
+    pub fn check(&mut self) -> Option<BudgetLimit> {
+        let exceeded = self.limit_reached(self.started.elapsed());
+        if exceeded.is_some() {
+            self.exceeded = exceeded;
+        }
+        exceeded
+    }
+
+    /// 已配置预算的使用比例（未显式配置循环次数时不计默认上限）
+    fn used_fractions(&self, elapsed: Duration) -> Vec<(BudgetLimit, f64)> {
+        let mut fractions = Vec::new();
+        if let Some(max) = self.limits.max_iterations.filter(|n| *n > 0) {
+            fractions.push((BudgetLimit::Iterations, self.loops.len() as f64 / max as f64));
+        }
+        if let Some(max) = self.limits.max_tokens.filter(|n| *n > 0) {
+            fractions.push((BudgetLimit::Tokens, self.usage().total() as f64 / max as f64));
+        }
+        if let Some(max) = self.limits.max_duration_secs.filter(|n| *n > 0) {
+            fractions.push((BudgetLimit::Duration, elapsed.as_secs_f64() / max as f64));
+        }
+        fractions
+    }
+
+    /// 本次首次达到提醒比例的预算及其使用比例，每个预算只返回一次
+    pub fn crossed_threshold(&mut self) -> Option<(BudgetLimit, f64)> {
+        let crossed = self
+            .used_fractions(self.started.elapsed())
+            .into_iter()
+            .find(|(limit, fraction)| *fraction >= BUDGET_WARNING_RATIO && !self.warned.contains(limit))?;
+        self.warned.push(crossed.0);
+        Some(crossed)
+    }
+
+    /// 距时长上限的剩余时间
+    pub fn remaining_time(&self) -> Option<Duration> {
+        self.limits.max_duration_secs.map(|max| Duration::from_secs(max).saturating_sub(self.started.elapsed()))
+    }
+
+    pub fn iterations(&self) -> usize {
+        self.loops.len()
+    }
+
+    /// 记录一轮的用量（`reported` 为空时使用估算值）
+    pub fn record(&mut self, reported: Option<TokenUsage>, estimate: impl FnOnce() -> TokenUsage) -> &LoopUsage {
+        let (usage, estimated) = match reported {
+            Some(usage) => (usage, false),
+            None => (estimate(), true),
+        };
+        self.loops.push(LoopUsage {
+            iteration: self.loops.len() + 1,
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            estimated,
+        });
+        &self.loops[self.loops.len() - 1]
+    }
+
+    /// 直接标记超出（如请求因时长上限被中断）
+    pub fn mark_exceeded(&mut self, limit: BudgetLimit) {
+        self.exceeded = Some(limit);
+    }
+
+    pub fn report(&self) -> BudgetReport {
+        let usage = self.usage();
+        BudgetReport {
+            iterations: self.loops.len(),
+            max_iterations: self.limits.iterations(),
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total(),
+            max_tokens: self.limits.max_tokens,
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            max_duration_secs: self.limits.max_duration_secs,
+            exceeded: self.exceeded,
+            loops: self.loops.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_usage_parsing() {
+        assert_eq!(
+            TokenUsage::from_openai(&json!({ "prompt_tokens": 120, "completion_tokens": 30, "total_tokens": 150 })),
+            Some(TokenUsage { prompt_tokens: 120, completion_tokens: 30 })
+        );
+        assert_eq!(TokenUsage::from_openai(&Value::Null), None);
+
+        let mut anthropic = TokenUsage::default();
+        assert!(anthropic.merge_anthropic_event(&json!({ "type": "message_start", "message": { "usage": { "input_tokens": 50, "cache_read_input_tokens": 10, "output_tokens": 1 } } })));
+        assert!(!anthropic.merge_anthropic_event(&json!({ "type": "content_block_delta" })));
+        assert!(anthropic.merge_anthropic_event(&json!({ "type": "message_delta", "usage": { "output_tokens": 42 } })));
+        assert_eq!(anthropic, TokenUsage { prompt_tokens: 60, completion_tokens: 42 });
+
+        let gemini = TokenUsage::from_gemini(&json!({ "usageMetadata": { "promptTokenCount": 7, "candidatesTokenCount": 3 } }));
+        assert_eq!(gemini.map(|u| u.total()), Some(10));
+    }
+
+    #[test]
+    fn test_budget_limits() {
+        let mut tracker = BudgetTracker::new(AgentLimits { max_iterations: Some(3), max_tokens: Some(500), ..Default::default() });
+        assert_eq!(tracker.check(), None);
+        tracker.record(Some(TokenUsage { prompt_tokens: 200, completion_tokens: 50 }), || unreachable!());
+        assert_eq!(tracker.check(), None);
+        let estimated = tracker.record(None, || TokenUsage { prompt_tokens: 260, completion_tokens: 10 });
+        assert!(estimated.estimated);
+        assert_eq!(tracker.check(), Some(BudgetLimit::Tokens));
+
+        let report = tracker.report();
+        assert_eq!((report.iterations, report.max_iterations, report.total_tokens), (2, 3, 520));
+        assert_eq!(report.exceeded, Some(BudgetLimit::Tokens));
+        assert_eq!(report.loops[1].iteration, 2);
+
+        let mut default = BudgetTracker::new(AgentLimits::default());
+        for _ in 0..DEFAULT_MAX_ITERATIONS {
+            assert_eq!(default.check(), None);
+            default.record(Some(TokenUsage::default()), TokenUsage::default);
+        }
+        assert_eq!(default.check(), Some(BudgetLimit::Iterations));
+        assert!(default.remaining_time().is_none());
+
+        let resumed = BudgetTracker::resumed(AgentLimits { max_iterations: Some(2), ..Default::default() }, report.loops.clone());
+        assert_eq!((resumed.iterations(), resumed.usage().total()), (2, 520));
+        assert_eq!(BudgetTracker::resumed(AgentLimits::default(), resumed.loops().to_vec()).report().iterations, 2);
+
+        let timed = BudgetTracker::new(AgentLimits { max_duration_secs: Some(0), ..Default::default() });
+        assert_eq!(timed.limit_reached(Duration::from_secs(1)), Some(BudgetLimit::Duration));
+    }
+
+    #[test]
+    fn test_budget_threshold_crossed_once() {
+        let mut tracker = BudgetTracker::new(AgentLimits { max_tokens: Some(1000), ..Default::default() });
+        tracker.record(Some(TokenUsage { prompt_tokens: 700, completion_tokens: 0 }), || unreachable!());
+        assert_eq!(tracker.crossed_threshold(), None);
+        tracker.record(Some(TokenUsage { prompt_tokens: 100, completion_tokens: 50 }), || unreachable!());
+        assert_eq!(tracker.crossed_threshold(), Some((BudgetLimit::Tokens, 0.85)));
+        tracker.record(Some(TokenUsage { prompt_tokens: 100, completion_tokens: 0 }), || unreachable!());
+        assert_eq!(tracker.crossed_threshold(), None);
+
+        // 未配置循环次数时，默认上限不触发提醒
+        let mut default = BudgetTracker::new(AgentLimits::default());
+        for _ in 0..DEFAULT_MAX_ITERATIONS {
+            default.record(Some(TokenUsage::default()), TokenUsage::default);
+        }
+        assert_eq!(default.crossed_threshold(), None);
+    }
+}
@@ -0,0 +1,283 @@
+/*!
+Agent Log - Agent 运行日志分级与记录
+====================================
+
+Agent 运行会产生大量日志，全部推送到前端会刷屏。
+
+- 每个运行可设置详细程度（默认 `info`），低于该级别的日志 / 思考 / 进度事件不发送到前端
+- 工具调用、状态、结果等驱动 UI 流程的事件始终发送
+- 所有事件（不论级别）都写入 `.ifai/agent_runs/{channel}.jsonl`，供事后查看
+- 超过 `BLOB_THRESHOLD_BYTES` 的工具结果存入 blob 存储，记录中只保留引用标记
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::AppHandle;
+
+use crate::events::{emit_event, LogLevel, StreamEvent};
+
+/// 工具结果超过该大小时存入 blob 存储
+const BLOB_THRESHOLD_BYTES: usize = 16 * 1024;
+
+/// 记录文件中的一条事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptEntry {
+    pub timestamp: i64,
+    pub level: LogLevel,
+    pub event: StreamEvent,
+}
+
+#[derive(Default)]
+struct RunLog {
+    verbosity: LogLevel,
+    transcript: Option<BufWriter<File>>,
+    project_root: Option<String>,
+}
+
+/// 以事件通道（`agent_{id}`）为键
+static RUNS: OnceLock<Mutex<HashMap<String, RunLog>>> = OnceLock::new();
+
+fn runs() -> &'static Mutex<HashMap<String, RunLog>> {
+    RUNS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn channel_for(agent_id: &str) -> String {
+    format!("agent_{}", agent_id)
+}
+
+fn transcript_path(project_root: &str, channel: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("agent_runs").join(format!("{}.jsonl", channel))
+}
+
+/// 是否应发送到前端
+fn should_emit(event: &StreamEvent, verbosity: LogLevel) -> bool {
+    !event.is_filterable() || event.level() >= verbosity
+}
+
+/// 设置运行的详细程度（可在运行中调整）
+pub fn set_verbosity(agent_id: &str, level: LogLevel) {
+    if let Ok(mut runs) = runs().lock() {
+        runs.entry(channel_for(agent_id)).or_default().verbosity = level;
+    }
+}
+
+/// 开始记录运行，打开记录文件
+pub fn begin_run(channel: &str, project_root: &str) {
+    let path = transcript_path(project_root, channel);
+    let file = path.parent()
+        .map(std::fs::create_dir_all)
+        .and_then(|r| r.ok())
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&path).ok());
+    if file.is_none() {
+        eprintln!("[AgentLog] Failed to open transcript: {}", path.display());
+    }
+    if let Ok(mut runs) = runs().lock() {
+        let run = runs.entry(channel.to_string()).or_default();
+        run.transcript = file.map(BufWriter::new);
+        run.project_root = Some(project_root.to_string());
+    }
+}
+
+/// 大的工具结果替换为 blob 引用，保持记录文件精简
+fn externalize_large_output(event: &StreamEvent, project_root: Option<&str>, channel: &str) -> StreamEvent {
+    match (event, project_root) {
+        (StreamEvent::ToolResult { tool_call_id, result, success }, Some(root)) if result.len() > BLOB_THRESHOLD_BYTES => {
+            StreamEvent::ToolResult {
+                tool_call_id: tool_call_id.clone(),
+                result: crate::blob_store::externalize_text(root, result, channel),
+                success: *success,
+            }
+        }
+        _ => event.clone(),
+    }
+}
+
+/// 结束运行，刷新并关闭记录文件
+pub fn end_run(channel: &str) {
+    crate::tool_output::clear_run(channel);
+    if let Ok(mut runs) = runs().lock() {
+        if let Some(mut run) = runs.remove(channel) {
+            if let Some(writer) = run.transcript.as_mut() {
+                let _ = writer.flush();
+            }
+        }
+    }
+}
+
+/// 记录事件，并按运行的详细程度决定是否发送到前端
+///
+/// 未通过 `begin_run` 注册的通道直接发送。
+pub fn emit(app: &AppHandle, channel: &str, event: &StreamEvent) {
+    let verbosity = {
+        let Ok(mut runs) = runs().lock() else {
+            emit_event(app, channel, event);
+            return;
+        };
+        match runs.get_mut(channel) {
+            Some(run) => {
+                if let Some(writer) = run.transcript.as_mut() {
+                    let entry = TranscriptEntry {
+                        timestamp: chrono::Utc::now().timestamp_millis(),
+                        level: event.level(),
+                        event: externalize_large_output(event, run.project_root.as_deref(), channel),
+                    };
+                    if let Ok(line) = serde_json::to_string(&entry) {
+                        let _ = writeln!(writer, "{}", line);
+                    }
+                }
+                run.verbosity
+            }
+            None => LogLevel::Debug,
+        }
+    };
+
+    if should_emit(event, verbosity) {
+        emit_event(app, channel, event);
+    }
+}
+
+/// 发送一条分级日志
+pub fn log(app: &AppHandle, channel: &str, level: LogLevel, message: impl Into<String>) {
+    emit(app, channel, &StreamEvent::Log { message: message.into(), level });
+}
+
+fn read_transcript(path: &Path, min_level: LogLevel) -> Result<Vec<TranscriptEntry>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open transcript: {}", e))?;
+    Ok(BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<TranscriptEntry>(&line).ok())
+        .filter(|entry| entry.level >= min_level)
+        .collect())
+}
+
+/// 将记录中的 blob 引用还原为完整内容
+fn resolve_blobs(project_root: &str, entries: &mut [TranscriptEntry]) {
+    for entry in entries {
+        if let StreamEvent::ToolResult { result, .. } = &mut entry.event {
+            *result = crate::blob_store::resolve_markers(project_root, result);
+        }
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 设置 Agent 运行的日志详细程度
+#[tauri::command]
+pub fn set_agent_log_verbosity(id: String, level: LogLevel) {
+    set_verbosity(&id, level);
+}
+
+/// 读取 Agent 运行的完整记录
+///
+/// 默认还原 blob 引用；`resolve_blobs` 为 false 时保留引用标记，由前端按需调用 `blob_read`
+#[tauri::command]
+pub fn get_agent_transcript(
+    project_root: String,
+    id: String,
+    min_level: Option<LogLevel>,
+    resolve_blobs: Option<bool>,
+) -> Result<Vec<TranscriptEntry>, String> {
+    let channel = channel_for(&id);
+    // 运行中的记录先刷新到磁盘
+    if let Ok(mut runs) = runs().lock() {
+        if let Some(writer) = runs.get_mut(&channel).and_then(|r| r.transcript.as_mut()) {
+            let _ = writer.flush();
+        }
+    }
+    let mut entries = read_transcript(&transcript_path(&project_root, &channel), min_level.unwrap_or(LogLevel::Debug))?;
+    if resolve_blobs.unwrap_or(true) {
+        self::resolve_blobs(&project_root, &mut entries);
+    }
+    Ok(entries)
+}
+
+/// 删除 Agent 运行记录及产物清单，并释放其引用的 blob
+#[tauri::command]
+pub fn delete_agent_transcript(project_root: String, id: String) -> Result<(), String> {
+    let channel = channel_for(&id);
+    let path = transcript_path(&project_root, &channel);
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to delete transcript: {}", e))?;
+    }
+    crate::agent_artifacts::remove(&project_root, &channel)?;
+    crate::blob_store::release(&project_root, &channel)?;
+    Ok(())
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_event(level: LogLevel) -> StreamEvent {
+        StreamEvent::Log { message: "m".to_string(), level }
+    }
+
+    #[test]
+    fn test_should_emit_respects_verbosity() {
+        assert!(!should_emit(&log_event(LogLevel::Debug), LogLevel::Info));
+        assert!(should_emit(&log_event(LogLevel::Warn), LogLevel::Info));
+        assert!(!should_emit(&StreamEvent::Thinking { content: "..".to_string() }, LogLevel::Warn));
+        // 结果与状态事件不受过滤
+        assert!(should_emit(&StreamEvent::Result { result: "ok".to_string() }, LogLevel::Error));
+        assert!(should_emit(&StreamEvent::Status { status: "running".to_string(), progress: None }, LogLevel::Error));
+    }
+
+    #[test]
+    fn test_transcript_roundtrip() {
+        let root = std::env::temp_dir().join(format!("ifai_agent_log_{}", uuid::Uuid::new_v4()));
+        let root_str = root.to_string_lossy().to_string();
+        let channel = channel_for("t1");
+
+        begin_run(&channel, &root_str);
+        {
+            let mut runs = runs().lock().unwrap();
+            let writer = runs.get_mut(&channel).unwrap().transcript.as_mut().unwrap();
+            for level in [LogLevel::Debug, LogLevel::Info, LogLevel::Error] {
+                let entry = TranscriptEntry { timestamp: 0, level, event: log_event(level) };
+                writeln!(writer, "{}", serde_json::to_string(&entry).unwrap()).unwrap();
+            }
+        }
+        end_run(&channel);
+
+        let all = get_agent_transcript(root_str.clone(), "t1".to_string(), None, None).unwrap();
+        assert_eq!(all.len(), 3);
+        let errors = get_agent_transcript(root_str, "t1".to_string(), Some(LogLevel::Warn), None).unwrap();
+        assert_eq!(errors.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_large_tool_output_stored_as_blob() {
+        let root = std::env::temp_dir().join(format!("ifai_agent_log_{}", uuid::Uuid::new_v4()));
+        let root_str = root.to_string_lossy().to_string();
+        let output = "ok\n".repeat(BLOB_THRESHOLD_BYTES);
+        let event = StreamEvent::ToolResult { tool_call_id: "c1".to_string(), result: output.clone(), success: true };
+
+        let stored = externalize_large_output(&event, Some(&root_str), "agent_t2");
+        let mut entries = vec![TranscriptEntry { timestamp: 0, level: LogLevel::Info, event: stored }];
+        match &entries[0].event {
+            StreamEvent::ToolResult { result, .. } => assert!(result.starts_with("[blob:sha256:") && result.len() < 1024),
+            _ => unreachable!(),
+        }
+        resolve_blobs(&root_str, &mut entries);
+        match &entries[0].event {
+            StreamEvent::ToolResult { result, .. } => assert_eq!(result, &output),
+            _ => unreachable!(),
+        }
+
+        delete_agent_transcript(root_str, "t2".to_string()).unwrap();
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -12,6 +12,8 @@ pub enum AgentStatus {
     Completed,
     Failed(String),
     Stopped,
+    /// 状态已保存，可通过 `resume_agent` 继续
+    Paused,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +23,12 @@ pub struct AgentContext {
     pub initial_prompt: String,
     pub variables: HashMap<String, String>,
     pub provider_config: crate::core_traits::ai::AIProviderConfig,
+    /// 循环次数 / Token / 时长预算
+    #[serde(default)]
+    pub limits: crate::agent_budget::AgentLimits,
+    /// 随任务一起发送的图片（data URL 或 http(s) URL）
+    #[serde(default)]
+    pub images: Vec<String>,
 }
 
 #[async_trait]
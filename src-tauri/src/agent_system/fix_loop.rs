@@ -0,0 +1,332 @@
+/*!
+Fix Loop - 构建 / lint 错误自动修复
+===================================
+
+`launch_fix_agent` 反复运行一条失败的命令（如 `cargo build`、`npm run lint`），直到通过：
+
+1. 运行命令，退出码为 0 时结束
+2. 用 `error_commands` 解析输出中的错误，并为前几个错误生成修复上下文（出错位置附近的代码）
+3. 以错误列表为任务启动一轮 `fix` Agent，写入照常经过审批
+4. 重复直到命令通过、修复轮数用完、Agent 被停止，或两轮之间错误没有任何变化
+
+每个阶段通过 `fix_agent:progress` 事件报告进度。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use super::{runner, tools, AgentContext, Supervisor};
+use crate::agent_budget::AgentLimits;
+use crate::commands::error_commands::{self, ErrorParserState, ParsedErrorFrontend};
+use crate::core_traits::ai::AIProviderConfig;
+
+/// 默认修复轮数
+const DEFAULT_MAX_ITERATIONS: usize = 5;
+const MAX_ITERATIONS: usize = 10;
+/// 命令默认超时（秒）
+const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 300;
+const MAX_COMMAND_TIMEOUT_SECS: u64 = 1800;
+/// 任务中列出的错误数
+const MAX_ERRORS_IN_TASK: usize = 10;
+/// 附带代码上下文的错误数
+const MAX_FIX_CONTEXTS: usize = 5;
+/// 任务中附带的原始输出（末尾）字符数
+const OUTPUT_TAIL_CHARS: usize = 4000;
+
+#[derive(Debug, Clone, Default)]
+pub struct FixLoopOptions {
+    /// 修复轮数上限，默认 5，最多 10
+    pub max_iterations: Option<usize>,
+    /// 命令超时（秒），默认 300
+    pub timeout_secs: Option<u64>,
+    /// 相对项目根目录的工作目录
+    pub cwd: Option<String>,
+    /// 每轮修复 Agent 的预算
+    pub limits: AgentLimits,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixPhase {
+    /// 正在运行命令
+    Running,
+    /// 命令失败，修复 Agent 正在处理
+    Fixing,
+    Passed,
+    /// 修复轮数用完仍未通过
+    Failed,
+    /// 因 Agent 被停止、没有进展或命令无法运行而提前结束
+    Stopped,
+}
+
+/// `fix_agent:progress` 事件负载
+#[derive(Debug, Clone, Serialize)]
+pub struct FixProgress {
+    pub fix_id: String,
+    pub command: String,
+    pub iteration: usize,
+    pub max_iterations: usize,
+    pub phase: FixPhase,
+    /// 本轮修复 Agent 的 id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub errors: Vec<ParsedErrorFrontend>,
+    pub message: String,
+}
+
+struct CommandRun {
+    exit_code: Option<i32>,
+    output: String,
+}
+
+/// 运行修复循环直到结束，返回最后一次进度
+#[allow(clippy::too_many_arguments)]
+pub async fn run_fix_loop(
+    app: AppHandle,
+    supervisor: Supervisor,
+    fix_id: String,
+    command: String,
+    project_root: String,
+    provider_config: AIProviderConfig,
+    options: FixLoopOptions,
+    session_id: Option<String>,
+) -> FixProgress {
+    let max_iterations = options.max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS).clamp(1, MAX_ITERATIONS);
+    let mut progress = FixProgress {
+        fix_id: fix_id.clone(),
+        command: command.clone(),
+        iteration: 0,
+        max_iterations,
+        phase: FixPhase::Running,
+        agent_id: None,
+        exit_code: None,
+        errors: Vec::new(),
+        message: String::new(),
+    };
+    let emit = |progress: &FixProgress| {
+        let _ = app.emit("fix_agent:progress", progress);
+    };
+
+    let root = tools::calibrate_project_root(&project_root);
+    let cwd = options.cwd.clone().unwrap_or_else(|| ".".to_string());
+    let working_dir = match crate::commands::core_wrappers::ensure_in_root(&root, &cwd, "run") {
+        Ok(dir) => dir,
+        Err(e) => return finish(progress, FixPhase::Stopped, e, &emit),
+    };
+    let timeout_secs = options.timeout_secs.unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECS).clamp(1, MAX_COMMAND_TIMEOUT_SECS);
+    let mut previous_errors: Option<String> = None;
+
+    for round in 0..=max_iterations {
+        progress.iteration = round;
+        progress.agent_id = None;
+        progress.phase = FixPhase::Running;
+        progress.message = format!("Running `{}`", command);
+        emit(&progress);
+
+        let run = match run_command(&app, &format!("fix_{}_{}", fix_id, round), &command, &working_dir, timeout_secs).await {
+            Ok(run) => run,
+            Err(e) => return finish(progress, FixPhase::Stopped, e, &emit),
+        };
+        progress.exit_code = run.exit_code;
+        if run.exit_code == Some(0) {
+            progress.errors.clear();
+            let message = if round == 0 {
+                "The command already passes; nothing to fix.".to_string()
+            } else {
+                format!("The command passes after {} fix round(s).", round)
+            };
+            return finish(progress, FixPhase::Passed, message, &emit);
+        }
+
+        progress.errors = parse_errors(&app, &run.output);
+        if round == max_iterations {
+            let message = format!("Still failing after {} fix round(s).", max_iterations);
+            return finish(progress, FixPhase::Failed, message, &emit);
+        }
+
+        // 错误与上一轮完全相同，说明修复没有进展
+        let signature = error_signature(&progress.errors, &run.output);
+        if previous_errors.as_deref() == Some(signature.as_str()) {
+            let message = "The errors did not change after the last fix round; stopping.".to_string();
+            return finish(progress, FixPhase::Stopped, message, &emit);
+        }
+        previous_errors = Some(signature);
+
+        let contexts = fix_contexts(&app, &progress.errors, &working_dir);
+        let task = build_fix_task(&command, &cwd, &progress.errors, &contexts, &run.output);
+        let agent_id = format!("{}-{}", fix_id, round + 1);
+        progress.agent_id = Some(agent_id.clone());
+        progress.phase = FixPhase::Fixing;
+        progress.message = format!("Fixing {} error(s) (round {} of {})", progress.errors.len().max(1), round + 1, max_iterations);
+        emit(&progress);
+
+        crate::privacy::bind_agent(&agent_id, session_id.as_deref());
+        supervisor.register_agent(agent_id.clone(), "fix".to_string()).await;
+        let context = AgentContext {
+            project_root: project_root.clone(),
+            task_description: task,
+            initial_prompt: String::new(),
+            variables: HashMap::new(),
+            provider_config: provider_config.clone(),
+            limits: options.limits.clone(),
+            images: Vec::new(),
+        };
+        if let Err(e) = runner::run_agent_task(app.clone(), supervisor.clone(), agent_id, "fix".to_string(), context).await {
+            return finish(progress, FixPhase::Stopped, format!("Fix agent stopped: {}", e), &emit);
+        }
+    }
+    unreachable!("the last round always returns")
+}
+
+fn finish(mut progress: FixProgress, phase: FixPhase, message: String, emit: &impl Fn(&FixProgress)) -> FixProgress {
+    println!("[FixLoop] {} {:?}: {}", progress.fix_id, phase, message);
+    progress.phase = phase;
+    progress.message = message;
+    emit(&progress);
+    progress
+}
+
+async fn run_command(app: &AppHandle, stream_id: &str, command: &str, working_dir: &Path, timeout_secs: u64) -> Result<CommandRun, String> {
+    let result = crate::commands::bash_streaming::execute_bash_command_streaming(
+        command.to_string(),
+        Some(working_dir.to_string_lossy().to_string()),
+        Some(timeout_secs * 1000),
+        Some(crate::commands::bash_streaming::filter_env(std::env::vars(), None)),
+        false,
+        stream_id.to_string(),
+        None,
+        app.clone(),
+    )
+    .await?;
+    if let Some(id) = result.background_id {
+        return Err(format!("`{}` kept running in the background ({}); the fix loop needs a command that exits", command, id));
+    }
+
+    let mut output = String::new();
+    for content in [&result.stdout, &result.stderr].into_iter().flatten() {
+        output.push_str(content);
+        output.push('\n');
+    }
+    if result.timed_out {
+        output.push_str(&format!("(timed out after {}s)\n", timeout_secs));
+    }
+    Ok(CommandRun { exit_code: if result.timed_out { None } else { Some(result.exit_code) }, output })
+}
+
+fn parse_errors(app: &AppHandle, output: &str) -> Vec<ParsedErrorFrontend> {
+    let state = app.state::<Mutex<ErrorParserState>>();
+    error_commands::parse_terminal_errors(state, output.to_string()).unwrap_or_default()
+}
+
+/// 前几个错误的代码上下文：(错误, 出错位置附近的代码)
+fn fix_contexts(app: &AppHandle, errors: &[ParsedErrorFrontend], working_dir: &Path) -> Vec<(usize, String)> {
+    errors
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| !e.file.is_empty() && e.line > 0)
+        .take(MAX_FIX_CONTEXTS)
+        .filter_map(|(index, e)| {
+            let file = working_dir.join(&e.file);
+            let context = error_commands::generate_error_fix_context(
+                app.state::<Mutex<ErrorParserState>>(),
+                file.to_string_lossy().to_string(),
+                e.code.clone(),
+                e.message.clone(),
+                e.line,
+                e.column,
+                e.language.clone(),
+                e.raw_line.clone(),
+            )
+            .ok()?;
+            Some((index, context.code_context))
+        })
+        .collect()
+}
+
+/// 判断两轮之间是否有进展的依据
+fn error_signature(errors: &[ParsedErrorFrontend], output: &str) -> String {
+    if errors.is_empty() {
+        return output.trim().to_string();
+    }
+    errors.iter().map(|e| format!("{}:{}:{}", e.file, e.line, e.message)).collect::<Vec<_>>().join("\n")
+}
+
+fn output_tail(output: &str) -> &str {
+    let trimmed = output.trim_end();
+    let start = trimmed.len().saturating_sub(OUTPUT_TAIL_CHARS);
+    let start = (start..=trimmed.len()).find(|i| trimmed.is_char_boundary(*i)).unwrap_or(trimmed.len());
+    &trimmed[start..]
+}
+
+/// 修复 Agent 的任务描述
+fn build_fix_task(
+    command: &str,
+    cwd: &str,
+    errors: &[ParsedErrorFrontend],
+    contexts: &[(usize, String)],
+    output: &str,
+) -> String {
+    let mut task = format!(
+        "The command `{}` (run in `{}`) fails. Fix the code so that it passes.\n\
+         Make the smallest changes that fix the errors; do not change unrelated code or weaken tests. \
+         The command is re-run automatically after you finish, so you do not need to run it yourself.\n",
+        command, cwd
+    );
+    if !errors.is_empty() {
+        task.push_str(&format!("\n## Errors ({} found)\n", errors.len()));
+        for (index, e) in errors.iter().take(MAX_ERRORS_IN_TASK).enumerate() {
+            let column = e.column.map(|c| format!(":{}", c)).unwrap_or_default();
+            task.push_str(&format!("{}. {}:{}{} [{}] {}\n", index + 1, e.file, e.line, column, e.code, e.message));
+            if let Some((_, context)) = contexts.iter().find(|(i, _)| *i == index) {
+                task.push_str(&format!("```\n{}\n```\n", context.trim_end()));
+            }
+        }
+        if errors.len() > MAX_ERRORS_IN_TASK {
+            task.push_str(&format!("... and {} more\n", errors.len() - MAX_ERRORS_IN_TASK));
+        }
+    }
+    task.push_str(&format!("\n## Command output (tail)\n```\n{}\n```\n", output_tail(output)));
+    task
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(file: &str, line: u32, message: &str) -> ParsedErrorFrontend {
+        ParsedErrorFrontend {
+            code: "E0308".to_string(),
+            message: message.to_string(),
+            file: file.to_string(),
+            line,
+            column: Some(5),
+            level: "Error".to_string(),
+            language: "Rust".to_string(),
+            raw_line: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_build_fix_task() {
+        let errors: Vec<ParsedErrorFrontend> = (1..=12).map(|i| error("src/lib.rs", i, "mismatched types")).collect();
+        let contexts = vec![(0, "let x: u32 = \"a\";".to_string())];
+        let output = format!("{}error: could not compile", "x".repeat(OUTPUT_TAIL_CHARS));
+        let task = build_fix_task("cargo build", ".", &errors, &contexts, &output);
+
+        assert!(task.starts_with("The command `cargo build` (run in `.`) fails."));
+        assert!(task.contains("## Errors (12 found)\n1. src/lib.rs:1:5 [E0308] mismatched types\n```\nlet x: u32 = \"a\";\n```\n2. "));
+        assert!(task.contains("10. src/lib.rs:10:5") && !task.contains("11. src/lib.rs"));
+        assert!(task.contains("... and 2 more"));
+        assert!(task.trim_end().ends_with("error: could not compile\n```"));
+        assert!(task.len() < OUTPUT_TAIL_CHARS + 2000);
+
+        assert_eq!(error_signature(&errors[..1], "ignored"), "src/lib.rs:1:mismatched types");
+        assert_eq!(error_signature(&[], " raw output \n"), "raw output");
+        assert_eq!(output_tail("短输出"), "短输出");
+    }
+}
@@ -6,11 +6,17 @@ pub mod supervisor;
 pub mod runner;
 #[cfg(feature = "commercial")]
 pub mod tools;
+#[cfg(feature = "commercial")]
+pub mod orchestrator;
+#[cfg(feature = "commercial")]
+pub mod persistence;
+#[cfg(feature = "commercial")]
+pub mod fix_loop;
 
 #[cfg(feature = "commercial")]
 pub use base::{AgentStatus, AgentContext};
 #[cfg(feature = "commercial")]
-pub use supervisor::Supervisor;
+pub use supervisor::{ApprovalDecision, Supervisor};
 
 #[cfg(not(feature = "commercial"))]
 pub struct Supervisor;
@@ -0,0 +1,533 @@
+/*!
+Orchestrator - 多 Agent 任务图编排
+==================================
+
+规划 Agent 通过 `launch_subagent` 声明子 Agent 及其依赖，依赖全部完成后子 Agent 才会启动，
+从而把 “explore → plan → implement → test” 拆成多个协作的 Agent：
+
+- 子 Agent 的任务中附带所依赖子 Agent 的输出与共享黑板内容
+- `blackboard_write` / `blackboard_read`：同一编排内所有 Agent 共享的键值上下文
+- `wait_for_subagents`：规划 Agent 等待子 Agent 完成并取回结果
+- 任务图每次变化都会发送 `agent:orchestration` 汇总事件
+
+依赖只能指向已声明的子 Agent，因此任务图天然无环；依赖失败的子 Agent 不再启动。
+子 Agent 不能继续派生子 Agent。
+*/
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::AppHandle;
+
+use crate::agent_system::base::AgentContext;
+use crate::agent_system::runner;
+use crate::agent_system::supervisor::Supervisor;
+use crate::events::{emit_event, OrchestrationEvent, SubagentStatus};
+
+/// 单个编排中子 Agent 的数量上限
+const MAX_SUBAGENTS: usize = 16;
+/// 依赖输出附加到子任务时的最大字符数
+const MAX_DEPENDENCY_OUTPUT: usize = 4000;
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+const DEFAULT_AGENT_TYPE: &str = "explore";
+
+const TOOL_LAUNCH: &str = "launch_subagent";
+const TOOL_WAIT: &str = "wait_for_subagents";
+const TOOL_BLACKBOARD_WRITE: &str = "blackboard_write";
+const TOOL_BLACKBOARD_READ: &str = "blackboard_read";
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubagentState {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+    /// 依赖失败或编排被取消，未启动
+    Skipped,
+}
+
+impl SubagentState {
+    fn is_settled(self) -> bool {
+        !matches!(self, Self::Pending | Self::Running)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Skipped => "skipped",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubagentNode {
+    /// 子 Agent 的运行 id：`<root>-<name>`
+    pub id: String,
+    pub name: String,
+    pub agent_type: String,
+    pub task: String,
+    /// 依赖的子 Agent 名称
+    pub depends_on: Vec<String>,
+    pub state: SubagentState,
+    /// 完成时为最终输出，失败时为错误原因
+    pub output: Option<String>,
+}
+
+struct Orchestration {
+    /// 规划 Agent 的上下文，子 Agent 沿用其项目与模型配置
+    context: AgentContext,
+    nodes: Vec<SubagentNode>,
+    blackboard: BTreeMap<String, Value>,
+    root_finished: bool,
+}
+
+impl Orchestration {
+    fn new(context: AgentContext) -> Self {
+        Self { context, nodes: Vec::new(), blackboard: BTreeMap::new(), root_finished: false }
+    }
+
+    fn node(&self, name: &str) -> Option<&SubagentNode> {
+        self.nodes.iter().find(|n| n.name == name)
+    }
+
+    fn add(&mut self, root_id: &str, name: &str, agent_type: &str, task: &str, depends_on: Vec<String>) -> Result<&SubagentNode, String> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid subagent name `{}`: use letters, digits, '-' or '_'", name));
+        }
+        if self.node(name).is_some() {
+            return Err(format!("Subagent `{}` already exists", name));
+        }
+        if self.nodes.len() >= MAX_SUBAGENTS {
+            return Err(format!("Too many subagents (max {})", MAX_SUBAGENTS));
+        }
+        if let Some(missing) = depends_on.iter().find(|dep| self.node(dep).is_none()) {
+            return Err(format!("Unknown dependency `{}`: launch it before subagents that depend on it", missing));
+        }
+        self.nodes.push(SubagentNode {
+            id: format!("{}-{}", root_id, name),
+            name: name.to_string(),
+            agent_type: agent_type.to_string(),
+            task: task.to_string(),
+            depends_on,
+            state: SubagentState::Pending,
+            output: None,
+        });
+        Ok(&self.nodes[self.nodes.len() - 1])
+    }
+
+    /// 取出依赖已全部完成的待启动节点并标记为运行中；依赖失败的节点标记为跳过
+    fn take_ready(&mut self) -> Vec<SubagentNode> {
+        let mut ready = Vec::new();
+        // 依赖总在前面声明，按顺序处理一遍即可传递跳过状态
+        for i in 0..self.nodes.len() {
+            if self.nodes[i].state != SubagentState::Pending {
+                continue;
+            }
+            let deps: Vec<SubagentState> = self.nodes[i].depends_on.iter()
+                .filter_map(|dep| self.node(dep).map(|n| n.state))
+                .collect();
+            let failed = self.nodes[i].depends_on.iter().zip(&deps)
+                .find(|(_, state)| matches!(state, SubagentState::Failed | SubagentState::Skipped))
+                .map(|(dep, _)| dep.clone());
+            let node = &mut self.nodes[i];
+            if let Some(dep) = failed {
+                node.state = SubagentState::Skipped;
+                node.output = Some(format!("Skipped: dependency `{}` did not complete", dep));
+            } else if deps.iter().all(|s| *s == SubagentState::Completed) {
+                node.state = SubagentState::Running;
+                ready.push(node.clone());
+            }
+        }
+        ready
+    }
+
+    fn finish(&mut self, node_id: &str, result: Result<String, String>) {
+        if let Some(node) = self.nodes.iter_mut().find(|n| n.id == node_id) {
+            node.state = if result.is_ok() { SubagentState::Completed } else { SubagentState::Failed };
+            node.output = Some(result.unwrap_or_else(|e| e));
+        }
+    }
+
+    fn all_settled(&self) -> bool {
+        self.nodes.iter().all(|n| n.state.is_settled())
+    }
+
+    /// 子 Agent 的完整任务：原任务 + 依赖输出 + 共享黑板
+    fn child_task(&self, node: &SubagentNode) -> String {
+        let mut task = node.task.clone();
+        for dep in node.depends_on.iter().filter_map(|d| self.node(d)) {
+            let output: String = dep.output.as_deref().unwrap_or("").chars().take(MAX_DEPENDENCY_OUTPUT).collect();
+            task.push_str(&format!("\n\n## Result of `{}` ({})\n{}", dep.name, dep.agent_type, output));
+        }
+        if !self.blackboard.is_empty() {
+            task.push_str("\n\n## Shared blackboard\n");
+            for (key, value) in &self.blackboard {
+                task.push_str(&format!("- {}: {}\n", key, value));
+            }
+        }
+        task
+    }
+
+    fn event(&self, root_id: &str) -> OrchestrationEvent {
+        OrchestrationEvent {
+            root_id: root_id.to_string(),
+            subagents: self.nodes.iter().map(|n| SubagentStatus {
+                id: n.id.clone(),
+                name: n.name.clone(),
+                agent_type: n.agent_type.clone(),
+                status: n.state.label().to_string(),
+                depends_on: n.depends_on.clone(),
+            }).collect(),
+            completed: self.nodes.iter().filter(|n| n.state == SubagentState::Completed).count(),
+            total: self.nodes.len(),
+        }
+    }
+}
+
+fn orchestrations() -> &'static Mutex<HashMap<String, Orchestration>> {
+    static ORCHESTRATIONS: OnceLock<Mutex<HashMap<String, Orchestration>>> = OnceLock::new();
+    ORCHESTRATIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 子 Agent 所属编排的根 id
+fn parent_of(map: &HashMap<String, Orchestration>, agent_id: &str) -> Option<String> {
+    map.iter()
+        .find(|(_, o)| o.nodes.iter().any(|n| n.id == agent_id))
+        .map(|(root, _)| root.clone())
+}
+
+pub fn is_subagent(agent_id: &str) -> bool {
+    orchestrations().lock().map(|map| parent_of(&map, agent_id).is_some()).unwrap_or(false)
+}
+
+fn emit_status(app: &AppHandle, root_id: &str) {
+    let event = orchestrations().lock().ok().and_then(|map| map.get(root_id).map(|o| o.event(root_id)));
+    if let Some(event) = event {
+        emit_event(app, "agent:orchestration", &event);
+    }
+}
+
+/// 启动所有依赖已满足的子 Agent；每个子 Agent 结束后重新调度
+fn schedule(app: AppHandle, supervisor: Supervisor, root_id: String) {
+    let ready: Vec<(SubagentNode, AgentContext)> = {
+        let Ok(mut map) = orchestrations().lock() else { return };
+        let Some(orchestration) = map.get_mut(&root_id) else { return };
+        let ready = orchestration.take_ready();
+        ready.into_iter()
+            .map(|node| {
+                let context = AgentContext {
+                    task_description: orchestration.child_task(&node),
+                    initial_prompt: String::new(),
+                    ..orchestration.context.clone()
+                };
+                (node, context)
+            })
+            .collect()
+    };
+    emit_status(&app, &root_id);
+
+    for (node, context) in ready {
+        let (app, supervisor, root_id) = (app.clone(), supervisor.clone(), root_id.clone());
+        tokio::spawn(async move {
+            println!("[Orchestrator] Starting subagent {} ({}) for {}", node.id, node.agent_type, root_id);
+            supervisor.register_agent(node.id.clone(), node.agent_type.clone()).await;
+            let result = runner::run_agent_task(app.clone(), supervisor.clone(), node.id.clone(), node.agent_type.clone(), context).await;
+            println!("[Orchestrator] Subagent {} finished (ok: {})", node.id, result.is_ok());
+            if let Ok(mut map) = orchestrations().lock() {
+                if let Some(orchestration) = map.get_mut(&root_id) {
+                    orchestration.finish(&node.id, result);
+                }
+            }
+            schedule(app, supervisor, root_id.clone());
+            release_if_done(&root_id);
+        });
+    }
+}
+
+/// 规划 Agent 已结束且子 Agent 全部结束时释放编排
+fn release_if_done(root_id: &str) {
+    if let Ok(mut map) = orchestrations().lock() {
+        if map.get(root_id).is_some_and(|o| o.root_finished && o.all_settled()) {
+            map.remove(root_id);
+        }
+    }
+}
+
+/// 规划 Agent 结束时调用：`cancelled` 时一并取消子 Agent；返回子 Agent 的最终状态
+pub fn finish_root(app: &AppHandle, root_id: &str, cancelled: bool) -> Vec<SubagentNode> {
+    let nodes = {
+        let Ok(mut map) = orchestrations().lock() else { return Vec::new() };
+        let Some(orchestration) = map.get_mut(root_id) else { return Vec::new() };
+        orchestration.root_finished = true;
+        if cancelled {
+            for node in orchestration.nodes.iter_mut() {
+                match node.state {
+                    SubagentState::Running => {
+                        crate::cancellation::cancel(&format!("agent_{}", node.id));
+                    }
+                    SubagentState::Pending => {
+                        node.state = SubagentState::Skipped;
+                        node.output = Some("Skipped: the orchestration was cancelled".to_string());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        orchestration.nodes.clone()
+    };
+    emit_status(app, root_id);
+    release_if_done(root_id);
+    nodes
+}
+
+// ============================================================================
+// 编排工具
+// ============================================================================
+
+pub fn is_orchestration_tool(tool_name: &str) -> bool {
+    matches!(tool_name, TOOL_LAUNCH | TOOL_WAIT | TOOL_BLACKBOARD_WRITE | TOOL_BLACKBOARD_READ)
+}
+
+/// 编排工具定义（子 Agent 只能读写共享黑板）
+pub fn tool_definitions(subagent: bool) -> Vec<Value> {
+    let mut tools = Vec::new();
+    if !subagent {
+        tools.push(json!({
+            "type": "function",
+            "function": {
+                "name": TOOL_LAUNCH,
+                "description": "Launch a child agent for one step of a larger task (e.g. explore, implement, test). It starts as soon as every agent listed in depends_on has completed, and receives their results plus the shared blackboard. Use wait_for_subagents to collect results.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string", "description": "Unique step name, e.g. 'explore' or 'implement-api' (letters, digits, '-', '_')" },
+                        "agent_type": { "type": "string", "description": "Agent type, e.g. 'explore', 'refactor-agent', 'review', 'bash' (default: explore)" },
+                        "task": { "type": "string", "description": "Self-contained task description for the child agent" },
+                        "depends_on": { "type": "array", "items": { "type": "string" }, "description": "Names of previously launched subagents that must complete first" }
+                    },
+                    "required": ["name", "task"]
+                }
+            }
+        }));
+        tools.push(json!({
+            "type": "function",
+            "function": {
+                "name": TOOL_WAIT,
+                "description": "Wait until the given subagents (default: all) have finished and return their results.",
+                "parameters": {
+                    "type": "object",
+                    "properties": {
+                        "names": { "type": "array", "items": { "type": "string" }, "description": "Subagent names to wait for (default: all)" },
+                        "timeout_secs": { "type": "number", "description": "Stop waiting after this many seconds and report progress so far (optional)" }
+                    }
+                }
+            }
+        }));
+    }
+    tools.push(json!({
+        "type": "function",
+        "function": {
+            "name": TOOL_BLACKBOARD_WRITE,
+            "description": "Store a value on the blackboard shared by all agents of this orchestration (e.g. decisions, file lists, API contracts).",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "key": { "type": "string", "description": "Entry key" },
+                    "value": { "description": "Any JSON value" }
+                },
+                "required": ["key", "value"]
+            }
+        }
+    }));
+    tools.push(json!({
+        "type": "function",
+        "function": {
+            "name": TOOL_BLACKBOARD_READ,
+            "description": "Read the shared blackboard (one key or all entries).",
+            "parameters": {
+                "type": "object",
+                "properties": {
+                    "key": { "type": "string", "description": "Entry key (default: all entries)" }
+                }
+            }
+        }
+    }));
+    tools
+}
+
+pub async fn execute_tool(
+    app: &AppHandle,
+    supervisor: &Supervisor,
+    agent_id: &str,
+    context: &AgentContext,
+    tool_name: &str,
+    args: &Value,
+) -> Result<String, String> {
+    match tool_name {
+        TOOL_LAUNCH => launch(app, supervisor, agent_id, context, args),
+        TOOL_WAIT => wait(agent_id, args).await,
+        TOOL_BLACKBOARD_WRITE => {
+            let key = args["key"].as_str().filter(|k| !k.is_empty()).ok_or("Missing 'key'")?;
+            let mut map = orchestrations().lock().map_err(|e| e.to_string())?;
+            let root = parent_of(&map, agent_id).unwrap_or_else(|| agent_id.to_string());
+            let orchestration = map.entry(root).or_insert_with(|| Orchestration::new(context.clone()));
+            orchestration.blackboard.insert(key.to_string(), args["value"].clone());
+            Ok(format!("Stored `{}` on the shared blackboard.", key))
+        }
+        TOOL_BLACKBOARD_READ => {
+            let map = orchestrations().lock().map_err(|e| e.to_string())?;
+            let root = parent_of(&map, agent_id).unwrap_or_else(|| agent_id.to_string());
+            let empty = BTreeMap::new();
+            let blackboard = map.get(&root).map(|o| &o.blackboard).unwrap_or(&empty);
+            match args["key"].as_str() {
+                Some(key) => Ok(blackboard.get(key).map(|v| v.to_string()).unwrap_or_else(|| format!("No blackboard entry `{}`.", key))),
+                None => serde_json::to_string_pretty(blackboard).map_err(|e| e.to_string()),
+            }
+        }
+        _ => Err(format!("Unknown orchestration tool: {}", tool_name)),
+    }
+}
+
+fn launch(app: &AppHandle, supervisor: &Supervisor, root_id: &str, context: &AgentContext, args: &Value) -> Result<String, String> {
+    let name = args["name"].as_str().ok_or("Missing 'name'")?;
+    let task = args["task"].as_str().filter(|t| !t.trim().is_empty()).ok_or("Missing 'task'")?;
+    let agent_type = args["agent_type"].as_str().filter(|t| !t.is_empty()).unwrap_or(DEFAULT_AGENT_TYPE);
+    let depends_on: Vec<String> = args["depends_on"].as_array()
+        .map(|deps| deps.iter().filter_map(|d| d.as_str()).map(String::from).collect())
+        .unwrap_or_default();
+
+    let node = {
+        let mut map = orchestrations().lock().map_err(|e| e.to_string())?;
+        if parent_of(&map, root_id).is_some() {
+            return Err("Subagents cannot launch further subagents".to_string());
+        }
+        let orchestration = map.entry(root_id.to_string()).or_insert_with(|| Orchestration::new(context.clone()));
+        orchestration.add(root_id, name, agent_type, task, depends_on)?.clone()
+    };
+    println!("[Orchestrator] {} declared subagent {} ({}), depends on {:?}", root_id, node.id, agent_type, node.depends_on);
+    schedule(app.clone(), supervisor.clone(), root_id.to_string());
+
+    Ok(if node.depends_on.is_empty() {
+        format!("Subagent `{}` ({}) started as agent {}.", name, agent_type, node.id)
+    } else {
+        format!("Subagent `{}` ({}) will start as agent {} after: {}.", name, agent_type, node.id, node.depends_on.join(", "))
+    })
+}
+
+/// 子 Agent 结果的文本报告
+fn report(nodes: &[&SubagentNode]) -> String {
+    nodes.iter()
+        .map(|n| format!("## {} ({}) - {}\n{}", n.name, n.agent_type, n.state.label(), n.output.as_deref().unwrap_or("")))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+async fn wait(root_id: &str, args: &Value) -> Result<String, String> {
+    let names: Option<Vec<String>> = args["names"].as_array()
+        .map(|names| names.iter().filter_map(|n| n.as_str()).map(String::from).collect());
+    let timeout = args["timeout_secs"].as_u64().map(Duration::from_secs);
+    let started = Instant::now();
+    loop {
+        {
+            let map = orchestrations().lock().map_err(|e| e.to_string())?;
+            let orchestration = map.get(root_id).ok_or("No subagents have been launched")?;
+            let nodes: Vec<&SubagentNode> = match &names {
+                Some(names) => names.iter()
+                    .map(|name| orchestration.node(name).ok_or_else(|| format!("Unknown subagent `{}`", name)))
+                    .collect::<Result<_, _>>()?,
+                None => orchestration.nodes.iter().collect(),
+            };
+            if nodes.iter().all(|n| n.state.is_settled()) {
+                return Ok(report(&nodes));
+            }
+            if timeout.is_some_and(|t| started.elapsed() >= t) {
+                let running: Vec<&str> = nodes.iter().filter(|n| !n.state.is_settled()).map(|n| n.name.as_str()).collect();
+                return Ok(format!("Timed out; still running: {}\n\n{}", running.join(", "), report(&nodes)));
+            }
+        }
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> AgentContext {
+        AgentContext {
+            project_root: "/p".to_string(),
+            task_description: "build feature".to_string(),
+            initial_prompt: String::new(),
+            variables: HashMap::new(),
+            provider_config: Default::default(),
+            limits: Default::default(),
+            images: Vec::new(),
+        }
+    }
+
+    fn pipeline() -> Orchestration {
+        let mut o = Orchestration::new(context());
+        o.add("root", "explore", "explore", "find the handlers", vec![]).unwrap();
+        o.add("root", "plan", "task-breakdown", "plan the change", vec!["explore".into()]).unwrap();
+        o.add("root", "implement", "refactor-agent", "implement it", vec!["plan".into()]).unwrap();
+        o.add("root", "docs", "explore", "update docs", vec!["explore".into()]).unwrap();
+        o
+    }
+
+    #[test]
+    fn test_dependency_scheduling() {
+        let mut o = pipeline();
+        assert!(o.add("root", "explore", "explore", "again", vec![]).is_err());
+        assert!(o.add("root", "test", "bash", "run tests", vec!["deploy".into()]).is_err());
+        assert!(o.add("root", "bad name", "bash", "x", vec![]).is_err());
+
+        let ready: Vec<String> = o.take_ready().into_iter().map(|n| n.id).collect();
+        assert_eq!(ready, vec!["root-explore"]);
+        assert!(o.take_ready().is_empty());
+
+        o.blackboard.insert("entry".to_string(), json!("src/api.rs"));
+        o.finish("root-explore", Ok("handlers live in src/api.rs".to_string()));
+        let ready = o.take_ready();
+        assert_eq!(ready.iter().map(|n| n.name.as_str()).collect::<Vec<_>>(), vec!["plan", "docs"]);
+        let task = o.child_task(&ready[0]);
+        assert!(task.starts_with("plan the change"));
+        assert!(task.contains("## Result of `explore` (explore)\nhandlers live in src/api.rs"));
+        assert!(task.contains("- entry: \"src/api.rs\""));
+
+        let event = o.event("root");
+        assert_eq!((event.completed, event.total), (1, 4));
+        assert_eq!(event.subagents[1].status, "running");
+        assert!(!o.all_settled());
+    }
+
+    #[test]
+    fn test_failed_dependency_skips_dependents() {
+        let mut o = pipeline();
+        o.take_ready();
+        o.finish("root-explore", Ok(String::new()));
+        o.take_ready();
+        o.finish("root-plan", Err("model error".to_string()));
+        o.finish("root-docs", Ok("done".to_string()));
+        assert!(o.take_ready().is_empty());
+
+        let implement = o.node("implement").unwrap();
+        assert_eq!(implement.state, SubagentState::Skipped);
+        assert!(implement.output.as_deref().unwrap().contains("`plan`"));
+        assert_eq!(o.node("plan").unwrap().output.as_deref(), Some("model error"));
+        assert!(o.all_settled());
+    }
+
+    #[test]
+    fn test_tool_definitions() {
+        let names = |tools: Vec<Value>| tools.iter().map(|t| t["function"]["name"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(names(tool_definitions(false)), vec![TOOL_LAUNCH, TOOL_WAIT, TOOL_BLACKBOARD_WRITE, TOOL_BLACKBOARD_READ]);
+        assert_eq!(names(tool_definitions(true)), vec![TOOL_BLACKBOARD_WRITE, TOOL_BLACKBOARD_READ]);
+        assert!(is_orchestration_tool("launch_subagent") && !is_orchestration_tool("bash"));
+    }
+}
@@ -0,0 +1,170 @@
+/*!
+Agent Persistence - Agent 暂停与恢复
+====================================
+
+runner 在每轮循环开始时更新运行快照（对话历史、已修改文件、已完成的循环及用量）：
+
+- `pause_agent`：Agent 在下一个循环边界把快照写入 `.ifai/agents/<id>.json` 后停止
+- 应用退出时 `persist_all` 把所有运行中 Agent 的最新快照写入磁盘
+- `resume_agent`：读取快照并从中断处继续运行，快照随即删除
+
+快照不保存 API Key，恢复时由调用方重新提供模型配置。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::agent_budget::LoopUsage;
+use crate::agent_system::base::AgentContext;
+use crate::core_traits::ai::Message;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSnapshot {
+    pub id: String,
+    pub agent_type: String,
+    pub context: AgentContext,
+    pub history: Vec<Message>,
+    pub created_files: Vec<String>,
+    pub last_ai_summary: String,
+    /// 已完成的循环（含各轮 Token 用量）
+    pub loops: Vec<LoopUsage>,
+    #[serde(default)]
+    pub paused_at: i64,
+}
+
+fn live() -> &'static Mutex<HashMap<String, AgentSnapshot>> {
+    static LIVE: OnceLock<Mutex<HashMap<String, AgentSnapshot>>> = OnceLock::new();
+    LIVE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 更新运行中 Agent 的最新快照
+pub fn update(snapshot: AgentSnapshot) {
+    if let Ok(mut map) = live().lock() {
+        map.insert(snapshot.id.clone(), snapshot);
+    }
+}
+
+/// Agent 运行结束（完成、失败、取消或暂停）后丢弃快照
+pub fn forget(id: &str) {
+    if let Ok(mut map) = live().lock() {
+        map.remove(id);
+    }
+}
+
+fn snapshot_path(project_root: &str, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid agent id: {}", id));
+    }
+    Ok(Path::new(project_root).join(".ifai").join("agents").join(format!("{}.json", id)))
+}
+
+pub fn save(snapshot: &AgentSnapshot) -> Result<PathBuf, String> {
+    let path = snapshot_path(&snapshot.context.project_root, &snapshot.id)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let mut stored = snapshot.clone();
+    stored.context.provider_config.api_key = String::new();
+    stored.paused_at = chrono::Utc::now().timestamp();
+    let content = serde_json::to_string_pretty(&stored).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    println!("[AgentPersistence] Saved agent {} ({} messages) to {}", snapshot.id, snapshot.history.len(), path.display());
+    Ok(path)
+}
+
+pub fn load(project_root: &str, id: &str) -> Result<AgentSnapshot, String> {
+    let path = snapshot_path(project_root, id)?;
+    let content = std::fs::read_to_string(&path).map_err(|_| format!("No paused state for agent {}", id))?;
+    serde_json::from_str(&content).map_err(|e| format!("Corrupted agent state {}: {}", path.display(), e))
+}
+
+pub fn remove(project_root: &str, id: &str) {
+    if let Ok(path) = snapshot_path(project_root, id) {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+/// 项目中已暂停的 Agent（按暂停时间倒序）
+pub fn list(project_root: &str) -> Vec<AgentSnapshot> {
+    let dir = Path::new(project_root).join(".ifai").join("agents");
+    let mut snapshots: Vec<AgentSnapshot> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| serde_json::from_str(&content).ok())
+        .collect();
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.paused_at));
+    snapshots
+}
+
+/// 应用退出时保存所有运行中 Agent 的最新快照，返回保存的数量
+pub fn persist_all() -> usize {
+    let snapshots: Vec<AgentSnapshot> = live().lock().map(|map| map.values().cloned().collect()).unwrap_or_default();
+    snapshots.iter()
+        .filter(|snapshot| match save(snapshot) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("[AgentPersistence] Failed to save agent {}: {}", snapshot.id, e);
+                false
+            }
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_traits::ai::Content;
+
+    fn snapshot(root: &str, id: &str) -> AgentSnapshot {
+        AgentSnapshot {
+            id: id.to_string(),
+            agent_type: "refactor-agent".to_string(),
+            context: AgentContext {
+                project_root: root.to_string(),
+                task_description: "rename the config loader".to_string(),
+                initial_prompt: String::new(),
+                variables: HashMap::new(),
+                provider_config: crate::core_traits::ai::AIProviderConfig { api_key: "sk-secret".to_string(), ..Default::default() },
+                limits: Default::default(),
+                images: Vec::new(),
+            },
+            history: vec![Message { role: "user".to_string(), content: Content::Text("rename it".to_string()), tool_calls: None, tool_call_id: None }],
+            created_files: vec!["src/config.rs".to_string()],
+            last_ai_summary: "Renamed loader".to_string(),
+            loops: vec![LoopUsage { iteration: 1, prompt_tokens: 100, completion_tokens: 20, estimated: false }],
+            paused_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_save_load_and_persist_all() {
+        let dir = std::env::temp_dir().join(format!("ifai_agent_state_{}", std::process::id()));
+        let root = dir.to_string_lossy().to_string();
+
+        save(&snapshot(&root, "a1")).unwrap();
+        let loaded = load(&root, "a1").unwrap();
+        assert_eq!(loaded.history.len(), 1);
+        assert_eq!(loaded.created_files, vec!["src/config.rs"]);
+        assert_eq!(loaded.loops[0].prompt_tokens, 100);
+        assert!(loaded.paused_at > 0);
+        // 不保存 API Key
+        assert!(loaded.context.provider_config.api_key.is_empty());
+        assert!(!std::fs::read_to_string(dir.join(".ifai/agents/a1.json")).unwrap().contains("sk-secret"));
+
+        update(snapshot(&root, "a2"));
+        assert!(persist_all() >= 1);
+        forget("a2");
+        let ids: Vec<String> = list(&root).into_iter().map(|s| s.id).collect();
+        assert!(ids.contains(&"a1".to_string()) && ids.contains(&"a2".to_string()));
+
+        remove(&root, "a1");
+        assert!(load(&root, "a1").is_err());
+        assert!(load(&root, "../a2").is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
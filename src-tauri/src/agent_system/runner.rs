@@ -1,38 +1,60 @@
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Manager};
 use crate::agent_system::base::{AgentStatus, AgentContext};
-use crate::agent_system::supervisor::Supervisor;
-use crate::agent_system::tools;
+use crate::agent_system::supervisor::{ApprovalDecision, PendingToolCall, Supervisor};
+use crate::agent_system::{orchestrator, tools};
+use crate::agent_system::persistence::{self, AgentSnapshot};
 use crate::prompt_manager;
 use crate::ai_utils;
-use crate::core_traits::ai::{Message, Content};
+use crate::core_traits::ai::{Message, Content, ContentPart, ImageUrl};
 use serde_json::{json, Value};
+use crate::agent_log;
+use crate::agent_budget::{BudgetLimit, BudgetTracker, TokenUsage};
+use crate::notifications::{notify, NotificationTrigger};
+use crate::events::{
+    emit_event, AgentResultEvent, AgentStatusEvent, DirectoryFinding, ExploreFindings,
+    ExploreProgress, LogLevel, ScanProgress, StreamEvent, ToolCallPayload,
+};
 
+/// 按上下文上限省略工具结果时，保持不变的最近消息数
+const CONTEXT_KEEP_RECENT: usize = 6;
+
+/// 运行 Agent 直至完成；返回最终输出，失败、取消或暂停时返回原因
 pub async fn run_agent_task(
     app: AppHandle,
     supervisor: Supervisor,
     id: String,
     agent_type: String,
     context: AgentContext,
-) {
+) -> Result<String, String> {
+    run_agent(app, supervisor, id, agent_type, context, None).await
+}
+
+/// 从 `pause_agent` 保存的快照处继续运行
+pub async fn resume_agent_task(app: AppHandle, supervisor: Supervisor, snapshot: AgentSnapshot) -> Result<String, String> {
+    let (id, agent_type, context) = (snapshot.id.clone(), snapshot.agent_type.clone(), snapshot.context.clone());
+    run_agent(app, supervisor, id, agent_type, context, Some(snapshot)).await
+}
+
+async fn run_agent(
+    app: AppHandle,
+    supervisor: Supervisor,
+    id: String,
+    agent_type: String,
+    context: AgentContext,
+    resumed: Option<AgentSnapshot>,
+) -> Result<String, String> {
     let event_id = format!("agent_{}", id);
+    // v0.3.4: 取消令牌，`cancel_ai_request(agent_{id})` 可在任意步骤中断运行
+    let cancel = crate::cancellation::register(&event_id);
+
+    // v0.3.4: 分级日志，完整记录写入 .ifai/agent_runs/
+    agent_log::begin_run(&event_id, &context.project_root);
 
-    // 🔥 使用 app.emit 发送日志到前端控制台
-    let _ = app.emit(&event_id, json!({
-        "type": "log",
-        "message": format!("[AgentRunner] 🔥🔥🔥 run_agent_task ENTRY - id: {}, agent_type: '{}'", id, agent_type)
-    }));
-    let _ = app.emit(&event_id, json!({
-        "type": "log",
-        "message": format!("[AgentRunner] event_id: {}", event_id)
-    }));
-    let _ = app.emit(&event_id, json!({
-        "type": "log",
-        "message": format!("[AgentRunner] project_root: {}", context.project_root)
-    }));
-    let _ = app.emit(&event_id, json!({
-        "type": "log",
-        "message": format!("[AgentRunner] task_description: {}", context.task_description)
-    }));
+    // 🔥 使用 agent_log 发送日志到前端控制台
+    agent_log::log(&app, &event_id, LogLevel::Debug, format!("[AgentRunner] 🔥🔥🔥 run_agent_task ENTRY - id: {}, agent_type: '{}'", id, agent_type));
+    agent_log::log(&app, &event_id, LogLevel::Debug, format!("[AgentRunner] event_id: {}", event_id));
+    agent_log::log(&app, &event_id, LogLevel::Debug, format!("[AgentRunner] project_root: {}", context.project_root));
+    agent_log::log(&app, &event_id, LogLevel::Debug, format!("[AgentRunner] task_description: {}", context.task_description));
 
     println!("[AgentRunner] 🔥🔥🔥 run_agent_task ENTRY - id: {}, agent_type: '{}'", id, agent_type);
     println!("[AgentRunner] event_id: {}", event_id);
@@ -40,12 +62,22 @@ pub async fn run_agent_task(
     println!("[AgentRunner] task_description: {}", context.task_description);
     println!("[AgentRunner] provider: {:?}", context.provider_config.protocol);
     println!("[AgentRunner] Starting task for: {} ({}), event_id: {}", id, agent_type, event_id);
+
+    // v0.3.4: IFAI.md 中为 Agent 指定的模型与上下文上限
+    let mut context = context;
+    crate::project_config::apply_model_route(&context.project_root, crate::project_config::ModelTask::Agent, &mut context.provider_config);
+    let mut max_context_tokens = crate::project_config::load_project_config_sync(&context.project_root).and_then(|c| c.max_context_tokens);
     
     let mut history: Vec<Message> = Vec::new();
     let mut created_files: Vec<String> = Vec::new();
     let mut last_ai_summary = String::new();
     
-    let system_prompt = prompt_manager::get_agent_prompt(&agent_type, &context.project_root, &context.task_description);
+    // v0.3.4: 监听提示词与 IFAI.md，变化后在下一轮循环开始时重建指令
+    if let Err(e) = crate::prompt_watch::watch(&app, &context.project_root) {
+        eprintln!("[AgentRunner] Prompt hot-reload unavailable: {}", e);
+    }
+    let mut prompt_revision = crate::prompt_watch::revision(&context.project_root);
+    let system_prompt = build_system_prompt(&agent_type, &context);
     
     history.push(Message {
         role: "system".to_string(),
@@ -56,11 +88,26 @@ pub async fn run_agent_task(
 
     history.push(Message {
         role: "user".to_string(),
-        content: Content::Text(context.task_description.clone()),
+        content: task_content(&context),
         tool_calls: None,
         tool_call_id: None,
     });
 
+    // v0.3.4: 从暂停点恢复时沿用保存的对话历史与已完成的循环
+    let mut completed_loops = Vec::new();
+    if let Some(snapshot) = resumed {
+        println!("[AgentRunner] Resuming agent {} after {} iterations", id, snapshot.loops.len());
+        agent_log::log(&app, &event_id, LogLevel::Info, format!("Resuming after {} iterations", snapshot.loops.len()));
+        history = snapshot.history;
+        // 暂停期间提示词可能已修改，恢复时使用当前的 system 提示词
+        if let Some(system) = history.first_mut().filter(|m| m.role == "system") {
+            system.content = Content::Text(system_content_with_tools(&system_prompt));
+        }
+        created_files = snapshot.created_files;
+        last_ai_summary = snapshot.last_ai_summary;
+        completed_loops = snapshot.loops;
+    }
+
     let _ = supervisor.update_status(&id, AgentStatus::Running).await;
 
     // Define tools based on agent type
@@ -240,6 +287,40 @@ pub async fn run_agent_task(
                     }
                 }
             }),
+            // v0.3.4: 代码搜索
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "agent_grep",
+                    "description": "Search file contents in the project with a regular expression (respects .gitignore). Returns JSON matches with path, line and snippet; use it to locate code before reading files.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "pattern": { "type": "string", "description": "Regular expression to search for" },
+                            "rel_path": { "type": "string", "description": "Directory to search in, relative to the project root (default: '.')" },
+                            "include": { "type": "array", "items": { "type": "string" }, "description": "Only search files matching these globs (e.g., '*.rs', 'src/**')" },
+                            "exclude": { "type": "array", "items": { "type": "string" }, "description": "Skip files matching these globs" },
+                            "case_insensitive": { "type": "boolean", "description": "Case-insensitive match (default: false)" },
+                            "max_results": { "type": "number", "description": "Maximum matches to return (default: 100, max: 500)" }
+                        },
+                        "required": ["pattern"]
+                    }
+                }
+            }),
+            // v0.3.4: 语言服务器诊断（编译错误 / lint 警告）
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "agent_get_diagnostics",
+                    "description": "List the errors and warnings the editor's language servers currently report (compiler and linter diagnostics) for a file or directory. Use it to check what is broken in a file, or whether your edits introduced problems.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "rel_path": { "type": "string", "description": "File or directory relative to the project root (default: '.', the whole project)" }
+                        }
+                    }
+                }
+            }),
             json!({
                 "type": "function",
                 "function": {
@@ -255,6 +336,34 @@ pub async fn run_agent_task(
                     }
                 }
             }),
+            // v0.3.4: 局部编辑大文件
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "agent_edit_file",
+                    "description": "Edit part of an existing file without rewriting it. Provide search/replace blocks (search must copy the current lines, with enough context to be unique) or a unified diff. Matching tolerates whitespace and small differences. Returns which edits were applied and which failed.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "rel_path": { "type": "string", "description": "Relative path to file" },
+                            "edits": {
+                                "type": "array",
+                                "description": "Search/replace blocks, applied in order",
+                                "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "search": { "type": "string", "description": "Existing lines to replace" },
+                                        "replace": { "type": "string", "description": "New lines" }
+                                    },
+                                    "required": ["search", "replace"]
+                                }
+                            },
+                            "diff": { "type": "string", "description": "Unified diff (@@ hunks) as an alternative to 'edits'" }
+                        },
+                        "required": ["rel_path"]
+                    }
+                }
+            }),
             json!({
                 "type": "function",
                 "function": {
@@ -270,31 +379,214 @@ pub async fn run_agent_task(
                         "required": ["command"]
                     }
                 }
+            }),
+            // v0.3.4: 在项目根目录内执行构建 / 测试命令，输出回传给 Agent
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "agent_run_command",
+                    "description": "Run a build, test or lint command in the project (e.g. `cargo test`, `npm run build`) and get its exit code, stdout and stderr. Use it to verify your changes. Runs inside the project root with credentials removed from the environment; requires user approval.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "command": { "type": "string", "description": "The shell command to run" },
+                            "cwd": { "type": "string", "description": "Working directory relative to the project root (default: project root)" },
+                            "timeout_secs": { "type": "number", "description": "Timeout in seconds (default: 120, max: 600)" },
+                            "env": { "type": "object", "description": "Extra environment variables, e.g. {\"RUST_BACKTRACE\": \"1\"}" }
+                        },
+                        "required": ["command"]
+                    }
+                }
+            }),
+            // v0.3.4: 分段读取被摘要的长工具输出
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "agent_read_tool_output",
+                    "description": "Read the full content of a tool output that was summarized because it was too long. Use the handle given in the summary; reads a range of lines (at most 500 lines / 6000 chars per call).",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "handle": { "type": "string", "description": "Handle from the summarized output" },
+                            "start_line": { "type": "number", "description": "First line to read, 1-based (default: 1)" },
+                            "max_lines": { "type": "number", "description": "Number of lines to read (default: 200, max: 500)" }
+                        },
+                        "required": ["handle"]
+                    }
+                }
+            }),
+            // v0.3.4: 沙箱化 JS/TS 片段执行（无文件系统 / 网络访问）
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "agent_eval_js",
+                    "description": "Evaluate a small JavaScript or TypeScript snippet in an isolated sandbox (no filesystem or network access, strict time and memory limits). Use it to check a regex, a date calculation or a small algorithm. Returns the value of the last expression and console output.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "snippet": { "type": "string", "description": "Code to evaluate; the value of the last expression is returned" },
+                            "language": { "type": "string", "enum": ["javascript", "typescript"], "description": "Snippet language (default: javascript)" },
+                            "timeout_ms": { "type": "number", "description": "Execution timeout in milliseconds (default: 2000, max: 10000)" }
+                        },
+                        "required": ["snippet"]
+                    }
+                }
+            }),
+            // v0.3.4: 网页抓取（受会话隐私级别约束）
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "agent_fetch_url",
+                    "description": "Fetch a web page (http/https only) and return its readable text, with scripts, styles and navigation removed. Only available when the session privacy level allows network access.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "url": { "type": "string", "description": "Absolute http(s) URL to fetch" },
+                            "max_chars": { "type": "number", "description": "Maximum characters of text to return (default: 20000)" }
+                        },
+                        "required": ["url"]
+                    }
+                }
+            }),
+            // v0.3.4: 登记报告 / 图表 / 导出等非代码产物
+            json!({
+                "type": "function",
+                "function": {
+                    "name": "agent_emit_artifact",
+                    "description": "Attach a non-code result to this run, such as an analysis report (markdown), a diagram (mermaid text) or a CSV export. Artifacts are stored outside the workspace and listed in the run result; use agent_write_file instead for files that belong in the project.",
+                    "parameters": {
+                        "type": "object",
+                        "properties": {
+                            "name": { "type": "string", "description": "Short artifact title, e.g. 'Dependency report'" },
+                            "kind": { "type": "string", "enum": ["report", "mermaid", "csv", "json", "text"], "description": "Artifact type (default: report)" },
+                            "content": { "type": "string", "description": "Full artifact content" },
+                            "description": { "type": "string", "description": "Optional one-line description" }
+                        },
+                        "required": ["name", "content"]
+                    }
+                }
             })
         ]
     };
 
-    let mut loop_count = 0;
-    const MAX_LOOPS: usize = 12;
+    // v0.3.4: 多 Agent 编排工具（子 Agent 只能读写共享黑板）
+    let mut tools = tools;
+    if agent_type != "bash" && agent_type != "/bash" {
+        tools.extend(orchestrator::tool_definitions(orchestrator::is_subagent(&id)));
+    }
+
+    // v0.3.4: 循环次数 / Token / 时长预算，超出时带着已有结果结束
+    let mut budget = BudgetTracker::resumed(context.limits.clone(), completed_loops);
+
+    while !cancel.is_cancelled() {
+        // v0.3.4: 循环边界的运行快照，暂停或应用退出时写入 .ifai/agents/<id>.json
+        let snapshot = AgentSnapshot {
+            id: id.clone(),
+            agent_type: agent_type.clone(),
+            context: context.clone(),
+            history: history.clone(),
+            created_files: created_files.clone(),
+            last_ai_summary: last_ai_summary.clone(),
+            loops: budget.loops().to_vec(),
+            paused_at: 0,
+        };
+        if supervisor.pause_requested(&id).await {
+            supervisor.clear_pause(&id).await;
+            match persistence::save(&snapshot) {
+                Ok(path) => {
+                    println!("[AgentRunner] Agent {} paused", id);
+                    let _ = supervisor.update_status(&id, AgentStatus::Paused).await;
+                    emit_event(&app, "agent:status", &AgentStatusEvent { id: id.clone(), status: "paused".to_string(), progress: None, error: None });
+                    agent_log::emit(&app, &event_id, &StreamEvent::Status { status: "paused".to_string(), progress: None });
+                    agent_log::log(&app, &event_id, LogLevel::Info, format!("Paused; state saved to {}", path.display()));
+                    persistence::forget(&id);
+                    agent_log::end_run(&event_id);
+                    crate::privacy::release_agent(&id);
+                    return Err("Agent paused".to_string());
+                }
+                Err(e) => {
+                    eprintln!("[AgentRunner] Failed to pause agent {}: {}", id, e);
+                    agent_log::log(&app, &event_id, LogLevel::Warn, format!("Could not pause, continuing: {}", e));
+                }
+            }
+        }
+        persistence::update(snapshot);
 
-    while loop_count < MAX_LOOPS {
-        loop_count += 1;
-        let _ = app.emit("agent:status", json!({ "id": id, "status": "running", "progress": 0.15 + (loop_count as f32 * 0.05) }));
-        let _ = app.emit(&event_id, json!({ "type": "status", "status": "running", "progress": 0.15 + (loop_count as f32 * 0.05) }));
+        if let Some(limit) = budget.check() {
+            println!("[AgentRunner] Agent {} stopped: {} reached", id, limit.label());
+            agent_log::log(&app, &event_id, LogLevel::Warn, format!("Stopping: {} reached", limit.label()));
+            break;
+        }
+        let loop_count = budget.iterations() + 1;
+        let progress = (0.15 + (loop_count as f32 * 0.05)).min(0.95);
+        emit_event(&app, "agent:status", &AgentStatusEvent { id: id.clone(), status: "running".to_string(), progress: Some(progress), error: None });
+        agent_log::emit(&app, &event_id, &StreamEvent::Status { status: "running".to_string(), progress: Some(progress) });
         // 🔥 FIX: Send 'thinking' event instead of 'log' to enable streaming content in message (with line breaks)
-        let _ = app.emit(&event_id, json!({ "type": "thinking", "content": "\n🤔 正在思考..." }));
-        let _ = app.emit(&event_id, json!({ "type": "log", "message": "Thinking..." }));
+        agent_log::emit(&app, &event_id, &StreamEvent::Thinking { content: "\n🤔 正在思考...".to_string() });
+        agent_log::log(&app, &event_id, LogLevel::Progress, "Thinking...".to_string());
+
+        // v0.3.4: 提示词或 IFAI.md 已变化时重建 system 提示词并重新读取上下文上限
+        let current_revision = crate::prompt_watch::revision(&context.project_root);
+        if current_revision != prompt_revision {
+            prompt_revision = current_revision;
+            if let Some(system) = history.first_mut().filter(|m| m.role == "system") {
+                system.content = Content::Text(system_content_with_tools(&build_system_prompt(&agent_type, &context)));
+            }
+            max_context_tokens = crate::project_config::load_project_config_sync(&context.project_root).and_then(|c| c.max_context_tokens);
+            agent_log::log(&app, &event_id, LogLevel::Info, "Prompts or IFAI.md changed; using the updated instructions".to_string());
+        }
+
+        // v0.3.4: 超出项目上下文上限时省略较早的工具结果（只影响本次请求，不改动历史）
+        let mut request_history = history.clone();
+        if let Some(max_tokens) = max_context_tokens {
+            let model = context.provider_config.models.first().cloned().unwrap_or_default();
+            let elided = crate::conversation::elide_tool_results(&mut request_history, &model, max_tokens, CONTEXT_KEEP_RECENT);
+            if elided > 0 {
+                agent_log::log(&app, &event_id, LogLevel::Debug, format!("Omitted {} earlier tool results to fit max_context_tokens ({})", elided, max_tokens));
+            }
+        }
 
-        match ai_utils::agent_stream_chat_with_root(
+        // v0.3.4: 瞬时错误的重试进度发送到本次运行的通道；用量记录归属到项目与 Agent
+        let options = crate::request_options::RequestOptions {
+            cancel: Some(&cancel),
+            retry_channel: Some((app.clone(), event_id.clone())),
+            usage_scope: crate::usage_meter::UsageScope { project: Some(context.project_root.clone()), agent_id: Some(id.clone()) },
+            ..Default::default()
+        };
+        let request = options.run(ai_utils::agent_stream_chat_with_root(
             &app,
             &context.provider_config,
-            history.clone(),
+            request_history,
             &id,
             Some(tools.clone()),
             Some(context.project_root.clone()),
             Some(agent_type.clone())
-        ).await {
+        ));
+        let response = match budget.remaining_time() {
+            Some(remaining) => match tokio::time::timeout(remaining, request).await {
+                Ok(response) => response,
+                Err(_) => {
+                    agent_log::log(&app, &event_id, LogLevel::Warn, "Stopping: time limit reached during model request".to_string());
+                    budget.mark_exceeded(BudgetLimit::Duration);
+                    break;
+                }
+            },
+            None => request.await,
+        };
+        match response {
             Ok(ai_message) => {
+                let usage = budget.record(crate::agent_budget::take_usage(&id), || estimate_usage(&context.provider_config, &history, &ai_message));
+                agent_log::log(&app, &event_id, LogLevel::Debug, format!(
+                    "Loop {} tokens: {} prompt + {} completion{}",
+                    usage.iteration, usage.prompt_tokens, usage.completion_tokens, if usage.estimated { " (estimated)" } else { "" }
+                ));
+                // v0.3.4: 用量接近预算时提醒
+                if let Some((limit, fraction)) = budget.crossed_threshold() {
+                    let message = format!("Agent {} has used {:.0}% of its {}", agent_type, fraction * 100.0, limit.label());
+                    agent_log::log(&app, &event_id, LogLevel::Warn, message.clone());
+                    notify(&app, NotificationTrigger::BudgetThreshold, "Agent budget almost used", &message);
+                }
+
                 if let Content::Text(ref text) = ai_message.content {
                     if !text.is_empty() {
                          last_ai_summary = text.clone();
@@ -304,50 +596,179 @@ pub async fn run_agent_task(
                 if let Some(tool_calls) = &ai_message.tool_calls {
                     if tool_calls.is_empty() { break; }
                     history.push(ai_message.clone());
+                    // v0.3.4: 登记本条消息的全部工具调用，供队列查看与“全部批准”
+                    supervisor.set_queue(&id, tool_calls.iter().map(|call| PendingToolCall {
+                        agent_id: id.clone(),
+                        tool_call_id: call.id.clone(),
+                        tool: call.function.name.clone(),
+                        args: serde_json::from_str(&call.function.arguments).unwrap_or(Value::Null),
+                        queued_at: chrono::Utc::now().timestamp(),
+                    }).collect()).await;
 
                     for (idx, tool_call) in tool_calls.iter().enumerate() {
+                        if cancel.is_cancelled() { break; }
+                        // v0.3.4: 已请求暂停时不再执行剩余的工具调用，但每个调用仍需有对应结果
+                        if supervisor.pause_requested(&id).await {
+                            history.push(Message {
+                                role: "tool".to_string(),
+                                content: Content::Text("Not executed: the agent was paused before this tool call ran. Call it again if it is still needed.".to_string()),
+                                tool_calls: None,
+                                tool_call_id: Some(tool_call.id.clone()),
+                            });
+                            supervisor.dequeue(&id, &tool_call.id).await;
+                            continue;
+                        }
                         let tool_name = &tool_call.function.name;
                         let args_res: Result<Value, _> = serde_json::from_str(&tool_call.function.arguments);
+                        // v0.3.4: 会话隐私级别不允许的工具直接拒绝，不进入审批
+                        let privacy_block = crate::privacy::check_tool(crate::privacy::level_for_agent(&id), tool_name).err();
+                        // v0.3.4: 项目审批策略（显式拒绝的工具、危险命令）
+                        let approval_policy = crate::approval_policy::load(&context.project_root);
+                        let policy_block = args_res.as_ref().ok().and_then(|args| approval_policy.blocked(tool_name, args));
 
                         // 🔥 FIX: Send 'thinking' event to show progress in message (with line breaks for better formatting)
-                        let _ = app.emit(&event_id, json!({ "type": "thinking", "content": format!("\n🔧 正在处理工具: {}...\n", tool_name) }));
-                        let _ = app.emit(&event_id, json!({ "type": "log", "message": format!("Processing tool: {}", tool_name) }));
+                        agent_log::emit(&app, &event_id, &StreamEvent::Thinking { content: format!("\n🔧 正在处理工具: {}...\n", tool_name) });
+                        agent_log::log(&app, &event_id, LogLevel::Info, format!("Processing tool: {}", tool_name));
 
                         let (tool_result, _success) = match args_res {
-                            Ok(args) => {
+                            Ok(_) if privacy_block.is_some() => {
+                                agent_log::log(&app, &event_id, LogLevel::Warn, format!("Tool {} blocked by privacy level", tool_name));
+                                (privacy_block.clone().unwrap_or_default(), false)
+                            },
+                            Ok(_) if policy_block.is_some() => {
+                                agent_log::log(&app, &event_id, LogLevel::Warn, format!("Tool {} blocked by approval policy", tool_name));
+                                (policy_block.clone().unwrap_or_default(), false)
+                            },
+                            // v0.3.4: 编排工具只影响 Agent 之间的协作，无需审批（子 Agent 的工具仍逐个审批）
+                            Ok(args) if orchestrator::is_orchestration_tool(tool_name) => {
+                                match cancel.run(orchestrator::execute_tool(&app, &supervisor, &id, &context, tool_name, &args)).await {
+                                    Ok(res) => (res, true),
+                                    Err(e) => (format!("Error: {}", e), false),
+                                }
+                            },
+                            Ok(mut args) => {
                                 // 🔥 FIX v0.3.8.2: 使用 LLM API 原始返回的 tool_call.id
                                 // 这样可以与 ai_utils.rs 流式响应中的 tool_call ID 保持一致
                                 let tool_id = tool_call.id.clone();
                                 println!("[AgentRunner] Requesting authorization for: {}, event_id={}, tool_id={}", tool_name, event_id, tool_id);
-                                let emit_result = app.emit(&event_id, json!({
-                                    "type": "tool_call",
-                                    "toolCall": {
-                                        "id": tool_id,  // Use consistent index-based ID
-                                        "tool": tool_name,
-                                        "args": args,
-                                        "isPartial": false
-                                    }
-                                }));
-                                if let Err(e) = emit_result {
-                                    eprintln!("[AgentRunner] ERROR emitting event: {}", e);
-                                } else {
-                                    eprintln!("[AgentRunner] Event emitted successfully");
+                                // v0.3.4: 写文件前的风险评估与差异预览，随审批请求一起发送
+                                let change = proposed_change(tool_name, &args, &context.project_root);
+                                let risk = change.as_ref().map(|c| write_risk(&app, c));
+                                let diff = change.as_ref().and_then(|c| {
+                                    crate::diff_preview::preview(&c.path, c.old_content.as_deref(), c.new_content.as_deref().unwrap_or("")).ok()
+                                });
+                                // v0.3.4: shell 命令的安全分析；高风险命令始终逐次审批，不走自动批准与已保存的“允许”
+                                let command_risk = tools::is_shell_tool(tool_name).then(|| {
+                                    let root = tools::calibrate_project_root(&context.project_root);
+                                    crate::command_safety::analyze(args["command"].as_str().unwrap_or(""), Some(&root))
+                                });
+                                let high_risk = command_risk.as_ref().is_some_and(|r| r.level >= crate::command_safety::CommandRiskLevel::High);
+                                if let Some(report) = command_risk.as_ref().filter(|_| high_risk) {
+                                    agent_log::log(&app, &event_id, LogLevel::Warn, format!("{} flagged as {} risk: {}", tool_name, report.level.label(), report.summary()));
                                 }
+                                agent_log::emit(&app, &event_id, &StreamEvent::ToolCall {
+                                    tool_call: ToolCallPayload {
+                                        id: tool_id.clone(),  // Use consistent index-based ID
+                                        tool: tool_name.to_string(),
+                                        args: args.clone(),
+                                        is_partial: false,
+                                        risk,
+                                        diff: diff.clone(),
+                                        command_risk,
+                                    },
+                                });
 
-                                let _ = supervisor.update_status(&id, AgentStatus::WaitingForTool).await;
-                                // Send waitingfortool status event to frontend
-                                let _ = app.emit("agent:status", json!({ "id": id.clone(), "status": "waitingfortool" }));
-                                let _ = app.emit(&event_id, json!({ "type": "status", "status": "waitingfortool" }));
+                                // v0.3.4: 按工具类别的项目级授权，已有决定时不再逐次审批
+                                let category = crate::tool_permissions::category_for_tool(tool_name);
+                                let stored = category.and_then(|c| crate::tool_permissions::decision_for(&context.project_root, c))
+                                    .filter(|d| !high_risk || *d == crate::tool_permissions::PermissionDecision::Deny);
+                                let decision = if !high_risk && approval_policy.mode_for(tool_name) == crate::approval_policy::ApprovalMode::Auto {
+                                    println!("[AgentRunner] {} auto-approved by approval policy", tool_name);
+                                    agent_log::log(&app, &event_id, LogLevel::Info, format!("{} auto-approved by project approval policy", tool_name));
+                                    ApprovalDecision { approved: true, ..Default::default() }
+                                } else if let (Some(category), Some(stored)) = (category, stored) {
+                                    let allowed = stored == crate::tool_permissions::PermissionDecision::Allow;
+                                    println!("[AgentRunner] {} {} by stored permission ({:?})", tool_name, if allowed { "approved" } else { "rejected" }, category);
+                                    agent_log::log(&app, &event_id, LogLevel::Info, format!(
+                                        "{} {} by project permission to {}",
+                                        tool_name, if allowed { "approved" } else { "rejected" }, category.label()
+                                    ));
+                                    ApprovalDecision { approved: allowed, ..Default::default() }
+                                } else if supervisor.is_batch_approved(&id).await {
+                                    println!("[AgentRunner] {} approved with the rest of the queue", tool_name);
+                                    agent_log::log(&app, &event_id, LogLevel::Info, format!("{} approved by \"approve all pending\"", tool_name));
+                                    ApprovalDecision { approved: true, ..Default::default() }
+                                } else {
+                                    if let Some(category) = category {
+                                        agent_log::emit(&app, &event_id, &StreamEvent::PermissionRequest {
+                                            tool_call_id: tool_id.clone(),
+                                            tool: tool_name.to_string(),
+                                            category,
+                                        });
+                                    }
+                                    let _ = supervisor.update_status(&id, AgentStatus::WaitingForTool).await;
+                                    // Send waitingfortool status event to frontend
+                                    emit_event(&app, "agent:status", &AgentStatusEvent { id: id.clone(), status: "waitingfortool".to_string(), progress: None, error: None });
+                                    agent_log::emit(&app, &event_id, &StreamEvent::Status { status: "waitingfortool".to_string(), progress: None });
+                                    notify(&app, NotificationTrigger::ApprovalRequired, "Approval required", &format!("Agent {} wants to run {}", agent_type, tool_name));
 
-                                let approved = supervisor.wait_for_approval(id.clone()).await;
+                                    // v0.3.4: 无人处理时按项目策略在超时后自动拒绝或批准（高风险命令始终拒绝）
+                                    let on_timeout = ApprovalDecision {
+                                        approved: approval_policy.approves_on_timeout(high_risk),
+                                        ..Default::default()
+                                    };
+                                    let decision = tokio::select! {
+                                        decision = supervisor.wait_for_decision_timeout(id.clone(), approval_policy.approval_timeout(), on_timeout) => decision,
+                                        _ = cancel.cancelled() => Default::default(),
+                                    };
+                                    let paused = supervisor.pause_requested(&id).await;
+                                    if decision.timed_out {
+                                        agent_log::log(&app, &event_id, LogLevel::Warn, format!(
+                                            "Approval for {} timed out; {}",
+                                            tool_name, if decision.approved { "auto-approved" } else { "auto-rejected" }
+                                        ));
+                                    }
+                                    // 首次使用的审批结果记录为该类别的决定（取消、超时、暂停不算用户决定；高风险命令只针对本次）
+                                    if let Some(category) = category.filter(|_| !cancel.is_cancelled() && !decision.timed_out && !paused && !high_risk) {
+                                        let value = if decision.approved {
+                                            crate::tool_permissions::PermissionDecision::Allow
+                                        } else {
+                                            crate::tool_permissions::PermissionDecision::Deny
+                                        };
+                                        if let Err(e) = crate::tool_permissions::record(&context.project_root, category, value, tool_name) {
+                                            eprintln!("[AgentRunner] Failed to record permission: {}", e);
+                                        }
+                                    }
+                                    decision
+                                };
+                                let approved = decision.approved;
                                 println!("[AgentRunner] Approval received for {}: {}", tool_name, approved);
+
+                                // v0.3.4: 用户在审批时改写了 shell 命令，执行改写后的版本并记录原命令
+                                let mut command_note = None;
+                                if approved && tools::is_shell_tool(tool_name) {
+                                    let original = args["command"].as_str().unwrap_or("").to_string();
+                                    if let Some(edited) = decision.rewritten_command(&original) {
+                                        println!("[AgentRunner] Command edited by user: {:?} -> {:?}", original, edited);
+                                        agent_log::emit(&app, &event_id, &StreamEvent::CommandEdited {
+                                            tool_call_id: tool_id.clone(),
+                                            original: original.clone(),
+                                            edited: edited.to_string(),
+                                        });
+                                        command_note = Some(format!(
+                                            "Note: the user edited this command before approving it.\nOriginal command: {}\nExecuted command: {}\n\n",
+                                            original, edited
+                                        ));
+                                        args["command"] = Value::String(edited.to_string());
+                                    }
+                                }
                                 
                                 if approved {
-                                    let _ = app.emit("agent:status", json!({ "id": id, "status": "running" }));
-                                    let _ = app.emit(&event_id, json!({ "type": "status", "status": "running" }));
+                                    emit_event(&app, "agent:status", &AgentStatusEvent { id: id.clone(), status: "running".to_string(), progress: None, error: None });
+                                    agent_log::emit(&app, &event_id, &StreamEvent::Status { status: "running".to_string(), progress: None });
                                     // 🔥 FIX: Send 'thinking' event to show execution progress (with line breaks)
-                                    let _ = app.emit(&event_id, json!({ "type": "thinking", "content": format!("\n🚀 正在执行: {}...\n", tool_name) }));
-                                    let _ = app.emit(&event_id, json!({ "type": "log", "message": format!("🚀 Executing {}...", tool_name) }));
+                                    agent_log::emit(&app, &event_id, &StreamEvent::Thinking { content: format!("\n🚀 正在执行: {}...\n", tool_name) });
+                                    agent_log::log(&app, &event_id, LogLevel::Info, format!("🚀 Executing {}...", tool_name));
                                     println!("[AgentRunner] Starting execution of {}", tool_name);
                                 }
 
@@ -355,7 +776,14 @@ pub async fn run_agent_task(
 
                                 if !approved {
                                     println!("[AgentRunner] Tool {} REJECTED by user", tool_name);
-                                    ("User rejected the operation.".to_string(), false)
+                                    agent_log::log(&app, &event_id, LogLevel::Warn, format!("Tool {} rejected by user", tool_name));
+                                    if decision.timed_out {
+                                        ("Approval timed out; the operation was not executed.".to_string(), false)
+                                    } else if supervisor.pause_requested(&id).await {
+                                        ("Not executed: the agent was paused while this operation was awaiting approval.".to_string(), false)
+                                    } else {
+                                        ("User rejected the operation.".to_string(), false)
+                                    }
                                 } else {
                                     if tool_name == "agent_write_file" {
                                         if let Some(path) = args["rel_path"].as_str() {
@@ -363,34 +791,88 @@ pub async fn run_agent_task(
                                         }
                                     }
 
+                                    // v0.3.4: 修改文件前保存检查点，便于一键回滚 Agent 的全部修改
+                                    if matches!(tool_name, "agent_write_file" | "agent_edit_file") {
+                                        if let Some(path) = args["rel_path"].as_str() {
+                                            let root = tools::calibrate_project_root(&context.project_root);
+                                            if let Err(e) = crate::checkpoints::snapshot(&root, &id, path, tool_name) {
+                                                eprintln!("[AgentRunner] Failed to create checkpoint for {}: {}", path, e);
+                                            }
+                                        }
+                                    }
+
                                     // Use recursive scan for agent_scan_directory to enable progress callbacks
-                                    let tool_result = if tool_name == "agent_scan_directory" {
+                                    // v0.3.4: 用户只接受了部分差异块时，直接写入合并后的内容
+                                    let partial = decision.accepted_hunks.as_deref().zip(change.as_ref()).zip(diff.as_ref());
+                                    let tool_result = if let Some(((accepted, change), diff)) = partial {
+                                        match write_accepted_hunks(&context.project_root, change, diff, accepted) {
+                                            Ok(res) => res,
+                                            Err(e) => format!("Error: {}", e),
+                                        }
+                                    } else if tool_name == "agent_emit_artifact" {
+                                        // 产物登记到当前运行，需要运行通道，不走通用工具分发
+                                        let kind_arg = args["kind"].as_str().unwrap_or("report");
+                                        match crate::agent_artifacts::ArtifactKind::parse(kind_arg) {
+                                            Some(kind) => match crate::agent_artifacts::emit(
+                                                &context.project_root,
+                                                &event_id,
+                                                args["name"].as_str().unwrap_or(""),
+                                                kind,
+                                                args["content"].as_str().unwrap_or(""),
+                                                args["description"].as_str().map(|s| s.to_string()),
+                                            ) {
+                                                Ok(artifact) => crate::agent_artifacts::format_for_model(&artifact),
+                                                Err(e) => format!("Error: {}", e),
+                                            },
+                                            None => format!("Error: Unsupported artifact kind: {}", kind_arg),
+                                        }
+                                    } else if tool_name == "agent_run_command" {
+                                        // 输出实时推送到 bash://stream/agent_{id}_{tool_call_id}
+                                        let stream_id = format!("{}_{}", event_id, tool_id);
+                                        match cancel.run(tools::run_command(&app, &stream_id, &args, &context.project_root)).await {
+                                            Ok(res) => res,
+                                            Err(e) => format!("Error: {}", e)
+                                        }
+                                    } else if tool_name == "agent_scan_directory" {
                                         println!("[AgentRunner] Executing scan_directory...");
                                         let rel_path = args["rel_path"].as_str().or_else(|| args["path"].as_str()).unwrap_or(".").to_string();
                                         let pattern = args["pattern"].as_str().map(|s| s.to_string());
                                         let max_depth = args["max_depth"].as_u64().map(|v| v as usize);
                                         let max_files = args["max_files"].as_u64().map(|v| v as usize);
 
-                                        match crate::commands::core_wrappers::agent_scan_directory_with_progress(
+                                        match cancel.run(crate::commands::core_wrappers::agent_scan_directory_with_progress(
                                             &app, &event_id, context.project_root.clone(), rel_path, pattern, max_depth, max_files
-                                        ).await {
+                                        )).await {
                                             Ok(res) => res,
                                             Err(e) => format!("Error: {}", e)
                                         }
                                     } else {
                                         println!("[AgentRunner] Calling tools::execute_tool_internal for {}", tool_name);
-                                        match tools::execute_tool_internal(tool_name, &args, &context.project_root).await {
+                                        match cancel.run(tools::execute_tool_internal(tool_name, &args, &context.project_root)).await {
                                             Ok(res) => {
                                                 println!("[AgentRunner] Execution success for {}. Result size: {}", tool_name, res.len());
                                                 res
                                             },
                                             Err(e) => {
                                                 println!("[AgentRunner] Execution FAILED for {}: {}", tool_name, e);
+                                                agent_log::log(&app, &event_id, LogLevel::Warn, format!("{} failed: {}", tool_name, e));
                                                 format!("Error: {}", e)
                                             }
                                         }
                                     };
 
+                                    // v0.3.4: IFAI.md 开启 formatting.on_agent_write 时，写入后自动格式化
+                                    let tool_result = match args["rel_path"].as_str() {
+                                        Some(path) if matches!(tool_name, "agent_write_file" | "agent_edit_file") && !tool_result.starts_with("Error") => {
+                                            let root = tools::calibrate_project_root(&context.project_root);
+                                            match crate::formatter::format_after_agent_write(&root, path).await {
+                                                Some(note) => tool_result + &note,
+                                                None => tool_result,
+                                            }
+                                        }
+                                        _ => tool_result,
+                                    };
+
                                     // Send explore_findings event for agent_scan_directory
                                     if tool_name == "agent_scan_directory" {
                                         if let Ok(scan_result) = serde_json::from_str::<Value>(&tool_result) {
@@ -398,17 +880,13 @@ pub async fn run_agent_task(
                                             let total_dirs = scan_result["stats"]["totalDirectories"].as_u64().unwrap_or(0);
 
                                             // Send analyzing progress event (scanning done, now analyzing findings)
-                                            let _ = app.emit(&event_id, json!({
-                                                "type": "explore_progress",
-                                                "exploreProgress": {
-                                                    "phase": "analyzing",
-                                                    "progress": {
-                                                        "total": 1,
-                                                        "scanned": 1,
-                                                        "byDirectory": {}
-                                                    }
-                                                }
-                                            }));
+                                            agent_log::emit(&app, &event_id, &StreamEvent::ExploreProgress {
+                                                explore_progress: ExploreProgress {
+                                                    phase: "analyzing".to_string(),
+                                                    progress: ScanProgress { total: 1, scanned: 1, ..Default::default() },
+                                                    ..Default::default()
+                                                },
+                                            });
 
                                             // Build directories array from scan result with sample files
                                             let directories = if let (Some(dirs_arr), Some(files_arr)) = (
@@ -438,12 +916,12 @@ pub async fn run_agent_task(
 
                                                     let file_count = dir_files.len();
 
-                                                    Some(json!({
-                                                        "path": dir_path,
-                                                        "fileCount": file_count,
-                                                        "keyFiles": dir_files
-                                                    }))
-                                                }).collect::<Vec<serde_json::Value>>()
+                                                    Some(DirectoryFinding {
+                                                        path: dir_path.to_string(),
+                                                        file_count,
+                                                        key_files: dir_files,
+                                                    })
+                                                }).collect::<Vec<DirectoryFinding>>()
                                             } else {
                                                 Vec::new()
                                             };
@@ -454,17 +932,16 @@ pub async fn run_agent_task(
                                                 total_dirs
                                             );
 
-                                            let _ = app.emit(&event_id, json!({
-                                                "type": "explore_findings",
-                                                "exploreFindings": {
-                                                    "summary": summary,
-                                                    "directories": directories
-                                                }
-                                            }));
+                                            agent_log::emit(&app, &event_id, &StreamEvent::ExploreFindings {
+                                                explore_findings: ExploreFindings { summary, directories },
+                                            });
                                         }
                                     }
 
-                                    (tool_result, true)
+                                    match command_note {
+                                        Some(note) => (format!("{}{}", note, tool_result), true),
+                                        None => (tool_result, true),
+                                    }
                                 }
                             },
                             Err(e) => (format!("Failed to parse arguments: {}", e), false)
@@ -474,30 +951,57 @@ pub async fn run_agent_task(
                         // 前端会根据 toolCallId 匹配并更新对应 toolCall 的 result 字段
                         // 🔥 FIX v0.3.8.2: 使用 LLM API 原始返回的 tool_call.id
                         let tool_id = tool_call.id.clone();
-                        let _ = app.emit(&event_id, json!({
-                            "type": "tool_result",
-                            "toolCallId": tool_id,
-                            "result": tool_result,
-                            "success": _success
-                        }));
+                        agent_log::emit(&app, &event_id, &StreamEvent::ToolResult {
+                            tool_call_id: tool_id,
+                            result: tool_result.clone(),
+                            success: _success,
+                        });
 
+                        // v0.3.4: 超长输出只把摘要放入模型历史，完整内容已随事件写入运行记录
+                        // v0.3.4: 编辑结果中的完整文件内容只用于 UI 差异视图，不发给模型
+                        let model_result = if tool_name == "agent_edit_file" {
+                            crate::file_edit::result_for_model(&tool_result)
+                        } else {
+                            tool_result.clone()
+                        };
+                        let history_content = crate::tool_output::condense(&event_id, &tool_call.id, tool_name, &model_result);
                         history.push(Message {
                             role: "tool".to_string(),
-                            content: Content::Text(tool_result),
+                            content: Content::Text(history_content),
                             tool_calls: None,
                             tool_call_id: Some(tool_call.id.clone()),
                         });
+                        supervisor.dequeue(&id, &tool_call.id).await;
                     }
+                    supervisor.clear_queue(&id).await;
                 } else { break; }
             },
+            Err(_) if cancel.is_cancelled() => break,
             Err(e) => {
-                let _ = app.emit(&event_id, json!({ "type": "error", "error": e }));
-                let _ = app.emit("agent:status", json!({ "id": id, "status": "failed", "error": e }));
-                return;
+                agent_log::emit(&app, &event_id, &StreamEvent::Error { error: e.clone() });
+                notify(&app, NotificationTrigger::AgentFailed, &format!("Agent {} failed", agent_type), &e);
+                emit_event(&app, "agent:status", &AgentStatusEvent { id: id.clone(), status: "failed".to_string(), progress: None, error: Some(e.clone()) });
+                orchestrator::finish_root(&app, &id, true);
+                agent_log::end_run(&event_id);
+                persistence::forget(&id);
+                crate::privacy::release_agent(&id);
+                return Err(e);
             }
         }
     }
 
+    if cancel.is_cancelled() {
+        println!("[AgentRunner] Agent {} cancelled", id);
+        let _ = supervisor.update_status(&id, AgentStatus::Stopped).await;
+        emit_event(&app, "agent:status", &AgentStatusEvent { id: id.clone(), status: "cancelled".to_string(), progress: None, error: None });
+        agent_log::emit(&app, &event_id, &StreamEvent::Cancelled { reason: "Agent run cancelled by user".to_string() });
+        orchestrator::finish_root(&app, &id, true);
+        agent_log::end_run(&event_id);
+        persistence::forget(&id);
+        crate::privacy::release_agent(&id);
+        return Err("Agent run cancelled by user".to_string());
+    }
+
     let mut final_output = if !last_ai_summary.is_empty() {
         last_ai_summary
     } else {
@@ -511,23 +1015,159 @@ pub async fn run_agent_task(
         }
     }
 
+    let budget_report = budget.report();
+    if let Some(limit) = budget_report.exceeded {
+        final_output.push_str(&format!(
+            "\n\n> ⚠️ Stopped early: {} reached after {} iterations, {} tokens, {}s. The result above is partial.\n",
+            limit.label(), budget_report.iterations, budget_report.total_tokens, budget_report.elapsed_ms / 1000
+        ));
+    }
+
+    let subagents = orchestrator::finish_root(&app, &id, false);
+    if !subagents.is_empty() {
+        final_output.push_str("\n\n### 🤝 Subagents:\n");
+        for node in &subagents {
+            final_output.push_str(&format!("- {} ({}): {}\n", node.name, node.agent_type, node.state.label()));
+        }
+    }
+
+    let artifacts = crate::agent_artifacts::list(&context.project_root, &event_id);
+    if !artifacts.is_empty() {
+        final_output.push_str("\n\n### 📎 Artifacts:\n");
+        for artifact in &artifacts {
+            final_output.push_str(&format!("- {} ({})\n", artifact.name, artifact.blob.media_type));
+        }
+    }
+
     let _ = supervisor.update_status(&id, AgentStatus::Completed).await;
-    let _ = app.emit("agent:status", json!({ "id": id, "status": "completed", "progress": 1.0 }));
-    let _ = app.emit(&event_id, json!({ "type": "status", "status": "completed", "progress": 1.0 }));
+    emit_event(&app, "agent:status", &AgentStatusEvent { id: id.clone(), status: "completed".to_string(), progress: Some(1.0), error: None });
+    notify(&app, NotificationTrigger::AgentCompleted, &format!("Agent {} completed", agent_type), &final_output.chars().take(200).collect::<String>());
+    agent_log::emit(&app, &event_id, &StreamEvent::Status { status: "completed".to_string(), progress: Some(1.0) });
 
     // Send final result through unified stream
-    let _ = app.emit(&event_id, json!({
-        "type": "result",
-        "result": final_output
-    }));
+    agent_log::emit(&app, &event_id, &StreamEvent::Result { result: final_output.clone() });
     
     // Also keep agent:result for backward compatibility and global listeners
-    let _ = app.emit("agent:result", json!({ "id": id, "output": final_output }));
+    emit_event(&app, "agent:result", &AgentResultEvent { id: id.clone(), output: final_output.clone(), artifacts, budget: Some(budget_report) });
+    agent_log::end_run(&event_id);
+    persistence::forget(&id);
+    crate::privacy::release_agent(&id);
+    Ok(final_output)
+}
+
+/// 写入类工具拟写入的内容（与工具执行时一致：反转义写入内容、预演局部编辑）
+///
+/// 路径超出项目根目录时返回 `None`，不读取根目录外的文件（工具执行时同样会拒绝）
+fn proposed_change(tool_name: &str, args: &Value, project_root: &str) -> Option<crate::commit_risk::ProposedChange> {
+    // 与工具执行时的操作名一致，越界时审计日志记录真实的操作
+    let operation = match tool_name {
+        "agent_write_file" => "write",
+        "agent_edit_file" => "edit",
+        _ => return None,
+    };
+    let rel_path = args["rel_path"].as_str()?;
+    let root = tools::calibrate_project_root(project_root);
+    let path = crate::commands::core_wrappers::ensure_in_root(&root, rel_path, operation).ok()?;
+    let old_content = std::fs::read_to_string(path).ok();
+    let new_content = match tool_name {
+        "agent_write_file" => args["content"].as_str().map(tools::unescape_string),
+        // 预演局部编辑，按编辑后的完整内容评估
+        "agent_edit_file" => {
+            let edits = serde_json::from_value(args["edits"].clone()).ok();
+            let blocks = crate::file_edit::collect_blocks(edits, args["diff"].as_str()).ok()?;
+            Some(crate::file_edit::apply_edits(old_content.as_deref().unwrap_or(""), &blocks).content)
+        }
+        _ => return None,
+    };
+    Some(crate::commit_risk::ProposedChange {
+        path: rel_path.to_string(),
+        old_content,
+        new_content,
+    })
+}
+
+/// 对写入类工具调用做启发式风险分析
+fn write_risk(app: &AppHandle, change: &crate::commit_risk::ProposedChange) -> crate::commit_risk::RiskReport {
+    let index_state = app.try_state::<std::sync::Arc<std::sync::Mutex<crate::commands::symbol_commands::SymbolIndexState>>>();
+    let index = index_state.as_ref().and_then(|s| s.lock().ok());
+    crate::commit_risk::analyze_changes(std::slice::from_ref(change), index.as_deref())
+}
+
+/// 只写入用户接受的差异块，并告知模型哪些块被拒绝
+fn write_accepted_hunks(
+    project_root: &str,
+    change: &crate::commit_risk::ProposedChange,
+    diff: &crate::diff_preview::DiffPreview,
+    accepted: &[usize],
+) -> Result<String, String> {
+    let root = tools::calibrate_project_root(project_root);
+    let path = crate::commands::core_wrappers::ensure_in_root(&root, &change.path, "write")?;
+    let old = change.old_content.as_deref().unwrap_or("");
+    let content = crate::diff_preview::apply_hunks(&change.path, old, change.new_content.as_deref().unwrap_or(""), accepted)?;
+    if !accepted.is_empty() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&path, &content).map_err(|e| format!("Failed to write {}: {}", change.path, e))?;
+    }
+    let rejected: Vec<usize> = (0..diff.hunks.len()).filter(|i| !accepted.contains(i)).collect();
+    println!("[AgentRunner] Applied {}/{} hunks to {}", accepted.len(), diff.hunks.len(), change.path);
+    Ok(format!(
+        "Applied {} of {} hunks to {}. The user rejected hunks {:?} (0-based, in diff order); the file keeps its original content there. Do not re-apply the rejected changes unless the user asks.",
+        accepted.len(), diff.hunks.len(), change.path, rejected
+    ))
+}
+
+/// Agent 的 system 提示词：提示词模板、monorepo 子包配置与语言规范
+fn build_system_prompt(agent_type: &str, context: &AgentContext) -> String {
+    // v0.3.4: 作用域为 monorepo 子包时，使用该包的上下文配置代替仓库级默认值
+    let mut system_prompt = prompt_manager::get_agent_prompt_for_scope(
+        agent_type,
+        &context.project_root,
+        &context.task_description,
+        context.variables.get("SCOPE_PATH").map(|s| s.as_str()),
+    );
+
+    // v0.3.4: 按任务涉及的语言附加语言规范
+    if let Some(addendum) = prompt_manager::languages::language_addendum(
+        &context.project_root,
+        &[&context.task_description],
+        prompt_manager::languages::DEFAULT_ADDENDUM_BUDGET,
+    ) {
+        system_prompt.push_str(&addendum);
+    }
+
+    system_prompt
+}
+
+/// Agent 的任务消息；附带图片时改为文本 + 图片的多段内容
+fn task_content(context: &AgentContext) -> Content {
+    if context.images.is_empty() {
+        return Content::Text(context.task_description.clone());
+    }
+    let mut parts = vec![ContentPart::Text { text: context.task_description.clone(), part_type: "text".to_string() }];
+    parts.extend(context.images.iter().map(|url| ContentPart::ImageUrl { image_url: ImageUrl { url: url.clone() } }));
+    Content::Parts(parts)
+}
+
+/// 接口未返回 usage 时按模型分词器估算本轮用量
+fn estimate_usage(config: &crate::core_traits::ai::AIProviderConfig, history: &[Message], response: &Message) -> TokenUsage {
+    let kind = crate::token_counter::tokenizer_for_model(config.models.first().map(String::as_str).unwrap_or(""));
+    let count = |message: &Message| {
+        let calls: usize = message.tool_calls.iter().flatten()
+            .map(|call| crate::token_counter::count_with(&call.function.arguments, kind))
+            .sum();
+        crate::token_counter::count_with(&crate::intelligence_router::extract_text_content(&message.content), kind) + calls
+    };
+    TokenUsage {
+        prompt_tokens: history.iter().map(count).sum::<usize>() as u64,
+        completion_tokens: count(response) as u64,
+    }
 }
 
 fn system_content_with_tools(base: &str) -> String {
     // 🔥 FIX v0.3.8: 明确指示 LLM 使用工具，而不是文本请求确认
     // 问题：智谱 API 将 "Wait for approval before writing files" 理解为文本请求确认
     // 修复：明确说明使用 agent_write_file 工具，该工具会自动等待用户审批
-    format!("{}\n\n## Tool Usage Guidelines\n\n- **ALWAYS use tools** for file operations (agent_read_file, agent_write_file, etc.)\n- For writing files: use the agent_write_file tool with the full content\n- The agent_write_file tool will **automatically** wait for user approval - you do NOT need to ask for text confirmation\n- Show the code you intend to write clearly in the tool's content parameter\n- Never ask \"请确认是否同意\" or similar text confirmation - always use the tool directly", base)
+    format!("{}\n\n## Tool Usage Guidelines\n\n- **ALWAYS use tools** for file operations (agent_read_file, agent_write_file, etc.)\n- For writing files: use the agent_write_file tool with the full content\n- For small changes to existing (especially large) files: use agent_edit_file with search/replace blocks instead of rewriting the whole file\n- The agent_write_file tool will **automatically** wait for user approval - you do NOT need to ask for text confirmation\n- Show the code you intend to write clearly in the tool's content parameter\n- Never ask \"请确认是否同意\" or similar text confirmation - always use the tool directly", base)
 }
\ No newline at end of file
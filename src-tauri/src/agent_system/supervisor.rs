@@ -1,8 +1,44 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
+use serde::Serialize;
+use serde_json::Value;
 use tokio::sync::{Mutex, oneshot};
 use crate::agent_system::base::{AgentStatus};
 
+/// 审批结果：用户可在批准前改写 shell 命令，或只接受写入的部分改动块
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ApprovalDecision {
+    pub approved: bool,
+    /// 用户编辑后的命令（仅对 shell 类工具生效）
+    pub edited_command: Option<String>,
+    /// 接受的差异块序号（仅对写入类工具生效，None 表示全部接受）
+    pub accepted_hunks: Option<Vec<usize>>,
+    /// 审批超时后按策略自动做出的决定
+    pub timed_out: bool,
+}
+
+impl ApprovalDecision {
+    /// 编辑后的命令与原命令不同时返回
+    pub fn rewritten_command(&self, original: &str) -> Option<&str> {
+        self.edited_command
+            .as_deref()
+            .map(str::trim)
+            .filter(|cmd| !cmd.is_empty() && *cmd != original.trim())
+    }
+}
+
+/// 同一条 AI 消息中排队等待审批的工具调用
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PendingToolCall {
+    pub agent_id: String,
+    pub tool_call_id: String,
+    pub tool: String,
+    pub args: Value,
+    /// 入队时间（Unix 秒）
+    pub queued_at: i64,
+}
+
 #[derive(Debug)]
 pub struct AgentHandle {
     pub id: String,
@@ -15,7 +51,13 @@ pub struct AgentHandle {
 pub struct Supervisor {
     pub agents: Arc<Mutex<HashMap<String, AgentHandle>>>,
     // Map of agent_id -> oneshot sender to resume the task
-    pub approval_txs: Arc<Mutex<HashMap<String, oneshot::Sender<bool>>>>,
+    pub approval_txs: Arc<Mutex<HashMap<String, oneshot::Sender<ApprovalDecision>>>>,
+    /// agent_id -> 尚未处理的工具调用（按执行顺序，队首为当前调用）
+    pub pending: Arc<Mutex<HashMap<String, Vec<PendingToolCall>>>>,
+    /// 已对当前队列执行“全部批准”的 Agent
+    pub batch_approved: Arc<Mutex<HashSet<String>>>,
+    /// 请求在下一个循环边界暂停的 Agent
+    pub pause_requests: Arc<Mutex<HashSet<String>>>,
 }
 
 impl Supervisor {
@@ -23,6 +65,9 @@ impl Supervisor {
         Self {
             agents: Arc::new(Mutex::new(HashMap::new())),
             approval_txs: Arc::new(Mutex::new(HashMap::new())),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            batch_approved: Arc::new(Mutex::new(HashSet::new())),
+            pause_requests: Arc::new(Mutex::new(HashSet::new())),
         }
     }
 
@@ -53,7 +98,16 @@ impl Supervisor {
     // --- Approval Mechanism ---
 
     pub async fn wait_for_approval(&self, id: String) -> bool {
-        println!("[Supervisor] wait_for_approval called: id={}", id);
+        self.wait_for_decision(id).await.approved
+    }
+
+    pub async fn wait_for_decision(&self, id: String) -> ApprovalDecision {
+        self.wait_for_decision_timeout(id, None, ApprovalDecision::default()).await
+    }
+
+    /// 等待审批，超过 `timeout` 仍无人处理时返回 `on_timeout`（并标记 `timed_out`）
+    pub async fn wait_for_decision_timeout(&self, id: String, timeout: Option<Duration>, on_timeout: ApprovalDecision) -> ApprovalDecision {
+        println!("[Supervisor] wait_for_approval called: id={}, timeout={:?}", id, timeout);
         let (tx, rx) = oneshot::channel();
         {
             let mut txs = self.approval_txs.lock().await;
@@ -62,20 +116,233 @@ impl Supervisor {
         }
 
         // This will block the async task until someone calls notify_approval
-        let result = rx.await.unwrap_or(false);
-        println!("[Supervisor] Approval received: id={}, approved={}", id, result);
+        let result = match timeout {
+            Some(duration) => match tokio::time::timeout(duration, rx).await {
+                Ok(received) => received.unwrap_or_default(),
+                Err(_) => {
+                    self.approval_txs.lock().await.remove(&id);
+                    println!("[Supervisor] Approval timed out after {:?}: id={}, approved={}", duration, id, on_timeout.approved);
+                    return ApprovalDecision { timed_out: true, ..on_timeout };
+                }
+            },
+            None => rx.await.unwrap_or_default(),
+        };
+        println!("[Supervisor] Approval received: id={}, approved={}, edited={}", id, result.approved, result.edited_command.is_some());
         result
     }
 
     pub async fn notify_approval(&self, id: &str, approved: bool) {
+        self.notify_decision(id, ApprovalDecision { approved, ..Default::default() }).await;
+    }
+
+    pub async fn notify_decision(&self, id: &str, decision: ApprovalDecision) {
+        let approved = decision.approved;
         println!("[Supervisor] notify_approval called: id={}, approved={}", id, approved);
         let mut txs = self.approval_txs.lock().await;
         println!("[Supervisor] Current pending approvals: {:?}", txs.keys().collect::<Vec<_>>());
         if let Some(tx) = txs.remove(id) {
             println!("[Supervisor] Sending approval signal: id={}, approved={}", id, approved);
-            let _ = tx.send(approved);
+            let _ = tx.send(decision);
         } else {
             println!("[Supervisor] WARNING: No pending approval found for id={}", id);
         }
     }
-}
\ No newline at end of file
+
+    // --- Approval Queue ---
+
+    /// 登记一条 AI 消息中的全部工具调用，并清除上一批的“全部批准”
+    pub async fn set_queue(&self, id: &str, calls: Vec<PendingToolCall>) {
+        self.batch_approved.lock().await.remove(id);
+        self.pending.lock().await.insert(id.to_string(), calls);
+    }
+
+    /// 工具调用处理完毕后出队
+    pub async fn dequeue(&self, id: &str, tool_call_id: &str) {
+        if let Some(queue) = self.pending.lock().await.get_mut(id) {
+            queue.retain(|call| call.tool_call_id != tool_call_id);
+        }
+    }
+
+    pub async fn clear_queue(&self, id: &str) {
+        self.pending.lock().await.remove(id);
+        self.batch_approved.lock().await.remove(id);
+    }
+
+    /// 排队中的工具调用（`id` 为空时返回所有 Agent 的）
+    pub async fn pending_calls(&self, id: Option<&str>) -> Vec<PendingToolCall> {
+        let pending = self.pending.lock().await;
+        let mut calls: Vec<PendingToolCall> = pending.iter()
+            .filter(|(agent_id, _)| id.is_none() || id == Some(agent_id.as_str()))
+            .flat_map(|(_, queue)| queue.iter().cloned())
+            .collect();
+        calls.sort_by(|a, b| a.agent_id.cmp(&b.agent_id));
+        calls
+    }
+
+    /// 批准当前等待中的调用及队列中其余调用，返回批准的数量
+    pub async fn approve_all_pending(&self, id: &str) -> usize {
+        let count = self.pending.lock().await.get(id).map_or(0, Vec::len);
+        self.batch_approved.lock().await.insert(id.to_string());
+        let waiting = self.approval_txs.lock().await.contains_key(id);
+        if waiting {
+            self.notify_approval(id, true).await;
+        }
+        println!("[Supervisor] Approved all pending tool calls: id={}, count={}", id, count);
+        count.max(waiting as usize)
+    }
+
+    pub async fn is_batch_approved(&self, id: &str) -> bool {
+        self.batch_approved.lock().await.contains(id)
+    }
+
+    // --- Pause ---
+
+    /// 请求暂停运行中的 Agent；正在等待审批时释放等待（该调用不执行）
+    pub async fn request_pause(&self, id: &str) -> Result<(), String> {
+        let running = self.agents.lock().await.get(id)
+            .is_some_and(|a| matches!(a.status, AgentStatus::Running | AgentStatus::WaitingForTool));
+        if !running {
+            return Err(format!("Agent {} is not running", id));
+        }
+        self.pause_requests.lock().await.insert(id.to_string());
+        if let Some(tx) = self.approval_txs.lock().await.remove(id) {
+            println!("[Supervisor] Releasing pending approval for paused agent: id={}", id);
+            let _ = tx.send(ApprovalDecision::default());
+        }
+        Ok(())
+    }
+
+    pub async fn pause_requested(&self, id: &str) -> bool {
+        self.pause_requests.lock().await.contains(id)
+    }
+
+    pub async fn clear_pause(&self, id: &str) {
+        self.pause_requests.lock().await.remove(id);
+    }
+
+    /// 停止运行中的 Agent：释放审批等待（视为拒绝）并标记为已停止
+    ///
+    /// 运行循环本身通过 `cancellation` 令牌中断
+    pub async fn stop_agent(&self, id: &str) {
+        if let Some(tx) = self.approval_txs.lock().await.remove(id) {
+            println!("[Supervisor] Releasing pending approval for stopped agent: id={}", id);
+            let _ = tx.send(ApprovalDecision::default());
+        }
+        self.clear_queue(id).await;
+        self.update_status(id, AgentStatus::Stopped).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewritten_command() {
+        let plain = ApprovalDecision { approved: true, ..Default::default() };
+        assert_eq!(plain.rewritten_command("ls"), None);
+
+        let same = ApprovalDecision { approved: true, edited_command: Some(" ls ".to_string()), ..Default::default() };
+        assert_eq!(same.rewritten_command("ls"), None);
+
+        let edited = ApprovalDecision { approved: true, edited_command: Some("ls -la\n".to_string()), ..Default::default() };
+        assert_eq!(edited.rewritten_command("ls"), Some("ls -la"));
+    }
+
+    #[tokio::test]
+    async fn test_decision_roundtrip() {
+        let supervisor = Supervisor::new();
+        let waiter = supervisor.clone();
+        let handle = tokio::spawn(async move { waiter.wait_for_decision("a1".to_string()).await });
+        while supervisor.approval_txs.lock().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        supervisor.notify_decision("a1", ApprovalDecision { approved: true, edited_command: Some("npm test".to_string()), accepted_hunks: Some(vec![1]), ..Default::default() }).await;
+        let decision = handle.await.unwrap();
+        assert!(decision.approved);
+        assert_eq!(decision.edited_command.as_deref(), Some("npm test"));
+        assert_eq!(decision.accepted_hunks, Some(vec![1]));
+    }
+
+    #[tokio::test]
+    async fn test_stop_agent_releases_approval() {
+        let supervisor = Supervisor::new();
+        supervisor.register_agent("a2".to_string(), "explore".to_string()).await;
+        let waiter = supervisor.clone();
+        let handle = tokio::spawn(async move { waiter.wait_for_decision("a2".to_string()).await });
+        while supervisor.approval_txs.lock().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        supervisor.stop_agent("a2").await;
+        assert!(!handle.await.unwrap().approved);
+        assert_eq!(supervisor.list_agents().await[0].2, AgentStatus::Stopped);
+    }
+
+    fn pending_call(agent_id: &str, tool_call_id: &str) -> PendingToolCall {
+        PendingToolCall {
+            agent_id: agent_id.to_string(),
+            tool_call_id: tool_call_id.to_string(),
+            tool: "bash".to_string(),
+            args: serde_json::json!({ "command": "ls" }),
+            queued_at: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_approval_timeout() {
+        let supervisor = Supervisor::new();
+        let rejected = supervisor.wait_for_decision_timeout("a3".to_string(), Some(Duration::from_millis(10)), ApprovalDecision::default()).await;
+        assert!(rejected.timed_out && !rejected.approved);
+        assert!(supervisor.approval_txs.lock().await.is_empty());
+
+        let on_timeout = ApprovalDecision { approved: true, ..Default::default() };
+        let approved = supervisor.wait_for_decision_timeout("a3".to_string(), Some(Duration::from_millis(10)), on_timeout).await;
+        assert!(approved.timed_out && approved.approved);
+    }
+
+    #[tokio::test]
+    async fn test_approve_all_pending() {
+        let supervisor = Supervisor::new();
+        supervisor.set_queue("a4", vec![pending_call("a4", "c1"), pending_call("a4", "c2"), pending_call("a4", "c3")]).await;
+        supervisor.set_queue("a5", vec![pending_call("a5", "c1")]).await;
+        assert_eq!(supervisor.pending_calls(None).await.len(), 4);
+
+        let waiter = supervisor.clone();
+        let handle = tokio::spawn(async move { waiter.wait_for_decision("a4".to_string()).await });
+        while supervisor.approval_txs.lock().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(supervisor.approve_all_pending("a4").await, 3);
+        assert!(handle.await.unwrap().approved);
+        assert!(supervisor.is_batch_approved("a4").await);
+        assert!(!supervisor.is_batch_approved("a5").await);
+
+        supervisor.dequeue("a4", "c1").await;
+        let remaining: Vec<String> = supervisor.pending_calls(Some("a4")).await.into_iter().map(|c| c.tool_call_id).collect();
+        assert_eq!(remaining, vec!["c2", "c3"]);
+
+        // 新一批工具调用需要重新审批
+        supervisor.set_queue("a4", vec![pending_call("a4", "c4")]).await;
+        assert!(!supervisor.is_batch_approved("a4").await);
+        supervisor.clear_queue("a4").await;
+        assert!(supervisor.pending_calls(Some("a4")).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_pause_request() {
+        let supervisor = Supervisor::new();
+        assert!(supervisor.request_pause("a6").await.is_err());
+        supervisor.register_agent("a6".to_string(), "explore".to_string()).await;
+        supervisor.update_status("a6", AgentStatus::WaitingForTool).await;
+        let waiter = supervisor.clone();
+        let handle = tokio::spawn(async move { waiter.wait_for_decision("a6".to_string()).await });
+        while supervisor.approval_txs.lock().await.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        supervisor.request_pause("a6").await.unwrap();
+        assert!(!handle.await.unwrap().approved);
+        assert!(supervisor.pause_requested("a6").await);
+        supervisor.clear_pause("a6").await;
+        assert!(!supervisor.pause_requested("a6").await);
+    }
+}
@@ -1,5 +1,6 @@
 use ifainew_core::agent;
 use serde_json::Value;
+use crate::commands::core_wrappers::ensure_in_root;
 
 /// Convert snake_case to camelCase (e.g., "rel_path" -> "relPath")
 fn to_camel_case(snake: &str) -> String {
@@ -54,7 +55,7 @@ fn get_arg_opt_u64(args: &Value, snake_key: &str) -> Option<u64> {
 
 /// Unescape escape sequences in a string (e.g., "\\n" -> "\n", "\\t" -> "\t")
 /// This is needed because JSON from AI contains escaped characters as literals
-fn unescape_string(s: &str) -> String {
+pub(crate) fn unescape_string(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
     let mut chars = s.chars();
     let mut escape = false;
@@ -93,7 +94,7 @@ fn unescape_string(s: &str) -> String {
 
 /// Calibrate project root path
 /// If the path points to 'src-tauri' (common dev environment issue), jump up to the parent directory.
-fn calibrate_project_root(raw_root: &str) -> String {
+pub(crate) fn calibrate_project_root(raw_root: &str) -> String {
     let mut base_path = std::path::PathBuf::from(raw_root);
     if base_path.ends_with("src-tauri") {
         println!("[AgentTools] Root calibration: Detected 'src-tauri', jumping to parent.");
@@ -104,6 +105,76 @@ fn calibrate_project_root(raw_root: &str) -> String {
     base_path.to_string_lossy().to_string()
 }
 
+/// Shell command tools (the approval flow lets the user edit their `command` argument)
+pub fn is_shell_tool(tool_name: &str) -> bool {
+    matches!(tool_name, "bash" | "agent_run_shell_command" | "agent_execute_command" | "agent_run_command")
+}
+
+/// Default / maximum timeout for `agent_run_command` (seconds)
+const RUN_COMMAND_DEFAULT_TIMEOUT_SECS: u64 = 120;
+const RUN_COMMAND_MAX_TIMEOUT_SECS: u64 = 600;
+
+/// v0.3.4: `agent_run_command` — runs a build / test command so the agent can check its changes.
+///
+/// The working directory is pinned inside the project root, credentials are stripped from the
+/// environment, and output streams on `bash://stream/{stream_id}` while it runs. The captured
+/// stdout / stderr and exit code are returned for the tool message.
+pub async fn run_command(
+    app: &tauri::AppHandle,
+    stream_id: &str,
+    args: &Value,
+    project_root: &str,
+) -> Result<String, String> {
+    let command = get_arg_str(args, "command", "");
+    if command.trim().is_empty() {
+        return Err("Missing 'command' in arguments".to_string());
+    }
+    let calibrated_root = calibrate_project_root(project_root);
+    let cwd = get_arg_opt_str(args, "cwd").unwrap_or_else(|| ".".to_string());
+    let working_dir = ensure_in_root(&calibrated_root, &cwd, "run")?;
+    if !working_dir.is_dir() {
+        return Err(format!("Working directory '{}' does not exist", cwd));
+    }
+    let timeout_secs = get_arg_opt_u64(args, "timeout_secs")
+        .unwrap_or(RUN_COMMAND_DEFAULT_TIMEOUT_SECS)
+        .clamp(1, RUN_COMMAND_MAX_TIMEOUT_SECS);
+    let extra_env = args.get("env").and_then(|v| v.as_object()).map(|obj| {
+        obj.iter()
+            .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+            .collect::<std::collections::HashMap<String, String>>()
+    });
+
+    println!("[AgentTools] Run command: {} (cwd: {}, timeout: {}s)", command, working_dir.display(), timeout_secs);
+    let result = crate::commands::bash_streaming::execute_bash_command_streaming(
+        command.to_string(),
+        Some(working_dir.to_string_lossy().to_string()),
+        Some(timeout_secs * 1000),
+        Some(crate::commands::bash_streaming::filter_env(std::env::vars(), extra_env)),
+        false,
+        stream_id.to_string(),
+        None,
+        app.clone(),
+    ).await?;
+
+    let mut output = if result.timed_out {
+        format!("Command '{}' timed out after {}s in {}.\n", command, timeout_secs, cwd)
+    } else {
+        format!("Command '{}' exited with code {} in {} ({} ms).\n", command, result.exit_code, cwd, result.elapsed_ms)
+    };
+    for (label, content) in [("stdout", &result.stdout), ("stderr", &result.stderr)] {
+        if let Some(content) = content.as_deref().filter(|c| !c.trim().is_empty()) {
+            output.push_str(&format!("{}:\n{}\n", label, content));
+        }
+    }
+    if result.stdout.is_none() && result.stderr.is_none() {
+        output.push_str("(No output produced)\n");
+    }
+    if let Some(id) = &result.background_id {
+        output.push_str(&format!("The process is still running in the background as {} (the user can view its logs or stop it).", id));
+    }
+    Ok(output)
+}
+
 pub async fn execute_tool_internal(
     tool_name: &str,
     args: &Value,
@@ -122,16 +193,19 @@ pub async fn execute_tool_internal(
     match tool_name {
         "agent_read_file" => {
             let rel_path = get_arg_str(args, "rel_path", "");
+            ensure_in_root(&calibrated_root, rel_path, "read")?;
             agent::agent_read_file(calibrated_root, rel_path.to_string()).await
         },
         "agent_list_dir" => {
             let rel_path = get_arg_str(args, "rel_path", ".");
+            ensure_in_root(&calibrated_root, rel_path, "list")?;
             let result = agent::agent_list_dir(calibrated_root, rel_path.to_string()).await?;
             Ok(result.join("\n"))
         },
         "agent_write_file" => {
             let rel_path = get_arg_str(args, "rel_path", "");
             let content = get_arg_str(args, "content", "");
+            ensure_in_root(&calibrated_root, rel_path, "write")?;
 
             // Fix: Unescape escape sequences in content (\\n -> \n, \\t -> \t, etc.)
             let unescaped_content = unescape_string(content);
@@ -143,6 +217,16 @@ pub async fn execute_tool_internal(
             serde_json::to_string(&result)
                 .map_err(|e| format!("Failed to serialize WriteFileResult: {}", e))
         },
+        "agent_edit_file" => {
+            let rel_path = get_arg_str(args, "rel_path", "");
+            let edits = match args.get("edits").filter(|v| !v.is_null()) {
+                Some(v) => Some(serde_json::from_value(v.clone()).map_err(|e| format!("Invalid 'edits' argument: {}", e))?),
+                None => None,
+            };
+            let diff = get_arg_opt_str(args, "diff");
+            println!("[AgentTools] Editing file: {}", rel_path);
+            crate::commands::core_wrappers::agent_edit_file(calibrated_root, rel_path.to_string(), edits, diff).await
+        },
         "agent_batch_read" => {
             let paths_array = args["paths"].as_array()
                 .or_else(|| args["Paths"].as_array())
@@ -176,6 +260,71 @@ pub async fn execute_tool_internal(
                 max_files
             ).await
         },
+        "agent_grep" => {
+            let pattern = get_arg_str(args, "pattern", "");
+            let rel_path = get_arg_opt_str(args, "rel_path");
+            let globs = |key: &str| -> Option<Vec<String>> {
+                match &args[key] {
+                    Value::Array(items) => Some(items.iter().filter_map(|v| v.as_str()).map(String::from).collect()),
+                    Value::String(s) => Some(s.split(',').map(|g| g.trim().to_string()).filter(|g| !g.is_empty()).collect()),
+                    _ => None,
+                }
+            };
+            let case_insensitive = args["case_insensitive"].as_bool().or_else(|| args["caseInsensitive"].as_bool());
+            let max_results = get_arg_opt_u64(args, "max_results").map(|v| v as usize);
+
+            println!("[AgentTools] Grep: {} (in {:?})", pattern, rel_path);
+            crate::commands::core_wrappers::agent_grep(
+                calibrated_root,
+                rel_path,
+                pattern.to_string(),
+                globs("include"),
+                globs("exclude"),
+                case_insensitive,
+                max_results
+            ).await
+        },
+        "agent_get_diagnostics" => {
+            let rel_path = get_arg_str(args, "rel_path", ".");
+            let target = ensure_in_root(&calibrated_root, rel_path, "read diagnostics for")?;
+            let files = crate::lsp::diagnostics_for(Some(&target.to_string_lossy()));
+            println!("[AgentTools] Diagnostics for {}: {} files", rel_path, files.len());
+            if files.is_empty() {
+                return Ok(format!(
+                    "No diagnostics reported for {}. (Diagnostics come from the editor's language servers and only cover files opened while a server is running.)",
+                    rel_path
+                ));
+            }
+            Ok(crate::lsp::format_diagnostics(&files, std::path::Path::new(&calibrated_root)))
+        },
+        "agent_read_tool_output" => {
+            let handle = get_arg_str(args, "handle", "");
+            let start_line = get_arg_opt_u64(args, "start_line").unwrap_or(1) as usize;
+            let max_lines = get_arg_opt_u64(args, "max_lines").map(|v| v as usize).unwrap_or(crate::tool_output::DEFAULT_READ_LINES);
+            crate::tool_output::read_full_output(handle, start_line, max_lines)
+        },
+        "agent_eval_js" => {
+            let snippet = get_arg_str(args, "snippet", "");
+            if snippet.is_empty() {
+                return Err("Missing 'snippet' in arguments".to_string());
+            }
+            let language = get_arg_opt_str(args, "language");
+            let timeout_ms = get_arg_opt_u64(args, "timeout_ms");
+
+            println!("[AgentTools] Evaluating JS snippet ({} chars)", snippet.len());
+            let result = crate::js_sandbox::eval_js(snippet.to_string(), language, timeout_ms).await?;
+            Ok(crate::js_sandbox::format_for_model(&result))
+        },
+        "agent_fetch_url" => {
+            let url = get_arg_str(args, "url", "");
+            if url.is_empty() {
+                return Err("Missing 'url' in arguments".to_string());
+            }
+            let max_chars = get_arg_opt_u64(args, "max_chars")
+                .map(|v| v as usize)
+                .unwrap_or(crate::web_fetch::DEFAULT_MAX_CHARS);
+            crate::web_fetch::fetch_text(url, max_chars).await
+        },
         "bash" | "agent_run_shell_command" | "agent_execute_command" => {
             let command = get_arg_str(args, "command", "");
             let working_dir_arg = get_arg_opt_str(args, "working_dir");
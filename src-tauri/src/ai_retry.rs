@@ -0,0 +1,243 @@
+/*!
+AI Retry - AI 请求重试
+======================
+
+429 限流、5xx 等瞬时错误不再直接中断整个对话或 Agent 运行：
+
+- 可重试：HTTP 408 / 409 / 429 / 5xx（含 Anthropic 529 过载），以及连接失败、超时等网络错误
+- 指数退避（`base_delay_ms * 2^n`，不超过 `max_delay_ms`）并叠加随机抖动；
+  服务端返回 `Retry-After` 时按其等待（同样不超过上限）
+- 只重试拿到响应之前的阶段；流式输出开始后出错不重试，避免重复输出
+- 每次重试前在当前通道（`with_channel` 设置）发送 `retrying` 事件，前端显示 "retrying (2/3)…"
+
+覆盖 `ai_utils` 发出的全部云端请求：补全、对话流式（社区版与商业版的 OpenAI / Anthropic / Gemini 协议）
+以及 Agent 流式请求。不经过 `ai_utils` 的请求（本地模型推理、`ifainew_core` 的 RAG 索引）不在重试范围内。
+
+配置保存在 `~/.ifai/ai_retry.json`。
+*/
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::events::StreamEvent;
+
+/// 错误信息中携带 `Retry-After` 的标记
+const RETRY_AFTER_MARKER: &str = "[retry-after: ";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AiRetryConfig {
+    /// 最大尝试次数（含首次），1 表示不重试
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间
+    pub base_delay_ms: u64,
+    /// 单次等待上限（也限制 Retry-After）
+    pub max_delay_ms: u64,
+    /// 抖动比例（0.2 表示 ±20%）
+    pub jitter: f64,
+}
+
+impl Default for AiRetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 1000,
+            max_delay_ms: 30_000,
+            jitter: 0.2,
+        }
+    }
+}
+
+fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("ai_retry.json")
+}
+
+pub fn load_config() -> AiRetryConfig {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(config: &AiRetryConfig) -> Result<(), String> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write AI retry config: {}", e))
+}
+
+// ============================================================================
+// Classification
+// ============================================================================
+
+/// 构造 HTTP 错误信息；带 `Retry-After` 时附加标记供重试逻辑读取
+pub fn api_error(status: StatusCode, headers: &HeaderMap, body: &str) -> String {
+    let mut err = format!("AI API Error ({}): {}", status, body);
+    if let Some(secs) = headers.get(RETRY_AFTER).and_then(|v| v.to_str().ok()).and_then(parse_retry_after) {
+        err.push_str(&format!(" {}{}s]", RETRY_AFTER_MARKER, secs));
+    }
+    err
+}
+
+/// `Retry-After` 可以是秒数或 HTTP 日期
+fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((date.timestamp() - chrono::Utc::now().timestamp()).max(0) as u64)
+}
+
+fn status_of(err: &str) -> Option<u16> {
+    let rest = err.strip_prefix("AI API Error (")?;
+    rest.split(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
+
+fn retry_after_of(err: &str) -> Option<Duration> {
+    let start = err.rfind(RETRY_AFTER_MARKER)? + RETRY_AFTER_MARKER.len();
+    let secs = err[start..].split('s').next()?.parse().ok()?;
+    Some(Duration::from_secs(secs))
+}
+
+/// 是否为可重试的瞬时错误
+pub fn is_retryable(err: &str) -> bool {
+    match status_of(err) {
+        Some(status) => matches!(status, 408 | 409 | 429) || status >= 500,
+        None => err.starts_with("Network error") || err.starts_with("Network/Request error") || err.starts_with("Failed to read response bytes"),
+    }
+}
+
+/// 第 `attempt` 次失败后的等待时间（`unit` 为 [0, 1) 的随机数）
+pub fn backoff_delay(config: &AiRetryConfig, attempt: u32, err: &str, unit: f64) -> Duration {
+    if let Some(after) = retry_after_of(err) {
+        return after.min(Duration::from_millis(config.max_delay_ms));
+    }
+    let exp = config.base_delay_ms.saturating_mul(1u64 << attempt.saturating_sub(1).min(20));
+    let capped = exp.min(config.max_delay_ms) as f64;
+    let jitter = config.jitter.clamp(0.0, 1.0);
+    let factor = 1.0 - jitter + 2.0 * jitter * unit.clamp(0.0, 1.0);
+    Duration::from_millis((capped * factor) as u64)
+}
+
+fn random_unit() -> f64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos())
+        .unwrap_or(0);
+    (nanos % 1000) as f64 / 1000.0
+}
+
+// ============================================================================
+// Retry
+// ============================================================================
+
+tokio::task_local! {
+    /// 重试进度事件的发送通道（由 `with_channel` 设置）
+    static RETRY_CHANNEL: (AppHandle, String);
+}
+
+/// 在指定事件通道上下文中执行请求，重试时向该通道发送 `retrying` 事件
+pub async fn with_channel<F: Future>(app: AppHandle, channel: String, fut: F) -> F::Output {
+    RETRY_CHANNEL.scope((app, channel), fut).await
+}
+
+fn report(attempt: u32, max_attempts: u32, delay: Duration, err: &str) {
+    let _ = RETRY_CHANNEL.try_with(|(app, channel)| {
+        crate::agent_log::emit(app, channel, &StreamEvent::Retrying {
+            attempt,
+            max_attempts,
+            delay_ms: delay.as_millis() as u64,
+            error: err.chars().take(300).collect(),
+        });
+    });
+}
+
+/// 按配置重试 `op`；`label` 仅用于日志
+pub async fn run<T, F, Fut>(label: &str, mut op: F) -> Result<T, String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, String>>,
+{
+    let config = load_config();
+    let max_attempts = config.max_attempts.max(1);
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(err) if attempt < max_attempts && is_retryable(&err) => {
+                let delay = backoff_delay(&config, attempt, &err, random_unit());
+                println!("[AIRetry] {} failed (attempt {}/{}), retrying in {:?}: {}", label, attempt, max_attempts, delay, err);
+                report(attempt + 1, max_attempts, delay, &err);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_ai_retry_config() -> AiRetryConfig {
+    load_config()
+}
+
+#[tauri::command]
+pub fn set_ai_retry_config(config: AiRetryConfig) -> Result<(), String> {
+    if config.max_attempts == 0 {
+        return Err("max_attempts must be at least 1".to_string());
+    }
+    if !(0.0..=1.0).contains(&config.jitter) {
+        return Err("jitter must be between 0 and 1".to_string());
+    }
+    save_config(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classification_and_retry_after() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "7".parse().unwrap());
+        let err = api_error(StatusCode::TOO_MANY_REQUESTS, &headers, "rate limited");
+        assert!(err.starts_with("AI API Error (429 Too Many Requests): rate limited"));
+        assert!(is_retryable(&err));
+        assert_eq!(retry_after_of(&err), Some(Duration::from_secs(7)));
+
+        assert!(is_retryable("AI API Error (503 Service Unavailable): "));
+        assert!(is_retryable("Network error: connection reset"));
+        assert!(!is_retryable("AI API Error (400 Bad Request): tools not supported"));
+        assert!(!is_retryable("AI API Error (401 Unauthorized): bad key"));
+        assert!(!is_retryable("Failed to parse AI response as JSON: eof"));
+    }
+
+    #[test]
+    fn test_backoff_delay() {
+        let config = AiRetryConfig { jitter: 0.0, ..Default::default() };
+        assert_eq!(backoff_delay(&config, 1, "Network error", 0.5), Duration::from_millis(1000));
+        assert_eq!(backoff_delay(&config, 3, "Network error", 0.5), Duration::from_millis(4000));
+        assert_eq!(backoff_delay(&config, 10, "Network error", 0.5), Duration::from_millis(30_000));
+        // Retry-After 优先，但不超过上限
+        assert_eq!(backoff_delay(&config, 1, "AI API Error (429): x [retry-after: 5s]", 0.5), Duration::from_secs(5));
+        assert_eq!(backoff_delay(&config, 1, "AI API Error (429): x [retry-after: 120s]", 0.5), Duration::from_secs(30));
+
+        let jittered = AiRetryConfig { jitter: 0.2, ..Default::default() };
+        assert_eq!(backoff_delay(&jittered, 1, "Network error", 0.0), Duration::from_millis(800));
+        assert_eq!(backoff_delay(&jittered, 1, "Network error", 1.0), Duration::from_millis(1200));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::core_traits::ai::{Message, Content, ToolCall, AIProviderConfig, FunctionCall};
+use crate::core_traits::ai::{Message, Content, ContentPart, ToolCall, AIProviderConfig, FunctionCall};
 use serde_json::{json, Value};
 use reqwest::Client;
 use std::time::{Duration, Instant};
@@ -6,6 +6,11 @@ use std::collections::HashMap;
 use tauri::{AppHandle, Emitter};
 use futures::stream::StreamExt;
 use eventsource_stream::Eventsource;
+use crate::partial_json::PartialJsonParser;
+use crate::anthropic_api::{self, AnthropicStream};
+use crate::gemini_api::{self, GeminiStream};
+use crate::tool_capability;
+use crate::events::{StreamEvent, ToolCallPayload};
 
 pub fn sanitize_messages(messages: &mut Vec<Message>) {
     let mut i = 0;
@@ -41,13 +46,70 @@ pub fn sanitize_messages(messages: &mut Vec<Message>) {
     }
 }
 
+/// v0.3.4: OpenAI 兼容协议的消息列表；多段内容只保留协议定义的字段
+/// （`text` 与 `image_url`），图片以 data URL 或 http(s) URL 原样传递
+pub fn openai_messages(messages: &[Message]) -> Value {
+    messages
+        .iter()
+        .map(|message| {
+            let mut value = json!(message);
+            if let Content::Parts(parts) = &message.content {
+                value["content"] = parts
+                    .iter()
+                    .map(|part| match part {
+                        ContentPart::Text { text, .. } => json!({ "type": "text", "text": text }),
+                        ContentPart::ImageUrl { image_url } => json!({ "type": "image_url", "image_url": { "url": image_url.url } }),
+                    })
+                    .collect();
+            }
+            value
+        })
+        .collect()
+}
+
 pub async fn fetch_ai_completion(
+    config: &AIProviderConfig,
+    messages: Vec<Message>,
+    tools: Option<Vec<Value>>,
+) -> Result<Message, String> {
+    // v0.3.4: 不支持 function calling 的模型降级为提示词工具协议
+    let Some(tool_list) = tools.filter(|t| !t.is_empty()) else {
+        return fetch_completion(config, messages, None).await;
+    };
+    if !tool_capability::supports_tools(config) {
+        return fetch_with_prompt_tools(config, &messages, &tool_list).await;
+    }
+    match fetch_completion(config, messages.clone(), Some(tool_list.clone())).await {
+        Err(e) if tool_capability_rejected(&e) => {
+            tool_capability::mark_unsupported(config);
+            fetch_with_prompt_tools(config, &messages, &tool_list).await
+        }
+        result => result,
+    }
+}
+
+/// 错误信息形如 `AI API Error (400 Bad Request): ...` 时判断是否因工具参数被拒
+fn tool_capability_rejected(err: &str) -> bool {
+    let Some(rest) = err.strip_prefix("AI API Error (") else {
+        return false;
+    };
+    let status = rest.split(|c: char| !c.is_ascii_digit()).next().and_then(|s| s.parse().ok()).unwrap_or(0);
+    tool_capability::is_tool_unsupported_error(status, rest)
+}
+
+async fn fetch_with_prompt_tools(config: &AIProviderConfig, messages: &[Message], tools: &[Value]) -> Result<Message, String> {
+    let prompt_messages = tool_capability::to_prompt_messages(messages, tools);
+    fetch_completion(config, prompt_messages, None).await.map(tool_capability::into_tool_message)
+}
+
+async fn fetch_completion(
     config: &AIProviderConfig,
     mut messages: Vec<Message>, // Change to mutable to allow sanitization
     tools: Option<Vec<Value>>,
 ) -> Result<Message, String> {
     // Apply sanitization before every internal API call
     sanitize_messages(&mut messages);
+    crate::idle_manager::touch();
 
     let client = Client::builder()
         .timeout(Duration::from_secs(120)) // Increase timeout to 2 minutes
@@ -57,19 +119,55 @@ pub async fn fetch_ai_completion(
         .build()
         .map_err(|e| e.to_string())?;
     
-    let mut request_body = json!({
-        "model": config.models[0],
-        "messages": messages,
-        "stream": false
-    });
+    let mut request_body = if anthropic_api::is_anthropic(config) {
+        anthropic_api::build_request(config, &messages, tools.as_deref(), false)
+    } else if gemini_api::is_gemini(config) {
+        gemini_api::build_request(&messages, tools.as_deref())
+    } else {
+        let mut body = json!({
+            "model": config.models[0],
+            "messages": openai_messages(&messages),
+            "stream": false
+        });
+        if let Some(t) = tools {
+            body["tools"] = json!(t);
+        }
+        body
+    };
 
-    if let Some(t) = tools {
-        request_body["tools"] = json!(t);
+    // v0.3.4: 按对话阶段自动调度 temperature；会话的详略预设决定 max_tokens
+    crate::temperature_schedule::apply_to_request(&mut request_body, &messages);
+    crate::verbosity::apply_to_request(&mut request_body);
+    if anthropic_api::is_anthropic(config) {
+        anthropic_api::clamp_temperature(&mut request_body);
+    } else if gemini_api::is_gemini(config) {
+        gemini_api::move_temperature(&mut request_body);
     }
 
-    let response = client.post(&config.base_url)
-        .header("Authorization", format!("Bearer {}", config.api_key))
-        .json(&request_body)
+    // v0.3.4: 瞬时错误退避重试；最终失败的请求记录下来，便于回放排查
+    let result = crate::ai_retry::run("completion", || post_completion(&client, config, &request_body)).await;
+    if let Err(e) = &result {
+        crate::failed_requests::record_failure("completion", config, &request_body, e);
+    }
+    result
+}
+
+async fn post_completion(
+    client: &Client,
+    config: &AIProviderConfig,
+    request_body: &Value,
+) -> Result<Message, String> {
+    let mut meter = crate::usage_meter::RequestMeter::start("completion");
+    let request = if anthropic_api::is_anthropic(config) {
+        anthropic_api::post(client, config)
+    } else if gemini_api::is_gemini(config) {
+        gemini_api::post(client, config, false)
+    } else {
+        client.post(&config.base_url)
+            .header("Authorization", format!("Bearer {}", config.api_key))
+    };
+    let response = request
+        .json(request_body)
         .send()
         .await
         .map_err(|e| format!("Network/Request error: {}", e))?;
@@ -89,7 +187,7 @@ pub async fn fetch_ai_completion(
     if !status.is_success() {
         let err_body = response.text().await.unwrap_or_default();
         eprintln!("[AIUtils] API HTTP Error {}: {}", status, err_body);
-        return Err(format!("AI API Error ({}): {}", status, err_body));
+        return Err(crate::ai_retry::api_error(status, &headers, &err_body));
     }
 
     // Try to read response as bytes first, then convert to string
@@ -128,6 +226,15 @@ pub async fn fetch_ai_completion(
         );
         format!("Failed to parse AI response as JSON: {}", e)
     })?;
+
+    meter.response(config, &res_json);
+    meter.finish(config, || None);
+    if anthropic_api::is_anthropic(config) {
+        return anthropic_api::parse_response(&res_json);
+    }
+    if gemini_api::is_gemini(config) {
+        return gemini_api::parse_response(&res_json);
+    }
     
     let choice = &res_json["choices"][0]["message"];
     if choice.is_null() {
@@ -162,10 +269,303 @@ pub async fn fetch_ai_completion(
     })
 }
 
+/// 发送 Anthropic 流式请求，返回 SSE 响应
+async fn send_anthropic_stream(
+    client: &Client,
+    config: &AIProviderConfig,
+    messages: &[Message],
+    tools: Option<&[Value]>,
+    source: &str,
+) -> Result<reqwest::Response, String> {
+    let mut request_body = anthropic_api::build_request(config, messages, tools, true);
+    crate::temperature_schedule::apply_to_request(&mut request_body, messages);
+    crate::verbosity::apply_to_request(&mut request_body);
+    anthropic_api::clamp_temperature(&mut request_body);
+
+    let body = &request_body;
+    let result = crate::ai_retry::run(source, || async move {
+        let response = anthropic_api::post(client, config)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let error_text = response.text().await.unwrap_or_default();
+            eprintln!("[AIUtils] Anthropic API Error: {}: {}", status, error_text);
+            return Err(crate::ai_retry::api_error(status, &headers, &error_text));
+        }
+        Ok(response)
+    }).await;
+    if let Err(err) = &result {
+        crate::failed_requests::record_failure(source, config, &request_body, err);
+    }
+    result
+}
+
+/// Anthropic 协议的对话流式请求
+///
+/// SSE 事件被转换为 OpenAI 兼容的数据块后交给回调，与其他 Provider 的流式输出格式一致
+pub async fn stream_chat_anthropic(
+    config: &AIProviderConfig,
+    mut messages: Vec<Message>,
+    tools: Option<Vec<Value>>,
+    callback: Box<dyn Fn(String) + Send>,
+) -> Result<(), String> {
+    sanitize_messages(&mut messages);
+    crate::idle_manager::touch();
+
+    let client = Client::builder()
+        .connect_timeout(Duration::from_secs(60))
+        .timeout(Duration::from_secs(600))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut meter = crate::usage_meter::RequestMeter::start("chat");
+    let response = send_anthropic_stream(&client, config, &messages, tools.as_deref(), "chat_stream").await?;
+    let mut stream = response.bytes_stream().eventsource();
+    let mut translator = AnthropicStream::new();
+
+    while let Some(event) = stream.next().await {
+        let event = event.map_err(|e| format!("Stream error: {}", e))?;
+        if let Ok(data) = serde_json::from_str::<Value>(&event.data) {
+            meter.anthropic_event(&data);
+        }
+        for chunk in translator.handle_event(&event.data)? {
+            callback(chunk.to_string());
+        }
+    }
+    meter.finish(config, || None);
+    Ok(())
+}
+
+/// 发送 Gemini 流式请求（`streamGenerateContent?alt=sse`），返回 SSE 响应
+async fn send_gemini_stream(
+    client: &Client,
+    config: &AIProviderConfig,
+    messages: &[Message],
+    tools: Option<&[Value]>,
+    source: &str,
+) -> Result<reqwest::Response, String> {
+    let mut request_body = gemini_api::build_request(messages, tools);
+    crate::temperature_schedule::apply_to_request(&mut request_body, messages);
+    crate::verbosity::apply_to_request(&mut request_body);
+    gemini_api::move_temperature(&mut request_body);
+
+    let body = &request_body;
+    let result = crate::ai_retry::run(source, || async move {
+        let response = gemini_api::post(client, config, true)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let error_text = response.text().await.unwrap_or_default();
+            eprintln!("[AIUtils] Gemini API Error: {}: {}", status, error_text);
+            return Err(crate::ai_retry::api_error(status, &headers, &error_text));
+        }
+        Ok(response)
+    }).await;
+    if let Err(err) = &result {
+        crate::failed_requests::record_failure(source, config, &request_body, err);
+    }
+    result
+}
+
+/// Gemini 协议的对话流式请求，数据块同样转换为 OpenAI 兼容格式
+pub async fn stream_chat_gemini(
+    config: &AIProviderConfig,
+    mut messages: Vec<Message>,
+    tools: Option<Vec<Value>>,
+    callback: Box<dyn Fn(String) + Send>,
+) -> Result<(), String> {
+    sanitize_messages(&mut messages);
+    crate::idle_manager::touch();
+
+    let client = Client::builder()
+        .connect_timeout(Duration::from_secs(60))
+        .timeout(Duration::from_secs(600))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut meter = crate::usage_meter::RequestMeter::start("chat");
+    let response = send_gemini_stream(&client, config, &messages, tools.as_deref(), "chat_stream").await?;
+    let mut stream = response.bytes_stream().eventsource();
+    let mut translator = GeminiStream::new();
+
+    while let Some(event) = stream.next().await {
+        let event = event.map_err(|e| format!("Stream error: {}", e))?;
+        if let Ok(data) = serde_json::from_str::<Value>(&event.data) {
+            meter.gemini_chunk(&data);
+        }
+        for chunk in translator.handle_event(&event.data)? {
+            callback(chunk.to_string());
+        }
+    }
+    meter.finish(config, || None);
+    Ok(())
+}
+
+/// 发送 OpenAI 兼容协议的流式请求，返回 SSE 响应
+async fn send_openai_stream(
+    client: &Client,
+    config: &AIProviderConfig,
+    messages: &[Message],
+    tools: Option<&[Value]>,
+    source: &str,
+) -> Result<reqwest::Response, String> {
+    let mut request_body = json!({
+        "model": config.models[0],
+        "messages": openai_messages(messages),
+        "stream": true
+    });
+    if let Some(t) = tools {
+        request_body["tools"] = json!(t);
+    }
+    crate::temperature_schedule::apply_to_request(&mut request_body, messages);
+    crate::verbosity::apply_to_request(&mut request_body);
+
+    let body = &request_body;
+    let result = crate::ai_retry::run(source, || async move {
+        let response = client
+            .post(&config.base_url)
+            .header("Authorization", format!("Bearer {}", config.api_key))
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| format!("Network error: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let error_text = response.text().await.unwrap_or_default();
+            eprintln!("[AIUtils] Stream API Error: {}: {}", status, error_text);
+            return Err(crate::ai_retry::api_error(status, &headers, &error_text));
+        }
+        Ok(response)
+    }).await;
+    if let Err(err) = &result {
+        crate::failed_requests::record_failure(source, config, &request_body, err);
+    }
+    result
+}
+
+/// 把完整的回复按流式数据块格式交给回调（用于无法流式输出的情况）
+pub fn emit_message_chunks(msg: &Message, callback: &(dyn Fn(String) + Send)) {
+    if let Content::Text(text) = &msg.content {
+        if !text.is_empty() {
+            callback(json!({ "choices": [{ "index": 0, "delta": { "content": text } }] }).to_string());
+        }
+    }
+    for (index, tc) in msg.tool_calls.iter().flatten().enumerate() {
+        callback(json!({
+            "type": "tool_call",
+            "tool_call": {
+                "index": index,
+                "id": &tc.id,
+                "type": &tc.r#type,
+                "function": { "name": &tc.function.name, "arguments": &tc.function.arguments }
+            }
+        }).to_string());
+    }
+}
+
+/// OpenAI 兼容协议的对话流式请求
+///
+/// 文本/推理数据块原样交给回调；工具调用增量转换为 `tool_call` 事件（按 `index` 累积）。
+/// 模型不支持 function calling 时回退为提示词工具协议的一次性请求。
+pub async fn stream_chat_openai(
+    config: &AIProviderConfig,
+    mut messages: Vec<Message>,
+    tools: Option<Vec<Value>>,
+    callback: Box<dyn Fn(String) + Send>,
+) -> Result<(), String> {
+    sanitize_messages(&mut messages);
+    crate::idle_manager::touch();
+
+    let tools = tools.filter(|t| !t.is_empty());
+    if tools.is_some() && !tool_capability::supports_tools(config) {
+        let msg = fetch_ai_completion(config, messages, tools).await?;
+        emit_message_chunks(&msg, callback.as_ref());
+        return Ok(());
+    }
+
+    let client = Client::builder()
+        .connect_timeout(Duration::from_secs(60))
+        .timeout(Duration::from_secs(600))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut meter = crate::usage_meter::RequestMeter::start("chat");
+    let response = match send_openai_stream(&client, config, &messages, tools.as_deref(), "chat_stream").await {
+        Err(e) if tools.is_some() && tool_capability_rejected(&e) => {
+            tool_capability::mark_unsupported(config);
+            let msg = fetch_ai_completion(config, messages, tools).await?;
+            emit_message_chunks(&msg, callback.as_ref());
+            return Ok(());
+        }
+        result => result?,
+    };
+
+    let mut stream = response.bytes_stream().eventsource();
+    // 接口未返回 usage 时用于估算输出 Token
+    let mut completion = String::new();
+    while let Some(event) = stream.next().await {
+        let event = event.map_err(|e| format!("Stream error: {}", e))?;
+        if event.data.trim() == "[DONE]" {
+            break;
+        }
+        let Ok(chunk) = serde_json::from_str::<Value>(&event.data) else {
+            continue;
+        };
+        if chunk["usage"].is_object() {
+            meter.openai_usage(&chunk["usage"]);
+        }
+
+        let delta = &chunk["choices"][0]["delta"];
+        for key in ["content", "reasoning_content"] {
+            completion.push_str(delta[key].as_str().unwrap_or(""));
+        }
+        if let Some(tool_chunks) = delta["tool_calls"].as_array() {
+            for tc in tool_chunks {
+                completion.push_str(tc["function"]["arguments"].as_str().unwrap_or(""));
+                callback(json!({
+                    "type": "tool_call",
+                    "tool_call": {
+                        "index": tc["index"].as_i64().unwrap_or(0),
+                        "id": tc["id"].as_str().unwrap_or(""),
+                        "type": "function",
+                        "function": {
+                            "name": tc["function"]["name"].as_str().unwrap_or(""),
+                            "arguments": tc["function"]["arguments"].as_str().unwrap_or("")
+                        }
+                    }
+                }).to_string());
+            }
+            // 同一数据块中的 finish_reason 仍需传递
+            if chunk["choices"][0]["finish_reason"].is_string() {
+                callback(json!({ "choices": [{ "index": 0, "delta": {}, "finish_reason": chunk["choices"][0]["finish_reason"] }] }).to_string());
+            }
+        } else {
+            callback(event.data);
+        }
+    }
+    meter.finish(config, || Some(crate::usage_meter::estimate(config, &messages, &completion)));
+    Ok(())
+}
+
 // Streaming response data structures
 #[derive(serde::Deserialize, Debug)]
 struct OpenAIStreamResponse {
     choices: Vec<StreamChoice>,
+    /// 部分服务在最后一个块中返回本次请求的 Token 用量
+    #[serde(default)]
+    usage: Option<Value>,
 }
 
 #[derive(serde::Deserialize, Debug)]
@@ -197,25 +597,8 @@ struct StreamingToolCall {
     id: String,
     name: String,
     arguments: String,
-}
-
-fn extract_partial_value(json_str: &str, key: &str) -> Option<String> {
-    // Enhanced regex to find string values in partial JSON, including multiline content
-    // Matches: "key": "value..." where value can contain escaped quotes and newlines
-    let pattern = format!(r#""{}"\s*:\s*"((?:[^"\\]|\\.|[\n\r])*)"#, key);
-    if let Ok(re) = regex::Regex::new(&pattern) {
-        if let Some(caps) = re.captures(json_str) {
-            let mut val = caps[1].to_string();
-            // Enhanced unescaping for display purposes
-            val = val.replace("\\n", "\n")
-                     .replace("\\r", "\r")
-                     .replace("\\t", "\t")
-                     .replace("\\\"", "\"")
-                     .replace("\\\\", "\\");
-            return Some(val);
-        }
-    }
-    None
+    /// v0.3.4: 增量解析参数，用于渐进式预览
+    parser: PartialJsonParser,
 }
 
 pub fn extract_task_path(msg: &str) -> String {
@@ -289,6 +672,54 @@ mod tests {
         assert_eq!(extract_task_path("这是个包含.的点号但很长的句子，不应该被识别为路径。"), ".");
         assert_eq!(extract_task_path("这是一个带有.js扩展名的中文字句"), ".");
     }
+
+    #[test]
+    fn test_emit_message_chunks() {
+        let chunks = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = chunks.clone();
+        let msg = Message {
+            role: "assistant".to_string(),
+            content: Content::Text("Running it".to_string()),
+            tool_calls: Some(vec![ToolCall {
+                id: "call_1".to_string(),
+                r#type: "function".to_string(),
+                function: FunctionCall { name: "bash".to_string(), arguments: "{\"command\":\"ls\"}".to_string() },
+            }]),
+            tool_call_id: None,
+        };
+        emit_message_chunks(&msg, &move |chunk| sink.lock().unwrap().push(chunk));
+
+        let chunks: Vec<Value> = chunks.lock().unwrap().iter().map(|c| serde_json::from_str(c).unwrap()).collect();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0]["choices"][0]["delta"]["content"], "Running it");
+        assert_eq!(chunks[1]["type"], "tool_call");
+        assert_eq!(chunks[1]["tool_call"]["function"]["name"], "bash");
+    }
+
+    #[test]
+    fn test_openai_messages_keep_images() {
+        use crate::core_traits::ai::ImageUrl;
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: Content::Parts(vec![
+                ContentPart::Text { text: "What is wrong here?".to_string(), part_type: "text".to_string() },
+                ContentPart::ImageUrl { image_url: ImageUrl { url: "data:image/png;base64,AAAA".to_string() } },
+            ]),
+            tool_calls: None,
+            tool_call_id: None,
+        }];
+        let value = openai_messages(&messages);
+        assert_eq!(value[0]["content"][0], json!({ "type": "text", "text": "What is wrong here?" }));
+        assert_eq!(value[0]["content"][1], json!({ "type": "image_url", "image_url": { "url": "data:image/png;base64,AAAA" } }));
+        assert_eq!(value[0]["role"], "user");
+    }
+
+    #[test]
+    fn test_tool_capability_rejected() {
+        assert!(tool_capability_rejected("AI API Error (400 Bad Request): {\"error\":\"model does not support tools\"}"));
+        assert!(!tool_capability_rejected("AI API Error (401 Unauthorized): tools not supported"));
+        assert!(!tool_capability_rejected("Network/Request error: tools not supported"));
+    }
 }
 
 /// Agent-specific streaming chat that returns a Message (unlike stream_chat which only emits events)
@@ -313,6 +744,7 @@ pub async fn agent_stream_chat_with_root(
     agent_type: Option<String>,
 ) -> Result<Message, String> {
     eprintln!("[AgentStream] agent_stream_chat called with agent_id: {}, agent_type: {:?}", agent_id, agent_type);
+    crate::idle_manager::touch();
 
     // 检查 agent 类型
     let (is_explore_agent, is_hybrid_agent) = if let Some(ref at) = agent_type {
@@ -969,34 +1401,78 @@ pub async fn agent_stream_chat_with_root(
             e.to_string()
         })?;
 
-    let mut request_body = json!({
-        "model": config.models[0],
-        "messages": clean_messages,
-        "stream": true  // Enable streaming
-    });
-
-    if let Some(t) = tools {
-        request_body["tools"] = json!(t);
+    // Anthropic Messages API 使用独立的 SSE 事件格式
+    if anthropic_api::is_anthropic(config) {
+        return agent_stream_anthropic(app, &client, config, &clean_messages, agent_id, tools).await;
+    }
+    if gemini_api::is_gemini(config) {
+        return agent_stream_gemini(app, &client, config, &clean_messages, agent_id, tools).await;
     }
 
-    eprintln!("[AgentStream] Sending streaming request for agent {}", agent_id);
-
-    // 3. Send HTTP request
-    let response = client
-        .post(&config.base_url)
-        .header("Authorization", format!("Bearer {}", config.api_key))
-        .header("Content-Type", "application/json")
-        .json(&request_body)
-        .send()
-        .await
-        .map_err(|e| format!("Network error: {}", e))?;
+    // v0.3.4: 不支持 function calling 的模型改用提示词工具协议
+    let tools = tools.filter(|t| !t.is_empty());
+    let mut prompt_tools = tools.is_some() && !tool_capability::supports_tools(config);
+    let mut meter = crate::usage_meter::RequestMeter::start("agent");
+
+    let response = loop {
+        let request_messages = match (&tools, prompt_tools) {
+            (Some(t), true) => tool_capability::to_prompt_messages(&clean_messages, t),
+            _ => clean_messages.clone(),
+        };
+        let mut request_body = json!({
+            "model": config.models[0],
+            "messages": openai_messages(&request_messages),
+            "stream": true  // Enable streaming
+        });
+
+        // v0.3.4: 按对话阶段自动调度 temperature；会话的详略预设决定 max_tokens
+        crate::temperature_schedule::apply_to_request(&mut request_body, &clean_messages);
+        crate::verbosity::apply_to_request(&mut request_body);
+
+        if let Some(t) = tools.as_ref().filter(|_| !prompt_tools) {
+            request_body["tools"] = json!(t);
+        }
 
-    let status = response.status();
-    if !status.is_success() {
-        let error_text = response.text().await.unwrap_or_default();
-        eprintln!("[AgentStream] API Error: {}: {}", status, error_text);
-        return Err(format!("AI API Error ({}): {}", status, error_text));
-    }
+        eprintln!("[AgentStream] Sending streaming request for agent {} (prompt tools: {})", agent_id, prompt_tools);
+
+        // 3. Send HTTP request（v0.3.4: 瞬时错误退避重试）
+        let (client, body) = (&client, &request_body);
+        let result = crate::ai_retry::run("agent_stream", || async move {
+            let response = client
+                .post(&config.base_url)
+                .header("Authorization", format!("Bearer {}", config.api_key))
+                .header("Content-Type", "application/json")
+                .json(body)
+                .send()
+                .await
+                .map_err(|e| format!("Network error: {}", e))?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let headers = response.headers().clone();
+                let error_text = response.text().await.unwrap_or_default();
+                eprintln!("[AgentStream] API Error: {}: {}", status, error_text);
+                return Err(crate::ai_retry::api_error(status, &headers, &error_text));
+            }
+            Ok(response)
+        }).await;
+
+        match result {
+            Ok(response) => break response,
+            Err(err) if tools.is_some() && !prompt_tools && tool_capability_rejected(&err) => {
+                tool_capability::mark_unsupported(config);
+                crate::agent_log::emit(app, &format!("agent_{}", agent_id), &StreamEvent::ToolFallback {
+                    model: config.models[0].clone(),
+                    reason: err.chars().take(300).collect(),
+                });
+                prompt_tools = true;
+            }
+            Err(err) => {
+                crate::failed_requests::record_failure("agent_stream", config, &request_body, &err);
+                return Err(err);
+            }
+        }
+    };
 
     // 4. Process SSE stream
     eprintln!("[AgentStream] Creating event stream...");
@@ -1007,6 +1483,8 @@ pub async fn agent_stream_chat_with_root(
 
     // 🔥 FIX v0.3.6: Track emitted tool_calls to prevent duplicates (Zhipu API may send same tool_call twice)
     let mut emitted_tool_call_ids: std::collections::HashSet<String> = std::collections::HashSet::new();
+    // v0.3.4: 接口返回的 Token 用量，供 Agent 预算统计
+    let mut reported_usage = None;
 
     // Stream statistics tracking
     let start_time = Instant::now();
@@ -1024,6 +1502,10 @@ pub async fn agent_stream_chat_with_root(
             Ok(event) => {
                 // ... (解析逻辑)
                 if let Ok(stream_response) = serde_json::from_str::<OpenAIStreamResponse>(&event.data) {
+                    if let Some(usage) = stream_response.usage.as_ref() {
+                        reported_usage = crate::agent_budget::TokenUsage::from_openai(usage).or(reported_usage);
+                        meter.openai_usage(usage);
+                    }
                     if let Some(choice) = stream_response.choices.first() {
                         // 处理推理内容 (reasoning_content)
                         if let Some(reasoning) = &choice.delta.reasoning_content {
@@ -1047,10 +1529,14 @@ pub async fn agent_stream_chat_with_root(
                                         }
 
                                         let tool_id = format!("glm_{}", uuid::Uuid::new_v4());
+                                        let arguments = serde_json::to_string(&args_map).unwrap_or_default();
+                                        let mut parser = PartialJsonParser::new();
+                                        parser.push(&arguments);
                                         accumulated_tool_calls.insert(999, StreamingToolCall {
                                             id: tool_id,
                                             name: tool_name.to_string(),
-                                            arguments: serde_json::to_string(&args_map).unwrap_or_default(),
+                                            arguments,
+                                            parser,
                                         });
                                     }
                                 }
@@ -1132,6 +1618,7 @@ pub async fn agent_stream_chat_with_root(
                                         id: String::new(),
                                         name: String::new(),
                                         arguments: String::new(),
+                                        parser: PartialJsonParser::new(),
                                     });
                                 }
 
@@ -1145,6 +1632,7 @@ pub async fn agent_stream_chat_with_root(
                                     }
                                     if let Some(args) = &func.arguments {
                                         st.arguments.push_str(args);
+                                        st.parser.push(args);
                                     }
                                 }
 
@@ -1163,15 +1651,10 @@ pub async fn agent_stream_chat_with_root(
 
                                     // Try full parse first
                                     let args_val: Value = serde_json::from_str(&st.arguments).unwrap_or_else(|_| {
-                                        // If not valid JSON, try to extract fields manually for better progressive UI
-                                        let mut map = serde_json::Map::new();
-                                        if let Some(path) = extract_partial_value(&st.arguments, "rel_path") {
-                                            map.insert("rel_path".to_string(), json!(path));
-                                        }
-                                        if let Some(content) = extract_partial_value(&st.arguments, "content") {
-                                            map.insert("content".to_string(), json!(content));
-                                        }
-                                        Value::Object(map)
+                                        // If not valid JSON yet, use the incremental parser's partial value for progressive UI
+                                        st.parser.snapshot()
+                                            .filter(|v| v.is_object())
+                                            .unwrap_or_else(|| Value::Object(serde_json::Map::new()))
                                     });
 
                                     // 🔥 FIX v0.3.5: 检测 tool_call 是否完整
@@ -1307,7 +1790,26 @@ pub async fn agent_stream_chat_with_root(
     eprintln!("[AgentStream] Stream completed. Events: {}, Time: {:.1}s, Content: {} chars, Tools: {}",
         event_count, total_time, accumulated_content.len(), accumulated_tool_calls.len());
 
+    if let Some(usage) = reported_usage {
+        crate::agent_budget::record_usage(agent_id, usage);
+    }
+    meter.finish(config, || Some(crate::usage_meter::estimate(config, &clean_messages, &accumulated_content)));
+
     // 5. Build final Message
+    if prompt_tools {
+        let (content, calls) = tool_capability::parse_tool_blocks(&accumulated_content);
+        for call in &calls {
+            let args: Value = serde_json::from_str(&call.function.arguments).unwrap_or_else(|_| json!({}));
+            emit_agent_tool_call(app, &format!("agent_{}", agent_id), &call.id, &call.function.name, args, false);
+        }
+        return Ok(Message {
+            role: "assistant".to_string(),
+            content: Content::Text(content),
+            tool_calls: if calls.is_empty() { None } else { Some(calls) },
+            tool_call_id: None,
+        });
+    }
+
     let tool_calls = if accumulated_tool_calls.is_empty() {
         None
     } else {
@@ -1332,4 +1834,143 @@ pub async fn agent_stream_chat_with_root(
         tool_calls,
         tool_call_id: None,
     })
-}
\ No newline at end of file
+}
+
+/// 在 Agent 通道上发送工具调用事件
+fn emit_agent_tool_call(app: &AppHandle, event_name: &str, id: &str, tool: &str, args: Value, is_partial: bool) {
+    crate::agent_log::emit(app, event_name, &StreamEvent::ToolCall {
+        tool_call: ToolCallPayload {
+            id: id.to_string(),
+            tool: tool.to_string(),
+            args,
+            is_partial,
+            risk: None,
+            diff: None,
+            command_risk: None,
+        },
+    });
+}
+
+/// Anthropic 协议的 Agent 流式请求，发送与 OpenAI 路径相同的 Agent 事件
+async fn agent_stream_anthropic(
+    app: &AppHandle,
+    client: &Client,
+    config: &AIProviderConfig,
+    messages: &[Message],
+    agent_id: &str,
+    tools: Option<Vec<Value>>,
+) -> Result<Message, String> {
+    let event_name = format!("agent_{}", agent_id);
+    eprintln!("[AgentStream] Sending Anthropic streaming request for agent {}", agent_id);
+
+    let response = send_anthropic_stream(client, config, messages, tools.as_deref(), "agent_stream").await?;
+    let mut stream = response.bytes_stream().eventsource();
+    let mut translator = AnthropicStream::new();
+    let mut usage = crate::agent_budget::TokenUsage::default();
+    let mut usage_reported = false;
+    let mut meter = crate::usage_meter::RequestMeter::start("agent");
+
+    let emit_tool = |translator: &AnthropicStream, index: i64| {
+        if let Some(progress) = translator.tool_progress(index) {
+            emit_agent_tool_call(app, &event_name, progress.id, progress.name, progress.args, !progress.complete);
+        }
+    };
+
+    while let Some(event) = stream.next().await {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                let err = format!("Stream error: {}", e);
+                crate::agent_log::emit(app, &event_name, &StreamEvent::Error { error: err.clone() });
+                return Err(err);
+            }
+        };
+        if let Ok(data) = serde_json::from_str::<Value>(&event.data) {
+            usage_reported |= usage.merge_anthropic_event(&data);
+            meter.anthropic_event(&data);
+        }
+        let chunks = translator.handle_event(&event.data).map_err(|e| {
+            crate::agent_log::emit(app, &event_name, &StreamEvent::Error { error: e.clone() });
+            e
+        })?;
+
+        for chunk in chunks {
+            let delta = &chunk["choices"][0]["delta"];
+            if let Some(content) = delta["content"].as_str() {
+                crate::agent_log::emit(app, &event_name, &StreamEvent::Thinking { content: content.to_string() });
+            }
+            for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                if let Some(index) = call["index"].as_i64() {
+                    emit_tool(&translator, index);
+                }
+            }
+        }
+    }
+
+    if usage_reported {
+        crate::agent_budget::record_usage(agent_id, usage);
+    }
+    meter.finish(config, || None);
+    let message = translator.into_message();
+    eprintln!("[AgentStream] Anthropic stream completed. Tools: {}", message.tool_calls.as_ref().map_or(0, |t| t.len()));
+    Ok(message)
+}
+
+async fn agent_stream_gemini(
+    app: &AppHandle,
+    client: &Client,
+    config: &AIProviderConfig,
+    messages: &[Message],
+    agent_id: &str,
+    tools: Option<Vec<Value>>,
+) -> Result<Message, String> {
+    let event_name = format!("agent_{}", agent_id);
+    eprintln!("[AgentStream] Sending Gemini streaming request for agent {}", agent_id);
+
+    let response = send_gemini_stream(client, config, messages, tools.as_deref(), "agent_stream").await?;
+    let mut stream = response.bytes_stream().eventsource();
+    let mut translator = GeminiStream::new();
+    let mut reported_usage = None;
+    let mut meter = crate::usage_meter::RequestMeter::start("agent");
+
+    while let Some(event) = stream.next().await {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                let err = format!("Stream error: {}", e);
+                crate::agent_log::emit(app, &event_name, &StreamEvent::Error { error: err.clone() });
+                return Err(err);
+            }
+        };
+        // usageMetadata 为累计值，保留最后一次
+        if let Ok(data) = serde_json::from_str::<Value>(&event.data) {
+            reported_usage = crate::agent_budget::TokenUsage::from_gemini(&data).or(reported_usage);
+            meter.gemini_chunk(&data);
+        }
+        let chunks = translator.handle_event(&event.data).map_err(|e| {
+            crate::agent_log::emit(app, &event_name, &StreamEvent::Error { error: e.clone() });
+            e
+        })?;
+
+        for chunk in chunks {
+            let delta = &chunk["choices"][0]["delta"];
+            if let Some(content) = delta["content"].as_str() {
+                crate::agent_log::emit(app, &event_name, &StreamEvent::Thinking { content: content.to_string() });
+            }
+            // Gemini 的函数调用一次性给出完整参数
+            for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                let Some(tool_call) = call["index"].as_u64().and_then(|i| translator.tool_calls().get(i as usize)) else { continue };
+                let args = serde_json::from_str::<Value>(&tool_call.function.arguments).unwrap_or_else(|_| json!({}));
+                emit_agent_tool_call(app, &event_name, &tool_call.id, &tool_call.function.name, args, false);
+            }
+        }
+    }
+
+    if let Some(usage) = reported_usage {
+        crate::agent_budget::record_usage(agent_id, usage);
+    }
+    meter.finish(config, || None);
+    let message = translator.into_message();
+    eprintln!("[AgentStream] Gemini stream completed. Tools: {}", message.tool_calls.as_ref().map_or(0, |t| t.len()));
+    Ok(message)
+}
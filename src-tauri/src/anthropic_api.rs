@@ -0,0 +1,524 @@
+/*!
+Anthropic API - Anthropic Messages 协议
+=======================================
+
+`AIProtocol::Anthropic` 的请求构建与响应解析，供 `ai_utils` 使用：
+
+- system 消息转为顶层 `system` 字段中的文本块，并由 `prompt_cache` 加上缓存断点
+- 文本 / 图片转换为内容块（data URL 图片转为 base64 source）
+- assistant 的 `tool_calls` 转为 `tool_use` 块，`tool` 消息转为 user 消息中的 `tool_result` 块
+- OpenAI 格式的工具定义转为 `{ name, description, input_schema }`
+- SSE 事件（`content_block_start` / `content_block_delta` / `message_delta` / `error`）
+  转换为 OpenAI 兼容的流式数据块，前端与现有拦截逻辑无需区分协议
+*/
+
+use crate::core_traits::ai::{AIProtocol, AIProviderConfig, Content, ContentPart, FunctionCall, Message, ToolCall};
+use crate::partial_json::PartialJsonParser;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+pub const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// Anthropic 要求显式给出 max_tokens
+const DEFAULT_MAX_TOKENS: u64 = 8192;
+/// Anthropic 的 temperature 上限
+const MAX_TEMPERATURE: f64 = 1.0;
+
+pub fn is_anthropic(config: &AIProviderConfig) -> bool {
+    matches!(config.protocol, AIProtocol::Anthropic)
+}
+
+/// 请求地址：允许配置为域名、`/v1` 或完整的 `/v1/messages`
+pub fn endpoint(base_url: &str) -> String {
+    let base = base_url.trim_end_matches('/');
+    if base.ends_with("/messages") {
+        base.to_string()
+    } else if base.ends_with("/v1") {
+        format!("{}/messages", base)
+    } else {
+        format!("{}/v1/messages", base)
+    }
+}
+
+/// 带认证头的 POST 请求
+pub fn post(client: &reqwest::Client, config: &AIProviderConfig) -> reqwest::RequestBuilder {
+    client
+        .post(endpoint(&config.base_url))
+        .header("x-api-key", &config.api_key)
+        .header("anthropic-version", ANTHROPIC_VERSION)
+        .header("Content-Type", "application/json")
+}
+
+// ============================================================================
+// Request
+// ============================================================================
+
+fn text_block(text: &str) -> Value {
+    json!({ "type": "text", "text": text })
+}
+
+/// 图片：data URL 转为 base64 source，其他按 URL 引用
+fn image_block(url: &str) -> Value {
+    if let Some(rest) = url.strip_prefix("data:") {
+        if let Some((media_type, data)) = rest.split_once(";base64,") {
+            return json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": media_type, "data": data }
+            });
+        }
+    }
+    json!({ "type": "image", "source": { "type": "url", "url": url } })
+}
+
+fn content_blocks(content: &Content) -> Vec<Value> {
+    match content {
+        Content::Text(text) if text.trim().is_empty() => Vec::new(),
+        Content::Text(text) => vec![text_block(text)],
+        Content::Parts(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text, .. } if text.trim().is_empty() => None,
+                ContentPart::Text { text, .. } => Some(text_block(text)),
+                ContentPart::ImageUrl { image_url } => Some(image_block(&image_url.url)),
+            })
+            .collect(),
+    }
+}
+
+fn content_text(content: &Content) -> String {
+    match content {
+        Content::Text(text) => text.clone(),
+        Content::Parts(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// 转换消息列表，返回 (system, messages)
+///
+/// Anthropic 要求 user / assistant 交替出现，相邻的同角色消息会被合并
+pub fn convert_messages(messages: &[Message]) -> (Option<String>, Vec<Value>) {
+    let mut system_parts = Vec::new();
+    let mut converted: Vec<(String, Vec<Value>)> = Vec::new();
+
+    for msg in messages {
+        let (role, blocks) = match msg.role.as_str() {
+            "system" => {
+                let text = content_text(&msg.content);
+                if !text.trim().is_empty() {
+                    system_parts.push(text);
+                }
+                continue;
+            }
+            "assistant" => {
+                let mut blocks = content_blocks(&msg.content);
+                for call in msg.tool_calls.iter().flatten() {
+                    let input = serde_json::from_str::<Value>(&call.function.arguments)
+                        .ok()
+                        .filter(|v| v.is_object())
+                        .unwrap_or_else(|| json!({}));
+                    blocks.push(json!({ "type": "tool_use", "id": call.id, "name": call.function.name, "input": input }));
+                }
+                ("assistant", blocks)
+            }
+            "tool" => {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": msg.tool_call_id.clone().unwrap_or_default(),
+                    "content": content_text(&msg.content),
+                });
+                ("user", vec![block])
+            }
+            _ => ("user", content_blocks(&msg.content)),
+        };
+        if blocks.is_empty() {
+            continue;
+        }
+        match converted.last_mut() {
+            Some((last_role, last_blocks)) if last_role == role => last_blocks.extend(blocks),
+            _ => converted.push((role.to_string(), blocks)),
+        }
+    }
+
+    let system = (!system_parts.is_empty()).then(|| system_parts.join("\n\n"));
+    let messages = converted
+        .into_iter()
+        .map(|(role, content)| json!({ "role": role, "content": content }))
+        .collect();
+    (system, messages)
+}
+
+/// OpenAI 格式工具定义 -> Anthropic 工具定义
+pub fn convert_tools(tools: &[Value]) -> Vec<Value> {
+    tools
+        .iter()
+        .filter_map(|tool| {
+            let function = tool.get("function").unwrap_or(tool);
+            let name = function["name"].as_str()?;
+            Some(json!({
+                "name": name,
+                "description": function["description"].as_str().unwrap_or(""),
+                "input_schema": function.get("parameters").cloned().unwrap_or_else(|| json!({ "type": "object", "properties": {} })),
+            }))
+        })
+        .collect()
+}
+
+/// 每条 system 消息一个文本块，便于对主提示词与项目上下文分别设置缓存断点
+pub fn system_blocks(messages: &[Message]) -> Vec<Value> {
+    messages
+        .iter()
+        .filter(|m| m.role == "system")
+        .map(|m| content_text(&m.content))
+        .filter(|text| !text.trim().is_empty())
+        .map(|text| text_block(&text))
+        .collect()
+}
+
+/// 构建 Messages API 请求体（带提示词缓存断点）
+pub fn build_request(config: &AIProviderConfig, messages: &[Message], tools: Option<&[Value]>, stream: bool) -> Value {
+    let (_, converted) = convert_messages(messages);
+    let mut body = json!({
+        "model": config.models.first().cloned().unwrap_or_default(),
+        "max_tokens": DEFAULT_MAX_TOKENS,
+        "messages": converted,
+        "stream": stream,
+    });
+    let system = system_blocks(messages);
+    if !system.is_empty() {
+        body["system"] = json!(system);
+    }
+    if let Some(tools) = tools.filter(|t| !t.is_empty()) {
+        body["tools"] = json!(convert_tools(tools));
+    }
+    crate::prompt_cache::annotate_anthropic(&mut body);
+    body
+}
+
+/// 调度后的 temperature 超出 Anthropic 范围时截断
+pub fn clamp_temperature(body: &mut Value) {
+    if let Some(t) = body["temperature"].as_f64() {
+        if t > MAX_TEMPERATURE {
+            body["temperature"] = json!(MAX_TEMPERATURE);
+        }
+    }
+}
+
+// ============================================================================
+// Response
+// ============================================================================
+
+/// 解析非流式响应
+pub fn parse_response(res_json: &Value) -> Result<Message, String> {
+    if res_json["type"] == "error" {
+        return Err(format!("AI API Error: {}", res_json["error"]["message"].as_str().unwrap_or("unknown error")));
+    }
+    let blocks = res_json["content"]
+        .as_array()
+        .ok_or("Malformed AI response: content field missing")?;
+
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for block in blocks {
+        match block["type"].as_str() {
+            Some("text") => text.push_str(block["text"].as_str().unwrap_or("")),
+            Some("tool_use") => tool_calls.push(ToolCall {
+                id: block["id"].as_str().unwrap_or("").to_string(),
+                r#type: "function".to_string(),
+                function: FunctionCall {
+                    name: block["name"].as_str().unwrap_or("").to_string(),
+                    arguments: block["input"].to_string(),
+                },
+            }),
+            _ => {}
+        }
+    }
+
+    Ok(Message {
+        role: "assistant".to_string(),
+        content: Content::Text(text),
+        tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+        tool_call_id: None,
+    })
+}
+
+fn finish_reason(stop_reason: &str) -> &'static str {
+    match stop_reason {
+        "tool_use" => "tool_calls",
+        "max_tokens" => "length",
+        _ => "stop",
+    }
+}
+
+enum StreamBlock {
+    Text,
+    Thinking,
+    ToolUse { id: String, name: String, input: String, parser: Box<PartialJsonParser> },
+}
+
+/// 流式工具调用的当前状态
+pub struct ToolUseProgress<'a> {
+    pub id: &'a str,
+    pub name: &'a str,
+    /// 当前可解析的参数（不完整时为增量解析的快照）
+    pub args: Value,
+    pub complete: bool,
+}
+
+/// SSE 事件转换器：累积内容并输出 OpenAI 兼容的数据块
+#[derive(Default)]
+pub struct AnthropicStream {
+    blocks: BTreeMap<i64, StreamBlock>,
+    /// 已完成的工具块
+    finished: Vec<i64>,
+    content: String,
+}
+
+impl AnthropicStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理一个 SSE 事件的 data，返回转换后的数据块；`error` 事件返回 Err
+    pub fn handle_event(&mut self, data: &str) -> Result<Vec<Value>, String> {
+        let Ok(event) = serde_json::from_str::<Value>(data) else {
+            return Ok(Vec::new());
+        };
+        let index = event["index"].as_i64().unwrap_or(0);
+        let delta_chunk = |delta: Value| json!({ "choices": [{ "index": 0, "delta": delta }] });
+
+        match event["type"].as_str().unwrap_or("") {
+            "content_block_start" => {
+                let block = &event["content_block"];
+                match block["type"].as_str() {
+                    Some("tool_use") => {
+                        let id = block["id"].as_str().unwrap_or("").to_string();
+                        let name = block["name"].as_str().unwrap_or("").to_string();
+                        let chunk = delta_chunk(json!({ "tool_calls": [{
+                            "index": index, "id": id, "type": "function",
+                            "function": { "name": name, "arguments": "" }
+                        }] }));
+                        self.blocks.insert(index, StreamBlock::ToolUse { id, name, input: String::new(), parser: Box::new(PartialJsonParser::new()) });
+                        Ok(vec![chunk])
+                    }
+                    Some("thinking") | Some("redacted_thinking") => {
+                        self.blocks.insert(index, StreamBlock::Thinking);
+                        Ok(Vec::new())
+                    }
+                    _ => {
+                        self.blocks.insert(index, StreamBlock::Text);
+                        // 部分实现会在 start 中携带初始文本
+                        match block["text"].as_str().filter(|t| !t.is_empty()) {
+                            Some(text) => {
+                                self.content.push_str(text);
+                                Ok(vec![delta_chunk(json!({ "content": text }))])
+                            }
+                            None => Ok(Vec::new()),
+                        }
+                    }
+                }
+            }
+            "content_block_delta" => {
+                let delta = &event["delta"];
+                match delta["type"].as_str().unwrap_or("") {
+                    "text_delta" => {
+                        let text = delta["text"].as_str().unwrap_or("");
+                        self.content.push_str(text);
+                        Ok(vec![delta_chunk(json!({ "content": text }))])
+                    }
+                    "thinking_delta" => {
+                        let thinking = delta["thinking"].as_str().unwrap_or("");
+                        Ok(vec![delta_chunk(json!({ "reasoning_content": thinking }))])
+                    }
+                    "input_json_delta" => {
+                        let partial = delta["partial_json"].as_str().unwrap_or("");
+                        if let Some(StreamBlock::ToolUse { input, parser, .. }) = self.blocks.get_mut(&index) {
+                            input.push_str(partial);
+                            parser.push(partial);
+                        }
+                        Ok(vec![delta_chunk(json!({ "tool_calls": [{ "index": index, "function": { "arguments": partial } }] }))])
+                    }
+                    _ => Ok(Vec::new()),
+                }
+            }
+            "content_block_stop" => {
+                if let Some(StreamBlock::ToolUse { .. }) = self.blocks.get(&index) {
+                    self.finished.push(index);
+                    // 空增量，通知调用方该工具调用已完整
+                    return Ok(vec![delta_chunk(json!({ "tool_calls": [{ "index": index, "function": { "arguments": "" } }] }))]);
+                }
+                Ok(Vec::new())
+            }
+            "message_delta" => match event["delta"]["stop_reason"].as_str() {
+                Some(reason) => Ok(vec![json!({ "choices": [{ "index": 0, "delta": {}, "finish_reason": finish_reason(reason) }] })]),
+                None => Ok(Vec::new()),
+            },
+            "error" => Err(format!(
+                "AI API Error ({}): {}",
+                event["error"]["type"].as_str().unwrap_or("error"),
+                event["error"]["message"].as_str().unwrap_or("unknown error")
+            )),
+            // message_start / message_stop / ping
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// 指定块的工具调用进度
+    pub fn tool_progress(&self, index: i64) -> Option<ToolUseProgress<'_>> {
+        match self.blocks.get(&index)? {
+            StreamBlock::ToolUse { id, name, input, parser } => {
+                let complete = self.finished.contains(&index);
+                let args = if input.trim().is_empty() {
+                    json!({})
+                } else {
+                    serde_json::from_str::<Value>(input)
+                        .ok()
+                        .or_else(|| parser.snapshot())
+                        .filter(|v| v.is_object())
+                        .unwrap_or_else(|| json!({}))
+                };
+                Some(ToolUseProgress { id, name, args, complete })
+            }
+            _ => None,
+        }
+    }
+
+    /// 构建最终消息
+    pub fn into_message(self) -> Message {
+        let tool_calls: Vec<ToolCall> = self
+            .blocks
+            .into_values()
+            .filter_map(|block| match block {
+                StreamBlock::ToolUse { id, name, input, .. } => Some(ToolCall {
+                    id,
+                    r#type: "function".to_string(),
+                    function: FunctionCall {
+                        name,
+                        arguments: if input.trim().is_empty() { "{}".to_string() } else { input },
+                    },
+                }),
+                _ => None,
+            })
+            .collect();
+
+        Message {
+            role: "assistant".to_string(),
+            content: Content::Text(self.content),
+            tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            tool_call_id: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, text: &str) -> Message {
+        Message { role: role.to_string(), content: Content::Text(text.to_string()), tool_calls: None, tool_call_id: None }
+    }
+
+    #[test]
+    fn test_endpoint() {
+        assert_eq!(endpoint("https://api.anthropic.com"), "https://api.anthropic.com/v1/messages");
+        assert_eq!(endpoint("https://api.anthropic.com/v1/"), "https://api.anthropic.com/v1/messages");
+        assert_eq!(endpoint("https://proxy.local/v1/messages"), "https://proxy.local/v1/messages");
+    }
+
+    #[test]
+    fn test_convert_messages_with_tools() {
+        let mut assistant = msg("assistant", "Let me check.");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "toolu_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall { name: "bash".to_string(), arguments: r#"{"command":"ls"}"#.to_string() },
+        }]);
+        let mut result = msg("tool", "Cargo.toml\nsrc");
+        result.tool_call_id = Some("toolu_1".to_string());
+        let messages = vec![msg("system", "You are helpful."), msg("user", "List files"), assistant, result, msg("user", "Thanks")];
+
+        let (system, converted) = convert_messages(&messages);
+        assert_eq!(system.as_deref(), Some("You are helpful."));
+        assert_eq!(converted.len(), 3);
+        assert_eq!(converted[1]["content"][1]["type"], "tool_use");
+        assert_eq!(converted[1]["content"][1]["input"]["command"], "ls");
+        // tool_result 与后续 user 消息合并
+        assert_eq!(converted[2]["role"], "user");
+        assert_eq!(converted[2]["content"][0]["type"], "tool_result");
+        assert_eq!(converted[2]["content"][0]["tool_use_id"], "toolu_1");
+        assert_eq!(converted[2]["content"][1]["text"], "Thanks");
+    }
+
+    #[test]
+    fn test_convert_tools_and_images() {
+        let tools = vec![json!({ "type": "function", "function": { "name": "bash", "description": "Run", "parameters": { "type": "object" } } })];
+        let converted = convert_tools(&tools);
+        assert_eq!(converted[0], json!({ "name": "bash", "description": "Run", "input_schema": { "type": "object" } }));
+
+        let image = image_block("data:image/png;base64,AAAA");
+        assert_eq!(image["source"]["media_type"], "image/png");
+        assert_eq!(image["source"]["data"], "AAAA");
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let res = json!({ "content": [
+            { "type": "text", "text": "Running" },
+            { "type": "tool_use", "id": "toolu_2", "name": "bash", "input": { "command": "pwd" } }
+        ] });
+        let message = parse_response(&res).unwrap();
+        assert!(matches!(message.content, Content::Text(ref t) if t == "Running"));
+        let calls = message.tool_calls.unwrap();
+        assert_eq!(calls[0].function.name, "bash");
+        assert_eq!(calls[0].function.arguments, r#"{"command":"pwd"}"#);
+    }
+
+    #[test]
+    fn test_stream_translation() {
+        let mut stream = AnthropicStream::new();
+        let events = [
+            r#"{"type":"message_start","message":{"id":"msg_1"}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":""}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Plan"}}"#,
+            r#"{"type":"content_block_start","index":1,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"content_block_delta","index":1,"delta":{"type":"text_delta","text":"Hi"}}"#,
+            r#"{"type":"content_block_start","index":2,"content_block":{"type":"tool_use","id":"toolu_3","name":"bash","input":{}}}"#,
+            r#"{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":"{\"comm"}}"#,
+        ];
+        let mut chunks = Vec::new();
+        for event in events {
+            chunks.extend(stream.handle_event(event).unwrap());
+        }
+        assert_eq!(chunks[0]["choices"][0]["delta"]["reasoning_content"], "Plan");
+        assert_eq!(chunks[1]["choices"][0]["delta"]["content"], "Hi");
+        assert_eq!(chunks[2]["choices"][0]["delta"]["tool_calls"][0]["id"], "toolu_3");
+
+        let progress = stream.tool_progress(2).unwrap();
+        assert!(!progress.complete);
+
+        stream.handle_event(r#"{"type":"content_block_delta","index":2,"delta":{"type":"input_json_delta","partial_json":"and\":\"ls\"}"}}"#).unwrap();
+        let stop = stream.handle_event(r#"{"type":"content_block_stop","index":2}"#).unwrap();
+        assert_eq!(stop[0]["choices"][0]["delta"]["tool_calls"][0]["index"], 2);
+        let finish = stream.handle_event(r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"}}"#).unwrap();
+        assert_eq!(finish[0]["choices"][0]["finish_reason"], "tool_calls");
+
+        let progress = stream.tool_progress(2).unwrap();
+        assert!(progress.complete);
+        assert_eq!(progress.args["command"], "ls");
+
+        let message = stream.into_message();
+        assert!(matches!(message.content, Content::Text(ref t) if t == "Hi"));
+        assert_eq!(message.tool_calls.unwrap()[0].function.arguments, r#"{"command":"ls"}"#);
+    }
+
+    #[test]
+    fn test_stream_error_event() {
+        let mut stream = AnthropicStream::new();
+        let err = stream.handle_event(r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#).unwrap_err();
+        assert!(err.contains("overloaded_error"));
+    }
+}
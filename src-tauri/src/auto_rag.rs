@@ -0,0 +1,267 @@
+/*!
+Auto RAG - 自适应检索触发
+=========================
+
+未使用 `@codebase` 时，判断问题是否需要项目上下文并自动检索：
+
+- 启发式打分：提到的文件名、代码标识符（符号索引中存在的加权更高）、
+  “in this project / 这个项目”等短语
+- 分数达到 `min_score` 才触发，检索结果使用更小的上下文预算
+- 自动注入的引用带 `"auto": true` 标记，系统提示词中也单独标注
+
+配置保存在 `~/.ifai/auto_rag.json`。
+*/
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::commands::symbol_commands::SymbolIndexState;
+
+/// 自动检索配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AutoRagConfig {
+    pub enabled: bool,
+    /// 触发检索的最低分数
+    pub min_score: u32,
+    /// 自动注入上下文的最大字符数（`@codebase` 为 12000）
+    pub context_budget: usize,
+    /// 自动注入时最多展示的引用数
+    pub max_references: usize,
+}
+
+impl Default for AutoRagConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_score: 3,
+            context_budget: 4000,
+            max_references: 5,
+        }
+    }
+}
+
+/// 触发判断结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AutoRagDecision {
+    pub score: u32,
+    pub triggered: bool,
+    /// 命中的信号，如 `file:lib.rs`、`symbol:run_agent`
+    pub signals: Vec<String>,
+}
+
+const PROJECT_PHRASES: &[&str] = &[
+    "in this project", "in this repo", "this codebase", "our codebase", "in the codebase",
+    "in our project", "this repository",
+    "这个项目", "本项目", "项目中", "项目里", "代码库", "仓库里",
+];
+
+const CODE_KEYWORDS: &[&str] = &[
+    "function", "method", "class", "struct", "module", "implemented", "defined", "where is", "called from",
+    "函数", "方法", "类", "结构体", "模块", "实现", "定义", "在哪", "调用",
+];
+
+struct Patterns {
+    file: Regex,
+    backtick: Regex,
+    identifier: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        file: Regex::new(
+            r"(?i)\b[\w\-/]+\.(rs|ts|tsx|js|jsx|py|go|java|kt|swift|c|cc|cpp|h|hpp|cs|rb|php|vue|toml|json|ya?ml)\b",
+        ).unwrap(),
+        backtick: Regex::new(r"`([A-Za-z_][\w:.]*)`").unwrap(),
+        // snake_case / camelCase / PascalCase（至少两段）/ a::b
+        identifier: Regex::new(
+            r"\b([a-z][a-z0-9]*(?:_[a-z0-9]+)+|[a-z][a-z0-9]*(?:[A-Z][a-z0-9]*)+|[A-Z][a-z0-9]+(?:[A-Z][a-z0-9]*)+|\w+::\w+(?:::\w+)*)\b",
+        ).unwrap(),
+    })
+}
+
+/// 对问题打分；`symbol_exists` 用于查询符号索引
+pub fn evaluate(text: &str, min_score: u32, symbol_exists: impl Fn(&str) -> bool) -> AutoRagDecision {
+    let p = patterns();
+    let lower = text.to_lowercase();
+    let mut score = 0;
+    let mut signals = Vec::new();
+
+    let files: BTreeSet<&str> = p.file.find_iter(text).map(|m| m.as_str()).collect();
+    for file in &files {
+        score += 3;
+        signals.push(format!("file:{}", file));
+    }
+
+    if let Some(phrase) = PROJECT_PHRASES.iter().find(|phrase| lower.contains(*phrase)) {
+        score += 3;
+        signals.push(format!("phrase:{}", phrase));
+    }
+
+    let mut identifiers: BTreeSet<&str> = p.backtick.captures_iter(text).filter_map(|c| c.get(1)).map(|m| m.as_str()).collect();
+    identifiers.extend(p.identifier.find_iter(text).map(|m| m.as_str()));
+    for ident in identifiers {
+        if files.iter().any(|f| f.contains(ident)) {
+            continue;
+        }
+        let name = ident.rsplit("::").next().unwrap_or(ident);
+        if symbol_exists(name) {
+            score += 4;
+            signals.push(format!("symbol:{}", name));
+        } else {
+            score += 2;
+            signals.push(format!("identifier:{}", ident));
+        }
+    }
+
+    if CODE_KEYWORDS.iter().any(|kw| lower.contains(kw)) {
+        score += 1;
+        signals.push("keyword".to_string());
+    }
+
+    AutoRagDecision { score, triggered: score >= min_score, signals }
+}
+
+/// 按配置判断是否自动检索
+pub fn decide(text: &str, config: &AutoRagConfig, index: Option<&SymbolIndexState>) -> Option<AutoRagDecision> {
+    if !config.enabled {
+        return None;
+    }
+    let decision = evaluate(text, config.min_score, |name| index.is_some_and(|idx| idx.contains_symbol(name)));
+    if decision.triggered {
+        println!("[AutoRAG] Triggered (score {}): {}", decision.score, decision.signals.join(", "));
+        Some(decision)
+    } else {
+        None
+    }
+}
+
+/// 截断上下文（按字符边界）
+fn truncate(context: &str, budget: usize) -> String {
+    if context.len() <= budget {
+        return context.to_string();
+    }
+    let mut end = budget;
+    while !context.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}... [Context Truncated]", &context[..end])
+}
+
+/// 渲染自动注入的上下文段落
+pub fn render_context(context: &str, budget: usize) -> String {
+    format!(
+        "\n\nProject Context (auto-retrieved, not explicitly requested by the user; ignore it if irrelevant):\n{}",
+        truncate(context, budget)
+    )
+}
+
+/// 给引用加上 `auto: true` 标记，并限制数量
+pub fn mark_references<T: Serialize>(references: &[T], max_references: usize) -> Vec<serde_json::Value> {
+    references.iter()
+        .take(max_references)
+        .filter_map(|r| serde_json::to_value(r).ok())
+        .map(|mut value| {
+            if let Some(obj) = value.as_object_mut() {
+                obj.insert("auto".to_string(), serde_json::Value::Bool(true));
+            }
+            value
+        })
+        .collect()
+}
+
+// ============================================================================
+// Config
+// ============================================================================
+
+fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("auto_rag.json")
+}
+
+pub fn load_config() -> AutoRagConfig {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(config: &AutoRagConfig) -> Result<(), String> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write auto RAG config: {}", e))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_auto_rag_config() -> AutoRagConfig {
+    load_config()
+}
+
+#[tauri::command]
+pub fn set_auto_rag_config(config: AutoRagConfig) -> Result<(), String> {
+    if config.context_budget == 0 {
+        return Err("context_budget must be greater than 0".to_string());
+    }
+    save_config(&config)
+}
+
+/// 预览某条消息是否会触发自动检索（用于设置页调试）
+#[tauri::command]
+pub fn preview_auto_rag(text: String) -> AutoRagDecision {
+    let config = load_config();
+    evaluate(&text, config.min_score, |_| false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_signals() {
+        let decision = evaluate("Why does parser.rs panic on empty input?", 3, |_| false);
+        assert!(decision.triggered);
+        assert!(decision.signals.contains(&"file:parser.rs".to_string()));
+
+        let decision = evaluate("这个项目的配置是怎么加载的", 3, |_| false);
+        assert!(decision.triggered);
+
+        let decision = evaluate("where is `load_config` defined?", 3, |_| false);
+        assert!(decision.triggered);
+
+        assert!(!evaluate("What is the capital of France?", 3, |_| false).triggered);
+        assert!(!evaluate("how do I write a for loop", 3, |_| false).triggered);
+    }
+
+    #[test]
+    fn test_known_symbol_weighs_more() {
+        let unknown = evaluate("explain run_agent", 3, |_| false);
+        assert!(!unknown.triggered);
+        let known = evaluate("explain run_agent", 3, |name| name == "run_agent");
+        assert!(known.triggered);
+        assert_eq!(known.signals, vec!["symbol:run_agent".to_string()]);
+    }
+
+    #[test]
+    fn test_render_and_mark() {
+        let rendered = render_context("你好世界", 4);
+        assert!(rendered.contains("auto-retrieved"));
+        assert!(rendered.ends_with("你... [Context Truncated]"));
+
+        let refs = vec![serde_json::json!({ "path": "a.rs" }), serde_json::json!({ "path": "b.rs" })];
+        let marked = mark_references(&refs, 1);
+        assert_eq!(marked, vec![serde_json::json!({ "path": "a.rs", "auto": true })]);
+    }
+}
@@ -0,0 +1,312 @@
+/*!
+Background Processes - 后台进程管理
+===================================
+
+`bash_streaming` 检测到开发服务器启动成功（或命令超时仍在运行）后，不再 `mem::forget` 子进程，
+而是交给 `BackgroundProcessRegistry` 托管：
+
+- 继续读取 stdout / stderr，保留最近的输出供 `get_process_logs` 查看
+- `list_background_processes` / `stop_background_process` 查看与停止
+- 主窗口关闭时结束所有仍在运行的进程
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+use tokio::io::{BufReader, Lines};
+use tokio::process::{Child, ChildStderr, ChildStdout};
+use tokio::time::{interval, Duration};
+
+/// 每个进程保留的最大输出行数
+const MAX_LOG_LINES: usize = 2000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum ProcessState {
+    Running,
+    /// 进程自行退出（被信号终止时没有退出码）
+    Exited { code: Option<i32> },
+    /// 被 `stop_background_process` 或应用退出结束
+    Stopped,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackgroundProcessInfo {
+    pub id: String,
+    pub command: String,
+    pub working_dir: Option<String>,
+    pub pid: Option<u32>,
+    pub started_at: i64,
+    #[serde(flatten)]
+    pub state: ProcessState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessLogLine {
+    pub content: String,
+    pub is_stderr: bool,
+}
+
+struct ManagedProcess {
+    info: BackgroundProcessInfo,
+    child: Option<Child>,
+    logs: VecDeque<ProcessLogLine>,
+}
+
+impl ManagedProcess {
+    fn push_log(&mut self, content: String, is_stderr: bool) {
+        if self.logs.len() >= MAX_LOG_LINES {
+            self.logs.pop_front();
+        }
+        self.logs.push_back(ProcessLogLine { content, is_stderr });
+    }
+
+    /// 进程已退出时记录退出码并释放句柄
+    fn poll_exit(&mut self) -> bool {
+        let Some(child) = self.child.as_mut() else { return true };
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                if self.info.state == ProcessState::Running {
+                    self.info.state = ProcessState::Exited { code: status.code() };
+                }
+                self.child = None;
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                eprintln!("[BackgroundProcess] Failed to poll {}: {}", self.info.id, e);
+                false
+            }
+        }
+    }
+
+    fn kill(&mut self) {
+        if let Some(child) = self.child.as_mut() {
+            if let Err(e) = child.start_kill() {
+                eprintln!("[BackgroundProcess] Failed to kill {}: {}", self.info.id, e);
+            }
+        }
+        if self.info.state == ProcessState::Running {
+            self.info.state = ProcessState::Stopped;
+        }
+    }
+}
+
+type Processes = Arc<Mutex<HashMap<String, ManagedProcess>>>;
+
+pub struct BackgroundProcessRegistry {
+    processes: Processes,
+    next_id: AtomicU64,
+}
+
+impl Default for BackgroundProcessRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BackgroundProcessRegistry {
+    pub fn new() -> Self {
+        Self {
+            processes: Arc::new(Mutex::new(HashMap::new())),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// 托管仍在运行的子进程，`output` 为已读取的输出；返回进程 id
+    pub fn adopt(
+        &self,
+        command: &str,
+        working_dir: Option<String>,
+        child: Child,
+        stdout: Lines<BufReader<ChildStdout>>,
+        stderr: Lines<BufReader<ChildStderr>>,
+        output: Vec<ProcessLogLine>,
+    ) -> String {
+        let id = format!("bg-{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        let mut process = ManagedProcess {
+            info: BackgroundProcessInfo {
+                id: id.clone(),
+                command: command.to_string(),
+                working_dir,
+                pid: child.id(),
+                started_at: chrono::Utc::now().timestamp(),
+                state: ProcessState::Running,
+            },
+            child: Some(child),
+            logs: VecDeque::new(),
+        };
+        for line in output {
+            process.push_log(line.content, line.is_stderr);
+        }
+        if let Ok(mut map) = self.processes.lock() {
+            map.insert(id.clone(), process);
+        }
+        println!("[BackgroundProcess] Tracking {} as {}", command, id);
+        tokio::spawn(pump_output(self.processes.clone(), id.clone(), stdout, stderr));
+        id
+    }
+
+    pub fn list(&self) -> Vec<BackgroundProcessInfo> {
+        let Ok(mut map) = self.processes.lock() else { return Vec::new() };
+        let mut list: Vec<BackgroundProcessInfo> = map.values_mut()
+            .map(|process| {
+                process.poll_exit();
+                process.info.clone()
+            })
+            .collect();
+        list.sort_by_key(|info| info.started_at);
+        list
+    }
+
+    pub fn stop(&self, id: &str) -> Result<BackgroundProcessInfo, String> {
+        let mut map = self.processes.lock().map_err(|e| e.to_string())?;
+        let process = map.get_mut(id).ok_or_else(|| format!("Background process not found: {}", id))?;
+        if !process.poll_exit() {
+            process.kill();
+            println!("[BackgroundProcess] Stopped {} ({})", id, process.info.command);
+        }
+        Ok(process.info.clone())
+    }
+
+    /// 最近的输出；`tail` 为空时返回全部保留的行
+    pub fn logs(&self, id: &str, tail: Option<usize>) -> Result<Vec<ProcessLogLine>, String> {
+        let map = self.processes.lock().map_err(|e| e.to_string())?;
+        let process = map.get(id).ok_or_else(|| format!("Background process not found: {}", id))?;
+        let skip = tail.map_or(0, |n| process.logs.len().saturating_sub(n));
+        Ok(process.logs.iter().skip(skip).cloned().collect())
+    }
+
+    /// 应用退出时结束所有仍在运行的进程，返回结束的数量
+    pub fn kill_all(&self) -> usize {
+        let Ok(mut map) = self.processes.lock() else { return 0 };
+        let mut killed = 0;
+        for process in map.values_mut() {
+            if !process.poll_exit() {
+                process.kill();
+                killed += 1;
+            }
+        }
+        if killed > 0 {
+            println!("[BackgroundProcess] Killed {} background processes", killed);
+        }
+        killed
+    }
+}
+
+/// 持续读取托管进程的输出直到进程结束
+async fn pump_output(
+    processes: Processes,
+    id: String,
+    mut stdout: Lines<BufReader<ChildStdout>>,
+    mut stderr: Lines<BufReader<ChildStderr>>,
+) {
+    let (mut stdout_open, mut stderr_open) = (true, true);
+    let mut ticker = interval(Duration::from_millis(500));
+    loop {
+        let line = tokio::select! {
+            line = stdout.next_line(), if stdout_open => match line {
+                Ok(Some(line)) => Some((line, false)),
+                _ => { stdout_open = false; None }
+            },
+            line = stderr.next_line(), if stderr_open => match line {
+                Ok(Some(line)) => Some((line, true)),
+                _ => { stderr_open = false; None }
+            },
+            _ = ticker.tick() => None,
+        };
+        let Ok(mut map) = processes.lock() else { return };
+        let Some(process) = map.get_mut(&id) else { return };
+        if let Some((content, is_stderr)) = line {
+            process.push_log(content, is_stderr);
+        } else if process.poll_exit() && !stdout_open && !stderr_open {
+            println!("[BackgroundProcess] {} finished: {:?}", id, process.info.state);
+            return;
+        }
+    }
+}
+
+// ============================================================================
+// Tauri 命令
+// ============================================================================
+
+#[tauri::command]
+pub fn list_background_processes(registry: State<'_, BackgroundProcessRegistry>) -> Vec<BackgroundProcessInfo> {
+    registry.list()
+}
+
+#[tauri::command]
+pub fn stop_background_process(registry: State<'_, BackgroundProcessRegistry>, id: String) -> Result<BackgroundProcessInfo, String> {
+    registry.stop(&id)
+}
+
+#[tauri::command]
+pub fn get_process_logs(
+    registry: State<'_, BackgroundProcessRegistry>,
+    id: String,
+    tail: Option<usize>,
+) -> Result<Vec<ProcessLogLine>, String> {
+    registry.logs(&id, tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Stdio;
+    use tokio::io::AsyncBufReadExt;
+    use tokio::process::Command;
+
+    fn spawn(script: &str) -> (Child, Lines<BufReader<ChildStdout>>, Lines<BufReader<ChildStderr>>) {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+        let stderr = BufReader::new(child.stderr.take().unwrap()).lines();
+        (child, stdout, stderr)
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_adopt_logs_and_stop() {
+        let registry = BackgroundProcessRegistry::new();
+        let (child, stdout, stderr) = spawn("echo ready; echo warn >&2; sleep 30");
+        let initial = vec![ProcessLogLine { content: "Local: http://localhost:5173".to_string(), is_stderr: false }];
+        let id = registry.adopt("npm run dev", None, child, stdout, stderr, initial);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let logs = registry.logs(&id, None).unwrap();
+        assert_eq!(logs[0].content, "Local: http://localhost:5173");
+        assert!(logs.iter().any(|l| l.content == "ready" && !l.is_stderr));
+        assert!(logs.iter().any(|l| l.content == "warn" && l.is_stderr));
+        assert_eq!(registry.logs(&id, Some(1)).unwrap().len(), 1);
+        assert_eq!(registry.list()[0].state, ProcessState::Running);
+
+        assert_eq!(registry.stop(&id).unwrap().state, ProcessState::Stopped);
+        assert_eq!(registry.kill_all(), 0);
+        assert!(registry.stop("bg-missing").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_exit_and_kill_all() {
+        let registry = BackgroundProcessRegistry::new();
+        let (child, stdout, stderr) = spawn("exit 3");
+        let exited = registry.adopt("false", None, child, stdout, stderr, Vec::new());
+        let (child, stdout, stderr) = spawn("sleep 30");
+        let running = registry.adopt("sleep", None, child, stdout, stderr, Vec::new());
+
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        let states: HashMap<String, ProcessState> = registry.list().into_iter().map(|p| (p.id, p.state)).collect();
+        assert_eq!(states[&exited], ProcessState::Exited { code: Some(3) });
+        assert_eq!(states[&running], ProcessState::Running);
+
+        assert_eq!(registry.kill_all(), 1);
+        assert_eq!(registry.stop(&running).unwrap().state, ProcessState::Stopped);
+    }
+}
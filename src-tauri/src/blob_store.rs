@@ -0,0 +1,366 @@
+/*!
+Blob Store - 内容寻址的大对象存储
+==================================
+
+扫描结果、测试日志、导出包、附件等大对象不再内联到 JSON 元数据中，
+而是存入项目下的 `.ifai/blobs/`，按 SHA-256 寻址：
+
+- 对象文件：`.ifai/blobs/{hash[0..2]}/{hash}`，相同内容只存一份
+- 索引：`.ifai/blobs/index.json`，记录大小、类型与引用者（运行记录、会话等）
+- 引用计数：引用者集合为空的对象在宽限期后由 GC 删除；不在索引中的对象文件同样清理
+- 元数据中通过 `blob:sha256:{hash}` 引用对象（见 `marker` / `resolve_markers`）
+*/
+
+use base64::Engine;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// 引用释放后保留的时间，避免刚写入还未被引用的对象被回收
+const GC_GRACE_SECS: i64 = 3600;
+/// 引用标记中附带的预览字符数
+const PREVIEW_CHARS: usize = 500;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 对象元数据
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlobMeta {
+    pub size: u64,
+    pub media_type: String,
+    /// 引用者（如 `agent_{id}`、`session:{id}`）
+    pub refs: BTreeSet<String>,
+    pub created_at: i64,
+    /// 最后一个引用被释放的时间
+    pub released_at: Option<i64>,
+}
+
+/// 写入后返回的引用
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlobRef {
+    /// `sha256:{hex}`
+    pub id: String,
+    pub size: u64,
+    pub media_type: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct BlobIndex {
+    blobs: BTreeMap<String, BlobMeta>,
+}
+
+/// GC 结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GcReport {
+    pub removed: usize,
+    pub freed_bytes: u64,
+    pub remaining: usize,
+}
+
+/// 读取结果（内容为 base64）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlobContent {
+    pub id: String,
+    pub media_type: String,
+    pub data_base64: String,
+}
+
+// ============================================================================
+// Storage
+// ============================================================================
+
+/// 索引读写锁（同一进程内串行化）
+static INDEX_LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+
+fn index_lock() -> &'static Mutex<()> {
+    INDEX_LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn blobs_dir(project_root: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("blobs")
+}
+
+fn index_path(project_root: &str) -> PathBuf {
+    blobs_dir(project_root).join("index.json")
+}
+
+/// 解析 `sha256:{hex}`（也接受不带前缀的 hex）
+fn parse_id(id: &str) -> Result<&str, String> {
+    let hex = id.strip_prefix("sha256:").unwrap_or(id);
+    if hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(hex)
+    } else {
+        Err(format!("Invalid blob id: {}", id))
+    }
+}
+
+fn object_path(project_root: &str, hex: &str) -> PathBuf {
+    blobs_dir(project_root).join(&hex[..2]).join(hex)
+}
+
+fn load_index(project_root: &str) -> BlobIndex {
+    std::fs::read_to_string(index_path(project_root))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(project_root: &str, index: &BlobIndex) -> Result<(), String> {
+    let path = index_path(project_root);
+    std::fs::create_dir_all(blobs_dir(project_root)).map_err(|e| format!("Failed to create blob dir: {}", e))?;
+    let content = serde_json::to_string_pretty(index).map_err(|e| e.to_string())?;
+    // 先写临时文件再重命名，避免崩溃时索引损坏
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, content).map_err(|e| format!("Failed to write blob index: {}", e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write blob index: {}", e))
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+pub fn hash_bytes(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// 写入对象并登记引用者；内容已存在时只增加引用
+pub fn put(project_root: &str, data: &[u8], media_type: &str, referrer: &str) -> Result<BlobRef, String> {
+    let hex = hash_bytes(data);
+    let path = object_path(project_root, &hex);
+    if !path.exists() {
+        let parent = path.parent().ok_or("Invalid blob path")?;
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create blob dir: {}", e))?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, data).map_err(|e| format!("Failed to write blob: {}", e))?;
+        std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write blob: {}", e))?;
+    }
+
+    let _guard = index_lock().lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut index = load_index(project_root);
+    let meta = index.blobs.entry(hex.clone()).or_insert_with(|| BlobMeta {
+        size: data.len() as u64,
+        media_type: media_type.to_string(),
+        refs: BTreeSet::new(),
+        created_at: now(),
+        released_at: None,
+    });
+    meta.refs.insert(referrer.to_string());
+    meta.released_at = None;
+    save_index(project_root, &index)?;
+
+    Ok(BlobRef { id: format!("sha256:{}", hex), size: data.len() as u64, media_type: media_type.to_string() })
+}
+
+/// 读取对象内容，并校验哈希
+pub fn get(project_root: &str, id: &str) -> Result<Vec<u8>, String> {
+    let hex = parse_id(id)?;
+    let data = std::fs::read(object_path(project_root, hex)).map_err(|e| format!("Blob {} not found: {}", id, e))?;
+    if hash_bytes(&data) != hex {
+        return Err(format!("Blob {} is corrupted", id));
+    }
+    Ok(data)
+}
+
+pub fn get_text(project_root: &str, id: &str) -> Result<String, String> {
+    String::from_utf8(get(project_root, id)?).map_err(|_| format!("Blob {} is not UTF-8 text", id))
+}
+
+/// 释放引用者持有的全部引用
+pub fn release(project_root: &str, referrer: &str) -> Result<usize, String> {
+    let _guard = index_lock().lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut index = load_index(project_root);
+    let mut released = 0;
+    for meta in index.blobs.values_mut() {
+        if meta.refs.remove(referrer) {
+            released += 1;
+            if meta.refs.is_empty() {
+                meta.released_at = Some(now());
+            }
+        }
+    }
+    if released > 0 {
+        save_index(project_root, &index)?;
+    }
+    Ok(released)
+}
+
+/// 删除无引用且超过宽限期的对象，以及不在索引中的对象文件
+fn gc_with_grace(project_root: &str, grace_secs: i64) -> Result<GcReport, String> {
+    let _guard = index_lock().lock().map_err(|e| format!("Lock error: {}", e))?;
+    let mut index = load_index(project_root);
+    let mut report = GcReport::default();
+    let cutoff = now() - grace_secs;
+
+    let unreferenced: Vec<String> = index.blobs.iter()
+        .filter(|(_, meta)| meta.refs.is_empty() && meta.released_at.unwrap_or(meta.created_at) <= cutoff)
+        .map(|(hex, _)| hex.clone())
+        .collect();
+    for hex in unreferenced {
+        if let Some(meta) = index.blobs.remove(&hex) {
+            if std::fs::remove_file(object_path(project_root, &hex)).is_ok() {
+                report.removed += 1;
+                report.freed_bytes += meta.size;
+            }
+        }
+    }
+
+    // 索引之外的对象文件（写入中途崩溃等）
+    let shards = std::fs::read_dir(blobs_dir(project_root)).into_iter().flatten().flatten().filter(|e| e.path().is_dir());
+    for shard in shards {
+        for entry in std::fs::read_dir(shard.path()).into_iter().flatten().flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let orphan = !index.blobs.contains_key(&name);
+            let stale = entry.metadata().ok()
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.elapsed().ok())
+                .is_some_and(|age| age.as_secs() as i64 >= grace_secs);
+            if orphan && stale {
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                if std::fs::remove_file(entry.path()).is_ok() {
+                    report.removed += 1;
+                    report.freed_bytes += size;
+                }
+            }
+        }
+    }
+
+    report.remaining = index.blobs.len();
+    save_index(project_root, &index)?;
+    Ok(report)
+}
+
+pub fn gc(project_root: &str) -> Result<GcReport, String> {
+    gc_with_grace(project_root, GC_GRACE_SECS)
+}
+
+// ============================================================================
+// Markers
+// ============================================================================
+
+/// 元数据中替代完整内容的引用标记：首行为 `[blob:sha256:{hex} {size} bytes]`，其后为预览
+pub fn marker(blob: &BlobRef, text: &str) -> String {
+    let preview: String = text.chars().take(PREVIEW_CHARS).collect();
+    format!("[blob:{} {} bytes]\n{}", blob.id, blob.size, preview)
+}
+
+/// 将文本存为对象并返回引用标记；写入失败时返回原文
+pub fn externalize_text(project_root: &str, text: &str, referrer: &str) -> String {
+    match put(project_root, text.as_bytes(), "text/plain", referrer) {
+        Ok(blob) => marker(&blob, text),
+        Err(e) => {
+            eprintln!("[BlobStore] {}", e);
+            text.to_string()
+        }
+    }
+}
+
+/// 还原引用标记为完整内容；对象缺失时保留标记
+pub fn resolve_markers(project_root: &str, text: &str) -> String {
+    let re = Regex::new(r"^\[blob:(sha256:[0-9a-f]{64}) \d+ bytes\](?:\n|$)").unwrap();
+    match re.captures(text).and_then(|c| c.get(1)) {
+        Some(id) => get_text(project_root, id.as_str()).unwrap_or_else(|_| text.to_string()),
+        None => text.to_string(),
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 存入文件（附件、导出包等）
+#[tauri::command]
+pub fn blob_put_file(project_root: String, path: String, referrer: String, media_type: Option<String>) -> Result<BlobRef, String> {
+    let data = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    put(&project_root, &data, media_type.as_deref().unwrap_or("application/octet-stream"), &referrer)
+}
+
+/// 存入文本
+#[tauri::command]
+pub fn blob_put_text(project_root: String, text: String, referrer: String) -> Result<BlobRef, String> {
+    put(&project_root, text.as_bytes(), "text/plain", &referrer)
+}
+
+#[tauri::command]
+pub fn blob_read(project_root: String, id: String) -> Result<BlobContent, String> {
+    let data = get(&project_root, &id)?;
+    let media_type = {
+        let _guard = index_lock().lock().map_err(|e| format!("Lock error: {}", e))?;
+        load_index(&project_root).blobs.get(parse_id(&id)?).map(|m| m.media_type.clone())
+    };
+    Ok(BlobContent {
+        id,
+        media_type: media_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+        data_base64: base64::engine::general_purpose::STANDARD.encode(data),
+    })
+}
+
+#[tauri::command]
+pub fn blob_release(project_root: String, referrer: String) -> Result<usize, String> {
+    release(&project_root, &referrer)
+}
+
+#[tauri::command]
+pub fn blob_gc(project_root: String) -> Result<GcReport, String> {
+    let report = gc(&project_root)?;
+    println!("[BlobStore] GC removed {} blobs ({} bytes)", report.removed, report.freed_bytes);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root() -> String {
+        let dir = std::env::temp_dir().join(format!("ifai_blobs_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn test_put_dedup_and_get() {
+        let root = temp_root();
+        let a = put(&root, b"hello", "text/plain", "agent_1").unwrap();
+        let b = put(&root, b"hello", "text/plain", "agent_2").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.id, "sha256:2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+        assert_eq!(get(&root, &a.id).unwrap(), b"hello");
+        assert_eq!(load_index(&root).blobs[parse_id(&a.id).unwrap()].refs.len(), 2);
+        assert!(get(&root, "sha256:zz").is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_release_and_gc() {
+        let root = temp_root();
+        let kept = put(&root, b"kept", "text/plain", "agent_1").unwrap();
+        let dropped = put(&root, b"dropped", "text/plain", "agent_2").unwrap();
+
+        assert_eq!(release(&root, "agent_2").unwrap(), 1);
+        // 宽限期内不回收
+        assert_eq!(gc_with_grace(&root, 3600).unwrap().removed, 0);
+
+        let report = gc_with_grace(&root, 0).unwrap();
+        assert_eq!(report.removed, 1);
+        assert_eq!(report.remaining, 1);
+        assert!(get(&root, &dropped.id).is_err());
+        assert!(get(&root, &kept.id).is_ok());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_markers_roundtrip() {
+        let root = temp_root();
+        let log = "line\n".repeat(10_000);
+        let marked = externalize_text(&root, &log, "agent_3");
+        assert!(marked.starts_with("[blob:sha256:"));
+        assert!(marked.len() < 1000);
+        assert_eq!(resolve_markers(&root, &marked), log);
+        assert_eq!(resolve_markers(&root, "plain result"), "plain result");
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,164 @@
+/*!
+Cancellation - 请求取消
+=======================
+
+进行中的 AI 请求与 Agent 运行按事件通道登记取消令牌（聊天为 `event_id`，Agent 为 `agent_{id}`）：
+
+- `cancel_ai_request` 触发令牌；被 `CancelGuard::run` 包裹的请求 future 随即被丢弃，
+  reqwest 连接关闭，不再继续接收流式数据
+- 被取消的请求在其通道上发送终止的 `cancelled` 事件
+- Agent 运行在流式请求、等待审批和执行工具时均可被中断，审批等待由 Supervisor 释放
+*/
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::Notify;
+
+/// 被取消请求返回的错误
+pub const CANCELLED_ERROR: &str = "Request cancelled";
+
+#[derive(Default)]
+pub struct CancelToken {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 等待取消信号
+    pub async fn cancelled(&self) {
+        loop {
+            // 先创建等待者再检查标志，避免错过 notify_waiters
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// 以事件通道为键
+static TOKENS: OnceLock<Mutex<HashMap<String, Arc<CancelToken>>>> = OnceLock::new();
+
+fn tokens() -> &'static Mutex<HashMap<String, Arc<CancelToken>>> {
+    TOKENS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 登记期间持有；drop 时注销（通道已被新请求复用时不影响新令牌）
+pub struct CancelGuard {
+    key: String,
+    token: Arc<CancelToken>,
+}
+
+impl CancelGuard {
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub async fn cancelled(&self) {
+        self.token.cancelled().await
+    }
+
+    /// 运行 future，取消时丢弃它并返回 `CANCELLED_ERROR`
+    pub async fn run<T>(&self, fut: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+        tokio::select! {
+            result = fut => result,
+            _ = self.token.cancelled() => Err(CANCELLED_ERROR.to_string()),
+        }
+    }
+}
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        if let Ok(mut map) = tokens().lock() {
+            if map.get(&self.key).is_some_and(|t| Arc::ptr_eq(t, &self.token)) {
+                map.remove(&self.key);
+            }
+        }
+    }
+}
+
+/// 为事件通道登记新的取消令牌
+pub fn register(key: &str) -> CancelGuard {
+    let token = Arc::new(CancelToken::default());
+    if let Ok(mut map) = tokens().lock() {
+        map.insert(key.to_string(), token.clone());
+    }
+    CancelGuard { key: key.to_string(), token }
+}
+
+/// 触发取消；通道没有进行中的请求时返回 false
+pub fn cancel(key: &str) -> bool {
+    let token = tokens().lock().ok().and_then(|map| map.get(key).cloned());
+    match token {
+        Some(token) => {
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 取消进行中的 `ai_chat` 请求或 Agent 运行（`agent_{id}`）
+#[tauri::command]
+pub async fn cancel_ai_request(
+    supervisor: tauri::State<'_, crate::agent_system::Supervisor>,
+    event_id: String,
+) -> Result<bool, String> {
+    let found = cancel(&event_id);
+    #[cfg(feature = "commercial")]
+    {
+        if let Some(agent_id) = event_id.strip_prefix("agent_") {
+            // 释放审批等待，并标记为已停止
+            supervisor.stop_agent(agent_id).await;
+        }
+    }
+    #[cfg(not(feature = "commercial"))]
+    let _ = supervisor;
+    println!("[Cancellation] cancel_ai_request: {} (active: {})", event_id, found);
+    Ok(found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancel_aborts_running_future() {
+        let guard = register("chat_test_1");
+        let pending = guard.run(async {
+            tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+            Ok::<_, String>(())
+        });
+        let canceller = async {
+            tokio::task::yield_now().await;
+            assert!(cancel("chat_test_1"));
+        };
+        let (result, _) = tokio::join!(pending, canceller);
+        assert_eq!(result, Err(CANCELLED_ERROR.to_string()));
+        assert!(guard.is_cancelled());
+
+        // 新请求复用同一通道时，旧 guard 的注销不影响新令牌
+        let newer = register("chat_test_1");
+        drop(guard);
+        assert!(!newer.is_cancelled());
+        assert!(cancel("chat_test_1"));
+        drop(newer);
+        assert!(!cancel("chat_test_1"));
+    }
+}
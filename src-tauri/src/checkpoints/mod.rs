@@ -0,0 +1,229 @@
+/*!
+Checkpoints - Agent 文件修改检查点
+==================================
+
+Agent 每次经审批执行 `agent_write_file` / `agent_edit_file` 之前，先把目标文件的当前内容
+保存到 `.ifai/checkpoints/<agent_id>/`。之后可以：
+
+- `list_checkpoints`：查看 Agent 的所有修改记录
+- `diff_checkpoint`：对比某个检查点之前的状态与当前文件
+- `restore_checkpoint`：回滚到某个检查点之前（不指定时回滚 Agent 的全部修改），
+  Agent 新建的文件会被删除
+*/
+
+mod store;
+
+pub use store::snapshot;
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 单次文件修改的检查点
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Checkpoint {
+    /// Agent 内递增的序号（从 1 开始）
+    pub id: u64,
+    /// 相对项目根目录的路径
+    pub path: String,
+    /// 触发修改的工具
+    pub tool: String,
+    pub created_at: i64,
+    /// 修改前文件是否存在（不存在时回滚会删除文件）
+    pub existed: bool,
+    /// 修改前的文件大小
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentCheckpoints {
+    pub agent_id: String,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CheckpointDiff {
+    pub path: String,
+    /// `added` / `modified` / `deleted`
+    pub status: String,
+    /// 统一差异格式
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct RestoreResult {
+    /// 恢复为检查点内容的文件
+    pub restored: Vec<String>,
+    /// Agent 新建、已被删除的文件
+    pub removed: Vec<String>,
+}
+
+/// 有检查点的 Agent（目录名即 Agent id）
+fn agent_ids(project_root: &str) -> Vec<String> {
+    let mut ids: Vec<String> = std::fs::read_dir(store::checkpoints_root(project_root))
+        .map(|entries| entries.filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir())
+            .filter_map(|e| e.file_name().to_str().map(String::from))
+            .collect())
+        .unwrap_or_default();
+    ids.sort();
+    ids
+}
+
+/// 检查点之前的状态与当前文件的差异
+pub fn diff(project_root: &str, agent_id: &str, from: u64) -> Vec<CheckpointDiff> {
+    let checkpoints = store::load(project_root, agent_id);
+    store::earliest_per_file(&checkpoints, from)
+        .into_iter()
+        .filter_map(|checkpoint| {
+            let old = store::snapshot_content(project_root, agent_id, checkpoint);
+            let new = std::fs::read(Path::new(project_root).join(&checkpoint.path)).ok();
+            let status = match (&old, &new) {
+                (None, None) => return None,
+                (Some(old), Some(new)) if old == new => return None,
+                (None, Some(_)) => "added",
+                (Some(_), None) => "deleted",
+                (Some(_), Some(_)) => "modified",
+            };
+            let path = Path::new(&checkpoint.path);
+            let diff = git2::Patch::from_buffers(old.as_deref().unwrap_or_default(), Some(path), new.as_deref().unwrap_or_default(), Some(path), None)
+                .and_then(|mut p| p.to_buf())
+                .map(|buf| String::from_utf8_lossy(&buf).to_string())
+                .unwrap_or_default();
+            Some(CheckpointDiff { path: checkpoint.path.clone(), status: status.to_string(), diff })
+        })
+        .collect()
+}
+
+/// 回滚到检查点 `from` 之前的状态，并删除 `from` 及之后的检查点
+pub fn restore(project_root: &str, agent_id: &str, from: u64) -> Result<RestoreResult, String> {
+    let _guard = store::lock();
+    let checkpoints = store::load(project_root, agent_id);
+    if checkpoints.is_empty() {
+        return Err(format!("No checkpoints for agent {}", agent_id));
+    }
+
+    let mut result = RestoreResult::default();
+    for checkpoint in store::earliest_per_file(&checkpoints, from) {
+        let target = Path::new(project_root).join(&checkpoint.path);
+        match store::snapshot_content(project_root, agent_id, checkpoint) {
+            Some(content) => {
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+                }
+                std::fs::write(&target, content).map_err(|e| format!("Failed to restore {}: {}", checkpoint.path, e))?;
+                result.restored.push(checkpoint.path.clone());
+            }
+            None if checkpoint.existed => {
+                return Err(format!("Snapshot missing for {} (checkpoint {})", checkpoint.path, checkpoint.id));
+            }
+            None => {
+                if target.exists() {
+                    std::fs::remove_file(&target).map_err(|e| format!("Failed to remove {}: {}", checkpoint.path, e))?;
+                }
+                result.removed.push(checkpoint.path.clone());
+            }
+        }
+    }
+
+    store::truncate(project_root, agent_id, from)?;
+    println!("[Checkpoints] Agent {} restored {} files, removed {}", agent_id, result.restored.len(), result.removed.len());
+    Ok(result)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 列出检查点；不指定 `agent_id` 时返回所有 Agent 的检查点
+#[tauri::command]
+pub fn list_checkpoints(project_root: String, agent_id: Option<String>) -> Vec<AgentCheckpoints> {
+    let ids = match agent_id {
+        Some(id) => vec![id],
+        None => agent_ids(&project_root),
+    };
+    ids.into_iter()
+        .map(|agent_id| AgentCheckpoints { checkpoints: store::load(&project_root, &agent_id), agent_id })
+        .filter(|a| !a.checkpoints.is_empty())
+        .collect()
+}
+
+/// 对比检查点之前的状态与当前文件（不指定 `checkpoint_id` 时对比 Agent 的全部修改）
+#[tauri::command]
+pub fn diff_checkpoint(project_root: String, agent_id: String, checkpoint_id: Option<u64>) -> Vec<CheckpointDiff> {
+    diff(&project_root, &agent_id, checkpoint_id.unwrap_or(0))
+}
+
+/// 回滚到检查点之前（不指定 `checkpoint_id` 时回滚 Agent 的全部修改）
+#[tauri::command]
+pub fn restore_checkpoint(project_root: String, agent_id: String, checkpoint_id: Option<u64>) -> Result<RestoreResult, String> {
+    restore(&project_root, &agent_id, checkpoint_id.unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_project() -> String {
+        let root = std::env::temp_dir().join(format!("ifai_checkpoints_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/lib.rs"), "fn a() {}\n").unwrap();
+        root.to_string_lossy().to_string()
+    }
+
+    fn write(root: &str, rel: &str, content: &str) {
+        let path = Path::new(root).join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    fn read(root: &str, rel: &str) -> Option<String> {
+        std::fs::read_to_string(Path::new(root).join(rel)).ok()
+    }
+
+    #[test]
+    fn test_snapshot_diff_and_restore_all() {
+        let root = temp_project();
+        snapshot(&root, "agent-1", "./src/lib.rs", "agent_edit_file").unwrap();
+        write(&root, "src/lib.rs", "fn a() { b(); }\n");
+        snapshot(&root, "agent-1", "src/new.rs", "agent_write_file").unwrap();
+        write(&root, "src/new.rs", "fn b() {}\n");
+        snapshot(&root, "agent-1", "src/lib.rs", "agent_write_file").unwrap();
+        write(&root, "src/lib.rs", "fn a() { b(); c(); }\n");
+
+        let listed = list_checkpoints(root.clone(), None);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].checkpoints.iter().map(|c| (c.id, c.path.as_str(), c.existed)).collect::<Vec<_>>(),
+            vec![(1, "src/lib.rs", true), (2, "src/new.rs", false), (3, "src/lib.rs", true)]);
+
+        let diffs = diff_checkpoint(root.clone(), "agent-1".into(), None);
+        assert_eq!(diffs.iter().map(|d| (d.path.as_str(), d.status.as_str())).collect::<Vec<_>>(),
+            vec![("src/lib.rs", "modified"), ("src/new.rs", "added")]);
+        assert!(diffs[0].diff.contains("-fn a() {}") && diffs[0].diff.contains("+fn a() { b(); c(); }"));
+
+        let result = restore_checkpoint(root.clone(), "agent-1".into(), None).unwrap();
+        assert_eq!(result, RestoreResult { restored: vec!["src/lib.rs".into()], removed: vec!["src/new.rs".into()] });
+        assert_eq!(read(&root, "src/lib.rs").as_deref(), Some("fn a() {}\n"));
+        assert!(read(&root, "src/new.rs").is_none());
+        assert!(list_checkpoints(root.clone(), None).is_empty());
+        assert!(restore_checkpoint(root.clone(), "agent-1".into(), None).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_restore_from_checkpoint() {
+        let root = temp_project();
+        snapshot(&root, "agent-2", "src/lib.rs", "agent_write_file").unwrap();
+        write(&root, "src/lib.rs", "v2\n");
+        snapshot(&root, "agent-2", "src/lib.rs", "agent_write_file").unwrap();
+        write(&root, "src/lib.rs", "v3\n");
+
+        // 只回滚第 2 次修改，第 1 次修改保留
+        let result = restore_checkpoint(root.clone(), "agent-2".into(), Some(2)).unwrap();
+        assert_eq!(result.restored, vec!["src/lib.rs".to_string()]);
+        assert_eq!(read(&root, "src/lib.rs").as_deref(), Some("v2\n"));
+        let remaining = list_checkpoints(root.clone(), Some("agent-2".into()));
+        assert_eq!(remaining[0].checkpoints.len(), 1);
+        assert_eq!(diff_checkpoint(root.clone(), "agent-2".into(), Some(2)), vec![]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
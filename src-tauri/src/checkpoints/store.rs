@@ -0,0 +1,109 @@
+//! 检查点存储：`.ifai/checkpoints/<agent_id>/manifest.json` 记录每次修改，
+//! `files/<id>` 保存修改前的文件内容
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use super::Checkpoint;
+
+/// 串行化 manifest 的读写
+static LOCK: Mutex<()> = Mutex::new(());
+
+const MANIFEST_FILE: &str = "manifest.json";
+const FILES_DIR: &str = "files";
+
+pub(super) fn lock() -> std::sync::MutexGuard<'static, ()> {
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub(super) fn checkpoints_root(project_root: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("checkpoints")
+}
+
+/// Agent id 用作目录名，去掉路径分隔符等字符
+fn agent_dir(project_root: &str, agent_id: &str) -> PathBuf {
+    let name: String = agent_id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    checkpoints_root(project_root).join(name)
+}
+
+pub(super) fn normalize_path(rel_path: &str) -> String {
+    rel_path.replace('\\', "/").trim_start_matches("./").to_string()
+}
+
+pub(super) fn load(project_root: &str, agent_id: &str) -> Vec<Checkpoint> {
+    std::fs::read_to_string(agent_dir(project_root, agent_id).join(MANIFEST_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save(project_root: &str, agent_id: &str, checkpoints: &[Checkpoint]) -> Result<(), String> {
+    let dir = agent_dir(project_root, agent_id);
+    if checkpoints.is_empty() {
+        return match std::fs::remove_dir_all(&dir) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(format!("Failed to remove checkpoints: {}", e)),
+            _ => Ok(()),
+        };
+    }
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create checkpoint dir: {}", e))?;
+    let content = serde_json::to_string_pretty(checkpoints).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(MANIFEST_FILE), content).map_err(|e| format!("Failed to write checkpoint manifest: {}", e))
+}
+
+/// 修改前的文件内容（修改前不存在时为 None）
+pub(super) fn snapshot_content(project_root: &str, agent_id: &str, checkpoint: &Checkpoint) -> Option<Vec<u8>> {
+    if !checkpoint.existed {
+        return None;
+    }
+    std::fs::read(agent_dir(project_root, agent_id).join(FILES_DIR).join(checkpoint.id.to_string())).ok()
+}
+
+/// 记录文件修改前的状态
+pub fn snapshot(project_root: &str, agent_id: &str, rel_path: &str, tool: &str) -> Result<Checkpoint, String> {
+    let _guard = lock();
+    let path = normalize_path(rel_path);
+    if Path::new(&path).is_absolute() || Path::new(&path).components().any(|c| c == Component::ParentDir) {
+        return Err(format!("Path is outside the project: {}", rel_path));
+    }
+    let content = std::fs::read(Path::new(project_root).join(&path)).ok();
+
+    let mut checkpoints = load(project_root, agent_id);
+    let checkpoint = Checkpoint {
+        id: checkpoints.last().map(|c| c.id + 1).unwrap_or(1),
+        path,
+        tool: tool.to_string(),
+        created_at: chrono::Utc::now().timestamp(),
+        existed: content.is_some(),
+        size: content.as_ref().map(|c| c.len() as u64).unwrap_or(0),
+    };
+
+    if let Some(content) = content {
+        let files = agent_dir(project_root, agent_id).join(FILES_DIR);
+        std::fs::create_dir_all(&files).map_err(|e| format!("Failed to create checkpoint dir: {}", e))?;
+        std::fs::write(files.join(checkpoint.id.to_string()), content).map_err(|e| format!("Failed to write snapshot: {}", e))?;
+    }
+    checkpoints.push(checkpoint.clone());
+    save(project_root, agent_id, &checkpoints)?;
+    Ok(checkpoint)
+}
+
+/// 每个文件在 `from` 及之后最早的检查点，即回滚到 `from` 之前时各文件应恢复的状态
+pub(super) fn earliest_per_file(checkpoints: &[Checkpoint], from: u64) -> Vec<&Checkpoint> {
+    let mut seen = std::collections::HashSet::new();
+    checkpoints.iter()
+        .filter(|c| c.id >= from && seen.insert(c.path.as_str()))
+        .collect()
+}
+
+/// 删除 `from` 及之后的检查点
+pub(super) fn truncate(project_root: &str, agent_id: &str, from: u64) -> Result<(), String> {
+    let mut checkpoints = load(project_root, agent_id);
+    let files = agent_dir(project_root, agent_id).join(FILES_DIR);
+    for checkpoint in checkpoints.iter().filter(|c| c.id >= from) {
+        let _ = std::fs::remove_file(files.join(checkpoint.id.to_string()));
+    }
+    checkpoints.retain(|c| c.id < from);
+    save(project_root, agent_id, &checkpoints)
+}
@@ -0,0 +1,360 @@
+/*!
+Code Chunker - 按语法结构切分代码
+=================================
+
+按固定字符数切分会把函数拦腰截断。这里基于 `symbol_engine` 提取的符号切分：
+
+- 每个函数 / 方法 / 类型单独成块，连同上方的文档注释和属性
+- 超过 `max_chars` 的类 / trait 按成员拆开，成员之外的部分（字段、类头）单独成块
+- 仍然过长的符号按行切成多段，相邻段重叠 `overlap_lines` 行
+- 符号之间的代码（导入、常量等）按行切块，过短的片段丢弃
+- 不支持的语言整体按行切块
+
+每个块附带符号信息（名称、限定名、类型），用于检索时的上下文和结果展示。
+*/
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::symbol_engine::{extract_symbols_from_source, Symbol};
+
+/// 作为切分单元的符号类型
+const CHUNK_KINDS: &[&str] = &[
+    "function_item", "struct_item", "enum_item", "trait_item",
+    "function_declaration", "method_definition", "class_declaration", "interface_declaration",
+];
+/// 符号之外的片段少于该数量的非空白字符时丢弃（如单独的 `}`）
+const MIN_CHUNK_CHARS: usize = 24;
+/// 向上并入文档注释 / 属性的最大行数
+const MAX_LEADING_LINES: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkOptions {
+    pub max_chars: usize,
+    pub overlap_lines: usize,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self { max_chars: 1500, overlap_lines: 3 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChunkSymbol {
+    pub name: String,
+    /// 含外层类型，如 `Store::get`、`UserService.load`
+    pub qualified_name: String,
+    pub kind: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CodeChunk {
+    pub file_path: String,
+    /// 从 1 开始，含首尾
+    pub start_line: usize,
+    pub end_line: usize,
+    pub content: String,
+    /// 所属符号；符号之间的代码为 `None`（类成员之外的部分为该类）
+    pub symbol: Option<ChunkSymbol>,
+}
+
+impl CodeChunk {
+    /// 索引中的分块 ID
+    pub fn id(&self) -> String {
+        format!("{}:{}-{}", self.file_path, self.start_line, self.end_line)
+    }
+
+    /// 计算向量时使用的文本：带上文件和符号，提升检索效果
+    pub fn embedding_text(&self) -> String {
+        match &self.symbol {
+            Some(symbol) => format!("{} {} ({})\n{}", self.file_path, symbol.qualified_name, symbol.kind, self.content),
+            None => format!("{}\n{}", self.file_path, self.content),
+        }
+    }
+}
+
+/// 符号树节点（行号从 0 开始）
+struct Unit {
+    start: usize,
+    end: usize,
+    symbol: ChunkSymbol,
+    children: Vec<Unit>,
+}
+
+fn impl_header() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^\s*(?:unsafe\s+)?impl\b(?:\s*<[^{]*?>)?\s+(?:[\w:]+(?:<[^{]*?>)?\s+for\s+)?([\w:]+)").unwrap()
+    })
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Rust 方法所在的 `impl` 类型（`symbol_engine` 不提取 impl 块，按缩进向上查找块头）
+fn rust_impl_type(lines: &[&str], line: usize) -> Option<String> {
+    let own = indent(lines.get(line)?);
+    if own == 0 {
+        return None;
+    }
+    let header = (0..line).rev().map(|i| lines[i]).find(|l| !l.trim().is_empty() && indent(l) < own)?;
+    let header_indent = indent(header);
+    // 块头可能跨多行（泛型约束、where 子句、单独一行的 `{`）
+    let header_lines = (0..line).rev().map(|i| lines[i]).filter(|l| !l.trim().is_empty()).skip_while(|l| indent(l) >= own);
+    for candidate in header_lines.take(4).take_while(|l| indent(l) == header_indent) {
+        if let Some(caps) = impl_header().captures(candidate) {
+            return caps[1].rsplit("::").next().map(|s| s.to_string());
+        }
+        if candidate.trim_end().ends_with('}') || candidate.trim_end().ends_with(';') {
+            break;
+        }
+    }
+    None
+}
+
+fn is_leading_line(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    ["//", "/*", "*", "#[", "#", "@"].iter().any(|p| trimmed.starts_with(p))
+}
+
+/// 按范围包含关系构建符号树
+fn build_units(symbols: Vec<Symbol>, lines: &[&str], language: &str) -> Vec<Unit> {
+    let mut symbols: Vec<Symbol> = symbols.into_iter().filter(|s| CHUNK_KINDS.contains(&s.kind.as_str())).collect();
+    symbols.sort_by(|a, b| a.range.start_line.cmp(&b.range.start_line).then(b.range.end_line.cmp(&a.range.end_line)));
+    let separator = if language == "rust" { "::" } else { "." };
+
+    let mut roots: Vec<Unit> = Vec::new();
+    for symbol in symbols {
+        let (start, end) = (symbol.range.start_line, symbol.range.end_line.min(lines.len().saturating_sub(1)));
+        // 沿最后一个子节点向下找到包含该符号的最内层节点
+        let mut siblings = &mut roots;
+        let mut parent_name: Option<String> = None;
+        loop {
+            match siblings.last() {
+                Some(last) if start >= last.start && end <= last.end => {
+                    parent_name = Some(last.symbol.qualified_name.clone());
+                    siblings = &mut siblings.last_mut().unwrap().children;
+                }
+                _ => break,
+            }
+        }
+        // 与前一个兄弟节点交叉（如同一行的多个定义）时跳过
+        if siblings.last().is_some_and(|last| start <= last.end) {
+            continue;
+        }
+        if parent_name.is_none() && language == "rust" {
+            parent_name = rust_impl_type(lines, start);
+        }
+        let qualified_name = match parent_name {
+            Some(parent) => format!("{}{}{}", parent, separator, symbol.name),
+            None => symbol.name.clone(),
+        };
+        siblings.push(Unit { start, end, symbol: ChunkSymbol { name: symbol.name, qualified_name, kind: symbol.kind }, children: Vec::new() });
+    }
+    roots
+}
+
+struct Chunker<'a> {
+    file_path: &'a str,
+    lines: Vec<&'a str>,
+    options: &'a ChunkOptions,
+    chunks: Vec<CodeChunk>,
+}
+
+impl Chunker<'_> {
+    fn text(&self, start: usize, end: usize) -> String {
+        self.lines[start..=end].join("\n")
+    }
+
+    fn push(&mut self, start: usize, end: usize, symbol: Option<&ChunkSymbol>) {
+        self.chunks.push(CodeChunk {
+            file_path: self.file_path.to_string(),
+            start_line: start + 1,
+            end_line: end + 1,
+            content: self.text(start, end),
+            symbol: symbol.cloned(),
+        });
+    }
+
+    /// 按行切成不超过 `max_chars` 的窗口，相邻窗口重叠 `overlap_lines` 行
+    fn push_windows(&mut self, start: usize, end: usize, symbol: Option<&ChunkSymbol>) {
+        let mut window_start = start;
+        loop {
+            let mut window_end = window_start;
+            let mut size = self.lines[window_start].len();
+            while window_end < end && size + 1 + self.lines[window_end + 1].len() <= self.options.max_chars {
+                window_end += 1;
+                size += 1 + self.lines[window_end].len();
+            }
+            self.push(window_start, window_end, symbol);
+            if window_end >= end {
+                break;
+            }
+            window_start = (window_end + 1).saturating_sub(self.options.overlap_lines).max(window_start + 1);
+        }
+    }
+
+    /// 符号之间的代码，过短时丢弃
+    fn push_gap(&mut self, start: usize, end: usize, symbol: Option<&ChunkSymbol>) {
+        if start > end {
+            return;
+        }
+        let meaningful = self.lines[start..=end].iter().map(|l| l.chars().filter(|c| !c.is_whitespace()).count()).sum::<usize>();
+        if meaningful >= MIN_CHUNK_CHARS {
+            self.push_windows(start, end, symbol);
+        }
+    }
+
+    /// 切分 `[start, end]` 行范围，其中 `units` 为该范围内的符号
+    fn chunk_region(&mut self, start: usize, end: usize, units: &[Unit], parent: Option<&ChunkSymbol>) {
+        let mut cursor = start;
+        for unit in units {
+            // 并入符号上方紧邻的文档注释和属性
+            let mut unit_start = unit.start;
+            while unit_start > cursor && unit.start - unit_start < MAX_LEADING_LINES && is_leading_line(self.lines[unit_start - 1]) {
+                unit_start -= 1;
+            }
+            if unit_start > cursor {
+                self.push_gap(cursor, unit_start - 1, parent);
+            }
+            self.chunk_unit(unit_start, unit);
+            cursor = unit.end + 1;
+        }
+        if cursor <= end {
+            self.push_gap(cursor, end, parent);
+        }
+    }
+
+    fn chunk_unit(&mut self, start: usize, unit: &Unit) {
+        if self.text(start, unit.end).len() <= self.options.max_chars {
+            self.push(start, unit.end, Some(&unit.symbol));
+        } else if unit.children.is_empty() {
+            self.push_windows(start, unit.end, Some(&unit.symbol));
+        } else {
+            self.chunk_region(start, unit.end, &unit.children, Some(&unit.symbol));
+        }
+    }
+}
+
+/// 切分单个文件（`language` 为 `LanguageMap` 识别的语言）
+pub fn chunk_source(file_path: &str, content: &str, language: &str, options: &ChunkOptions) -> Vec<CodeChunk> {
+    let lines: Vec<&str> = content.lines().collect();
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    let units = build_units(extract_symbols_from_source(content, language), &lines, language);
+    let last = lines.len() - 1;
+    let mut chunker = Chunker { file_path, lines, options, chunks: Vec::new() };
+    chunker.chunk_region(0, last, &units, None);
+    chunker.chunks
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 切分项目中的文件（`path` 相对项目根目录）
+#[tauri::command]
+pub async fn chunk_file(project_root: String, path: String, options: Option<ChunkOptions>) -> Result<Vec<CodeChunk>, String> {
+    let rel_path = path.replace('\\', "/");
+    let content = std::fs::read_to_string(std::path::Path::new(&project_root).join(&rel_path))
+        .map_err(|e| format!("Failed to read {}: {}", rel_path, e))?;
+    let language = crate::language_map::LanguageMap::load(&project_root).for_path(&rel_path).unwrap_or_default().to_string();
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || chunk_source(&rel_path, &content, &language, &options))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST_SOURCE: &str = r#"use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A cached store
+#[derive(Debug)]
+pub struct Store<T> {
+    items: HashMap<String, T>,
+}
+
+impl<T: Clone> Store<T> {
+    /// Look up an item
+    pub fn get(&self, key: &str) -> Option<T> {
+        self.items.get(key).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+fn helper() -> u32 {
+    let a = 1;
+    let b = 2;
+    let c = 3;
+    let d = 4;
+    let e = 5;
+    a + b + c + d + e
+}
+"#;
+
+    fn symbols(chunks: &[CodeChunk]) -> Vec<Option<String>> {
+        chunks.iter().map(|c| c.symbol.as_ref().map(|s| s.qualified_name.clone())).collect()
+    }
+
+    #[test]
+    fn test_rust_chunks_follow_symbols() {
+        let chunks = chunk_source("src/store.rs", RUST_SOURCE, "rust", &ChunkOptions::default());
+        assert_eq!(
+            symbols(&chunks),
+            vec![None, Some("Store".into()), Some("Store::get".into()), Some("Store::len".into()), Some("helper".into())]
+        );
+        // 文档注释和属性并入符号块
+        assert_eq!((chunks[1].start_line, chunks[1].end_line), (4, 8));
+        assert!(chunks[1].content.starts_with("/// A cached store\n#[derive(Debug)]"));
+        assert_eq!((chunks[2].start_line, chunks[2].end_line), (11, 14));
+        assert_eq!(chunks[2].symbol.as_ref().unwrap().kind, "function_item");
+        assert_eq!(chunks[2].id(), "src/store.rs:11-14");
+        assert!(chunks[2].embedding_text().starts_with("src/store.rs Store::get (function_item)\n"));
+    }
+
+    #[test]
+    fn test_long_symbols_split_with_overlap() {
+        let options = ChunkOptions { max_chars: 60, overlap_lines: 1 };
+        let chunks = chunk_source("src/store.rs", RUST_SOURCE, "rust", &options);
+        let helper: Vec<&CodeChunk> = chunks.iter().filter(|c| c.symbol.as_ref().is_some_and(|s| s.name == "helper")).collect();
+        assert!(helper.len() > 1);
+        assert_eq!(helper.first().unwrap().start_line, 21);
+        assert_eq!(helper.last().unwrap().end_line, 28);
+        for pair in helper.windows(2) {
+            assert_eq!(pair[1].start_line, pair[0].end_line);
+        }
+        assert!(chunks.iter().all(|c| c.content.len() <= 60 || c.start_line == c.end_line));
+    }
+
+    #[test]
+    fn test_typescript_class_split_into_members() {
+        let methods: String = (0..4).map(|i| format!("  method{}(value: number): number {{\n    return value * {};\n  }}\n\n", i, i)).collect();
+        let source = format!("export class Calculator {{\n  private base = 10;\n\n{}}}\n", methods);
+        let chunks = chunk_source("src/calc.ts", &source, "typescript", &ChunkOptions { max_chars: 120, overlap_lines: 0 });
+        let names = symbols(&chunks);
+        assert_eq!(names[0].as_deref(), Some("Calculator"));
+        assert!(chunks[0].content.contains("private base"));
+        assert!(names.contains(&Some("Calculator.method3".into())));
+        assert_eq!(names.iter().filter(|n| n.as_deref() == Some("Calculator")).count(), 1);
+    }
+
+    #[test]
+    fn test_unsupported_language_falls_back_to_lines() {
+        let source = "x = 1\n".repeat(100);
+        let chunks = chunk_source("run.sh", &source, "shell", &ChunkOptions { max_chars: 100, overlap_lines: 2 });
+        assert!(chunks.len() > 1 && chunks.iter().all(|c| c.symbol.is_none()));
+        assert_eq!(chunks.last().unwrap().end_line, 100);
+        assert!(chunk_source("empty.rs", "", "rust", &ChunkOptions::default()).is_empty());
+    }
+}
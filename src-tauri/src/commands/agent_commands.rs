@@ -14,6 +14,16 @@ pub struct AgentInfo {
     pub status: AgentStatus,
 }
 
+/// 排队等待审批的工具调用
+#[derive(Serialize)]
+pub struct PendingApproval {
+    pub agent_id: String,
+    pub tool_call_id: String,
+    pub tool: String,
+    pub args: serde_json::Value,
+    pub queued_at: i64,
+}
+
 #[tauri::command]
 pub async fn launch_agent(
     app: tauri::AppHandle,
@@ -23,10 +33,23 @@ pub async fn launch_agent(
     task: String,
     project_root: String,
     provider_config: AIProviderConfig,
+    scope_path: Option<String>,
+    verbosity: Option<crate::events::LogLevel>,
+    session_id: Option<String>,
+    isolation: Option<crate::git::AgentIsolation>,
+    limits: Option<crate::agent_budget::AgentLimits>,
+    images: Option<Vec<String>>,
 ) -> Result<String, String> {
     // 🔥 使用 log::info 而不是 println!，这样可以通过 tauri-plugin-log 输出到前端
     log::info!("[AgentCommands] 🔥 launch_agent ENTRY - id: {}, agent_type: '{}'", id, agent_type);
     log::info!("[AgentCommands] project_root: {}", project_root);
+    crate::idle_manager::touch();
+    // v0.3.4: 本次运行的日志详细程度
+    if let Some(level) = verbosity {
+        crate::agent_log::set_verbosity(&id, level);
+    }
+    // v0.3.4: 绑定所属会话的隐私级别
+    crate::privacy::bind_agent(&id, session_id.as_deref());
     log::info!("[AgentCommands] provider: {:?}", provider_config.protocol);
     log::info!("[AgentCommands] model: {:?}", provider_config.models.first());
 
@@ -35,6 +58,12 @@ pub async fn launch_agent(
     println!("[AgentCommands] provider: {:?}", provider_config.protocol);
     println!("[AgentCommands] model: {:?}", provider_config.models.first());
 
+    // v0.3.4: 工作流录制
+    super::workflow_commands::record_step(
+        super::workflow_commands::WorkflowAction::Agent,
+        serde_json::json!({ "agent_type": agent_type, "task": task, "scope_path": scope_path }),
+    );
+
     #[cfg(feature = "commercial")]
     {
         log::info!("[AgentCommands] ✅ Commercial feature IS enabled");
@@ -44,14 +73,29 @@ pub async fn launch_agent(
         let _ = app.emit("agent_diagnostic", format!("launch_agent: Commercial feature enabled, id={}", id));
 
         println!("[AgentSystem] launch_agent called with id: {}, agent_type: {}", id, agent_type);
+
+        // v0.3.4: Agent 的改动落在 `ifai/agent-<id>` 分支或独立工作树上，结束后用 git_finish_agent_branch 合并或丢弃
+        let project_root = match isolation {
+            Some(isolation) => crate::git::prepare_agent_workspace(&project_root, &id, isolation)?,
+            None => project_root,
+        };
+
         supervisor.register_agent(id.clone(), agent_type.clone()).await;
 
+        // v0.3.4: 记录任务作用域，runner 据此应用子包上下文配置
+        let mut variables = HashMap::new();
+        if let Some(scope) = scope_path {
+            variables.insert("SCOPE_PATH".to_string(), scope);
+        }
+
         let context = AgentContext {
             project_root,
             task_description: task,
             initial_prompt: String::new(),
-            variables: HashMap::new(),
+            variables,
             provider_config,
+            limits: limits.unwrap_or_default(),
+            images: images.unwrap_or_default(),
         };
 
         let supervisor_inner = supervisor.inner().clone();
@@ -66,7 +110,7 @@ pub async fn launch_agent(
         tokio::spawn(async move {
             // 🔥 发送诊断事件：任务开始执行
             let _ = app_clone.emit("agent_diagnostic", format!("Task started for agent: {}", id_clone));
-            runner::run_agent_task(app_clone, supervisor_inner, id_clone, agent_type_clone, context).await;
+            let _ = runner::run_agent_task(app_clone, supervisor_inner, id_clone, agent_type_clone, context).await;
         });
 
         // 🔥 发送诊断事件：任务已 spawn
@@ -85,6 +129,54 @@ pub async fn launch_agent(
     }
 }
 
+/// 反复运行失败的构建 / lint 命令，并启动修复 Agent 处理解析出的错误，直到命令通过或轮数用完；
+/// 进度通过 `fix_agent:progress` 事件报告，每轮修复 Agent 的 id 为 `<id>-<轮次>`
+#[tauri::command]
+pub async fn launch_fix_agent(
+    app: tauri::AppHandle,
+    supervisor: State<'_, Supervisor>,
+    id: String,
+    command: String,
+    project_root: String,
+    provider_config: AIProviderConfig,
+    cwd: Option<String>,
+    max_iterations: Option<usize>,
+    timeout_secs: Option<u64>,
+    limits: Option<crate::agent_budget::AgentLimits>,
+    session_id: Option<String>,
+) -> Result<String, String> {
+    crate::idle_manager::touch();
+    let report = crate::command_safety::analyze(&command, Some(&project_root));
+    if report.level == crate::command_safety::CommandRiskLevel::Critical {
+        return Err(format!("Refusing to run `{}` in a fix loop: {}", command, report.summary()));
+    }
+
+    #[cfg(feature = "commercial")]
+    {
+        println!("[AgentSystem] launch_fix_agent called with id: {}, command: {}", id, command);
+        let options = crate::agent_system::fix_loop::FixLoopOptions {
+            max_iterations,
+            timeout_secs,
+            cwd,
+            limits: limits.unwrap_or_default(),
+        };
+        let supervisor_inner = supervisor.inner().clone();
+        let id_clone = id.clone();
+        tokio::spawn(async move {
+            crate::agent_system::fix_loop::run_fix_loop(
+                app, supervisor_inner, id_clone, command, project_root, provider_config, options, session_id,
+            )
+            .await;
+        });
+        Ok(id)
+    }
+
+    #[cfg(not(feature = "commercial"))]
+    {
+        Err("Agents are available in Commercial Edition".to_string())
+    }
+}
+
 #[tauri::command]
 pub async fn list_running_agents(
     supervisor: State<'_, Supervisor>,
@@ -109,16 +201,20 @@ pub async fn list_running_agents(
     }
 }
 
+/// 审批 Agent 的工具调用；shell 命令可通过 `edited_command` 改写后再执行，
+/// 写入类工具可通过 `accepted_hunks` 只接受差异预览中的部分块
 #[tauri::command]
 pub async fn approve_agent_action(
     supervisor: State<'_, Supervisor>,
     id: String,
     approved: bool,
+    edited_command: Option<String>,
+    accepted_hunks: Option<Vec<usize>>,
 ) -> Result<(), String> {
     #[cfg(feature = "commercial")]
     {
         println!("[AgentCommands] approve_agent_action called: id={}, approved={}", id, approved);
-        supervisor.notify_approval(&id, approved).await;
+        supervisor.notify_decision(&id, crate::agent_system::ApprovalDecision { approved, edited_command, accepted_hunks, ..Default::default() }).await;
         println!("[AgentCommands] notify_approval completed for id={}", id);
         Ok(())
     }
@@ -127,3 +223,126 @@ pub async fn approve_agent_action(
         Err("Agents are available in Commercial Edition".to_string())
     }
 }
+
+/// 一次批准 Agent 当前消息中所有排队的工具调用，返回批准的数量
+///
+/// 被项目策略拒绝的调用不受影响
+#[tauri::command]
+pub async fn approve_all_pending(
+    supervisor: State<'_, Supervisor>,
+    agent_id: String,
+) -> Result<usize, String> {
+    #[cfg(feature = "commercial")]
+    {
+        Ok(supervisor.approve_all_pending(&agent_id).await)
+    }
+    #[cfg(not(feature = "commercial"))]
+    {
+        Err("Agents are available in Commercial Edition".to_string())
+    }
+}
+
+/// 查看排队等待审批的工具调用（`agent_id` 为空时返回所有 Agent 的）
+#[tauri::command]
+pub async fn list_pending_approvals(
+    supervisor: State<'_, Supervisor>,
+    agent_id: Option<String>,
+) -> Result<Vec<PendingApproval>, String> {
+    #[cfg(feature = "commercial")]
+    {
+        Ok(supervisor.pending_calls(agent_id.as_deref()).await
+            .into_iter()
+            .map(|call| PendingApproval {
+                agent_id: call.agent_id,
+                tool_call_id: call.tool_call_id,
+                tool: call.tool,
+                args: call.args,
+                queued_at: call.queued_at,
+            })
+            .collect())
+    }
+    #[cfg(not(feature = "commercial"))]
+    {
+        Ok(vec![])
+    }
+}
+
+/// 已暂停、可恢复的 Agent
+#[derive(Serialize)]
+pub struct PausedAgentInfo {
+    pub id: String,
+    pub agent_type: String,
+    pub task: String,
+    pub iterations: usize,
+    pub paused_at: i64,
+}
+
+/// 请求暂停 Agent；Agent 在下一个循环边界保存状态后停止
+#[tauri::command]
+pub async fn pause_agent(
+    supervisor: State<'_, Supervisor>,
+    id: String,
+) -> Result<(), String> {
+    #[cfg(feature = "commercial")]
+    {
+        supervisor.request_pause(&id).await
+    }
+    #[cfg(not(feature = "commercial"))]
+    {
+        Err("Agents are available in Commercial Edition".to_string())
+    }
+}
+
+/// 从保存的状态恢复已暂停的 Agent（快照不含 API Key，需重新提供模型配置）
+#[tauri::command]
+pub async fn resume_agent(
+    app: tauri::AppHandle,
+    supervisor: State<'_, Supervisor>,
+    id: String,
+    project_root: String,
+    provider_config: AIProviderConfig,
+    session_id: Option<String>,
+) -> Result<String, String> {
+    #[cfg(feature = "commercial")]
+    {
+        let mut snapshot = crate::agent_system::persistence::load(&project_root, &id)?;
+        snapshot.context.provider_config = provider_config;
+        crate::agent_system::persistence::remove(&project_root, &id);
+        crate::privacy::bind_agent(&id, session_id.as_deref());
+
+        supervisor.register_agent(id.clone(), snapshot.agent_type.clone()).await;
+        println!("[AgentSystem] Resuming agent: {} ({})", id, snapshot.agent_type);
+
+        let supervisor_inner = supervisor.inner().clone();
+        tokio::spawn(async move {
+            let _ = runner::resume_agent_task(app, supervisor_inner, snapshot).await;
+        });
+        Ok(id)
+    }
+    #[cfg(not(feature = "commercial"))]
+    {
+        Err("Agents are available in Commercial Edition".to_string())
+    }
+}
+
+/// 列出项目中已暂停的 Agent
+#[tauri::command]
+pub async fn list_paused_agents(project_root: String) -> Result<Vec<PausedAgentInfo>, String> {
+    #[cfg(feature = "commercial")]
+    {
+        Ok(crate::agent_system::persistence::list(&project_root)
+            .into_iter()
+            .map(|snapshot| PausedAgentInfo {
+                iterations: snapshot.loops.len(),
+                id: snapshot.id,
+                agent_type: snapshot.agent_type,
+                task: snapshot.context.task_description,
+                paused_at: snapshot.paused_at,
+            })
+            .collect())
+    }
+    #[cfg(not(feature = "commercial"))]
+    {
+        Ok(vec![])
+    }
+}
@@ -8,9 +8,11 @@
 
 use tauri::State;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
 use std::collections::HashMap;
+use crate::fs_retry::{retry_io, FsRetryRecord};
+use crate::commit_risk::{analyze_changes, review_with_llm, ProposedChange, RiskReport};
 
 // ============================================================================
 // 类型定义
@@ -57,11 +59,23 @@ pub struct AtomicWriteResult {
     pub applied_files: Vec<String>,
     pub conflicts: Vec<String>,
     pub errors: Vec<String>,
+    /// v0.3.4: 因文件被占用而发生过重试的操作
+    #[serde(default)]
+    pub retries: Vec<FsRetryRecord>,
+    /// 提交失败后恢复到提交前状态的文件
+    #[serde(default)]
+    pub rolled_back: Vec<String>,
+    /// v0.3.4: 提交前的风险评估（高风险且未确认时不提交）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk: Option<RiskReport>,
 }
 
 // 全局会话存储
 pub type SessionStore = HashMap<String, AtomicWriteSession>;
 
+/// 会话临时目录前缀（位于系统临时目录下），启动清理据此识别遗留目录
+pub const ATOMIC_TEMP_PREFIX: &str = "ifainew-atomic-";
+
 // ============================================================================
 // 内部辅助函数（供测试和 Tauri 命令使用）
 // ============================================================================
@@ -72,7 +86,7 @@ pub fn atomic_write_start_internal(
 ) -> Result<String, String> {
     let session_id = uuid::Uuid::new_v4().to_string();
     let temp_dir = std::env::temp_dir()
-        .join(format!("ifainew-atomic-{}", session_id));
+        .join(format!("{}{}", ATOMIC_TEMP_PREFIX, session_id));
 
     // 创建临时目录
     fs::create_dir_all(&temp_dir)
@@ -167,7 +181,61 @@ pub fn atomic_write_detect_conflicts_internal(
     Ok(conflicts)
 }
 
+/// 提交中已生效的单个操作（回滚时按相反顺序撤销）
+struct AppliedOperation {
+    path: PathBuf,
+    display: String,
+}
+
+/// 与目标文件同目录的暂存文件名，保证 rename 在同一文件系统内完成
+fn staging_path(path: &Path, session_id: &str, index: usize) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let short_id: String = session_id.chars().take(8).collect();
+    path.with_file_name(format!(".{}.ifai-tmp-{}-{}", name, short_id, index))
+}
+
+/// `dir` 及其尚不存在的上级目录（由深到浅），回滚时据此删除提交中新建的目录
+fn missing_dirs(dir: &Path) -> Vec<PathBuf> {
+    dir.ancestors()
+        .take_while(|d| !d.as_os_str().is_empty() && !d.exists())
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// 撤销已生效的操作：存在备份的文件写回原内容，新建的文件删除；返回已恢复的文件
+fn restore_backups(
+    applied: &[AppliedOperation],
+    backups: &HashMap<PathBuf, Option<Vec<u8>>>,
+    errors: &mut Vec<String>,
+    retries: &mut Vec<FsRetryRecord>,
+) -> Vec<String> {
+    let mut rolled_back: Vec<String> = Vec::new();
+    for operation in applied.iter().rev() {
+        if rolled_back.contains(&operation.display) {
+            continue;
+        }
+        let path = &operation.path;
+        let (result, retry) = match backups.get(path) {
+            Some(Some(original)) => retry_io("restore", path, || fs::write(path, original)),
+            _ => retry_io("remove_file", path, || match fs::remove_file(path) {
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                other => other,
+            }),
+        };
+        retries.extend(retry);
+        match result {
+            Ok(()) => rolled_back.push(operation.display.clone()),
+            Err(e) => errors.push(format!("Rollback failed for {}: {}", operation.display, e)),
+        }
+    }
+    rolled_back
+}
+
 /// 内部函数：提交原子写入会话
+///
+/// 先把所有新内容写入目标旁的暂存文件，再依次 rename 到位并执行删除。
+/// 任一步骤失败时撤销已生效的操作（恢复备份、删除新建的文件与目录），
+/// 结果中 `rolled_back` 列出被恢复的文件，项目不会停留在部分更新的状态。
 pub fn atomic_write_commit_internal(
     sessions: &std::sync::Mutex<SessionStore>,
     session_id: String,
@@ -176,92 +244,155 @@ pub fn atomic_write_commit_internal(
         .map_err(|e| format!("Failed to acquire lock: {}", e))?;
     let session = store.remove(&session_id)
         .ok_or_else(|| format!("Session not found: {}", session_id))?;
+    drop(store);
 
-    let mut applied_files = Vec::new();
     let conflicts = Vec::new();
     let mut errors = Vec::new();
+    let mut retries = Vec::new();
 
-    // 创建备份
-    let mut backups: HashMap<PathBuf, String> = HashMap::new();
+    // 原始内容备份（`None` 表示提交前文件不存在），同一文件只保留第一次的备份
+    let mut backups: HashMap<PathBuf, Option<Vec<u8>>> = HashMap::new();
+    // 暂存文件：(暂存路径, 目标路径)，Delete 操作没有暂存文件
+    let mut staged: Vec<(Option<PathBuf>, usize)> = Vec::new();
+    let mut created_dirs: Vec<PathBuf> = Vec::new();
 
-    for operation in &session.operations {
+    // 阶段一：备份并写入暂存文件，此时项目文件尚未改动
+    for (index, operation) in session.operations.iter().enumerate() {
         let path = PathBuf::from(&operation.path);
 
-        match &operation.op_type {
-            FileOperationType::Create => {
-                if let Some(content) = &operation.content {
-                    // 确保目录存在
-                    if let Some(parent) = path.parent() {
-                        fs::create_dir_all(parent)
-                            .map_err(|e| format!("Failed to create dir: {}", e))?;
+        if !backups.contains_key(&path) {
+            let original = if path.is_file() {
+                match fs::read(&path) {
+                    Ok(bytes) => Some(bytes),
+                    Err(e) => {
+                        errors.push(format!("{}: failed to backup: {}", operation.path, e));
+                        break;
                     }
-
-                    fs::write(&path, content)
-                        .map_err(|e| {
-                            errors.push(format!("{}: {}", operation.path, e));
-                            e
-                        })
-                        .ok();
-
-                    applied_files.push(operation.path.clone());
                 }
-            }
+            } else {
+                None
+            };
+            backups.insert(path.clone(), original);
+        }
 
-            FileOperationType::Update => {
-                // 创建备份
+        let content = match &operation.op_type {
+            FileOperationType::Create | FileOperationType::Update => match &operation.content {
+                Some(content) => content,
+                None => continue,
+            },
+            FileOperationType::Delete => {
                 if path.exists() {
-                    let backup_content = fs::read_to_string(&path)
-                        .map_err(|e| format!("Failed to backup: {}", e))?;
-                    backups.insert(path.clone(), backup_content);
+                    staged.push((None, index));
                 }
+                continue;
+            }
+        };
+
+        // 确保目录存在
+        if let Some(parent) = path.parent() {
+            let missing = missing_dirs(parent);
+            let (result, retry) = retry_io("create_dir_all", parent, || fs::create_dir_all(parent));
+            retries.extend(retry);
+            if let Err(e) = result {
+                errors.push(format!("{}: failed to create dir: {}", operation.path, e));
+                break;
+            }
+            created_dirs.extend(missing);
+        }
 
-                if let Some(content) = &operation.content {
-                    fs::write(&path, content)
-                        .map_err(|e| {
-                            errors.push(format!("{}: {}", operation.path, e));
-                            e
-                        })
-                        .ok();
+        let temp = staging_path(&path, &session_id, index);
+        let (result, retry) = retry_io("write", &temp, || fs::write(&temp, content));
+        retries.extend(retry);
+        if let Err(e) = result {
+            errors.push(format!("{}: {}", operation.path, e));
+            break;
+        }
+        staged.push((Some(temp), index));
+    }
 
-                    applied_files.push(operation.path.clone());
-                }
+    // 阶段二：rename 暂存文件到位、删除文件
+    let mut applied: Vec<AppliedOperation> = Vec::new();
+    if errors.is_empty() {
+        for (temp, index) in &staged {
+            let operation = &session.operations[*index];
+            let path = PathBuf::from(&operation.path);
+            let (result, retry) = match temp {
+                Some(temp) => retry_io("rename", &path, || fs::rename(temp, &path)),
+                None => retry_io("remove_file", &path, || fs::remove_file(&path)),
+            };
+            retries.extend(retry);
+            if let Err(e) = result {
+                errors.push(format!("{}: {}", operation.path, e));
+                break;
             }
+            applied.push(AppliedOperation { path, display: operation.path.clone() });
+        }
+    }
 
-            FileOperationType::Delete => {
-                if path.exists() {
-                    // 创建备份
-                    let backup_content = fs::read_to_string(&path)
-                        .map_err(|e| format!("Failed to backup: {}", e))?;
-                    backups.insert(path.clone(), backup_content);
-
-                    fs::remove_file(&path)
-                        .map_err(|e| {
-                            errors.push(format!("{}: {}", operation.path, e));
-                            e
-                        })
-                        .ok();
-
-                    applied_files.push(operation.path.clone());
-                }
-            }
+    // 失败时撤销已生效的操作
+    let mut rolled_back = Vec::new();
+    if !errors.is_empty() {
+        rolled_back = restore_backups(&applied, &backups, &mut errors, &mut retries);
+        created_dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+        for dir in &created_dirs {
+            fs::remove_dir(dir).ok();
         }
+        println!(
+            "[AtomicWrite] Commit {} failed, rolled back {} file(s): {}",
+            session_id,
+            rolled_back.len(),
+            errors.join("; ")
+        );
     }
 
-    // 清理临时目录
+    // 清理未 rename 的暂存文件与会话临时目录
+    for temp in staged.iter().filter_map(|(temp, _)| temp.as_ref()) {
+        if temp.exists() {
+            fs::remove_file(temp).ok();
+        }
+    }
     let temp_path = PathBuf::from(&session.temp_dir);
     if temp_path.exists() {
         fs::remove_dir_all(temp_path).ok();
     }
 
+    let success = errors.is_empty();
     Ok(AtomicWriteResult {
         session_id,
-        success: errors.is_empty(),
-        applied_files,
+        success,
+        applied_files: if success { applied.into_iter().map(|a| a.display).collect() } else { Vec::new() },
         conflicts,
         errors,
+        retries,
+        rolled_back,
+        risk: None,
     })
 }
 
+/// 内部函数：会话中的修改（用于风险评估）
+pub fn session_changes(
+    sessions: &std::sync::Mutex<SessionStore>,
+    session_id: &str,
+) -> Result<Vec<ProposedChange>, String> {
+    let operations = {
+        let store = sessions.lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+        store.get(session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?
+            .operations
+            .clone()
+    };
+
+    Ok(operations.into_iter().map(|op| {
+        let on_disk = fs::read_to_string(&op.path).ok();
+        match op.op_type {
+            FileOperationType::Create => ProposedChange { path: op.path, old_content: on_disk, new_content: op.content },
+            FileOperationType::Update => ProposedChange { path: op.path, old_content: op.original_content.or(on_disk), new_content: op.content },
+            FileOperationType::Delete => ProposedChange { path: op.path, old_content: on_disk, new_content: None },
+        }
+    }).collect())
+}
+
 /// 内部函数：回滚原子写入会话
 pub fn atomic_write_rollback_internal(
     sessions: &std::sync::Mutex<SessionStore>,
@@ -320,12 +451,39 @@ pub fn atomic_write_detect_conflicts(
 }
 
 /// 提交原子写入会话
+///
+/// v0.3.4: 提交前做启发式风险评估，结果随提交结果返回；
+/// 高风险且未传 `acknowledge_risk` 时不提交（会话保留），由前端确认后再次提交
 #[tauri::command]
 pub fn atomic_write_commit(
     sessions: State<std::sync::Mutex<SessionStore>>,
+    symbol_index: State<'_, std::sync::Arc<std::sync::Mutex<crate::commands::symbol_commands::SymbolIndexState>>>,
     session_id: String,
+    acknowledge_risk: Option<bool>,
 ) -> Result<AtomicWriteResult, String> {
-    atomic_write_commit_internal(&sessions, session_id)
+    let changes = session_changes(&sessions, &session_id)?;
+    let risk = {
+        let index = symbol_index.lock().ok();
+        analyze_changes(&changes, index.as_deref())
+    };
+    println!("[AtomicWrite] Risk for session {}: {} ({})", session_id, risk.score, risk.level);
+
+    if risk.level == "high" && !acknowledge_risk.unwrap_or(false) {
+        return Ok(AtomicWriteResult {
+            session_id,
+            success: false,
+            applied_files: Vec::new(),
+            conflicts: Vec::new(),
+            errors: vec![format!("High-risk change (score {}), confirmation required", risk.score)],
+            retries: Vec::new(),
+            rolled_back: Vec::new(),
+            risk: Some(risk),
+        });
+    }
+
+    let mut result = atomic_write_commit_internal(&sessions, session_id)?;
+    result.risk = Some(risk);
+    Ok(result)
 }
 
 /// 回滚原子写入会话
@@ -351,6 +509,29 @@ pub fn atomic_write_get_session(
     Ok(session.clone())
 }
 
+/// v0.3.4: 提交前分析会话中修改的风险
+///
+/// 传入 `provider_config` 时追加 LLM 快速审查。
+#[tauri::command]
+pub async fn atomic_write_analyze_risk(
+    sessions: State<'_, std::sync::Mutex<SessionStore>>,
+    symbol_index: State<'_, std::sync::Arc<std::sync::Mutex<crate::commands::symbol_commands::SymbolIndexState>>>,
+    session_id: String,
+    provider_config: Option<crate::core_traits::ai::AIProviderConfig>,
+) -> Result<RiskReport, String> {
+    let changes = session_changes(&sessions, &session_id)?;
+    let report = {
+        let index = symbol_index.lock().ok();
+        analyze_changes(&changes, index.as_deref())
+    };
+    let report = match provider_config {
+        Some(config) => review_with_llm(&config, &changes, report).await,
+        None => report,
+    };
+    println!("[AtomicWrite] Risk for session {}: {} ({})", session_id, report.score, report.level);
+    Ok(report)
+}
+
 /// 计算文件哈希
 #[tauri::command]
 pub fn atomic_file_hash(path: String) -> Result<String, String> {
@@ -384,6 +565,7 @@ pub fn atomic_check_conflict(
  * 3. 冲突检测：文件已被修改，检测到冲突
  * 4. 更新/删除/混合操作测试
  * 5. 会话隔离测试
+ * 6. 部分失败时自动回滚
  */
 #[cfg(test)]
 mod tests {
@@ -801,4 +983,65 @@ mod tests {
 
         cleanup_test_dir(&test_dir);
     }
+
+    /// CMP-001-9: 部分失败回滚测试 - 后续操作失败时恢复已写入的文件
+    #[test]
+    fn test_atomic_write_commit_rolls_back_on_failure() {
+        let test_dir = setup_test_dir();
+        let store = std::sync::Mutex::new(create_test_store());
+
+        let file_to_update = test_dir.join("update.txt");
+        let file_to_delete = test_dir.join("delete.txt");
+        fs::write(&file_to_update, "Original").unwrap();
+        fs::write(&file_to_delete, "Keep me").unwrap();
+        let file_to_create = test_dir.join("new").join("create.txt");
+        // 目标是非空目录，rename 会失败
+        let blocked = test_dir.join("blocked");
+        fs::create_dir_all(blocked.join("inner")).unwrap();
+
+        let session_id = atomic_write_start_internal(&store).unwrap();
+        let operations = [
+            (&file_to_create, FileOperationType::Create, Some("New file")),
+            (&file_to_update, FileOperationType::Update, Some("Updated")),
+            (&file_to_delete, FileOperationType::Delete, None),
+            (&blocked, FileOperationType::Create, Some("Never written")),
+        ];
+        for (path, op_type, content) in operations {
+            atomic_write_add_operation_internal(
+                &store,
+                session_id.clone(),
+                FileOperationRequest {
+                    path: path.to_string_lossy().to_string(),
+                    op_type,
+                    content: content.map(str::to_string),
+                    original_content: None,
+                }
+            ).unwrap();
+        }
+
+        let result = atomic_write_commit_internal(&store, session_id.clone()).unwrap();
+
+        assert!(!result.success);
+        assert!(result.applied_files.is_empty());
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].contains("blocked"));
+        assert_eq!(result.rolled_back.len(), 3);
+        assert!(result.rolled_back.contains(&file_to_update.to_string_lossy().to_string()));
+
+        // 项目恢复到提交前的状态
+        assert_eq!(fs::read_to_string(&file_to_update).unwrap(), "Original");
+        assert_eq!(fs::read_to_string(&file_to_delete).unwrap(), "Keep me");
+        assert!(!file_to_create.exists());
+        assert!(!test_dir.join("new").exists());
+        assert!(blocked.join("inner").is_dir());
+
+        // 不残留暂存文件
+        let leftovers: Vec<_> = fs::read_dir(&test_dir).unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().contains(".ifai-tmp-"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        cleanup_test_dir(&test_dir);
+    }
 }
@@ -84,6 +84,12 @@ pub async fn execute_bash_command(
     timeout_ms: Option<u64>,
     env_vars: Option<HashMap<String, String>>,
 ) -> Result<BashResult, String> {
+    // v0.3.4: 工作流录制
+    super::workflow_commands::record_step(
+        super::workflow_commands::WorkflowAction::Bash,
+        serde_json::json!({ "command": command, "working_dir": working_dir, "timeout_ms": timeout_ms }),
+    );
+
     let start_time = Instant::now();
     let timeout_duration = Duration::from_millis(timeout_ms.unwrap_or(30000));
     const MAX_OUTPUT_SIZE: u64 = 10 * 1024 * 1024; // 10MB limit
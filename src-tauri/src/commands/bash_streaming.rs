@@ -5,7 +5,8 @@ use tokio::process::Command;
 use tokio::time::{timeout, Duration};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use std::time::Instant;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
+use crate::background_processes::{BackgroundProcessRegistry, ProcessLogLine};
 
 /// 检测输出是否包含启动成功的标志
 ///
@@ -71,10 +72,32 @@ fn detect_startup_success(stdout_lines: &[String], stderr_lines: &[String]) -> b
     false
 }
 
+/// 不传给 Agent 命令的环境变量：凭据类变量，以及可向子进程注入代码的加载器变量
+const SECRET_ENV_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD", "PASSWD", "CREDENTIAL", "COOKIE"];
+const INJECTION_ENV_VARS: &[&str] = &["LD_PRELOAD", "LD_LIBRARY_PATH", "LD_AUDIT", "BASH_ENV", "ENV", "PROMPT_COMMAND", "SHELLOPTS", "PS4", "NODE_OPTIONS", "PYTHONSTARTUP"];
+
+fn env_var_allowed(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    !upper.starts_with("DYLD_")
+        && !INJECTION_ENV_VARS.contains(&upper.as_str())
+        && !SECRET_ENV_MARKERS.iter().any(|marker| upper.contains(marker))
+}
+
+/// 过滤后的环境变量：继承的变量与调用方追加的变量都去掉凭据和注入类变量
+pub fn filter_env(
+    inherited: impl IntoIterator<Item = (String, String)>,
+    extra: Option<HashMap<String, String>>,
+) -> HashMap<String, String> {
+    inherited.into_iter()
+        .chain(extra.unwrap_or_default())
+        .filter(|(name, _)| env_var_allowed(name))
+        .collect()
+}
+
 /// 流式输出事件数据
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BashStreamEvent {
-    /// 事件类型：output（输出行）、error（错误行）、complete（完成）
+    /// 事件类型：output（输出行）、error（错误行）、complete（完成）、background（转入后台，内容为进程 id）
     pub event_type: String,
     /// 输出内容
     pub content: String,
@@ -103,6 +126,9 @@ pub struct BashStreamResult {
     /// ⚡️ FIX: 添加标准错误内容
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stderr: Option<String>,
+    /// v0.3.4: 仍在后台运行时的进程 id（见 `list_background_processes`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_id: Option<String>,
 }
 
 /// 流式执行 Bash 命令
@@ -112,6 +138,7 @@ pub struct BashStreamResult {
 /// - `working_dir`: 工作目录
 /// - `timeout_ms`: 超时时间（毫秒）
 /// - `env_vars`: 环境变量
+/// - `inherit_env`: 是否继承应用进程的环境变量（为 false 时只使用 `env_vars`）
 /// - `event_id`: 事件 ID，用于前端监听
 /// - `throttle_lines`: 节流行数，每 N 行发送一次事件（默认 10）
 /// - `app_handle`: Tauri 应用句柄
@@ -123,6 +150,7 @@ pub async fn execute_bash_command_streaming(
     working_dir: Option<String>,
     timeout_ms: Option<u64>,
     env_vars: Option<HashMap<String, String>>,
+    inherit_env: bool,
     event_id: String,
     throttle_lines: Option<usize>,
     app_handle: AppHandle,
@@ -152,6 +180,9 @@ pub async fn execute_bash_command_streaming(
         }
     }
 
+    if !inherit_env {
+        cmd.env_clear();
+    }
     if let Some(envs) = &env_vars {
         cmd.envs(envs);
     }
@@ -222,13 +253,11 @@ pub async fn execute_bash_command_streaming(
                                     line_count,
                                 })?;
 
-                                // 🔥 修复：放弃 child 所有权，让进程真正在后台运行
-                                // 使用 forget() 来防止进程被 drop 时终止
-                                std::mem::forget(child);
-                                println!("[Bash Streaming] ✅ Detected startup success, forgot child process to keep it running");
+                                // v0.3.4: 进程交给 BackgroundProcessRegistry 托管，继续在后台运行
+                                println!("[Bash Streaming] ✅ Detected startup success, keeping child process running in background");
 
                                 // 提前结束循环，返回成功状态
-                                return Ok::<_, String>(true); // true 表示检测到启动成功
+                                return Ok::<_, String>(None); // None 表示检测到启动成功
                             }
 
                             // 达到节流阈值时发送
@@ -291,13 +320,11 @@ pub async fn execute_bash_command_streaming(
                                     line_count,
                                 })?;
 
-                                // 🔥 修复：放弃 child 所有权，让进程真正在后台运行
-                                // 使用 forget() 来防止进程被 drop 时终止
-                                std::mem::forget(child);
-                                println!("[Bash Streaming] ✅ Detected startup success, forgot child process to keep it running");
+                                // v0.3.4: 进程交给 BackgroundProcessRegistry 托管，继续在后台运行
+                                println!("[Bash Streaming] ✅ Detected startup success, keeping child process running in background");
 
                                 // 提前结束循环，返回成功状态
-                                return Ok::<_, String>(true); // true 表示检测到启动成功
+                                return Ok::<_, String>(None); // None 表示检测到启动成功
                             }
 
                             if buffer.len() >= throttle {
@@ -339,8 +366,8 @@ pub async fn execute_bash_command_streaming(
         // 等待进程结束
         let status = child.wait().await.map_err(|e| e.to_string())?;
 
-        // 返回 false 表示没有提前检测到启动成功，进程正常结束
-        Ok::<_, String>(false)
+        // 返回退出码表示没有提前检测到启动成功，进程已结束（被信号终止时为 -1）
+        Ok::<_, String>(Some(status.code().unwrap_or(-1)))
     };
 
     // 执行流式读取（带超时）
@@ -349,23 +376,19 @@ pub async fn execute_bash_command_streaming(
 
     // 发送完成事件并确定结果
     let (exit_code, success, timed_out) = match result {
-        Ok(Ok(detected_startup)) => {
-            // detected_startup: true 表示检测到启动成功并提前结束
-            if detected_startup {
-                // 检测到启动成功，返回成功状态
-                (0, true, false) // exit_code: 0, success: true, timed_out: false
-            } else {
-                // 进程正常结束（没有提前检测到启动成功）
-                // 这里需要重新获取进程状态，但我们没有保存它
-                // 由于进程已经正常结束，我们可以假设它是成功的
-                emit_event(&app_handle, &event_id, BashStreamEvent {
-                    event_type: "complete".to_string(),
-                    content: "Command completed (process exited normally)".to_string(),
-                    is_stderr: false,
-                    line_count,
-                })?;
-                (0, true, false)
-            }
+        Ok(Ok(None)) => {
+            // 检测到启动成功并提前结束，返回成功状态
+            (0, true, false) // exit_code: 0, success: true, timed_out: false
+        }
+        Ok(Ok(Some(code))) => {
+            // 进程结束（没有提前检测到启动成功），按退出码判断是否成功
+            emit_event(&app_handle, &event_id, BashStreamEvent {
+                event_type: "complete".to_string(),
+                content: format!("Command completed (exit code {})", code),
+                is_stderr: false,
+                line_count,
+            })?;
+            (code, code == 0, false)
         }
         Ok(Err(e)) => {
             emit_event(&app_handle, &event_id, BashStreamEvent {
@@ -388,6 +411,25 @@ pub async fn execute_bash_command_streaming(
         }
     };
 
+    // v0.3.4: 仍在运行的进程（已启动的服务、超时的命令）交给后台进程管理，可随时查看日志或停止
+    let background_id = match child.try_wait() {
+        Ok(None) => {
+            let output = stdout_buffer.iter().map(|line| ProcessLogLine { content: line.clone(), is_stderr: false })
+                .chain(stderr_buffer.iter().map(|line| ProcessLogLine { content: line.clone(), is_stderr: true }))
+                .collect();
+            let id = app_handle.state::<BackgroundProcessRegistry>()
+                .adopt(&command, working_dir.clone(), child, stdout_reader, stderr_reader, output);
+            emit_event(&app_handle, &event_id, BashStreamEvent {
+                event_type: "background".to_string(),
+                content: id.clone(),
+                is_stderr: false,
+                line_count,
+            })?;
+            Some(id)
+        }
+        _ => None,
+    };
+
     Ok(BashStreamResult {
         exit_code,
         total_lines: line_count,
@@ -405,6 +447,7 @@ pub async fn execute_bash_command_streaming(
         } else {
             Some(stderr_buffer.join("\n"))
         },
+        background_id,
     })
 }
 
@@ -448,9 +491,35 @@ pub async fn bash_execute_streaming(
         working_dir,
         timeout_ms,
         env_vars,
+        true,
         event_id,
         throttle_lines,
         app_handle,
     )
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_env() {
+        let inherited = vec![
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("HOME".to_string(), "/home/dev".to_string()),
+            ("OPENAI_API_KEY".to_string(), "sk-test".to_string()),
+            ("GITHUB_TOKEN".to_string(), "ghp".to_string()),
+            ("DYLD_INSERT_LIBRARIES".to_string(), "x.dylib".to_string()),
+        ];
+        let extra = HashMap::from([
+            ("RUST_BACKTRACE".to_string(), "1".to_string()),
+            ("LD_PRELOAD".to_string(), "evil.so".to_string()),
+            ("db_password".to_string(), "hunter2".to_string()),
+        ]);
+        let env = filter_env(inherited, Some(extra));
+        let mut names: Vec<&str> = env.keys().map(|k| k.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["HOME", "PATH", "RUST_BACKTRACE"]);
+    }
+}
@@ -1,16 +1,30 @@
 use crate::AppState;
 use crate::core_traits::rag::RagResult;
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
 
 // For optimized directory scanning
 use walkdir::WalkDir;
 
+/// 提交项目索引任务（后台执行，进度见任务队列；同一项目已有待执行的索引任务时不重复提交）
 #[tauri::command]
 pub async fn init_rag_index(
     _app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     root_path: String
 ) -> Result<(), String> {
-    state.rag_service.index_project(&root_path).await
+    if cfg!(all(feature = "commercial", feature = "fastembed")) {
+        crate::job_queue::enqueue_unique(
+            "rag_index",
+            serde_json::json!({ "root": root_path }),
+            crate::job_queue::JobPriority::Normal,
+            3,
+        )
+        .map(|_| ())
+    } else {
+        // 不支持索引的版本直接返回服务的错误，不提交注定失败的任务
+        state.rag_service.index_project(&root_path).await
+    }
 }
 
 #[tauri::command]
@@ -37,7 +51,76 @@ pub async fn build_context(
     query: String,
     root_path: String
 ) -> Result<RagResult, String> {
-    state.rag_service.retrieve_context(&query, &root_path).await
+    crate::vector_index::retrieve_context(state.rag_service.as_ref(), &query, &root_path).await
+}
+
+// ============================================================================
+// Path Sandbox - Agent 工具路径限制在项目根目录内
+// ============================================================================
+
+/// 解析路径：已存在的部分交给系统规范化（处理符号链接和 `..`），不存在的部分按字面处理
+fn resolve_path(path: &Path) -> Result<PathBuf, String> {
+    let existing = path.ancestors()
+        .find(|p| !p.as_os_str().is_empty() && p.exists())
+        .ok_or_else(|| format!("Cannot resolve path: {}", path.display()))?;
+    let mut resolved = std::fs::canonicalize(existing)
+        .map_err(|e| format!("Cannot resolve path {}: {}", existing.display(), e))?;
+
+    let rest = path.strip_prefix(existing).unwrap_or(Path::new(""));
+    for component in rest.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::ParentDir => { resolved.pop(); }
+            _ => {}
+        }
+    }
+    Ok(resolved)
+}
+
+/// 项目配置中允许访问的项目外路径
+fn path_allowlist(root: &Path, root_path: &str) -> Vec<PathBuf> {
+    crate::project_config::load_project_config_sync(root_path)
+        .and_then(|c| c.path_allowlist)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|entry| resolve_path(&root.join(entry)).ok())
+        .collect()
+}
+
+/// 记录被拒绝的越界访问到 `.ifai/security/path_rejections.jsonl`
+fn audit_rejection(root: &Path, operation: &str, rel_path: &str, resolved: &Path) {
+    eprintln!("[PathSandbox] Rejected {} of '{}' (resolves to {}) outside {}",
+        operation, rel_path, resolved.display(), root.display());
+
+    let entry = serde_json::json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "operation": operation,
+        "relPath": rel_path,
+        "resolved": resolved.to_string_lossy(),
+    });
+    let path = root.join(".ifai").join("security").join("path_rejections.jsonl");
+    let written = path.parent()
+        .map(std::fs::create_dir_all)
+        .unwrap_or(Ok(()))
+        .and_then(|_| std::fs::OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut file| writeln!(file, "{}", entry));
+    if let Err(e) = written {
+        eprintln!("[PathSandbox] Failed to write audit log: {}", e);
+    }
+}
+
+/// 校验 `rel_path` 位于 `root_path` 内（或在项目白名单中），返回规范化后的目标路径
+pub fn ensure_in_root(root_path: &str, rel_path: &str, operation: &str) -> Result<PathBuf, String> {
+    let root = std::fs::canonicalize(root_path)
+        .map_err(|e| format!("Invalid project root {}: {}", root_path, e))?;
+    let target = resolve_path(&root.join(rel_path))?;
+
+    if target.starts_with(&root) || path_allowlist(&root, root_path).iter().any(|allowed| target.starts_with(allowed)) {
+        return Ok(target);
+    }
+
+    audit_rejection(&root, operation, rel_path, &target);
+    Err(format!("Access denied: '{}' is outside the project root", rel_path))
 }
 
 // FS / Agent Tools Wrappers
@@ -45,8 +128,11 @@ pub async fn build_context(
 
 #[tauri::command]
 pub async fn agent_write_file(root_path: String, rel_path: String, content: String) -> Result<String, String> {
+    let path = ensure_in_root(&root_path, &rel_path, "write")?;
+
     #[cfg(feature = "commercial")]
     {
+        let _ = path;
         // Call the core library which now returns WriteFileResult
         let result = ifainew_core::agent::agent_write_file(root_path, rel_path, content).await?;
 
@@ -57,8 +143,6 @@ pub async fn agent_write_file(root_path: String, rel_path: String, content: Stri
     #[cfg(not(feature = "commercial"))]
     {
         // Community edition: provide basic implementation with diff data
-        let path = std::path::Path::new(&root_path).join(&rel_path);
-
         // Read original content for diff (before writing)
         let original_content = if path.exists() {
             Some(tokio::fs::read_to_string(&path).await.unwrap_or_default())
@@ -71,8 +155,9 @@ pub async fn agent_write_file(root_path: String, rel_path: String, content: Stri
             let _ = tokio::fs::create_dir_all(parent).await;
         }
 
-        // Write new content
-        tokio::fs::write(&path, &content).await.map_err(|e| e.to_string())?;
+        // Write new content (retry on transient file locks, e.g. antivirus on Windows)
+        let (write_result, retry) = crate::fs_retry::retry_io_async("write", &path, || tokio::fs::write(&path, &content)).await;
+        write_result.map_err(|e| e.to_string())?;
 
         // Get timestamp
         use std::time::{SystemTime, UNIX_EPOCH};
@@ -88,7 +173,8 @@ pub async fn agent_write_file(root_path: String, rel_path: String, content: Stri
             "originalContent": original_content,
             "newContent": content,
             "filePath": rel_path,
-            "timestamp": timestamp
+            "timestamp": timestamp,
+            "retries": retry
         });
 
         serde_json::to_string(&result)
@@ -96,28 +182,87 @@ pub async fn agent_write_file(root_path: String, rel_path: String, content: Stri
     }
 }
 
+/// 局部编辑文件：应用 search/replace 块或 unified diff，返回成功/失败的块
+///
+/// 至少一个块成功时写回文件；失败的块附带原因，供模型修正后重试
+#[tauri::command]
+pub async fn agent_edit_file(
+    root_path: String,
+    rel_path: String,
+    edits: Option<Vec<crate::file_edit::EditBlock>>,
+    diff: Option<String>,
+) -> Result<String, String> {
+    let path = ensure_in_root(&root_path, &rel_path, "edit")?;
+    let blocks = crate::file_edit::collect_blocks(edits, diff.as_deref())?;
+
+    let original_content = if path.exists() {
+        tokio::fs::read_to_string(&path).await.map_err(|e| format!("Failed to read {}: {}", rel_path, e))?
+    } else {
+        String::new()
+    };
+    let outcome = crate::file_edit::apply_edits(&original_content, &blocks);
+
+    let mut retries = None;
+    if !outcome.applied.is_empty() && outcome.content != original_content {
+        if let Some(parent) = path.parent() {
+            let _ = tokio::fs::create_dir_all(parent).await;
+        }
+        let (write_result, retry) = crate::fs_retry::retry_io_async("write", &path, || tokio::fs::write(&path, &outcome.content)).await;
+        write_result.map_err(|e| e.to_string())?;
+        retries = retry;
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let message = if outcome.failed.is_empty() {
+        format!("Applied {} edit(s)", outcome.applied.len())
+    } else {
+        format!("Applied {} of {} edit(s); {} failed", outcome.applied.len(), blocks.len(), outcome.failed.len())
+    };
+
+    let result = serde_json::json!({
+        "success": outcome.failed.is_empty(),
+        "message": message,
+        "applied": outcome.applied,
+        "failed": outcome.failed,
+        "originalContent": original_content,
+        "newContent": outcome.content,
+        "filePath": rel_path,
+        "timestamp": timestamp,
+        "retries": retries
+    });
+    serde_json::to_string(&result)
+        .map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
 #[tauri::command]
 pub async fn agent_read_file(root_path: String, rel_path: String) -> Result<String, String> {
+    let path = ensure_in_root(&root_path, &rel_path, "read")?;
+
     #[cfg(feature = "commercial")]
     {
+        let _ = path;
         return ifainew_core::agent::agent_read_file(root_path, rel_path).await;
     }
     #[cfg(not(feature = "commercial"))]
     {
-        let path = std::path::Path::new(&root_path).join(&rel_path);
         tokio::fs::read_to_string(&path).await.map_err(|e| e.to_string())
     }
 }
 
 #[tauri::command]
 pub async fn agent_list_dir(root_path: String, rel_path: String) -> Result<Vec<String>, String> {
+    let path = ensure_in_root(&root_path, &rel_path, "list")?;
+
     #[cfg(feature = "commercial")]
     {
+        let _ = path;
         return ifainew_core::agent::agent_list_dir(root_path, rel_path).await;
     }
     #[cfg(not(feature = "commercial"))]
     {
-        let path = std::path::Path::new(&root_path).join(&rel_path);
         let mut entries = Vec::new();
         let mut read_dir = tokio::fs::read_dir(&path).await.map_err(|e| e.to_string())?;
         while let Ok(Some(entry)) = read_dir.next_entry().await {
@@ -133,15 +278,21 @@ pub async fn agent_list_dir(root_path: String, rel_path: String) -> Result<Vec<S
 /// Used for rollback functionality to physically delete newly created files
 #[tauri::command]
 pub async fn agent_delete_file(root_path: String, rel_path: String) -> Result<String, String> {
+    let path = ensure_in_root(&root_path, &rel_path, "delete")?;
+
     #[cfg(feature = "commercial")]
     {
+        let _ = path;
         return ifainew_core::agent::agent_delete_file(root_path, rel_path).await;
     }
     #[cfg(not(feature = "commercial"))]
     {
-        let path = std::path::Path::new(&root_path).join(&rel_path);
-        tokio::fs::remove_file(&path).await.map_err(|e| e.to_string())?;
-        Ok(format!("File deleted: {}", rel_path))
+        let (result, retry) = crate::fs_retry::retry_io_async("remove_file", &path, || tokio::fs::remove_file(&path)).await;
+        result.map_err(|e| e.to_string())?;
+        match retry {
+            Some(r) => Ok(format!("File deleted: {} (after {} attempts)", rel_path, r.attempts)),
+            None => Ok(format!("File deleted: {}", rel_path)),
+        }
     }
 }
 
@@ -155,10 +306,13 @@ pub async fn agent_batch_read(root_path: String, paths: Vec<String>) -> Result<S
     let futures: Vec<_> = paths.into_iter().map(|rel_path| {
         let root = root_path.clone();
         async move {
-            let path = std::path::Path::new(&root).join(&rel_path);
+            let path = match ensure_in_root(&root, &rel_path, "read") {
+                Ok(path) => path,
+                Err(e) => return (rel_path, Err(e)),
+            };
             match tokio::fs::read_to_string(&path).await {
-                Ok(content) => (rel_path, Some(content)),
-                Err(e) => (rel_path, None::<String>),
+                Ok(content) => (rel_path, Ok(content)),
+                Err(_) => (rel_path, Err("File not found or cannot be read".to_string())),
             }
         }
     }).collect();
@@ -168,15 +322,15 @@ pub async fn agent_batch_read(root_path: String, paths: Vec<String>) -> Result<S
     // Build JSON response
     let json_results: Vec<serde_json::Value> = results.into_iter().map(|(path, content)| {
         match content {
-            Some(c) => json!({
+            Ok(c) => json!({
                 "path": path,
                 "status": "success",
                 "content": c
             }),
-            None => json!({
+            Err(e) => json!({
                 "path": path,
                 "status": "error",
-                "error": "File not found or cannot be read"
+                "error": e
             })
         }
     }).collect();
@@ -184,6 +338,67 @@ pub async fn agent_batch_read(root_path: String, paths: Vec<String>) -> Result<S
     serde_json::to_string(&json_results).map_err(|e| e.to_string())
 }
 
+/// Agent 默认/最大返回的匹配数
+const AGENT_GREP_DEFAULT_RESULTS: usize = 100;
+const AGENT_GREP_MAX_RESULTS: usize = 500;
+/// 单行片段的最大长度（字符）
+const AGENT_GREP_SNIPPET_CHARS: usize = 240;
+
+/// Regex search across project files for agents
+/// Returns capped JSON: matches with relative path, line and snippet
+#[tauri::command]
+pub async fn agent_grep(
+    root_path: String,
+    rel_path: Option<String>,
+    pattern: String,
+    include: Option<Vec<String>>,
+    exclude: Option<Vec<String>>,
+    case_insensitive: Option<bool>,
+    max_results: Option<usize>,
+) -> Result<String, String> {
+    if pattern.is_empty() {
+        return Err("Missing 'pattern' for search".to_string());
+    }
+    let rel_path = rel_path.unwrap_or_else(|| ".".to_string());
+    let search_root = ensure_in_root(&root_path, &rel_path, "search")?;
+    let limit = max_results.unwrap_or(AGENT_GREP_DEFAULT_RESULTS).clamp(1, AGENT_GREP_MAX_RESULTS);
+    // 多取一条用于判断是否被截断
+    let options = crate::search::GrepOptions {
+        include: include.unwrap_or_default(),
+        exclude: exclude.unwrap_or_default(),
+        case_insensitive: case_insensitive.unwrap_or(false),
+        max_results: limit + 1,
+        ..Default::default()
+    };
+
+    let search_dir = search_root.to_string_lossy().to_string();
+    let mut matches = tokio::task::spawn_blocking(move || crate::search::grep_search_with(&search_dir, &pattern, &options))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Search failed: {}", e))?;
+
+    let truncated = matches.len() > limit;
+    matches.truncate(limit);
+
+    let root = std::fs::canonicalize(&root_path).unwrap_or_else(|_| PathBuf::from(&root_path));
+    let results: Vec<serde_json::Value> = matches.iter()
+        .map(|m| {
+            let path = Path::new(&m.path);
+            let relative = path.strip_prefix(&root).unwrap_or(path).to_string_lossy().replace('\\', "/");
+            let line = m.content.trim_end();
+            let snippet: String = line.chars().take(AGENT_GREP_SNIPPET_CHARS).collect();
+            serde_json::json!({ "path": relative, "line": m.line_number, "snippet": snippet })
+        })
+        .collect();
+
+    let result = serde_json::json!({
+        "matches": results,
+        "count": results.len(),
+        "truncated": truncated
+    });
+    serde_json::to_string(&result).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
 /// Scan directory and return structured file tree
 /// Supports glob patterns and file limits
 #[tauri::command]
@@ -196,8 +411,21 @@ pub async fn agent_scan_directory(
 ) -> Result<String, String> {
     use serde_json::json;
     use glob::glob;
-    use std::path::Path;
 
+    ensure_in_root(&root_path, &rel_path, "scan")?;
+    let canonical_root = std::fs::canonicalize(&root_path)
+        .map_err(|e| format!("Invalid project root {}: {}", root_path, e))?;
+    // 模式同样不能越出项目根目录：拒绝绝对路径与 `..`
+    if let Some(p) = &pattern {
+        let pattern_path = Path::new(p);
+        let escapes = pattern_path.has_root()
+            || pattern_path.is_absolute()
+            || pattern_path.components().any(|c| matches!(c, Component::ParentDir | Component::Prefix(_)));
+        if escapes {
+            audit_rejection(&canonical_root, "scan", p, &Path::new(&root_path).join(p));
+            return Err(format!("Access denied: pattern '{}' must be relative to the project root without '..'", p));
+        }
+    }
     let base_path = Path::new(&root_path).join(&rel_path);
     let max_files = max_files.unwrap_or(500);
     let max_depth = max_depth.unwrap_or(10);
@@ -248,8 +476,8 @@ pub async fn agent_scan_directory(
     // Build glob pattern
     let glob_pattern = if let Some(p) = &pattern {
         // Use provided pattern (e.g., "**/*.ts")
-        if p.starts_with('/') || p.starts_with('.') {
-            // Absolute or relative pattern
+        if p.starts_with('.') {
+            // Pattern relative to the project root (e.g. "./src/*.ts", ".env*")
             Path::new(&root_path).join(p).to_string_lossy().to_string()
         } else if p == "**" {
             // Special case: "**" means all files recursively
@@ -267,6 +495,7 @@ pub async fn agent_scan_directory(
     };
 
     println!("[agent_scan_directory] glob_pattern = {}", glob_pattern);
+    let ifai_ignore = crate::ifai_ignore::IfaiIgnore::load(&root_path);
 
     let mut files: Vec<String> = Vec::new();
     let mut directories: Vec<String> = Vec::new();
@@ -282,8 +511,9 @@ pub async fn agent_scan_directory(
 
                 match entry {
                     Ok(path) => {
-                        // Skip if not inside root_path
-                        if !path.starts_with(&root_path) {
+                        // Skip if not inside root_path (after resolving symlinks)
+                        if !path.starts_with(&root_path)
+                            || !std::fs::canonicalize(&path).is_ok_and(|p| p.starts_with(&canonical_root)) {
                             continue;
                         }
 
@@ -301,7 +531,7 @@ pub async fn agent_scan_directory(
 
                         // Skip ignored paths
                         let is_dir = path.is_dir();
-                        if should_ignore_path(&rel, is_dir) {
+                        if should_ignore_path(&rel, is_dir) || ifai_ignore.is_ignored(&rel, is_dir) {
                             continue;
                         }
 
@@ -355,17 +585,12 @@ pub async fn agent_scan_directory_with_progress(
     max_files: Option<usize>
 ) -> Result<String, String> {
     use serde_json::json;
-    use std::path::Path;
     use std::collections::HashMap;
-    use tauri::Emitter;
-
-    #[derive(Clone, serde::Serialize)]
-    struct ScanStatus {
-        total: usize,
-        scanned: usize,
-        status: String,
-    }
+    use crate::events::{
+        DirectoryScanStatus as ScanStatus, ExploreProgress, ScanProgress, StreamEvent,
+    };
 
+    ensure_in_root(&root_path, &rel_path, "scan")?;
     let base_path = Path::new(&root_path).join(&rel_path);
     let max_files = max_files.unwrap_or(500);
     let max_depth = max_depth.unwrap_or(10);
@@ -381,6 +606,7 @@ pub async fn agent_scan_directory_with_progress(
     ];
 
     println!("[core_wrappers] Scan setup: depth={}, max_files={}", max_depth, max_files);
+    let ifai_ignore = crate::ifai_ignore::IfaiIgnore::load(&root_path);
 
     // STEP 1: First pass - count total directories for progress
     let total_directories = WalkDir::new(&base_path)
@@ -423,7 +649,7 @@ pub async fn agent_scan_directory_with_progress(
                 ancestor.file_name()
                     .and_then(|n| n.to_str())
                     .map_or(false, |name| ignore_dirs.contains(&name))
-            });
+            }) || ifai_ignore.is_ignored_path(path, entry.file_type().is_dir());
 
         if is_ignored {
             continue;
@@ -485,31 +711,18 @@ pub async fn agent_scan_directory_with_progress(
             files.push(full_rel.clone());
 
             // Emit per-file progress
-            let by_dir_serializable: HashMap<String, serde_json::Value> = by_directory
-                .iter()
-                .map(|(k, v)| {
-                    (k.clone(), json!({
-                        "total": v.total,
-                        "scanned": v.scanned,
-                        "status": v.status
-                    }))
-                })
-                .collect();
-
-            let progress = json!({
-                "type": "explore_progress",
-                "exploreProgress": {
-                    "phase": "scanning",
-                    "currentPath": file_dir,
-                    "currentFile": &full_rel,
-                    "progress": {
-                        "total": total_estimate,
-                        "scanned": dirs_scanned,
-                        "byDirectory": by_dir_serializable
-                    }
-                }
+            crate::agent_log::emit(app, event_id, &StreamEvent::ExploreProgress {
+                explore_progress: ExploreProgress {
+                    phase: "scanning".to_string(),
+                    current_path: Some(file_dir.to_string()),
+                    current_file: Some(full_rel.clone()),
+                    progress: ScanProgress {
+                        total: total_estimate,
+                        scanned: dirs_scanned,
+                        by_directory: by_directory.clone(),
+                    },
+                },
             });
-            let _ = app.emit(event_id, progress);
         }
 
         if files.len() >= max_files {
@@ -547,4 +760,83 @@ pub async fn agent_scan_directory_with_progress(
     });
 
     serde_json::to_string(&result).map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (PathBuf, PathBuf) {
+        let base = std::env::temp_dir().join(format!("ifai_sandbox_{}", uuid::Uuid::new_v4()));
+        let root = base.join("project");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(base.join("shared")).unwrap();
+        (base, root)
+    }
+
+    #[test]
+    fn test_ensure_in_root() {
+        let (base, root) = setup();
+        let root_str = root.to_string_lossy().to_string();
+
+        assert!(ensure_in_root(&root_str, "src/main.rs", "write").is_ok());
+        assert!(ensure_in_root(&root_str, "new/dir/../file.rs", "write").is_ok());
+        assert!(ensure_in_root(&root_str, ".", "list").is_ok());
+
+        assert!(ensure_in_root(&root_str, "../shared/a.txt", "write").is_err());
+        assert!(ensure_in_root(&root_str, "src/../../../etc/passwd", "read").is_err());
+        assert!(ensure_in_root(&root_str, "/etc/passwd", "read").is_err());
+        assert!(ensure_in_root(&root_str, "missing/../../../x", "write").is_err());
+
+        let audit = std::fs::read_to_string(root.join(".ifai/security/path_rejections.jsonl")).unwrap();
+        assert_eq!(audit.lines().count(), 4);
+        assert!(audit.contains(r#""operation":"write""#));
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn test_allowlist_from_project_config() {
+        let (base, root) = setup();
+        let root_str = root.to_string_lossy().to_string();
+        std::fs::create_dir_all(root.join(".ifai")).unwrap();
+        std::fs::write(root.join(".ifai/IFAI.md"), "---\npath_allowlist:\n  - ../shared\n---\n").unwrap();
+
+        assert!(ensure_in_root(&root_str, "../shared/a.txt", "write").is_ok());
+        assert!(ensure_in_root(&root_str, "../other/a.txt", "write").is_err());
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_escape_rejected() {
+        let (base, root) = setup();
+        let root_str = root.to_string_lossy().to_string();
+        std::os::unix::fs::symlink(base.join("shared"), root.join("link")).unwrap();
+
+        assert!(ensure_in_root(&root_str, "link/a.txt", "write").is_err());
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[tokio::test]
+    async fn test_scan_pattern_stays_in_root() {
+        let (base, root) = setup();
+        let root_str = root.to_string_lossy().to_string();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(base.join("shared/secret.txt"), "x").unwrap();
+
+        for pattern in ["../*", "../shared/*", "src/../../shared/*", "/etc/*"] {
+            let result = agent_scan_directory(root_str.clone(), ".".to_string(), Some(pattern.to_string()), None, None).await;
+            assert!(result.is_err(), "{}", pattern);
+        }
+        let audit = std::fs::read_to_string(root.join(".ifai/security/path_rejections.jsonl")).unwrap();
+        assert!(audit.contains(r#""operation":"scan""#));
+
+        let listed = agent_scan_directory(root_str.clone(), ".".to_string(), Some("**/*.rs".to_string()), None, None).await.unwrap();
+        assert!(listed.contains("src/main.rs"));
+        assert!(!listed.contains("secret.txt"));
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
@@ -118,28 +118,8 @@ pub fn parse_terminal_errors(
 
     #[cfg(not(feature = "commercial"))]
     {
-        // 社区版：提供基本的错误解析
-        use regex::Regex;
-
-        let re = Regex::new(r"(.+?):(\d+):(.+)?").unwrap();
-        let mut errors = Vec::new();
-
-        for line in output.lines() {
-            if let Some(caps) = re.captures(line) {
-                errors.push(ParsedErrorFrontend {
-                    code: "ERROR".to_string(),
-                    message: caps.get(3).map(|m| m.as_str().to_string()).unwrap_or_default(),
-                    file: caps.get(1).map(|m| m.as_str().to_string()).unwrap_or_default(),
-                    line: caps.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                    column: None,
-                    level: "Error".to_string(),
-                    language: "Generic".to_string(),
-                    raw_line: line.to_string(),
-                });
-            }
-        }
-
-        Ok(errors)
+        // 社区版：按语言规则解析（rustc、tsc、ESLint、Python、Go，其余走通用规则）
+        Ok(crate::community::error_parser::parse_output(&output))
     }
 }
 
@@ -264,24 +244,8 @@ pub fn quick_parse_error_line(
 
     #[cfg(not(feature = "commercial"))]
     {
-        // 社区版：使用基本的正则解析
-        use regex::Regex;
-        let re = Regex::new(r"(.+?):(\d+):(.+)?").unwrap();
-
-        if let Some(caps) = re.captures(&line) {
-            Ok(Some(ParsedErrorFrontend {
-                code: "ERROR".to_string(),
-                message: caps.get(3).map(|m| m.as_str().to_string()).unwrap_or_default(),
-                file: caps.get(1).map(|m| m.as_str().to_string()).unwrap_or_default(),
-                line: caps.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                column: None,
-                level: "Error".to_string(),
-                language: "Generic".to_string(),
-                raw_line: line,
-            }))
-        } else {
-            Ok(None)
-        }
+        // 社区版：与 parse_terminal_errors 使用同一套规则
+        Ok(crate::community::error_parser::parse_output(&line).into_iter().next())
     }
 }
 
@@ -348,28 +312,8 @@ pub fn batch_parse_errors(
 
     #[cfg(not(feature = "commercial"))]
     {
-        use regex::Regex;
-        let re = Regex::new(r"(.+?):(\d+):(.+)?").unwrap();
-
         for output in outputs {
-            let mut errors = Vec::new();
-
-            for line in output.lines() {
-                if let Some(caps) = re.captures(line) {
-                    errors.push(ParsedErrorFrontend {
-                        code: "ERROR".to_string(),
-                        message: caps.get(3).map(|m| m.as_str().to_string()).unwrap_or_default(),
-                        file: caps.get(1).map(|m| m.as_str().to_string()).unwrap_or_default(),
-                        line: caps.get(2).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
-                        column: None,
-                        level: "Error".to_string(),
-                        language: "Generic".to_string(),
-                        raw_line: line.to_string(),
-                    });
-                }
-            }
-
-            all_errors.push(errors);
+            all_errors.push(crate::community::error_parser::parse_output(&output));
         }
     }
 
@@ -401,6 +345,178 @@ pub fn get_error_file_content(
     Ok(context_lines)
 }
 
+// ============================================================================
+// v0.3.4: 错误解释与修复建议
+// ============================================================================
+
+/// Rust 文档页面最大保留字符数
+const MAX_DOCS_CHARS: usize = 4_000;
+
+/// 错误解释结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorExplanation {
+    pub error: ParsedErrorFrontend,
+    /// 一句话概括
+    pub summary: String,
+    /// 根本原因
+    pub cause: String,
+    /// 详细解释
+    pub explanation: String,
+    /// 修复建议
+    pub suggested_fix: String,
+    /// 统一 diff 格式的修复补丁（模型无法给出时为空）
+    pub patch: Option<String>,
+    /// 出错位置附近的代码
+    pub code_context: String,
+    /// 相关符号及其定义位置 "name @ path:line"
+    pub related_symbols: Vec<String>,
+    /// 参考的文档地址（仅在允许联网且成功获取时）
+    pub docs_url: Option<String>,
+}
+
+/// 带行号和标记的代码片段
+fn code_snippet(content: &str, line: u32, radius: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let target = (line as usize).saturating_sub(1);
+    let start = target.saturating_sub(radius);
+    let end = (target + radius + 1).min(lines.len());
+    (start..end)
+        .map(|i| format!("{}{:>5} | {}", if i == target { ">" } else { " " }, i + 1, lines[i]))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 从错误消息中提取被引用的标识符（`name` 或 'name'）
+fn referenced_identifiers(message: &str) -> Vec<String> {
+    let re = regex::Regex::new(r"[`']&?(?:mut )?([A-Za-z_][A-Za-z0-9_:]*)[`']").unwrap();
+    let mut names: Vec<String> = re.captures_iter(message)
+        .map(|c| c[1].rsplit("::").next().unwrap_or(&c[1]).to_string())
+        .filter(|n| n.len() > 1)
+        .collect();
+    names.dedup();
+    names
+}
+
+/// 错误码的官方文档地址
+///
+/// 目前只覆盖 Rust 的 `E####` 错误码（doc.rust-lang.org）；其他语言没有稳定的按错误码索引的页面，返回 `None`，
+/// 解释时不附带文档。
+fn docs_url(language: &str, code: &str) -> Option<String> {
+    let code = code.trim();
+    let is_rust_code = code.len() == 5 && code.starts_with('E') && code[1..].chars().all(|c| c.is_ascii_digit());
+    if is_rust_code && (language.eq_ignore_ascii_case("rust") || language.eq_ignore_ascii_case("generic")) {
+        return Some(format!("https://doc.rust-lang.org/error_codes/{}.html", code));
+    }
+    None
+}
+
+/// 解析模型返回的 JSON
+fn parse_explanation(text: &str) -> Option<serde_json::Value> {
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    serde_json::from_str(&text[start..=end]).ok()
+}
+
+/// 解释错误并给出修复建议
+///
+/// 收集出错代码、相关符号定义、（`allow_network` 时）官方文档，请求模型返回结构化解释与补丁。
+/// 文档经 `web_fetch` 抓取（与 `agent_fetch_url` 工具同一路径），目前仅支持 Rust 错误码，见 [`docs_url`]。
+#[tauri::command]
+pub async fn explain_error(
+    symbol_index: State<'_, std::sync::Arc<Mutex<crate::commands::symbol_commands::SymbolIndexState>>>,
+    provider_config: crate::core_traits::ai::AIProviderConfig,
+    parsed_error: ParsedErrorFrontend,
+    project_root: Option<String>,
+    allow_network: Option<bool>,
+    session_id: Option<String>,
+) -> Result<ErrorExplanation, String> {
+    use crate::core_traits::ai::{Content, Message};
+
+    let mut path = PathBuf::from(&parsed_error.file);
+    if path.is_relative() {
+        if let Some(root) = &project_root {
+            path = PathBuf::from(root).join(&parsed_error.file);
+        }
+    }
+    let file_content = fs::read_to_string(&path).unwrap_or_default();
+    let code_context = code_snippet(&file_content, parsed_error.line, 8);
+
+    // 相关符号：出错位置所在的符号 + 错误消息中引用的符号
+    let mut related_symbols = Vec::new();
+    let language_map = project_root.as_deref().map(crate::language_map::LanguageMap::load).unwrap_or_default();
+    let language_id = language_map.for_path(&parsed_error.file)
+        .unwrap_or_else(|| path.extension().and_then(|e| e.to_str()).unwrap_or(""));
+    let line_idx = parsed_error.line.saturating_sub(1) as usize;
+    if let Some(enclosing) = crate::symbol_engine::extract_symbols_from_source(&file_content, language_id)
+        .into_iter()
+        .filter(|s| s.range.start_line <= line_idx && line_idx <= s.range.end_line)
+        .min_by_key(|s| s.range.end_line - s.range.start_line)
+    {
+        related_symbols.push(format!("{} ({}) @ {}:{}", enclosing.name, enclosing.kind, parsed_error.file, enclosing.range.start_line + 1));
+    }
+    if let Ok(index) = symbol_index.lock() {
+        for name in referenced_identifiers(&parsed_error.message) {
+            for reference in index.find_references(&name).into_iter().take(3) {
+                related_symbols.push(format!("{} @ {}", reference.symbol_name, reference.defined_at));
+            }
+        }
+    }
+
+    // 文档（仅在允许联网时；指定会话时还需会话隐私级别允许）
+    let mut used_docs_url = None;
+    let mut docs = None;
+    let network_permitted = session_id.is_none() || crate::privacy::level_for_session(session_id.as_deref()).allows_network();
+    if allow_network.unwrap_or(false) && network_permitted {
+        if let Some(url) = docs_url(&parsed_error.language, &parsed_error.code) {
+            docs = crate::web_fetch::fetch_text(&url, MAX_DOCS_CHARS).await
+                .map_err(|e| println!("[ErrorCommands] Docs fetch failed: {}", e))
+                .ok();
+            if docs.is_some() {
+                used_docs_url = Some(url);
+            }
+        }
+    }
+
+    let mut prompt = format!(
+        "Explain this {} error and propose a fix.\n\nError: [{}] {}\nLocation: {}:{}\nRaw output: {}\n\nCode (> marks the error line):\n```\n{}\n```\n",
+        parsed_error.language, parsed_error.code, parsed_error.message,
+        parsed_error.file, parsed_error.line, parsed_error.raw_line, code_context
+    );
+    if !related_symbols.is_empty() {
+        prompt.push_str(&format!("\nRelated symbols:\n- {}\n", related_symbols.join("\n- ")));
+    }
+    if let Some(docs) = &docs {
+        prompt.push_str(&format!("\nOfficial documentation for {}:\n{}\n", parsed_error.code, docs));
+    }
+    prompt.push_str(&format!(
+        "\nRespond with only a JSON object: {{\"summary\": \"...\", \"cause\": \"...\", \"explanation\": \"...\", \"suggested_fix\": \"...\", \"patch\": \"<unified diff against {} or empty>\"}}",
+        parsed_error.file
+    ));
+
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: Content::Text(prompt),
+        tool_calls: None,
+        tool_call_id: None,
+    }];
+    let response = crate::ai_utils::fetch_ai_completion(&provider_config, messages, None).await?;
+    let text = crate::intelligence_router::extract_text_content(&response.content);
+    let value = parse_explanation(&text).unwrap_or_else(|| serde_json::json!({ "explanation": text }));
+    let field = |key: &str| value[key].as_str().unwrap_or_default().to_string();
+
+    Ok(ErrorExplanation {
+        summary: field("summary"),
+        cause: field("cause"),
+        explanation: field("explanation"),
+        suggested_fix: field("suggested_fix"),
+        patch: Some(field("patch")).filter(|p| !p.trim().is_empty()),
+        code_context,
+        related_symbols,
+        docs_url: used_docs_url,
+        error: parsed_error,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -410,4 +526,12 @@ mod tests {
         // Error parsing logic tested in error_parser.rs
         // This is just a placeholder for command-level tests
     }
+
+    #[test]
+    fn test_explain_error_helpers() {
+        assert_eq!(code_snippet("a\nb\nc\nd", 2, 1), "     1 | a\n>    2 | b\n     3 | c");
+        assert_eq!(referenced_identifiers("mismatched types: expected `String`, found `&str` in `crate::foo::bar`"), vec!["String", "str", "bar"]);
+        assert_eq!(docs_url("Rust", "E0308").as_deref(), Some("https://doc.rust-lang.org/error_codes/E0308.html"));
+        assert_eq!(docs_url("TypeScript", "TS2322"), None);
+    }
 }
@@ -0,0 +1,312 @@
+//! v0.3.4 项目上下文导出（repo2txt）
+//!
+//! 将选中的文件合并为单个 Markdown 文本，便于粘贴到外部 LLM 或附加到 Issue：
+//! - 目录树头部
+//! - 逐文件截断与 Token 计数
+//! - 遵循 .gitignore、.ifaiignore 与 IFAI.md 的 `ignore` 规则，跳过二进制与超大文件
+//! - 导出内容经过凭证脱敏
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::command;
+use serde::{Serialize, Deserialize};
+use ignore::WalkBuilder;
+
+use crate::secret_scrub::scrub_secrets;
+use crate::token_counter::estimate_tokens;
+use crate::language_map::LanguageMap;
+use crate::ifai_ignore::{self, IfaiIgnore};
+
+/// 默认总 Token 预算
+const DEFAULT_BUDGET: usize = 100_000;
+/// 默认单文件 Token 上限
+const DEFAULT_MAX_FILE_TOKENS: usize = 8_000;
+/// 超过该大小的文件直接跳过
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+// ============================================================================
+// 类型定义
+// ============================================================================
+
+/// 跳过的文件及原因
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    /// "ignored" | "binary" | "too_large" | "budget" | "unreadable"
+    pub reason: String,
+}
+
+/// 导出结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoContextExport {
+    /// Markdown 文本
+    pub content: String,
+    pub total_tokens: usize,
+    pub included_files: Vec<String>,
+    pub truncated_files: Vec<String>,
+    pub skipped_files: Vec<SkippedFile>,
+}
+
+// ============================================================================
+// 内部实现
+// ============================================================================
+
+/// 收集待导出的文件（相对路径，已排序去重）
+///
+/// 遍历目录时跳过 `.ifaiignore` 排除的路径；显式指定的文件由调用方再检查一次。
+fn collect_files(root: &Path, paths: &[String]) -> Vec<String> {
+    let targets: Vec<PathBuf> = if paths.is_empty() {
+        vec![root.to_path_buf()]
+    } else {
+        paths.iter().map(|p| root.join(p)).collect()
+    };
+
+    let mut files = Vec::new();
+    for target in targets {
+        if target.is_file() {
+            files.push(target);
+            continue;
+        }
+        let mut walker = WalkBuilder::new(&target);
+        walker.standard_filters(true).hidden(true);
+        for entry in ifai_ignore::apply_project(&mut walker, &root.to_string_lossy())
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+        {
+            files.push(entry.into_path());
+        }
+    }
+
+    let mut rel: Vec<String> = files.iter()
+        .filter_map(|f| f.strip_prefix(root).ok())
+        .map(|f| f.to_string_lossy().replace('\\', "/"))
+        .collect();
+    rel.sort();
+    rel.dedup();
+    rel
+}
+
+/// 渲染目录树
+fn render_tree(files: &[String]) -> String {
+    #[derive(Default)]
+    struct Node(BTreeMap<String, Node>);
+
+    let mut root = Node::default();
+    for file in files {
+        let mut node = &mut root;
+        for part in file.split('/') {
+            node = node.0.entry(part.to_string()).or_default();
+        }
+    }
+
+    fn walk(node: &Node, prefix: &str, out: &mut String) {
+        let count = node.0.len();
+        for (i, (name, child)) in node.0.iter().enumerate() {
+            let last = i + 1 == count;
+            out.push_str(&format!("{}{}{}{}\n", prefix, if last { "└── " } else { "├── " }, name, if child.0.is_empty() { "" } else { "/" }));
+            walk(child, &format!("{}{}", prefix, if last { "    " } else { "│   " }), out);
+        }
+    }
+
+    let mut out = String::from(".\n");
+    walk(&root, "", &mut out);
+    out
+}
+
+/// 按行截断到指定 Token 数
+fn truncate_to_tokens(content: &str, max_tokens: usize) -> (String, bool) {
+    if estimate_tokens(content) <= max_tokens {
+        return (content.to_string(), false);
+    }
+    let mut result = String::new();
+    let mut used = 0;
+    for line in content.lines() {
+        let cost = estimate_tokens(line) + 1;
+        if used + cost > max_tokens {
+            // 首行即超长（如压缩文件）时按字符截取
+            if result.is_empty() {
+                let mut take = line.chars().count();
+                while take > 0 && estimate_tokens(&line.chars().take(take).collect::<String>()) > max_tokens {
+                    take /= 2;
+                }
+                result = line.chars().take(take).collect();
+            }
+            break;
+        }
+        used += cost;
+        result.push_str(line);
+        result.push('\n');
+    }
+    (result, true)
+}
+
+fn fence_language<'a>(language_map: &'a LanguageMap, path: &'a str) -> &'a str {
+    language_map.for_path(path)
+        .unwrap_or_else(|| Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or(""))
+}
+
+/// 构建导出内容
+pub fn build_repo_export(
+    project_root: &str,
+    paths: &[String],
+    budget: usize,
+    max_file_tokens: usize,
+) -> RepoContextExport {
+    let root = Path::new(project_root);
+    let candidates = collect_files(root, paths);
+    let language_map = LanguageMap::load(project_root);
+    let ignore = IfaiIgnore::load(project_root);
+
+    let mut sections = Vec::new();
+    let mut included_files = Vec::new();
+    let mut truncated_files = Vec::new();
+    let mut skipped_files = Vec::new();
+    let mut used_tokens = 0;
+
+    for rel in candidates {
+        let abs = root.join(&rel);
+        let skip = |reason: &str| SkippedFile { path: rel.clone(), reason: reason.to_string() };
+
+        if ignore.is_ignored(&rel, false) {
+            skipped_files.push(skip("ignored"));
+            continue;
+        }
+
+        if abs.metadata().map(|m| m.len() > MAX_FILE_BYTES).unwrap_or(true) {
+            skipped_files.push(skip("too_large"));
+            continue;
+        }
+        let Ok(bytes) = std::fs::read(&abs) else {
+            skipped_files.push(skip("unreadable"));
+            continue;
+        };
+        if bytes.iter().take(8192).any(|b| *b == 0) {
+            skipped_files.push(skip("binary"));
+            continue;
+        }
+
+        let content = scrub_secrets(&String::from_utf8_lossy(&bytes));
+        let remaining = budget.saturating_sub(used_tokens);
+        let (body, truncated) = truncate_to_tokens(&content, max_file_tokens.min(remaining));
+        if body.trim().is_empty() && !content.trim().is_empty() {
+            skipped_files.push(skip("budget"));
+            continue;
+        }
+
+        let mut section = format!("## {}\n\n```{}\n{}", rel, fence_language(&language_map, &rel), body);
+        if !section.ends_with('\n') {
+            section.push('\n');
+        }
+        if truncated {
+            section.push_str("... [truncated]\n");
+            truncated_files.push(rel.clone());
+        }
+        section.push_str("```\n");
+
+        used_tokens += estimate_tokens(&section);
+        sections.push(section);
+        included_files.push(rel);
+    }
+
+    let project_name = root.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let mut content = format!(
+        "# {}\n\n{} files, ~{} tokens\n\n## File Tree\n\n```\n{}```\n\n",
+        project_name,
+        included_files.len(),
+        used_tokens,
+        render_tree(&included_files)
+    );
+    content.push_str(&sections.join("\n"));
+
+    RepoContextExport {
+        total_tokens: estimate_tokens(&content),
+        content,
+        included_files,
+        truncated_files,
+        skipped_files,
+    }
+}
+
+// ============================================================================
+// Tauri 命令
+// ============================================================================
+
+/// 导出项目上下文为单个 Markdown 文本
+///
+/// `paths` 为空时导出整个项目；`budget` 为总 Token 预算。
+#[command]
+pub async fn export_repo_context(
+    project_root: String,
+    paths: Option<Vec<String>>,
+    budget: Option<usize>,
+    max_file_tokens: Option<usize>,
+) -> Result<RepoContextExport, String> {
+    if !Path::new(&project_root).is_dir() {
+        return Err(format!("Directory does not exist: {}", project_root));
+    }
+    let paths = paths.unwrap_or_default();
+    let budget = budget.unwrap_or(DEFAULT_BUDGET);
+    let max_file_tokens = max_file_tokens.unwrap_or(DEFAULT_MAX_FILE_TOKENS);
+
+    let export = tokio::task::spawn_blocking(move || {
+        build_repo_export(&project_root, &paths, budget, max_file_tokens)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+
+    println!(
+        "[ExportRepo] {} files, {} truncated, {} skipped, ~{} tokens",
+        export.included_files.len(),
+        export.truncated_files.len(),
+        export.skipped_files.len(),
+        export.total_tokens
+    );
+    Ok(export)
+}
+
+// ============================================================================
+// 测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_tree() {
+        let files = vec!["src/lib.rs".to_string(), "src/util/mod.rs".to_string(), "README.md".to_string()];
+        assert_eq!(
+            render_tree(&files),
+            ".\n├── README.md\n└── src/\n    ├── lib.rs\n    └── util/\n        └── mod.rs\n"
+        );
+    }
+
+    #[test]
+    fn test_build_repo_export() {
+        let dir = std::env::temp_dir().join(format!("ifai_export_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}\nconst KEY: &str = \"sk-abcdefghijklmnopqrstuvwx\";\n").unwrap();
+        std::fs::write(dir.join("src/big.txt"), "word\n".repeat(2000)).unwrap();
+        std::fs::write(dir.join("logo.bin"), [0u8, 1, 2, 3]).unwrap();
+
+        let export = build_repo_export(&dir.to_string_lossy(), &[], 10_000, 100);
+        assert_eq!(export.included_files, vec!["src/big.txt", "src/main.rs"]);
+        assert_eq!(export.truncated_files, vec!["src/big.txt"]);
+        assert_eq!(export.skipped_files[0].reason, "binary");
+        assert!(export.content.contains("## src/main.rs\n\n```rust\nfn main() {}"));
+        assert!(!export.content.contains("sk-abcdefghijklmnop"));
+
+        let only_main = build_repo_export(&dir.to_string_lossy(), &["src/main.rs".to_string()], 10_000, 100);
+        assert_eq!(only_main.included_files, vec!["src/main.rs"]);
+
+        std::fs::write(dir.join(".ifaiignore"), "src/big.txt\n").unwrap();
+        let filtered = build_repo_export(&dir.to_string_lossy(), &[], 10_000, 100);
+        assert_eq!(filtered.included_files, vec!["src/main.rs"]);
+        let explicit = build_repo_export(&dir.to_string_lossy(), &["src/big.txt".to_string()], 10_000, 100);
+        assert!(explicit.included_files.is_empty());
+        assert_eq!(explicit.skipped_files[0].reason, "ignored");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,341 @@
+//! v0.3.4 调用图与文件依赖图
+//!
+//! 基于符号索引中已解析的引用构建：
+//! - 调用图：引用所在的最内层符号视为调用方，边为 调用方 -> 被引用的定义；
+//!   不在任何符号内的引用（模块顶层代码）以文件节点作为调用方
+//! - 文件依赖图：文件引用了哪些文件中的定义（`depends_on`），以及被哪些文件引用（`dependents`）
+//!
+//! 返回 nodes / edges 结构，前端可直接用于可视化；`direction = "callers"` 即
+//! “修改这个函数会影响哪里”。
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tauri::command;
+use serde::{Serialize, Deserialize};
+
+use super::symbol_commands::{Symbol, SymbolIndexState};
+
+/// 默认展开层数
+const DEFAULT_DEPTH: usize = 3;
+/// 单次返回的节点上限
+const MAX_NODES: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GraphNode {
+    /// 定义位置 "path:line"；文件节点为文件路径
+    pub id: String,
+    pub name: String,
+    pub kind: String,
+    pub path: String,
+    pub line: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+    /// 引用所在位置 "path:line"
+    pub site: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallGraph {
+    /// 查询符号的定义（可能有多个同名定义）
+    pub roots: Vec<String>,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+    /// 节点数达到上限时为 true
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphDirection {
+    /// 谁引用了它（影响范围）
+    Callers,
+    /// 它引用了谁
+    Callees,
+    #[default]
+    Both,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileDependency {
+    pub path: String,
+    /// 两个文件之间的引用次数
+    pub references: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDependencies {
+    pub path: String,
+    pub depends_on: Vec<FileDependency>,
+    pub dependents: Vec<FileDependency>,
+}
+
+// ============================================================================
+// 图构建
+// ============================================================================
+
+fn split_location(location: &str) -> Option<(&str, u32)> {
+    let (path, line) = location.rsplit_once(':')?;
+    Some((path, line.parse().ok()?))
+}
+
+fn symbol_at<'a>(index: &'a SymbolIndexState, path: &str, line: u32) -> Option<&'a Symbol> {
+    index.file_symbols(path)?.symbols.iter().find(|s| s.line == line)
+}
+
+/// 包含该行的最内层符号
+fn enclosing_symbol<'a>(index: &'a SymbolIndexState, path: &str, line: u32) -> Option<&'a Symbol> {
+    index.file_symbols(path)?
+        .symbols
+        .iter()
+        .filter(|s| !s.kind.starts_with("impl") && s.line <= line && s.end_line.unwrap_or(s.line) >= line)
+        .min_by_key(|s| s.end_line.unwrap_or(s.line) - s.line)
+}
+
+fn symbol_node(path: &str, symbol: &Symbol) -> GraphNode {
+    GraphNode {
+        id: format!("{}:{}", path, symbol.line),
+        name: symbol.qualified_name.clone(),
+        kind: symbol.kind.clone(),
+        path: path.to_string(),
+        line: symbol.line,
+    }
+}
+
+fn file_node(path: &str) -> GraphNode {
+    GraphNode {
+        id: path.to_string(),
+        name: Path::new(path).file_name().and_then(|n| n.to_str()).unwrap_or(path).to_string(),
+        kind: "file".to_string(),
+        path: path.to_string(),
+        line: 0,
+    }
+}
+
+/// 由引用表构建完整的调用边
+fn build_edges(index: &SymbolIndexState) -> (HashMap<String, GraphNode>, Vec<GraphEdge>) {
+    let mut nodes = HashMap::new();
+    let mut edges = Vec::new();
+    for (definition, sites) in index.reference_map() {
+        let Some((def_path, def_line)) = split_location(definition) else { continue };
+        let callee = match symbol_at(index, def_path, def_line) {
+            Some(symbol) => symbol_node(def_path, symbol),
+            None => continue,
+        };
+        for site in sites {
+            let Some((path, line)) = split_location(site) else { continue };
+            let caller = enclosing_symbol(index, path, line).map(|s| symbol_node(path, s)).unwrap_or_else(|| file_node(path));
+            edges.push(GraphEdge { from: caller.id.clone(), to: callee.id.clone(), site: site.clone() });
+            nodes.entry(caller.id.clone()).or_insert(caller);
+        }
+        nodes.entry(callee.id.clone()).or_insert(callee);
+    }
+    // 保证输出稳定
+    edges.sort_by(|a, b| (&a.from, &a.to, &a.site).cmp(&(&b.from, &b.to, &b.site)));
+    (nodes, edges)
+}
+
+/// 以符号为中心展开调用图（按名称匹配限定名或短名）
+pub fn call_graph(index: &SymbolIndexState, symbol: &str, direction: GraphDirection, depth: usize) -> CallGraph {
+    let mut roots = index.definition_locations(symbol);
+    if roots.is_empty() {
+        roots = index.indexed_files()
+            .flat_map(|f| f.symbols.iter().filter(|s| s.name == symbol).map(move |s| format!("{}:{}", f.path, s.line)))
+            .collect();
+    }
+    roots.sort();
+
+    let (all_nodes, all_edges) = build_edges(index);
+    let mut incoming: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut outgoing: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, edge) in all_edges.iter().enumerate() {
+        incoming.entry(edge.to.as_str()).or_default().push(i);
+        outgoing.entry(edge.from.as_str()).or_default().push(i);
+    }
+
+    let mut visited: HashSet<String> = roots.iter().cloned().collect();
+    let mut included_edges: HashSet<usize> = HashSet::new();
+    let mut truncated = false;
+    // 调用方与被调用方分别展开，避免“调用方的其他被调用方”混入
+    let passes: &[bool] = match direction {
+        GraphDirection::Callers => &[true],
+        GraphDirection::Callees => &[false],
+        GraphDirection::Both => &[true, false],
+    };
+    for &callers in passes {
+        let mut queue: VecDeque<(String, usize)> = roots.iter().map(|r| (r.clone(), 0)).collect();
+        let mut expanded: HashSet<String> = HashSet::new();
+        while let Some((node, level)) = queue.pop_front() {
+            if level >= depth || !expanded.insert(node.clone()) {
+                continue;
+            }
+            let adjacent = if callers { incoming.get(node.as_str()) } else { outgoing.get(node.as_str()) };
+            for &i in adjacent.into_iter().flatten() {
+                let edge = &all_edges[i];
+                let next = if callers { &edge.from } else { &edge.to };
+                if !visited.contains(next) {
+                    if visited.len() >= MAX_NODES {
+                        truncated = true;
+                        continue;
+                    }
+                    visited.insert(next.clone());
+                }
+                included_edges.insert(i);
+                queue.push_back((next.clone(), level + 1));
+            }
+        }
+    }
+
+    let mut nodes: Vec<GraphNode> = visited.iter()
+        .map(|id| all_nodes.get(id).cloned().unwrap_or_else(|| match split_location(id).and_then(|(p, l)| symbol_at(index, p, l).map(|s| symbol_node(p, s))) {
+            Some(node) => node,
+            None => file_node(id),
+        }))
+        .collect();
+    nodes.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut edge_ids: Vec<usize> = included_edges.into_iter().collect();
+    edge_ids.sort();
+
+    CallGraph { roots, nodes, edges: edge_ids.into_iter().map(|i| all_edges[i].clone()).collect(), truncated }
+}
+
+/// 文件的依赖与被依赖关系（`path` 可为索引中的绝对路径或其后缀）
+pub fn file_dependencies(index: &SymbolIndexState, path: &str) -> Option<FileDependencies> {
+    let normalized = path.replace('\\', "/");
+    let target = index.indexed_files()
+        .map(|f| f.path.as_str())
+        .chain(index.reference_map().values().flatten().filter_map(|site| split_location(site).map(|(p, _)| p)))
+        .find(|p| *p == normalized || p.replace('\\', "/").ends_with(&format!("/{}", normalized.trim_start_matches("./"))))?
+        .to_string();
+
+    let mut depends_on: BTreeMap<String, usize> = BTreeMap::new();
+    let mut dependents: BTreeMap<String, usize> = BTreeMap::new();
+    for (definition, sites) in index.reference_map() {
+        let Some((def_path, _)) = split_location(definition) else { continue };
+        for site in sites {
+            let Some((site_path, _)) = split_location(site) else { continue };
+            if site_path == target && def_path != target {
+                *depends_on.entry(def_path.to_string()).or_default() += 1;
+            } else if def_path == target && site_path != target {
+                *dependents.entry(site_path.to_string()).or_default() += 1;
+            }
+        }
+    }
+    let sorted = |map: BTreeMap<String, usize>| {
+        let mut list: Vec<FileDependency> = map.into_iter().map(|(path, references)| FileDependency { path, references }).collect();
+        list.sort_by(|a, b| b.references.cmp(&a.references).then_with(|| a.path.cmp(&b.path)));
+        list
+    };
+    Some(FileDependencies { path: target, depends_on: sorted(depends_on), dependents: sorted(dependents) })
+}
+
+// ============================================================================
+// Tauri 命令
+// ============================================================================
+
+/// 获取符号的调用图（需先执行 `index_project_symbols`）
+#[command]
+pub async fn get_call_graph(
+    state: tauri::State<'_, Arc<Mutex<SymbolIndexState>>>,
+    symbol: String,
+    direction: Option<GraphDirection>,
+    depth: Option<usize>,
+) -> Result<CallGraph, String> {
+    let index_state = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let graph = call_graph(&index_state, &symbol, direction.unwrap_or_default(), depth.unwrap_or(DEFAULT_DEPTH));
+    if graph.roots.is_empty() {
+        return Err(format!("Symbol not found in index: {}", symbol));
+    }
+    Ok(graph)
+}
+
+/// 获取文件的依赖关系
+#[command]
+pub async fn get_file_dependencies(
+    state: tauri::State<'_, Arc<Mutex<SymbolIndexState>>>,
+    path: String,
+) -> Result<FileDependencies, String> {
+    let index_state = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+    file_dependencies(&index_state, &path).ok_or_else(|| format!("File not found in index: {}", path))
+}
+
+// ============================================================================
+// 单元测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::symbol_commands::FileSymbols;
+    use crate::symbol_references::ResolvedReference;
+
+    fn function(name: &str, line: u32, end_line: u32) -> Symbol {
+        Symbol {
+            kind: "function_item".to_string(),
+            name: name.to_string(),
+            line,
+            end_line: Some(end_line),
+            parent: None,
+            qualified_name: name.to_string(),
+        }
+    }
+
+    fn reference(definition: &str, line: u32) -> ResolvedReference {
+        ResolvedReference { definition: definition.to_string(), line }
+    }
+
+    /// main.rs: main -> handle -> (db.rs) query；cli.rs 顶层代码 -> handle
+    fn sample_index() -> SymbolIndexState {
+        let mut state = SymbolIndexState::new();
+        state.index_file(FileSymbols { path: "/p/src/main.rs".into(), symbols: vec![function("main", 1, 3), function("handle", 5, 8)], hash: "a".into() });
+        state.index_file(FileSymbols { path: "/p/src/db.rs".into(), symbols: vec![function("query", 1, 4)], hash: "b".into() });
+        state.index_file(FileSymbols { path: "/p/src/cli.rs".into(), symbols: vec![], hash: "c".into() });
+        state.index_references("/p/src/main.rs", vec![reference("/p/src/main.rs:5", 2), reference("/p/src/db.rs:1", 6), reference("/p/src/db.rs:1", 7)]);
+        state.index_references("/p/src/cli.rs", vec![reference("/p/src/main.rs:5", 10)]);
+        state
+    }
+
+    #[test]
+    fn test_callers_graph() {
+        let state = sample_index();
+        let graph = call_graph(&state, "query", GraphDirection::Callers, 3);
+        assert_eq!(graph.roots, vec!["/p/src/db.rs:1"]);
+        let ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["/p/src/cli.rs", "/p/src/db.rs:1", "/p/src/main.rs:1", "/p/src/main.rs:5"]);
+        // handle 中两处调用 query
+        assert_eq!(graph.edges.iter().filter(|e| e.from == "/p/src/main.rs:5" && e.to == "/p/src/db.rs:1").count(), 2);
+        assert_eq!(graph.nodes[0].kind, "file");
+
+        let shallow = call_graph(&state, "query", GraphDirection::Callers, 1);
+        assert_eq!(shallow.nodes.len(), 2);
+        assert!(!shallow.truncated);
+    }
+
+    #[test]
+    fn test_callees_graph() {
+        let state = sample_index();
+        let graph = call_graph(&state, "main", GraphDirection::Callees, 5);
+        let ids: Vec<&str> = graph.nodes.iter().map(|n| n.id.as_str()).collect();
+        assert_eq!(ids, vec!["/p/src/db.rs:1", "/p/src/main.rs:1", "/p/src/main.rs:5"]);
+        assert!(graph.edges.iter().all(|e| e.from != "/p/src/cli.rs"));
+        assert!(call_graph(&state, "missing", GraphDirection::Both, 3).roots.is_empty());
+    }
+
+    #[test]
+    fn test_file_dependencies() {
+        let state = sample_index();
+        let main = file_dependencies(&state, "src/main.rs").unwrap();
+        assert_eq!(main.path, "/p/src/main.rs");
+        assert_eq!(main.depends_on, vec![FileDependency { path: "/p/src/db.rs".into(), references: 2 }]);
+        assert_eq!(main.dependents, vec![FileDependency { path: "/p/src/cli.rs".into(), references: 1 }]);
+
+        let db = file_dependencies(&state, "/p/src/db.rs").unwrap();
+        assert!(db.depends_on.is_empty());
+        assert_eq!(db.dependents[0].path, "/p/src/main.rs");
+        assert!(file_dependencies(&state, "src/none.rs").is_none());
+    }
+}
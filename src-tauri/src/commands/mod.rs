@@ -7,9 +7,19 @@ pub mod task_commands;
 pub mod proposal_commands;
 // v0.5.0 新增：Bash 命令执行
 pub mod bash_commands;
+// v0.3.4 新增：流式 Bash 命令执行（Agent 的 agent_run_command 工具）
+pub mod bash_streaming;
 // v0.2.8 新增：符号索引与跨文件关联
 pub mod symbol_commands;
 // v0.2.8 新增：原子文件操作
 pub mod atomic_commands;
 // v0.2.8 新增：终端错误解析
-pub mod error_commands;
\ No newline at end of file
+pub mod error_commands;
+// v0.3.4 新增：工作流录制与回放
+pub mod workflow_commands;
+// v0.3.4 新增：项目上下文导出
+pub mod export_commands;
+// v0.3.4 新增：单元测试生成
+pub mod testgen_commands;
+// v0.3.4 新增：调用图与文件依赖图
+pub mod graph_commands;
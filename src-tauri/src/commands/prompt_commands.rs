@@ -110,7 +110,15 @@ pub async fn update_prompt(project_root: String, path: String, content: String)
     Ok(final_rel_path)
 }
 
+/// 渲染模板（可含 front matter）；`project_root` 用于解析 `{{> partials/xyz}}` 与 `extends:`，
+/// `path` 为模板自身路径，用于循环检测。错误信息包含完整的引用链
 #[tauri::command]
-pub async fn render_prompt_template(content: String, variables: HashMap<String, String>) -> Result<String, String> {
-    template::render_template(&content, &variables).map_err(|e| e.to_string())
+pub async fn render_prompt_template(
+    content: String,
+    variables: HashMap<String, String>,
+    project_root: Option<String>,
+    path: Option<String>,
+) -> Result<String, String> {
+    let origin = path.as_deref().map(|p| p.trim_start_matches("builtin://")).unwrap_or("untitled");
+    template::render_prompt(project_root.as_deref(), &content, origin, &variables).map_err(|e| format!("{:#}", e))
 }
\ No newline at end of file
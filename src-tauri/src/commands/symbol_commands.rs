@@ -10,6 +10,8 @@ use std::sync::{Arc, Mutex};
 use tauri::command;
 use serde::{Serialize, Deserialize};
 use ignore::WalkBuilder;
+use crate::language_map::LanguageMap;
+use crate::symbol_references::{resolve_references, DefinitionSite, ResolvedReference};
 
 // ============================================================================
 // 类型定义 (兼容 ifainew-core)
@@ -60,7 +62,7 @@ pub struct SymbolIndexState {
     /// 符号名 -> 定义位置 "path:line"
     definitions: HashMap<String, Vec<String>>,
 
-    /// 符号名 -> 引用位置列表 "path:line"
+    /// 定义位置 "path:line" -> 引用位置列表 "path:line"
     references: HashMap<String, Vec<String>>,
 }
 
@@ -87,9 +89,53 @@ impl SymbolIndexState {
                 .or_insert_with(Vec::new)
                 .push(format!("{}:{}", path, symbol.line));
         }
+    }
 
-        // TODO: 建立引用索引（需要解析符号引用）
-        // 这需要更复杂的分析，暂时留空
+    /// 短名 -> 定义位置，供引用解析使用
+    pub fn definition_sites(&self) -> HashMap<String, Vec<DefinitionSite>> {
+        let mut sites: HashMap<String, Vec<DefinitionSite>> = HashMap::new();
+        for (path, file_symbols) in &self.file_symbols {
+            for symbol in &file_symbols.symbols {
+                sites.entry(symbol.name.clone()).or_default().push(DefinitionSite { path: path.clone(), line: symbol.line });
+            }
+        }
+        sites
+    }
+
+    /// 记录文件中解析出的引用（需在所有文件的定义建立之后调用）
+    pub fn index_references(&mut self, path: &str, references: Vec<ResolvedReference>) {
+        for reference in references {
+            self.references
+                .entry(reference.definition)
+                .or_default()
+                .push(format!("{}:{}", path, reference.line));
+        }
+    }
+
+    /// 获取已索引文件的符号
+    pub fn file_symbols(&self, path: &str) -> Option<&FileSymbols> {
+        self.file_symbols.get(path)
+    }
+
+    /// 所有已索引文件
+    pub fn indexed_files(&self) -> impl Iterator<Item = &FileSymbols> {
+        self.file_symbols.values()
+    }
+
+    /// 符号的定义位置 "path:line"（匹配限定名）
+    pub fn definition_locations(&self, name: &str) -> Vec<String> {
+        self.definitions.get(name).cloned().unwrap_or_default()
+    }
+
+    /// 定义位置 -> 引用位置
+    pub fn reference_map(&self) -> &HashMap<String, Vec<String>> {
+        &self.references
+    }
+
+    /// 索引中是否存在同名符号（匹配短名或限定名）
+    pub fn contains_symbol(&self, name: &str) -> bool {
+        self.definitions.contains_key(name)
+            || self.file_symbols.values().any(|fs| fs.symbols.iter().any(|s| s.name == name))
     }
 
     /// 查找符号的所有引用
@@ -101,7 +147,7 @@ impl SymbolIndexState {
                 refs.push(SymbolReference {
                     symbol_name: symbol_name.to_string(),
                     defined_at: def_loc.clone(),
-                    referenced_in: self.references.get(symbol_name).cloned().unwrap_or_default(),
+                    referenced_in: self.references.get(def_loc).cloned().unwrap_or_default(),
                 });
             }
         }
@@ -188,6 +234,7 @@ pub async fn extract_symbols(
 /// 索引整个项目的符号
 #[command]
 pub async fn index_project_symbols(
+    app: tauri::AppHandle,
     state: tauri::State<'_, Arc<Mutex<SymbolIndexState>>>,
     root_path: String,
 ) -> Result<ProjectIndexResult, String> {
@@ -200,12 +247,14 @@ pub async fn index_project_symbols(
     let mut files_indexed = 0;
     let mut symbols_found = 0;
     let mut indexed_files = Vec::new();
+    // 所有参与索引的源文件（含没有定义的文件，用于第二遍解析引用）
+    let mut sources = Vec::new();
+    let language_map = LanguageMap::load(&root_path);
 
     // 遍历项目文件并提取符号（不持有锁）
-    let walker = WalkBuilder::new(&root_path)
-        .hidden(true)
-        .git_ignore(true)
-        .build();
+    let mut walker = WalkBuilder::new(&root_path);
+    walker.hidden(true).git_ignore(true);
+    let walker = crate::ifai_ignore::apply_project(&mut walker, &root_path).build();
 
     for result in walker {
         match result {
@@ -215,12 +264,11 @@ pub async fn index_project_symbols(
                 }
 
                 let path = entry.path();
-                let extension = path.extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("");
+                let rel_path = path.strip_prefix(&root_path).unwrap_or(path).to_string_lossy().to_string();
 
                 // 只索引支持的代码文件
-                if !["rs", "ts", "tsx", "js", "jsx", "py"].contains(&extension) {
+                let language = detect_language(&language_map, &rel_path);
+                if !INDEXED_LANGUAGES.contains(&language) {
                     continue;
                 }
 
@@ -230,12 +278,11 @@ pub async fn index_project_symbols(
                     Err(_) => continue,
                 };
 
+                sources.push((path.to_string_lossy().to_string(), language.to_string()));
+
                 // 计算文件哈希（在移动之前）
                 let content_hash = format!("{:x}", md5::compute(&content));
 
-                // 检测语言
-                let language = detect_language_from_ext(extension);
-
                 // 提取符号
                 match extract_symbols(
                     content,
@@ -266,11 +313,35 @@ pub async fn index_project_symbols(
     }
 
     // 最后批量更新索引（获取锁）
-    {
+    let definitions = {
         let mut index_state = state.lock().map_err(|e| format!("Lock error: {}", e))?;
         for file_symbols in indexed_files {
             index_state.index_file(file_symbols);
         }
+        index_state.definition_sites()
+    };
+
+    // 第二遍：定义表完整后解析各文件中的引用（不持有锁）
+    let resolved: Vec<(String, Vec<ResolvedReference>)> = sources
+        .into_iter()
+        .filter_map(|(path, language)| {
+            let content = std::fs::read_to_string(&path).ok()?;
+            let references = resolve_references(&path, &content, &language, &definitions);
+            Some((path, references))
+        })
+        .collect();
+    let references_found: usize = resolved.iter().map(|(_, refs)| refs.len()).sum();
+    {
+        let mut index_state = state.lock().map_err(|e| format!("Lock error: {}", e))?;
+        for (path, references) in resolved {
+            index_state.index_references(&path, references);
+        }
+    }
+    println!("[SymbolIndex] {} symbols, {} resolved references", symbols_found, references_found);
+
+    // v0.3.4: 记录索引时间，供最近项目列表展示索引新鲜度
+    if let Err(e) = crate::recent_projects::record_index(&app, &root_path, files_indexed) {
+        eprintln!("[SymbolIndex] Failed to record index time: {}", e);
     }
 
     Ok(ProjectIndexResult {
@@ -313,17 +384,12 @@ pub async fn clear_symbol_index(
 // 辅助函数
 // ============================================================================
 
-/// 从文件扩展名检测语言
-fn detect_language_from_ext(ext: &str) -> &str {
-    match ext {
-        "rs" => "rust",
-        "ts" | "tsx" => "typescript",
-        "js" | "jsx" => "javascript",
-        "py" => "python",
-        "go" => "go",
-        "java" => "java",
-        _ => "unknown",
-    }
+/// 参与符号索引的语言
+const INDEXED_LANGUAGES: &[&str] = &["rust", "typescript", "javascript", "python", "go", "java", "c", "cpp"];
+
+/// 检测文件语言（内置扩展名映射 + IFAI.md 覆盖）
+fn detect_language<'a>(language_map: &'a LanguageMap, path: &str) -> &'a str {
+    language_map.for_path(path).unwrap_or("unknown")
 }
 
 // ============================================================================
@@ -335,13 +401,15 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_detect_language_from_ext() {
-        assert_eq!(detect_language_from_ext("rs"), "rust");
-        assert_eq!(detect_language_from_ext("ts"), "typescript");
-        assert_eq!(detect_language_from_ext("tsx"), "typescript");
-        assert_eq!(detect_language_from_ext("js"), "javascript");
-        assert_eq!(detect_language_from_ext("py"), "python");
-        assert_eq!(detect_language_from_ext("xyz"), "unknown");
+    fn test_detect_language() {
+        let map = LanguageMap::default();
+        assert_eq!(detect_language(&map, "main.rs"), "rust");
+        assert_eq!(detect_language(&map, "app.ts"), "typescript");
+        assert_eq!(detect_language(&map, "App.tsx"), "typescript");
+        assert_eq!(detect_language(&map, "index.js"), "javascript");
+        assert_eq!(detect_language(&map, "server.mjs"), "javascript");
+        assert_eq!(detect_language(&map, "main.py"), "python");
+        assert_eq!(detect_language(&map, "file.xyz"), "unknown");
     }
 
     #[test]
@@ -382,6 +450,33 @@ mod tests {
         let refs = state.find_references("User");
         assert_eq!(refs.len(), 1);
         assert_eq!(refs[0].symbol_name, "User");
+        assert!(refs[0].referenced_in.is_empty());
+    }
+
+    #[test]
+    fn test_cross_file_references() {
+        let mut state = SymbolIndexState::new();
+        let symbol = |name: &str, line: u32| Symbol {
+            kind: "struct_item".to_string(),
+            name: name.to_string(),
+            line,
+            end_line: Some(line + 2),
+            parent: None,
+            qualified_name: name.to_string(),
+        };
+        state.index_file(FileSymbols { path: "/p/src/storage.rs".to_string(), symbols: vec![symbol("Store", 3)], hash: "a".to_string() });
+        state.index_file(FileSymbols { path: "/p/src/legacy.rs".to_string(), symbols: vec![symbol("Store", 1)], hash: "b".to_string() });
+
+        let source = "use crate::storage::Store;\n\nfn open() -> Store {\n    Store::default()\n}\n";
+        let references = resolve_references("/p/src/main.rs", source, "rust", &state.definition_sites());
+        state.index_references("/p/src/main.rs", references);
+
+        let refs = state.find_references("Store");
+        assert_eq!(refs.len(), 2);
+        let storage = refs.iter().find(|r| r.defined_at == "/p/src/storage.rs:3").unwrap();
+        assert_eq!(storage.referenced_in, vec!["/p/src/main.rs:3", "/p/src/main.rs:4"]);
+        let legacy = refs.iter().find(|r| r.defined_at == "/p/src/legacy.rs:1").unwrap();
+        assert!(legacy.referenced_in.is_empty());
     }
 
     #[test]
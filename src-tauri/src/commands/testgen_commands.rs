@@ -0,0 +1,527 @@
+//! v0.3.4 单元测试生成
+//!
+//! 为文件或符号生成单元测试：
+//! - 收集上下文：目标代码、项目中已有测试的写法、测试框架
+//! - 请求模型返回结构化的测试代码
+//! - 通过原子写入会话写入约定的测试位置（Rust 源文件已有 `mod tests` 时插入该模块内）
+//! - 可选运行新测试（按生成的测试名过滤）并返回初始通过/失败结果
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{command, State};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use ignore::WalkBuilder;
+
+use crate::core_traits::ai::{AIProviderConfig, Content, Message};
+use crate::intelligence_router::extract_text_content;
+use super::atomic_commands::{
+    atomic_write_add_operation_internal, atomic_write_commit_internal, atomic_write_start_internal,
+    FileOperationRequest, FileOperationType, SessionStore,
+};
+
+/// 目标代码最大字符数
+const MAX_TARGET_CHARS: usize = 12_000;
+/// 示例测试最大字符数
+const MAX_EXAMPLE_CHARS: usize = 3_000;
+/// 运行测试超时
+const RUN_TIMEOUT_SECS: u64 = 300;
+
+// ============================================================================
+// 类型定义
+// ============================================================================
+
+/// 测试框架
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestFramework {
+    Cargo,
+    Vitest,
+    Jest,
+    Pytest,
+    Go,
+}
+
+/// 测试写入位置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestLocation {
+    /// 相对项目根目录的路径
+    pub path: String,
+    /// 追加到源文件末尾（Rust 内联 `mod tests`）
+    pub append_to_source: bool,
+}
+
+/// 测试运行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRunResult {
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    /// 输出末尾部分
+    pub output: String,
+}
+
+/// 生成结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GeneratedTests {
+    pub framework: TestFramework,
+    pub location: TestLocation,
+    pub test_code: String,
+    pub test_names: Vec<String>,
+    pub session_id: String,
+    pub applied: bool,
+    pub run: Option<TestRunResult>,
+}
+
+// ============================================================================
+// 上下文收集
+// ============================================================================
+
+fn extension(path: &str) -> &str {
+    Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("")
+}
+
+/// 根据文件类型与项目配置识别测试框架
+fn detect_framework(root: &Path, rel_path: &str) -> Result<TestFramework, String> {
+    match extension(rel_path) {
+        "rs" => Ok(TestFramework::Cargo),
+        "py" => Ok(TestFramework::Pytest),
+        "go" => Ok(TestFramework::Go),
+        "ts" | "tsx" | "js" | "jsx" | "mjs" | "cjs" => {
+            let package = std::fs::read_to_string(root.join("package.json")).unwrap_or_default();
+            if package.contains("\"jest\"") && !package.contains("\"vitest\"") {
+                Ok(TestFramework::Jest)
+            } else {
+                Ok(TestFramework::Vitest)
+            }
+        }
+        ext => Err(format!("Unsupported file type for test generation: .{}", ext)),
+    }
+}
+
+/// 按框架约定计算测试文件位置
+fn test_location(framework: TestFramework, rel_path: &str) -> TestLocation {
+    let path = Path::new(rel_path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("module");
+    let parent = path.parent().map(|p| p.to_string_lossy().replace('\\', "/")).unwrap_or_default();
+    let join = |name: String| if parent.is_empty() { name } else { format!("{}/{}", parent, name) };
+
+    match framework {
+        TestFramework::Cargo => TestLocation { path: rel_path.to_string(), append_to_source: true },
+        TestFramework::Go => TestLocation { path: join(format!("{}_test.go", stem)), append_to_source: false },
+        TestFramework::Pytest => TestLocation { path: format!("tests/test_{}.py", stem), append_to_source: false },
+        TestFramework::Vitest | TestFramework::Jest => {
+            let ext = if extension(rel_path).starts_with("ts") { "ts" } else { "js" };
+            TestLocation { path: join(format!("{}.test.{}", stem, ext)), append_to_source: false }
+        }
+    }
+}
+
+fn is_test_file(rel: &str, framework: TestFramework) -> bool {
+    let name = Path::new(rel).file_name().and_then(|n| n.to_str()).unwrap_or("");
+    match framework {
+        TestFramework::Cargo => rel.starts_with("tests/") && name.ends_with(".rs"),
+        TestFramework::Go => name.ends_with("_test.go"),
+        TestFramework::Pytest => name.starts_with("test_") && name.ends_with(".py"),
+        TestFramework::Vitest | TestFramework::Jest => name.contains(".test.") || name.contains(".spec."),
+    }
+}
+
+/// 找一个已有测试作为写法示例
+fn find_example_test(root: &Path, framework: TestFramework, exclude: &str) -> Option<(String, String)> {
+    WalkBuilder::new(root)
+        .standard_filters(true)
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(root).ok()?.to_string_lossy().replace('\\', "/");
+            if rel == exclude {
+                return None;
+            }
+            if framework == TestFramework::Cargo {
+                if extension(&rel) != "rs" {
+                    return None;
+                }
+                // Rust 项目优先参考内联测试模块
+                let content = std::fs::read_to_string(e.path()).ok()?;
+                let start = content.find("#[cfg(test)]")?;
+                return Some((rel, content[start..].to_string()));
+            }
+            if !is_test_file(&rel, framework) {
+                return None;
+            }
+            Some((rel, std::fs::read_to_string(e.path()).ok()?))
+        })
+        .next()
+        .map(|(rel, content)| (rel, content.chars().take(MAX_EXAMPLE_CHARS).collect()))
+}
+
+/// 截取符号所在的代码；未指定或找不到时返回整个文件
+fn target_code(source: &str, language: &str, symbol: Option<&str>) -> String {
+    let code = symbol
+        .and_then(|name| {
+            crate::symbol_engine::extract_symbols_from_source(source, language)
+                .into_iter()
+                .find(|s| s.name == name)
+        })
+        .map(|s| {
+            source.lines()
+                .skip(s.range.start_line)
+                .take(s.range.end_line - s.range.start_line + 1)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_else(|| source.to_string());
+    code.chars().take(MAX_TARGET_CHARS).collect()
+}
+
+/// Rust 源文件中已有的 `mod tests` 模块：返回模块体结束的 `}` 的字节位置
+fn existing_test_module(source: &str) -> Option<usize> {
+    let start = source.find("mod tests {")? + "mod tests ".len();
+    closing_brace(source, start)
+}
+
+/// `open` 处的 `{` 对应的 `}` 位置，跳过字符串、字符字面量与注释中的括号
+fn closing_brace(source: &str, open: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0usize;
+    let mut i = open;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'\'' if bytes.get(i + 2) == Some(&b'\'') => i += 2,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// 去掉模型仍然返回的 `mod tests { use super::*; ... }` 外层，只保留测试函数
+fn strip_test_module(code: &str) -> String {
+    let Some(open) = code.find("mod tests {").map(|i| i + "mod tests ".len()) else {
+        return code.to_string();
+    };
+    let Some(close) = closing_brace(code, open) else { return code.to_string() };
+    code[open + 1..close]
+        .lines()
+        .filter(|line| line.trim() != "use super::*;")
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 把新测试插入已有的测试模块末尾
+fn insert_into_test_module(source: &str, module_end: usize, test_code: &str) -> String {
+    let indented: Vec<String> = strip_test_module(test_code)
+        .trim_matches('\n')
+        .lines()
+        .map(|line| if line.trim().is_empty() || line.starts_with("    ") { line.to_string() } else { format!("    {}", line) })
+        .collect();
+    format!("{}\n\n{}\n{}", source[..module_end].trim_end(), indented.join("\n"), &source[module_end..])
+}
+
+fn build_prompt(
+    framework: TestFramework,
+    rel_path: &str,
+    location: &TestLocation,
+    code: &str,
+    symbol: Option<&str>,
+    example: Option<&(String, String)>,
+    has_test_module: bool,
+) -> String {
+    let mut prompt = format!(
+        "Write unit tests using {:?} for {} in `{}`.\n\n```\n{}\n```\n\n",
+        framework,
+        symbol.map(|s| format!("`{}`", s)).unwrap_or_else(|| "the code".to_string()),
+        rel_path,
+        code
+    );
+    if let Some((path, content)) = example {
+        prompt.push_str(&format!("Follow the conventions of this existing test (`{}`):\n\n```\n{}\n```\n\n", path, content));
+    }
+    if has_test_module {
+        prompt.push_str("The source file already has a `#[cfg(test)] mod tests` module; the tests will be inserted into it, so write only the new `#[test]` functions without a module wrapper or imports, and do not reuse existing test names.\n");
+    } else if location.append_to_source {
+        prompt.push_str("The tests will be appended to the end of the source file, so write a single `#[cfg(test)] mod tests { use super::*; ... }` block.\n");
+    } else {
+        prompt.push_str(&format!("The tests will be written to `{}`; include the imports needed from `{}`.\n", location.path, rel_path));
+    }
+    prompt.push_str("Respond with only a JSON object: {\"test_code\": \"<complete test code>\", \"test_names\": [\"<test name>\", ...]}");
+    prompt
+}
+
+/// 从模型回复中解析测试代码，兼容 JSON 与纯代码块两种格式
+fn parse_response(text: &str) -> Option<(String, Vec<String>)> {
+    let json_text = match (text.find('{'), text.rfind('}')) {
+        (Some(start), Some(end)) if end > start => &text[start..=end],
+        _ => "",
+    };
+    if let Ok(value) = serde_json::from_str::<Value>(json_text) {
+        if let Some(code) = value["test_code"].as_str().filter(|c| !c.trim().is_empty()) {
+            let names = value["test_names"].as_array()
+                .map(|a| a.iter().filter_map(|n| n.as_str().map(String::from)).collect())
+                .unwrap_or_default();
+            return Some((code.to_string(), names));
+        }
+    }
+
+    // 回退：取第一个代码块
+    let start = text.find("```")?;
+    let body = &text[start + 3..];
+    let body = &body[body.find('\n')? + 1..];
+    let end = body.find("```")?;
+    let code = body[..end].to_string();
+    (!code.trim().is_empty()).then_some((code, Vec::new()))
+}
+
+/// Rust 源文件对应的模块路径（`src/a/b.rs` -> `a::b`，`lib.rs` / `main.rs` 为空）
+fn rust_module_path(rel_path: &str) -> String {
+    let path = rel_path.strip_suffix(".rs").unwrap_or(rel_path);
+    let path = path.rsplit_once("src/").map(|(_, rest)| rest).unwrap_or(path);
+    let mut parts: Vec<&str> = path.split('/').filter(|p| !p.is_empty()).collect();
+    if matches!(parts.last(), Some(&"mod") | Some(&"lib") | Some(&"main")) {
+        parts.pop();
+    }
+    parts.join("::")
+}
+
+/// 只运行新生成的测试；测试名来自模型回复，只保留标识符字符后再拼入命令
+fn run_command(framework: TestFramework, rel_path: &str, location: &TestLocation, test_names: &[String]) -> String {
+    let names: Vec<&str> = test_names
+        .iter()
+        .map(|n| n.trim())
+        .filter(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .collect();
+    match framework {
+        TestFramework::Cargo => {
+            let module = rust_module_path(rel_path);
+            let prefix = if module.is_empty() { "tests::".to_string() } else { format!("{}::tests::", module) };
+            if names.is_empty() {
+                format!("cargo test -- {}", prefix)
+            } else {
+                let filters: Vec<String> = names.iter().map(|n| format!("{}{}", prefix, n)).collect();
+                format!("cargo test -- --exact {}", filters.join(" "))
+            }
+        }
+        TestFramework::Vitest => format!("npx vitest run {}", location.path),
+        TestFramework::Jest => format!("npx jest {}", location.path),
+        TestFramework::Pytest if !names.is_empty() => format!("python -m pytest {} -k \"{}\"", location.path, names.join(" or ")),
+        TestFramework::Pytest => format!("python -m pytest {}", location.path),
+        TestFramework::Go => {
+            let dir = Path::new(&location.path).parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+            if names.is_empty() {
+                format!("go test ./{}", dir)
+            } else {
+                format!("go test ./{} -run '^({})$'", dir, names.join("|"))
+            }
+        }
+    }
+}
+
+async fn run_tests(root: &Path, command: String) -> TestRunResult {
+    #[cfg(target_os = "windows")]
+    let (shell, arg) = ("cmd", "/C");
+    #[cfg(not(target_os = "windows"))]
+    let (shell, arg) = ("sh", "-c");
+
+    let output = tokio::time::timeout(
+        Duration::from_secs(RUN_TIMEOUT_SECS),
+        tokio::process::Command::new(shell).arg(arg).arg(&command).current_dir(root).kill_on_drop(true).output(),
+    )
+    .await;
+
+    match output {
+        Ok(Ok(out)) => {
+            let text = format!("{}{}", String::from_utf8_lossy(&out.stdout), String::from_utf8_lossy(&out.stderr));
+            let tail: Vec<&str> = text.lines().rev().take(80).collect();
+            TestRunResult {
+                command,
+                success: out.status.success(),
+                exit_code: out.status.code(),
+                output: tail.into_iter().rev().collect::<Vec<_>>().join("\n"),
+            }
+        }
+        Ok(Err(e)) => TestRunResult { command, success: false, exit_code: None, output: format!("Failed to run tests: {}", e) },
+        Err(_) => TestRunResult { command, success: false, exit_code: None, output: format!("Timed out after {}s", RUN_TIMEOUT_SECS) },
+    }
+}
+
+// ============================================================================
+// Tauri 命令
+// ============================================================================
+
+/// 为文件或符号生成单元测试并写入约定位置
+#[command]
+pub async fn generate_tests(
+    sessions: State<'_, Mutex<SessionStore>>,
+    provider_config: AIProviderConfig,
+    project_root: String,
+    path: String,
+    symbol: Option<String>,
+    run: Option<bool>,
+) -> Result<GeneratedTests, String> {
+    let root = PathBuf::from(&project_root);
+    let rel_path = path.replace('\\', "/");
+    let source_path = crate::commands::core_wrappers::ensure_in_root(&project_root, &rel_path, "read")?;
+    let source = std::fs::read_to_string(&source_path)
+        .map_err(|e| format!("Failed to read {}: {}", rel_path, e))?;
+
+    let framework = detect_framework(&root, &rel_path)?;
+    let location = test_location(framework, &rel_path);
+    let target = crate::commands::core_wrappers::ensure_in_root(&project_root, &location.path, "write")?;
+    let test_module_end = if location.append_to_source { existing_test_module(&source) } else { None };
+    let language_map = crate::language_map::LanguageMap::load(&project_root);
+    let language = language_map.for_path(&rel_path).unwrap_or_else(|| extension(&rel_path));
+    let code = target_code(&source, language, symbol.as_deref());
+    let example = {
+        let root = root.clone();
+        let rel_path = rel_path.clone();
+        tokio::task::spawn_blocking(move || find_example_test(&root, framework, &rel_path))
+            .await
+            .map_err(|e| format!("Task join error: {}", e))?
+    };
+
+    let prompt = build_prompt(framework, &rel_path, &location, &code, symbol.as_deref(), example.as_ref(), test_module_end.is_some());
+    let messages = vec![
+        Message {
+            role: "system".to_string(),
+            content: Content::Text("You are an expert at writing focused, idiomatic unit tests.".to_string()),
+            tool_calls: None,
+            tool_call_id: None,
+        },
+        Message {
+            role: "user".to_string(),
+            content: Content::Text(prompt),
+            tool_calls: None,
+            tool_call_id: None,
+        },
+    ];
+    let response = crate::ai_utils::fetch_ai_completion(&provider_config, messages, None).await?;
+    let (test_code, test_names) = parse_response(&extract_text_content(&response.content))
+        .ok_or_else(|| "Model response did not contain test code".to_string())?;
+
+    // 通过原子会话写入
+    let operation = if location.append_to_source {
+        let content = match test_module_end {
+            Some(end) => insert_into_test_module(&source, end, &test_code),
+            None => format!("{}\n\n{}\n", source.trim_end(), test_code.trim_end()),
+        };
+        FileOperationRequest {
+            path: target.to_string_lossy().to_string(),
+            op_type: FileOperationType::Update,
+            content: Some(content),
+            original_content: Some(source.clone()),
+        }
+    } else {
+        let existing = std::fs::read_to_string(&target).ok();
+        FileOperationRequest {
+            path: target.to_string_lossy().to_string(),
+            op_type: if existing.is_some() { FileOperationType::Update } else { FileOperationType::Create },
+            content: Some(match &existing {
+                Some(old) => format!("{}\n\n{}\n", old.trim_end(), test_code.trim_end()),
+                None => format!("{}\n", test_code.trim_end()),
+            }),
+            original_content: existing,
+        }
+    };
+
+    let session_id = atomic_write_start_internal(&sessions)?;
+    atomic_write_add_operation_internal(&sessions, session_id.clone(), operation)?;
+    let commit = atomic_write_commit_internal(&sessions, session_id.clone())?;
+    println!("[TestGen] Wrote {} tests for {} to {}", test_names.len(), rel_path, location.path);
+
+    let run_result = if run.unwrap_or(false) && commit.success {
+        Some(run_tests(&root, run_command(framework, &rel_path, &location, &test_names)).await)
+    } else {
+        None
+    };
+
+    Ok(GeneratedTests {
+        framework,
+        location,
+        test_code,
+        test_names,
+        session_id,
+        applied: commit.success,
+        run: run_result,
+    })
+}
+
+// ============================================================================
+// 测试
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location_by_framework() {
+        assert_eq!(test_location(TestFramework::Cargo, "src/lib.rs"), TestLocation { path: "src/lib.rs".to_string(), append_to_source: true });
+        assert_eq!(test_location(TestFramework::Vitest, "src/utils/math.ts").path, "src/utils/math.test.ts");
+        assert_eq!(test_location(TestFramework::Jest, "index.jsx").path, "index.test.js");
+        assert_eq!(test_location(TestFramework::Pytest, "pkg/calc.py").path, "tests/test_calc.py");
+        assert_eq!(test_location(TestFramework::Go, "pkg/calc.go").path, "pkg/calc_test.go");
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let json = "Here you go:\n{\"test_code\": \"#[test]\\nfn adds() {}\", \"test_names\": [\"adds\"]}";
+        assert_eq!(parse_response(json), Some(("#[test]\nfn adds() {}".to_string(), vec!["adds".to_string()])));
+
+        let fenced = "```python\ndef test_add():\n    assert add(1, 2) == 3\n```";
+        assert_eq!(parse_response(fenced).unwrap().0, "def test_add():\n    assert add(1, 2) == 3\n");
+
+        assert_eq!(parse_response("no code here"), None);
+    }
+
+    #[test]
+    fn test_insert_into_existing_test_module() {
+        let source = "fn add(a: i32, b: i32) -> i32 { a + b }\n\n#[cfg(test)]\nmod tests {\n    use super::*;\n\n    #[test]\n    fn braces() {\n        assert_eq!(format!(\"{}\", '}'), \"}\");\n    }\n}\n";
+        let end = existing_test_module(source).unwrap();
+        assert_eq!(&source[end..], "}\n");
+
+        let generated = "#[cfg(test)]\nmod tests {\n    use super::*;\n\n    #[test]\n    fn adds() {\n        assert_eq!(add(1, 2), 3);\n    }\n}";
+        let updated = insert_into_test_module(source, end, generated);
+        assert_eq!(updated.matches("mod tests").count(), 1);
+        assert!(updated.contains("    }\n\n    #[test]\n    fn adds() {\n        assert_eq!(add(1, 2), 3);\n    }\n}\n"));
+        assert_eq!(existing_test_module("fn main() {}"), None);
+    }
+
+    #[test]
+    fn test_run_command_filters_generated_tests() {
+        let location = test_location(TestFramework::Cargo, "src/utils/math.rs");
+        let names = vec!["adds".to_string(), "rm -rf /".to_string()];
+        assert_eq!(
+            run_command(TestFramework::Cargo, "src/utils/math.rs", &location, &names),
+            "cargo test -- --exact utils::math::tests::adds"
+        );
+        assert_eq!(run_command(TestFramework::Cargo, "src/lib.rs", &location, &[]), "cargo test -- tests::");
+        assert_eq!(rust_module_path("crates/core/src/parser/mod.rs"), "parser");
+
+        let go = test_location(TestFramework::Go, "pkg/calc.go");
+        assert_eq!(run_command(TestFramework::Go, "pkg/calc.go", &go, &["TestAdd".to_string()]), "go test ./pkg -run '^(TestAdd)$'");
+    }
+}
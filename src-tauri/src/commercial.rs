@@ -26,20 +26,19 @@ pub mod impls {
             &self,
             config: &AIProviderConfig,
             messages: Vec<Message>,
-            event_id: &str,
+            _event_id: &str,
             tools: Option<Vec<serde_json::Value>>,
-            _callback: Box<dyn Fn(String) + Send>,
+            callback: Box<dyn Fn(String) + Send>,
         ) -> Result<(), String> {
-            // 注意：如果 ifainew_core::ai::stream_chat 目前不支持 tools 参数，
-            // 我们可以暂时忽略或通过其他方式注入。
-            // 但为了修复编译错误，必须匹配核心 trait 的方法签名。
-            ifainew_core::ai::stream_chat(
-                self.app.clone(),
-                config.clone(),
-                messages,
-                event_id.to_string(),
-                true
-            ).await
+            // 所有协议均由 ai_utils 处理，数据块经回调发送；
+            // 温度调度、max_tokens 与重试在 ai_utils 的请求构建层生效
+            if crate::anthropic_api::is_anthropic(config) {
+                return crate::ai_utils::stream_chat_anthropic(config, messages, tools, callback).await;
+            }
+            if crate::gemini_api::is_gemini(config) {
+                return crate::ai_utils::stream_chat_gemini(config, messages, tools, callback).await;
+            }
+            crate::ai_utils::stream_chat_openai(config, messages, tools, callback).await
         }
     }
 
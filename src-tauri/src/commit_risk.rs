@@ -0,0 +1,303 @@
+/*!
+Commit Risk - 提交前风险分析
+============================
+
+在应用 Agent 的修改（原子提交 / 写文件审批）之前评估风险，结果随审批请求一起展示：
+
+- 公共 API 变更：修改或删除了 `pub` / `export` 符号（结合符号索引统计引用数）
+- 大量删除：删除行数多或占原文件比例高，或直接删除文件
+- 配置 / 迁移文件：依赖清单、构建配置、数据库迁移等
+- 可选的 LLM 快速审查：对汇总 diff 给出风险等级与理由
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use crate::commands::symbol_commands::SymbolIndexState;
+use crate::language_map::LanguageMap;
+use crate::core_traits::ai::{AIProviderConfig, Content, Message};
+
+/// 超过该删除行数视为大量删除
+const LARGE_DELETION_LINES: usize = 50;
+/// 超过该删除比例视为大量删除（原文件至少 20 行）
+const LARGE_DELETION_RATIO: f32 = 0.3;
+/// 发送给 LLM 审查的 diff 最大字符数
+const MAX_REVIEW_DIFF_CHARS: usize = 16_000;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 待应用的文件修改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposedChange {
+    pub path: String,
+    /// 修改前内容（新建文件为 None）
+    pub old_content: Option<String>,
+    /// 修改后内容（删除文件为 None）
+    pub new_content: Option<String>,
+}
+
+/// 单项风险因素
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RiskFactor {
+    /// "public_api" | "large_deletion" | "file_deletion" | "config" | "migration" | "llm_review"
+    pub kind: String,
+    pub path: String,
+    pub detail: String,
+    pub weight: u32,
+}
+
+/// 风险评估结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RiskReport {
+    /// 0-100
+    pub score: u32,
+    /// "low" | "medium" | "high"
+    pub level: String,
+    pub reasons: Vec<RiskFactor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub llm_review: Option<String>,
+}
+
+// ============================================================================
+// Heuristics
+// ============================================================================
+
+fn level_for(score: u32) -> &'static str {
+    match score {
+        0..=24 => "low",
+        25..=59 => "medium",
+        _ => "high",
+    }
+}
+
+fn finalize(reasons: Vec<RiskFactor>, llm_review: Option<String>) -> RiskReport {
+    let score = reasons.iter().map(|r| r.weight).sum::<u32>().min(100);
+    RiskReport { score, level: level_for(score).to_string(), reasons, llm_review }
+}
+
+fn language_for<'a>(language_map: &'a LanguageMap, path: &'a str) -> &'a str {
+    language_map.for_path(path)
+        .unwrap_or_else(|| Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or(""))
+}
+
+/// 判断符号所在行是否为公共声明
+fn is_public_declaration(line: &str, name: &str, language: &str) -> bool {
+    let line = line.trim_start();
+    match language {
+        "rust" => line.starts_with("pub ") && !line.starts_with("pub(crate)") && !line.starts_with("pub(super)"),
+        "typescript" | "javascript" => line.starts_with("export "),
+        "python" => !name.starts_with('_'),
+        _ => false,
+    }
+}
+
+/// 源码中的公共符号：名称 -> 符号文本
+fn public_symbols(content: &str, language: &str) -> Vec<(String, String)> {
+    let lines: Vec<&str> = content.lines().collect();
+    crate::symbol_engine::extract_symbols_from_source(content, language)
+        .into_iter()
+        .filter(|s| lines.get(s.range.start_line).map(|l| is_public_declaration(l, &s.name, language)).unwrap_or(false))
+        .map(|s| {
+            let end = (s.range.end_line + 1).min(lines.len());
+            (s.name, lines[s.range.start_line..end].join("\n"))
+        })
+        .collect()
+}
+
+fn is_migration(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    lower.contains("migrations/") || lower.contains("migrate/") || lower.ends_with(".sql")
+}
+
+fn is_config(path: &str) -> bool {
+    let lower = path.to_lowercase().replace('\\', "/");
+    let name = lower.rsplit('/').next().unwrap_or(&lower);
+    matches!(
+        name,
+        "cargo.toml" | "cargo.lock" | "package.json" | "package-lock.json" | "pnpm-lock.yaml" | "yarn.lock"
+            | "tsconfig.json" | "tauri.conf.json" | "dockerfile" | "docker-compose.yml" | "vite.config.ts"
+            | "pyproject.toml" | "requirements.txt" | "go.mod" | "build.rs"
+    ) || name.starts_with(".env")
+        || lower.contains(".github/workflows/")
+}
+
+/// (删除行数, 新增行数)
+fn line_changes(old: &str, new: &str) -> (usize, usize) {
+    git2::Patch::from_buffers(old.as_bytes(), None, new.as_bytes(), None, None)
+        .and_then(|p| p.line_stats())
+        .map(|(_, added, deleted)| (deleted, added))
+        .unwrap_or((0, 0))
+}
+
+/// 启发式风险分析
+pub fn analyze_changes(changes: &[ProposedChange], index: Option<&SymbolIndexState>) -> RiskReport {
+    let mut reasons = Vec::new();
+    let factor = |kind: &str, path: &str, detail: String, weight: u32| RiskFactor {
+        kind: kind.to_string(),
+        path: path.to_string(),
+        detail,
+        weight,
+    };
+    let language_map = LanguageMap::default();
+
+    for change in changes {
+        let path = change.path.as_str();
+
+        if is_migration(path) {
+            reasons.push(factor("migration", path, "Database migration file".to_string(), 30));
+        } else if is_config(path) {
+            reasons.push(factor("config", path, "Build / dependency / environment configuration".to_string(), 15));
+        }
+
+        let Some(old) = change.old_content.as_deref() else { continue };
+        let new = change.new_content.as_deref();
+
+        match new {
+            None => reasons.push(factor("file_deletion", path, format!("File deleted ({} lines)", old.lines().count()), 20)),
+            Some(new) => {
+                let (deleted, _) = line_changes(old, new);
+                let total = old.lines().count();
+                let ratio = if total > 0 { deleted as f32 / total as f32 } else { 0.0 };
+                if deleted >= LARGE_DELETION_LINES || (total >= 20 && ratio >= LARGE_DELETION_RATIO) {
+                    reasons.push(factor("large_deletion", path, format!("{} of {} lines deleted", deleted, total), 25));
+                }
+            }
+        }
+
+        // 公共 API 变更
+        let language = language_for(&language_map, path);
+        let new_symbols = new.map(|n| public_symbols(n, language)).unwrap_or_default();
+        for (name, body) in public_symbols(old, language) {
+            let action = match new_symbols.iter().find(|(n, _)| *n == name) {
+                None => "removed",
+                Some((_, new_body)) if *new_body != body => "modified",
+                _ => continue,
+            };
+            let references = index
+                .map(|idx| idx.find_references(&name).iter().map(|r| r.referenced_in.len()).sum::<usize>())
+                .unwrap_or(0);
+            let detail = if references > 0 {
+                format!("Public API `{}` {} ({} references)", name, action, references)
+            } else {
+                format!("Public API `{}` {}", name, action)
+            };
+            let weight = if action == "removed" { 20 } else { 10 } + if references > 0 { 10 } else { 0 };
+            reasons.push(factor("public_api", path, detail, weight));
+        }
+    }
+
+    finalize(reasons, None)
+}
+
+/// 汇总 diff
+pub fn aggregate_diff(changes: &[ProposedChange]) -> String {
+    let mut out = String::new();
+    for change in changes {
+        let old = change.old_content.as_deref().unwrap_or("");
+        let new = change.new_content.as_deref().unwrap_or("");
+        let text = git2::Patch::from_buffers(old.as_bytes(), Some(Path::new(&change.path)), new.as_bytes(), Some(Path::new(&change.path)), None)
+            .and_then(|mut p| p.to_buf())
+            .map(|buf| String::from_utf8_lossy(&buf).to_string())
+            .unwrap_or_default();
+        out.push_str(&text);
+    }
+    out
+}
+
+/// 在启发式结果基础上追加 LLM 快速审查
+pub async fn review_with_llm(config: &AIProviderConfig, changes: &[ProposedChange], report: RiskReport) -> RiskReport {
+    let diff: String = aggregate_diff(changes).chars().take(MAX_REVIEW_DIFF_CHARS).collect();
+    let prompt = format!(
+        "Review this diff for risk before it is applied (breaking changes, data loss, security, config mistakes).\n\n```diff\n{}\n```\n\nRespond with only a JSON object: {{\"risk\": \"low|medium|high\", \"reasons\": [\"...\"]}}",
+        diff
+    );
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: Content::Text(prompt),
+        tool_calls: None,
+        tool_call_id: None,
+    }];
+
+    let response = match crate::ai_utils::fetch_ai_completion(config, messages, None).await {
+        Ok(msg) => crate::intelligence_router::extract_text_content(&msg.content),
+        Err(e) => {
+            eprintln!("[CommitRisk] LLM review failed: {}", e);
+            return report;
+        }
+    };
+
+    let value = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if end > start => serde_json::from_str::<serde_json::Value>(&response[start..=end]).ok(),
+        _ => None,
+    };
+    let Some(value) = value else { return report };
+
+    let weight = match value["risk"].as_str().unwrap_or("low") {
+        "high" => 30,
+        "medium" => 15,
+        _ => 0,
+    };
+    let review = value["reasons"].as_array()
+        .map(|a| a.iter().filter_map(|r| r.as_str()).collect::<Vec<_>>().join("; "))
+        .unwrap_or_default();
+
+    let mut reasons = report.reasons;
+    if weight > 0 {
+        reasons.push(RiskFactor { kind: "llm_review".to_string(), path: String::new(), detail: review.clone(), weight });
+    }
+    finalize(reasons, Some(review).filter(|r| !r.is_empty()))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, old: Option<&str>, new: Option<&str>) -> ProposedChange {
+        ProposedChange { path: path.to_string(), old_content: old.map(String::from), new_content: new.map(String::from) }
+    }
+
+    #[test]
+    fn test_config_and_migration_files() {
+        let report = analyze_changes(&[
+            change("Cargo.toml", Some("[package]\n"), Some("[package]\nname = \"x\"\n")),
+            change("db/migrations/001_init.sql", None, Some("CREATE TABLE t();")),
+        ], None);
+        let kinds: Vec<&str> = report.reasons.iter().map(|r| r.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["config", "migration"]);
+        assert_eq!(report.score, 45);
+        assert_eq!(report.level, "medium");
+    }
+
+    #[test]
+    fn test_deletions() {
+        let old = "line\n".repeat(100);
+        let report = analyze_changes(&[
+            change("notes.txt", Some(&old), Some("line\n")),
+            change("old.txt", Some("x\n"), None),
+        ], None);
+        assert_eq!(report.reasons[0].kind, "large_deletion");
+        assert_eq!(report.reasons[0].detail, "99 of 100 lines deleted");
+        assert_eq!(report.reasons[1].kind, "file_deletion");
+    }
+
+    #[test]
+    fn test_public_api_changes() {
+        let old = "pub fn keep() {}\npub fn change() -> u32 { 1 }\npub fn remove() {}\nfn private() {}\n";
+        let new = "pub fn keep() {}\npub fn change() -> u32 { 2 }\nfn private() { let _ = 1; }\n";
+        let report = analyze_changes(&[change("src/lib.rs", Some(old), Some(new))], None);
+        let details: Vec<&str> = report.reasons.iter().map(|r| r.detail.as_str()).collect();
+        assert_eq!(details, vec!["Public API `change` modified", "Public API `remove` removed"]);
+    }
+
+    #[test]
+    fn test_small_edit_is_low_risk() {
+        let report = analyze_changes(&[change("README.md", Some("# A\n"), Some("# B\n"))], None);
+        assert_eq!(report, RiskReport { score: 0, level: "low".to_string(), reasons: vec![], llm_review: None });
+    }
+}
@@ -0,0 +1,416 @@
+//! 社区版终端错误解析
+//!
+//! 按工具识别编译器 / lint 输出：rustc / cargo、TypeScript (tsc)、ESLint、Python traceback、Go，
+//! 其余 `file:line[:col]: message` 形式的行由通用规则兜底。
+//! 多行错误（rustc 的 span 块、tsc 与 Go 的续行、Python 的调用栈）合并为一条，`raw_line` 保留整块原文。
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::commands::error_commands::ParsedErrorFrontend;
+
+/// 解析结果与下一条待解析行的下标
+type Parsed = Option<(Vec<ParsedErrorFrontend>, usize)>;
+
+/// 解析终端输出中的所有错误 / 警告
+pub fn parse_output(output: &str) -> Vec<ParsedErrorFrontend> {
+    let cleaned = ansi_re().replace_all(output, "");
+    let lines: Vec<&str> = cleaned.lines().collect();
+    let mut errors = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let parsed = parse_rust(&lines, index)
+            .or_else(|| parse_python(&lines, index))
+            .or_else(|| parse_eslint(&lines, index))
+            .or_else(|| parse_typescript(&lines, index))
+            .or_else(|| parse_go(&lines, index))
+            .or_else(|| parse_generic(&lines, index));
+        match parsed {
+            Some((found, next)) => {
+                errors.extend(found);
+                index = next.max(index + 1);
+            }
+            None => index += 1,
+        }
+    }
+    errors
+}
+
+fn cached(cell: &'static OnceLock<Regex>, pattern: &str) -> &'static Regex {
+    cell.get_or_init(|| Regex::new(pattern).unwrap())
+}
+
+fn ansi_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    cached(&RE, r"\x1b\[[0-9;]*[A-Za-z]")
+}
+
+/// 根据文件扩展名判断语言（与商业版 `Language` 的 Debug 名称一致）
+fn language_for(path: &str) -> &'static str {
+    let ext = path.rsplit('.').next().unwrap_or("").to_ascii_lowercase();
+    match ext.as_str() {
+        "rs" => "Rust",
+        "ts" | "tsx" | "mts" | "cts" => "TypeScript",
+        "js" | "jsx" | "mjs" | "cjs" | "vue" | "svelte" => "JavaScript",
+        "py" => "Python",
+        "go" => "Go",
+        "java" => "Java",
+        "c" | "cc" | "cpp" | "cxx" | "h" | "hpp" => "Cpp",
+        _ => "Generic",
+    }
+}
+
+/// 根据消息开头的关键字判断级别
+fn level_for(message: &str) -> &'static str {
+    let lower = message.trim_start().to_ascii_lowercase();
+    if lower.starts_with("warning") {
+        "Warning"
+    } else if lower.starts_with("note") || lower.starts_with("info") {
+        "Note"
+    } else if lower.starts_with("help") || lower.starts_with("hint") {
+        "Help"
+    } else {
+        "Error"
+    }
+}
+
+fn capitalize(level: &str) -> String {
+    let mut chars = level.chars();
+    chars.next().map(|c| c.to_ascii_uppercase().to_string() + chars.as_str()).unwrap_or_default()
+}
+
+/// 从 `start` 开始、以 `prefix` 开头的续行范围的结束下标
+fn continuation_end(lines: &[&str], start: usize, prefix: &str) -> usize {
+    let mut end = start;
+    while end < lines.len() && lines[end].starts_with(prefix) && !lines[end].trim().is_empty() {
+        end += 1;
+    }
+    end
+}
+
+// ============================================================================
+// rustc / cargo
+// ============================================================================
+
+/// `error[E0308]: mismatched types` 开头、到空行为止的一块
+fn parse_rust(lines: &[&str], start: usize) -> Parsed {
+    static HEADER: OnceLock<Regex> = OnceLock::new();
+    static LOCATION: OnceLock<Regex> = OnceLock::new();
+    static LINT: OnceLock<Regex> = OnceLock::new();
+    let header = cached(&HEADER, r"^(error|warning)(?:\[(\w+)\])?: (.+)$");
+    let caps = header.captures(lines[start])?;
+
+    let mut end = start + 1;
+    while end < lines.len() && !lines[end].trim().is_empty() && !header.is_match(lines[end]) {
+        end += 1;
+    }
+    let block = &lines[start..end];
+    // 没有位置的汇总行（could not compile、generated N warnings）直接跳过
+    let Some(location) = block[1..]
+        .iter()
+        .find_map(|l| cached(&LOCATION, r"^\s*--> (.+?):(\d+):(\d+)$").captures(l))
+    else {
+        return Some((Vec::new(), end));
+    };
+
+    let level = &caps[1];
+    let mut message = caps[3].to_string();
+    let mut lint = None;
+    for line in &block[1..] {
+        let trimmed = line.trim_start();
+        if let Some(name) = cached(&LINT, r"#\[(?:warn|deny)\(([\w:]+)\)\]").captures(trimmed) {
+            lint = Some(name[1].to_string());
+        } else if trimmed.starts_with("= note:") || trimmed.starts_with("= help:") {
+            message.push('\n');
+            message.push_str(&trimmed[2..]);
+        }
+    }
+    let code = caps.get(2).map(|m| m.as_str().to_string()).or(lint).unwrap_or_else(|| level.to_ascii_uppercase());
+
+    Some((
+        vec![ParsedErrorFrontend {
+            code,
+            message,
+            file: location[1].to_string(),
+            line: location[2].parse().unwrap_or(0),
+            column: location[3].parse().ok(),
+            level: capitalize(level),
+            language: "Rust".to_string(),
+            raw_line: block.join("\n"),
+        }],
+        end,
+    ))
+}
+
+// ============================================================================
+// Python
+// ============================================================================
+
+/// `Traceback (most recent call last):`（或 SyntaxError 的 `File "...", line N`）开始，
+/// 到顶格的异常行结束；位置取最内层的项目内调用帧
+fn parse_python(lines: &[&str], start: usize) -> Parsed {
+    static FRAME: OnceLock<Regex> = OnceLock::new();
+    static EXCEPTION: OnceLock<Regex> = OnceLock::new();
+    let frame_re = cached(&FRAME, r#"^\s*File "(.+?)", line (\d+)"#);
+    if lines[start].trim() != "Traceback (most recent call last):" && !frame_re.is_match(lines[start]) {
+        return None;
+    }
+
+    let mut frames: Vec<(String, u32)> = Vec::new();
+    for (offset, line) in lines[start..].iter().enumerate() {
+        if let Some(frame) = frame_re.captures(line) {
+            frames.push((frame[1].to_string(), frame[2].parse().unwrap_or(0)));
+            continue;
+        }
+        if offset == 0 || line.starts_with(char::is_whitespace) || line.is_empty() {
+            continue;
+        }
+        let exception = cached(&EXCEPTION, r"^([A-Za-z_][\w.]*(?:Error|Exception|Warning|Exit|Interrupt))(?::\s?(.*))?$")
+            .captures(line)?;
+        let (file, line_number) = frames
+            .iter()
+            .rev()
+            .find(|(file, _)| !file.contains("site-packages") && !file.starts_with('<'))
+            .or(frames.last())
+            .cloned()?;
+        let name = exception[1].to_string();
+        let end = start + offset + 1;
+        return Some((
+            vec![ParsedErrorFrontend {
+                code: name.clone(),
+                message: exception.get(2).map(|m| m.as_str().to_string()).filter(|m| !m.is_empty()).unwrap_or_else(|| name.clone()),
+                file,
+                line: line_number,
+                column: None,
+                level: if name.ends_with("Warning") { "Warning" } else { "Error" }.to_string(),
+                language: "Python".to_string(),
+                raw_line: lines[start..end].join("\n"),
+            }],
+            end,
+        ));
+    }
+    None
+}
+
+// ============================================================================
+// ESLint (stylish)
+// ============================================================================
+
+/// 顶格的文件名，后跟 `  12:5  error  message  rule-name` 形式的缩进行
+fn parse_eslint(lines: &[&str], start: usize) -> Parsed {
+    static FILE: OnceLock<Regex> = OnceLock::new();
+    static ENTRY: OnceLock<Regex> = OnceLock::new();
+    let file = cached(&FILE, r"^(\S.*\.(?:[cm]?[jt]sx?|vue|svelte))$").captures(lines[start])?;
+    let entry_re = cached(&ENTRY, r"^\s+(\d+):(\d+)\s+(error|warning)\s+(.+?)(?:\s{2,}(\S+))?\s*$");
+    if !lines.get(start + 1).is_some_and(|l| entry_re.is_match(l)) {
+        return None;
+    }
+
+    let path = file[1].to_string();
+    let mut errors = Vec::new();
+    let mut end = start + 1;
+    while let Some(entry) = lines.get(end).and_then(|l| entry_re.captures(l)) {
+        errors.push(ParsedErrorFrontend {
+            code: entry.get(5).map(|m| m.as_str()).unwrap_or("eslint").to_string(),
+            message: entry[4].to_string(),
+            file: path.clone(),
+            line: entry[1].parse().unwrap_or(0),
+            column: entry[2].parse().ok(),
+            level: capitalize(&entry[3]),
+            language: language_for(&path).to_string(),
+            raw_line: lines[end].to_string(),
+        });
+        end += 1;
+    }
+    Some((errors, end))
+}
+
+// ============================================================================
+// TypeScript (tsc)
+// ============================================================================
+
+/// `src/a.ts(12,5): error TS2322: ...` 或 `src/a.ts:12:5 - error TS2322: ...`，缩进续行并入消息
+fn parse_typescript(lines: &[&str], start: usize) -> Parsed {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let caps = cached(
+        &RE,
+        r"^(.+?\.(?:[cm]?[jt]sx?|vue))(?:\((\d+),(\d+)\)|:(\d+):(\d+))\s*[:-]\s*(error|warning)\s+(TS\d+):\s*(.*)$",
+    )
+    .captures(lines[start])?;
+    let end = continuation_end(lines, start + 1, "  ");
+    let mut message = caps[8].to_string();
+    for line in &lines[start + 1..end] {
+        message.push('\n');
+        message.push_str(line.trim());
+    }
+
+    Some((
+        vec![ParsedErrorFrontend {
+            code: caps[7].to_string(),
+            message,
+            file: caps[1].to_string(),
+            line: caps.get(2).or(caps.get(4)).and_then(|m| m.as_str().parse().ok()).unwrap_or(0),
+            column: caps.get(3).or(caps.get(5)).and_then(|m| m.as_str().parse().ok()),
+            level: capitalize(&caps[6]),
+            language: "TypeScript".to_string(),
+            raw_line: lines[start..end].join("\n"),
+        }],
+        end,
+    ))
+}
+
+// ============================================================================
+// Go
+// ============================================================================
+
+/// `./main.go:12:5: undefined: foo`，制表符缩进的续行（have / want）并入消息
+fn parse_go(lines: &[&str], start: usize) -> Parsed {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let caps = cached(&RE, r"^((?:vet: )?[^\s:]+\.go):(\d+)(?::(\d+))?:\s*(.+)$").captures(lines[start])?;
+    let end = continuation_end(lines, start + 1, "\t");
+    let mut message = caps[4].to_string();
+    for line in &lines[start + 1..end] {
+        message.push('\n');
+        message.push_str(line.trim());
+    }
+
+    Some((
+        vec![ParsedErrorFrontend {
+            code: "ERROR".to_string(),
+            message,
+            file: caps[1].trim_start_matches("vet: ").to_string(),
+            line: caps[2].parse().unwrap_or(0),
+            column: caps.get(3).and_then(|m| m.as_str().parse().ok()),
+            level: "Error".to_string(),
+            language: "Go".to_string(),
+            raw_line: lines[start..end].join("\n"),
+        }],
+        end,
+    ))
+}
+
+// ============================================================================
+// 通用规则
+// ============================================================================
+
+/// `file.ext:line[:col]: [error|warning:] message`（gcc / clang / javac / pylint 等）
+fn parse_generic(lines: &[&str], start: usize) -> Parsed {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    static LEVEL_PREFIX: OnceLock<Regex> = OnceLock::new();
+    let caps = cached(&RE, r"^\s*((?:[A-Za-z]:)?[^\s:]+\.[A-Za-z0-9]+):(\d+)(?::(\d+))?:?\s*(.*)$").captures(lines[start])?;
+    let file = caps[1].to_string();
+    let raw_message = caps[4].trim();
+    let message = cached(&LEVEL_PREFIX, r"(?i)^(?:fatal )?(?:error|warning|note|info|help)\s*:\s*").replace(raw_message, "");
+
+    Some((
+        vec![ParsedErrorFrontend {
+            code: "ERROR".to_string(),
+            message: message.to_string(),
+            language: language_for(&file).to_string(),
+            file,
+            line: caps[2].parse().unwrap_or(0),
+            column: caps.get(3).and_then(|m| m.as_str().parse().ok()),
+            level: level_for(raw_message.trim_start_matches("fatal ")).to_string(),
+            raw_line: lines[start].to_string(),
+        }],
+        start + 1,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(errors: &[ParsedErrorFrontend]) -> Vec<String> {
+        errors
+            .iter()
+            .map(|e| format!("{} {} {}:{}:{} [{}] {}", e.language, e.level, e.file, e.line, e.column.unwrap_or(0), e.code, e.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_rust_blocks() {
+        let output = "\x1b[1m\x1b[31merror[E0308]\x1b[0m: mismatched types
+  --> src/main.rs:4:18
+   |
+4  |     let x: u32 = \"a\";
+   |            ---   ^^^ expected `u32`, found `&str`
+   |
+   = help: try using a conversion method
+
+warning: unused variable: `y`
+ --> src/lib.rs:2:9
+  |
+2 |     let y = 1;
+  |         ^ help: if this is intentional, prefix it with an underscore: `_y`
+  |
+  = note: `#[warn(unused_variables)]` on by default
+
+warning: `demo` (lib) generated 1 warning
+error: could not compile `demo` due to 1 previous error";
+        let errors = parse_output(output);
+        assert_eq!(
+            summary(&errors),
+            vec![
+                "Rust Error src/main.rs:4:18 [E0308] mismatched types\nhelp: try using a conversion method",
+                "Rust Warning src/lib.rs:2:9 [unused_variables] unused variable: `y`",
+            ]
+        );
+        assert!(errors[0].raw_line.starts_with("error[E0308]") && errors[0].raw_line.contains("^^^ expected"));
+    }
+
+    #[test]
+    fn test_parse_typescript_and_eslint() {
+        let output = "src/app.ts(12,5): error TS2322: Type 'string' is not assignable to type 'number'.
+  Type 'a' is not assignable to type 'b'.
+src/util.tsx:3:1 - error TS2304: Cannot find name 'foo'.
+
+/home/me/project/src/index.js
+  1:10  error    'foo' is defined but never used  no-unused-vars
+  2:1   warning  Unexpected console statement     no-console
+
+✖ 2 problems (1 error, 1 warning)";
+        assert_eq!(
+            summary(&parse_output(output)),
+            vec![
+                "TypeScript Error src/app.ts:12:5 [TS2322] Type 'string' is not assignable to type 'number'.\nType 'a' is not assignable to type 'b'.",
+                "TypeScript Error src/util.tsx:3:1 [TS2304] Cannot find name 'foo'.",
+                "JavaScript Error /home/me/project/src/index.js:1:10 [no-unused-vars] 'foo' is defined but never used",
+                "JavaScript Warning /home/me/project/src/index.js:2:1 [no-console] Unexpected console statement",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_python_go_and_generic() {
+        let output = "Traceback (most recent call last):
+  File \"/app/main.py\", line 10, in <module>
+    run()
+  File \"/app/jobs.py\", line 3, in run
+    parse(x)
+  File \"/usr/lib/python3/site-packages/lib.py\", line 88, in parse
+    raise ValueError(\"bad input\")
+ValueError: bad input
+  File \"setup.py\", line 2
+    def f(:
+          ^
+SyntaxError: invalid syntax
+# example.com/demo
+./main.go:12:5: undefined: foo
+./main.go:20:9: cannot use x (variable of type int) as string value in return statement
+\thave (int)
+\twant (string)
+main.c:7:3: warning: implicit declaration of function 'bar'
+Visit http://localhost:3000 for details";
+        assert_eq!(
+            summary(&parse_output(output)),
+            vec![
+                "Python Error /app/jobs.py:3:0 [ValueError] bad input",
+                "Python Error setup.py:2:0 [SyntaxError] invalid syntax",
+                "Go Error ./main.go:12:5 [ERROR] undefined: foo",
+                "Go Error ./main.go:20:9 [ERROR] cannot use x (variable of type int) as string value in return statement\nhave (int)\nwant (string)",
+                "Cpp Warning main.c:7:3 [ERROR] implicit declaration of function 'bar'",
+            ]
+        );
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(not(feature = "commercial"))]
+pub mod error_parser;
+
 use crate::core_traits::ai::{AIService, AIProviderConfig, Message};
 use crate::core_traits::rag::RagService;
 use crate::core_traits::agent::AgentService;
@@ -23,46 +26,16 @@ impl AIService for BasicAIService {
         tools: Option<Vec<serde_json::Value>>,
         callback: Box<dyn Fn(String) + Send>,
     ) -> Result<(), String> {
-        // Enhanced implementation that supports tool_calls
-        match ai_utils::fetch_ai_completion(config, messages, tools).await {
-            Ok(msg) => {
-                // 1. Send text content
-                match &msg.content {
-                    crate::core_traits::ai::Content::Text(text) => {
-                        if !text.is_empty() {
-                            callback(text.to_string());
-                        }
-                    }
-                    _ => {}
-                }
-
-                // 2. Send tool_calls if present (format matches ifainew_core)
-                if let Some(tool_calls) = &msg.tool_calls {
-                    use serde_json::json;
-
-                    for tc in tool_calls {
-                        // Create tool_call chunk in the same format as ifainew_core
-                        let tool_call_event = json!({
-                            "type": "tool_call",
-                            "tool_call": {
-                                "index": 0,
-                                "id": &tc.id,
-                                "type": &tc.r#type,
-                                "function": {
-                                    "name": &tc.function.name,
-                                    "arguments": &tc.function.arguments
-                                }
-                            }
-                        });
-
-                        callback(tool_call_event.to_string());
-                    }
-                }
-
-                Ok(())
-            }
-            Err(e) => Err(e),
+        // Anthropic / Gemini：真实流式输出，数据块已转换为 OpenAI 兼容格式
+        if crate::anthropic_api::is_anthropic(config) {
+            return ai_utils::stream_chat_anthropic(config, messages, tools, callback).await;
+        }
+        if crate::gemini_api::is_gemini(config) {
+            return ai_utils::stream_chat_gemini(config, messages, tools, callback).await;
         }
+
+        // OpenAI 兼容协议：SSE 真实流式输出
+        ai_utils::stream_chat_openai(config, messages, tools, callback).await
     }
 }
 
@@ -0,0 +1,424 @@
+/*!
+Completion Cache - 流式响应缓存
+===============================
+
+对话流式响应结束后，将最终组装的响应（思考内容、正文、工具调用）写入磁盘，用于：
+
+- 重新打开会话时直接回放，完整还原思考过程与工具调用块，无需依赖存储的历史
+- 识别崩溃或强制退出时中断的流（没有结束标记的缓存即为中断的流）
+
+缓存文件为 `~/.ifai/completion_cache/{event_id}.jsonl`：
+请求开始时写入首行元数据；结束时由组装的响应生成数据块（与流式事件负载格式一致），
+每行一个，最后追加结束标记。
+超过 `MAX_AGE_DAYS` 或数量超过 `MAX_CACHED_STREAMS` 的旧缓存在启动时清理。
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+/// 保留的缓存数
+const MAX_CACHED_STREAMS: usize = 500;
+/// 缓存保留天数
+const MAX_AGE_DAYS: u64 = 14;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamStatus {
+    /// 仍在接收
+    Streaming,
+    Completed,
+    Failed,
+    /// 没有结束标记且不在接收中（应用崩溃或被强制退出）
+    Interrupted,
+}
+
+/// 缓存文件中的一行
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum CacheLine {
+    Meta { event_id: String, session_id: Option<String>, started_at: i64 },
+    Chunk { at: i64, data: String },
+    End { at: i64, status: StreamStatus, error: Option<String> },
+}
+
+/// 缓存摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedStreamInfo {
+    pub event_id: String,
+    pub session_id: Option<String>,
+    pub started_at: i64,
+    pub status: StreamStatus,
+    pub chunk_count: usize,
+}
+
+/// 回放用的完整缓存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedStream {
+    #[serde(flatten)]
+    pub info: CachedStreamInfo,
+    /// 由最终响应生成的数据块（与流式事件负载格式一致）
+    pub chunks: Vec<String>,
+    /// 由数据块聚合出的正文
+    pub content: String,
+    /// 由数据块聚合出的思考内容
+    pub reasoning: String,
+    /// 聚合后的工具调用
+    pub tool_calls: Vec<Value>,
+    pub error: Option<String>,
+}
+
+/// 流结束时组装的响应
+#[derive(Debug, Clone, Default)]
+pub struct AssembledResponse {
+    pub reasoning: String,
+    pub content: String,
+    /// 按 `index` 合并后的工具调用
+    pub tool_calls: BTreeMap<i64, Value>,
+}
+
+impl AssembledResponse {
+    /// 合并一个工具调用增量（`name` / `arguments` 按 `index` 拼接）
+    pub fn merge_tool_call(&mut self, call: &Value) {
+        merge_tool_call(&mut self.tool_calls, call);
+    }
+
+    /// 回放用的数据块
+    fn chunks(&self) -> Vec<String> {
+        let mut chunks = Vec::new();
+        for (key, text) in [("reasoning_content", &self.reasoning), ("content", &self.content)] {
+            if !text.is_empty() {
+                chunks.push(serde_json::json!({ "choices": [{ "index": 0, "delta": { key: text } }] }).to_string());
+            }
+        }
+        for call in self.tool_calls.values() {
+            chunks.push(serde_json::json!({ "type": "tool_call", "tool_call": call }).to_string());
+        }
+        chunks
+    }
+}
+
+fn merge_tool_call(calls: &mut BTreeMap<i64, Value>, call: &Value) {
+    let entry = calls.entry(call["index"].as_i64().unwrap_or(0)).or_insert_with(|| {
+        serde_json::json!({ "id": "", "type": "function", "function": { "name": "", "arguments": "" } })
+    });
+    if let Some(id) = call["id"].as_str().filter(|id| !id.is_empty()) {
+        entry["id"] = Value::String(id.to_string());
+    }
+    for field in ["name", "arguments"] {
+        if let Some(part) = call["function"][field].as_str() {
+            let merged = format!("{}{}", entry["function"][field].as_str().unwrap_or(""), part);
+            entry["function"][field] = Value::String(merged);
+        }
+    }
+}
+
+// ============================================================================
+// Storage
+// ============================================================================
+
+/// 正在写入的流
+static ACTIVE: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+fn active() -> &'static Mutex<HashSet<String>> {
+    ACTIVE.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+fn cache_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("completion_cache")
+}
+
+fn cache_file(dir: &std::path::Path, event_id: &str) -> PathBuf {
+    let safe: String = event_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    dir.join(format!("{}.jsonl", safe))
+}
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// 一个流的缓存写入器
+pub struct StreamTee {
+    event_id: String,
+    writer: Mutex<Option<BufWriter<File>>>,
+}
+
+impl StreamTee {
+    fn write_line(&self, line: &CacheLine) {
+        let Ok(mut guard) = self.writer.lock() else { return };
+        let Some(writer) = guard.as_mut() else { return };
+        let result = serde_json::to_string(line)
+            .map_err(|e| e.to_string())
+            .and_then(|json| writeln!(writer, "{}", json).and_then(|_| writer.flush()).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("[CompletionCache] Failed to write {}: {}", self.event_id, e);
+            *guard = None;
+        }
+    }
+
+    /// 写入最终组装的响应与结束标记（只生效一次）
+    pub fn finish(&self, result: &Result<(), String>, response: &AssembledResponse) {
+        for chunk in response.chunks() {
+            self.write_line(&CacheLine::Chunk { at: now_millis(), data: chunk });
+        }
+        let (status, error) = match result {
+            Ok(()) => (StreamStatus::Completed, None),
+            Err(e) => (StreamStatus::Failed, Some(e.clone())),
+        };
+        self.write_line(&CacheLine::End { at: now_millis(), status, error });
+        if let Ok(mut guard) = self.writer.lock() {
+            *guard = None;
+        }
+        if let Ok(mut active) = active().lock() {
+            active.remove(&self.event_id);
+        }
+    }
+}
+
+fn begin_in(dir: &std::path::Path, event_id: &str, session_id: Option<&str>) -> Option<StreamTee> {
+    std::fs::create_dir_all(dir).ok()?;
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(cache_file(dir, event_id))
+        .map_err(|e| eprintln!("[CompletionCache] Failed to open cache for {}: {}", event_id, e))
+        .ok()?;
+    if let Ok(mut active) = active().lock() {
+        active.insert(event_id.to_string());
+    }
+    let tee = StreamTee {
+        event_id: event_id.to_string(),
+        writer: Mutex::new(Some(BufWriter::new(file))),
+    };
+    tee.write_line(&CacheLine::Meta {
+        event_id: event_id.to_string(),
+        session_id: session_id.map(String::from),
+        started_at: now_millis(),
+    });
+    Some(tee)
+}
+
+/// 开始缓存一个流（同一 event_id 会覆盖旧缓存）
+pub fn begin(event_id: &str, session_id: Option<&str>) -> Option<StreamTee> {
+    begin_in(&cache_dir(), event_id, session_id)
+}
+
+// ============================================================================
+// Replay
+// ============================================================================
+
+/// 合并一个数据块到聚合结果
+fn aggregate_chunk(stream: &mut CachedStream, partial_calls: &mut BTreeMap<i64, Value>, chunk: &str) {
+    let Ok(json) = serde_json::from_str::<Value>(chunk) else {
+        stream.content.push_str(chunk);
+        return;
+    };
+    if json["type"] == "tool_call" {
+        merge_tool_call(partial_calls, &json["tool_call"]);
+        return;
+    }
+    let delta = &json["choices"][0]["delta"];
+    if let Some(reasoning) = delta["reasoning_content"].as_str() {
+        stream.reasoning.push_str(reasoning);
+    }
+    if let Some(content) = delta["content"].as_str() {
+        stream.content.push_str(content);
+    }
+    for call in delta["tool_calls"].as_array().into_iter().flatten() {
+        merge_tool_call(partial_calls, call);
+    }
+}
+
+fn read_stream(path: &std::path::Path, include_chunks: bool) -> Option<CachedStream> {
+    let file = File::open(path).ok()?;
+    let mut stream: Option<CachedStream> = None;
+    let mut partial_calls = BTreeMap::new();
+    let mut ended = None;
+
+    // 崩溃时最后一行可能不完整，解析失败的行直接跳过
+    for line in BufReader::new(file).lines().map_while(Result::ok) {
+        match serde_json::from_str::<CacheLine>(&line) {
+            Ok(CacheLine::Meta { event_id, session_id, started_at }) => {
+                stream = Some(CachedStream {
+                    info: CachedStreamInfo { event_id, session_id, started_at, status: StreamStatus::Streaming, chunk_count: 0 },
+                    chunks: Vec::new(),
+                    content: String::new(),
+                    reasoning: String::new(),
+                    tool_calls: Vec::new(),
+                    error: None,
+                });
+            }
+            Ok(CacheLine::Chunk { data, .. }) => {
+                let Some(stream) = stream.as_mut() else { continue };
+                stream.info.chunk_count += 1;
+                if include_chunks {
+                    aggregate_chunk(stream, &mut partial_calls, &data);
+                    stream.chunks.push(data);
+                }
+            }
+            Ok(CacheLine::End { status, error, .. }) => ended = Some((status, error)),
+            Err(_) => continue,
+        }
+    }
+
+    let mut stream = stream?;
+    stream.tool_calls.extend(partial_calls.into_values());
+    match ended {
+        Some((status, error)) => {
+            stream.info.status = status;
+            stream.error = error;
+        }
+        None => {
+            let streaming = active().lock().map(|a| a.contains(&stream.info.event_id)).unwrap_or(false);
+            if !streaming {
+                stream.info.status = StreamStatus::Interrupted;
+            }
+        }
+    }
+    Some(stream)
+}
+
+fn cached_files(dir: &std::path::Path) -> Vec<(PathBuf, std::time::SystemTime)> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<_> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+        .filter_map(|p| {
+            let modified = std::fs::metadata(&p).and_then(|m| m.modified()).ok()?;
+            Some((p, modified))
+        })
+        .collect();
+    // 最近的在前
+    files.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+    files
+}
+
+/// 清理过期与超量的缓存
+fn prune_in(dir: &std::path::Path, max_streams: usize, max_age: std::time::Duration) -> usize {
+    let now = std::time::SystemTime::now();
+    let mut removed = 0;
+    for (i, (path, modified)) in cached_files(dir).into_iter().enumerate() {
+        let expired = now.duration_since(modified).is_ok_and(|age| age > max_age);
+        if (i >= max_streams || expired) && std::fs::remove_file(&path).is_ok() {
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// 启动时清理旧缓存
+pub fn prune() {
+    let removed = prune_in(&cache_dir(), MAX_CACHED_STREAMS, std::time::Duration::from_secs(MAX_AGE_DAYS * 24 * 3600));
+    if removed > 0 {
+        println!("[CompletionCache] Pruned {} cached streams", removed);
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 读取缓存的流，用于重新显示
+#[tauri::command]
+pub fn get_cached_stream(event_id: String) -> Result<Option<CachedStream>, String> {
+    Ok(read_stream(&cache_file(&cache_dir(), &event_id), true))
+}
+
+/// 列出缓存的流（可按会话过滤，或只列出中断的流）
+#[tauri::command]
+pub fn list_cached_streams(session_id: Option<String>, interrupted_only: Option<bool>) -> Vec<CachedStreamInfo> {
+    let interrupted_only = interrupted_only.unwrap_or(false);
+    cached_files(&cache_dir())
+        .into_iter()
+        .filter_map(|(path, _)| read_stream(&path, false))
+        .map(|s| s.info)
+        .filter(|info| session_id.is_none() || info.session_id == session_id)
+        .filter(|info| !interrupted_only || info.status == StreamStatus::Interrupted)
+        .collect()
+}
+
+#[tauri::command]
+pub fn delete_cached_stream(event_id: String) -> Result<(), String> {
+    let path = cache_file(&cache_dir(), &event_id);
+    if path.exists() {
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to delete cached stream: {}", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("ifai_completion_cache_{}", uuid::Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_tee_and_replay() {
+        let dir = temp_dir();
+        let tee = begin_in(&dir, "chat_1", Some("s1")).unwrap();
+        let mut response = AssembledResponse { reasoning: "Let me think".to_string(), content: "Hello".to_string(), ..Default::default() };
+        response.merge_tool_call(&serde_json::json!({"index":0,"id":"call_1","function":{"name":"bash","arguments":"{\"comm"}}));
+        response.merge_tool_call(&serde_json::json!({"index":0,"id":"","function":{"name":"","arguments":"and\":\"ls\"}"}}));
+
+        // 结束前：仍在接收，尚无数据块
+        let partial = read_stream(&cache_file(&dir, "chat_1"), true).unwrap();
+        assert_eq!(partial.info.status, StreamStatus::Streaming);
+        assert_eq!(partial.info.chunk_count, 0);
+
+        tee.finish(&Ok(()), &response);
+        let stream = read_stream(&cache_file(&dir, "chat_1"), true).unwrap();
+        assert_eq!(stream.info.status, StreamStatus::Completed);
+        assert_eq!(stream.info.session_id.as_deref(), Some("s1"));
+        assert_eq!(stream.chunks.len(), 3);
+        assert_eq!(stream.reasoning, "Let me think");
+        assert_eq!(stream.content, "Hello");
+        assert_eq!(stream.tool_calls[0]["function"]["arguments"], r#"{"command":"ls"}"#);
+        assert_eq!(stream.tool_calls[0]["id"], "call_1");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_interrupted_stream_recovery() {
+        let dir = temp_dir();
+        let tee = begin_in(&dir, "chat_2", None).unwrap();
+        drop(tee);
+        // 模拟崩溃：不在活动列表且最后一行被截断
+        active().lock().unwrap().remove("chat_2");
+        let mut file = OpenOptions::new().append(true).open(cache_file(&dir, "chat_2")).unwrap();
+        write!(file, "{{\"kind\":\"chunk\",\"at\":1,\"da").unwrap();
+
+        let stream = read_stream(&cache_file(&dir, "chat_2"), true).unwrap();
+        assert_eq!(stream.info.status, StreamStatus::Interrupted);
+        assert!(stream.content.is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_prune_keeps_recent() {
+        let dir = temp_dir();
+        for i in 0..3 {
+            begin_in(&dir, &format!("chat_{}", i), None).unwrap().finish(&Err("boom".to_string()), &AssembledResponse::default());
+        }
+        assert_eq!(prune_in(&dir, 2, std::time::Duration::from_secs(3600)), 1);
+        assert_eq!(cached_files(&dir).len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,191 @@
+/*!
+Context Plan - 上下文预算分配说明
+=================================
+
+记录每次请求中各部分占用的 Token，以及被丢弃或压缩的内容，
+通过 `{event_id}_context_plan` 事件发送给前端，用于解释模型为什么"忘记"了某些内容。
+
+分区：
+- `system`：主系统提示词（含 workspace 配置、语言规范）
+- `summary`：对话摘要
+- `rag`：检索注入的项目上下文
+- `pinned`：显式附带的代码（编辑器选区、粘贴内容解析出的片段）
+- `history`：对话消息
+- `tools`：工具定义（tools 参数及提示词中的工具说明）
+
+预算取 IFAI.md `summarization.max_tokens`（超过即触发对话压缩）。
+*/
+
+use serde::{Deserialize, Serialize};
+
+use crate::core_traits::ai::Message;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ContextSection {
+    System,
+    Summary,
+    Rag,
+    Pinned,
+    History,
+    Tools,
+}
+
+const SECTIONS: [ContextSection; 6] = [
+    ContextSection::System,
+    ContextSection::Summary,
+    ContextSection::Rag,
+    ContextSection::Pinned,
+    ContextSection::History,
+    ContextSection::Tools,
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SectionUsage {
+    pub section: ContextSection,
+    pub tokens: usize,
+    /// 片段/消息/工具数量
+    pub items: usize,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdjustmentKind {
+    /// 完全未发送
+    Dropped,
+    /// 截断或摘要后发送
+    Compressed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextAdjustment {
+    pub section: ContextSection,
+    pub kind: AdjustmentKind,
+    pub detail: String,
+    pub original_tokens: usize,
+    pub kept_tokens: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextPlan {
+    pub model: String,
+    pub encoding: String,
+    pub budget: usize,
+    pub total_tokens: usize,
+    pub sections: Vec<SectionUsage>,
+    pub adjustments: Vec<ContextAdjustment>,
+}
+
+/// 在组装请求的过程中逐项记录
+pub struct ContextPlanner {
+    model: String,
+    budget: usize,
+    sections: Vec<SectionUsage>,
+    adjustments: Vec<ContextAdjustment>,
+}
+
+impl ContextPlanner {
+    pub fn new(model: &str, budget: usize) -> Self {
+        Self {
+            model: model.to_string(),
+            budget,
+            sections: SECTIONS.iter().map(|&section| SectionUsage { section, tokens: 0, items: 0 }).collect(),
+            adjustments: Vec::new(),
+        }
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        crate::token_counter::count_tokens_for_model(text, &self.model).count
+    }
+
+    fn add_tokens(&mut self, section: ContextSection, tokens: usize) {
+        if let Some(usage) = self.sections.iter_mut().find(|u| u.section == section) {
+            usage.tokens += tokens;
+            usage.items += 1;
+        }
+    }
+
+    /// 记录注入的一段文本
+    pub fn add(&mut self, section: ContextSection, text: &str) {
+        if !text.trim().is_empty() {
+            let tokens = self.count(text);
+            self.add_tokens(section, tokens);
+        }
+    }
+
+    /// 记录一条消息（含消息格式开销）
+    pub fn add_message(&mut self, section: ContextSection, message: &Message) {
+        let tokens = crate::conversation::token_counter::count_messages_tokens_for_model(std::slice::from_ref(message), &self.model);
+        self.add_tokens(section, tokens);
+    }
+
+    pub fn dropped(&mut self, section: ContextSection, detail: impl Into<String>, original_tokens: usize) {
+        self.adjustments.push(ContextAdjustment {
+            section,
+            kind: AdjustmentKind::Dropped,
+            detail: detail.into(),
+            original_tokens,
+            kept_tokens: 0,
+        });
+    }
+
+    /// 记录压缩；实际没有变小时忽略
+    pub fn compressed(&mut self, section: ContextSection, detail: impl Into<String>, original_tokens: usize, kept_tokens: usize) {
+        if kept_tokens >= original_tokens {
+            return;
+        }
+        self.adjustments.push(ContextAdjustment {
+            section,
+            kind: AdjustmentKind::Compressed,
+            detail: detail.into(),
+            original_tokens,
+            kept_tokens,
+        });
+    }
+
+    pub fn finish(self) -> ContextPlan {
+        ContextPlan {
+            encoding: crate::token_counter::tokenizer_for_model(&self.model).name().to_string(),
+            total_tokens: self.sections.iter().map(|u| u.tokens).sum(),
+            model: self.model,
+            budget: self.budget,
+            sections: self.sections,
+            adjustments: self.adjustments,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_traits::ai::Content;
+
+    #[test]
+    fn test_plan_totals_and_adjustments() {
+        let mut planner = ContextPlanner::new("gpt-4o", 150_000);
+        planner.add(ContextSection::System, "You are a helpful assistant.");
+        planner.add(ContextSection::Rag, "   ");
+        planner.add_message(ContextSection::History, &Message {
+            role: "user".to_string(),
+            content: Content::Text("hello".to_string()),
+            tool_calls: None,
+            tool_call_id: None,
+        });
+        planner.compressed(ContextSection::Rag, "truncated", 500, 200);
+        planner.compressed(ContextSection::Rag, "not smaller", 100, 100);
+        planner.dropped(ContextSection::Pinned, "withheld", 0);
+
+        let plan = planner.finish();
+        assert_eq!(plan.sections.len(), 6);
+        let usage = |s: ContextSection| plan.sections.iter().find(|u| u.section == s).unwrap().clone();
+        assert_eq!(usage(ContextSection::System).items, 1);
+        assert_eq!(usage(ContextSection::Rag).items, 0);
+        assert_eq!(usage(ContextSection::History).items, 1);
+        assert_eq!(plan.total_tokens, usage(ContextSection::System).tokens + usage(ContextSection::History).tokens);
+        assert_eq!(plan.adjustments.len(), 2);
+        assert_eq!(plan.adjustments[0].kind, AdjustmentKind::Compressed);
+
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["sections"][0]["section"], "system");
+    }
+}
@@ -0,0 +1,476 @@
+/*!
+Conversation Export - 会话导出
+==============================
+
+把一次会话导出为 Markdown、独立 HTML 或 JSON 文件：
+
+- 消息来源：前端传入的消息列表，或 `.ifai/sessions/archive/{id}.json` 中的会话快照
+- 工具调用以参数 JSON 展示，工具结果按 `tool_call_id` 标注，超长结果截断
+- 开头汇总 `agent_write_file` / `agent_edit_file` / `agent_delete_file` 涉及的文件及增删行数
+- HTML 为单文件（内联样式），代码块按围栏语言标注
+- 导出内容经过凭证脱敏；未指定路径时通过对话框选择保存位置
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+
+use super::merge::{archive_path, SessionHistory};
+use crate::core_traits::ai::{Content, ContentPart, Message};
+use crate::secret_scrub::scrub_secrets;
+
+/// 单条工具结果导出的最大字符数
+const MAX_TOOL_RESULT_CHARS: usize = 4_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Json => "json",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Markdown => "Markdown",
+            Self::Html => "HTML",
+            Self::Json => "JSON",
+        }
+    }
+}
+
+/// 会话中修改过的文件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChange {
+    pub path: String,
+    /// "write" | "edit" | "delete"（多次修改时取最后一次）
+    pub action: String,
+    pub added: usize,
+    pub removed: usize,
+    /// 修改次数
+    pub operations: usize,
+}
+
+// ============================================================================
+// 内容整理
+// ============================================================================
+
+fn message_text(content: &Content) -> String {
+    match content {
+        Content::Text(text) => text.clone(),
+        Content::Parts(parts) => parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text, .. } => text.clone(),
+                ContentPart::ImageUrl { image_url } if image_url.url.starts_with("data:") => "[image]".to_string(),
+                ContentPart::ImageUrl { image_url } => format!("[image]({})", image_url.url),
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    }
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}\n… [{} more characters omitted]", &text[..end], text[end..].chars().count()),
+        None => text.to_string(),
+    }
+}
+
+/// 工具参数格式化为缩进 JSON；不是合法 JSON 时原样返回
+fn pretty_args(arguments: &str) -> String {
+    serde_json::from_str::<Value>(arguments)
+        .ok()
+        .and_then(|v| serde_json::to_string_pretty(&v).ok())
+        .unwrap_or_else(|| arguments.to_string())
+}
+
+fn line_count(text: &str) -> usize {
+    text.lines().count()
+}
+
+/// 从文件类工具调用中统计修改过的文件
+pub fn file_changes(messages: &[Message]) -> Vec<FileChange> {
+    let mut changes: Vec<FileChange> = Vec::new();
+    for call in messages.iter().flat_map(|m| m.tool_calls.iter().flatten()) {
+        let Ok(args) = serde_json::from_str::<Value>(&call.function.arguments) else { continue };
+        let Some(path) = args["rel_path"].as_str().filter(|p| !p.is_empty()) else { continue };
+        let (action, added, removed) = match call.function.name.as_str() {
+            "agent_write_file" => ("write", line_count(args["content"].as_str().unwrap_or("")), 0),
+            "agent_edit_file" => {
+                let (mut added, mut removed) = (0, 0);
+                for edit in args["edits"].as_array().into_iter().flatten() {
+                    added += line_count(edit["replace"].as_str().unwrap_or(""));
+                    removed += line_count(edit["search"].as_str().unwrap_or(""));
+                }
+                for line in args["diff"].as_str().unwrap_or("").lines() {
+                    if line.starts_with('+') && !line.starts_with("+++") {
+                        added += 1;
+                    } else if line.starts_with('-') && !line.starts_with("---") {
+                        removed += 1;
+                    }
+                }
+                ("edit", added, removed)
+            }
+            "agent_delete_file" => ("delete", 0, 0),
+            _ => continue,
+        };
+
+        match changes.iter_mut().find(|c| c.path == path) {
+            Some(change) => {
+                change.action = action.to_string();
+                change.added += added;
+                change.removed += removed;
+                change.operations += 1;
+            }
+            None => changes.push(FileChange { path: path.to_string(), action: action.to_string(), added, removed, operations: 1 }),
+        }
+    }
+    changes
+}
+
+fn role_label(role: &str) -> &str {
+    match role {
+        "user" => "User",
+        "assistant" => "Assistant",
+        "system" => "System",
+        "tool" => "Tool result",
+        other => other,
+    }
+}
+
+// ============================================================================
+// Markdown
+// ============================================================================
+
+/// 比内容中最长的反引号序列更长的代码围栏
+fn fence_for(text: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn code_block(lang: &str, text: &str) -> String {
+    let fence = fence_for(text);
+    format!("{}{}\n{}\n{}\n", fence, lang, text.trim_end_matches('\n'), fence)
+}
+
+pub fn render_markdown(title: &str, messages: &[Message]) -> String {
+    let mut out = format!("# {}\n\n", title);
+    out.push_str(&format!("_Exported {} · {} messages_\n\n", chrono::Local::now().format("%Y-%m-%d %H:%M"), messages.len()));
+
+    let changes = file_changes(messages);
+    if !changes.is_empty() {
+        out.push_str("## Files changed\n\n| File | Change | + | - |\n| --- | --- | ---: | ---: |\n");
+        for change in &changes {
+            out.push_str(&format!("| `{}` | {} | {} | {} |\n", change.path, change.action, change.added, change.removed));
+        }
+        out.push('\n');
+    }
+
+    for message in messages {
+        let text = message_text(&message.content);
+        out.push_str("---\n\n");
+        match message.role.as_str() {
+            "system" => {
+                out.push_str(&format!("<details>\n<summary>System</summary>\n\n{}\n</details>\n\n", code_block("", &text)));
+                continue;
+            }
+            "tool" => {
+                let id = message.tool_call_id.as_deref().unwrap_or("");
+                out.push_str(&format!("### Tool result `{}`\n\n", id));
+                out.push_str(&code_block("", &truncate_chars(&text, MAX_TOOL_RESULT_CHARS)));
+                out.push('\n');
+                continue;
+            }
+            role => out.push_str(&format!("### {}\n\n", role_label(role))),
+        }
+        if !text.trim().is_empty() {
+            out.push_str(text.trim_end());
+            out.push_str("\n\n");
+        }
+        for call in message.tool_calls.iter().flatten() {
+            out.push_str(&format!("**Tool call** `{}` (`{}`)\n\n", call.function.name, call.id));
+            out.push_str(&code_block("json", &pretty_args(&call.function.arguments)));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// ============================================================================
+// HTML
+// ============================================================================
+
+const HTML_STYLE: &str = "body{font-family:-apple-system,BlinkMacSystemFont,'Segoe UI',sans-serif;max-width:900px;margin:2rem auto;padding:0 1rem;color:#1f2328;line-height:1.55}\
+.msg{border:1px solid #d0d7de;border-radius:8px;margin:1rem 0;padding:.75rem 1rem}\
+.role{font-weight:600;font-size:.85rem;text-transform:uppercase;color:#57606a;margin-bottom:.5rem}\
+.user{background:#f6f8fa}.tool{background:#fbfbf7}\
+.text{white-space:pre-wrap;margin:.5rem 0}\
+pre{background:#0d1117;color:#e6edf3;padding:.75rem;border-radius:6px;overflow-x:auto;font-size:.85rem}\
+table{border-collapse:collapse}td,th{border:1px solid #d0d7de;padding:.25rem .75rem}td.num{text-align:right}\
+.meta{color:#57606a;font-size:.9rem}summary{cursor:pointer;color:#57606a}";
+
+pub fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn html_code(lang: &str, code: &str) -> String {
+    let class = if lang.is_empty() { String::new() } else { format!(" class=\"language-{}\"", escape_html(lang)) };
+    format!("<pre><code{}>{}</code></pre>\n", class, escape_html(code.trim_end_matches('\n')))
+}
+
+/// 消息正文：围栏代码块渲染为 `<pre>`，其余文本保留换行
+fn html_body(text: &str) -> String {
+    let mut out = String::new();
+    let mut prose = String::new();
+    let mut code: Option<(String, String, String)> = None; // (fence, lang, body)
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        match code.as_mut() {
+            Some((fence, _, _)) if trimmed.starts_with(fence.as_str()) && trimmed.trim_start_matches('`').is_empty() => {
+                let (_, lang, body) = code.take().unwrap_or_default();
+                out.push_str(&html_code(&lang, &body));
+            }
+            Some((_, _, body)) => body.push_str(line),
+            None if trimmed.starts_with("```") => {
+                if !prose.trim().is_empty() {
+                    out.push_str(&format!("<div class=\"text\">{}</div>\n", escape_html(prose.trim_matches('\n'))));
+                }
+                prose.clear();
+                let fence_len = trimmed.len() - trimmed.trim_start_matches('`').len();
+                code = Some((trimmed[..fence_len].to_string(), trimmed[fence_len..].trim().to_string(), String::new()));
+            }
+            None => prose.push_str(line),
+        }
+    }
+    // 未闭合的代码块按代码输出
+    if let Some((_, lang, body)) = code {
+        out.push_str(&html_code(&lang, &body));
+    }
+    if !prose.trim().is_empty() {
+        out.push_str(&format!("<div class=\"text\">{}</div>\n", escape_html(prose.trim_matches('\n'))));
+    }
+    out
+}
+
+pub fn render_html(title: &str, messages: &[Message]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+        escape_html(title), HTML_STYLE, escape_html(title)
+    );
+    out.push_str(&format!(
+        "<p class=\"meta\">Exported {} · {} messages</p>\n",
+        chrono::Local::now().format("%Y-%m-%d %H:%M"),
+        messages.len()
+    ));
+
+    let changes = file_changes(messages);
+    if !changes.is_empty() {
+        out.push_str("<h2>Files changed</h2>\n<table>\n<tr><th>File</th><th>Change</th><th>+</th><th>-</th></tr>\n");
+        for change in &changes {
+            out.push_str(&format!(
+                "<tr><td><code>{}</code></td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n",
+                escape_html(&change.path), change.action, change.added, change.removed
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+
+    for message in messages {
+        let text = message_text(&message.content);
+        let role = escape_html(&message.role);
+        out.push_str(&format!("<div class=\"msg {}\">\n", role));
+        match message.role.as_str() {
+            "system" => {
+                out.push_str(&format!("<details><summary>System</summary>\n{}</details>\n", html_code("", &text)));
+            }
+            "tool" => {
+                out.push_str(&format!(
+                    "<details><summary>Tool result <code>{}</code></summary>\n{}</details>\n",
+                    escape_html(message.tool_call_id.as_deref().unwrap_or("")),
+                    html_code("", &truncate_chars(&text, MAX_TOOL_RESULT_CHARS))
+                ));
+            }
+            other => {
+                out.push_str(&format!("<div class=\"role\">{}</div>\n", escape_html(role_label(other))));
+                out.push_str(&html_body(&text));
+                for call in message.tool_calls.iter().flatten() {
+                    out.push_str(&format!(
+                        "<details><summary>Tool call <code>{}</code> ({})</summary>\n{}</details>\n",
+                        escape_html(&call.function.name),
+                        escape_html(&call.id),
+                        html_code("json", &pretty_args(&call.function.arguments))
+                    ));
+                }
+            }
+        }
+        out.push_str("</div>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+// ============================================================================
+// 导出
+// ============================================================================
+
+pub fn render(format: ExportFormat, title: &str, messages: &[Message]) -> Result<String, String> {
+    let rendered = match format {
+        ExportFormat::Markdown => render_markdown(title, messages),
+        ExportFormat::Html => render_html(title, messages),
+        ExportFormat::Json => serde_json::to_string_pretty(&json!({
+            "title": title,
+            "exportedAt": chrono::Utc::now().to_rfc3339(),
+            "fileChanges": file_changes(messages),
+            "messages": messages,
+        }))
+        .map_err(|e| e.to_string())?,
+    };
+    Ok(scrub_secrets(&rendered))
+}
+
+fn load_archived(project_root: &str, session_id: &str) -> Result<SessionHistory, String> {
+    let path = archive_path(project_root, session_id);
+    let json = std::fs::read_to_string(&path).map_err(|e| format!("Session {} not found ({}): {}", session_id, path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid session file {}: {}", path.display(), e))
+}
+
+/// 弹出保存对话框；用户取消时返回 None
+async fn pick_path(app: &AppHandle, format: ExportFormat, title: &str) -> Result<Option<std::path::PathBuf>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let file_name: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Export Conversation")
+        .set_file_name(format!("{}.{}", file_name, format.extension()))
+        .add_filter(format.label(), &[format.extension()])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+    match rx.await.map_err(|e| e.to_string())? {
+        Some(path) => path.into_path().map(Some).map_err(|e| e.to_string()),
+        None => Ok(None),
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 导出会话；`messages` 与 `session_id`（需要 `project_root`）二选一，
+/// 未指定 `path` 时弹出保存对话框。返回写入的路径，用户取消时返回 None
+#[tauri::command]
+pub async fn export_conversation(
+    app: AppHandle,
+    format: ExportFormat,
+    messages: Option<Vec<Message>>,
+    session_id: Option<String>,
+    project_root: Option<String>,
+    title: Option<String>,
+    path: Option<String>,
+) -> Result<Option<String>, String> {
+    let (messages, session_title) = match (messages, session_id) {
+        (Some(messages), _) => (messages, None),
+        (None, Some(id)) => {
+            let root = project_root.ok_or("project_root is required to export a saved session")?;
+            let session = load_archived(&root, &id)?;
+            (session.messages, Some(session.title).filter(|t| !t.is_empty()))
+        }
+        (None, None) => return Err("Either messages or session_id is required".to_string()),
+    };
+    let title = title.or(session_title).unwrap_or_else(|| "Conversation".to_string());
+
+    let path = match path {
+        Some(path) => std::path::PathBuf::from(path),
+        None => match pick_path(&app, format, &title).await? {
+            Some(path) => path,
+            None => return Ok(None),
+        },
+    };
+
+    let content = render(format, &title, &messages)?;
+    std::fs::write(&path, &content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    println!("[ConversationExport] Exported {} messages as {} to {}", messages.len(), format.label(), path.display());
+    Ok(Some(path.to_string_lossy().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_traits::ai::{FunctionCall, ToolCall};
+
+    fn msg(role: &str, text: &str) -> Message {
+        Message { role: role.to_string(), content: Content::Text(text.to_string()), tool_calls: None, tool_call_id: None }
+    }
+
+    fn call(id: &str, name: &str, args: Value) -> ToolCall {
+        ToolCall { id: id.into(), r#type: "function".into(), function: FunctionCall { name: name.into(), arguments: args.to_string() } }
+    }
+
+    fn conversation() -> Vec<Message> {
+        let mut assistant = msg("assistant", "Updating the parser:\n```rust\nfn a() {}\n```\nDone <ok>");
+        assistant.tool_calls = Some(vec![
+            call("call_1", "agent_write_file", json!({ "rel_path": "src/a.rs", "content": "fn a() {}\nfn b() {}\n" })),
+            call("call_2", "agent_edit_file", json!({ "rel_path": "src/a.rs", "edits": [{ "search": "fn b() {}", "replace": "fn b() {\n}\n" }] })),
+            call("call_3", "agent_read_file", json!({ "rel_path": "README.md" })),
+        ]);
+        let mut result = msg("tool", "ok ``` fenced");
+        result.tool_call_id = Some("call_1".into());
+        vec![msg("system", "You are IfAI"), msg("user", "fix a.rs"), assistant, result]
+    }
+
+    #[test]
+    fn test_file_changes_and_markdown() {
+        let messages = conversation();
+        let changes = file_changes(&messages);
+        assert_eq!(changes, vec![FileChange { path: "src/a.rs".into(), action: "edit".into(), added: 4, removed: 1, operations: 2 }]);
+
+        let md = render_markdown("Fix parser", &messages);
+        assert!(md.starts_with("# Fix parser\n"));
+        assert!(md.contains("| `src/a.rs` | edit | 4 | 1 |"));
+        assert!(md.contains("### User\n\nfix a.rs"));
+        assert!(md.contains("**Tool call** `agent_read_file` (`call_3`)\n\n```json\n{\n  \"rel_path\": \"README.md\"\n}\n```"));
+        // 结果中含有 ``` 时使用更长的围栏
+        assert!(md.contains("### Tool result `call_1`\n\n````\nok ``` fenced\n````"));
+        assert_eq!(fence_for("no ticks"), "```");
+    }
+
+    #[test]
+    fn test_html_escapes_and_code_blocks() {
+        let html = render_html("A <b> title", &conversation());
+        assert!(html.contains("<title>A &lt;b&gt; title</title>"));
+        assert!(html.contains("<pre><code class=\"language-rust\">fn a() {}</code></pre>"));
+        assert!(html.contains("<div class=\"text\">Done &lt;ok&gt;</div>"));
+        assert!(html.contains("Tool result <code>call_1</code>"));
+        assert!(!html.contains("<ok>"));
+
+        assert_eq!(truncate_chars("abcdef", 3), "abc\n… [3 more characters omitted]");
+    }
+}
@@ -0,0 +1,295 @@
+/*!
+Session Merge - 会话合并
+========================
+
+把两个（或多个）会话合并为一个新会话：
+
+- `concatenate`：按顺序拼接；`interleave`：按轮次（一条用户消息及其后的回复与工具结果）交替，
+  保证工具调用与其结果不被拆开
+- 只保留第一个会话的系统提示词，原有的对话摘要被丢弃，由合并后的历史重新生成
+- 内容相同的工具结果只保留第一次出现，之后的替换为简短说明（保留 `tool_call_id` 以维持配对）
+- 原会话快照写入 `.ifai/sessions/archive/{id}.json` 并设为只读，合并不修改原会话
+
+会话列表保存在前端，命令接收待合并会话的完整历史并返回新会话。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use super::summarizer;
+use super::SummarizationConfig;
+use crate::core_traits::ai::{AIProviderConfig, Content, Message};
+
+/// 小于该长度的工具结果不去重（替换说明并不更短）
+const DEDUP_MIN_CHARS: usize = 80;
+const DUPLICATE_NOTE: &str = "[Duplicate tool result omitted: identical to an earlier tool result in this conversation]";
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    Concatenate,
+    Interleave,
+}
+
+/// 待合并的会话
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionHistory {
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    pub messages: Vec<Message>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MergedSession {
+    pub id: String,
+    pub title: String,
+    pub messages: Vec<Message>,
+    pub merged_from: Vec<String>,
+    pub strategy: MergeStrategy,
+    pub deduplicated_tool_results: usize,
+    /// 生成摘要的模型；摘要失败时为空
+    pub summary_model: Option<String>,
+    /// 原会话的只读快照
+    pub archived: Vec<String>,
+}
+
+fn content_text(content: &Content) -> String {
+    match content {
+        Content::Text(text) => text.clone(),
+        Content::Parts(_) => serde_json::to_string(content).unwrap_or_default(),
+    }
+}
+
+fn is_summary(message: &Message) -> bool {
+    message.role == "system" && content_text(&message.content).starts_with(super::SUMMARY_HEADER)
+}
+
+/// 按轮次切分（跳过系统消息）；每轮以用户消息开始
+fn split_turns(messages: &[Message]) -> Vec<Vec<Message>> {
+    let mut turns: Vec<Vec<Message>> = Vec::new();
+    for message in messages.iter().filter(|m| m.role != "system") {
+        match turns.last_mut() {
+            Some(turn) if message.role != "user" => turn.push(message.clone()),
+            _ => turns.push(vec![message.clone()]),
+        }
+    }
+    turns
+}
+
+/// 内容相同的工具结果只保留第一次，返回替换的数量
+fn dedupe_tool_results(messages: &mut [Message]) -> usize {
+    let mut seen = HashSet::new();
+    let mut replaced = 0;
+    for message in messages.iter_mut().filter(|m| m.role == "tool") {
+        let text = content_text(&message.content);
+        if text.len() < DEDUP_MIN_CHARS {
+            continue;
+        }
+        if !seen.insert(text) {
+            message.content = Content::Text(DUPLICATE_NOTE.to_string());
+            replaced += 1;
+        }
+    }
+    replaced
+}
+
+/// 合并历史（不含摘要），返回消息与去重数量
+pub fn merge_histories(sessions: &[SessionHistory], strategy: MergeStrategy) -> (Vec<Message>, usize) {
+    let mut merged = Vec::new();
+    if let Some(system) = sessions
+        .iter()
+        .flat_map(|s| s.messages.iter())
+        .find(|m| m.role == "system" && !is_summary(m))
+    {
+        merged.push(system.clone());
+    }
+
+    let turns: Vec<Vec<Vec<Message>>> = sessions.iter().map(|s| split_turns(&s.messages)).collect();
+    match strategy {
+        MergeStrategy::Concatenate => merged.extend(turns.into_iter().flatten().flatten()),
+        MergeStrategy::Interleave => {
+            let rounds = turns.iter().map(|t| t.len()).max().unwrap_or(0);
+            for i in 0..rounds {
+                for session_turns in &turns {
+                    if let Some(turn) = session_turns.get(i) {
+                        merged.extend(turn.iter().cloned());
+                    }
+                }
+            }
+        }
+    }
+
+    let deduplicated = dedupe_tool_results(&mut merged);
+    (merged, deduplicated)
+}
+
+fn archive_dir(project_root: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("sessions").join("archive")
+}
+
+/// 会话快照路径（会话 id 中的特殊字符替换为 `_`）
+pub(super) fn archive_path(project_root: &str, session_id: &str) -> PathBuf {
+    let file_name: String = session_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    archive_dir(project_root).join(format!("{}.json", file_name))
+}
+
+/// 写入原会话的只读快照；已存在时保持不变
+fn archive_session(project_root: &str, session: &SessionHistory) -> Result<PathBuf, String> {
+    let path = archive_path(project_root, &session.id);
+    if path.exists() {
+        return Ok(path);
+    }
+    std::fs::create_dir_all(archive_dir(project_root)).map_err(|e| format!("Failed to create archive dir: {}", e))?;
+    let json = serde_json::to_string_pretty(session).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to archive session {}: {}", session.id, e))?;
+
+    let mut permissions = std::fs::metadata(&path).map_err(|e| e.to_string())?.permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&path, permissions).map_err(|e| format!("Failed to mark archive read-only: {}", e))?;
+    Ok(path)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn merge_sessions(
+    project_root: String,
+    provider_config: AIProviderConfig,
+    sessions: Vec<SessionHistory>,
+    strategy: MergeStrategy,
+) -> Result<MergedSession, String> {
+    if sessions.len() < 2 {
+        return Err("At least two sessions are required to merge".to_string());
+    }
+    let mut ids = HashSet::new();
+    if let Some(dup) = sessions.iter().find(|s| !ids.insert(s.id.as_str())) {
+        return Err(format!("Session {} is listed more than once", dup.id));
+    }
+
+    let archived = sessions
+        .iter()
+        .map(|s| archive_session(&project_root, s).map(|p| p.to_string_lossy().to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (mut messages, deduplicated) = merge_histories(&sessions, strategy);
+
+    // 重新生成摘要，插入在系统提示词之后
+    let config = SummarizationConfig::load(&project_root);
+    let history: Vec<Message> = messages.iter().filter(|m| m.role != "system").cloned().collect();
+    let summary_model = match summarizer::generate_summary(&project_root, &provider_config, history, config.summary_model.as_deref()).await {
+        Ok(summary) => {
+            let at = usize::from(messages.first().is_some_and(|m| m.role == "system"));
+            messages.insert(at, super::summary_message(&summary));
+            Some(summary.model)
+        }
+        Err(e) => {
+            eprintln!("[SessionMerge] Summary generation failed, merging without summary: {}", e);
+            None
+        }
+    };
+
+    let title = format!(
+        "Merged: {}",
+        sessions.iter().map(|s| if s.title.is_empty() { s.id.as_str() } else { s.title.as_str() }).collect::<Vec<_>>().join(" + ")
+    );
+    println!(
+        "[SessionMerge] Merged {} sessions ({:?}): {} messages, {} duplicate tool results",
+        sessions.len(), strategy, messages.len(), deduplicated
+    );
+
+    Ok(MergedSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        title,
+        messages,
+        merged_from: sessions.into_iter().map(|s| s.id).collect(),
+        strategy,
+        deduplicated_tool_results: deduplicated,
+        summary_model,
+        archived,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, text: &str, tool_call_id: Option<&str>) -> Message {
+        Message {
+            role: role.to_string(),
+            content: Content::Text(text.to_string()),
+            tool_calls: None,
+            tool_call_id: tool_call_id.map(|s| s.to_string()),
+        }
+    }
+
+    fn texts(messages: &[Message]) -> Vec<String> {
+        messages.iter().map(|m| content_text(&m.content)).collect()
+    }
+
+    #[test]
+    fn test_merge_strategies_and_dedupe() {
+        let listing = "src/main.rs\nsrc/lib.rs\n".repeat(10);
+        let a = SessionHistory {
+            id: "a".into(),
+            title: "Refactor".into(),
+            messages: vec![
+                msg("system", "You are IfAI", None),
+                msg("system", &format!("{}\n_old_", crate::conversation::SUMMARY_HEADER), None),
+                msg("user", "a1", None),
+                msg("assistant", "", None),
+                msg("tool", &listing, Some("call_1")),
+                msg("user", "a2", None),
+            ],
+        };
+        let b = SessionHistory {
+            id: "b".into(),
+            title: String::new(),
+            messages: vec![
+                msg("system", "Other prompt", None),
+                msg("user", "b1", None),
+                msg("tool", &listing, Some("call_2")),
+                msg("tool", "ok", Some("call_3")),
+                msg("tool", "ok", Some("call_4")),
+            ],
+        };
+
+        let (concat, _) = merge_histories(&[a.clone(), b.clone()], MergeStrategy::Concatenate);
+        assert_eq!(texts(&concat)[..4], ["You are IfAI", "a1", "", listing.as_str()]);
+        assert_eq!(texts(&concat)[5], "b1");
+
+        let (interleaved, deduplicated) = merge_histories(&[a, b], MergeStrategy::Interleave);
+        let t = texts(&interleaved);
+        assert_eq!(t.iter().filter(|m| m.as_str() == "You are IfAI" || m.as_str() == "Other prompt").count(), 1);
+        assert_eq!(t[1..], ["a1", "", listing.as_str(), "b1", DUPLICATE_NOTE, "ok", "ok", "a2"]);
+        assert_eq!(deduplicated, 1);
+        assert_eq!(interleaved[5].tool_call_id.as_deref(), Some("call_2"));
+    }
+
+    #[test]
+    fn test_archive_is_read_only() {
+        let dir = std::env::temp_dir().join(format!("ifai_session_merge_{}", uuid::Uuid::new_v4()));
+        let root = dir.to_string_lossy().to_string();
+        let session = SessionHistory { id: "chat/1".into(), title: "t".into(), messages: vec![msg("user", "hi", None)] };
+
+        let path = archive_session(&root, &session).unwrap();
+        assert!(path.ends_with("chat_1.json"));
+        assert!(std::fs::metadata(&path).unwrap().permissions().readonly());
+        // 再次合并时不覆盖已有快照
+        assert_eq!(archive_session(&root, &session).unwrap(), path);
+
+        let mut permissions = std::fs::metadata(&path).unwrap().permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        let _ = std::fs::set_permissions(&path, permissions);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
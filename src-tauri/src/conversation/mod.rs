@@ -1,19 +1,111 @@
 pub mod token_counter;
 pub mod summarizer;
+pub mod merge;
+pub mod export;
 
 use crate::core_traits::ai::{Message, Content, AIProviderConfig};
+use serde::{Deserialize, Serialize};
 
-pub async fn should_summarize(messages: &[Message]) -> bool {
+/// 对话压缩配置（IFAI.md 的 `summarization` 字段）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SummarizationConfig {
+    /// 设为 false 关闭自动压缩
+    pub enabled: bool,
+    /// 超过该 Token 数触发压缩
+    pub max_tokens: usize,
+    /// 超过该消息数触发压缩
+    pub max_messages: usize,
+    /// 少于该消息数时不压缩
+    pub min_messages: usize,
+    /// 压缩后保留的最近消息数
+    pub tail_size: usize,
+    /// 摘要模型：模型名或 `local`，优先于 `.ifai/summarizer.json`
+    pub summary_model: Option<String>,
+}
+
+impl Default for SummarizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_tokens: 150_000,
+            max_messages: 100,
+            min_messages: 10,
+            tail_size: 10,
+            summary_model: None,
+        }
+    }
+}
+
+impl SummarizationConfig {
+    pub fn load(project_root: &str) -> Self {
+        crate::project_config::load_project_config_sync(project_root)
+            .and_then(|c| c.summarization)
+            .unwrap_or_default()
+    }
+}
+
+/// 摘要系统消息的开头，用于识别已有摘要
+pub const SUMMARY_HEADER: &str = "## CONVERSATION SUMMARY";
+
+/// 将摘要包装为系统消息
+pub fn summary_message(summary: &summarizer::SummaryOutput) -> Message {
+    Message {
+        role: "system".to_string(),
+        content: Content::Text(format!(
+            "{}\n_Summarized by {}{}_\n\n{}\n\n=== End of Summary ===",
+            SUMMARY_HEADER,
+            summary.model,
+            if summary.fallback { " (fallback)" } else { "" },
+            summary.text
+        )),
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+pub async fn should_summarize(messages: &[Message], model: &str, config: &SummarizationConfig) -> bool {
+    if !config.enabled {
+        return false;
+    }
     // Guard: Don't summarize short conversations regardless of token count
-    if messages.len() < 10 {
+    if messages.len() < config.min_messages {
         return false;
     }
 
-    let token_count = token_counter::count_messages_tokens(messages);
+    let token_count = token_counter::count_messages_tokens_for_model(messages, model);
     println!("[Conversation] Check summary: {} messages, {} tokens", messages.len(), token_count);
-    
-    // Thresholds: 150k tokens or 100 messages
-    token_count > 150_000 || messages.len() > 100
+
+    token_count > config.max_tokens || messages.len() > config.max_messages
+}
+
+/// 为满足上下文上限而省略的工具结果
+pub const ELIDED_TOOL_RESULT: &str = "[Earlier tool output omitted to fit the project's max_context_tokens]";
+
+/// 超过 `max_tokens` 时从最早的工具结果开始替换为占位内容（保留 `tool_call_id` 配对），
+/// 最近 `keep_recent` 条消息不受影响。返回替换的数量
+pub fn elide_tool_results(messages: &mut [Message], model: &str, max_tokens: usize, keep_recent: usize) -> usize {
+    let mut total = token_counter::count_messages_tokens_for_model(messages, model);
+    if total <= max_tokens {
+        return 0;
+    }
+    let placeholder = Message { role: "tool".to_string(), content: Content::Text(ELIDED_TOOL_RESULT.to_string()), tool_calls: None, tool_call_id: None };
+    let placeholder_tokens = token_counter::count_messages_tokens_for_model(std::slice::from_ref(&placeholder), model);
+    let cutoff = messages.len().saturating_sub(keep_recent);
+    let mut elided = 0;
+    for message in messages[..cutoff].iter_mut().filter(|m| m.role == "tool") {
+        if total <= max_tokens {
+            break;
+        }
+        let tokens = token_counter::count_messages_tokens_for_model(std::slice::from_ref(message), model);
+        if tokens <= placeholder_tokens {
+            continue;
+        }
+        message.content = placeholder.content.clone();
+        total -= tokens - placeholder_tokens;
+        elided += 1;
+    }
+    elided
 }
 
 use tauri::{AppHandle, Emitter};
@@ -25,14 +117,17 @@ pub async fn auto_summarize(
     provider_config: &AIProviderConfig,
     messages: &mut Vec<Message>,
 ) -> Result<(), String> {
-    if !should_summarize(messages).await {
+    let config = SummarizationConfig::load(project_root);
+    let model = provider_config.models.first().map(|m| m.as_str()).unwrap_or("");
+    if !should_summarize(messages, model, &config).await {
         return Ok(());
     }
 
     println!("[Conversation] Context threshold reached. Starting auto-summarization.");
 
-    // 1. Generate the summary
-    let summary = summarizer::generate_summary(project_root, provider_config, messages.clone()).await?;
+    // 1. Generate the summary (with the project's summarizer strategy)
+    let summary = summarizer::generate_summary(project_root, provider_config, messages.clone(), config.summary_model.as_deref()).await?;
+    println!("[Conversation] Summary produced by {} (fallback: {})", summary.model, summary.fallback);
 
     // 2. Archive existing messages (Simplified: for now we just log it)
     // TODO: Write to .ifai/sessions/archive/
@@ -49,15 +144,10 @@ pub async fn auto_summarize(
     }
 
     // Inject the summary as a new system message
-    new_history.push(Message {
-        role: "system".to_string(),
-        content: Content::Text(format!("## CONVERSATION SUMMARY\n\n{}\n\n=== End of Summary ===", summary)),
-        tool_calls: None,
-        tool_call_id: None,
-    });
+    new_history.push(summary_message(&summary));
 
-    // Keep the last 10 messages for context
-    let tail_size = std::cmp::min(messages.len(), 10);
+    // Keep the last `tail_size` messages for context
+    let tail_size = std::cmp::min(messages.len(), config.tail_size);
     let start_idx = messages.len() - tail_size;
     for i in start_idx..messages.len() {
         new_history.push(messages[i].clone());
@@ -67,8 +157,63 @@ pub async fn auto_summarize(
     
     // Notify frontend to update its history
     let _ = app.emit(&format!("{}_compacted", event_id), new_history);
+    let _ = app.emit(&format!("{}_summary", event_id), serde_json::json!({
+        "model": summary.model,
+        "strategy": summary.strategy,
+        "fallback": summary.fallback,
+    }));
     
     println!("[Conversation] History compacted successfully.");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(n: usize) -> Vec<Message> {
+        (0..n).map(|i| Message {
+            role: if i % 2 == 0 { "user" } else { "assistant" }.to_string(),
+            content: Content::Text(format!("message {}", i)),
+            tool_calls: None,
+            tool_call_id: None,
+        }).collect()
+    }
+
+    #[tokio::test]
+    async fn test_should_summarize_thresholds() {
+        let defaults = SummarizationConfig::default();
+        assert!(!should_summarize(&history(50), "gpt-4", &defaults).await);
+
+        let small_context = SummarizationConfig { max_tokens: 100, ..Default::default() };
+        assert!(should_summarize(&history(50), "gpt-4", &small_context).await);
+        assert!(!should_summarize(&history(5), "gpt-4", &small_context).await);
+
+        let disabled = SummarizationConfig { enabled: false, max_messages: 1, ..Default::default() };
+        assert!(!should_summarize(&history(50), "gpt-4", &disabled).await);
+    }
+
+    #[test]
+    fn test_elide_tool_results() {
+        let mut messages = history(2);
+        for i in 0..4 {
+            messages.push(Message {
+                role: "tool".to_string(),
+                content: Content::Text(format!("output {} ", i).repeat(200)),
+                tool_calls: None,
+                tool_call_id: Some(format!("call_{}", i)),
+            });
+        }
+        let full = token_counter::count_messages_tokens_for_model(&messages, "gpt-4");
+        assert_eq!(elide_tool_results(&mut messages.clone(), "gpt-4", full, 1), 0);
+
+        let elided = elide_tool_results(&mut messages, "gpt-4", full * 2 / 3, 1);
+        assert_eq!(elided, 2);
+        assert!(matches!(&messages[2].content, Content::Text(t) if t == ELIDED_TOOL_RESULT));
+        assert_eq!(messages[2].tool_call_id.as_deref(), Some("call_0"));
+        assert!(token_counter::count_messages_tokens_for_model(&messages, "gpt-4") <= full * 2 / 3);
+        // 最近的消息保持不变
+        assert!(matches!(&messages[5].content, Content::Text(t) if t.starts_with("output 3")));
+    }
+}
@@ -1,19 +1,146 @@
 use crate::prompt_manager;
 use crate::ai_utils;
 use crate::core_traits::ai::{Message, Content, AIProviderConfig};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 本地模型输入上限（字符），超出时只保留最近的对话
+const LOCAL_MAX_INPUT_CHARS: usize = 6000;
+/// 本地模型摘要的最大生成长度
+#[cfg(feature = "llm-inference")]
+const LOCAL_MAX_TOKENS: usize = 512;
+
+/// 摘要模型策略（项目级，保存在 `.ifai/summarizer.json`）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum SummarizerStrategy {
+    /// 使用当前对话的主模型
+    #[default]
+    Main,
+    /// 使用主 Provider 下的另一个（更便宜的）模型
+    Model { model: String },
+    /// 使用本地 GGUF 模型
+    Local,
+}
+
+/// 摘要结果及其来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SummaryOutput {
+    pub text: String,
+    /// 实际生成摘要的模型，如 `openai/gpt-4o-mini`、`local/qwen2.5-coder.gguf`
+    pub model: String,
+    pub strategy: SummarizerStrategy,
+    /// 配置的策略失败，由主模型兜底
+    pub fallback: bool,
+}
+
+fn strategy_path(project_root: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("summarizer.json")
+}
+
+pub fn load_strategy(project_root: &str) -> SummarizerStrategy {
+    std::fs::read_to_string(strategy_path(project_root))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn cloud_model_label(config: &AIProviderConfig) -> String {
+    let provider = if config.name.is_empty() { &config.id } else { &config.name };
+    format!("{}/{}", provider, config.models.first().map(|m| m.as_str()).unwrap_or("unknown"))
+}
+
+async fn summarize_with_provider(provider_config: &AIProviderConfig, messages: Vec<Message>) -> Result<String, String> {
+    println!("[Summarizer] Sending request to AI (Model: {})...", provider_config.models[0]);
+    match ai_utils::fetch_ai_completion(provider_config, messages, None).await {
+        Ok(res_msg) => {
+            if let Content::Text(summary_text) = res_msg.content {
+                println!("[Summarizer] Summary generated successfully ({} chars)", summary_text.len());
+                Ok(summary_text)
+            } else {
+                let err = "AI returned multimodal content instead of text for summary".to_string();
+                eprintln!("[Summarizer] Error: {}", err);
+                Err(err)
+            }
+        },
+        Err(e) => {
+            eprintln!("[Summarizer] AI request failed: {}", e);
+            Err(format!("AI request for summary failed: {}", e))
+        }
+    }
+}
+
+/// 将对话渲染为纯文本，超出上限时保留末尾
+fn render_transcript(history: &[Message], max_chars: usize) -> String {
+    let transcript = history.iter()
+        .filter(|m| m.role != "system")
+        .map(|m| format!("{}: {}", m.role, crate::intelligence_router::extract_text_content(&m.content)))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let total = transcript.chars().count();
+    if total <= max_chars {
+        return transcript;
+    }
+    let tail: String = transcript.chars().skip(total - max_chars).collect();
+    format!("[earlier conversation omitted]\n{}", tail)
+}
+
+/// 本地模型摘要（Qwen ChatML 格式）
+async fn summarize_locally(instruction: &str, history: &[Message]) -> Result<(String, String), String> {
+    let config = crate::local_model::LocalModelConfig::default();
+    if !config.model_path.exists() {
+        return Err("本地模型文件不存在".to_string());
+    }
+
+    let prompt = format!(
+        "<|im_start|>system\nYou summarize software development conversations.<|im_end|>\n<|im_start|>user\n{}\n\n{}<|im_end|>\n<|im_start|>assistant\n",
+        instruction,
+        render_transcript(history, LOCAL_MAX_INPUT_CHARS)
+    );
+
+    #[cfg(not(feature = "llm-inference"))]
+    {
+        let _ = prompt;
+        Err("本地推理功能未启用".to_string())
+    }
+
+    #[cfg(feature = "llm-inference")]
+    {
+        let text = tokio::task::spawn_blocking(move || crate::llm_inference::generate_completion(&prompt, LOCAL_MAX_TOKENS))
+            .await
+            .map_err(|e| format!("任务调度失败: {}", e))?
+            .map_err(|e| format!("本地推理失败: {}", e))?;
+        let text = text.split("<|").next().unwrap_or("").trim().to_string();
+        if text.is_empty() {
+            return Err("本地模型返回了空摘要".to_string());
+        }
+        let model = config.model_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        Ok((text, format!("local/{}", model)))
+    }
+}
+
+/// IFAI.md 中的 `summary_model` 覆盖：`local` 表示本地模型，其余为模型名
+fn strategy_override(summary_model: &str) -> SummarizerStrategy {
+    match summary_model.trim() {
+        "" | "main" => SummarizerStrategy::Main,
+        "local" => SummarizerStrategy::Local,
+        model => SummarizerStrategy::Model { model: model.to_string() },
+    }
+}
 
 pub async fn generate_summary(
     project_root: &str,
     provider_config: &AIProviderConfig,
     history: Vec<Message>,
-) -> Result<String, String> {
+    summary_model: Option<&str>,
+) -> Result<SummaryOutput, String> {
     println!("[Summarizer] Triggering conversation summarization...");
-    
+
     // 1. Load the summary prompt template
     // Note: We use "conversation-summary" as the type to match our filename
     let summary_instruction = prompt_manager::get_agent_prompt(
-        "conversation-summary", 
-        project_root, 
+        "conversation-summary",
+        project_root,
         "Please provide a structured summary of our conversation so far."
     );
 
@@ -22,27 +149,101 @@ pub async fn generate_summary(
     let mut messages = history.clone();
     messages.push(Message {
         role: "user".to_string(),
-        content: Content::Text(summary_instruction),
+        content: Content::Text(summary_instruction.clone()),
         tool_calls: None,
         tool_call_id: None,
     });
 
-    // 3. Call AI
-    println!("[Summarizer] Sending request to AI (Model: {})...", provider_config.models[0]);
-    match ai_utils::fetch_ai_completion(provider_config, messages, None).await {
-        Ok(res_msg) => {
-            if let Content::Text(summary_text) = res_msg.content {
-                println!("[Summarizer] Summary generated successfully ({} chars)", summary_text.len());
-                Ok(summary_text)
-            } else {
-                let err = "AI returned multimodal content instead of text for summary".to_string();
-                eprintln!("[Summarizer] Error: {}", err);
-                Err(err)
-            }
-        },
-        Err(e) => {
-            eprintln!("[Summarizer] AI request failed: {}", e);
-            Err(format!("AI request for summary failed: {}", e))
+    // 3. Try the configured strategy first
+    let strategy = summary_model.map(strategy_override).unwrap_or_else(|| load_strategy(project_root));
+    let attempt = match &strategy {
+        SummarizerStrategy::Main => None,
+        SummarizerStrategy::Model { model } => {
+            let mut cheap_config = provider_config.clone();
+            cheap_config.models = vec![model.clone()];
+            let label = cloud_model_label(&cheap_config);
+            Some(summarize_with_provider(&cheap_config, messages.clone()).await.map(|text| (text, label)))
+        }
+        SummarizerStrategy::Local => Some(summarize_locally(&summary_instruction, &history).await),
+    };
+
+    let fallback = match attempt {
+        Some(Ok((text, model))) => return Ok(SummaryOutput { text, model, strategy, fallback: false }),
+        Some(Err(e)) => {
+            eprintln!("[Summarizer] {:?} strategy failed, falling back to main model: {}", strategy, e);
+            true
         }
+        None => false,
+    };
+
+    // 4. Main model
+    let text = summarize_with_provider(provider_config, messages).await?;
+    Ok(SummaryOutput { text, model: cloud_model_label(provider_config), strategy, fallback })
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_summarizer_strategy(project_root: String) -> SummarizerStrategy {
+    load_strategy(&project_root)
+}
+
+#[tauri::command]
+pub fn set_summarizer_strategy(project_root: String, strategy: SummarizerStrategy) -> Result<(), String> {
+    if let SummarizerStrategy::Model { model } = &strategy {
+        if model.trim().is_empty() {
+            return Err("Summarizer model name is empty".to_string());
+        }
+    }
+    let path = strategy_path(&project_root);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create .ifai dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(&strategy).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write summarizer config: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, text: &str) -> Message {
+        Message { role: role.to_string(), content: Content::Text(text.to_string()), tool_calls: None, tool_call_id: None }
+    }
+
+    #[test]
+    fn test_strategy_roundtrip() {
+        let root = std::env::temp_dir().join(format!("ifai_summarizer_{}", uuid::Uuid::new_v4()));
+        let root_str = root.to_string_lossy().to_string();
+        assert_eq!(load_strategy(&root_str), SummarizerStrategy::Main);
+
+        let cheap = SummarizerStrategy::Model { model: "gpt-4o-mini".to_string() };
+        set_summarizer_strategy(root_str.clone(), cheap.clone()).unwrap();
+        assert_eq!(load_strategy(&root_str), cheap);
+        assert!(set_summarizer_strategy(root_str.clone(), SummarizerStrategy::Model { model: " ".to_string() }).is_err());
+
+        let raw = std::fs::read_to_string(strategy_path(&root_str)).unwrap();
+        assert!(raw.contains(r#""strategy": "model""#));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_strategy_override() {
+        assert_eq!(strategy_override("local"), SummarizerStrategy::Local);
+        assert_eq!(strategy_override(" main "), SummarizerStrategy::Main);
+        assert_eq!(strategy_override("glm-4-flash"), SummarizerStrategy::Model { model: "glm-4-flash".to_string() });
+    }
+
+    #[test]
+    fn test_render_transcript_keeps_tail() {
+        let history = vec![msg("system", "prompt"), msg("user", "first question"), msg("assistant", "latest answer")];
+        let full = render_transcript(&history, 1000);
+        assert_eq!(full, "user: first question\n\nassistant: latest answer");
+
+        let tail = render_transcript(&history, 13);
+        assert!(tail.starts_with("[earlier conversation omitted]"));
+        assert!(tail.ends_with("latest answer"));
     }
 }
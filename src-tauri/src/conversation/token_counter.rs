@@ -1,24 +1,23 @@
-use tiktoken_rs::cl100k_base;
 use crate::core_traits::ai::{Message, Content, ContentPart};
+use crate::token_counter::{count_with, tokenizer_for_model};
+
+/// 按模型的分词方式计数消息列表
+pub fn count_messages_tokens_for_model(messages: &[Message], model: &str) -> usize {
+    let kind = tokenizer_for_model(model);
+    let count = |text: &str| count_with(text, kind);
 
-pub fn count_messages_tokens(messages: &[Message]) -> usize {
-    let bpe = match cl100k_base() {
-        Ok(b) => b,
-        Err(_) => return 0,
-    };
-    
     let mut total_tokens = 0;
     
     for msg in messages {
         total_tokens += 4; // Role/Metadata overhead
         
         match &msg.content {
-            Content::Text(text) => total_tokens += bpe.encode_with_special_tokens(text).len(),
+            Content::Text(text) => total_tokens += count(text),
             Content::Parts(parts) => {
                 for part in parts {
                     match part {
                         ContentPart::Text { text, .. } => {
-                             total_tokens += bpe.encode_with_special_tokens(text).len();
+                             total_tokens += count(text);
                         }
                         _ => {
                             // Image or other part
@@ -31,13 +30,13 @@ pub fn count_messages_tokens(messages: &[Message]) -> usize {
         
         if let Some(tool_calls) = &msg.tool_calls {
             for tc in tool_calls {
-                total_tokens += bpe.encode_with_special_tokens(&tc.function.name).len();
-                total_tokens += bpe.encode_with_special_tokens(&tc.function.arguments).len();
+                total_tokens += count(&tc.function.name);
+                total_tokens += count(&tc.function.arguments);
             }
         }
         
         if let Some(id) = &msg.tool_call_id {
-            total_tokens += bpe.encode_with_special_tokens(id).len();
+            total_tokens += count(id);
         }
     }
     
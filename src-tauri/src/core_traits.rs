@@ -115,7 +115,7 @@ pub mod agent {
     #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
     #[serde(rename_all = "lowercase")]
     pub enum AgentStatus {
-        #[default] Idle, Running, WaitingForTool, Completed, Failed(String), Stopped,
+        #[default] Idle, Running, WaitingForTool, Completed, Failed(String), Stopped, Paused,
     }
 
     #[async_trait::async_trait]
@@ -0,0 +1,164 @@
+/*!
+Diff Preview - 写入前的差异预览
+===============================
+
+Agent 请求 `agent_write_file` / `agent_edit_file` 时，后端先计算原文件与拟写入内容的
+统一差异，随 `tool_call` 审批事件发给前端。用户可以逐块接受或拒绝：
+`approve_agent_action` 的 `accepted_hunks` 指定接受的块序号，`apply_hunks`
+只把这些块应用到原文件上。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 改动块前后保留的上下文行数
+const CONTEXT_LINES: u32 = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffHunk {
+    /// 块序号（从 0 开始），用于逐块审批
+    pub index: usize,
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    /// 带 ` ` / `-` / `+` 前缀的行
+    pub lines: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffPreview {
+    pub path: String,
+    pub is_new_file: bool,
+    /// 完整的统一差异文本
+    pub unified: String,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// 统一差异文本与各改动块（块内的行保留原始换行符，供 `apply_hunks` 还原内容）
+fn diff_hunks(path: &str, old: &str, new: &str) -> Result<(String, Vec<(DiffHunk, Vec<String>)>), String> {
+    let mut options = git2::DiffOptions::new();
+    options.context_lines(CONTEXT_LINES);
+    let file = Path::new(path);
+    let mut patch = git2::Patch::from_buffers(old.as_bytes(), Some(file), new.as_bytes(), Some(file), Some(&mut options))
+        .map_err(|e| format!("Failed to diff {}: {}", path, e))?;
+
+    let mut hunks = Vec::new();
+    for h in 0..patch.num_hunks() {
+        let (hunk, count) = patch.hunk(h).map_err(|e| e.to_string())?;
+        let mut preview = DiffHunk {
+            index: h,
+            header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines: Vec::with_capacity(count),
+        };
+        let mut raw = Vec::with_capacity(count);
+        for l in 0..count {
+            let line = patch.line_in_hunk(h, l).map_err(|e| e.to_string())?;
+            // 跳过 “\ No newline at end of file” 标记，此时行内容本身不含换行符
+            if !matches!(line.origin(), ' ' | '-' | '+') {
+                continue;
+            }
+            let content = String::from_utf8_lossy(line.content()).to_string();
+            preview.lines.push(format!("{}{}", line.origin(), content.trim_end_matches(['\r', '\n'])));
+            raw.push(format!("{}{}", line.origin(), content));
+        }
+        hunks.push((preview, raw));
+    }
+
+    let unified = patch.to_buf()
+        .map(|buf| String::from_utf8_lossy(&buf).to_string())
+        .map_err(|e| e.to_string())?;
+    Ok((unified, hunks))
+}
+
+/// 计算写入预览（`old` 为 None 表示新建文件）
+pub fn preview(path: &str, old: Option<&str>, new: &str) -> Result<DiffPreview, String> {
+    let (unified, hunks) = diff_hunks(path, old.unwrap_or(""), new)?;
+    Ok(DiffPreview {
+        path: path.to_string(),
+        is_new_file: old.is_none(),
+        unified,
+        hunks: hunks.into_iter().map(|(hunk, _)| hunk).collect(),
+    })
+}
+
+/// 只应用 `accepted` 中的块，其余块保持原文件内容
+pub fn apply_hunks(path: &str, old: &str, new: &str, accepted: &[usize]) -> Result<String, String> {
+    let (_, hunks) = diff_hunks(path, old, new)?;
+    if let Some(invalid) = accepted.iter().find(|i| **i >= hunks.len()) {
+        return Err(format!("Hunk {} does not exist ({} hunks)", invalid, hunks.len()));
+    }
+
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let mut result = String::with_capacity(new.len().max(old.len()));
+    let mut cursor = 0;
+    for (hunk, lines) in &hunks {
+        // 没有删除行的块插入在 old_start 行之后
+        let start = if hunk.old_lines == 0 { hunk.old_start as usize } else { hunk.old_start as usize - 1 };
+        for line in &old_lines[cursor..start.min(old_lines.len())] {
+            result.push_str(line);
+        }
+        let keep = if accepted.contains(&hunk.index) { '+' } else { '-' };
+        for line in lines {
+            let origin = line.chars().next().unwrap_or(' ');
+            if origin == ' ' || origin == keep {
+                result.push_str(&line[1..]);
+            }
+        }
+        cursor = start + hunk.old_lines as usize;
+    }
+    for line in old_lines.iter().skip(cursor) {
+        result.push_str(line);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "fn a() {}\n\nfn b() {}\n\nfn c() {}\n\nfn d() {}\n\nfn e() {}\n\nfn f() {}\n";
+
+    fn edited() -> String {
+        OLD.replace("fn a() {}", "fn a() { 1 }").replace("fn f() {}", "fn f() { 6 }\nfn g() {}")
+    }
+
+    #[test]
+    fn test_preview_hunks() {
+        let new = edited();
+        let preview = preview("src/lib.rs", Some(OLD), &new).unwrap();
+        assert!(!preview.is_new_file);
+        assert_eq!(preview.hunks.len(), 2);
+        assert!(preview.unified.contains("--- a/src/lib.rs") && preview.unified.contains("+fn a() { 1 }"));
+        assert_eq!(preview.hunks[0].lines[0], "-fn a() {}");
+        assert_eq!(preview.hunks[0].lines[1], "+fn a() { 1 }");
+        assert!(preview.hunks[1].header.starts_with("@@"));
+
+        let created = super::preview("new.rs", None, "fn x() {}\n").unwrap();
+        assert!(created.is_new_file);
+        assert_eq!(created.hunks[0].lines, vec!["+fn x() {}"]);
+    }
+
+    #[test]
+    fn test_apply_selected_hunks() {
+        let new = edited();
+        assert_eq!(apply_hunks("lib.rs", OLD, &new, &[0, 1]).unwrap(), new);
+        assert_eq!(apply_hunks("lib.rs", OLD, &new, &[]).unwrap(), OLD);
+        assert_eq!(apply_hunks("lib.rs", OLD, &new, &[0]).unwrap(), OLD.replace("fn a() {}", "fn a() { 1 }"));
+        assert_eq!(apply_hunks("lib.rs", OLD, &new, &[1]).unwrap(), OLD.replace("fn f() {}", "fn f() { 6 }\nfn g() {}"));
+        assert!(apply_hunks("lib.rs", OLD, &new, &[2]).is_err());
+
+        // 纯插入与缺少末尾换行
+        assert_eq!(apply_hunks("x", "a\nb", "a\nx\nb", &[0]).unwrap(), "a\nx\nb");
+        assert_eq!(apply_hunks("x", "a\nb", "a\nb\nc", &[]).unwrap(), "a\nb");
+        assert_eq!(apply_hunks("x", "a\nb", "a\nb\nc", &[0]).unwrap(), "a\nb\nc");
+        assert_eq!(apply_hunks("x", "", "new\n", &[0]).unwrap(), "new\n");
+    }
+}
@@ -0,0 +1,286 @@
+/*!
+Diff RAG - 基于两个 Git 引用差异的检索上下文
+============================================
+
+回答“这个分支对认证做了哪些修改？”这类迁移问题时，检索范围限定为两个引用之间改动的文件：
+
+- 在消息中使用 `@diff main...feature`（三点：相对合并基点）或 `@diff main..feature`（两点：直接比较）
+- 每个改动块同时提取旧版本与新版本，并分别标注所属引用
+- 按与问题的关键词相关度排序，在字符预算内组装上下文
+*/
+
+use git2::{DiffOptions, Oid, Patch, Repository};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::core_traits::rag::{RagReference, RagResult};
+
+/// 上下文最大字符数
+const MAX_CONTEXT_CHARS: usize = 10_000;
+/// 单个版本片段的最大行数
+const MAX_CHUNK_LINES: usize = 120;
+/// 改动块前后保留的上下文行数
+const CONTEXT_LINES: u32 = 3;
+
+/// 差异检索范围
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DiffScope {
+    pub base: String,
+    pub head: String,
+    /// `...`：以合并基点为旧版本
+    pub merge_base: bool,
+}
+
+/// 单个改动块的新旧版本
+#[derive(Debug, Clone, PartialEq)]
+struct DiffChunk {
+    path: String,
+    status: &'static str,
+    /// (起始行, 内容)
+    old: Option<(usize, String)>,
+    new: Option<(usize, String)>,
+}
+
+// ============================================================================
+// Mention Parsing
+// ============================================================================
+
+/// 解析消息中的 `@diff base...head`，返回范围与去掉标记后的问题
+pub fn parse_diff_mention(text: &str) -> Option<(DiffScope, String)> {
+    let re = Regex::new(r"(?i)@diff\s+([\w./@^~-]+?)(\.\.\.?)([\w./@^~-]+)").unwrap();
+    let caps = re.captures(text)?;
+    let scope = DiffScope {
+        base: caps[1].to_string(),
+        head: caps[3].to_string(),
+        merge_base: &caps[2] == "...",
+    };
+    let query = re.replace(text, "").trim().to_string();
+    Some((scope, query))
+}
+
+// ============================================================================
+// Diff Extraction
+// ============================================================================
+
+fn resolve_commit(repo: &Repository, spec: &str) -> Result<Oid, String> {
+    repo.revparse_single(spec)
+        .and_then(|obj| obj.peel_to_commit())
+        .map(|c| c.id())
+        .map_err(|e| format!("Unknown git ref '{}': {}", spec, e))
+}
+
+fn slice_lines(content: &str, start: usize, count: usize) -> String {
+    content.lines()
+        .skip(start.saturating_sub(1))
+        .take(count.min(MAX_CHUNK_LINES))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn blob_text(repo: &Repository, id: Oid) -> Option<String> {
+    if id.is_zero() {
+        return None;
+    }
+    let blob = repo.find_blob(id).ok()?;
+    (!blob.is_binary()).then(|| String::from_utf8_lossy(blob.content()).to_string())
+}
+
+/// 提取两个引用之间所有改动块的新旧版本
+fn collect_chunks(root: &str, scope: &DiffScope) -> Result<Vec<DiffChunk>, String> {
+    let repo = Repository::discover(root).map_err(|e| format!("Not a git repository: {}", e))?;
+    let head_id = resolve_commit(&repo, &scope.head)?;
+    let mut base_id = resolve_commit(&repo, &scope.base)?;
+    if scope.merge_base {
+        base_id = repo.merge_base(base_id, head_id).map_err(|e| format!("No merge base: {}", e))?;
+    }
+
+    let base_tree = repo.find_commit(base_id).and_then(|c| c.tree()).map_err(|e| e.to_string())?;
+    let head_tree = repo.find_commit(head_id).and_then(|c| c.tree()).map_err(|e| e.to_string())?;
+    let mut opts = DiffOptions::new();
+    opts.context_lines(CONTEXT_LINES);
+    let diff = repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), Some(&mut opts))
+        .map_err(|e| format!("Failed to diff {}..{}: {}", scope.base, scope.head, e))?;
+
+    let mut chunks = Vec::new();
+    for (idx, delta) in diff.deltas().enumerate() {
+        let status = match delta.status() {
+            git2::Delta::Added => "added",
+            git2::Delta::Deleted => "deleted",
+            git2::Delta::Renamed => "renamed",
+            _ => "modified",
+        };
+        let path = delta.new_file().path().or_else(|| delta.old_file().path())
+            .map(|p| p.to_string_lossy().replace('\\', "/"))
+            .unwrap_or_default();
+        let old_text = blob_text(&repo, delta.old_file().id());
+        let new_text = blob_text(&repo, delta.new_file().id());
+        if old_text.is_none() && new_text.is_none() {
+            continue;
+        }
+
+        let Ok(Some(patch)) = Patch::from_diff(&diff, idx) else { continue };
+        for h in 0..patch.num_hunks() {
+            let Ok((hunk, _)) = patch.hunk(h) else { continue };
+            let old = old_text.as_deref()
+                .filter(|_| hunk.old_lines() > 0)
+                .map(|t| (hunk.old_start() as usize, slice_lines(t, hunk.old_start() as usize, hunk.old_lines() as usize)));
+            let new = new_text.as_deref()
+                .filter(|_| hunk.new_lines() > 0)
+                .map(|t| (hunk.new_start() as usize, slice_lines(t, hunk.new_start() as usize, hunk.new_lines() as usize)));
+            chunks.push(DiffChunk { path: path.clone(), status, old, new });
+        }
+    }
+    Ok(chunks)
+}
+
+// ============================================================================
+// Ranking & Assembly
+// ============================================================================
+
+fn query_terms(query: &str) -> Vec<String> {
+    query.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|t| t.chars().count() >= 3)
+        .map(|t| t.to_lowercase())
+        .collect()
+}
+
+/// 关键词命中数（路径命中权重更高）
+fn score_chunk(chunk: &DiffChunk, terms: &[String]) -> usize {
+    let path = chunk.path.to_lowercase();
+    let body = format!(
+        "{}\n{}",
+        chunk.old.as_ref().map(|(_, t)| t.as_str()).unwrap_or(""),
+        chunk.new.as_ref().map(|(_, t)| t.as_str()).unwrap_or("")
+    ).to_lowercase();
+    terms.iter().map(|t| body.matches(t.as_str()).count() + if path.contains(t.as_str()) { 5 } else { 0 }).sum()
+}
+
+fn render_chunk(chunk: &DiffChunk, scope: &DiffScope, old_ref: &str) -> String {
+    let mut out = format!("<change path=\"{}\" status=\"{}\">\n", chunk.path, chunk.status);
+    if let Some((start, text)) = &chunk.old {
+        out.push_str(&format!("<old ref=\"{}\" line_start=\"{}\">\n{}\n</old>\n", old_ref, start, text));
+    }
+    if let Some((start, text)) = &chunk.new {
+        out.push_str(&format!("<new ref=\"{}\" line_start=\"{}\">\n{}\n</new>\n", scope.head, start, text));
+    }
+    out.push_str("</change>\n");
+    out
+}
+
+fn assemble(chunks: Vec<DiffChunk>, scope: &DiffScope, query: &str) -> RagResult {
+    let terms = query_terms(query);
+    let mut ranked: Vec<(usize, DiffChunk)> = chunks.into_iter().map(|c| (score_chunk(&c, &terms), c)).collect();
+    ranked.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let files: std::collections::HashSet<&str> = ranked.iter().map(|(_, c)| c.path.as_str()).collect();
+    let old_ref = if scope.merge_base { format!("merge-base({}, {})", scope.base, scope.head) } else { scope.base.clone() };
+    let mut context = format!(
+        "<diff_context base=\"{}\" head=\"{}\" files_changed=\"{}\">\n",
+        old_ref, scope.head, files.len()
+    );
+    let mut references = Vec::new();
+    let mut omitted = 0;
+
+    for (_, chunk) in &ranked {
+        let rendered = render_chunk(chunk, scope, &old_ref);
+        if context.len() + rendered.len() > MAX_CONTEXT_CHARS {
+            omitted += 1;
+            continue;
+        }
+        context.push_str(&rendered);
+        if let Some((start, text)) = chunk.new.as_ref().or(chunk.old.as_ref()) {
+            references.push(RagReference { file_path: chunk.path.clone(), line_start: *start, content: text.clone() });
+        }
+    }
+    if omitted > 0 {
+        context.push_str(&format!("<!-- {} less relevant changes omitted -->\n", omitted));
+    }
+    context.push_str("</diff_context>");
+
+    RagResult { context, references }
+}
+
+/// 构建两个引用之间的差异上下文
+pub fn build_diff_context(root: &str, scope: &DiffScope, query: &str) -> Result<RagResult, String> {
+    let chunks = collect_chunks(root, scope)?;
+    println!("[DiffRag] {} changed hunks between {} and {}", chunks.len(), scope.base, scope.head);
+    Ok(assemble(chunks, scope, query))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 以两个引用之间的改动为检索范围构建上下文
+#[tauri::command]
+pub async fn build_diff_rag_context(
+    root_path: String,
+    base: String,
+    head: String,
+    query: String,
+    merge_base: Option<bool>,
+) -> Result<RagResult, String> {
+    let scope = DiffScope { base, head, merge_base: merge_base.unwrap_or(true) };
+    tokio::task::spawn_blocking(move || build_diff_context(&root_path, &scope, &query))
+        .await
+        .map_err(|e| format!("Diff context task failed: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn commit_all(repo: &Repository, message: &str, parents: &[&git2::Commit]) -> Oid {
+        let mut index = repo.index().unwrap();
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None).unwrap();
+        index.update_all(["*"].iter(), None).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("test", "test@example.com").unwrap();
+        repo.commit(None, &sig, &sig, message, &tree, parents).unwrap()
+    }
+
+    #[test]
+    fn test_parse_diff_mention() {
+        let (scope, query) = parse_diff_mention("@diff main...feature/auth what changes about auth?").unwrap();
+        assert_eq!(scope, DiffScope { base: "main".into(), head: "feature/auth".into(), merge_base: true });
+        assert_eq!(query, "what changes about auth?");
+
+        let (scope, _) = parse_diff_mention("compare @diff v0.3.3..HEAD").unwrap();
+        assert!(!scope.merge_base);
+        assert_eq!(scope.base, "v0.3.3");
+        assert!(parse_diff_mention("@codebase auth").is_none());
+    }
+
+    #[test]
+    fn test_build_diff_context() {
+        let root = std::env::temp_dir().join(format!("ifai_diffrag_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let repo = Repository::init(&root).unwrap();
+        let write = |rel: &str, content: &str| std::fs::write(Path::new(&root).join(rel), content).unwrap();
+
+        write("auth.rs", "fn login(user: &str) -> bool {\n    user == \"admin\"\n}\n");
+        write("util.rs", "fn helper() {}\n");
+        let base = commit_all(&repo, "base", &[]);
+        repo.branch("main", &repo.find_commit(base).unwrap(), true).unwrap();
+
+        write("auth.rs", "fn login(user: &str, token: &str) -> bool {\n    verify_token(user, token)\n}\n");
+        write("util.rs", "fn helper() { println!(\"x\"); }\n");
+        let head = commit_all(&repo, "feature", &[&repo.find_commit(base).unwrap()]);
+        repo.branch("feature", &repo.find_commit(head).unwrap(), true).unwrap();
+
+        let (scope, query) = parse_diff_mention("@diff main...feature how did login auth change?").unwrap();
+        let result = build_diff_context(&root.to_string_lossy(), &scope, &query).unwrap();
+
+        assert!(result.context.contains("files_changed=\"2\""));
+        let auth_pos = result.context.find("path=\"auth.rs\"").unwrap();
+        assert!(auth_pos < result.context.find("path=\"util.rs\"").unwrap());
+        assert!(result.context.contains("user == \"admin\""));
+        assert!(result.context.contains("<new ref=\"feature\" line_start=\"1\">\nfn login(user: &str, token: &str)"));
+        assert_eq!(result.references[0].file_path, "auth.rs");
+
+        assert!(build_diff_context(&root.to_string_lossy(), &DiffScope { base: "nope".into(), head: "feature".into(), merge_base: false }, "").is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,299 @@
+/*!
+Editor Context - 编辑器上下文
+=============================
+
+前端随编辑器状态变化调用 `set_editor_context`，上报当前文件、光标、选区（与剪贴板），
+状态保存在 `EditorContextState` 中；`ai_chat` 根据最后一条用户消息中的提及在后端展开：
+
+- `@selection`: 当前选中的文本（前端上报的原文，附所在文件与行号）
+- `@file`: 当前文件（从磁盘读取，标注光标所在行，超长时以光标为中心截取）
+- `@clipboard`: 前端上报的剪贴板文本
+
+消息没有显式 `@selection` 但指代选中的代码（“这段代码” 等）时，同样注入选区（见 `selection_context`）。
+与 `@codebase` 一样，上下文组装都在后端完成，前端只负责上报状态。
+*/
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use tauri::State;
+
+use crate::selection_context::SelectionRange;
+
+/// `@file` 注入的最大行数
+const MAX_FILE_LINES: usize = 400;
+/// `@selection` / `@clipboard` 注入的最大字符数
+const MAX_TEXT_CHARS: usize = 20_000;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 光标位置：行号从 1 开始，列为字符偏移（从 0 开始）
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CursorPosition {
+    pub line: usize,
+    #[serde(default)]
+    pub column: usize,
+}
+
+/// 选中的文本
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EditorSelection {
+    pub text: String,
+    pub range: SelectionRange,
+}
+
+/// 前端上报的编辑器状态
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditorContext {
+    /// 当前文件的绝对路径
+    #[serde(default)]
+    pub file_path: Option<String>,
+    /// 编辑器识别的语言（为空时按路径推断）
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub cursor: Option<CursorPosition>,
+    #[serde(default)]
+    pub selection: Option<EditorSelection>,
+    #[serde(default)]
+    pub clipboard: Option<String>,
+    /// 最近一次上报的时间（Unix 秒），由后端填写
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+#[derive(Default)]
+pub struct EditorContextState(Mutex<EditorContext>);
+
+impl EditorContextState {
+    pub fn snapshot(&self) -> EditorContext {
+        self.0.lock().map(|ctx| ctx.clone()).unwrap_or_default()
+    }
+
+    /// 修改部分状态并刷新上报时间
+    pub fn update(&self, f: impl FnOnce(&mut EditorContext)) -> Result<(), String> {
+        let mut context = self.0.lock().map_err(|e| e.to_string())?;
+        f(&mut context);
+        context.updated_at = chrono::Utc::now().timestamp();
+        Ok(())
+    }
+}
+
+/// 消息中的上下文提及
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mention {
+    Selection,
+    File,
+    Clipboard,
+}
+
+// ============================================================================
+// Mentions
+// ============================================================================
+
+fn mention_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)(?:^|[\s(（])@(selection|file|clipboard)\b(:\S*)?").unwrap())
+}
+
+/// 按出现顺序返回消息中的提及（去重）；`@file:path` 由 `mention_context` 处理
+pub fn mentions(message: &str) -> Vec<Mention> {
+    let mut found = Vec::new();
+    for caps in mention_pattern().captures_iter(message) {
+        if caps.get(2).is_some() {
+            continue;
+        }
+        let mention = match caps[1].to_lowercase().as_str() {
+            "selection" => Mention::Selection,
+            "file" => Mention::File,
+            _ => Mention::Clipboard,
+        };
+        if !found.contains(&mention) {
+            found.push(mention);
+        }
+    }
+    found
+}
+
+fn language_for(project_root: &str, context: &EditorContext, path: &str) -> String {
+    context.language.clone()
+        .or_else(|| crate::language_map::LanguageMap::load(project_root).for_path(path).map(|s| s.to_string()))
+        .or_else(|| Path::new(path).extension().and_then(|e| e.to_str()).map(|s| s.to_string()))
+        .unwrap_or_default()
+}
+
+/// 显示用路径：项目内的文件使用相对路径
+fn display_path(project_root: &str, path: &str) -> String {
+    Path::new(path)
+        .strip_prefix(project_root)
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_else(|_| path.to_string())
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((idx, _)) => format!("{}\n... [truncated]", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
+fn render_selection(project_root: &str, context: &EditorContext) -> Result<String, String> {
+    let selection = context.selection.as_ref().filter(|s| !s.text.is_empty()).ok_or("no selection")?;
+    let path = context.file_path.as_deref().unwrap_or("untitled");
+    Ok(format!(
+        "## Selection (`{}` lines {}-{})\n```{}\n{}\n```",
+        display_path(project_root, path),
+        selection.range.start_line,
+        selection.range.end_line,
+        language_for(project_root, context, path),
+        truncate_chars(&selection.text, MAX_TEXT_CHARS)
+    ))
+}
+
+/// 当前文件；超过 `MAX_FILE_LINES` 时截取光标附近的行，光标所在行以注释标出
+fn render_file(project_root: &str, context: &EditorContext) -> Result<String, String> {
+    let path = context.file_path.as_deref().ok_or("no file open")?;
+    let content = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let lines: Vec<&str> = content.lines().collect();
+    let cursor_line = context.cursor.map(|c| c.line.clamp(1, lines.len().max(1)));
+
+    let start = match cursor_line {
+        Some(line) if lines.len() > MAX_FILE_LINES => (line - 1).saturating_sub(MAX_FILE_LINES / 2).min(lines.len() - MAX_FILE_LINES),
+        _ => 0,
+    };
+    let end = (start + MAX_FILE_LINES).min(lines.len());
+
+    let mut header = format!("## Current File `{}`", display_path(project_root, path));
+    if let Some(line) = cursor_line {
+        header.push_str(&format!(" (cursor at line {})", line));
+    }
+    if start > 0 || end < lines.len() {
+        header.push_str(&format!(", showing lines {}-{} of {}", start + 1, end, lines.len()));
+    }
+    let body = lines[start..end]
+        .iter()
+        .enumerate()
+        .map(|(i, line)| if Some(start + i + 1) == cursor_line { format!("{}  <-- cursor", line) } else { line.to_string() })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok(format!("{}\n```{}\n{}\n```", header, language_for(project_root, context, path), body))
+}
+
+fn render_clipboard(context: &EditorContext) -> Result<String, String> {
+    let text = context.clipboard.as_deref().filter(|t| !t.trim().is_empty()).ok_or("clipboard is empty")?;
+    Ok(format!("## Clipboard\n```\n{}\n```", truncate_chars(text, MAX_TEXT_CHARS)))
+}
+
+/// 展开消息中的 `@selection` / `@file` / `@clipboard`，没有可注入的内容时返回 None
+pub fn expand_mentions(project_root: &str, context: &EditorContext, message: &str) -> Option<String> {
+    let sections: Vec<String> = mentions(message)
+        .into_iter()
+        .filter_map(|mention| {
+            let rendered = match mention {
+                Mention::Selection => render_selection(project_root, context),
+                Mention::File => render_file(project_root, context),
+                Mention::Clipboard => render_clipboard(context),
+            };
+            rendered.map_err(|e| println!("[EditorContext] Skipping {:?}: {}", mention, e)).ok()
+        })
+        .collect();
+    if sections.is_empty() {
+        return None;
+    }
+    Some(format!("# Editor Context\nThe user referenced the following editor state:\n\n{}", sections.join("\n\n")))
+}
+
+/// 消息需要注入的编辑器上下文：展开显式提及，未提及 `@selection` 但指代选中代码时附加选区
+pub fn context_for_message(
+    project_root: &str,
+    context: &EditorContext,
+    message: &str,
+    index: Option<&crate::commands::symbol_commands::SymbolIndexState>,
+) -> Option<String> {
+    let explicit = expand_mentions(project_root, context, message);
+    if mentions(message).contains(&Mention::Selection) {
+        return explicit;
+    }
+    match (explicit, crate::selection_context::context_for_message(message, context, index)) {
+        (Some(explicit), Some(selection)) => Some(format!("{}\n\n{}", explicit, selection)),
+        (explicit, selection) => explicit.or(selection),
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 上报编辑器状态（整体替换）
+#[tauri::command]
+pub fn set_editor_context(state: State<'_, EditorContextState>, mut context: EditorContext) -> Result<(), String> {
+    if let Some(selection) = &context.selection {
+        if selection.range.start_line == 0 || selection.range.end_line < selection.range.start_line {
+            return Err(format!("Invalid selection range: {}-{}", selection.range.start_line, selection.range.end_line));
+        }
+    }
+    context.updated_at = chrono::Utc::now().timestamp();
+    *state.0.lock().map_err(|e| e.to_string())? = context;
+    Ok(())
+}
+
+/// 当前保存的编辑器状态
+#[tauri::command]
+pub fn get_editor_context(state: State<'_, EditorContextState>) -> EditorContext {
+    state.snapshot()
+}
+
+#[tauri::command]
+pub fn clear_editor_context(state: State<'_, EditorContextState>) {
+    if let Ok(mut context) = state.0.lock() {
+        *context = EditorContext::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mentions() {
+        assert_eq!(mentions("explain @selection and compare with @file"), vec![Mention::Selection, Mention::File]);
+        assert_eq!(mentions("@Clipboard 里的报错是什么意思？@clipboard"), vec![Mention::Clipboard]);
+        assert!(mentions("mail me at dev@file.io or use @files").is_empty());
+        assert_eq!(mentions("@file:src/main.rs vs @file"), vec![Mention::File]);
+    }
+
+    #[test]
+    fn test_expand_mentions() {
+        let dir = std::env::temp_dir().join(format!("ifai_editor_context_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let file = dir.join("src/main.rs");
+        let source: Vec<String> = (1..=500).map(|i| format!("// line {}", i)).collect();
+        std::fs::write(&file, source.join("\n")).unwrap();
+        let root = dir.to_string_lossy().to_string();
+
+        let context = EditorContext {
+            file_path: Some(file.to_string_lossy().to_string()),
+            cursor: Some(CursorPosition { line: 480, column: 2 }),
+            selection: Some(EditorSelection {
+                text: "// line 2".to_string(),
+                range: SelectionRange { start_line: 2, end_line: 2, start_column: None, end_column: None },
+            }),
+            ..Default::default()
+        };
+
+        let expanded = expand_mentions(&root, &context, "why is @selection here? see @file").unwrap();
+        assert!(expanded.contains("## Selection (`src/main.rs` lines 2-2)\n```rust\n// line 2\n```"));
+        assert!(expanded.contains("(cursor at line 480), showing lines 101-500 of 500"));
+        assert!(expanded.contains("// line 480  <-- cursor"));
+        assert!(!expanded.contains("// line 100\n"));
+
+        // 没有剪贴板内容时不注入
+        assert_eq!(expand_mentions(&root, &context, "@clipboard"), None);
+        assert_eq!(expand_mentions(&root, &context, "no mentions"), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
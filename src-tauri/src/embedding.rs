@@ -0,0 +1,351 @@
+/*!
+Embedding - 文本向量模型抽象
+============================
+
+向量模型不再写死为 fastembed AllMiniLML6V2，可按项目选择：
+
+- `fastembed`：本地 ONNX 模型（需 `rag` feature），可选 `multilingual-e5-small`、
+  `bge-small-zh-v1.5` 等多语言 / 中文模型
+- `openai_compatible`：OpenAI 兼容的 `/embeddings` 接口（API Key 从环境变量读取，不写入项目配置）
+- `local_gguf`：`~/.ifai/models/` 下的 GGUF 嵌入模型（需 `llm-inference` feature）
+
+选择写在项目配置（`.ifai/IFAI.md` 的 `embedding` 字段），未配置时为 fastembed AllMiniLML6V2。
+`model_id` 随向量一起返回，模型变化后调用方应重建索引。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// OpenAI 兼容接口单次请求的文本数
+const REMOTE_BATCH_SIZE: usize = 64;
+
+/// 项目的向量模型配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum EmbeddingConfig {
+    Fastembed {
+        /// 模型名，默认 `all-minilm-l6-v2`
+        #[serde(default)]
+        model: Option<String>,
+    },
+    OpenaiCompatible {
+        /// 如 `https://api.openai.com/v1`
+        base_url: String,
+        model: String,
+        /// 保存 API Key 的环境变量名
+        #[serde(default)]
+        api_key_env: Option<String>,
+        /// 输出维度（支持该参数的模型）
+        #[serde(default)]
+        dimensions: Option<usize>,
+    },
+    LocalGguf {
+        /// 模型目录下的文件名
+        model_file: String,
+    },
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        Self::Fastembed { model: None }
+    }
+}
+
+#[async_trait::async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// 模型标识（写入索引元数据，变化时需要重建索引）
+    fn model_id(&self) -> String;
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, String>;
+}
+
+/// 按配置创建向量模型
+pub fn provider_for(config: &EmbeddingConfig) -> Result<Box<dyn EmbeddingProvider>, String> {
+    match config {
+        EmbeddingConfig::Fastembed { model } => {
+            let name = model.clone().unwrap_or_else(|| "all-minilm-l6-v2".to_string());
+            fastembed_provider::FastembedProvider::new(name).map(|p| Box::new(p) as Box<dyn EmbeddingProvider>)
+        }
+        EmbeddingConfig::OpenaiCompatible { base_url, model, api_key_env, dimensions } => {
+            let api_key = match api_key_env {
+                Some(var) => Some(std::env::var(var).map_err(|_| format!("Environment variable {} is not set", var))?),
+                None => None,
+            };
+            Ok(Box::new(OpenAiCompatibleProvider {
+                base_url: base_url.trim_end_matches('/').to_string(),
+                model: model.clone(),
+                api_key,
+                dimensions: *dimensions,
+            }))
+        }
+        EmbeddingConfig::LocalGguf { model_file } => Ok(Box::new(LocalGgufProvider { model_file: model_file.clone() })),
+    }
+}
+
+/// 释放已加载的本地向量模型，下次使用时重新加载（由 `idle_manager` 调用）
+pub fn unload_models() -> bool {
+    fastembed_provider::unload_models()
+}
+
+/// 项目配置的向量模型
+pub fn provider_for_project(project_root: &str) -> Result<Box<dyn EmbeddingProvider>, String> {
+    let config = crate::project_config::load_project_config_sync(project_root)
+        .and_then(|c| c.embedding)
+        .unwrap_or_default();
+    provider_for(&config)
+}
+
+// ============================================================================
+// fastembed
+// ============================================================================
+
+#[cfg(feature = "fastembed")]
+mod fastembed_provider {
+    use super::EmbeddingProvider;
+    use fastembed::{EmbeddingModel, TextEmbedding, TextInitOptions};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex, OnceLock};
+
+    /// 已加载的模型（加载较慢，按模型名缓存）
+    static MODELS: OnceLock<Mutex<HashMap<String, Arc<Mutex<TextEmbedding>>>>> = OnceLock::new();
+
+    fn model_for(name: &str) -> Result<EmbeddingModel, String> {
+        match name {
+            "all-minilm-l6-v2" => Ok(EmbeddingModel::AllMiniLML6V2),
+            "paraphrase-multilingual-minilm-l12-v2" => Ok(EmbeddingModel::ParaphraseMLMiniLML12V2),
+            "multilingual-e5-small" => Ok(EmbeddingModel::MultilingualE5Small),
+            "bge-small-zh-v1.5" => Ok(EmbeddingModel::BGESmallZHV15),
+            _ => Err(format!("Unsupported fastembed model: {}", name)),
+        }
+    }
+
+    pub struct FastembedProvider {
+        name: String,
+        model: EmbeddingModel,
+    }
+
+    /// 释放已加载的模型（空闲回收），返回是否有模型被释放
+    pub fn unload_models() -> bool {
+        let Some(models) = MODELS.get() else { return false };
+        let Ok(mut models) = models.lock() else { return false };
+        let loaded = !models.is_empty();
+        models.clear();
+        loaded
+    }
+
+    impl FastembedProvider {
+        pub fn new(name: String) -> Result<Self, String> {
+            let model = model_for(&name)?;
+            Ok(Self { name, model })
+        }
+
+        fn instance(&self) -> Result<Arc<Mutex<TextEmbedding>>, String> {
+            let mut models = MODELS.get_or_init(|| Mutex::new(HashMap::new())).lock().map_err(|e| e.to_string())?;
+            if let Some(instance) = models.get(&self.name) {
+                return Ok(instance.clone());
+            }
+            println!("[Embedding] Loading fastembed model {}", self.name);
+            let embedding = TextEmbedding::try_new(TextInitOptions::new(self.model.clone()))
+                .map_err(|e| format!("Failed to load fastembed model {}: {}", self.name, e))?;
+            let instance = Arc::new(Mutex::new(embedding));
+            models.insert(self.name.clone(), instance.clone());
+            Ok(instance)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for FastembedProvider {
+        fn model_id(&self) -> String {
+            format!("fastembed/{}", self.name)
+        }
+
+        async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+            let instance = self.instance()?;
+            tokio::task::spawn_blocking(move || {
+                let mut embedding = instance.lock().map_err(|e| e.to_string())?;
+                embedding.embed(texts, None).map_err(|e| format!("fastembed failed: {}", e))
+            })
+            .await
+            .map_err(|e| e.to_string())?
+        }
+    }
+}
+
+#[cfg(not(feature = "fastembed"))]
+mod fastembed_provider {
+    pub struct FastembedProvider;
+
+    pub fn unload_models() -> bool {
+        false
+    }
+
+    impl FastembedProvider {
+        pub fn new(_name: String) -> Result<Self, String> {
+            Err("fastembed embeddings require the rag feature".to_string())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl super::EmbeddingProvider for FastembedProvider {
+        fn model_id(&self) -> String {
+            String::new()
+        }
+
+        async fn embed(&self, _texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+            Err("fastembed embeddings require the rag feature".to_string())
+        }
+    }
+}
+
+// ============================================================================
+// OpenAI-compatible endpoint
+// ============================================================================
+
+pub struct OpenAiCompatibleProvider {
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    dimensions: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// 按 `index` 还原输入顺序
+fn ordered_vectors(mut data: Vec<EmbeddingData>, expected: usize) -> Result<Vec<Vec<f32>>, String> {
+    if data.len() != expected {
+        return Err(format!("Embedding endpoint returned {} vectors for {} inputs", data.len(), expected));
+    }
+    data.sort_by_key(|d| d.index);
+    Ok(data.into_iter().map(|d| d.embedding).collect())
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for OpenAiCompatibleProvider {
+    fn model_id(&self) -> String {
+        match self.dimensions {
+            Some(dims) => format!("{}/{}@{}", self.base_url, self.model, dims),
+            None => format!("{}/{}", self.base_url, self.model),
+        }
+    }
+
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .map_err(|e| e.to_string())?;
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(REMOTE_BATCH_SIZE) {
+            let mut body = serde_json::json!({ "model": self.model, "input": batch });
+            if let Some(dims) = self.dimensions {
+                body["dimensions"] = serde_json::json!(dims);
+            }
+            let mut request = client.post(format!("{}/embeddings", self.base_url)).json(&body);
+            if let Some(key) = &self.api_key {
+                request = request.bearer_auth(key);
+            }
+            let response = request.send().await.map_err(|e| format!("Network error: {}", e))?;
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                return Err(format!("Embedding API Error ({}): {}", status, text));
+            }
+            let parsed: EmbeddingResponse = response.json().await.map_err(|e| format!("Invalid embedding response: {}", e))?;
+            vectors.extend(ordered_vectors(parsed.data, batch.len())?);
+        }
+        Ok(vectors)
+    }
+}
+
+// ============================================================================
+// Local GGUF
+// ============================================================================
+
+pub struct LocalGgufProvider {
+    model_file: String,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for LocalGgufProvider {
+    fn model_id(&self) -> String {
+        format!("gguf/{}", self.model_file)
+    }
+
+    #[cfg(feature = "llm-inference")]
+    async fn embed(&self, texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        crate::model_registry::validate_file_name(&self.model_file)?;
+        let path = crate::local_model::LocalModelConfig::model_dir().join(&self.model_file);
+        if !path.is_file() {
+            return Err(format!("Embedding model not found: {}", path.display()));
+        }
+        tokio::task::spawn_blocking(move || crate::llm_inference::embed_texts(&path, &texts).map_err(|e| e.to_string()))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
+    #[cfg(not(feature = "llm-inference"))]
+    async fn embed(&self, _texts: Vec<String>) -> Result<Vec<Vec<f32>>, String> {
+        Err("Local GGUF embeddings require the llm-inference feature".to_string())
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingOutput {
+    pub model_id: String,
+    pub dimension: usize,
+    pub vectors: Vec<Vec<f32>>,
+}
+
+/// 使用项目配置的向量模型计算文本向量
+#[tauri::command]
+pub async fn embed_texts(project_root: String, texts: Vec<String>) -> Result<EmbeddingOutput, String> {
+    let provider = provider_for_project(&project_root)?;
+    let vectors = provider.embed(texts).await?;
+    Ok(EmbeddingOutput {
+        model_id: provider.model_id(),
+        dimension: vectors.first().map(|v| v.len()).unwrap_or(0),
+        vectors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parsing() {
+        let config: EmbeddingConfig = serde_yaml::from_str(
+            "provider: openai_compatible\nbase_url: https://api.example.com/v1/\nmodel: text-embedding-3-small\napi_key_env: IFAI_TEST_UNSET_KEY\n",
+        )
+        .unwrap();
+        assert!(matches!(&config, EmbeddingConfig::OpenaiCompatible { model, dimensions: None, .. } if model == "text-embedding-3-small"));
+        // API Key 的环境变量不存在时报错，而不是发送未认证的请求
+        assert!(provider_for(&config).err().unwrap().contains("IFAI_TEST_UNSET_KEY"));
+
+        let local: EmbeddingConfig = serde_yaml::from_str("provider: local_gguf\nmodel_file: bge-m3-Q8_0.gguf\n").unwrap();
+        assert_eq!(provider_for(&local).unwrap().model_id(), "gguf/bge-m3-Q8_0.gguf");
+        assert_eq!(EmbeddingConfig::default(), EmbeddingConfig::Fastembed { model: None });
+    }
+
+    #[test]
+    fn test_ordered_vectors() {
+        let data = vec![
+            EmbeddingData { index: 1, embedding: vec![0.2] },
+            EmbeddingData { index: 0, embedding: vec![0.1] },
+        ];
+        assert_eq!(ordered_vectors(data, 2).unwrap(), vec![vec![0.1], vec![0.2]]);
+        assert!(ordered_vectors(vec![], 1).is_err());
+    }
+}
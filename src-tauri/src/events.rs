@@ -0,0 +1,536 @@
+/*!
+Frontend Events - 前端事件结构与版本
+====================================
+
+推送到 WebView 的事件统一在此定义为带类型的结构，避免各处手写 JSON 导致
+字段在版本间漂移。
+
+- 每个事件都带有 `schema_version` 字段
+- 前端启动时通过 `negotiate_event_schema` 告知自身支持的版本
+- 前端版本较旧时，`downconvert` 将事件逐版本降级为旧格式后再发送
+
+版本历史：
+- v1: 旧版无类型负载（无 `schema_version` 字段）
+- v2: 带类型事件，增加 `schema_version`
+- v3: `log` 事件增加 `level` 字段
+- v4: 新增 `command_edited` 事件（用户在审批时改写了 shell 命令）
+- v5: 新增终止的 `cancelled` 事件（请求被用户取消）
+- v6: 新增 `retrying` 事件（AI 请求遇到瞬时错误后退避重试）
+- v7: 新增 `permission_request` 事件（工具类别在项目中首次使用，征求授权）
+- v8: 新增终止的 `truncated` 事件（回复超过会话硬上限，在句子边界截断）
+- v9: 新增 `tool_fallback` 事件（模型不支持原生工具调用，改用提示词工具协议重试）
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use tauri::{AppHandle, Emitter};
+
+/// 当前后端事件结构版本
+pub const EVENT_SCHEMA_VERSION: u32 = 9;
+
+/// 前端声明支持的版本（未协商时按当前版本发送）
+static FRONTEND_SCHEMA_VERSION: AtomicU32 = AtomicU32::new(EVENT_SCHEMA_VERSION);
+
+// ============================================================================
+// Event Types
+// ============================================================================
+
+/// 流式通道事件（`agent_{id}`、聊天 `event_id` 等）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Log {
+        message: String,
+        #[serde(default)]
+        level: LogLevel,
+    },
+    Thinking {
+        content: String,
+    },
+    Status {
+        status: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        progress: Option<f32>,
+    },
+    ToolCall {
+        #[serde(rename = "toolCall")]
+        tool_call: ToolCallPayload,
+    },
+    ToolResult {
+        #[serde(rename = "toolCallId")]
+        tool_call_id: String,
+        result: String,
+        success: bool,
+    },
+    /// 用户审批时改写了命令，同时记录原命令与实际执行的命令
+    CommandEdited {
+        #[serde(rename = "toolCallId")]
+        tool_call_id: String,
+        original: String,
+        edited: String,
+    },
+    ExploreProgress {
+        #[serde(rename = "exploreProgress")]
+        explore_progress: ExploreProgress,
+    },
+    ExploreFindings {
+        #[serde(rename = "exploreFindings")]
+        explore_findings: ExploreFindings,
+    },
+    Result {
+        result: String,
+    },
+    Error {
+        error: String,
+    },
+    /// 请求被取消（终止事件）
+    Cancelled {
+        reason: String,
+    },
+    /// 即将进行第 `attempt` 次尝试（共 `max_attempts` 次）
+    Retrying {
+        attempt: u32,
+        #[serde(rename = "maxAttempts")]
+        max_attempts: u32,
+        #[serde(rename = "delayMs")]
+        delay_ms: u64,
+        error: String,
+    },
+    /// 工具类别首次使用：对这次调用的审批结果将记录为该类别的决定
+    PermissionRequest {
+        #[serde(rename = "toolCallId")]
+        tool_call_id: String,
+        tool: String,
+        category: crate::tool_permissions::PermissionCategory,
+    },
+    /// 回复超过硬上限被截断（终止事件）；`content` 为截断后保留的完整正文
+    Truncated {
+        reason: String,
+        content: String,
+    },
+    /// 模型拒绝了工具定义，本次及之后的请求改用提示词工具协议
+    ToolFallback {
+        model: String,
+        reason: String,
+    },
+}
+
+/// 日志级别（从低到高）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Progress,
+    Warn,
+    Error,
+}
+
+impl StreamEvent {
+    /// 事件对应的日志级别
+    pub fn level(&self) -> LogLevel {
+        match self {
+            StreamEvent::Log { level, .. } => *level,
+            StreamEvent::Thinking { .. } | StreamEvent::Status { .. } | StreamEvent::ExploreProgress { .. } => LogLevel::Progress,
+            StreamEvent::Error { .. } => LogLevel::Error,
+            StreamEvent::CommandEdited { .. }
+            | StreamEvent::Cancelled { .. }
+            | StreamEvent::Retrying { .. }
+            | StreamEvent::Truncated { .. }
+            | StreamEvent::ToolFallback { .. } => LogLevel::Warn,
+            _ => LogLevel::Info,
+        }
+    }
+
+    /// 是否可按详细程度过滤（工具调用、状态、结果等驱动 UI 流程的事件始终发送）
+    pub fn is_filterable(&self) -> bool {
+        matches!(self, StreamEvent::Log { .. } | StreamEvent::Thinking { .. } | StreamEvent::ExploreProgress { .. })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallPayload {
+    pub id: String,
+    pub tool: String,
+    pub args: Value,
+    pub is_partial: bool,
+    /// 写入类工具的风险评估，随审批请求展示
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk: Option<crate::commit_risk::RiskReport>,
+    /// 写入类工具的差异预览，支持逐块审批
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diff: Option<crate::diff_preview::DiffPreview>,
+    /// shell 工具的命令安全分析
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_risk: Option<crate::command_safety::CommandSafetyReport>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ExploreProgress {
+    /// "scanning" | "analyzing"
+    pub phase: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_file: Option<String>,
+    pub progress: ScanProgress,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanProgress {
+    pub total: usize,
+    pub scanned: usize,
+    pub by_directory: HashMap<String, DirectoryScanStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DirectoryScanStatus {
+    pub total: usize,
+    pub scanned: usize,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExploreFindings {
+    pub summary: String,
+    pub directories: Vec<DirectoryFinding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryFinding {
+    pub path: String,
+    pub file_count: usize,
+    pub key_files: Vec<String>,
+}
+
+/// 全局 `agent:status` 事件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentStatusEvent {
+    pub id: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 全局 `agent:result` 事件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentResultEvent {
+    pub id: String,
+    pub output: String,
+    /// 运行中通过 `agent_emit_artifact` 登记的产物
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<crate::agent_artifacts::AgentArtifact>,
+    /// 本次运行的循环次数、Token 用量与耗时
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget: Option<crate::agent_budget::BudgetReport>,
+}
+
+/// 全局 `agent:orchestration` 事件：多 Agent 任务图的汇总状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct OrchestrationEvent {
+    /// 发起编排的规划 Agent
+    pub root_id: String,
+    pub subagents: Vec<SubagentStatus>,
+    pub completed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SubagentStatus {
+    /// 子 Agent 的运行 id（事件通道为 `agent_{id}`）
+    pub id: String,
+    pub name: String,
+    pub agent_type: String,
+    /// "pending" | "running" | "completed" | "failed" | "skipped"
+    pub status: String,
+    pub depends_on: Vec<String>,
+}
+
+// ============================================================================
+// Versioning
+// ============================================================================
+
+/// 序列化事件并附加 `schema_version`
+pub fn to_versioned(event: &impl Serialize) -> Value {
+    let mut value = serde_json::to_value(event).unwrap_or(Value::Null);
+    if let Some(obj) = value.as_object_mut() {
+        obj.insert("schema_version".to_string(), Value::from(EVENT_SCHEMA_VERSION));
+    }
+    value
+}
+
+/// 将事件逐版本降级到 `target` 版本
+pub fn downconvert(mut value: Value, target: u32) -> Value {
+    let mut version = value.get("schema_version").and_then(|v| v.as_u64()).unwrap_or(1) as u32;
+    while version > target.max(1) {
+        value = match version {
+            // v9 -> v8: tool_fallback 转为 warn 级别的 log 事件
+            9 => {
+                if value.get("type").and_then(|t| t.as_str()) == Some("tool_fallback") {
+                    let message = format!(
+                        "Model {} does not support tool calling, retrying with prompt-based tools",
+                        value["model"].as_str().unwrap_or("")
+                    );
+                    value = serde_json::json!({ "type": "log", "message": message, "level": "warn", "schema_version": 9 });
+                }
+                value
+            }
+            // v8 -> v7: truncated 转为 warn 级别的 log 事件（旧前端保留已收到的全部输出）
+            8 => {
+                if value.get("type").and_then(|t| t.as_str()) == Some("truncated") {
+                    let message = format!("Response truncated: {}", value["reason"].as_str().unwrap_or(""));
+                    value = serde_json::json!({ "type": "log", "message": message, "level": "warn", "schema_version": 8 });
+                }
+                value
+            }
+            // v7 -> v6: permission_request 转为 log 事件（旧前端仍按 tool_call 逐次审批）
+            7 => {
+                if value.get("type").and_then(|t| t.as_str()) == Some("permission_request") {
+                    let message = format!(
+                        "Permission requested: {} (first use by {})",
+                        value["category"].as_str().unwrap_or(""),
+                        value["tool"].as_str().unwrap_or("")
+                    );
+                    value = serde_json::json!({ "type": "log", "message": message, "level": "info", "schema_version": 7 });
+                }
+                value
+            }
+            // v6 -> v5: retrying 转为 warn 级别的 log 事件
+            6 => {
+                if value.get("type").and_then(|t| t.as_str()) == Some("retrying") {
+                    let message = format!(
+                        "Retrying ({}/{}) in {}ms: {}",
+                        value["attempt"],
+                        value["maxAttempts"],
+                        value["delayMs"],
+                        value["error"].as_str().unwrap_or("")
+                    );
+                    value = serde_json::json!({ "type": "log", "message": message, "level": "warn", "schema_version": 6 });
+                }
+                value
+            }
+            // v5 -> v4: cancelled 转为 error 事件，旧前端同样会结束当前请求
+            5 => {
+                if value.get("type").and_then(|t| t.as_str()) == Some("cancelled") {
+                    let error = value["reason"].as_str().unwrap_or(crate::cancellation::CANCELLED_ERROR).to_string();
+                    value = serde_json::json!({ "type": "error", "error": error, "schema_version": 5 });
+                }
+                value
+            }
+            // v4 -> v3: command_edited 转为 warn 级别的 log 事件
+            4 => {
+                if value.get("type").and_then(|t| t.as_str()) == Some("command_edited") {
+                    let message = format!(
+                        "Command edited by user: `{}` -> `{}`",
+                        value["original"].as_str().unwrap_or(""),
+                        value["edited"].as_str().unwrap_or("")
+                    );
+                    value = serde_json::json!({ "type": "log", "message": message, "level": "warn", "schema_version": 4 });
+                }
+                value
+            }
+            // v3 -> v2: 去掉 log 事件的 level 字段
+            3 => {
+                if let Some(obj) = value.as_object_mut() {
+                    if obj.get("type").and_then(|t| t.as_str()) == Some("log") {
+                        obj.remove("level");
+                    }
+                }
+                value
+            }
+            // v2 -> v1: 事件负载一致，仅去掉版本字段
+            2 => {
+                if let Some(obj) = value.as_object_mut() {
+                    obj.remove("schema_version");
+                }
+                value
+            }
+            _ => value,
+        };
+        version -= 1;
+    }
+    if let Some(obj) = value.as_object_mut() {
+        if obj.contains_key("schema_version") {
+            obj.insert("schema_version".to_string(), Value::from(version));
+        }
+    }
+    value
+}
+
+/// 按前端协商的版本发送事件
+pub fn emit_event(app: &AppHandle, channel: &str, event: &impl Serialize) {
+    let target = FRONTEND_SCHEMA_VERSION.load(Ordering::Relaxed);
+    let payload = downconvert(to_versioned(event), target);
+    if let Err(e) = app.emit(channel, payload) {
+        eprintln!("[Events] Failed to emit on {}: {}", channel, e);
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 前端声明支持的事件版本，返回后端实际使用的版本
+#[tauri::command]
+pub fn negotiate_event_schema(frontend_version: u32) -> u32 {
+    let effective = frontend_version.clamp(1, EVENT_SCHEMA_VERSION);
+    FRONTEND_SCHEMA_VERSION.store(effective, Ordering::Relaxed);
+    println!("[Events] Frontend schema v{}, emitting v{}", frontend_version, effective);
+    effective
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_stream_event_matches_legacy_shape() {
+        let event = StreamEvent::ToolCall {
+            tool_call: ToolCallPayload {
+                id: "call_1".to_string(),
+                tool: "agent_read_file".to_string(),
+                args: json!({ "rel_path": "a.rs" }),
+                is_partial: false,
+                risk: None,
+                diff: None,
+                command_risk: None,
+            },
+        };
+        assert_eq!(to_versioned(&event), json!({
+            "type": "tool_call",
+            "toolCall": { "id": "call_1", "tool": "agent_read_file", "args": { "rel_path": "a.rs" }, "isPartial": false },
+            "schema_version": EVENT_SCHEMA_VERSION
+        }));
+
+        let status = StreamEvent::Status { status: "running".to_string(), progress: None };
+        assert_eq!(to_versioned(&status), json!({ "type": "status", "status": "running", "schema_version": EVENT_SCHEMA_VERSION }));
+    }
+
+    #[test]
+    fn test_downconvert_to_v1_strips_version() {
+        let value = to_versioned(&StreamEvent::Result { result: "ok".to_string() });
+        assert_eq!(downconvert(value.clone(), 1), json!({ "type": "result", "result": "ok" }));
+        assert_eq!(downconvert(value.clone(), EVENT_SCHEMA_VERSION), value);
+    }
+
+    #[test]
+    fn test_log_level_downconvert() {
+        let value = to_versioned(&StreamEvent::Log { message: "hi".to_string(), level: LogLevel::Warn });
+        assert_eq!(value["level"], "warn");
+        assert_eq!(downconvert(value.clone(), 2), json!({ "type": "log", "message": "hi", "schema_version": 2 }));
+        assert_eq!(downconvert(value, 1), json!({ "type": "log", "message": "hi" }));
+
+        // 旧负载缺少 level 时按 info 解析
+        let parsed: StreamEvent = serde_json::from_value(json!({ "type": "log", "message": "x" })).unwrap();
+        assert_eq!(parsed.level(), LogLevel::Info);
+    }
+
+    #[test]
+    fn test_command_edited_downconvert() {
+        let value = to_versioned(&StreamEvent::CommandEdited {
+            tool_call_id: "call_1".to_string(),
+            original: "rm -rf build".to_string(),
+            edited: "rm -rf build/tmp".to_string(),
+        });
+        assert_eq!(value["toolCallId"], "call_1");
+        assert_eq!(downconvert(value.clone(), 3), json!({
+            "type": "log",
+            "message": "Command edited by user: `rm -rf build` -> `rm -rf build/tmp`",
+            "level": "warn",
+            "schema_version": 3
+        }));
+        assert_eq!(downconvert(value, 2)["type"], "log");
+    }
+
+    #[test]
+    fn test_permission_request_downconvert() {
+        let value = to_versioned(&StreamEvent::PermissionRequest {
+            tool_call_id: "call_1".to_string(),
+            tool: "bash".to_string(),
+            category: crate::tool_permissions::PermissionCategory::RunCommands,
+        });
+        assert_eq!(value["category"], "run_commands");
+        assert_eq!(downconvert(value, 6), json!({
+            "type": "log",
+            "message": "Permission requested: run_commands (first use by bash)",
+            "level": "info",
+            "schema_version": 6
+        }));
+    }
+
+    #[test]
+    fn test_truncated_downconvert() {
+        let value = to_versioned(&StreamEvent::Truncated { reason: "hard cap of 200 tokens".to_string(), content: "Done.".to_string() });
+        assert_eq!(value["type"], "truncated");
+        assert_eq!(downconvert(value, 7), json!({
+            "type": "log",
+            "message": "Response truncated: hard cap of 200 tokens",
+            "level": "warn",
+            "schema_version": 7
+        }));
+    }
+
+    #[test]
+    fn test_tool_fallback_downconvert() {
+        let value = to_versioned(&StreamEvent::ToolFallback { model: "qwen-7b".to_string(), reason: "tools not supported".to_string() });
+        assert_eq!(value["type"], "tool_fallback");
+        assert_eq!(downconvert(value, 8), json!({
+            "type": "log",
+            "message": "Model qwen-7b does not support tool calling, retrying with prompt-based tools",
+            "level": "warn",
+            "schema_version": 8
+        }));
+    }
+
+    #[test]
+    fn test_retrying_downconvert() {
+        let value = to_versioned(&StreamEvent::Retrying { attempt: 2, max_attempts: 3, delay_ms: 1000, error: "AI API Error (429)".to_string() });
+        assert_eq!(value["maxAttempts"], 3);
+        assert_eq!(downconvert(value.clone(), 5), json!({
+            "type": "log",
+            "message": "Retrying (2/3) in 1000ms: AI API Error (429)",
+            "level": "warn",
+            "schema_version": 5
+        }));
+        assert_eq!(downconvert(value, 2), json!({ "type": "log", "message": "Retrying (2/3) in 1000ms: AI API Error (429)", "schema_version": 2 }));
+    }
+
+    #[test]
+    fn test_cancelled_downconvert() {
+        let value = to_versioned(&StreamEvent::Cancelled { reason: "Request cancelled by user".to_string() });
+        assert_eq!(value["type"], "cancelled");
+        assert_eq!(downconvert(value.clone(), 4), json!({ "type": "error", "error": "Request cancelled by user", "schema_version": 4 }));
+        assert_eq!(downconvert(value, 1), json!({ "type": "error", "error": "Request cancelled by user" }));
+    }
+
+    #[test]
+    fn test_stream_event_roundtrip() {
+        let event = StreamEvent::ExploreProgress {
+            explore_progress: ExploreProgress {
+                phase: "scanning".to_string(),
+                current_path: Some("src".to_string()),
+                current_file: None,
+                progress: ScanProgress { total: 3, scanned: 1, by_directory: HashMap::new() },
+            },
+        };
+        let value = to_versioned(&event);
+        assert_eq!(value["exploreProgress"]["currentPath"], "src");
+        assert_eq!(value["exploreProgress"]["progress"]["byDirectory"], json!({}));
+        let parsed: StreamEvent = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed, event);
+    }
+}
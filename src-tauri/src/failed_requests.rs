@@ -0,0 +1,349 @@
+/*!
+Failed Request Log - 失败请求记录与回放
+=======================================
+
+功能：
+- 记录最近 N 次失败的 AI Provider 请求（已脱敏）及其错误响应
+- 支持修改参数后重新发起请求，并返回新旧结果对照
+- 用于快速排查协议兼容问题
+- 按记录的协议回放（OpenAI 兼容 / Anthropic / Gemini 各自的地址与认证头）
+
+脱敏规则：
+- 不保存 API Key（请求头中的凭证不入库）
+- 请求体中出现的 API Key 文本替换为 `***`
+- 错误信息按常见凭证格式脱敏（`secret_scrub`）
+- base_url 去掉查询参数（部分 Provider 通过 `?key=` 传递凭证）
+*/
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::core_traits::ai::{AIProtocol, AIProviderConfig};
+use crate::{anthropic_api, gemini_api};
+use crate::secret_scrub::scrub_secrets;
+
+/// 最多保留的失败请求数
+const MAX_FAILED_REQUESTS: usize = 20;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 一次失败的 Provider 请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedRequest {
+    pub id: String,
+    /// Unix 毫秒时间戳
+    pub timestamp: i64,
+    /// 请求来源（如 "completion", "agent_stream"）
+    pub source: String,
+    pub provider_id: String,
+    pub provider_name: String,
+    /// 去掉查询参数后的请求地址
+    pub base_url: String,
+    /// 请求使用的协议，决定回放时的地址与认证方式
+    #[serde(default)]
+    pub protocol: AIProtocol,
+    /// Gemini 的模型在 URL 中而不在请求体里，因此单独记录
+    pub model: String,
+    /// 脱敏后的请求体
+    pub request_body: Value,
+    /// 错误信息 / 错误响应
+    pub error: String,
+}
+
+/// 回放时可覆盖的参数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplayOverrides {
+    /// 凭证不会被记录，回放时必须提供
+    pub api_key: Option<String>,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    /// 合并到请求体顶层的字段（如 temperature、max_tokens、tools）
+    pub params: Option<serde_json::Map<String, Value>>,
+}
+
+/// 回放结果（旧错误 vs 新结果）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayComparison {
+    pub id: String,
+    pub original_request: Value,
+    pub original_error: String,
+    pub replay_request: Value,
+    pub success: bool,
+    pub status: Option<u16>,
+    /// 新的响应体（原样返回，便于对照）
+    pub response: Option<String>,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+}
+
+// ============================================================================
+// Store
+// ============================================================================
+
+static FAILED_REQUESTS: OnceLock<Mutex<VecDeque<FailedRequest>>> = OnceLock::new();
+
+fn store() -> &'static Mutex<VecDeque<FailedRequest>> {
+    FAILED_REQUESTS.get_or_init(|| Mutex::new(VecDeque::with_capacity(MAX_FAILED_REQUESTS)))
+}
+
+/// 去掉 URL 中的查询参数
+fn strip_query(url: &str) -> String {
+    url.split('?').next().unwrap_or(url).to_string()
+}
+
+/// 递归替换请求体中出现的密钥
+fn redact_value(value: &Value, secret: &str) -> Value {
+    if secret.is_empty() {
+        return value.clone();
+    }
+    match value {
+        Value::String(s) if s.contains(secret) => Value::String(s.replace(secret, "***")),
+        Value::Array(items) => Value::Array(items.iter().map(|v| redact_value(v, secret)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter().map(|(k, v)| (k.clone(), redact_value(v, secret))).collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// 记录一次失败请求
+pub fn record_failure(source: &str, config: &AIProviderConfig, request_body: &Value, error: &str) {
+    let entry = FailedRequest {
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+        source: source.to_string(),
+        provider_id: config.id.clone(),
+        provider_name: config.name.clone(),
+        base_url: strip_query(&config.base_url),
+        protocol: config.protocol.clone(),
+        model: request_body.get("model")
+            .and_then(|m| m.as_str())
+            .or(config.models.first().map(String::as_str))
+            .unwrap_or_default()
+            .to_string(),
+        request_body: redact_value(request_body, &config.api_key),
+        error: scrub_secrets(&if config.api_key.is_empty() { error.to_string() } else { error.replace(&config.api_key, "***") }),
+    };
+
+    if let Ok(mut list) = store().lock() {
+        println!("[FailedRequests] Recorded failed {} request: {}", source, entry.id);
+        if list.len() >= MAX_FAILED_REQUESTS {
+            list.pop_front();
+        }
+        list.push_back(entry);
+    }
+}
+
+fn find_request(id: &str) -> Option<FailedRequest> {
+    store().lock().ok()?.iter().find(|r| r.id == id).cloned()
+}
+
+/// 根据覆盖参数构建回放请求体
+fn build_replay_body(original: &FailedRequest, overrides: &ReplayOverrides) -> Value {
+    let mut body = original.request_body.clone();
+    let is_gemini = matches!(original.protocol, AIProtocol::Gemini);
+    if let Some(obj) = body.as_object_mut() {
+        // 回放统一使用非流式请求，便于直接对照完整响应；Gemini 通过端点区分流式，请求体不接受 stream / model
+        if is_gemini {
+            obj.remove("stream");
+        } else {
+            obj.insert("stream".to_string(), Value::Bool(false));
+        }
+        if let Some(model) = overrides.model.as_ref().filter(|_| !is_gemini) {
+            obj.insert("model".to_string(), Value::String(model.clone()));
+        }
+        if let Some(params) = &overrides.params {
+            for (k, v) in params {
+                obj.insert(k.clone(), v.clone());
+            }
+        }
+    }
+    body
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 列出最近的失败请求（新的在前）
+#[tauri::command]
+pub fn list_failed_requests() -> Vec<FailedRequest> {
+    store()
+        .lock()
+        .map(|list| list.iter().rev().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// 获取单个失败请求
+#[tauri::command]
+pub fn get_failed_request(id: String) -> Result<FailedRequest, String> {
+    find_request(&id).ok_or_else(|| format!("Failed request {} not found", id))
+}
+
+/// 清空失败请求记录
+#[tauri::command]
+pub fn clear_failed_requests() {
+    if let Ok(mut list) = store().lock() {
+        list.clear();
+    }
+}
+
+/// 使用可选覆盖参数重新发起失败的请求
+#[tauri::command]
+pub async fn replay_failed_request(
+    id: String,
+    overrides: Option<ReplayOverrides>,
+) -> Result<ReplayComparison, String> {
+    let original = find_request(&id).ok_or_else(|| format!("Failed request {} not found", id))?;
+    let overrides = overrides.unwrap_or_default();
+    let api_key = overrides.api_key.clone()
+        .ok_or("api_key is required to replay a request (credentials are never stored)")?;
+    let config = AIProviderConfig {
+        id: original.provider_id.clone(),
+        name: original.provider_name.clone(),
+        api_key: api_key.clone(),
+        base_url: overrides.base_url.clone().unwrap_or_else(|| original.base_url.clone()),
+        models: vec![overrides.model.clone().unwrap_or_else(|| original.model.clone())],
+        protocol: original.protocol.clone(),
+        ..Default::default()
+    };
+    let body = build_replay_body(&original, &overrides);
+
+    println!("[FailedRequests] Replaying {} against {} ({:?})", id, config.base_url, config.protocol);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(120))
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    // 与 ai_utils 的非流式请求相同的地址与认证头
+    let request = if anthropic_api::is_anthropic(&config) {
+        anthropic_api::post(&client, &config)
+    } else if gemini_api::is_gemini(&config) {
+        gemini_api::post(&client, &config, false)
+    } else {
+        client.post(&config.base_url)
+            .header("Authorization", format!("Bearer {}", api_key))
+    };
+
+    let start = Instant::now();
+    let result = request
+        .json(&body)
+        .send()
+        .await;
+
+    let (success, status, response, error) = match result {
+        Ok(resp) => {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            if status.is_success() {
+                (true, Some(status.as_u16()), Some(text), None)
+            } else {
+                (false, Some(status.as_u16()), Some(text.clone()), Some(format!("AI API Error ({}): {}", status, text)))
+            }
+        }
+        Err(e) => (false, None, None, Some(format!("Network/Request error: {}", e))),
+    };
+
+    Ok(ReplayComparison {
+        id,
+        original_request: original.request_body,
+        original_error: original.error,
+        replay_request: redact_value(&body, &api_key),
+        success,
+        status,
+        response,
+        error,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_record_failure_redacts_secrets() {
+        let config = AIProviderConfig {
+            id: "p1".to_string(),
+            name: "Test".to_string(),
+            api_key: "sk-secret".to_string(),
+            base_url: "https://api.example.com/v1/chat?key=sk-secret".to_string(),
+            models: vec!["m1".to_string()],
+            ..Default::default()
+        };
+        let body = json!({ "model": "m1", "messages": [{ "role": "user", "content": "key is sk-secret" }] });
+        record_failure("completion", &config, &body, "invalid key sk-secret");
+
+        let entry = list_failed_requests().into_iter().find(|r| r.provider_id == "p1").unwrap();
+        assert_eq!(entry.base_url, "https://api.example.com/v1/chat");
+        assert_eq!(entry.model, "m1");
+        assert!(!entry.request_body.to_string().contains("sk-secret"));
+        assert!(!entry.error.contains("sk-secret"));
+    }
+
+    #[test]
+    fn test_build_replay_body_applies_overrides() {
+        let original = FailedRequest {
+            id: "x".to_string(),
+            timestamp: 0,
+            source: "agent_stream".to_string(),
+            provider_id: String::new(),
+            provider_name: String::new(),
+            base_url: String::new(),
+            protocol: AIProtocol::Openai,
+            model: "old".to_string(),
+            request_body: json!({ "model": "old", "stream": true, "messages": [] }),
+            error: String::new(),
+        };
+        let mut params = serde_json::Map::new();
+        params.insert("temperature".to_string(), json!(0.1));
+        let overrides = ReplayOverrides {
+            model: Some("new".to_string()),
+            params: Some(params),
+            ..Default::default()
+        };
+
+        let body = build_replay_body(&original, &overrides);
+        assert_eq!(body["model"], "new");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["temperature"], 0.1);
+
+        // Gemini：模型在 URL 中，请求体不带 stream / model
+        let gemini = FailedRequest {
+            protocol: AIProtocol::Gemini,
+            request_body: json!({ "contents": [], "generationConfig": { "temperature": 0.7 } }),
+            ..original
+        };
+        let body = build_replay_body(&gemini, &overrides);
+        assert!(body.get("stream").is_none());
+        assert!(body.get("model").is_none());
+        assert_eq!(body["temperature"], 0.1);
+    }
+
+    #[test]
+    fn test_record_failure_keeps_protocol_and_model() {
+        let config = AIProviderConfig {
+            id: "gemini-p".to_string(),
+            base_url: "https://generativelanguage.googleapis.com".to_string(),
+            models: vec!["gemini-2.0-flash".to_string()],
+            protocol: AIProtocol::Gemini,
+            ..Default::default()
+        };
+        record_failure("chat_stream", &config, &json!({ "contents": [] }), "HTTP 400");
+
+        let entry = list_failed_requests().into_iter().find(|r| r.provider_id == "gemini-p").unwrap();
+        assert!(matches!(entry.protocol, AIProtocol::Gemini));
+        assert_eq!(entry.model, "gemini-2.0-flash");
+    }
+}
@@ -0,0 +1,503 @@
+/*!
+File Edit - 基于 diff 的局部文件编辑
+====================================
+
+`agent_edit_file` 的核心逻辑：模型只需给出要修改的片段，而不是整个文件。
+
+- 输入：search/replace 块（JSON 数组，或 `<<<<<<< SEARCH` / `=======` / `>>>>>>> REPLACE` 文本）
+  或 unified diff（每个 hunk 视为一个 search/replace 块，`@@ -l` 作为位置提示）
+- 匹配：精确匹配 → 忽略空白匹配 → 相似度匹配（以完全相同的行为锚点，相似度 ≥ `FUZZY_THRESHOLD`）；
+  非精确匹配时按实际缩进调整替换内容
+- 多处匹配时取最接近位置提示的一处，没有提示则判为歧义
+- 每个块独立应用，结果中分别列出成功与失败的块，方便模型重试
+- 完整的原文与新内容只随 UI 事件发送（差异视图），模型只收到各块的结果与简短片段
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// 相似度匹配的最低分数
+const FUZZY_THRESHOLD: f32 = 0.8;
+/// 块结果中片段的最大行数
+const SNIPPET_LINES: usize = 6;
+
+/// 一个 search/replace 块
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EditBlock {
+    pub search: String,
+    pub replace: String,
+    /// 位置提示（1-based 行号），用于多处匹配时消歧
+    #[serde(default)]
+    pub line_hint: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStrategy {
+    Exact,
+    Whitespace,
+    Fuzzy,
+    Insert,
+}
+
+/// 单个块的应用结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkResult {
+    pub index: usize,
+    /// 匹配位置（1-based 行号）
+    pub line: Option<usize>,
+    pub strategy: Option<MatchStrategy>,
+    pub similarity: Option<f32>,
+    pub error: Option<String>,
+    /// 成功时为写入的内容开头，失败时为未找到的 search 内容开头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EditOutcome {
+    pub content: String,
+    pub applied: Vec<HunkResult>,
+    pub failed: Vec<HunkResult>,
+}
+
+struct Match {
+    start: usize,
+    strategy: MatchStrategy,
+    similarity: f32,
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// 解析 `<<<<<<< SEARCH` / `=======` / `>>>>>>> REPLACE` 格式
+pub fn parse_search_replace_blocks(text: &str) -> Vec<EditBlock> {
+    let mut blocks = Vec::new();
+    let mut lines = text.lines();
+
+    while let Some(line) = lines.next() {
+        if !line.trim_start().starts_with("<<<<<<< SEARCH") {
+            continue;
+        }
+        let mut search = Vec::new();
+        let mut replace = Vec::new();
+        let mut in_replace = false;
+        let mut closed = false;
+        for line in lines.by_ref() {
+            let marker = line.trim();
+            if !in_replace && marker == "=======" {
+                in_replace = true;
+            } else if in_replace && marker.starts_with(">>>>>>> REPLACE") {
+                closed = true;
+                break;
+            } else if in_replace {
+                replace.push(line);
+            } else {
+                search.push(line);
+            }
+        }
+        if closed {
+            blocks.push(EditBlock { search: search.join("\n"), replace: replace.join("\n"), line_hint: None });
+        }
+    }
+
+    blocks
+}
+
+/// 解析 unified diff，每个 hunk 转为一个块
+pub fn parse_unified_diff(diff: &str) -> Result<Vec<EditBlock>, String> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(Vec<&str>, Vec<&str>, usize)> = None;
+
+    let mut finish = |current: &mut Option<(Vec<&str>, Vec<&str>, usize)>| {
+        if let Some((search, replace, hint)) = current.take() {
+            blocks.push(EditBlock { search: search.join("\n"), replace: replace.join("\n"), line_hint: Some(hint) });
+        }
+    };
+
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("@@") {
+            finish(&mut current);
+            let hint = header.trim_start()
+                .strip_prefix('-')
+                .and_then(|s| s.split([',', ' ']).next())
+                .and_then(|s| s.parse::<usize>().ok())
+                .ok_or_else(|| format!("Invalid hunk header: {}", line))?;
+            current = Some((Vec::new(), Vec::new(), hint.max(1)));
+            continue;
+        }
+        let Some((search, replace, _)) = current.as_mut() else {
+            continue;
+        };
+        if line.starts_with("---") || line.starts_with("+++") || line.starts_with('\\') {
+            continue;
+        }
+        match line.chars().next() {
+            Some('-') => search.push(&line[1..]),
+            Some('+') => replace.push(&line[1..]),
+            Some(' ') => {
+                search.push(&line[1..]);
+                replace.push(&line[1..]);
+            }
+            None => {
+                search.push("");
+                replace.push("");
+            }
+            _ => {}
+        }
+    }
+    finish(&mut current);
+
+    if blocks.is_empty() {
+        return Err("No hunks found in diff".to_string());
+    }
+    Ok(blocks)
+}
+
+/// 合并工具参数：`edits` 为 JSON 块数组，`diff` 为 unified diff 或 SEARCH/REPLACE 文本
+pub fn collect_blocks(edits: Option<Vec<EditBlock>>, diff: Option<&str>) -> Result<Vec<EditBlock>, String> {
+    let mut blocks = edits.unwrap_or_default();
+    if let Some(diff) = diff.filter(|d| !d.trim().is_empty()) {
+        if diff.contains("<<<<<<< SEARCH") {
+            blocks.extend(parse_search_replace_blocks(diff));
+        } else {
+            blocks.extend(parse_unified_diff(diff)?);
+        }
+    }
+    if blocks.is_empty() {
+        return Err("No edits provided: pass 'edits' (search/replace blocks) or 'diff'".to_string());
+    }
+    Ok(blocks)
+}
+
+// ============================================================================
+// Matching
+// ============================================================================
+
+fn block_lines(text: &str) -> Vec<&str> {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    if text.is_empty() {
+        Vec::new()
+    } else {
+        text.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l)).collect()
+    }
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn line_similarity(a: &str, b: &str) -> f32 {
+    let (a, b) = (a.trim(), b.trim());
+    let max_len = a.chars().count().max(b.chars().count());
+    if max_len == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f32 / max_len as f32
+}
+
+fn window_similarity(window: &[String], search: &[&str]) -> f32 {
+    let total: f32 = window.iter().zip(search).map(|(a, b)| line_similarity(a, b)).sum();
+    total / search.len() as f32
+}
+
+/// 在多个候选中选取最接近位置提示的一个
+fn pick(candidates: Vec<usize>, hint: Option<usize>) -> Result<Option<usize>, String> {
+    match (candidates.len(), hint) {
+        (0, _) => Ok(None),
+        (1, _) => Ok(Some(candidates[0])),
+        (_, Some(hint)) => Ok(candidates.into_iter().min_by_key(|start| (start + 1).abs_diff(hint))),
+        (n, None) => Err(format!("Search block matches {} locations; include more surrounding lines", n)),
+    }
+}
+
+fn find_match(lines: &[String], search: &[&str], hint: Option<usize>) -> Result<Match, String> {
+    if search.len() > lines.len() {
+        return Err("Search block is longer than the file".to_string());
+    }
+    let starts = 0..=lines.len() - search.len();
+    let matches_with = |eq: &dyn Fn(&str, &str) -> bool| -> Vec<usize> {
+        starts.clone()
+            .filter(|&i| lines[i..i + search.len()].iter().zip(search).all(|(a, b)| eq(a, b)))
+            .collect()
+    };
+
+    if let Some(start) = pick(matches_with(&|a, b| a == b), hint)? {
+        return Ok(Match { start, strategy: MatchStrategy::Exact, similarity: 1.0 });
+    }
+    if let Some(start) = pick(matches_with(&|a, b| a.trim() == b.trim()), hint)? {
+        return Ok(Match { start, strategy: MatchStrategy::Whitespace, similarity: 1.0 });
+    }
+
+    // 相似度匹配：只评估至少有一行完全相同（忽略空白）的窗口
+    let mut anchored: Vec<usize> = Vec::new();
+    for (k, search_line) in search.iter().enumerate() {
+        if search_line.trim().is_empty() {
+            continue;
+        }
+        for (i, line) in lines.iter().enumerate() {
+            if line.trim() == search_line.trim() && i >= k && i - k + search.len() <= lines.len() {
+                anchored.push(i - k);
+            }
+        }
+    }
+    anchored.sort_unstable();
+    anchored.dedup();
+
+    let scored: Vec<(usize, f32)> = anchored.into_iter()
+        .map(|start| (start, window_similarity(&lines[start..start + search.len()], search)))
+        .filter(|(_, score)| *score >= FUZZY_THRESHOLD)
+        .collect();
+    let best = scored.iter().map(|(_, s)| *s).fold(0.0f32, f32::max);
+    let top: Vec<usize> = scored.iter().filter(|(_, s)| best - s < 0.001).map(|(start, _)| *start).collect();
+    match pick(top, hint)? {
+        Some(start) => Ok(Match { start, strategy: MatchStrategy::Fuzzy, similarity: best }),
+        None => Err("Search block not found in file".to_string()),
+    }
+}
+
+fn indent_of(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// 按匹配位置的实际缩进调整替换内容
+fn reindent(replace: &[&str], search: &[&str], matched: &[String]) -> Vec<String> {
+    let pair = search.iter().zip(matched).find(|(s, _)| !s.trim().is_empty());
+    let Some((search_line, file_line)) = pair else {
+        return replace.iter().map(|l| l.to_string()).collect();
+    };
+    let (from, to) = (indent_of(search_line), indent_of(file_line));
+    if from == to {
+        return replace.iter().map(|l| l.to_string()).collect();
+    }
+    replace.iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.to_string()
+            } else if let Some(rest) = line.strip_prefix(from) {
+                format!("{}{}", to, rest)
+            } else {
+                line.to_string()
+            }
+        })
+        .collect()
+}
+
+// ============================================================================
+// Apply
+// ============================================================================
+
+/// 取前 `SNIPPET_LINES` 行，超出时注明剩余行数
+fn snippet<S: AsRef<str>>(lines: &[S]) -> Option<String> {
+    if lines.is_empty() {
+        return None;
+    }
+    let mut out = lines.iter().take(SNIPPET_LINES).map(|l| l.as_ref()).collect::<Vec<_>>().join("\n");
+    if lines.len() > SNIPPET_LINES {
+        out.push_str(&format!("\n… ({} more lines)", lines.len() - SNIPPET_LINES));
+    }
+    Some(out)
+}
+
+/// 依次应用所有块；失败的块跳过并记录原因
+pub fn apply_edits(content: &str, blocks: &[EditBlock]) -> EditOutcome {
+    let crlf = content.contains("\r\n");
+    let normalized = if crlf { content.replace("\r\n", "\n") } else { content.to_string() };
+    let mut lines: Vec<String> = if normalized.is_empty() {
+        Vec::new()
+    } else {
+        normalized.split('\n').map(String::from).collect()
+    };
+
+    let mut applied = Vec::new();
+    let mut failed = Vec::new();
+    // 已应用块造成的行号偏移，用于修正后续块的位置提示
+    let mut offset: isize = 0;
+
+    for (index, block) in blocks.iter().enumerate() {
+        let search = block_lines(&block.search);
+        let replace = block_lines(&block.replace);
+        let hint = block.line_hint.map(|h| (h as isize + offset).max(1) as usize);
+
+        if search.is_empty() {
+            let at = hint.map(|h| (h - 1).min(lines.len())).unwrap_or_else(|| {
+                // 追加时放在末尾换行之前
+                if lines.last().is_some_and(|l| l.is_empty()) { lines.len() - 1 } else { lines.len() }
+            });
+            lines.splice(at..at, replace.iter().map(|l| l.to_string()));
+            offset += replace.len() as isize;
+            applied.push(HunkResult {
+                index,
+                line: Some(at + 1),
+                strategy: Some(MatchStrategy::Insert),
+                similarity: None,
+                error: None,
+                snippet: snippet(&replace),
+            });
+            continue;
+        }
+
+        match find_match(&lines, &search, hint) {
+            Ok(m) => {
+                let matched = &lines[m.start..m.start + search.len()];
+                let new_lines = if m.strategy == MatchStrategy::Exact {
+                    replace.iter().map(|l| l.to_string()).collect()
+                } else {
+                    reindent(&replace, &search, matched)
+                };
+                offset += new_lines.len() as isize - search.len() as isize;
+                let written = snippet(&new_lines);
+                lines.splice(m.start..m.start + search.len(), new_lines);
+                applied.push(HunkResult {
+                    index,
+                    line: Some(m.start + 1),
+                    strategy: Some(m.strategy),
+                    similarity: (m.strategy == MatchStrategy::Fuzzy).then_some(m.similarity),
+                    error: None,
+                    snippet: written,
+                });
+            }
+            Err(e) => failed.push(HunkResult {
+                index,
+                line: None,
+                strategy: None,
+                similarity: None,
+                error: Some(e),
+                snippet: snippet(&search),
+            }),
+        }
+    }
+
+    let joined = lines.join("\n");
+    EditOutcome {
+        content: if crlf { joined.replace('\n', "\r\n") } else { joined },
+        applied,
+        failed,
+    }
+}
+
+/// 发给模型的 `agent_edit_file` 结果：去掉完整的原文与新内容，只保留各块结果
+///
+/// 结果 JSON 之后追加的说明（如自动格式化）原样保留；无法解析时原样返回
+pub fn result_for_model(tool_result: &str) -> String {
+    let mut values = serde_json::Deserializer::from_str(tool_result).into_iter::<Value>();
+    let Some(Ok(result)) = values.next() else { return tool_result.to_string() };
+    let rest = &tool_result[values.byte_offset()..];
+    let summary = serde_json::json!({
+        "success": result["success"],
+        "message": result["message"],
+        "filePath": result["filePath"],
+        "applied": result["applied"],
+        "failed": result["failed"],
+    });
+    format!("{}{}", summary, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(search: &str, replace: &str) -> EditBlock {
+        EditBlock { search: search.to_string(), replace: replace.to_string(), line_hint: None }
+    }
+
+    const SOURCE: &str = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n";
+
+    #[test]
+    fn test_exact_and_whitespace_match() {
+        let out = apply_edits(SOURCE, &[block("    let x = 1;", "    let x = 2;")]);
+        assert_eq!(out.content, "fn main() {\n    let x = 2;\n    println!(\"{}\", x);\n}\n");
+        assert_eq!(out.applied[0].strategy, Some(MatchStrategy::Exact));
+
+        // 缩进不同：按文件中的缩进替换
+        let out = apply_edits(SOURCE, &[block("let x = 1;\nprintln!(\"{}\", x);", "let x = 3;\nprintln!(\"x = {}\", x);")]);
+        assert_eq!(out.content, "fn main() {\n    let x = 3;\n    println!(\"x = {}\", x);\n}\n");
+        assert_eq!(out.applied[0].strategy, Some(MatchStrategy::Whitespace));
+    }
+
+    #[test]
+    fn test_fuzzy_match_and_failure() {
+        let out = apply_edits(SOURCE, &[
+            block("fn main() {\n    let x = 1;\n    println!(\"{}\", y);", "fn main() {\n    let x = 5;\n    println!(\"{}\", x);"),
+            block("let missing = true;", "let missing = false;"),
+        ]);
+        assert_eq!(out.applied.len(), 1);
+        assert_eq!(out.applied[0].strategy, Some(MatchStrategy::Fuzzy));
+        assert!(out.content.contains("let x = 5;"));
+        assert_eq!(out.failed.len(), 1);
+        assert_eq!(out.failed[0].index, 1);
+    }
+
+    #[test]
+    fn test_result_for_model_drops_contents() {
+        let out = apply_edits(SOURCE, &[block("    let x = 1;", "    let x = 2;"), block("let missing = true;", "")]);
+        assert_eq!(out.applied[0].snippet.as_deref(), Some("    let x = 2;"));
+        assert_eq!(out.failed[0].snippet.as_deref(), Some("let missing = true;"));
+
+        let result = serde_json::json!({
+            "success": false,
+            "message": "Applied 1 of 2 edit(s); 1 failed",
+            "applied": out.applied,
+            "failed": out.failed,
+            "originalContent": SOURCE,
+            "newContent": out.content,
+            "filePath": "src/main.rs",
+        });
+        let for_model = result_for_model(&format!("{}\n\n[Formatted with rustfmt]", result));
+        assert!(!for_model.contains("originalContent") && !for_model.contains("newContent"));
+        assert!(for_model.contains("\"snippet\":\"    let x = 2;\""));
+        assert!(for_model.ends_with("\n\n[Formatted with rustfmt]"));
+        assert_eq!(result_for_model("Error: not found"), "Error: not found");
+    }
+
+    #[test]
+    fn test_ambiguous_match_uses_hint() {
+        let content = "a\nx\nb\nx\nc";
+        let out = apply_edits(content, &[block("x", "y")]);
+        assert!(out.failed[0].error.as_ref().unwrap().contains("2 locations"));
+
+        let out = apply_edits(content, &[EditBlock { search: "x".into(), replace: "y".into(), line_hint: Some(4) }]);
+        assert_eq!(out.content, "a\nx\nb\ny\nc");
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let diff = "--- a/main.rs\n+++ b/main.rs\n@@ -1,3 +1,3 @@\n fn main() {\n-    let x = 1;\n+    let x = 10;\n     println!(\"{}\", x);\n@@ -4,1 +4,2 @@\n }\n+// end\n";
+        let blocks = parse_unified_diff(diff).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].line_hint, Some(1));
+
+        let out = apply_edits(SOURCE, &blocks);
+        assert!(out.failed.is_empty());
+        assert_eq!(out.content, "fn main() {\n    let x = 10;\n    println!(\"{}\", x);\n}\n// end\n");
+    }
+
+    #[test]
+    fn test_search_replace_text_and_crlf() {
+        let text = "Change it:\n<<<<<<< SEARCH\n    let x = 1;\n=======\n    let x = 7;\n>>>>>>> REPLACE\n";
+        let blocks = parse_search_replace_blocks(text);
+        assert_eq!(blocks, vec![block("    let x = 1;", "    let x = 7;")]);
+
+        let out = apply_edits(&SOURCE.replace('\n', "\r\n"), &blocks);
+        assert_eq!(out.content, "fn main() {\r\n    let x = 7;\r\n    println!(\"{}\", x);\r\n}\r\n");
+    }
+
+    #[test]
+    fn test_insert_into_empty_file() {
+        let out = apply_edits("", &[block("", "line one\nline two\n")]);
+        assert_eq!(out.content, "line one\nline two");
+        assert_eq!(out.applied[0].strategy, Some(MatchStrategy::Insert));
+    }
+}
@@ -0,0 +1,332 @@
+/*!
+Formatter - 代码格式化
+======================
+
+按扩展名选择格式化工具（rustfmt / prettier / black / gofmt）。内容通过 stdin 交给格式化工具，
+从 stdout 取回结果，不让工具直接改写文件：
+
+- `format_file` / `format_project` 返回统一差异，`apply` 为 true 时才写回磁盘
+- IFAI.md 中 `formatting.on_agent_write: true` 时，Agent 写入 / 编辑文件后自动格式化，
+  并在工具结果中告知模型文件已被改动
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::time::{timeout, Duration};
+
+use crate::commands::core_wrappers::ensure_in_root;
+
+/// 单个文件的格式化超时
+const FORMAT_TIMEOUT_SECS: u64 = 30;
+/// `format_project` 默认最多检查的文件数
+const DEFAULT_MAX_PROJECT_FILES: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Formatter {
+    Rustfmt,
+    Prettier,
+    Black,
+    Gofmt,
+}
+
+/// IFAI.md 中的 `formatting` 配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FormattingConfig {
+    /// Agent 写入文件后自动格式化
+    pub on_agent_write: bool,
+    /// 不使用的格式化工具
+    pub disabled: Vec<Formatter>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormatResult {
+    /// 相对项目根目录的路径
+    pub path: String,
+    pub formatter: Formatter,
+    pub changed: bool,
+    /// 统一差异（无变化时为空）
+    pub diff: String,
+    /// 格式化结果已写回文件
+    pub applied: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectFormatResult {
+    /// 只包含需要改动的文件
+    pub files: Vec<FormatResult>,
+    /// 检查过的文件数
+    pub checked: usize,
+    pub errors: Vec<String>,
+    /// 达到文件数上限，其余文件未检查
+    pub truncated: bool,
+}
+
+impl Formatter {
+    /// 按扩展名选择格式化工具
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "rs" => Some(Formatter::Rustfmt),
+            "js" | "jsx" | "mjs" | "cjs" | "ts" | "tsx" | "json" | "css" | "scss" | "less" | "html" | "vue" | "md"
+            | "yaml" | "yml" => Some(Formatter::Prettier),
+            "py" | "pyi" => Some(Formatter::Black),
+            "go" => Some(Formatter::Gofmt),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Formatter::Rustfmt => "rustfmt",
+            Formatter::Prettier => "prettier",
+            Formatter::Black => "black",
+            Formatter::Gofmt => "gofmt",
+        }
+    }
+
+    /// 从 stdin 读入、向 stdout 输出的命令行
+    fn command(self, root: &Path, file: &Path) -> (PathBuf, Vec<String>) {
+        let file_arg = file.to_string_lossy().to_string();
+        match self {
+            Formatter::Rustfmt => (
+                PathBuf::from("rustfmt"),
+                vec!["--edition".to_string(), cargo_edition(root, file).unwrap_or_else(|| "2021".to_string())],
+            ),
+            Formatter::Prettier => {
+                // 优先使用项目内安装的 prettier，保证与项目锁定的版本一致
+                let bin = if cfg!(windows) { "prettier.cmd" } else { "prettier" };
+                let local = root.join("node_modules").join(".bin").join(bin);
+                let program = if local.is_file() { local } else { PathBuf::from(bin) };
+                (program, vec!["--stdin-filepath".to_string(), file_arg])
+            }
+            Formatter::Black => (
+                PathBuf::from("black"),
+                vec!["-q".to_string(), "--stdin-filename".to_string(), file_arg, "-".to_string()],
+            ),
+            Formatter::Gofmt => (PathBuf::from("gofmt"), Vec::new()),
+        }
+    }
+}
+
+/// 最近的 Cargo.toml 中声明的 edition（rustfmt 从 stdin 读取时无法自行推断）
+fn cargo_edition(root: &Path, file: &Path) -> Option<String> {
+    let pattern = regex::Regex::new(r#"(?m)^\s*edition\s*=\s*"(\d{4})""#).ok()?;
+    file.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(root))
+        .filter_map(|dir| std::fs::read_to_string(dir.join("Cargo.toml")).ok())
+        .find_map(|manifest| pattern.captures(&manifest).map(|c| c[1].to_string()))
+}
+
+/// 格式化内容并返回结果，不修改文件
+pub async fn run_formatter(formatter: Formatter, root: &Path, file: &Path, content: &str) -> Result<String, String> {
+    let (program, args) = formatter.command(root, file);
+    let working_dir = file.parent().filter(|dir| dir.is_dir()).unwrap_or(root);
+    let mut child = Command::new(&program)
+        .args(&args)
+        .current_dir(working_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => format!("{} is not installed (or not on PATH)", formatter.label()),
+            _ => format!("Failed to start {}: {}", formatter.label(), e),
+        })?;
+
+    let mut stdin = child.stdin.take().ok_or("Failed to open formatter stdin")?;
+    let input = content.to_string();
+    // 写入放到单独的任务中，避免输出缓冲区写满时互相等待
+    let writer = tokio::spawn(async move {
+        let _ = stdin.write_all(input.as_bytes()).await;
+    });
+    let output = timeout(Duration::from_secs(FORMAT_TIMEOUT_SECS), child.wait_with_output())
+        .await
+        .map_err(|_| format!("{} timed out after {}s", formatter.label(), FORMAT_TIMEOUT_SECS))?
+        .map_err(|e| format!("{} failed: {}", formatter.label(), e))?;
+    let _ = writer.await;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let detail: Vec<&str> = stderr.lines().filter(|l| !l.trim().is_empty()).take(5).collect();
+        return Err(format!("{} failed: {}", formatter.label(), detail.join("\n")));
+    }
+    String::from_utf8(output.stdout).map_err(|_| format!("{} produced non UTF-8 output", formatter.label()))
+}
+
+fn formatting_config(project_root: &str) -> FormattingConfig {
+    crate::project_config::load_project_config_sync(project_root)
+        .and_then(|config| config.formatting)
+        .unwrap_or_default()
+}
+
+/// 格式化单个文件（`rel_path` 相对项目根目录），`apply` 为 true 时写回
+pub async fn format_path(project_root: &str, rel_path: &str, apply: bool) -> Result<FormatResult, String> {
+    let target = ensure_in_root(project_root, rel_path, "format")?;
+    let formatter = Formatter::for_path(&target).ok_or_else(|| format!("No formatter available for {}", rel_path))?;
+    if formatting_config(project_root).disabled.contains(&formatter) {
+        return Err(format!("{} is disabled in IFAI.md", formatter.label()));
+    }
+    let root = std::fs::canonicalize(project_root).map_err(|e| format!("Invalid project root {}: {}", project_root, e))?;
+    format_target(formatter, &root, &target, apply).await
+}
+
+async fn format_target(formatter: Formatter, root: &Path, target: &Path, apply: bool) -> Result<FormatResult, String> {
+    let display = target.strip_prefix(root).unwrap_or(target).to_string_lossy().replace('\\', "/");
+    let original = std::fs::read_to_string(target).map_err(|e| format!("Failed to read {}: {}", display, e))?;
+    let formatted = run_formatter(formatter, root, target, &original).await?;
+
+    let changed = formatted != original;
+    let diff = if changed {
+        crate::diff_preview::preview(&display, Some(&original), &formatted)?.unified
+    } else {
+        String::new()
+    };
+    if apply && changed {
+        std::fs::write(target, &formatted).map_err(|e| format!("Failed to write {}: {}", display, e))?;
+        println!("[Formatter] Formatted {} with {}", display, formatter.label());
+    }
+    Ok(FormatResult { path: display, formatter, changed, diff, applied: apply && changed })
+}
+
+/// 格式化项目中所有支持的文件（遵循 .gitignore），只返回需要改动的文件
+pub async fn format_project_files(project_root: &str, apply: bool, max_files: usize) -> Result<ProjectFormatResult, String> {
+    let root = std::fs::canonicalize(project_root).map_err(|e| format!("Invalid project root {}: {}", project_root, e))?;
+    let config = formatting_config(project_root);
+    let mut unavailable: HashSet<Formatter> = config.disabled.iter().copied().collect();
+
+    let mut result = ProjectFormatResult { files: Vec::new(), checked: 0, errors: Vec::new(), truncated: false };
+    for entry in ignore::WalkBuilder::new(&root).build().flatten() {
+        if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+            continue;
+        }
+        let Some(formatter) = Formatter::for_path(entry.path()).filter(|f| !unavailable.contains(f)) else {
+            continue;
+        };
+        if result.checked >= max_files {
+            result.truncated = true;
+            break;
+        }
+        result.checked += 1;
+        match format_target(formatter, &root, entry.path(), apply).await {
+            Ok(file) if file.changed => result.files.push(file),
+            Ok(_) => {}
+            Err(e) => {
+                // 工具未安装时只报告一次，跳过该工具的其余文件
+                if e.contains("not installed") {
+                    unavailable.insert(formatter);
+                }
+                result.errors.push(e);
+            }
+        }
+    }
+    println!(
+        "[Formatter] Checked {} files, {} need formatting{}",
+        result.checked,
+        result.files.len(),
+        if apply { " (applied)" } else { "" }
+    );
+    Ok(result)
+}
+
+/// Agent 写入文件后按 IFAI.md 配置自动格式化；文件被改动时返回附加给模型的说明
+pub async fn format_after_agent_write(project_root: &str, rel_path: &str) -> Option<String> {
+    if !formatting_config(project_root).on_agent_write {
+        return None;
+    }
+    match format_path(project_root, rel_path, true).await {
+        Ok(result) if result.changed => Some(format!(
+            "\n\n[The file was auto-formatted with {} after writing. Re-read it before making further edits.]",
+            result.formatter.label()
+        )),
+        Ok(_) => None,
+        Err(e) => {
+            println!("[Formatter] Skipped auto-format for {}: {}", rel_path, e);
+            None
+        }
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 格式化单个文件，返回差异；`apply` 为 true 时写回
+#[tauri::command]
+pub async fn format_file(project_root: String, path: String, apply: Option<bool>) -> Result<FormatResult, String> {
+    format_path(&project_root, &path, apply.unwrap_or(false)).await
+}
+
+/// 格式化整个项目，返回需要改动的文件及差异；`apply` 为 true 时写回
+#[tauri::command]
+pub async fn format_project(
+    project_root: String,
+    apply: Option<bool>,
+    max_files: Option<usize>,
+) -> Result<ProjectFormatResult, String> {
+    let max_files = max_files.filter(|n| *n > 0).unwrap_or(DEFAULT_MAX_PROJECT_FILES);
+    format_project_files(&project_root, apply.unwrap_or(false), max_files).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detection_and_edition() {
+        assert_eq!(Formatter::for_path(Path::new("src/main.rs")), Some(Formatter::Rustfmt));
+        assert_eq!(Formatter::for_path(Path::new("web/App.TSX")), Some(Formatter::Prettier));
+        assert_eq!(Formatter::for_path(Path::new("tool.py")), Some(Formatter::Black));
+        assert_eq!(Formatter::for_path(Path::new("main.go")), Some(Formatter::Gofmt));
+        assert_eq!(Formatter::for_path(Path::new("Makefile")), None);
+
+        let root = std::env::temp_dir().join(format!("ifai_format_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("crate/src")).unwrap();
+        std::fs::write(root.join("crate/Cargo.toml"), "[package]\nname = \"x\"\nedition = \"2018\"\n").unwrap();
+        assert_eq!(cargo_edition(&root, &root.join("crate/src/lib.rs")).as_deref(), Some("2018"));
+        assert_eq!(cargo_edition(&root, &root.join("other.rs")), None);
+
+        let config: FormattingConfig = serde_yaml::from_str("on_agent_write: true\ndisabled: [prettier]").unwrap();
+        assert!(config.on_agent_write);
+        assert_eq!(config.disabled, vec![Formatter::Prettier]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn test_format_rust_file() {
+        let root = std::env::temp_dir().join(format!("ifai_format_rs_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let file = root.join("lib.rs");
+        std::fs::write(&file, "fn  main( ){let x=1;}\n").unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        let preview = match format_path(&root_str, "lib.rs", false).await {
+            Ok(result) => result,
+            // 没有安装 rustfmt 的环境跳过
+            Err(e) if e.contains("not installed") => return,
+            Err(e) => panic!("{}", e),
+        };
+        assert!(preview.changed && !preview.applied);
+        assert!(preview.diff.contains("+    let x = 1;"));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn  main( ){let x=1;}\n");
+
+        let applied = format_path(&root_str, "lib.rs", true).await.unwrap();
+        assert!(applied.applied);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "fn main() {\n    let x = 1;\n}\n");
+        assert!(!format_path(&root_str, "lib.rs", false).await.unwrap().changed);
+
+        std::fs::write(&file, "fn broken( {\n").unwrap();
+        assert!(format_path(&root_str, "lib.rs", false).await.unwrap_err().contains("rustfmt failed"));
+
+        let project = format_project_files(&root_str, false, 10).await.unwrap();
+        assert_eq!((project.checked, project.errors.len()), (1, 1));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,218 @@
+/*!
+Filesystem Retry - 文件系统瞬时错误重试
+========================================
+
+Windows 上杀毒软件、编辑器等会短暂占用文件，导致写入偶发失败
+（os error 32 共享冲突 / 33 锁冲突 / 5 拒绝访问）。
+
+- 区分瞬时锁错误与永久错误：只有瞬时错误才会退避重试
+- 指数退避（默认 5 次，50ms 起，最长 1s）
+- 发生过重试的操作会生成 `FsRetryRecord`，返回给调用方并记录到最近重试日志
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// 最大尝试次数（含首次）
+const MAX_ATTEMPTS: u32 = 5;
+/// 首次重试前的等待时间
+const BASE_DELAY_MS: u64 = 50;
+/// 单次等待上限
+const MAX_DELAY_MS: u64 = 1000;
+/// 保留的重试记录数
+const MAX_RETRY_LOG: usize = 50;
+
+/// 一次发生过重试的文件操作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsRetryRecord {
+    /// 操作名（如 "write", "remove_file"）
+    pub operation: String,
+    pub path: String,
+    /// 总尝试次数
+    pub attempts: u32,
+    /// 每次失败的错误信息
+    pub errors: Vec<String>,
+    pub succeeded: bool,
+    pub timestamp: i64,
+}
+
+static RETRY_LOG: OnceLock<Mutex<VecDeque<FsRetryRecord>>> = OnceLock::new();
+
+fn retry_log() -> &'static Mutex<VecDeque<FsRetryRecord>> {
+    RETRY_LOG.get_or_init(|| Mutex::new(VecDeque::with_capacity(MAX_RETRY_LOG)))
+}
+
+/// 是否为可重试的瞬时错误（文件被占用 / 锁定）
+pub fn is_transient(err: &io::Error) -> bool {
+    if matches!(err.kind(), io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) {
+        return true;
+    }
+    match err.raw_os_error() {
+        // ERROR_ACCESS_DENIED / ERROR_SHARING_VIOLATION / ERROR_LOCK_VIOLATION
+        Some(5) | Some(32) | Some(33) if cfg!(windows) => true,
+        // EBUSY / ETXTBSY
+        Some(16) | Some(26) if cfg!(unix) => true,
+        _ => false,
+    }
+}
+
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_millis((BASE_DELAY_MS << (attempt - 1)).min(MAX_DELAY_MS))
+}
+
+fn finish<T>(
+    operation: &str,
+    path: &Path,
+    attempts: u32,
+    errors: Vec<String>,
+    result: io::Result<T>,
+) -> (io::Result<T>, Option<FsRetryRecord>) {
+    if attempts <= 1 {
+        return (result, None);
+    }
+    let record = FsRetryRecord {
+        operation: operation.to_string(),
+        path: path.to_string_lossy().to_string(),
+        attempts,
+        errors,
+        succeeded: result.is_ok(),
+        timestamp: chrono::Utc::now().timestamp_millis(),
+    };
+    println!(
+        "[FsRetry] {} {} after {} attempts ({})",
+        operation,
+        record.path,
+        attempts,
+        if record.succeeded { "succeeded" } else { "gave up" }
+    );
+    if let Ok(mut log) = retry_log().lock() {
+        if log.len() >= MAX_RETRY_LOG {
+            log.pop_front();
+        }
+        log.push_back(record.clone());
+    }
+    (result, Some(record))
+}
+
+/// 同步重试（用于原子提交等同步代码）
+pub fn retry_io<T>(
+    operation: &str,
+    path: &Path,
+    mut op: impl FnMut() -> io::Result<T>,
+) -> (io::Result<T>, Option<FsRetryRecord>) {
+    let mut errors = Vec::new();
+    let mut attempt = 1;
+    loop {
+        match op() {
+            Err(e) if is_transient(&e) && attempt < MAX_ATTEMPTS => {
+                errors.push(e.to_string());
+                std::thread::sleep(backoff(attempt));
+                attempt += 1;
+            }
+            result => {
+                if let Err(e) = &result {
+                    errors.push(e.to_string());
+                }
+                return finish(operation, path, attempt, errors, result);
+            }
+        }
+    }
+}
+
+/// 异步重试（用于 Agent 工具）
+pub async fn retry_io_async<T, F, Fut>(
+    operation: &str,
+    path: &Path,
+    mut op: F,
+) -> (io::Result<T>, Option<FsRetryRecord>)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = io::Result<T>>,
+{
+    let mut errors = Vec::new();
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if is_transient(&e) && attempt < MAX_ATTEMPTS => {
+                errors.push(e.to_string());
+                tokio::time::sleep(backoff(attempt)).await;
+                attempt += 1;
+            }
+            result => {
+                if let Err(e) = &result {
+                    errors.push(e.to_string());
+                }
+                return finish(operation, path, attempt, errors, result);
+            }
+        }
+    }
+}
+
+/// 获取最近发生的文件操作重试（新的在前）
+#[tauri::command]
+pub fn get_fs_retry_log() -> Vec<FsRetryRecord> {
+    retry_log()
+        .lock()
+        .map(|log| log.iter().rev().cloned().collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn locked() -> io::Error {
+        if cfg!(windows) {
+            io::Error::from_raw_os_error(32)
+        } else {
+            io::Error::from(io::ErrorKind::WouldBlock)
+        }
+    }
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient(&locked()));
+        assert!(!is_transient(&io::Error::from(io::ErrorKind::NotFound)));
+    }
+
+    #[test]
+    fn test_retry_until_success() {
+        let mut calls = 0;
+        let (result, record) = retry_io("write", Path::new("a.txt"), || {
+            calls += 1;
+            if calls < 3 { Err(locked()) } else { Ok(calls) }
+        });
+        assert_eq!(result.unwrap(), 3);
+        let record = record.unwrap();
+        assert_eq!(record.attempts, 3);
+        assert_eq!(record.errors.len(), 2);
+        assert!(record.succeeded);
+    }
+
+    #[test]
+    fn test_permanent_error_is_not_retried() {
+        let mut calls = 0;
+        let (result, record) = retry_io("write", Path::new("a.txt"), || -> io::Result<()> {
+            calls += 1;
+            Err(io::Error::from(io::ErrorKind::NotFound))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+        assert!(record.is_none());
+    }
+
+    #[test]
+    fn test_gives_up_after_max_attempts() {
+        let (result, record) = retry_io("remove_file", Path::new("a.txt"), || -> io::Result<()> { Err(locked()) });
+        assert!(result.is_err());
+        let record = record.unwrap();
+        assert_eq!(record.attempts, MAX_ATTEMPTS);
+        assert!(!record.succeeded);
+        assert!(get_fs_retry_log().iter().any(|r| r.operation == "remove_file"));
+    }
+}
@@ -0,0 +1,446 @@
+/*!
+Gemini API - Google Gemini generateContent 协议
+===============================================
+
+`AIProtocol::Gemini` 的请求构建与响应解析，供 `ai_utils` 使用：
+
+- system 消息合并为 `systemInstruction`，assistant 角色映射为 `model`
+- assistant 的 `tool_calls` 转为 `functionCall` 部件，`tool` 消息转为 `functionResponse`
+  （Gemini 按函数名关联结果，名称从前面的 `tool_calls` 中按 id 查找）
+- OpenAI 格式的工具定义转为 `functionDeclarations`，并移除 Gemini 不支持的 schema 字段
+- `streamGenerateContent?alt=sse` 的数据块转换为 OpenAI 兼容的流式数据块；
+  Gemini 的 `functionCall` 总是一次性给出完整参数，因此每个调用只输出一个数据块
+*/
+
+use crate::core_traits::ai::{AIProtocol, AIProviderConfig, Content, ContentPart, FunctionCall, Message, ToolCall};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Gemini 不接受的 JSON Schema 字段
+const UNSUPPORTED_SCHEMA_KEYS: &[&str] = &["$schema", "additionalProperties", "$ref", "definitions"];
+
+pub fn is_gemini(config: &AIProviderConfig) -> bool {
+    matches!(config.protocol, AIProtocol::Gemini)
+}
+
+/// 请求地址：允许配置为域名、`/v1beta` 或 `/v1beta/models`
+pub fn endpoint(base_url: &str, model: &str, stream: bool) -> String {
+    let base = base_url.trim_end_matches('/');
+    let models = if base.ends_with("/models") {
+        base.to_string()
+    } else if base.ends_with("/v1beta") || base.ends_with("/v1") {
+        format!("{}/models", base)
+    } else {
+        format!("{}/v1beta/models", base)
+    };
+    if stream {
+        format!("{}/{}:streamGenerateContent?alt=sse", models, model)
+    } else {
+        format!("{}/{}:generateContent", models, model)
+    }
+}
+
+/// 带认证头的 POST 请求
+pub fn post(client: &reqwest::Client, config: &AIProviderConfig, stream: bool) -> reqwest::RequestBuilder {
+    let model = config.models.first().cloned().unwrap_or_default();
+    client
+        .post(endpoint(&config.base_url, &model, stream))
+        .header("x-goog-api-key", &config.api_key)
+        .header("Content-Type", "application/json")
+}
+
+// ============================================================================
+// Request
+// ============================================================================
+
+/// 图片：data URL 转为 inlineData，其他按 fileData 引用
+fn image_part(url: &str) -> Value {
+    if let Some(rest) = url.strip_prefix("data:") {
+        if let Some((mime_type, data)) = rest.split_once(";base64,") {
+            return json!({ "inlineData": { "mimeType": mime_type, "data": data } });
+        }
+    }
+    json!({ "fileData": { "fileUri": url } })
+}
+
+fn content_parts(content: &Content) -> Vec<Value> {
+    match content {
+        Content::Text(text) if text.trim().is_empty() => Vec::new(),
+        Content::Text(text) => vec![json!({ "text": text })],
+        Content::Parts(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text, .. } if text.trim().is_empty() => None,
+                ContentPart::Text { text, .. } => Some(json!({ "text": text })),
+                ContentPart::ImageUrl { image_url } => Some(image_part(&image_url.url)),
+            })
+            .collect(),
+    }
+}
+
+fn content_text(content: &Content) -> String {
+    match content {
+        Content::Text(text) => text.clone(),
+        Content::Parts(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// 转换消息列表，返回 (systemInstruction, contents)
+///
+/// 相邻的同角色消息会被合并（多个 functionResponse 必须位于同一个 user 轮次）
+pub fn convert_messages(messages: &[Message]) -> (Option<Value>, Vec<Value>) {
+    let mut system_parts = Vec::new();
+    let mut call_names: HashMap<String, String> = HashMap::new();
+    let mut converted: Vec<(&str, Vec<Value>)> = Vec::new();
+
+    for msg in messages {
+        let (role, parts) = match msg.role.as_str() {
+            "system" => {
+                let text = content_text(&msg.content);
+                if !text.trim().is_empty() {
+                    system_parts.push(json!({ "text": text }));
+                }
+                continue;
+            }
+            "assistant" => {
+                let mut parts = content_parts(&msg.content);
+                for call in msg.tool_calls.iter().flatten() {
+                    call_names.insert(call.id.clone(), call.function.name.clone());
+                    let args = serde_json::from_str::<Value>(&call.function.arguments)
+                        .ok()
+                        .filter(|v| v.is_object())
+                        .unwrap_or_else(|| json!({}));
+                    parts.push(json!({ "functionCall": { "name": call.function.name, "args": args } }));
+                }
+                ("model", parts)
+            }
+            "tool" => {
+                let id = msg.tool_call_id.clone().unwrap_or_default();
+                let name = call_names.get(&id).cloned().unwrap_or(id);
+                let part = json!({
+                    "functionResponse": { "name": name, "response": { "content": content_text(&msg.content) } }
+                });
+                ("user", vec![part])
+            }
+            _ => ("user", content_parts(&msg.content)),
+        };
+        if parts.is_empty() {
+            continue;
+        }
+        match converted.last_mut() {
+            Some((last_role, last_parts)) if *last_role == role => last_parts.extend(parts),
+            _ => converted.push((role, parts)),
+        }
+    }
+
+    let system = (!system_parts.is_empty()).then(|| json!({ "parts": system_parts }));
+    let contents = converted
+        .into_iter()
+        .map(|(role, parts)| json!({ "role": role, "parts": parts }))
+        .collect();
+    (system, contents)
+}
+
+/// 递归移除 Gemini 不支持的 schema 字段
+fn sanitize_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(obj) => Value::Object(
+            obj.iter()
+                .filter(|(key, _)| !UNSUPPORTED_SCHEMA_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), sanitize_schema(value)))
+                .collect::<Map<_, _>>(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(sanitize_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+/// OpenAI 格式工具定义 -> Gemini functionDeclarations
+pub fn convert_tools(tools: &[Value]) -> Vec<Value> {
+    tools
+        .iter()
+        .filter_map(|tool| {
+            let function = tool.get("function").unwrap_or(tool);
+            let name = function["name"].as_str()?;
+            let mut declaration = json!({
+                "name": name,
+                "description": function["description"].as_str().unwrap_or(""),
+            });
+            // 无参数的函数不能带空的 properties
+            if let Some(params) = function.get("parameters").filter(|p| !p["properties"].as_object().is_some_and(|o| o.is_empty())) {
+                declaration["parameters"] = sanitize_schema(params);
+            }
+            Some(declaration)
+        })
+        .collect()
+}
+
+/// 构建 generateContent 请求体
+pub fn build_request(messages: &[Message], tools: Option<&[Value]>) -> Value {
+    let (system, contents) = convert_messages(messages);
+    let mut body = json!({ "contents": contents });
+    if let Some(system) = system {
+        body["systemInstruction"] = system;
+    }
+    if let Some(tools) = tools.filter(|t| !t.is_empty()) {
+        body["tools"] = json!([{ "functionDeclarations": convert_tools(tools) }]);
+    }
+    body
+}
+
+/// 温度调度与长度预设写入的是顶层 `temperature` / `max_tokens`，Gemini 需要放在 `generationConfig` 中
+pub fn move_temperature(body: &mut Value) {
+    if let Some(t) = body.as_object_mut().and_then(|obj| obj.remove("temperature")) {
+        body["generationConfig"]["temperature"] = t;
+    }
+    if let Some(n) = body.as_object_mut().and_then(|obj| obj.remove("max_tokens")) {
+        body["generationConfig"]["maxOutputTokens"] = n;
+    }
+}
+
+// ============================================================================
+// Response
+// ============================================================================
+
+fn api_error(res_json: &Value) -> Option<String> {
+    let error = res_json.get("error")?;
+    Some(format!(
+        "AI API Error ({}): {}",
+        error["status"].as_str().unwrap_or("error"),
+        error["message"].as_str().unwrap_or("unknown error")
+    ))
+}
+
+fn finish_reason(reason: &str, has_tool_calls: bool) -> &'static str {
+    match reason {
+        _ if has_tool_calls => "tool_calls",
+        "MAX_TOKENS" => "length",
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" => "content_filter",
+        _ => "stop",
+    }
+}
+
+fn to_tool_call(call: &Value, fallback_id: usize) -> ToolCall {
+    let args = call.get("args").filter(|a| a.is_object()).cloned().unwrap_or_else(|| json!({}));
+    ToolCall {
+        id: call["id"].as_str().map(|s| s.to_string()).unwrap_or_else(|| format!("gemini_call_{}", fallback_id)),
+        r#type: "function".to_string(),
+        function: FunctionCall {
+            name: call["name"].as_str().unwrap_or("").to_string(),
+            arguments: args.to_string(),
+        },
+    }
+}
+
+/// 解析非流式响应
+pub fn parse_response(res_json: &Value) -> Result<Message, String> {
+    if let Some(err) = api_error(res_json) {
+        return Err(err);
+    }
+    if let Some(reason) = res_json["promptFeedback"]["blockReason"].as_str() {
+        return Err(format!("AI API Error: prompt blocked ({})", reason));
+    }
+    let parts = res_json["candidates"][0]["content"]["parts"]
+        .as_array()
+        .ok_or("Malformed AI response: candidates[0].content.parts missing")?;
+
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for part in parts {
+        if let Some(call) = part.get("functionCall") {
+            tool_calls.push(to_tool_call(call, tool_calls.len()));
+        } else if part["thought"].as_bool() != Some(true) {
+            text.push_str(part["text"].as_str().unwrap_or(""));
+        }
+    }
+
+    Ok(Message {
+        role: "assistant".to_string(),
+        content: Content::Text(text),
+        tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+        tool_call_id: None,
+    })
+}
+
+/// SSE 数据块转换器：累积内容并输出 OpenAI 兼容的数据块
+#[derive(Default)]
+pub struct GeminiStream {
+    tool_calls: Vec<ToolCall>,
+    content: String,
+}
+
+impl GeminiStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理一个 SSE 数据块，返回转换后的数据块；错误响应返回 Err
+    pub fn handle_event(&mut self, data: &str) -> Result<Vec<Value>, String> {
+        let Ok(event) = serde_json::from_str::<Value>(data) else {
+            return Ok(Vec::new());
+        };
+        if let Some(err) = api_error(&event) {
+            return Err(err);
+        }
+        if let Some(reason) = event["promptFeedback"]["blockReason"].as_str() {
+            return Err(format!("AI API Error: prompt blocked ({})", reason));
+        }
+
+        let delta_chunk = |delta: Value| json!({ "choices": [{ "index": 0, "delta": delta }] });
+        let candidate = &event["candidates"][0];
+        let mut chunks = Vec::new();
+
+        for part in candidate["content"]["parts"].as_array().into_iter().flatten() {
+            if let Some(call) = part.get("functionCall") {
+                let index = self.tool_calls.len();
+                let tool_call = to_tool_call(call, index);
+                chunks.push(delta_chunk(json!({ "tool_calls": [{
+                    "index": index, "id": tool_call.id, "type": "function",
+                    "function": { "name": tool_call.function.name, "arguments": tool_call.function.arguments }
+                }] })));
+                self.tool_calls.push(tool_call);
+            } else if let Some(text) = part["text"].as_str().filter(|t| !t.is_empty()) {
+                if part["thought"].as_bool() == Some(true) {
+                    chunks.push(delta_chunk(json!({ "reasoning_content": text })));
+                } else {
+                    self.content.push_str(text);
+                    chunks.push(delta_chunk(json!({ "content": text })));
+                }
+            }
+        }
+
+        if let Some(reason) = candidate["finishReason"].as_str() {
+            chunks.push(json!({ "choices": [{
+                "index": 0, "delta": {}, "finish_reason": finish_reason(reason, !self.tool_calls.is_empty())
+            }] }));
+        }
+        Ok(chunks)
+    }
+
+    /// 已收到的工具调用
+    pub fn tool_calls(&self) -> &[ToolCall] {
+        &self.tool_calls
+    }
+
+    /// 构建最终消息
+    pub fn into_message(self) -> Message {
+        Message {
+            role: "assistant".to_string(),
+            content: Content::Text(self.content),
+            tool_calls: (!self.tool_calls.is_empty()).then_some(self.tool_calls),
+            tool_call_id: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(role: &str, text: &str) -> Message {
+        Message { role: role.to_string(), content: Content::Text(text.to_string()), tool_calls: None, tool_call_id: None }
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let base = "https://generativelanguage.googleapis.com";
+        assert_eq!(
+            endpoint(base, "gemini-2.0-flash", false),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent"
+        );
+        assert_eq!(
+            endpoint("https://proxy.local/v1beta/models/", "gemini-pro", true),
+            "https://proxy.local/v1beta/models/gemini-pro:streamGenerateContent?alt=sse"
+        );
+    }
+
+    #[test]
+    fn test_convert_messages_with_tools() {
+        let mut assistant = msg("assistant", "");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall { name: "agent_read_file".to_string(), arguments: r#"{"rel_path":"a.rs"}"#.to_string() },
+        }]);
+        let mut result = msg("tool", "fn main() {}");
+        result.tool_call_id = Some("call_1".to_string());
+        let messages = vec![msg("system", "Be brief."), msg("user", "Read a.rs"), assistant, result, msg("user", "Explain")];
+
+        let (system, contents) = convert_messages(&messages);
+        assert_eq!(system.unwrap()["parts"][0]["text"], "Be brief.");
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(contents[1]["parts"][0]["functionCall"]["args"]["rel_path"], "a.rs");
+        assert_eq!(contents[2]["parts"][0]["functionResponse"]["name"], "agent_read_file");
+        assert_eq!(contents[2]["parts"][1]["text"], "Explain");
+    }
+
+    #[test]
+    fn test_convert_tools_strips_unsupported_schema() {
+        let tools = vec![
+            json!({ "type": "function", "function": { "name": "bash", "description": "Run", "parameters": {
+                "type": "object", "additionalProperties": false,
+                "properties": { "command": { "type": "string" } }
+            } } }),
+            json!({ "type": "function", "function": { "name": "now", "parameters": { "type": "object", "properties": {} } } }),
+        ];
+        let converted = convert_tools(&tools);
+        assert!(converted[0]["parameters"].get("additionalProperties").is_none());
+        assert_eq!(converted[0]["parameters"]["properties"]["command"]["type"], "string");
+        assert!(converted[1].get("parameters").is_none());
+
+        let mut body = build_request(&[msg("user", "hi")], Some(&tools));
+        body["temperature"] = json!(0.3);
+        move_temperature(&mut body);
+        assert!(body.get("temperature").is_none());
+        assert_eq!(body["generationConfig"]["temperature"], 0.3);
+
+        body["max_tokens"] = json!(1024);
+        move_temperature(&mut body);
+        assert!(body.get("max_tokens").is_none());
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 1024);
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let res = json!({ "candidates": [{ "content": { "role": "model", "parts": [
+            { "text": "thinking...", "thought": true },
+            { "text": "Checking" },
+            { "functionCall": { "name": "bash", "args": { "command": "pwd" } } }
+        ] }, "finishReason": "STOP" }] });
+        let message = parse_response(&res).unwrap();
+        assert!(matches!(message.content, Content::Text(ref t) if t == "Checking"));
+        let calls = message.tool_calls.unwrap();
+        assert_eq!(calls[0].id, "gemini_call_0");
+        assert_eq!(calls[0].function.arguments, r#"{"command":"pwd"}"#);
+
+        let err = parse_response(&json!({ "error": { "code": 429, "message": "Quota", "status": "RESOURCE_EXHAUSTED" } })).unwrap_err();
+        assert!(err.contains("RESOURCE_EXHAUSTED"));
+    }
+
+    #[test]
+    fn test_stream_translation() {
+        let mut stream = GeminiStream::new();
+        let text = stream.handle_event(r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"Hel"}]}}]}"#).unwrap();
+        assert_eq!(text[0]["choices"][0]["delta"]["content"], "Hel");
+
+        let chunks = stream.handle_event(
+            r#"{"candidates":[{"content":{"role":"model","parts":[{"text":"lo"},{"functionCall":{"name":"bash","args":{"command":"ls"}}}]},"finishReason":"STOP"}]}"#,
+        ).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[1]["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"], r#"{"command":"ls"}"#);
+        assert_eq!(chunks[2]["choices"][0]["finish_reason"], "tool_calls");
+        assert_eq!(stream.tool_calls()[0].function.name, "bash");
+
+        let message = stream.into_message();
+        assert!(matches!(message.content, Content::Text(ref t) if t == "Hello"));
+        assert_eq!(message.tool_calls.unwrap().len(), 1);
+    }
+}
@@ -1,6 +1,7 @@
-use serde::Serialize;
-use git2::{Repository, StatusOptions, Status};
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use git2::{BranchType, DiffFormat, DiffOptions, IndexAddOption, Repository, Sort, StatusOptions, Status};
+use git2::build::CheckoutBuilder;
+use std::path::{Path, PathBuf};
 use tauri::command;
 use std::collections::HashMap;
 
@@ -147,3 +148,560 @@ pub async fn get_git_statuses_pattern(
 
     Ok(file_statuses)
 }
+
+// ============================================================================
+// v0.3.4: 暂存 / 提交 / 差异 / 日志
+// ============================================================================
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct GitCommitInfo {
+    pub id: String,
+    pub short_id: String,
+    pub summary: String,
+    pub author: String,
+    pub email: String,
+    /// Unix 时间戳（秒）
+    pub time: i64,
+}
+
+/// 绝对路径转换为相对仓库根目录的路径（相对路径原样返回）
+fn repo_relative(repo: &Repository, path: &str) -> Result<PathBuf, String> {
+    let workdir = repo.workdir().ok_or("Bare repositories are not supported")?;
+    let path = Path::new(path);
+    if path.is_absolute() {
+        path.strip_prefix(workdir)
+            .map(Path::to_path_buf)
+            .map_err(|_| format!("Path is outside the repository: {}", path.display()))
+    } else {
+        Ok(path.to_path_buf())
+    }
+}
+
+fn head_commit(repo: &Repository) -> Option<git2::Commit<'_>> {
+    repo.head().ok().and_then(|head| head.peel_to_commit().ok())
+}
+
+fn commit_info(commit: &git2::Commit) -> GitCommitInfo {
+    let id = commit.id().to_string();
+    GitCommitInfo {
+        short_id: id.chars().take(7).collect(),
+        id,
+        summary: commit.summary().unwrap_or("").to_string(),
+        author: commit.author().name().unwrap_or("").to_string(),
+        email: commit.author().email().unwrap_or("").to_string(),
+        time: commit.time().seconds(),
+    }
+}
+
+/// 暂存文件（已删除的文件从索引中移除）
+#[command]
+pub async fn git_stage_files(repo_path: String, paths: Vec<String>) -> Result<(), String> {
+    let repo = Repository::open(&repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+    let workdir = repo.workdir().ok_or("Bare repositories are not supported")?.to_path_buf();
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+
+    for path in &paths {
+        let rel_path = repo_relative(&repo, path)?;
+        let result = if workdir.join(&rel_path).exists() {
+            index.add_path(&rel_path)
+        } else {
+            index.remove_path(&rel_path)
+        };
+        result.map_err(|e| format!("Failed to stage {}: {}", rel_path.display(), e))?;
+    }
+
+    index.write().map_err(|e| format!("Failed to write index: {}", e))
+}
+
+/// 取消暂存（恢复为 HEAD 中的状态，尚无提交时从索引中移除）
+#[command]
+pub async fn git_unstage(repo_path: String, paths: Vec<String>) -> Result<(), String> {
+    let repo = Repository::open(&repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+    let rel_paths = paths.iter()
+        .map(|p| repo_relative(&repo, p))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let head = head_commit(&repo);
+    match head {
+        Some(head) => repo.reset_default(Some(head.as_object()), rel_paths.iter())
+            .map_err(|e| format!("Failed to unstage: {}", e)),
+        None => {
+            let mut index = repo.index().map_err(|e| e.to_string())?;
+            for rel_path in &rel_paths {
+                index.remove_path(rel_path).map_err(|e| format!("Failed to unstage {}: {}", rel_path.display(), e))?;
+            }
+            index.write().map_err(|e| format!("Failed to write index: {}", e))
+        }
+    }
+}
+
+/// 提交已暂存的改动（作者取自 git 配置的 user.name / user.email）
+#[command]
+pub async fn git_commit(repo_path: String, message: String) -> Result<GitCommitInfo, String> {
+    if message.trim().is_empty() {
+        return Err("Commit message cannot be empty".to_string());
+    }
+    let repo = Repository::open(&repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+    let signature = repo.signature()
+        .map_err(|e| format!("Git user.name / user.email not configured: {}", e))?;
+
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    let tree_id = index.write_tree().map_err(|e| format!("Failed to write tree: {}", e))?;
+    let tree = repo.find_tree(tree_id).map_err(|e| e.to_string())?;
+
+    let parent = head_commit(&repo);
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree_id) {
+        return Err("Nothing to commit: no staged changes".to_string());
+    }
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+
+    let id = repo.commit(Some("HEAD"), &signature, &signature, &message, &tree, &parents)
+        .map_err(|e| format!("Failed to commit: {}", e))?;
+    let commit = repo.find_commit(id).map_err(|e| e.to_string())?;
+    println!("[Git] Created commit {}", id);
+    Ok(commit_info(&commit))
+}
+
+/// 统一差异格式的改动（`staged`：HEAD 与索引的差异，否则为索引与工作区的差异）
+#[command]
+pub async fn git_diff(repo_path: String, path: Option<String>, staged: bool) -> Result<String, String> {
+    let repo = Repository::open(&repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+
+    let mut options = DiffOptions::new();
+    if let Some(path) = &path {
+        options.pathspec(repo_relative(&repo, path)?);
+    }
+    let diff = if staged {
+        let head_tree = head_commit(&repo).and_then(|c| c.tree().ok());
+        repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut options))
+    } else {
+        options.include_untracked(true).recurse_untracked_dirs(true).show_untracked_content(true);
+        repo.diff_index_to_workdir(None, Some(&mut options))
+    }
+    .map_err(|e| format!("Failed to diff: {}", e))?;
+
+    let mut patch = String::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        if matches!(line.origin(), '+' | '-' | ' ') {
+            patch.push(line.origin());
+        }
+        patch.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })
+    .map_err(|e| format!("Failed to format diff: {}", e))?;
+    Ok(patch)
+}
+
+/// 当前分支最近的提交（默认 20 条）
+#[command]
+pub async fn git_log(repo_path: String, limit: Option<usize>) -> Result<Vec<GitCommitInfo>, String> {
+    let repo = Repository::open(&repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+    if head_commit(&repo).is_none() {
+        return Ok(Vec::new());
+    }
+
+    let mut revwalk = repo.revwalk().map_err(|e| e.to_string())?;
+    revwalk.push_head().map_err(|e| e.to_string())?;
+    revwalk.set_sorting(Sort::TIME).map_err(|e| e.to_string())?;
+
+    let mut commits = Vec::new();
+    for oid in revwalk.take(limit.unwrap_or(20)) {
+        let oid = oid.map_err(|e| e.to_string())?;
+        let commit = repo.find_commit(oid).map_err(|e| e.to_string())?;
+        commits.push(commit_info(&commit));
+    }
+    Ok(commits)
+}
+
+// ============================================================================
+// v0.3.4: 分支与工作树（Agent 隔离）
+// ============================================================================
+
+/// Agent 分支前缀
+pub const AGENT_BRANCH_PREFIX: &str = "ifai/agent-";
+/// Agent 工作树目录（相对仓库根目录）
+const WORKTREE_DIR: &str = ".ifai/worktrees";
+/// IfAI 自身的数据目录（索引等），不属于 Agent 的改动
+const IFAI_DIR: &str = ".ifai/";
+
+/// Agent 改动的隔离方式
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentIsolation {
+    /// 在当前工作区切换到 Agent 分支（要求工作区干净）
+    Branch,
+    /// 在独立工作树中检出 Agent 分支，不影响当前工作区
+    Worktree,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AgentBranchAction {
+    Merge,
+    Discard,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct AgentBranchResult {
+    pub branch: String,
+    /// 合并产生的提交（快进时为 Agent 分支的提交；已是最新时为空）
+    pub merge_commit: Option<String>,
+}
+
+/// Agent 分支名：`ifai/agent-<id>`（id 中不能用于引用名的字符替换为 `-`）
+pub fn agent_branch_name(agent_id: &str) -> String {
+    format!("{}{}", AGENT_BRANCH_PREFIX, sanitize_name(agent_id))
+}
+
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect()
+}
+
+/// 分支对应的工作树名（`ifai/agent-1` -> `agent-1`）
+fn worktree_name(branch: &str) -> String {
+    sanitize_name(branch.trim_start_matches("ifai/"))
+}
+
+/// 记录 Agent 分支创建时所在的分支，结束时切回
+fn base_config_key(branch: &str) -> String {
+    format!("branch.{}.ifaibase", branch)
+}
+
+fn current_branch(repo: &Repository) -> Option<String> {
+    let head = repo.head().ok()?;
+    head.is_branch().then(|| head.shorthand().map(String::from)).flatten()
+}
+
+fn create_branch(repo: &Repository, name: &str, start_point: Option<&str>) -> Result<(), String> {
+    let commit = match start_point {
+        Some(spec) => repo.revparse_single(spec)
+            .and_then(|obj| obj.peel_to_commit())
+            .map_err(|e| format!("Unknown git ref '{}': {}", spec, e))?,
+        None => head_commit(repo).ok_or("Repository has no commits yet")?,
+    };
+    repo.branch(name, &commit, false)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to create branch {}: {}", name, e))
+}
+
+/// 在 `.ifai/worktrees/` 下检出分支（分支不存在时从 HEAD 创建），已存在时直接返回
+fn add_worktree(repo: &Repository, branch: &str) -> Result<PathBuf, String> {
+    let name = worktree_name(branch);
+    if let Ok(worktree) = repo.find_worktree(&name) {
+        return Ok(worktree.path().to_path_buf());
+    }
+    if repo.find_branch(branch, BranchType::Local).is_err() {
+        create_branch(repo, branch, None)?;
+    }
+    let worktrees_dir = repo.workdir().ok_or("Bare repositories are not supported")?.join(WORKTREE_DIR);
+    std::fs::create_dir_all(&worktrees_dir).map_err(|e| format!("Failed to create worktree dir: {}", e))?;
+    // 不让工作树出现在仓库的未跟踪文件中
+    let _ = std::fs::write(worktrees_dir.join(".gitignore"), "*\n");
+    let path = worktrees_dir.join(&name);
+    let reference = repo.find_branch(branch, BranchType::Local).map_err(|e| e.to_string())?.into_reference();
+    repo.worktree(&name, &path, Some(git2::WorktreeAddOptions::new().reference(Some(&reference))))
+        .map_err(|e| format!("Failed to create worktree: {}", e))?;
+    Ok(path)
+}
+
+/// 检出分支；本地改动会被覆盖时失败
+fn switch_branch(repo: &Repository, name: &str) -> Result<(), String> {
+    let branch = repo.find_branch(name, BranchType::Local)
+        .map_err(|e| format!("Branch not found: {}: {}", name, e))?;
+    let reference = branch.get().name().ok_or("Invalid branch name")?.to_string();
+    let target = branch.get().peel(git2::ObjectType::Commit).map_err(|e| e.to_string())?;
+    repo.checkout_tree(&target, Some(CheckoutBuilder::new().safe()))
+        .map_err(|e| format!("Cannot switch to {}: {}", name, e))?;
+    repo.set_head(&reference).map_err(|e| e.to_string())
+}
+
+fn has_changes(repo: &Repository) -> Result<bool, String> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).exclude_submodules(true);
+    let statuses = repo.statuses(Some(&mut options)).map_err(|e| e.to_string())?;
+    Ok(statuses.iter().any(|entry| {
+        !entry.status().is_ignored() && !entry.path().is_some_and(|p| p.starts_with(IFAI_DIR))
+    }))
+}
+
+/// 提交工作区中的所有改动，没有改动时返回 None
+fn commit_all(repo: &Repository, message: &str) -> Result<Option<git2::Oid>, String> {
+    let mut index = repo.index().map_err(|e| e.to_string())?;
+    let mut skip_ifai = |path: &Path, _: &[u8]| if path.starts_with(IFAI_DIR) { 1 } else { 0 };
+    index.add_all(["*"].iter(), IndexAddOption::DEFAULT, Some(&mut skip_ifai)).map_err(|e| e.to_string())?;
+    index.update_all(["*"].iter(), None).map_err(|e| e.to_string())?;
+    index.write().map_err(|e| e.to_string())?;
+    let tree_id = index.write_tree().map_err(|e| e.to_string())?;
+
+    let parent = head_commit(repo);
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree_id) {
+        return Ok(None);
+    }
+    let tree = repo.find_tree(tree_id).map_err(|e| e.to_string())?;
+    let signature = repo.signature()
+        .or_else(|_| git2::Signature::now("IfAI Agent", "agent@ifai.local"))
+        .map_err(|e| e.to_string())?;
+    let parents: Vec<&git2::Commit> = parent.iter().collect();
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parents)
+        .map(Some)
+        .map_err(|e| format!("Failed to commit: {}", e))
+}
+
+/// 将分支合并到当前 HEAD（可快进时快进，否则创建合并提交；有冲突时失败且不修改工作区）
+fn merge_branch(repo: &Repository, name: &str) -> Result<Option<git2::Oid>, String> {
+    let branch = repo.find_branch(name, BranchType::Local).map_err(|e| e.to_string())?;
+    let theirs = repo.reference_to_annotated_commit(branch.get()).map_err(|e| e.to_string())?;
+    let (analysis, _) = repo.merge_analysis(&[&theirs]).map_err(|e| e.to_string())?;
+    if analysis.is_up_to_date() {
+        return Ok(None);
+    }
+
+    let their_commit = repo.find_commit(theirs.id()).map_err(|e| e.to_string())?;
+    if analysis.is_fast_forward() {
+        repo.checkout_tree(their_commit.as_object(), Some(CheckoutBuilder::new().safe()))
+            .map_err(|e| format!("Cannot merge {}: {}", name, e))?;
+        let mut head = repo.head().map_err(|e| e.to_string())?;
+        head.set_target(theirs.id(), &format!("merge {}: fast-forward", name)).map_err(|e| e.to_string())?;
+        return Ok(Some(theirs.id()));
+    }
+
+    let our_commit = head_commit(repo).ok_or("Repository has no commits yet")?;
+    let mut index = repo.merge_commits(&our_commit, &their_commit, None).map_err(|e| e.to_string())?;
+    if index.has_conflicts() {
+        let conflicts: Vec<String> = index.conflicts().map_err(|e| e.to_string())?
+            .filter_map(|c| c.ok())
+            .filter_map(|c| c.our.or(c.their))
+            .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+            .collect();
+        return Err(format!("Merge conflicts in: {}", conflicts.join(", ")));
+    }
+    let tree_id = index.write_tree_to(repo).map_err(|e| e.to_string())?;
+    let tree = repo.find_tree(tree_id).map_err(|e| e.to_string())?;
+    repo.checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))
+        .map_err(|e| format!("Cannot merge {}: {}", name, e))?;
+    repo.index().and_then(|mut i| { i.read_tree(&tree)?; i.write() }).map_err(|e| e.to_string())?;
+
+    let signature = repo.signature()
+        .or_else(|_| git2::Signature::now("IfAI Agent", "agent@ifai.local"))
+        .map_err(|e| e.to_string())?;
+    repo.commit(Some("HEAD"), &signature, &signature, &format!("Merge branch '{}'", name), &tree, &[&our_commit, &their_commit])
+        .map(Some)
+        .map_err(|e| format!("Failed to commit merge: {}", e))
+}
+
+/// 为 Agent 准备隔离的工作区，返回 Agent 应使用的项目根目录
+pub fn prepare_agent_workspace(repo_path: &str, agent_id: &str, isolation: AgentIsolation) -> Result<String, String> {
+    let repo = Repository::open(repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+    let branch = agent_branch_name(agent_id);
+
+    match isolation {
+        AgentIsolation::Branch => {
+            if has_changes(&repo)? {
+                return Err("Working tree has uncommitted changes; use worktree isolation instead".to_string());
+            }
+            if repo.find_branch(&branch, BranchType::Local).is_err() {
+                create_branch(&repo, &branch, None)?;
+            }
+            if let Some(base) = current_branch(&repo).filter(|b| *b != branch) {
+                repo.config().and_then(|mut c| c.set_str(&base_config_key(&branch), &base)).map_err(|e| e.to_string())?;
+            }
+            switch_branch(&repo, &branch)?;
+            println!("[Git] Agent {} working on branch {}", agent_id, branch);
+            Ok(repo_path.to_string())
+        }
+        AgentIsolation::Worktree => {
+            let path = add_worktree(&repo, &branch)?;
+            println!("[Git] Agent {} working in worktree {}", agent_id, path.display());
+            Ok(path.to_string_lossy().to_string())
+        }
+    }
+}
+
+/// 结束 Agent 分支：提交 Agent 未提交的改动，移除工作树，合并或丢弃后删除分支
+pub fn finish_agent_workspace(repo_path: &str, agent_id: &str, action: AgentBranchAction) -> Result<AgentBranchResult, String> {
+    let repo = Repository::open(repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+    let branch = agent_branch_name(agent_id);
+    repo.find_branch(&branch, BranchType::Local).map_err(|_| format!("Agent branch not found: {}", branch))?;
+    let message = format!("Agent {} changes", agent_id);
+
+    if let Ok(worktree) = repo.find_worktree(&worktree_name(&branch)) {
+        if action == AgentBranchAction::Merge {
+            let agent_repo = Repository::open_from_worktree(&worktree).map_err(|e| e.to_string())?;
+            commit_all(&agent_repo, &message)?;
+        }
+        worktree.prune(Some(git2::WorktreePruneOptions::new().valid(true).working_tree(true)))
+            .map_err(|e| format!("Failed to remove worktree: {}", e))?;
+    }
+
+    if current_branch(&repo).as_deref() == Some(branch.as_str()) {
+        // 分支模式：改动就在当前工作区，先提交到 Agent 分支再切回原分支
+        commit_all(&repo, &message)?;
+        let base = repo.config()
+            .and_then(|c| c.get_string(&base_config_key(&branch)))
+            .map_err(|_| format!("Unknown base branch for {}", branch))?;
+        switch_branch(&repo, &base)?;
+    }
+
+    let merge_commit = match action {
+        AgentBranchAction::Merge => merge_branch(&repo, &branch)?,
+        AgentBranchAction::Discard => None,
+    };
+
+    repo.find_branch(&branch, BranchType::Local)
+        .and_then(|mut b| b.delete())
+        .map_err(|e| format!("Failed to delete branch {}: {}", branch, e))?;
+    let _ = repo.config().and_then(|mut c| c.remove(&base_config_key(&branch)));
+    println!("[Git] Agent branch {} {:?}", branch, action);
+
+    Ok(AgentBranchResult { branch, merge_commit: merge_commit.map(|id| id.to_string()) })
+}
+
+/// 从 `start_point`（默认 HEAD）创建分支，`checkout` 为 true 时同时切换
+#[command]
+pub async fn git_create_branch(repo_path: String, name: String, start_point: Option<String>, checkout: Option<bool>) -> Result<(), String> {
+    let repo = Repository::open(&repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+    create_branch(&repo, &name, start_point.as_deref())?;
+    if checkout.unwrap_or(false) {
+        switch_branch(&repo, &name)?;
+    }
+    Ok(())
+}
+
+#[command]
+pub async fn git_switch_branch(repo_path: String, name: String) -> Result<(), String> {
+    let repo = Repository::open(&repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+    switch_branch(&repo, &name)
+}
+
+/// 为分支创建工作树，返回工作树路径
+#[command]
+pub async fn git_create_worktree(repo_path: String, branch: String) -> Result<String, String> {
+    let repo = Repository::open(&repo_path).map_err(|e| format!("Failed to open repo: {}", e))?;
+    add_worktree(&repo, &branch).map(|path| path.to_string_lossy().to_string())
+}
+
+/// 合并或丢弃 Agent 分支 `ifai/agent-<id>`
+#[command]
+pub async fn git_finish_agent_branch(repo_path: String, agent_id: String, action: AgentBranchAction) -> Result<AgentBranchResult, String> {
+    tokio::task::spawn_blocking(move || finish_agent_workspace(&repo_path, &agent_id, action))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_repo() -> (PathBuf, String) {
+        let root = std::env::temp_dir().join(format!("ifai_git_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let repo = Repository::init(&root).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let repo_path = root.to_string_lossy().to_string();
+        (root, repo_path)
+    }
+
+    #[tokio::test]
+    async fn test_stage_commit_diff_log() {
+        let (root, repo_path) = init_repo();
+        std::fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+        std::fs::write(root.join("notes.txt"), "todo\n").unwrap();
+
+        assert!(git_log(repo_path.clone(), None).await.unwrap().is_empty());
+        git_stage_files(repo_path.clone(), vec![root.join("main.rs").to_string_lossy().to_string()]).await.unwrap();
+        let staged = git_diff(repo_path.clone(), None, true).await.unwrap();
+        assert!(staged.contains("+fn main() {}"));
+        assert!(!staged.contains("notes.txt"));
+
+        let first = git_commit(repo_path.clone(), "Initial commit".to_string()).await.unwrap();
+        assert_eq!(first.summary, "Initial commit");
+        assert!(git_commit(repo_path.clone(), "Empty".to_string()).await.is_err());
+
+        std::fs::write(root.join("main.rs"), "fn main() { run(); }\n").unwrap();
+        let unstaged = git_diff(repo_path.clone(), Some("main.rs".to_string()), false).await.unwrap();
+        assert!(unstaged.contains("-fn main() {}") && unstaged.contains("+fn main() { run(); }"));
+
+        git_stage_files(repo_path.clone(), vec!["main.rs".to_string(), "notes.txt".to_string()]).await.unwrap();
+        git_unstage(repo_path.clone(), vec!["notes.txt".to_string()]).await.unwrap();
+        let staged = git_diff(repo_path.clone(), None, true).await.unwrap();
+        assert!(staged.contains("main.rs") && !staged.contains("notes.txt"));
+
+        git_commit(repo_path.clone(), "Call run".to_string()).await.unwrap();
+        let log = git_log(repo_path.clone(), Some(10)).await.unwrap();
+        assert_eq!(log.iter().map(|c| c.summary.as_str()).collect::<Vec<_>>(), vec!["Call run", "Initial commit"]);
+        assert_eq!(log[1].id, first.id);
+        assert_eq!(git_log(repo_path.clone(), Some(1)).await.unwrap().len(), 1);
+
+        // 删除的文件通过暂存从索引移除
+        std::fs::remove_file(root.join("main.rs")).unwrap();
+        git_stage_files(repo_path.clone(), vec!["main.rs".to_string()]).await.unwrap();
+        assert!(git_diff(repo_path.clone(), None, true).await.unwrap().contains("deleted file"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    fn init_with_commit() -> (PathBuf, String, Repository) {
+        let (root, repo_path) = init_repo();
+        std::fs::write(root.join("lib.rs"), "pub fn a() {}\n").unwrap();
+        let repo = Repository::open(&root).unwrap();
+        commit_all(&repo, "Initial commit").unwrap();
+        (root, repo_path, repo)
+    }
+
+    #[test]
+    fn test_agent_worktree_merge() {
+        let (root, repo_path, repo) = init_with_commit();
+        let base = current_branch(&repo).unwrap();
+        // IfAI 的索引文件不影响隔离
+        std::fs::create_dir_all(root.join(".ifai")).unwrap();
+        std::fs::write(root.join(".ifai/index.hnsw"), "x").unwrap();
+
+        let workspace = PathBuf::from(prepare_agent_workspace(&repo_path, "run/1", AgentIsolation::Worktree).unwrap());
+        assert!(workspace.starts_with(root.join(WORKTREE_DIR)));
+        assert_eq!(current_branch(&repo).unwrap(), base);
+        std::fs::write(workspace.join("agent.rs"), "pub fn b() {}\n").unwrap();
+        assert!(!root.join("agent.rs").exists());
+        assert!(!has_changes(&repo).unwrap());
+
+        let result = finish_agent_workspace(&repo_path, "run/1", AgentBranchAction::Merge).unwrap();
+        assert_eq!(result.branch, "ifai/agent-run-1");
+        assert!(result.merge_commit.is_some());
+        assert_eq!(std::fs::read_to_string(root.join("agent.rs")).unwrap(), "pub fn b() {}\n");
+        assert!(!workspace.exists());
+        assert!(repo.find_branch("ifai/agent-run-1", BranchType::Local).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_agent_branch_discard_and_merge_commit() {
+        let (root, repo_path, repo) = init_with_commit();
+        let base = current_branch(&repo).unwrap();
+
+        assert_eq!(prepare_agent_workspace(&repo_path, "2", AgentIsolation::Branch).unwrap(), repo_path);
+        assert_eq!(current_branch(&repo).unwrap(), "ifai/agent-2");
+        std::fs::write(root.join("scratch.rs"), "fn tmp() {}\n").unwrap();
+        finish_agent_workspace(&repo_path, "2", AgentBranchAction::Discard).unwrap();
+        assert_eq!(current_branch(&repo).unwrap(), base);
+        assert!(!root.join("scratch.rs").exists());
+
+        // 用户在 Agent 运行期间也有提交时创建合并提交
+        prepare_agent_workspace(&repo_path, "3", AgentIsolation::Branch).unwrap();
+        std::fs::write(root.join("agent.rs"), "fn agent() {}\n").unwrap();
+        commit_all(&repo, "agent work").unwrap();
+        switch_branch(&repo, &base).unwrap();
+        std::fs::write(root.join("user.rs"), "fn user() {}\n").unwrap();
+        commit_all(&repo, "user work").unwrap();
+        let result = finish_agent_workspace(&repo_path, "3", AgentBranchAction::Merge).unwrap();
+        let merge = repo.find_commit(git2::Oid::from_str(&result.merge_commit.unwrap()).unwrap()).unwrap();
+        assert_eq!(merge.parent_count(), 2);
+        assert!(root.join("agent.rs").exists() && root.join("user.rs").exists());
+        assert!(!has_changes(&repo).unwrap());
+
+        std::fs::write(root.join("dirty.rs"), "").unwrap();
+        assert!(prepare_agent_workspace(&repo_path, "4", AgentIsolation::Branch).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,416 @@
+/*!
+Glossary - 项目术语表
+=====================
+
+从代码库中提取领域术语，使回复中的术语与项目保持一致：
+
+- 类型名（struct / enum / trait / class / interface / type）及其上方的文档注释
+- 常量（`UPPER_SNAKE_CASE`）
+- 文档标题（Markdown 的一到三级标题）及其后的第一段
+- 按引用该术语的文件数排序，保留前 `MAX_ENTRIES` 条
+- 可选由模型精炼释义（只改写释义，不增删术语）
+
+术语表保存在 `.ifai/glossary.json`；对话时用户问题中出现的术语会随系统提示词注入。
+*/
+
+use ignore::WalkBuilder;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::core_traits::ai::{AIProviderConfig, Content, Message};
+
+/// 术语表条目上限
+const MAX_ENTRIES: usize = 300;
+/// 扫描的文件数上限
+const MAX_FILES: usize = 5000;
+/// 超过该大小的文件跳过
+const MAX_FILE_BYTES: u64 = 512 * 1024;
+/// 单次注入的条目上限
+const MAX_INJECTED: usize = 12;
+/// 交给模型精炼的条目数
+const REFINE_BATCH: usize = 80;
+
+const CODE_EXTENSIONS: &[&str] = &["rs", "ts", "tsx", "js", "jsx", "py", "go", "java", "kt", "swift", "cs", "cpp", "hpp", "h", "c"];
+
+/// 过于通用、不构成领域术语的名称
+const GENERIC_TERMS: &[&str] = &[
+    "Error", "Result", "Config", "Options", "Props", "State", "Context", "Builder", "Handler", "Manager", "Service",
+    "Request", "Response", "Event", "Data", "Item", "Value", "Type", "Kind", "Info", "Params", "Args", "Default",
+    "Test", "Tests", "App", "Main", "Utils", "Helper", "Client", "Server", "Node", "Entry",
+];
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TermKind {
+    Type,
+    Constant,
+    Heading,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GlossaryEntry {
+    pub term: String,
+    pub kind: TermKind,
+    /// 文档注释或标题后的第一段；没有时为空
+    #[serde(default)]
+    pub definition: String,
+    /// 定义位置（`path:line`）
+    pub source: String,
+    /// 引用该术语的文件数
+    pub references: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Glossary {
+    pub generated_at: i64,
+    /// 释义是否经过模型精炼
+    pub refined: bool,
+    pub entries: Vec<GlossaryEntry>,
+}
+
+fn glossary_path(project_root: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("glossary.json")
+}
+
+pub fn load(project_root: &str) -> Option<Glossary> {
+    std::fs::read_to_string(glossary_path(project_root))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+}
+
+fn save(project_root: &str, glossary: &Glossary) -> Result<(), String> {
+    let path = glossary_path(project_root);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create .ifai dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(glossary).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write glossary: {}", e))
+}
+
+// ============================================================================
+// Extraction
+// ============================================================================
+
+fn type_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+|export\s+(?:default\s+)?|public\s+|abstract\s+)*(?:struct|enum|trait|class|interface|type)\s+([A-Z][A-Za-z0-9_]{2,})").unwrap()
+    })
+}
+
+fn const_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+|export\s+)*(?:(?:const|static|final)\s+)?([A-Z][A-Z0-9]*_[A-Z0-9_]+)\s*[:=]").unwrap()
+    })
+}
+
+fn heading_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^#{1,3}\s+(.+?)\s*#*\s*$").unwrap())
+}
+
+fn ident_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap())
+}
+
+/// 定义行上方紧邻的注释（`///`、`//`、`#`、`*`），按原顺序拼接
+fn doc_comment_above(lines: &[&str], index: usize) -> String {
+    let mut parts = Vec::new();
+    for line in lines[..index].iter().rev() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("#[") || trimmed.starts_with('@') {
+            continue;
+        }
+        let text = ["///", "//!", "//", "/**", "*/", "*", "#"]
+            .iter()
+            .find_map(|prefix| trimmed.strip_prefix(prefix));
+        match text {
+            Some(text) => parts.push(text.trim().trim_end_matches("*/").trim().to_string()),
+            None => break,
+        }
+    }
+    parts.reverse();
+    parts.retain(|p| !p.is_empty());
+    parts.join(" ")
+}
+
+/// 从单个文件中提取候选术语
+fn extract_from_file(rel_path: &str, content: &str) -> Vec<GlossaryEntry> {
+    let ext = rel_path.rsplit('.').next().unwrap_or("").to_lowercase();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut entries = Vec::new();
+    let entry = |term: &str, kind, definition: String, line: usize| GlossaryEntry {
+        term: term.to_string(),
+        kind,
+        definition,
+        source: format!("{}:{}", rel_path, line + 1),
+        references: 0,
+    };
+
+    if ext == "md" || ext == "mdx" {
+        let mut in_fence = false;
+        for (i, line) in lines.iter().enumerate() {
+            if line.trim_start().starts_with("```") {
+                in_fence = !in_fence;
+            }
+            if in_fence {
+                continue;
+            }
+            if let Some(caps) = heading_re().captures(line) {
+                let title = caps[1].trim_matches(|c: char| c == '`' || c == '*').trim();
+                if title.chars().count() < 3 || title.chars().count() > 60 {
+                    continue;
+                }
+                let definition = lines[i + 1..]
+                    .iter()
+                    .map(|l| l.trim())
+                    .skip_while(|l| l.is_empty())
+                    .take_while(|l| !l.is_empty() && !l.starts_with('#') && !l.starts_with("```"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                entries.push(entry(title, TermKind::Heading, definition, i));
+            }
+        }
+    } else if CODE_EXTENSIONS.contains(&ext.as_str()) {
+        for (i, line) in lines.iter().enumerate() {
+            if let Some(caps) = type_re().captures(line) {
+                entries.push(entry(&caps[1], TermKind::Type, doc_comment_above(&lines, i), i));
+            } else if let Some(caps) = const_re().captures(line) {
+                entries.push(entry(&caps[1], TermKind::Constant, doc_comment_above(&lines, i), i));
+            }
+        }
+    }
+    entries
+}
+
+/// 扫描代码库生成术语表（不含模型精炼）
+pub fn mine(project_root: &str) -> Glossary {
+    let root = Path::new(project_root);
+    let mut candidates: Vec<GlossaryEntry> = Vec::new();
+    // 每个标识符出现在多少个文件中
+    let mut document_frequency: HashMap<String, usize> = HashMap::new();
+
+    let files = WalkBuilder::new(root)
+        .standard_filters(true)
+        .hidden(true)
+        .build()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().map(|ft| ft.is_file()).unwrap_or(false))
+        .filter(|e| e.metadata().map(|m| m.len() <= MAX_FILE_BYTES).unwrap_or(false))
+        .take(MAX_FILES);
+
+    for file in files {
+        let Ok(content) = std::fs::read_to_string(file.path()) else { continue };
+        let rel = file.path().strip_prefix(root).unwrap_or(file.path()).to_string_lossy().replace('\\', "/");
+        candidates.extend(extract_from_file(&rel, &content));
+
+        let idents: HashSet<&str> = ident_re().find_iter(&content).map(|m| m.as_str()).collect();
+        for ident in idents {
+            *document_frequency.entry(ident.to_string()).or_default() += 1;
+        }
+    }
+
+    // 同名术语保留有释义的第一条
+    let mut by_term: HashMap<String, GlossaryEntry> = HashMap::new();
+    for mut candidate in candidates {
+        if GENERIC_TERMS.contains(&candidate.term.as_str()) {
+            continue;
+        }
+        candidate.references = document_frequency.get(&candidate.term).copied().unwrap_or(0);
+        match by_term.get_mut(&candidate.term.to_lowercase()) {
+            Some(existing) if existing.definition.is_empty() && !candidate.definition.is_empty() => *existing = candidate,
+            Some(_) => {}
+            None => {
+                by_term.insert(candidate.term.to_lowercase(), candidate);
+            }
+        }
+    }
+
+    let mut entries: Vec<GlossaryEntry> = by_term.into_values().collect();
+    entries.sort_by(|a, b| {
+        b.references
+            .cmp(&a.references)
+            .then_with(|| a.definition.is_empty().cmp(&b.definition.is_empty()))
+            .then_with(|| a.term.cmp(&b.term))
+    });
+    entries.truncate(MAX_ENTRIES);
+
+    Glossary { generated_at: chrono::Utc::now().timestamp(), refined: false, entries }
+}
+
+// ============================================================================
+// Refinement
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+struct RefinedDefinition {
+    term: String,
+    definition: String,
+}
+
+/// 解析模型返回的 JSON 数组（允许包裹在代码块或说明文字中）
+fn parse_refined(text: &str) -> Vec<RefinedDefinition> {
+    let (Some(start), Some(end)) = (text.find('['), text.rfind(']')) else { return Vec::new() };
+    if end < start {
+        return Vec::new();
+    }
+    serde_json::from_str(&text[start..=end]).unwrap_or_default()
+}
+
+/// 由模型改写释义；只更新已有术语
+async fn refine(glossary: &mut Glossary, provider_config: &AIProviderConfig) -> Result<(), String> {
+    let listing = glossary
+        .entries
+        .iter()
+        .take(REFINE_BATCH)
+        .map(|e| format!("- {} ({:?}, {}): {}", e.term, e.kind, e.source, if e.definition.is_empty() { "(no docs)" } else { &e.definition }))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = format!(
+        "Below are domain terms extracted from a codebase with their doc comments.\n\
+         Write a one-sentence definition for each term as it is used in this project.\n\
+         Respond with a JSON array only: [{{\"term\": \"...\", \"definition\": \"...\"}}]. \
+         Do not add or rename terms; skip terms you cannot define.\n\n{}",
+        listing
+    );
+    let messages = vec![Message { role: "user".to_string(), content: Content::Text(prompt), tool_calls: None, tool_call_id: None }];
+    let reply = crate::ai_utils::fetch_ai_completion(provider_config, messages, None).await?;
+    let text = match reply.content {
+        Content::Text(text) => text,
+        other => crate::intelligence_router::extract_text_content(&other),
+    };
+
+    let refined: HashMap<String, String> = parse_refined(&text)
+        .into_iter()
+        .filter(|r| !r.definition.trim().is_empty())
+        .map(|r| (r.term, r.definition.trim().to_string()))
+        .collect();
+    let mut updated = 0;
+    for entry in glossary.entries.iter_mut() {
+        if let Some(definition) = refined.get(&entry.term) {
+            entry.definition = definition.clone();
+            updated += 1;
+        }
+    }
+    println!("[Glossary] Model refined {} definitions", updated);
+    glossary.refined = updated > 0;
+    Ok(())
+}
+
+// ============================================================================
+// Prompt Injection
+// ============================================================================
+
+/// 问题中出现的术语（整词、不区分大小写），按引用数排序
+pub fn relevant_entries<'a>(glossary: &'a Glossary, text: &str) -> Vec<&'a GlossaryEntry> {
+    let words: HashSet<String> = ident_re().find_iter(text).map(|m| m.as_str().to_lowercase()).collect();
+    let lower = text.to_lowercase();
+    glossary
+        .entries
+        .iter()
+        .filter(|e| !e.definition.is_empty())
+        .filter(|e| match e.kind {
+            // 标题可能包含空格，按子串匹配
+            TermKind::Heading => e.term.chars().count() >= 4 && lower.contains(&e.term.to_lowercase()),
+            _ => words.contains(&e.term.to_lowercase()),
+        })
+        .take(MAX_INJECTED)
+        .collect()
+}
+
+/// 系统提示词中的术语段落；没有相关术语时为空
+pub fn prompt_section(project_root: &str, text: &str) -> Option<String> {
+    let glossary = load(project_root)?;
+    let entries = relevant_entries(&glossary, text);
+    if entries.is_empty() {
+        return None;
+    }
+    let mut section = String::from("\n\n# PROJECT GLOSSARY\nUse these project terms consistently and with the meanings below:\n");
+    for entry in entries {
+        section.push_str(&format!("- **{}** ({}): {}\n", entry.term, entry.source, entry.definition));
+    }
+    Some(section)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 扫描代码库生成术语表；`refine_with_model` 为 true 且提供了 `provider_config` 时由模型精炼释义
+#[tauri::command]
+pub async fn build_glossary(
+    root: String,
+    refine_with_model: Option<bool>,
+    provider_config: Option<AIProviderConfig>,
+) -> Result<Glossary, String> {
+    if !Path::new(&root).is_dir() {
+        return Err(format!("Directory does not exist: {}", root));
+    }
+    let root_for_mine = root.clone();
+    let mut glossary = tokio::task::spawn_blocking(move || mine(&root_for_mine))
+        .await
+        .map_err(|e| format!("Glossary task failed: {}", e))?;
+    println!("[Glossary] Extracted {} terms from {}", glossary.entries.len(), root);
+
+    if let (Some(true), Some(config)) = (refine_with_model, provider_config.as_ref()) {
+        if let Err(e) = refine(&mut glossary, config).await {
+            eprintln!("[Glossary] Model refinement failed, keeping extracted definitions: {}", e);
+        }
+    }
+
+    save(&root, &glossary)?;
+    Ok(glossary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_and_inject() {
+        let dir = std::env::temp_dir().join(format!("ifai_glossary_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(
+            dir.join("src/ledger.rs"),
+            "/// A double-entry record of money movements.\n#[derive(Debug)]\npub struct LedgerEntry {}\n\n\
+             /// Days before an invoice is overdue\npub const GRACE_PERIOD_DAYS: u32 = 14;\n\npub struct Config {}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("src/api.ts"), "export interface LedgerEntry { amount: number }\n").unwrap();
+        std::fs::write(dir.join("README.md"), "# Billing\n\n## Settlement Window\n\nThe nightly batch that closes open entries.\n").unwrap();
+        let root = dir.to_string_lossy().to_string();
+
+        let glossary = mine(&root);
+        let ledger = glossary.entries.iter().find(|e| e.term == "LedgerEntry").unwrap();
+        assert_eq!(ledger.definition, "A double-entry record of money movements.");
+        assert_eq!(ledger.source, "src/ledger.rs:3");
+        assert_eq!(ledger.references, 2);
+        let grace = glossary.entries.iter().find(|e| e.term == "GRACE_PERIOD_DAYS").unwrap();
+        assert_eq!((grace.kind, grace.definition.as_str()), (TermKind::Constant, "Days before an invoice is overdue"));
+        let heading = glossary.entries.iter().find(|e| e.term == "Settlement Window").unwrap();
+        assert_eq!(heading.definition, "The nightly batch that closes open entries.");
+        assert!(glossary.entries.iter().all(|e| e.term != "Config"));
+
+        save(&root, &glossary).unwrap();
+        let section = prompt_section(&root, "why is the ledgerentry total wrong during the settlement window?").unwrap();
+        assert!(section.contains("**LedgerEntry** (src/ledger.rs:3)"));
+        assert!(section.contains("**Settlement Window**"));
+        assert!(!section.contains("GRACE_PERIOD_DAYS"));
+        assert!(prompt_section(&root, "hello").is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_parse_refined() {
+        let reply = "Here you go:\n```json\n[{\"term\": \"LedgerEntry\", \"definition\": \"One posting.\"}]\n```";
+        let parsed = parse_refined(reply);
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].term, "LedgerEntry");
+        assert!(parse_refined("no json").is_empty());
+    }
+}
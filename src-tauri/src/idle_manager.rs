@@ -0,0 +1,278 @@
+/*!
+Idle Manager - 空闲资源回收
+============================
+
+长时间无操作后释放占用内存的资源，下次使用时再懒加载：
+- 本地 GGUF 模型（`llm-inference`，下次推理时由 `ensure_model_loaded` 重新加载）
+- 文件内容缓存
+- 通过 `register_reclaimer` 注册的资源：fastembed 向量模型与交叉编码器、HNSW 索引（启动时注册）
+
+活动由 `touch()` 记录（AI 请求、Agent 启动、本地模型推理等入口处调用）。
+后台循环每 30 秒检查一次，空闲超过配置时长即回收，每段空闲期只回收一次。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// 检查空闲状态的间隔
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 空闲回收配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdleConfig {
+    pub enabled: bool,
+    /// 空闲多少分钟后回收
+    pub idle_minutes: u64,
+}
+
+impl Default for IdleConfig {
+    fn default() -> Self {
+        Self { enabled: true, idle_minutes: 10 }
+    }
+}
+
+/// 资源与内存使用指标
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdleMetrics {
+    /// 进程常驻内存（字节），无法获取时为 None
+    pub rss_bytes: Option<u64>,
+    pub idle_secs: u64,
+    pub llm_loaded: bool,
+    pub file_cache_entries: usize,
+    pub reclaim_count: u64,
+    /// 上次回收时间（Unix 毫秒）
+    pub last_reclaim_at: Option<i64>,
+    /// 上次回收前后的内存变化（字节，正数表示释放）
+    pub last_reclaimed_bytes: Option<i64>,
+    /// 上次回收释放的组件
+    pub last_reclaimed: Vec<String>,
+}
+
+/// 资源回收函数，返回是否实际释放了资源
+pub type Reclaimer = Box<dyn Fn() -> bool + Send + Sync>;
+
+// ============================================================================
+// State
+// ============================================================================
+
+static LAST_ACTIVITY: AtomicI64 = AtomicI64::new(0);
+/// 最近一次回收对应的活动时间戳，用于保证每段空闲期只回收一次
+static RECLAIMED_FOR: AtomicI64 = AtomicI64::new(-1);
+static CONFIG: OnceLock<Mutex<IdleConfig>> = OnceLock::new();
+static METRICS: OnceLock<Mutex<IdleMetrics>> = OnceLock::new();
+static RECLAIMERS: OnceLock<Mutex<Vec<(String, Reclaimer)>>> = OnceLock::new();
+
+fn config() -> &'static Mutex<IdleConfig> {
+    CONFIG.get_or_init(|| Mutex::new(IdleConfig::default()))
+}
+
+fn metrics() -> &'static Mutex<IdleMetrics> {
+    METRICS.get_or_init(|| Mutex::new(IdleMetrics::default()))
+}
+
+fn reclaimers() -> &'static Mutex<Vec<(String, Reclaimer)>> {
+    RECLAIMERS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// 记录一次活动
+pub fn touch() {
+    LAST_ACTIVITY.store(now_millis(), Ordering::Relaxed);
+}
+
+/// 注册额外的资源回收函数
+pub fn register_reclaimer(name: &str, reclaimer: Reclaimer) {
+    if let Ok(mut list) = reclaimers().lock() {
+        list.push((name.to_string(), reclaimer));
+    }
+}
+
+fn idle_secs() -> u64 {
+    let last = LAST_ACTIVITY.load(Ordering::Relaxed);
+    ((now_millis() - last).max(0) / 1000) as u64
+}
+
+/// 是否应当回收：已启用、空闲超时，且本段空闲期尚未回收
+fn should_reclaim(config: &IdleConfig, idle_secs: u64, last_activity: i64, reclaimed_for: i64) -> bool {
+    config.enabled && idle_secs >= config.idle_minutes * 60 && reclaimed_for != last_activity
+}
+
+// ============================================================================
+// Reclamation
+// ============================================================================
+
+/// 读取进程常驻内存
+pub fn current_rss_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let kb = status.lines()
+            .find(|l| l.starts_with("VmRSS:"))?
+            .split_whitespace()
+            .nth(1)?
+            .parse::<u64>()
+            .ok()?;
+        Some(kb * 1024)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("ps")
+            .args(["-o", "rss=", "-p", &std::process::id().to_string()])
+            .output()
+            .ok()?;
+        let kb = String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok()?;
+        Some(kb * 1024)
+    }
+    #[cfg(target_os = "windows")]
+    {
+        let script = format!("(Get-Process -Id {}).WorkingSet64", std::process::id());
+        let output = std::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", &script])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok()
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    {
+        None
+    }
+}
+
+fn llm_loaded() -> bool {
+    #[cfg(feature = "llm-inference")]
+    {
+        crate::llm_inference::is_model_loaded()
+    }
+    #[cfg(not(feature = "llm-inference"))]
+    {
+        false
+    }
+}
+
+/// 释放所有可回收资源，返回被释放的组件名
+pub fn reclaim_now() -> Vec<String> {
+    let before = current_rss_bytes();
+    let mut reclaimed = Vec::new();
+
+    #[cfg(feature = "llm-inference")]
+    if crate::llm_inference::is_model_loaded() && crate::llm_inference::unload_model().is_ok() {
+        reclaimed.push("local_llm".to_string());
+    }
+
+    if crate::file_cache::get_cache_stats().entries > 0 {
+        crate::file_cache::clear_global_cache();
+        reclaimed.push("file_cache".to_string());
+    }
+
+    if let Ok(list) = reclaimers().lock() {
+        for (name, reclaimer) in list.iter() {
+            if reclaimer() {
+                reclaimed.push(name.clone());
+            }
+        }
+    }
+
+    let after = current_rss_bytes();
+    println!("[IdleManager] Reclaimed: {:?}, rss {:?} -> {:?}", reclaimed, before, after);
+
+    if let Ok(mut m) = metrics().lock() {
+        m.reclaim_count += 1;
+        m.last_reclaim_at = Some(now_millis());
+        m.last_reclaimed_bytes = before.zip(after).map(|(b, a)| b as i64 - a as i64);
+        m.last_reclaimed = reclaimed.clone();
+    }
+    reclaimed
+}
+
+/// 后台空闲检查循环（在应用启动时调用一次）
+pub fn start_idle_monitor() {
+    touch();
+    tauri::async_runtime::spawn(async {
+        loop {
+            tokio::time::sleep(Duration::from_secs(CHECK_INTERVAL_SECS)).await;
+            let Ok(config) = config().lock().map(|c| c.clone()) else { continue };
+            let last_activity = LAST_ACTIVITY.load(Ordering::Relaxed);
+            if should_reclaim(&config, idle_secs(), last_activity, RECLAIMED_FOR.load(Ordering::Relaxed)) {
+                RECLAIMED_FOR.store(last_activity, Ordering::Relaxed);
+                let _ = tokio::task::spawn_blocking(reclaim_now).await;
+            }
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 获取资源与内存使用指标
+#[tauri::command]
+pub fn get_idle_metrics() -> IdleMetrics {
+    let mut m = metrics().lock().map(|m| m.clone()).unwrap_or_default();
+    m.rss_bytes = current_rss_bytes();
+    m.idle_secs = idle_secs();
+    m.llm_loaded = llm_loaded();
+    m.file_cache_entries = crate::file_cache::get_cache_stats().entries;
+    m
+}
+
+#[tauri::command]
+pub fn get_idle_config() -> IdleConfig {
+    config().lock().map(|c| c.clone()).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn set_idle_config(config_value: IdleConfig) -> Result<(), String> {
+    if config_value.idle_minutes == 0 {
+        return Err("idle_minutes must be greater than 0".to_string());
+    }
+    *config().lock().map_err(|e| e.to_string())? = config_value;
+    Ok(())
+}
+
+/// 立即回收空闲资源
+#[tauri::command]
+pub async fn reclaim_idle_resources() -> Result<Vec<String>, String> {
+    tokio::task::spawn_blocking(reclaim_now)
+        .await
+        .map_err(|e| format!("Task join error: {}", e))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_reclaim_once_per_idle_period() {
+        let config = IdleConfig { enabled: true, idle_minutes: 10 };
+        assert!(!should_reclaim(&config, 599, 1000, -1));
+        assert!(should_reclaim(&config, 600, 1000, -1));
+        // 同一段空闲期已回收过
+        assert!(!should_reclaim(&config, 1200, 1000, 1000));
+        // 有新活动后再次空闲
+        assert!(should_reclaim(&config, 600, 2000, 1000));
+
+        let disabled = IdleConfig { enabled: false, idle_minutes: 10 };
+        assert!(!should_reclaim(&disabled, 6000, 1000, -1));
+    }
+
+    #[test]
+    fn test_registered_reclaimer_runs() {
+        register_reclaimer("test_index", Box::new(|| true));
+        let reclaimed = reclaim_now();
+        assert!(reclaimed.contains(&"test_index".to_string()));
+        assert!(get_idle_metrics().reclaim_count >= 1);
+    }
+}
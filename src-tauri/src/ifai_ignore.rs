@@ -0,0 +1,253 @@
+/*!
+IfaiIgnore - 索引排除规则
+=========================
+
+`.ifaiignore` 使用 gitignore 语法，在 `.gitignore` 之外额外排除不希望被索引的路径
+（生成代码、数据集、第三方快照等），作用于：
+
+- 符号索引、语义索引（`semantic_index_file`）
+- `agent_scan_directory`（含带进度的扫描）
+
+和 `.gitignore` 一样可以放在子目录中，离文件最近的规则优先，`!pattern` 可重新包含。
+IFAI.md 的 `ignore` 列表视为项目根目录下的规则，优先级低于根目录的 `.ifaiignore`。
+`check_ifaiignore` 报告每个路径命中的规则，便于排查。
+*/
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Match, WalkBuilder};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const IGNORE_FILE: &str = ".ifaiignore";
+
+/// 让 `ignore` 遍历同时遵循 `.ifaiignore`
+pub fn apply(builder: &mut WalkBuilder) -> &mut WalkBuilder {
+    builder.add_custom_ignore_filename(IGNORE_FILE)
+}
+
+/// 在 `apply` 的基础上再排除 IFAI.md 的 `ignore` 规则
+pub fn apply_project<'a>(builder: &'a mut WalkBuilder, project_root: &str) -> &'a mut WalkBuilder {
+    if let Some(matcher) = config_matcher(project_root) {
+        let root = PathBuf::from(project_root);
+        builder.filter_entry(move |entry| {
+            let rel = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            rel.as_os_str().is_empty()
+                || !matcher.matched_path_or_any_parents(rel, entry.file_type().is_some_and(|t| t.is_dir())).is_ignore()
+        });
+    }
+    apply(builder)
+}
+
+/// IFAI.md 中 `ignore` 列表对应的规则
+fn config_matcher(project_root: &str) -> Option<Gitignore> {
+    let patterns = crate::project_config::load_project_config_sync(project_root)?.ignore?;
+    let mut builder = GitignoreBuilder::new(project_root);
+    for pattern in &patterns {
+        if let Err(e) = builder.add_line(None, pattern) {
+            eprintln!("[IfaiIgnore] Invalid IFAI.md ignore rule {}: {}", pattern, e);
+        }
+    }
+    builder.build().ok().filter(|m| !m.is_empty())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IgnoreMatch {
+    pub path: String,
+    pub ignored: bool,
+    /// 命中的规则（原文）；`!` 开头的规则表示被重新包含
+    pub pattern: Option<String>,
+    /// 规则所在的 `.ifaiignore`（相对项目根目录）
+    pub source: Option<String>,
+    /// 规则在文件中的行号（从 1 开始）
+    pub line: Option<usize>,
+}
+
+/// 项目的 `.ifaiignore` 规则（按目录加载，越深的目录优先）
+pub struct IfaiIgnore {
+    root: PathBuf,
+    /// `(目录相对路径, 规则)`，按目录深度降序
+    matchers: Vec<(PathBuf, Gitignore)>,
+}
+
+fn build_matcher(dir: &Path) -> Option<Gitignore> {
+    let file = dir.join(IGNORE_FILE);
+    if !file.is_file() {
+        return None;
+    }
+    let mut builder = GitignoreBuilder::new(dir);
+    if let Some(e) = builder.add(&file) {
+        eprintln!("[IfaiIgnore] Invalid rule in {}: {}", file.display(), e);
+    }
+    builder.build().ok().filter(|m| !m.is_empty())
+}
+
+impl IfaiIgnore {
+    /// 加载项目中所有 `.ifaiignore`（遍历时跳过 `.gitignore` 排除的目录）
+    pub fn load(project_root: &str) -> Self {
+        let root = PathBuf::from(project_root);
+        let mut walker = WalkBuilder::new(&root);
+        walker.hidden(false).filter_entry(|e| e.file_name() != std::ffi::OsStr::new(".git"));
+        let mut matchers: Vec<(PathBuf, Gitignore)> = apply(&mut walker)
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_some_and(|t| t.is_dir()))
+            .filter_map(|e| {
+                let matcher = build_matcher(e.path())?;
+                Some((e.path().strip_prefix(&root).unwrap_or(e.path()).to_path_buf(), matcher))
+            })
+            .collect();
+        matchers.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+        // IFAI.md 规则追加在最后，优先级最低
+        if let Some(matcher) = config_matcher(project_root) {
+            matchers.push((PathBuf::new(), matcher));
+        }
+        Self { root, matchers }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matchers.is_empty()
+    }
+
+    /// `rel_path` 相对项目根目录
+    pub fn is_ignored(&self, rel_path: &str, is_dir: bool) -> bool {
+        self.check(rel_path, is_dir).ignored
+    }
+
+    pub fn check(&self, rel_path: &str, is_dir: bool) -> IgnoreMatch {
+        let normalized = rel_path.replace('\\', "/");
+        let rel = Path::new(normalized.trim_start_matches("./"));
+        let mut result = IgnoreMatch { path: rel_path.to_string(), ignored: false, pattern: None, source: None, line: None };
+        for (dir, matcher) in &self.matchers {
+            let Ok(inner) = rel.strip_prefix(dir) else { continue };
+            if inner.as_os_str().is_empty() {
+                continue;
+            }
+            let glob = match matcher.matched_path_or_any_parents(inner, is_dir) {
+                Match::None => continue,
+                Match::Ignore(glob) => {
+                    result.ignored = true;
+                    glob
+                }
+                Match::Whitelist(glob) => glob,
+            };
+            result.pattern = Some(glob.original().to_string());
+            result.source = Some(match glob.from() {
+                Some(_) => dir.join(IGNORE_FILE).to_string_lossy().replace('\\', "/"),
+                None => ".ifai/IFAI.md".to_string(),
+            });
+            result.line = glob.from().and_then(|file| std::fs::read_to_string(file).ok()).and_then(|content| {
+                content.lines().position(|l| l.trim() == glob.original()).map(|i| i + 1)
+            });
+            break;
+        }
+        result
+    }
+
+    /// 绝对路径形式的检查（不在项目内的路径视为不排除）
+    pub fn is_ignored_path(&self, path: &Path, is_dir: bool) -> bool {
+        path.strip_prefix(&self.root)
+            .map(|rel| self.is_ignored(&rel.to_string_lossy(), is_dir))
+            .unwrap_or(false)
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 报告每个路径是否被 `.ifaiignore` 排除及命中的规则（`paths` 相对项目根目录）
+#[tauri::command]
+pub async fn check_ifaiignore(project_root: String, paths: Vec<String>) -> Result<Vec<IgnoreMatch>, String> {
+    tokio::task::spawn_blocking(move || {
+        let ignore = IfaiIgnore::load(&project_root);
+        paths.iter()
+            .map(|p| ignore.check(p, Path::new(&project_root).join(p).is_dir()))
+            .collect()
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_project(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ifai_ignore_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("data/keep")).unwrap();
+        std::fs::create_dir_all(dir.join("src/generated")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_rules_and_nested_files() {
+        let dir = temp_project("rules");
+        std::fs::write(dir.join(IGNORE_FILE), "# 数据集\ndata/\n*.snap\n").unwrap();
+        std::fs::write(dir.join("src").join(IGNORE_FILE), "generated/\n!keep.snap\n").unwrap();
+        let ignore = IfaiIgnore::load(&dir.to_string_lossy());
+
+        let data = ignore.check("data/keep/train.csv", false);
+        assert!(data.ignored);
+        assert_eq!((data.pattern.as_deref(), data.source.as_deref(), data.line), (Some("data/"), Some(".ifaiignore"), Some(2)));
+
+        let generated = ignore.check("src/generated/api.rs", false);
+        assert!(generated.ignored);
+        assert_eq!(generated.source.as_deref(), Some("src/.ifaiignore"));
+
+        assert!(ignore.is_ignored("tests/ui.snap", false));
+        // 子目录的 `!` 规则优先于上级规则
+        let kept = ignore.check("src/keep.snap", false);
+        assert!(!kept.ignored);
+        assert_eq!(kept.pattern.as_deref(), Some("!keep.snap"));
+
+        assert!(!ignore.is_ignored("src/main.rs", false));
+        assert!(ignore.is_ignored_path(&dir.join("data"), true));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_ifai_md_rules() {
+        let dir = temp_project("config");
+        std::fs::create_dir_all(dir.join(".ifai")).unwrap();
+        std::fs::write(dir.join(".ifai/IFAI.md"), "---\nignore:\n  - data/\n---\n").unwrap();
+        std::fs::write(dir.join("data/keep/rows.csv"), "a,b").unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        let root = dir.to_string_lossy().to_string();
+
+        let data = IfaiIgnore::load(&root).check("data/keep/rows.csv", false);
+        assert!(data.ignored);
+        assert_eq!((data.source.as_deref(), data.line), (Some(".ifai/IFAI.md"), None));
+
+        let mut builder = WalkBuilder::new(&dir);
+        let files: Vec<_> = apply_project(&mut builder, &root)
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+            .map(|e| e.path().strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        assert!(files.contains(&"src/main.rs".to_string()));
+        assert!(!files.iter().any(|f| f.starts_with("data/")));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_walk_respects_ifaiignore() {
+        let dir = temp_project("walk");
+        std::fs::write(dir.join(IGNORE_FILE), "data/\n").unwrap();
+        std::fs::write(dir.join("data/keep/rows.csv"), "a,b").unwrap();
+        std::fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+
+        let mut builder = WalkBuilder::new(&dir);
+        let files: Vec<String> = apply(&mut builder)
+            .build()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_some_and(|t| t.is_file()))
+            .map(|e| e.path().strip_prefix(&dir).unwrap().to_string_lossy().replace('\\', "/"))
+            .collect();
+        assert!(files.contains(&"src/main.rs".to_string()));
+        assert!(!files.iter().any(|f| f.starts_with("data/")));
+        assert!(IfaiIgnore::load("/nonexistent/ifai").is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
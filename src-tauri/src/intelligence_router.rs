@@ -9,10 +9,16 @@ Intelligence Router - Smart Local/Cloud Routing
 - 本地模型工具调用解析
 - 本地/云端路由决策
 - 自动降级处理
+- 从用户纠正中学习（v0.3.4）：记录每次决策的特征，用户改用云端 / 本地时
+  在线更新逻辑回归权重，样本足够后据此调整复杂度阈值；`get_router_stats` 查看准确率变化
 */
 
 use crate::core_traits::ai::Message;
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
 // ============================================================================
@@ -78,18 +84,14 @@ impl IntelligenceRouter {
         *state = available;
     }
 
-    /// 判断任务复杂度
-    pub fn assess_complexity(&self, messages: &[Message]) -> TaskComplexity {
+    /// 提取路由决策特征
+    pub fn route_features(&self, messages: &[Message]) -> Option<RouteFeatures> {
         // 获取最后一条用户消息
         let user_message = messages
             .iter()
             .filter(|m| m.role == "user")
-            .last();
-
-        let text = match user_message {
-            Some(msg) => extract_text_content(&msg.content),
-            None => return TaskComplexity::Complex,
-        };
+            .last()?;
+        let text = extract_text_content(&user_message.content);
 
         // 计算消息数量（简单的会话长度指标）
         let conversation_length = messages.len();
@@ -101,41 +103,27 @@ impl IntelligenceRouter {
             .sum();
         let estimated_tokens = total_chars / 3; // 粗略估算
 
-        // 判断复杂度
-        let is_simple_query = self.is_simple_query(&text);
-        let is_tool_request = self.is_tool_request(&text);
-        let is_long_context = estimated_tokens > 4000 || conversation_length > 20;
+        let features = RouteFeatures {
+            estimated_tokens,
+            message_count: conversation_length,
+            is_tool_request: self.is_tool_request(&text),
+            is_simple_query: self.is_simple_query(&text),
+            is_long_context: estimated_tokens > 4000 || conversation_length > 20,
+            has_code: text.contains("```"),
+        };
 
         // 打印调试信息
         println!("[Router] text='{}', is_tool_request={}, is_long_context={}, tokens={}, msg_len={}",
-                 text.chars().take(50).collect::<String>(), is_tool_request, is_long_context, estimated_tokens, conversation_length);
-
-        match (is_tool_request, is_simple_query, is_long_context) {
-            // 工具调用优先 - 即使上下文较长也优先使用本地
-            (true, false, _) | (true, true, false) => {
-                // 工具调用请求
-                if estimated_tokens < 2000 {
-                    TaskComplexity::Simple
-                } else {
-                    TaskComplexity::Medium
-                }
-            }
-            (_, _, true) => {
-                // 长上下文但没有工具请求
-                TaskComplexity::Complex
-            }
-            (false, true, false) => {
-                // 简单问答且上下文不长
-                TaskComplexity::Simple
-            }
-            (false, false, false) => {
-                // 其他情况
-                if estimated_tokens < 1000 {
-                    TaskComplexity::Simple
-                } else {
-                    TaskComplexity::Medium
-                }
-            }
+                 text.chars().take(50).collect::<String>(), features.is_tool_request, features.is_long_context, estimated_tokens, conversation_length);
+
+        Some(features)
+    }
+
+    /// 判断任务复杂度
+    pub fn assess_complexity(&self, messages: &[Message]) -> TaskComplexity {
+        match self.route_features(messages) {
+            Some(features) => complexity_for(&features),
+            None => TaskComplexity::Complex,
         }
     }
 
@@ -152,7 +140,22 @@ impl IntelligenceRouter {
         }
 
         // 评估任务复杂度
-        let complexity = self.assess_complexity(messages);
+        let Some(features) = self.route_features(messages) else {
+            return RouteDecision::Cloud {
+                reason: "复杂任务，需要云端 API".to_string(),
+            };
+        };
+        let complexity = complexity_for(&features);
+
+        // v0.3.4: 根据用户纠正学习到的倾向调整
+        let adjusted = learning().lock().ok().and_then(|l| l.adjust(&features, &complexity));
+        if let Some((complexity, reason)) = adjusted {
+            println!("[Router] Learned adjustment: {}", reason);
+            return match complexity {
+                TaskComplexity::Simple => RouteDecision::Local { reason },
+                _ => RouteDecision::Cloud { reason },
+            };
+        }
 
         match complexity {
             TaskComplexity::Simple => {
@@ -220,29 +223,365 @@ impl IntelligenceRouter {
     }
 }
 
+/// 按特征判断复杂度（启发式规则）
+fn complexity_for(features: &RouteFeatures) -> TaskComplexity {
+    let estimated_tokens = features.estimated_tokens;
+
+    match (features.is_tool_request, features.is_simple_query, features.is_long_context) {
+        // 工具调用优先 - 即使上下文较长也优先使用本地
+        (true, false, _) | (true, true, false) => {
+            // 工具调用请求
+            if estimated_tokens < 2000 {
+                TaskComplexity::Simple
+            } else {
+                TaskComplexity::Medium
+            }
+        }
+        (_, _, true) => {
+            // 长上下文但没有工具请求
+            TaskComplexity::Complex
+        }
+        (false, true, false) => {
+            // 简单问答且上下文不长
+            TaskComplexity::Simple
+        }
+        (false, false, false) => {
+            // 其他情况
+            if estimated_tokens < 1000 {
+                TaskComplexity::Simple
+            } else {
+                TaskComplexity::Medium
+            }
+        }
+    }
+}
+
 impl Default for IntelligenceRouter {
     fn default() -> Self {
         Self::new()
     }
 }
 
+// ============================================================================
+// Learning from Corrections (v0.3.4)
+// ============================================================================
+
+/// 开始依据学习结果调整路由前需要的训练样本数
+const MIN_SAMPLES: u64 = 10;
+/// 本地结果可接受的概率低于该值时改用云端
+const LOW_LOCAL_PROBABILITY: f64 = 0.4;
+/// 高于该值时中等任务直接使用本地模型
+const HIGH_LOCAL_PROBABILITY: f64 = 0.75;
+/// 在线逻辑回归的学习率与 L2 正则系数
+const LEARNING_RATE: f64 = 0.3;
+const L2_PENALTY: f64 = 0.001;
+/// 超过该时间未收到反馈的决策视为用户接受
+const FEEDBACK_WINDOW: Duration = Duration::from_secs(10 * 60);
+/// 保留的决策结果条数（用于准确率统计）
+const MAX_HISTORY: usize = 2000;
+
+const FEATURE_NAMES: [&str; 7] = ["bias", "tool_request", "simple_query", "long_context", "tokens", "messages", "code"];
+
+/// 路由决策特征
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RouteFeatures {
+    pub estimated_tokens: usize,
+    pub message_count: usize,
+    pub is_tool_request: bool,
+    pub is_simple_query: bool,
+    pub is_long_context: bool,
+    /// 最后一条用户消息包含代码块
+    pub has_code: bool,
+}
+
+impl RouteFeatures {
+    /// 归一化的特征向量（首项为偏置）
+    fn vector(&self) -> [f64; FEATURE_NAMES.len()] {
+        let flag = |b: bool| if b { 1.0 } else { 0.0 };
+        [
+            1.0,
+            flag(self.is_tool_request),
+            flag(self.is_simple_query),
+            flag(self.is_long_context),
+            (self.estimated_tokens as f64 / 4000.0).min(2.0),
+            (self.message_count as f64 / 20.0).min(2.0),
+            flag(self.has_code),
+        ]
+    }
+}
+
+/// 用户对一次路由决策的反馈
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteOutcome {
+    /// 接受了路由结果
+    Accepted,
+    /// 改用云端重新生成（如本地回答质量差）
+    ForcedCloud,
+    /// 改用本地模型
+    ForcedLocal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OutcomeRecord {
+    timestamp: i64,
+    local: bool,
+    outcome: RouteOutcome,
+}
+
+struct PendingDecision {
+    features: RouteFeatures,
+    local: bool,
+    at: Instant,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct RouterLearning {
+    weights: Vec<f64>,
+    /// 参与训练的样本数
+    samples: u64,
+    history: Vec<OutcomeRecord>,
+    #[serde(skip)]
+    pending: HashMap<String, PendingDecision>,
+}
+
+/// 某一天的路由准确率
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyAccuracy {
+    pub date: String,
+    pub decisions: u64,
+    pub overrides: u64,
+    pub accuracy: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouterStats {
+    pub decisions: u64,
+    pub accepted: u64,
+    pub forced_cloud: u64,
+    pub forced_local: u64,
+    /// 未被用户纠正的决策比例
+    pub accuracy: f64,
+    /// 参与训练的样本数；达到阈值后学习结果才参与路由
+    pub samples: u64,
+    pub learning_active: bool,
+    pub weights: BTreeMap<String, f64>,
+    /// 按天的准确率（时间升序）
+    pub daily: Vec<DailyAccuracy>,
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+impl RouterLearning {
+    /// 本地结果可被接受的概率；样本不足时返回 None
+    fn local_probability(&self, features: &RouteFeatures) -> Option<f64> {
+        if self.samples < MIN_SAMPLES || self.weights.len() != FEATURE_NAMES.len() {
+            return None;
+        }
+        let z: f64 = self.weights.iter().zip(features.vector()).map(|(w, x)| w * x).sum();
+        Some(sigmoid(z))
+    }
+
+    /// 学习结果与启发式判断不一致时返回调整后的复杂度与原因
+    fn adjust(&self, features: &RouteFeatures, complexity: &TaskComplexity) -> Option<(TaskComplexity, String)> {
+        let p = self.local_probability(features)?;
+        match complexity {
+            TaskComplexity::Simple | TaskComplexity::Medium if p < LOW_LOCAL_PROBABILITY => Some((
+                TaskComplexity::Complex,
+                format!("类似请求的本地结果常被改用云端（本地可接受概率 {:.0}%），使用云端 API", p * 100.0),
+            )),
+            TaskComplexity::Medium if p > HIGH_LOCAL_PROBABILITY => Some((
+                TaskComplexity::Simple,
+                format!("类似请求的本地结果通常被接受（本地可接受概率 {:.0}%），本地模型处理", p * 100.0),
+            )),
+            _ => None,
+        }
+    }
+
+    /// SGD 更新：`label` 为 1 表示本地结果可接受
+    fn train(&mut self, features: &RouteFeatures, label: f64) {
+        if self.weights.len() != FEATURE_NAMES.len() {
+            self.weights = vec![0.0; FEATURE_NAMES.len()];
+        }
+        let x = features.vector();
+        let z: f64 = self.weights.iter().zip(x).map(|(w, x)| w * x).sum();
+        let error = label - sigmoid(z);
+        for (w, x) in self.weights.iter_mut().zip(x) {
+            *w += LEARNING_RATE * (error * x - L2_PENALTY * *w);
+        }
+        self.samples += 1;
+    }
+
+    fn record(&mut self, features: RouteFeatures, local: bool, now: Instant) -> String {
+        self.expire(now);
+        let id = uuid::Uuid::new_v4().to_string();
+        self.pending.insert(id.clone(), PendingDecision { features, local, at: now });
+        id
+    }
+
+    fn resolve(&mut self, decision: PendingDecision, outcome: RouteOutcome) {
+        // 云端结果被接受不说明本地会失败，不参与训练
+        let label = match (decision.local, outcome) {
+            (true, RouteOutcome::Accepted) | (_, RouteOutcome::ForcedLocal) => Some(1.0),
+            (_, RouteOutcome::ForcedCloud) => Some(0.0),
+            (false, RouteOutcome::Accepted) => None,
+        };
+        if let Some(label) = label {
+            self.train(&decision.features, label);
+        }
+        self.history.push(OutcomeRecord { timestamp: chrono::Utc::now().timestamp(), local: decision.local, outcome });
+        if self.history.len() > MAX_HISTORY {
+            let excess = self.history.len() - MAX_HISTORY;
+            self.history.drain(..excess);
+        }
+    }
+
+    fn feedback(&mut self, decision_id: &str, outcome: RouteOutcome) -> Result<(), String> {
+        let decision = self.pending.remove(decision_id).ok_or_else(|| format!("Unknown or expired routing decision: {}", decision_id))?;
+        self.resolve(decision, outcome);
+        Ok(())
+    }
+
+    /// 超过反馈窗口的决策按接受处理；返回处理的条数
+    fn expire(&mut self, now: Instant) -> usize {
+        let expired: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, d)| now.saturating_duration_since(d.at) > FEEDBACK_WINDOW)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            if let Some(decision) = self.pending.remove(id) {
+                self.resolve(decision, RouteOutcome::Accepted);
+            }
+        }
+        expired.len()
+    }
+
+    fn stats(&self) -> RouterStats {
+        let count = |outcome: RouteOutcome| self.history.iter().filter(|r| r.outcome == outcome).count() as u64;
+        let accuracy = |decisions: u64, overrides: u64| if decisions == 0 { 0.0 } else { (decisions - overrides) as f64 / decisions as f64 };
+
+        let mut days: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        for record in &self.history {
+            let date = chrono::DateTime::from_timestamp(record.timestamp, 0)
+                .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string())
+                .unwrap_or_default();
+            let day = days.entry(date).or_default();
+            day.0 += 1;
+            if record.outcome != RouteOutcome::Accepted {
+                day.1 += 1;
+            }
+        }
+
+        let (accepted, forced_cloud, forced_local) = (count(RouteOutcome::Accepted), count(RouteOutcome::ForcedCloud), count(RouteOutcome::ForcedLocal));
+        let decisions = self.history.len() as u64;
+        RouterStats {
+            decisions,
+            accepted,
+            forced_cloud,
+            forced_local,
+            accuracy: accuracy(decisions, forced_cloud + forced_local),
+            samples: self.samples,
+            learning_active: self.samples >= MIN_SAMPLES,
+            weights: FEATURE_NAMES.iter().zip(&self.weights).map(|(n, w)| (n.to_string(), *w)).collect(),
+            daily: days
+                .into_iter()
+                .map(|(date, (decisions, overrides))| DailyAccuracy { date, decisions, overrides, accuracy: accuracy(decisions, overrides) })
+                .collect(),
+        }
+    }
+}
+
+fn learning_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("router_learning.json")
+}
+
+fn learning() -> &'static std::sync::Mutex<RouterLearning> {
+    static LEARNING: OnceLock<std::sync::Mutex<RouterLearning>> = OnceLock::new();
+    LEARNING.get_or_init(|| {
+        // 测试不读取用户的学习数据，保证路由结果稳定
+        if cfg!(test) {
+            return std::sync::Mutex::new(RouterLearning::default());
+        }
+        let learning = std::fs::read_to_string(learning_path())
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default();
+        std::sync::Mutex::new(learning)
+    })
+}
+
+fn save(learning: &RouterLearning) {
+    if cfg!(test) {
+        return;
+    }
+    let path = learning_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match serde_json::to_string(learning) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(&path, json) {
+                eprintln!("[Router] Failed to save {}: {}", path.display(), e);
+            }
+        }
+        Err(e) => eprintln!("[Router] Failed to serialize router learning: {}", e),
+    }
+}
+
+/// 记录一次实际采用的路由，返回用于反馈的决策 ID
+pub fn record_route(features: RouteFeatures, local: bool) -> Option<String> {
+    let mut learning = learning().lock().ok()?;
+    let before = learning.history.len();
+    let id = learning.record(features, local, Instant::now());
+    if learning.history.len() != before {
+        save(&learning);
+    }
+    Some(id)
+}
+
+/// 用户对路由决策的反馈（改用云端 / 本地，或确认接受）
+#[tauri::command]
+pub fn record_route_feedback(decision_id: String, outcome: RouteOutcome) -> Result<(), String> {
+    let mut learning = learning().lock().map_err(|e| e.to_string())?;
+    learning.feedback(&decision_id, outcome)?;
+    println!("[Router] Feedback for {}: {:?} ({} samples)", decision_id, outcome, learning.samples);
+    save(&learning);
+    Ok(())
+}
+
+/// 路由准确率与学习状态
+#[tauri::command]
+pub fn get_router_stats() -> Result<RouterStats, String> {
+    let mut learning = learning().lock().map_err(|e| e.to_string())?;
+    if learning.expire(Instant::now()) > 0 {
+        save(&learning);
+    }
+    Ok(learning.stats())
+}
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
 
 /// 从消息内容中提取文本
+///
+/// v0.3.4: 图片以 `[image]` 占位，对话摘要与纯文本工具协议仍能看出消息附带了图片
 pub fn extract_text_content(content: &crate::core_traits::ai::Content) -> String {
     match content {
         crate::core_traits::ai::Content::Text(text) => text.clone(),
         crate::core_traits::ai::Content::Parts(parts) => {
             parts
                 .iter()
-                .filter_map(|p| {
-                    if let crate::core_traits::ai::ContentPart::Text { text, .. } = p {
-                        Some(text.clone())
-                    } else {
-                        None
-                    }
+                .map(|p| match p {
+                    crate::core_traits::ai::ContentPart::Text { text, .. } => text.clone(),
+                    crate::core_traits::ai::ContentPart::ImageUrl { .. } => IMAGE_PLACEHOLDER.to_string(),
                 })
                 .collect::<Vec<_>>()
                 .join("\n")
@@ -250,6 +589,9 @@ pub fn extract_text_content(content: &crate::core_traits::ai::Content) -> String
     }
 }
 
+/// 纯文本中代表图片的占位符
+pub const IMAGE_PLACEHOLDER: &str = "[image]";
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -257,7 +599,7 @@ pub fn extract_text_content(content: &crate::core_traits::ai::Content) -> String
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core_traits::ai::{Message, Content};
+    use crate::core_traits::ai::{Message, Content, ContentPart, ImageUrl};
 
     #[test]
     fn test_assess_simple_query() {
@@ -306,10 +648,48 @@ mod tests {
         assert_eq!(complexity, TaskComplexity::Complex);
     }
 
+    #[test]
+    fn test_learning_from_corrections() {
+        let tool = RouteFeatures { estimated_tokens: 300, message_count: 2, is_tool_request: true, is_simple_query: false, is_long_context: false, has_code: false };
+        let question = RouteFeatures { is_tool_request: false, is_simple_query: true, ..tool };
+        let mut learning = RouterLearning::default();
+        let now = Instant::now();
+
+        // 样本不足时不调整
+        assert_eq!(learning.adjust(&tool, &TaskComplexity::Simple), None);
+        assert!(learning.feedback("missing", RouteOutcome::Accepted).is_err());
+
+        for _ in 0..12 {
+            let id = learning.record(tool, true, now);
+            learning.feedback(&id, RouteOutcome::ForcedCloud).unwrap();
+            learning.record(question, true, now);
+        }
+        // 简单问答未收到反馈，超过反馈窗口后按接受处理
+        assert_eq!(learning.expire(now + FEEDBACK_WINDOW + Duration::from_secs(1)), 12);
+
+        let (complexity, reason) = learning.adjust(&tool, &TaskComplexity::Simple).unwrap();
+        assert_eq!(complexity, TaskComplexity::Complex);
+        assert!(reason.contains("云端"));
+        assert_eq!(learning.adjust(&question, &TaskComplexity::Simple), None);
+        assert_eq!(learning.adjust(&question, &TaskComplexity::Medium).map(|(c, _)| c), Some(TaskComplexity::Simple));
+
+        let stats = learning.stats();
+        assert_eq!((stats.decisions, stats.accepted, stats.forced_cloud), (24, 12, 12));
+        assert!((stats.accuracy - 0.5).abs() < 1e-9);
+        assert!(stats.learning_active);
+        assert_eq!(stats.daily.iter().map(|d| d.decisions).sum::<u64>(), 24);
+    }
+
     #[test]
     fn test_extract_text_content() {
         let content = Content::Text("Hello world".to_string());
         let text = extract_text_content(&content);
         assert_eq!(text, "Hello world");
+
+        let content = Content::Parts(vec![
+            ContentPart::Text { text: "Look at this".to_string(), part_type: "text".to_string() },
+            ContentPart::ImageUrl { image_url: ImageUrl { url: "data:image/png;base64,AAAA".to_string() } },
+        ]);
+        assert_eq!(extract_text_content(&content), "Look at this\n[image]");
     }
 }
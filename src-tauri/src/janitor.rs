@@ -0,0 +1,322 @@
+/*!
+Janitor - 启动时清理遗留文件
+============================
+
+进程崩溃或被强制退出后可能留下：
+- 系统临时目录下以 `ifainew-atomic-` 开头的原子写入会话目录、`ifai-screenshot-` 截图
+- `~/.ifai/models` 中 `.part` 后缀的未完成模型下载
+- `~/.ifai` 中 `.lock` / `.pid` 锁文件（记录的进程已退出）
+
+只清理修改时间超过 `ORPHAN_MIN_AGE` 的文件（未完成的下载可断点续传，保留 `PARTIAL_DOWNLOAD_MIN_AGE`），
+仍在使用的会话目录和进行中的下载会被跳过。
+启动时在后台线程执行一次，结果写入日志；`run_cleanup_now` 可手动触发。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::commands::atomic_commands::{SessionStore, ATOMIC_TEMP_PREFIX};
+
+/// 遗留文件的最小存在时间，避免误删刚创建的文件
+const ORPHAN_MIN_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// 未完成下载的保留时间，期间重新下载会从断点继续
+const PARTIAL_DOWNLOAD_MIN_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+const SCREENSHOT_PREFIX: &str = "ifai-screenshot-";
+
+/// 遗留文件类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactKind {
+    AtomicTempDir,
+    Screenshot,
+    PartialDownload,
+    StaleLock,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanedArtifact {
+    pub kind: ArtifactKind,
+    pub path: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CleanupReport {
+    pub cleaned: Vec<CleanedArtifact>,
+    /// 符合规则但仍在使用或未到期的文件
+    pub skipped: Vec<String>,
+    pub errors: Vec<String>,
+    pub bytes_freed: u64,
+}
+
+/// 清理范围
+pub struct CleanupOptions {
+    pub temp_dir: PathBuf,
+    pub ifai_dir: PathBuf,
+    pub min_age: Duration,
+    /// `.part` 文件的最小存在时间
+    pub partial_min_age: Duration,
+    /// 仍在使用的路径（如活动中的原子写入会话目录）
+    pub protected: Vec<PathBuf>,
+    /// 有下载正在进行时不清理 `.part` 文件
+    pub downloading: bool,
+}
+
+impl Default for CleanupOptions {
+    fn default() -> Self {
+        Self {
+            temp_dir: std::env::temp_dir(),
+            ifai_dir: dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join(".ifai"),
+            min_age: ORPHAN_MIN_AGE,
+            partial_min_age: PARTIAL_DOWNLOAD_MIN_AGE,
+            protected: Vec::new(),
+            downloading: false,
+        }
+    }
+}
+
+// ============================================================================
+// Detection
+// ============================================================================
+
+fn classify_temp(name: &str, is_dir: bool) -> Option<ArtifactKind> {
+    if is_dir && name.starts_with(ATOMIC_TEMP_PREFIX) {
+        Some(ArtifactKind::AtomicTempDir)
+    } else if !is_dir && name.starts_with(SCREENSHOT_PREFIX) && name.ends_with(".png") {
+        Some(ArtifactKind::Screenshot)
+    } else {
+        None
+    }
+}
+
+fn age_of(path: &Path) -> Option<Duration> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(SystemTime::now().duration_since(modified).unwrap_or_default())
+}
+
+fn size_of(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+/// 进程是否仍在运行；无法判断时返回 `None`
+fn pid_alive(pid: u32) -> Option<bool> {
+    if pid == std::process::id() {
+        return Some(true);
+    }
+    #[cfg(target_os = "linux")]
+    {
+        Some(Path::new("/proc").join(pid.to_string()).exists())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// 锁文件是否已失效：记录的进程已退出，或无法判断进程状态且已超过最小存在时间
+fn is_stale_lock(path: &Path, min_age: Duration) -> bool {
+    let pid = std::fs::read_to_string(path).ok().and_then(|s| s.trim().parse::<u32>().ok());
+    match pid.and_then(pid_alive) {
+        Some(alive) => !alive,
+        None => age_of(path).is_some_and(|age| age >= min_age),
+    }
+}
+
+/// 列出候选的遗留文件（尚未检查存在时间与占用情况）
+fn candidates(options: &CleanupOptions) -> Vec<(ArtifactKind, PathBuf)> {
+    let mut found = Vec::new();
+
+    for entry in std::fs::read_dir(&options.temp_dir).into_iter().flatten().flatten() {
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if let Some(kind) = classify_temp(&entry.file_name().to_string_lossy(), is_dir) {
+            found.push((kind, entry.path()));
+        }
+    }
+
+    let models_dir = options.ifai_dir.join("models");
+    for entry in std::fs::read_dir(&models_dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == crate::local_model::PARTIAL_DOWNLOAD_EXT) {
+            found.push((ArtifactKind::PartialDownload, path));
+        }
+    }
+
+    for entry in std::fs::read_dir(&options.ifai_dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "lock" || ext == "pid") {
+            found.push((ArtifactKind::StaleLock, path));
+        }
+    }
+
+    found
+}
+
+// ============================================================================
+// Cleanup
+// ============================================================================
+
+pub fn run_cleanup(options: &CleanupOptions) -> CleanupReport {
+    let mut report = CleanupReport::default();
+
+    for (kind, path) in candidates(options) {
+        let display = path.to_string_lossy().to_string();
+        let in_use = options.protected.iter().any(|p| p == &path)
+            || (kind == ArtifactKind::PartialDownload && options.downloading);
+        let expired = match kind {
+            ArtifactKind::StaleLock => is_stale_lock(&path, options.min_age),
+            ArtifactKind::PartialDownload => age_of(&path).is_some_and(|age| age >= options.partial_min_age),
+            _ => age_of(&path).is_some_and(|age| age >= options.min_age),
+        };
+        if in_use || !expired {
+            report.skipped.push(display);
+            continue;
+        }
+
+        let bytes = size_of(&path);
+        let result = if path.is_dir() { std::fs::remove_dir_all(&path) } else { std::fs::remove_file(&path) };
+        match result {
+            Ok(()) => {
+                report.bytes_freed += bytes;
+                report.cleaned.push(CleanedArtifact { kind, path: display, bytes });
+            }
+            Err(e) => report.errors.push(format!("{}: {}", display, e)),
+        }
+    }
+
+    report
+}
+
+fn log_report(report: &CleanupReport) {
+    for item in &report.cleaned {
+        println!("[Janitor] Removed {:?} {} ({} bytes)", item.kind, item.path, item.bytes);
+    }
+    for err in &report.errors {
+        eprintln!("[Janitor] Failed to remove {}", err);
+    }
+    println!(
+        "[Janitor] Cleanup finished: {} removed, {} skipped, {} bytes freed",
+        report.cleaned.len(),
+        report.skipped.len(),
+        report.bytes_freed
+    );
+}
+
+/// 启动时在后台线程执行一次清理
+pub fn start_startup_cleanup() {
+    std::thread::spawn(|| {
+        let report = run_cleanup(&CleanupOptions::default());
+        log_report(&report);
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 立即执行一次清理（跳过活动中的原子写入会话和进行中的下载）
+#[tauri::command]
+pub async fn run_cleanup_now(sessions: tauri::State<'_, std::sync::Mutex<SessionStore>>) -> Result<CleanupReport, String> {
+    let protected = sessions.lock()
+        .map_err(|e| format!("Failed to acquire lock: {}", e))?
+        .values()
+        .map(|s| PathBuf::from(&s.temp_dir))
+        .collect();
+    let options = CleanupOptions {
+        protected,
+        downloading: crate::local_model::is_downloading().await,
+        ..Default::default()
+    };
+    let report = tokio::task::spawn_blocking(move || run_cleanup(&options))
+        .await
+        .map_err(|e| e.to_string())?;
+    log_report(&report);
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (PathBuf, CleanupOptions) {
+        let root = std::env::temp_dir().join(format!("ifai_janitor_{}", uuid::Uuid::new_v4()));
+        let temp_dir = root.join("tmp");
+        let ifai_dir = root.join(".ifai");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        std::fs::create_dir_all(ifai_dir.join("models")).unwrap();
+        let options = CleanupOptions { temp_dir, ifai_dir, min_age: Duration::ZERO, partial_min_age: Duration::ZERO, protected: Vec::new(), downloading: false };
+        (root, options)
+    }
+
+    #[test]
+    fn test_classify_temp() {
+        assert_eq!(classify_temp("ifainew-atomic-1234", true), Some(ArtifactKind::AtomicTempDir));
+        assert_eq!(classify_temp("ifainew-atomic-1234", false), None);
+        assert_eq!(classify_temp("ifai-screenshot-abc.png", false), Some(ArtifactKind::Screenshot));
+        assert_eq!(classify_temp("other-app-tmp", true), None);
+    }
+
+    #[test]
+    fn test_cleanup_removes_orphans_and_keeps_protected() {
+        let (root, mut options) = setup();
+        let orphan = options.temp_dir.join(format!("{}orphan", ATOMIC_TEMP_PREFIX));
+        let active = options.temp_dir.join(format!("{}active", ATOMIC_TEMP_PREFIX));
+        std::fs::create_dir_all(&orphan).unwrap();
+        std::fs::write(orphan.join("file.txt"), "12345").unwrap();
+        std::fs::create_dir_all(&active).unwrap();
+        std::fs::write(options.temp_dir.join("unrelated.txt"), "keep").unwrap();
+        let partial = options.ifai_dir.join("models").join("model.gguf.part");
+        std::fs::write(&partial, "partial").unwrap();
+        options.protected.push(active.clone());
+
+        let report = run_cleanup(&options);
+        assert!(!orphan.exists());
+        assert!(active.exists());
+        assert!(!partial.exists());
+        assert!(options.temp_dir.join("unrelated.txt").exists());
+        assert_eq!(report.cleaned.len(), 2);
+        assert_eq!(report.bytes_freed, 12);
+        assert_eq!(report.skipped.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_cleanup_respects_age_and_downloads() {
+        let (root, mut options) = setup();
+        let partial = options.ifai_dir.join("models").join("model.gguf.part");
+        std::fs::write(&partial, "partial").unwrap();
+
+        options.downloading = true;
+        assert!(run_cleanup(&options).cleaned.is_empty());
+
+        options.downloading = false;
+        options.partial_min_age = PARTIAL_DOWNLOAD_MIN_AGE;
+        assert!(run_cleanup(&options).cleaned.is_empty());
+        assert!(partial.exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_lock_of_current_process_is_kept() {
+        let (root, options) = setup();
+        let lock = options.ifai_dir.join("ifai.lock");
+        std::fs::write(&lock, std::process::id().to_string()).unwrap();
+        assert!(!is_stale_lock(&lock, Duration::ZERO));
+
+        std::fs::write(&lock, "not a pid").unwrap();
+        assert!(is_stale_lock(&lock, Duration::ZERO));
+        assert!(!is_stale_lock(&lock, ORPHAN_MIN_AGE));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,489 @@
+/*!
+Job Queue - 后台任务队列
+========================
+
+索引、摘要、Webhook、定时 Agent 等后台工作统一通过任务队列执行，替代零散的 `tokio::spawn`：
+
+- 优先级：高优先级先执行，同优先级按提交时间
+- 失败重试：指数退避，超过 `max_attempts` 标记为失败
+- 取消：排队中的任务直接取消，运行中的任务通过取消标志通知处理器
+- 持久化：任务列表保存在 `~/.ifai/jobs.json`，重启后恢复，运行中被中断的任务重新排队
+- 状态变化时发送 `jobs:updated` 事件
+
+处理器按任务类型注册（`register_handler`），启动时由 `start_job_worker` 开始调度。
+内置任务类型：`rag_index`（`init_rag_index` 提交，同一项目只保留一个待执行任务）、
+`model_download`（`start_download` 提交，见 `local_model`）。
+*/
+
+use futures::future::{BoxFuture, FutureExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+use crate::events::emit_event;
+
+/// 同时运行的任务数
+const MAX_CONCURRENT_JOBS: usize = 2;
+/// 保留的已结束任务数
+const MAX_FINISHED_JOBS: usize = 200;
+/// 重试退避基数（秒）
+const RETRY_BASE_SECS: i64 = 5;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum JobPriority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Succeeded | JobStatus::Failed | JobStatus::Cancelled)
+    }
+}
+
+/// 后台任务
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Job {
+    pub id: String,
+    /// 任务类型，对应已注册的处理器
+    pub kind: String,
+    pub payload: Value,
+    pub priority: JobPriority,
+    pub status: JobStatus,
+    pub attempts: u32,
+    pub max_attempts: u32,
+    pub last_error: Option<String>,
+    pub result: Option<Value>,
+    pub created_at: i64,
+    pub updated_at: i64,
+    /// 最早可执行时间（重试退避）
+    pub run_after: i64,
+}
+
+/// 处理器：接收负载与取消标志
+pub type JobHandler = Arc<dyn Fn(AppHandle, Value, Arc<AtomicBool>) -> BoxFuture<'static, Result<Value, String>> + Send + Sync>;
+
+// ============================================================================
+// State
+// ============================================================================
+
+#[derive(Default)]
+struct QueueState {
+    jobs: Vec<Job>,
+    handlers: HashMap<String, JobHandler>,
+    cancel_flags: HashMap<String, Arc<AtomicBool>>,
+    loaded: bool,
+}
+
+static QUEUE: OnceLock<Mutex<QueueState>> = OnceLock::new();
+static WAKE: OnceLock<Notify> = OnceLock::new();
+
+fn queue() -> &'static Mutex<QueueState> {
+    QUEUE.get_or_init(|| Mutex::new(QueueState::default()))
+}
+
+fn wake() -> &'static Notify {
+    WAKE.get_or_init(Notify::new)
+}
+
+fn jobs_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("jobs.json")
+}
+
+fn load_jobs() -> Vec<Job> {
+    std::fs::read_to_string(jobs_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_jobs(jobs: &[Job]) {
+    let path = jobs_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    match serde_json::to_string_pretty(jobs) {
+        Ok(content) => {
+            if let Err(e) = std::fs::write(&path, content) {
+                eprintln!("[JobQueue] Failed to persist jobs: {}", e);
+            }
+        }
+        Err(e) => eprintln!("[JobQueue] Failed to serialize jobs: {}", e),
+    }
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+// ============================================================================
+// Scheduling
+// ============================================================================
+
+/// 重启后恢复：运行中被中断的任务重新排队
+fn recover_interrupted(jobs: &mut [Job]) {
+    for job in jobs.iter_mut().filter(|j| j.status == JobStatus::Running) {
+        job.status = JobStatus::Queued;
+        job.run_after = 0;
+    }
+}
+
+/// 选择下一个可执行的任务
+fn next_runnable(jobs: &[Job], now: i64) -> Option<usize> {
+    jobs.iter()
+        .enumerate()
+        .filter(|(_, j)| j.status == JobStatus::Queued && j.run_after <= now)
+        .max_by(|(_, a), (_, b)| a.priority.cmp(&b.priority).then(b.created_at.cmp(&a.created_at)))
+        .map(|(i, _)| i)
+}
+
+/// 记录执行结果，失败时按退避重新排队
+fn apply_outcome(job: &mut Job, outcome: Result<Value, String>, cancelled: bool, now: i64) {
+    job.updated_at = now;
+    match outcome {
+        _ if cancelled => job.status = JobStatus::Cancelled,
+        Ok(result) => {
+            job.status = JobStatus::Succeeded;
+            job.result = Some(result);
+            job.last_error = None;
+        }
+        Err(e) => {
+            job.last_error = Some(e);
+            if job.attempts < job.max_attempts {
+                job.status = JobStatus::Queued;
+                job.run_after = now + RETRY_BASE_SECS * 2i64.pow(job.attempts.saturating_sub(1).min(10));
+            } else {
+                job.status = JobStatus::Failed;
+            }
+        }
+    }
+}
+
+/// 只保留最近的已结束任务
+fn prune_finished(jobs: &mut Vec<Job>) {
+    let finished = jobs.iter().filter(|j| j.status.is_finished()).count();
+    if finished <= MAX_FINISHED_JOBS {
+        return;
+    }
+    let mut to_drop = finished - MAX_FINISHED_JOBS;
+    jobs.sort_by_key(|j| j.created_at);
+    jobs.retain(|j| {
+        if to_drop > 0 && j.status.is_finished() {
+            to_drop -= 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+fn ensure_loaded(state: &mut QueueState) {
+    if !state.loaded {
+        state.jobs = load_jobs();
+        recover_interrupted(&mut state.jobs);
+        state.loaded = true;
+    }
+}
+
+fn emit_job(app: &AppHandle, job: &Job) {
+    emit_event(app, "jobs:updated", job);
+}
+
+// ============================================================================
+// Public API
+// ============================================================================
+
+/// 注册任务类型的处理器
+pub fn register_handler(kind: &str, handler: JobHandler) {
+    if let Ok(mut state) = queue().lock() {
+        state.handlers.insert(kind.to_string(), handler);
+    }
+}
+
+/// 提交任务，返回任务 ID
+pub fn enqueue(kind: &str, payload: Value, priority: JobPriority, max_attempts: u32) -> Result<Job, String> {
+    let mut state = queue().lock().map_err(|e| format!("Lock error: {}", e))?;
+    ensure_loaded(&mut state);
+    if !state.handlers.contains_key(kind) {
+        return Err(format!("Unknown job kind: {}", kind));
+    }
+    Ok(push_job(state, kind, payload, priority, max_attempts))
+}
+
+/// 提交任务；已有同类型、同负载的任务在排队或运行时直接返回该任务（如重复触发的项目索引）
+pub fn enqueue_unique(kind: &str, payload: Value, priority: JobPriority, max_attempts: u32) -> Result<Job, String> {
+    let mut state = queue().lock().map_err(|e| format!("Lock error: {}", e))?;
+    ensure_loaded(&mut state);
+    if !state.handlers.contains_key(kind) {
+        return Err(format!("Unknown job kind: {}", kind));
+    }
+    if let Some(existing) = find_pending(&state.jobs, kind, &payload) {
+        return Ok(existing.clone());
+    }
+    Ok(push_job(state, kind, payload, priority, max_attempts))
+}
+
+/// 排队或运行中的同类型、同负载任务
+fn find_pending<'a>(jobs: &'a [Job], kind: &str, payload: &Value) -> Option<&'a Job> {
+    jobs.iter().find(|j| j.kind == kind && &j.payload == payload && !j.status.is_finished())
+}
+
+fn push_job(mut state: std::sync::MutexGuard<'_, QueueState>, kind: &str, payload: Value, priority: JobPriority, max_attempts: u32) -> Job {
+    let now = now();
+    let job = Job {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind: kind.to_string(),
+        payload,
+        priority,
+        status: JobStatus::Queued,
+        attempts: 0,
+        max_attempts: max_attempts.max(1),
+        last_error: None,
+        result: None,
+        created_at: now,
+        updated_at: now,
+        run_after: 0,
+    };
+    state.jobs.push(job.clone());
+    save_jobs(&state.jobs);
+    drop(state);
+    wake().notify_one();
+    job
+}
+
+/// 取出下一个任务并标记为运行中
+fn claim_next() -> Option<(Job, JobHandler, Arc<AtomicBool>)> {
+    let mut state = queue().lock().ok()?;
+    ensure_loaded(&mut state);
+    let running = state.jobs.iter().filter(|j| j.status == JobStatus::Running).count();
+    if running >= MAX_CONCURRENT_JOBS {
+        return None;
+    }
+    let idx = next_runnable(&state.jobs, now())?;
+    let Some(handler) = state.handlers.get(&state.jobs[idx].kind).cloned() else {
+        let job = &mut state.jobs[idx];
+        job.status = JobStatus::Failed;
+        job.last_error = Some(format!("No handler registered for {}", job.kind));
+        save_jobs(&state.jobs);
+        return None;
+    };
+    let flag = Arc::new(AtomicBool::new(false));
+    let job = &mut state.jobs[idx];
+    job.status = JobStatus::Running;
+    job.attempts += 1;
+    job.updated_at = now();
+    let job = job.clone();
+    state.cancel_flags.insert(job.id.clone(), flag.clone());
+    save_jobs(&state.jobs);
+    Some((job, handler, flag))
+}
+
+fn finish(app: &AppHandle, id: &str, outcome: Result<Value, String>) {
+    let Ok(mut state) = queue().lock() else { return };
+    let cancelled = state.cancel_flags.remove(id).map_or(false, |f| f.load(Ordering::SeqCst));
+    if let Some(job) = state.jobs.iter_mut().find(|j| j.id == id) {
+        apply_outcome(job, outcome, cancelled, now());
+        println!("[JobQueue] {} ({}) -> {:?}", job.kind, job.id, job.status);
+        emit_job(app, job);
+    }
+    prune_finished(&mut state.jobs);
+    save_jobs(&state.jobs);
+}
+
+/// 启动调度循环
+pub fn start_job_worker(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            while let Some((job, handler, flag)) = claim_next() {
+                emit_job(&app, &job);
+                let app_for_job = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    let outcome = handler(app_for_job.clone(), job.payload.clone(), flag).await;
+                    finish(&app_for_job, &job.id, outcome);
+                    wake().notify_one();
+                });
+            }
+            // 等待新任务，或定期检查退避到期的任务
+            let _ = tokio::time::timeout(Duration::from_secs(1), wake().notified()).await;
+        }
+    });
+}
+
+/// 注册内置任务类型
+pub fn register_builtin_handlers() {
+    register_handler("rag_index", Arc::new(|app: AppHandle, payload: Value, _cancel: Arc<AtomicBool>| {
+        async move {
+            let root = payload["root"].as_str().ok_or("Missing 'root' in payload")?.to_string();
+            let rag = app.state::<crate::AppState>().rag_service.clone();
+            rag.index_project(&root).await?;
+            Ok(serde_json::json!({ "root": root }))
+        }.boxed()
+    }));
+    register_handler(crate::local_model::DOWNLOAD_JOB_KIND, Arc::new(|app: AppHandle, payload: Value, cancel: Arc<AtomicBool>| {
+        crate::local_model::run_download_job(app, payload, cancel).boxed()
+    }));
+}
+
+/// 取消任务：排队中的直接取消，运行中的通知处理器停止
+pub fn cancel(app: &AppHandle, id: &str) -> Result<(), String> {
+    let mut state = queue().lock().map_err(|e| format!("Lock error: {}", e))?;
+    ensure_loaded(&mut state);
+    if let Some(flag) = state.cancel_flags.get(id) {
+        flag.store(true, Ordering::SeqCst);
+        return Ok(());
+    }
+    let job = state.jobs.iter_mut().find(|j| j.id == id).ok_or_else(|| format!("Job not found: {}", id))?;
+    if job.status.is_finished() {
+        return Err(format!("Job {} already finished", id));
+    }
+    job.status = JobStatus::Cancelled;
+    job.updated_at = now();
+    emit_job(app, job);
+    save_jobs(&state.jobs);
+    Ok(())
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 列出任务（可按状态过滤）
+#[tauri::command]
+pub fn list_jobs(status: Option<JobStatus>) -> Result<Vec<Job>, String> {
+    let mut state = queue().lock().map_err(|e| format!("Lock error: {}", e))?;
+    ensure_loaded(&mut state);
+    let mut jobs: Vec<Job> = state.jobs.iter()
+        .filter(|j| status.map_or(true, |s| j.status == s))
+        .cloned()
+        .collect();
+    jobs.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(jobs)
+}
+
+#[tauri::command]
+pub fn enqueue_job(kind: String, payload: Value, priority: Option<JobPriority>, max_attempts: Option<u32>) -> Result<Job, String> {
+    enqueue(&kind, payload, priority.unwrap_or_default(), max_attempts.unwrap_or(3))
+}
+
+#[tauri::command]
+pub fn cancel_job(app: AppHandle, id: String) -> Result<(), String> {
+    cancel(&app, &id)
+}
+
+/// 清除已结束的任务
+#[tauri::command]
+pub fn clear_finished_jobs() -> Result<usize, String> {
+    let mut state = queue().lock().map_err(|e| format!("Lock error: {}", e))?;
+    ensure_loaded(&mut state);
+    let before = state.jobs.len();
+    state.jobs.retain(|j| !j.status.is_finished());
+    save_jobs(&state.jobs);
+    Ok(before - state.jobs.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(id: &str, priority: JobPriority, created_at: i64) -> Job {
+        Job {
+            id: id.to_string(),
+            kind: "test".to_string(),
+            payload: Value::Null,
+            priority,
+            status: JobStatus::Queued,
+            attempts: 0,
+            max_attempts: 3,
+            last_error: None,
+            result: None,
+            created_at,
+            updated_at: created_at,
+            run_after: 0,
+        }
+    }
+
+    #[test]
+    fn test_next_runnable_respects_priority_and_backoff() {
+        let mut jobs = vec![job("a", JobPriority::Normal, 1), job("b", JobPriority::High, 2), job("c", JobPriority::Normal, 0)];
+        assert_eq!(jobs[next_runnable(&jobs, 10).unwrap()].id, "b");
+        jobs[1].run_after = 100;
+        assert_eq!(jobs[next_runnable(&jobs, 10).unwrap()].id, "c");
+        jobs[2].status = JobStatus::Running;
+        assert_eq!(jobs[next_runnable(&jobs, 10).unwrap()].id, "a");
+    }
+
+    #[test]
+    fn test_retry_then_fail() {
+        let mut j = job("a", JobPriority::Normal, 0);
+        j.attempts = 1;
+        apply_outcome(&mut j, Err("boom".to_string()), false, 100);
+        assert_eq!(j.status, JobStatus::Queued);
+        assert_eq!(j.run_after, 100 + RETRY_BASE_SECS);
+
+        j.attempts = 3;
+        apply_outcome(&mut j, Err("boom".to_string()), false, 200);
+        assert_eq!(j.status, JobStatus::Failed);
+        assert_eq!(j.last_error.as_deref(), Some("boom"));
+
+        let mut c = job("c", JobPriority::Low, 0);
+        apply_outcome(&mut c, Ok(Value::Null), true, 1);
+        assert_eq!(c.status, JobStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_recover_and_prune() {
+        let mut jobs = vec![job("a", JobPriority::Normal, 0)];
+        jobs[0].status = JobStatus::Running;
+        jobs[0].run_after = 50;
+        recover_interrupted(&mut jobs);
+        assert_eq!(jobs[0].status, JobStatus::Queued);
+        assert_eq!(jobs[0].run_after, 0);
+
+        let mut many: Vec<Job> = (0..MAX_FINISHED_JOBS as i64 + 5)
+            .map(|i| Job { status: JobStatus::Succeeded, ..job(&i.to_string(), JobPriority::Normal, i) })
+            .collect();
+        many.push(job("pending", JobPriority::Normal, 0));
+        prune_finished(&mut many);
+        assert_eq!(many.len(), MAX_FINISHED_JOBS + 1);
+        assert!(many.iter().any(|j| j.id == "pending"));
+        assert!(!many.iter().any(|j| j.id == "0"));
+    }
+
+    #[test]
+    fn test_find_pending_matches_kind_and_payload() {
+        let payload = serde_json::json!({ "root": "/p" });
+        let mut jobs = vec![Job { payload: payload.clone(), ..job("a", JobPriority::Normal, 0) }];
+        assert_eq!(find_pending(&jobs, "test", &payload).map(|j| j.id.as_str()), Some("a"));
+        assert!(find_pending(&jobs, "test", &serde_json::json!({ "root": "/q" })).is_none());
+        assert!(find_pending(&jobs, "other", &payload).is_none());
+
+        jobs[0].status = JobStatus::Succeeded;
+        assert!(find_pending(&jobs, "test", &payload).is_none());
+    }
+}
@@ -0,0 +1,519 @@
+/*!
+JS Sandbox - 沙箱化 JavaScript/TypeScript 片段执行
+==================================================
+
+为模型提供 `agent_eval_js` 工具，用于验证算法行为或转换数据，而无需调用用户的 shell。
+
+- 运行于嵌入式 QuickJS，仅包含 ECMAScript 内置对象：没有文件系统、网络、进程访问
+- 严格限制执行时间（中断回调）、内存与栈大小
+- `console.log` 输出被捕获返回；最后一个表达式的值以 JSON 形式返回
+- TypeScript 片段先经 oxc 解析，再把类型语法替换为空白（保留行号）；枚举、命名空间、装饰器等需要编译的语法直接报错
+*/
+
+use oxc_allocator::Allocator;
+use oxc_ast::ast::{
+    AccessorProperty, AccessorPropertyType, Class, Decorator, FormalParameter, MethodDefinition, PropertyDefinition,
+    PropertyDefinitionType, Statement, TSAsExpression, TSClassImplements, TSIndexSignature, TSNonNullExpression,
+    TSSatisfiesExpression, TSThisParameter, TSTypeAnnotation, TSTypeAssertion, TSTypeParameterDeclaration,
+    TSTypeParameterInstantiation,
+};
+use oxc_ast_visit::{walk, Visit};
+use oxc_parser::Parser;
+use oxc_span::{GetSpan, SourceType, Span};
+use rquickjs::function::Rest;
+use rquickjs::{CatchResultExt, Context, Ctx, Function, Object, Runtime, Value};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// 默认超时
+const DEFAULT_TIMEOUT_MS: u64 = 2_000;
+/// 超时上限
+const MAX_TIMEOUT_MS: u64 = 10_000;
+/// 内存上限
+const MEMORY_LIMIT_BYTES: usize = 64 * 1024 * 1024;
+/// 栈上限
+const MAX_STACK_BYTES: usize = 512 * 1024;
+/// 片段最大长度
+const MAX_SNIPPET_CHARS: usize = 50_000;
+/// 捕获输出最大长度
+const MAX_OUTPUT_CHARS: usize = 20_000;
+
+/// 执行结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JsEvalResult {
+    pub success: bool,
+    /// 最后一个表达式的值（JSON）
+    pub result: Option<String>,
+    /// 捕获的 console 输出
+    pub logs: Vec<String>,
+    pub error: Option<String>,
+    pub elapsed_ms: u64,
+    pub timed_out: bool,
+}
+
+// ============================================================================
+// TypeScript 类型擦除
+// ============================================================================
+
+/// 需要擦除的类成员修饰符
+const TS_MEMBER_MODIFIERS: &[&str] = &["public", "private", "protected", "readonly", "override", "declare", "abstract"];
+
+/// 用 oxc 解析 TypeScript，并把纯类型语法替换为空白
+///
+/// 只擦除不影响运行时语义的部分（类型注解、接口、类型别名、泛型、`as` / `satisfies` / 非空断言、
+/// 访问修饰符、重载签名、`declare` 声明）；字符串、正则、解构等 JavaScript 语法原样保留。
+/// 枚举、命名空间、构造函数参数属性、装饰器需要真正的编译，直接返回错误。
+pub fn strip_typescript(source: &str) -> Result<String, String> {
+    let allocator = Allocator::default();
+    let source_type = SourceType::ts().with_script(true);
+    let parsed = Parser::new(&allocator, source, source_type).parse();
+    if let Some(error) = parsed.errors.first() {
+        return Err(format!("TypeScript parse error: {}", error));
+    }
+
+    let mut eraser = TypeEraser { source, ranges: Vec::new(), unsupported: None };
+    eraser.visit_program(&parsed.program);
+    if let Some(syntax) = eraser.unsupported {
+        return Err(format!("Unsupported TypeScript syntax: {} (needs compilation; rewrite the snippet in plain JavaScript)", syntax));
+    }
+    Ok(eraser.apply())
+}
+
+/// 收集需要擦除的源码区间
+struct TypeEraser<'s> {
+    source: &'s str,
+    /// (start, end, 是否在开头补 `;`)
+    ranges: Vec<(u32, u32, bool)>,
+    /// 第一个遇到的不支持语法
+    unsupported: Option<&'static str>,
+}
+
+impl TypeEraser<'_> {
+    fn blank(&mut self, start: u32, end: u32) {
+        if start < end {
+            self.ranges.push((start, end, false));
+        }
+    }
+
+    /// 擦除整条语句或类成员；补一个 `;` 防止前后两行被自动分号插入规则拼接
+    fn blank_statement(&mut self, span: Span) {
+        self.ranges.push((span.start, span.end, true));
+    }
+
+    fn unsupported(&mut self, syntax: &'static str) {
+        self.unsupported.get_or_insert(syntax);
+    }
+
+    /// 擦除 `start..end` 中出现的 TypeScript 成员修饰符（成员开头到键名之间只有修饰符）
+    fn blank_modifiers(&mut self, start: u32, end: u32) {
+        let text = &self.source[start as usize..end as usize];
+        for word in text.split(|c: char| !c.is_ascii_alphabetic()) {
+            if TS_MEMBER_MODIFIERS.contains(&word) {
+                let word_start = start + (word.as_ptr() as usize - text.as_ptr() as usize) as u32;
+                self.blank(word_start, word_start + word.len() as u32);
+            }
+        }
+    }
+
+    /// 擦除 `from` 之后的第一个 `?`（可选参数 / 可选方法标记）
+    fn blank_question_after(&mut self, from: u32) {
+        if let Some(i) = self.source[from as usize..].find('?') {
+            let pos = from + i as u32;
+            self.blank(pos, pos + 1);
+        }
+    }
+
+    fn apply(mut self) -> String {
+        self.ranges.sort_unstable();
+        let mut out = String::with_capacity(self.source.len());
+        let mut cursor = 0usize;
+        for (start, end, semicolon) in self.ranges {
+            let (start, end) = (start as usize, end as usize);
+            if end <= cursor {
+                continue;
+            }
+            let start = start.max(cursor);
+            out.push_str(&self.source[cursor..start]);
+            for (i, c) in self.source[start..end].chars().enumerate() {
+                out.push(match c {
+                    '\n' | '\r' => c,
+                    _ if i == 0 && semicolon => ';',
+                    _ => ' ',
+                });
+            }
+            cursor = end;
+        }
+        out.push_str(&self.source[cursor..]);
+        out
+    }
+}
+
+impl<'a> Visit<'a> for TypeEraser<'_> {
+    fn visit_statement(&mut self, it: &Statement<'a>) {
+        match it {
+            Statement::TSTypeAliasDeclaration(_) | Statement::TSInterfaceDeclaration(_) => self.blank_statement(it.span()),
+            Statement::VariableDeclaration(decl) if decl.declare => self.blank_statement(it.span()),
+            Statement::ClassDeclaration(class) if class.declare => self.blank_statement(it.span()),
+            Statement::FunctionDeclaration(func) if func.declare || func.body.is_none() => self.blank_statement(it.span()),
+            Statement::TSEnumDeclaration(decl) if decl.declare => self.blank_statement(it.span()),
+            Statement::TSModuleDeclaration(decl) if decl.declare => self.blank_statement(it.span()),
+            Statement::TSGlobalDeclaration(_) => self.blank_statement(it.span()),
+            Statement::TSEnumDeclaration(_) => self.unsupported("enum"),
+            Statement::TSModuleDeclaration(_) => self.unsupported("namespace"),
+            Statement::TSImportEqualsDeclaration(_) => self.unsupported("import = require"),
+            Statement::TSExportAssignment(_) | Statement::TSNamespaceExportDeclaration(_) => self.unsupported("export ="),
+            _ => walk::walk_statement(self, it),
+        }
+    }
+
+    fn visit_ts_type_annotation(&mut self, it: &TSTypeAnnotation<'a>) {
+        // 连同前面的可选标记 `?` 或确定赋值标记 `!` 一起擦除
+        let before = self.source[..it.span.start as usize].trim_end();
+        let start = if before.ends_with(['?', '!']) { before.len() as u32 - 1 } else { it.span.start };
+        self.blank(start, it.span.end);
+    }
+
+    fn visit_ts_type_parameter_declaration(&mut self, it: &TSTypeParameterDeclaration<'a>) {
+        self.blank(it.span.start, it.span.end);
+    }
+
+    fn visit_ts_type_parameter_instantiation(&mut self, it: &TSTypeParameterInstantiation<'a>) {
+        self.blank(it.span.start, it.span.end);
+    }
+
+    fn visit_ts_this_parameter(&mut self, it: &TSThisParameter<'a>) {
+        // `this: T` 及其后的逗号
+        let rest = &self.source[it.span.end as usize..];
+        let end = match rest.trim_start().strip_prefix(',') {
+            Some(after) => self.source.len() - after.len(),
+            None => it.span.end as usize,
+        };
+        self.blank(it.span.start, end as u32);
+    }
+
+    fn visit_ts_as_expression(&mut self, it: &TSAsExpression<'a>) {
+        self.blank(it.expression.span().end, it.span.end);
+        self.visit_expression(&it.expression);
+    }
+
+    fn visit_ts_satisfies_expression(&mut self, it: &TSSatisfiesExpression<'a>) {
+        self.blank(it.expression.span().end, it.span.end);
+        self.visit_expression(&it.expression);
+    }
+
+    fn visit_ts_type_assertion(&mut self, it: &TSTypeAssertion<'a>) {
+        self.blank(it.span.start, it.expression.span().start);
+        self.visit_expression(&it.expression);
+    }
+
+    fn visit_ts_non_null_expression(&mut self, it: &TSNonNullExpression<'a>) {
+        self.blank(it.span.end - 1, it.span.end);
+        self.visit_expression(&it.expression);
+    }
+
+    fn visit_formal_parameter(&mut self, it: &FormalParameter<'a>) {
+        if it.accessibility.is_some() || it.readonly || it.r#override {
+            self.unsupported("constructor parameter properties");
+        }
+        if it.optional && it.type_annotation.is_none() {
+            self.blank_question_after(it.pattern.span().end);
+        }
+        walk::walk_formal_parameter(self, it);
+    }
+
+    fn visit_decorator(&mut self, _it: &Decorator<'a>) {
+        self.unsupported("decorators");
+    }
+
+    fn visit_class(&mut self, it: &Class<'a>) {
+        if it.r#abstract {
+            let class_keyword = self.source[it.span.start as usize..].find("class").unwrap_or(0) as u32;
+            self.blank_modifiers(it.span.start, it.span.start + class_keyword);
+        }
+        if let (Some(first), Some(last)) = (it.implements.first(), it.implements.last()) {
+            let keyword = self.source[..first.span.start as usize].rfind("implements").unwrap_or(first.span.start as usize);
+            self.blank(keyword as u32, last.span.end);
+        }
+        walk::walk_class(self, it);
+    }
+
+    fn visit_ts_class_implements(&mut self, _it: &TSClassImplements<'a>) {}
+
+    fn visit_ts_index_signature(&mut self, it: &TSIndexSignature<'a>) {
+        self.blank_statement(it.span);
+    }
+
+    fn visit_property_definition(&mut self, it: &PropertyDefinition<'a>) {
+        if it.declare || it.r#type == PropertyDefinitionType::TSAbstractPropertyDefinition {
+            return self.blank_statement(it.span);
+        }
+        self.blank_modifiers(it.span.start, it.key.span().start);
+        walk::walk_property_definition(self, it);
+    }
+
+    fn visit_accessor_property(&mut self, it: &AccessorProperty<'a>) {
+        if it.r#type == AccessorPropertyType::TSAbstractAccessorProperty {
+            return self.blank_statement(it.span);
+        }
+        self.blank_modifiers(it.span.start, it.key.span().start);
+        walk::walk_accessor_property(self, it);
+    }
+
+    fn visit_method_definition(&mut self, it: &MethodDefinition<'a>) {
+        // 重载签名与抽象方法没有函数体
+        if it.value.body.is_none() {
+            return self.blank_statement(it.span);
+        }
+        self.blank_modifiers(it.span.start, it.key.span().start);
+        if it.optional {
+            self.blank_question_after(it.key.span().end);
+        }
+        walk::walk_method_definition(self, it);
+    }
+}
+
+// ============================================================================
+// 执行
+// ============================================================================
+
+fn format_exception(ctx: &Ctx<'_>) -> String {
+    let exception = ctx.catch();
+    if let Some(obj) = exception.as_object() {
+        let message: Option<String> = obj.get("message").ok();
+        let stack: Option<String> = obj.get("stack").ok();
+        if let Some(message) = message {
+            return match stack.filter(|s| !s.trim().is_empty()) {
+                Some(stack) => format!("{}\n{}", message, stack.trim_end()),
+                None => message,
+            };
+        }
+    }
+    ctx.json_stringify(exception)
+        .ok()
+        .flatten()
+        .and_then(|s| s.to_string().ok())
+        .unwrap_or_else(|| "Unknown error".to_string())
+}
+
+fn stringify<'js>(ctx: &Ctx<'js>, value: Value<'js>) -> Option<String> {
+    if value.is_undefined() {
+        return None;
+    }
+    if let Some(s) = value.as_string() {
+        return s.to_string().ok().map(|s| serde_json::to_string(&s).unwrap_or(s));
+    }
+    ctx.json_stringify(value).ok().flatten().and_then(|s| s.to_string().ok())
+}
+
+fn install_console<'js>(ctx: &Ctx<'js>, logs: Rc<RefCell<Vec<String>>>) -> rquickjs::Result<()> {
+    let console = Object::new(ctx.clone())?;
+    for (name, prefix) in [("log", ""), ("info", ""), ("debug", ""), ("warn", "[warn] "), ("error", "[error] ")] {
+        let logs = logs.clone();
+        let func = Function::new(ctx.clone(), move |ctx: Ctx<'js>, args: Rest<Value<'js>>| {
+            let line = args.0.into_iter()
+                .map(|v| match v.as_string() {
+                    Some(s) => s.to_string().unwrap_or_default(),
+                    None => stringify(&ctx, v).unwrap_or_else(|| "undefined".to_string()),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            logs.borrow_mut().push(format!("{}{}", prefix, line));
+        })?;
+        console.set(name, func)?;
+    }
+    ctx.globals().set("console", console)
+}
+
+/// 在沙箱中同步执行片段（阻塞，调用方应放在 `spawn_blocking` 中）
+pub fn eval_snippet(snippet: &str, typescript: bool, timeout: Duration) -> JsEvalResult {
+    let start = Instant::now();
+    let failed = |error: String| JsEvalResult {
+        success: false,
+        result: None,
+        logs: Vec::new(),
+        error: Some(error),
+        elapsed_ms: start.elapsed().as_millis() as u64,
+        timed_out: false,
+    };
+
+    if snippet.chars().count() > MAX_SNIPPET_CHARS {
+        return failed(format!("Snippet exceeds {} characters", MAX_SNIPPET_CHARS));
+    }
+    let source = if typescript {
+        match strip_typescript(snippet) {
+            Ok(source) => source,
+            Err(e) => return failed(e),
+        }
+    } else {
+        snippet.to_string()
+    };
+
+    let runtime = match Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => return failed(format!("Failed to create runtime: {}", e)),
+    };
+    runtime.set_memory_limit(MEMORY_LIMIT_BYTES);
+    runtime.set_max_stack_size(MAX_STACK_BYTES);
+    let deadline = start + timeout;
+    runtime.set_interrupt_handler(Some(Box::new(move || Instant::now() > deadline)));
+
+    let context = match Context::full(&runtime) {
+        Ok(ctx) => ctx,
+        Err(e) => return failed(format!("Failed to create context: {}", e)),
+    };
+
+    let logs = Rc::new(RefCell::new(Vec::new()));
+    let outcome: Result<Option<String>, String> = context.with(|ctx| {
+        install_console(&ctx, logs.clone()).map_err(|e| e.to_string())?;
+        let value: Value = ctx.eval(source.as_str()).catch(&ctx).map_err(|e| e.to_string())?;
+
+        // 执行 Promise 任务队列
+        while Instant::now() <= deadline && ctx.execute_pending_job() {}
+
+        match value.as_promise().cloned() {
+            Some(promise) => match promise.result::<Value>() {
+                Some(Ok(v)) => Ok(stringify(&ctx, v)),
+                Some(Err(_)) => Err(format_exception(&ctx)),
+                None => Err("Promise did not settle".to_string()),
+            },
+            None => Ok(stringify(&ctx, value)),
+        }
+    });
+
+    let timed_out = Instant::now() > deadline;
+    let mut logs = logs.take();
+    let mut total = 0;
+    logs.retain(|line| {
+        total += line.len();
+        total <= MAX_OUTPUT_CHARS
+    });
+
+    match outcome {
+        Ok(result) => JsEvalResult {
+            success: true,
+            result: result.map(|r| r.chars().take(MAX_OUTPUT_CHARS).collect()),
+            logs,
+            error: None,
+            elapsed_ms: start.elapsed().as_millis() as u64,
+            timed_out: false,
+        },
+        Err(error) => JsEvalResult {
+            success: false,
+            result: None,
+            logs,
+            error: Some(if timed_out { format!("Execution timed out after {}ms", timeout.as_millis()) } else { error }),
+            elapsed_ms: start.elapsed().as_millis() as u64,
+            timed_out,
+        },
+    }
+}
+
+/// 异步执行入口
+pub async fn eval_js(snippet: String, language: Option<String>, timeout_ms: Option<u64>) -> Result<JsEvalResult, String> {
+    let typescript = matches!(language.as_deref(), Some("ts") | Some("typescript"));
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).min(MAX_TIMEOUT_MS));
+    tokio::task::spawn_blocking(move || eval_snippet(&snippet, typescript, timeout))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))
+}
+
+/// 转换为适合模型阅读的文本
+pub fn format_for_model(result: &JsEvalResult) -> String {
+    let mut out = String::new();
+    if !result.logs.is_empty() {
+        out.push_str(&format!("console:\n{}\n", result.logs.join("\n")));
+    }
+    match (&result.error, &result.result) {
+        (Some(error), _) => out.push_str(&format!("error: {}\n", error)),
+        (None, Some(value)) => out.push_str(&format!("result: {}\n", value)),
+        (None, None) => out.push_str("result: undefined\n"),
+    }
+    out.push_str(&format!("({}ms)", result.elapsed_ms));
+    out
+}
+
+/// 在沙箱中执行 JS/TS 片段（无文件系统与网络访问）
+#[tauri::command]
+pub async fn agent_eval_js(
+    snippet: String,
+    language: Option<String>,
+    timeout_ms: Option<u64>,
+) -> Result<JsEvalResult, String> {
+    eval_js(snippet, language, timeout_ms).await
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: &str) -> JsEvalResult {
+        eval_snippet(code, false, Duration::from_millis(DEFAULT_TIMEOUT_MS))
+    }
+
+    #[test]
+    fn test_eval_result_and_logs() {
+        let result = run("const xs = [3, 1, 2]; console.log('sorting', xs.length); xs.sort((a, b) => a - b)");
+        assert!(result.success);
+        assert_eq!(result.result.as_deref(), Some("[1,2,3]"));
+        assert_eq!(result.logs, vec!["sorting 3"]);
+    }
+
+    #[test]
+    fn test_eval_error_and_no_host_access() {
+        let result = run("throw new Error('boom')");
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("boom"));
+
+        let result = run("typeof require + typeof fetch + typeof std + typeof os");
+        assert_eq!(result.result.as_deref(), Some("\"undefinedundefinedundefinedundefined\""));
+    }
+
+    #[test]
+    fn test_eval_timeout() {
+        let result = eval_snippet("while (true) {}", false, Duration::from_millis(100));
+        assert!(!result.success);
+        assert!(result.timed_out);
+    }
+
+    #[test]
+    fn test_eval_promise() {
+        let result = run("Promise.resolve(21).then(x => ({ answer: x * 2 }))");
+        assert_eq!(result.result.as_deref(), Some("{\"answer\":42}"));
+    }
+
+    #[test]
+    fn test_typescript() {
+        let ts = "interface Point { x: number; y: number }\ntype Id = string;\nfunction dist<T>(a: Point, b: Point): number {\n  return Math.hypot(a.x - b.x, a.y - b.y);\n}\nconst p = { x: 3, y: 4 } as Point;\nconst origin = { x: p.x - p.x, y: 0 };\nconst f = (q: Point): number => q.x > 0 ? dist(q, origin) : 0;\nf(p)";
+        let result = eval_snippet(ts, true, Duration::from_millis(DEFAULT_TIMEOUT_MS));
+        assert_eq!(result.result.as_deref(), Some("5"), "{:?}", result.error);
+    }
+
+    #[test]
+    fn test_typescript_keeps_strings_and_destructuring() {
+        let ts = "const label: string = 'x as Foo, {a: b}';\nfunction pick({ a: renamed, b = 2 }: { a: number; b?: number }, c?): number[] {\n  return [renamed, b];\n}\n`${label as string}|${pick({ a: 1 })}`";
+        let result = eval_snippet(ts, true, Duration::from_millis(DEFAULT_TIMEOUT_MS));
+        assert_eq!(result.result.as_deref(), Some("\"x as Foo, {a: b}|1,2\""), "{:?}", result.error);
+    }
+
+    #[test]
+    fn test_typescript_classes() {
+        let ts = "abstract class Shape<T> implements Named {\n  private readonly sides!: number;\n  declare tag: string;\n  abstract area(): number;\n  public describe(this: Shape<T>, unit?: string): string { return `${this.area()}${unit!}`; }\n}\nclass Square extends Shape<number> {\n  constructor(public_size: number) { super(); this.size = public_size; }\n  size?: number;\n  area() { return this.size! ** 2; }\n}\nnew Square(3).describe('m2')";
+        let result = eval_snippet(ts, true, Duration::from_millis(DEFAULT_TIMEOUT_MS));
+        assert_eq!(result.result.as_deref(), Some("\"9m2\""), "{:?}", result.error);
+    }
+
+    #[test]
+    fn test_typescript_rejects_compiled_syntax() {
+        assert!(strip_typescript("enum Color { Red }").unwrap_err().contains("enum"));
+        assert!(strip_typescript("class A { constructor(private x: number) {} }").unwrap_err().contains("parameter properties"));
+        assert!(strip_typescript("const x: = 1").unwrap_err().contains("parse error"));
+        let stripped = strip_typescript("declare const x: number;\nlet y = 1").unwrap();
+        assert_eq!(stripped, format!(";{}\nlet y = 1", " ".repeat(23)));
+    }
+}
@@ -0,0 +1,161 @@
+/*!
+Language Map - 文件扩展名到语言的映射
+=====================================
+
+符号提取、导出代码块标记、测试生成、错误分析等处共用的语言识别：
+
+- 内置映射覆盖常见扩展名（含 `.mjs`、`.svelte`、`.vue`、`.proto` 等）
+- `.ifai/IFAI.md` 中的 `languages` 可追加/覆盖扩展名映射，
+  并按 glob 为单个文件指定语言（如 `Jenkinsfile: groovy`）
+
+```yaml
+languages:
+  extensions:
+    inc: php
+  files:
+    "*.tpl": html
+```
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 内置扩展名映射
+const DEFAULT_EXTENSIONS: &[(&str, &str)] = &[
+    ("rs", "rust"),
+    ("ts", "typescript"), ("tsx", "typescript"), ("mts", "typescript"), ("cts", "typescript"),
+    ("js", "javascript"), ("jsx", "javascript"), ("mjs", "javascript"), ("cjs", "javascript"),
+    ("py", "python"), ("pyi", "python"),
+    ("go", "go"),
+    ("java", "java"),
+    ("kt", "kotlin"), ("kts", "kotlin"),
+    ("swift", "swift"),
+    ("c", "c"), ("h", "c"),
+    ("cc", "cpp"), ("cpp", "cpp"), ("cxx", "cpp"), ("hpp", "cpp"), ("hh", "cpp"),
+    ("cs", "csharp"),
+    ("rb", "ruby"),
+    ("php", "php"),
+    ("vue", "vue"),
+    ("svelte", "svelte"),
+    ("proto", "protobuf"),
+    ("sh", "shell"), ("bash", "shell"), ("zsh", "shell"),
+    ("sql", "sql"),
+    ("html", "html"), ("htm", "html"),
+    ("css", "css"), ("scss", "scss"),
+    ("md", "markdown"),
+    ("json", "json"),
+    ("yml", "yaml"), ("yaml", "yaml"),
+    ("toml", "toml"),
+    ("lua", "lua"),
+    ("dart", "dart"),
+    ("scala", "scala"),
+];
+
+/// IFAI.md 中的 `languages` 配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LanguageMapConfig {
+    /// 扩展名（可带或不带 `.`）-> 语言
+    pub extensions: HashMap<String, String>,
+    /// 相对项目根目录的 glob -> 语言，优先于扩展名
+    pub files: HashMap<String, String>,
+}
+
+/// 合并内置映射与项目配置后的语言表
+#[derive(Debug, Clone)]
+pub struct LanguageMap {
+    extensions: HashMap<String, String>,
+    files: Vec<(glob::Pattern, String)>,
+}
+
+impl Default for LanguageMap {
+    fn default() -> Self {
+        Self {
+            extensions: DEFAULT_EXTENSIONS.iter().map(|(ext, lang)| (ext.to_string(), lang.to_string())).collect(),
+            files: Vec::new(),
+        }
+    }
+}
+
+fn normalize_ext(ext: &str) -> String {
+    ext.trim().trim_start_matches('.').to_lowercase()
+}
+
+impl LanguageMap {
+    pub fn with_config(config: &LanguageMapConfig) -> Self {
+        let mut map = Self::default();
+        for (ext, lang) in &config.extensions {
+            map.extensions.insert(normalize_ext(ext), lang.trim().to_string());
+        }
+        for (pattern, lang) in &config.files {
+            match glob::Pattern::new(pattern) {
+                Ok(p) => map.files.push((p, lang.trim().to_string())),
+                Err(e) => eprintln!("[LanguageMap] Invalid file pattern '{}': {}", pattern, e),
+            }
+        }
+        // 更长（更具体）的模式优先匹配
+        map.files.sort_by(|a, b| b.0.as_str().len().cmp(&a.0.as_str().len()).then_with(|| a.0.as_str().cmp(b.0.as_str())));
+        map
+    }
+
+    /// 加载项目配置（没有配置时只使用内置映射）
+    pub fn load(project_root: &str) -> Self {
+        crate::project_config::load_project_config_sync(project_root)
+            .and_then(|c| c.languages)
+            .map(|config| Self::with_config(&config))
+            .unwrap_or_default()
+    }
+
+    pub fn for_extension(&self, ext: &str) -> Option<&str> {
+        self.extensions.get(&normalize_ext(ext)).map(|s| s.as_str())
+    }
+
+    /// 识别文件语言：先匹配文件级覆盖，再按扩展名
+    pub fn for_path(&self, path: &str) -> Option<&str> {
+        let path = path.replace('\\', "/");
+        let file_name = path.rsplit('/').next().unwrap_or(&path);
+        let overridden = self.files.iter()
+            .find(|(pattern, _)| pattern.matches(&path) || pattern.matches(file_name))
+            .map(|(_, lang)| lang.as_str());
+        overridden.or_else(|| Path::new(file_name).extension().and_then(|e| e.to_str()).and_then(|ext| self.for_extension(ext)))
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 识别文件语言（`path` 相对项目根目录）
+#[tauri::command]
+pub fn detect_file_language(project_root: String, path: String) -> Option<String> {
+    LanguageMap::load(&project_root).for_path(&path).map(|s| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults() {
+        let map = LanguageMap::default();
+        assert_eq!(map.for_extension("mjs"), Some("javascript"));
+        assert_eq!(map.for_extension(".Svelte"), Some("svelte"));
+        assert_eq!(map.for_path("src/api/user.proto"), Some("protobuf"));
+        assert_eq!(map.for_path("Makefile"), None);
+    }
+
+    #[test]
+    fn test_project_overrides() {
+        let config: LanguageMapConfig = serde_yaml::from_str(
+            "extensions:\n  .inc: php\n  vue: html\nfiles:\n  Jenkinsfile: groovy\n  \"scripts/*.js\": typescript\n",
+        ).unwrap();
+        let map = LanguageMap::with_config(&config);
+
+        assert_eq!(map.for_path("lib/header.inc"), Some("php"));
+        assert_eq!(map.for_path("App.vue"), Some("html"));
+        assert_eq!(map.for_path("ci/Jenkinsfile"), Some("groovy"));
+        assert_eq!(map.for_path("scripts/build.js"), Some("typescript"));
+        assert_eq!(map.for_path("src/index.js"), Some("javascript"));
+    }
+}
@@ -27,12 +27,84 @@ mod token_counter; // v0.2.6 新增：Token 计数模块
 mod openspec; // v0.2.6 新增：OpenSpec 集成
 mod multimodal; // v0.3.0 新增：多模态功能
 mod tool_classification; // v0.3.3 新增：工具分类系统
+mod workspace_profiles; // v0.3.4 新增：Monorepo 子包上下文配置
+mod failed_requests; // v0.3.4 新增：失败请求记录与回放
+mod secret_scrub; // v0.3.4 新增：凭证脱敏
+mod web_fetch; // v0.3.4 新增：网页抓取（agent_fetch_url 与错误文档共用）
+mod slash_commands; // v0.3.4 新增：斜杠命令框架
+mod paste_enrichment; // v0.3.4 新增：粘贴内容增强
+mod partial_json; // v0.3.4 新增：流式工具参数增量解析
+mod quick_answer; // v0.3.4 新增：限时快速回答模式
+mod temperature_schedule; // v0.3.4 新增：对话级温度调度
+mod events; // v0.3.4 新增：前端事件结构与版本
+mod fs_retry; // v0.3.4 新增：文件系统瞬时错误重试
+mod idle_manager; // v0.3.4 新增：空闲资源回收
+mod agent_log; // v0.3.4 新增：Agent 分级日志与运行记录
+mod commit_risk; // v0.3.4 新增：提交前风险分析
+mod update_check; // v0.3.4 新增：版本更新检查
+mod js_sandbox; // v0.3.4 新增：沙箱化 JS/TS 片段执行
+mod recent_projects; // v0.3.4 新增：最近项目列表与快速统计
+mod project_init; // v0.3.4 新增：.ifai 目录初始化
+mod diff_rag; // v0.3.4 新增：基于 Git 引用差异的检索上下文
+mod tool_output; // v0.3.4 新增：长工具输出摘要
+mod notifications; // v0.3.4 新增：长时间操作的系统通知
+mod privacy; // v0.3.4 新增：会话隐私级别
+mod selection_context; // v0.3.4 新增：编辑器选区上下文
+mod editor_context; // v0.3.4 新增：编辑器上下文（@selection / @file / @clipboard）
+mod mention_context; // v0.3.4 新增：@file: / @folder: / @symbol: 定向提及展开
+mod job_queue; // v0.3.4 新增：后台任务队列
+mod completion_cache; // v0.3.4 新增：流式响应缓存
+mod anthropic_api; // v0.3.4 新增：Anthropic Messages 协议
+mod blob_store; // v0.3.4 新增：内容寻址的大对象存储
+mod auto_rag; // v0.3.4 新增：自适应 RAG 触发
+mod gemini_api; // v0.3.4 新增：Gemini generateContent 协议
+mod tool_capability; // v0.3.4 新增：模型工具调用能力探测与降级
+mod janitor; // v0.3.4 新增：启动时清理遗留临时文件
+mod language_map; // v0.3.4 新增：扩展名/文件级语言映射
+mod file_edit; // v0.3.4 新增：search/replace 与 diff 局部编辑
+mod provider_sharing; // v0.3.4 新增：Provider 配置导入/导出
+mod context_plan; // v0.3.4 新增：上下文预算分配说明
+mod safe_mode; // v0.3.4 新增：安全模式启动
+mod agent_artifacts; // v0.3.4 新增：Agent 运行产物
+mod cancellation; // v0.3.4 新增：AI 请求与 Agent 运行取消
+mod ai_retry; // v0.3.4 新增：AI 请求退避重试
+mod tool_permissions; // v0.3.4 新增：按工具类别的项目级授权
+mod verbosity; // v0.3.4 新增：回复长度与详略控制
+mod glossary; // v0.3.4 新增：项目术语表
+#[cfg(test)]
+mod symbol_accuracy; // v0.3.4 新增：符号提取准确率评测（黄金文件比对）
+mod model_registry; // v0.3.4 新增：本地 GGUF 模型目录与多模型管理
+mod embedding; // v0.3.4 新增：可切换的文本向量模型
+mod vector_index; // v0.3.4 新增：HNSW 近似最近邻索引
+mod code_chunker; // v0.3.4 新增：按语法结构切分代码
+mod rerank; // v0.3.4 新增：检索结果重排序
+mod ifai_ignore; // v0.3.4 新增：.ifaiignore 索引排除规则
+mod symbol_references; // v0.3.4 新增：符号跨文件引用解析
+mod checkpoints; // v0.3.4 新增：Agent 文件修改检查点与回滚
+mod diff_preview; // v0.3.4 新增：写入前的差异预览与逐块审批
+mod approval_policy; // v0.3.4 新增：Agent 工具调用的自动审批策略
+mod agent_budget; // v0.3.4 新增：Agent 运行预算与 Token 用量统计
+mod background_processes; // v0.3.4 新增：开发服务器等后台进程的托管与停止
+mod command_safety; // v0.3.4 新增：shell 命令执行前的安全分析
+mod formatter; // v0.3.4 新增：代码格式化（rustfmt / prettier / black / gofmt）
+mod prompt_cache; // v0.3.4 新增：提示词缓存断点与缓存命中统计
+mod usage_meter; // v0.3.4 新增：用量计量与费用估算
+mod request_options; // v0.3.4 新增：AI 请求选项（取消、重试、详略、温度、用量归属）
+mod prompt_watch; // v0.3.4 新增：提示词与 IFAI.md 热重载
 
 // LLM inference using llama.cpp (GGUF native support)
 // Phase 1: placeholder module, Phase 2: actual implementation
 #[cfg(feature = "llm-inference")]
 pub mod llm_inference;
 
+// 未启用推理时只提供配置类型（采样参数出现在命令签名与分类配置中）
+#[cfg(not(feature = "llm-inference"))]
+pub mod llm_inference {
+    #[path = "config.rs"]
+    pub mod config;
+    pub use config::{LlmInferenceConfig, SamplingParams};
+}
+
 #[cfg(feature = "commercial")]
 mod commercial;
 
@@ -56,23 +128,6 @@ fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
 }
 
-/// Smart RAG detection: Check if user query is code-related
-fn should_use_rag(text: &str) -> bool {
-    let code_keywords = [
-        // Chinese keywords
-        "代码", "文件", "函数", "类", "接口", "模块", "实现", "逻辑",
-        "如何工作", "在哪", "在哪里", "bug", "错误", "项目", "这个项目",
-        "怎么", "如何", "为什么", "哪里",
-        // English keywords
-        "code", "file", "function", "class", "interface", "module",
-        "implementation", "logic", "how does", "where is", "locate",
-        "bug", "error", "project", "this project",
-        "what", "how", "why", "where",
-    ];
-
-    code_keywords.iter().any(|kw| text.contains(kw))
-}
-
 /// 本地工具执行器（兼容社区版和商业版）
 pub async fn execute_local_tool(
     tool_name: &str,
@@ -118,6 +173,15 @@ pub async fn execute_local_tool(
                 Err(e) => format!("错误: {}", e)
             }
         }
+        "agent_edit_file" => {
+            let rel_path = args["rel_path"].as_str().unwrap_or("");
+            let edits = serde_json::from_value(args["edits"].clone()).ok();
+            let diff = args["diff"].as_str().map(|s| s.to_string());
+            match core_wrappers::agent_edit_file(project_root.to_string(), rel_path.to_string(), edits, diff).await {
+                Ok(json_result) => json_result,
+                Err(e) => format!("错误: {}", e)
+            }
+        }
         "agent_batch_read" => {
             if let Some(paths_array) = args["paths"].as_array() {
                 let paths: Vec<String> = paths_array.iter()
@@ -167,26 +231,58 @@ pub async fn execute_local_tool(
 async fn ai_chat(
     app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
-    provider_config: core_traits::ai::AIProviderConfig,
+    mut provider_config: core_traits::ai::AIProviderConfig,
     mut messages: Vec<core_traits::ai::Message>,
     event_id: String,
     enable_tools: Option<bool>,
     project_root: Option<String>,
+    scope_path: Option<String>,
+    session_id: Option<String>,
 ) -> Result<(), String> {
     println!("[AI Chat] Entry - project_root: {:?}, event_id: {}", project_root, event_id);
     println!("[AI Chat] Received {} messages", messages.len());
+    idle_manager::touch();
+    // v0.3.4: 登记取消令牌，`cancel_ai_request` 可中断本次请求
+    let cancel = cancellation::register(&event_id);
 
     // Ensure all messages have unique IDs
     // Sanitize messages
     ai_utils::sanitize_messages(&mut messages);
     println!("[AI Chat] After sanitize: {} messages", messages.len());
 
+    // v0.3.4: 工作流录制（记录最后一条用户消息）
+    if let Some(core_traits::ai::Content::Text(text)) = messages.iter().filter(|m| m.role == "user").last().map(|m| &m.content) {
+        commands::workflow_commands::record_step(
+            commands::workflow_commands::WorkflowAction::Chat,
+            json!({ "message": text }),
+        );
+    }
+
+    // v0.3.4: IFAI.md 中为对话指定的模型
+    if let Some(ref root) = project_root {
+        project_config::apply_model_route(root, project_config::ModelTask::Chat, &mut provider_config);
+    }
+
+    // v0.3.4: 记录上下文预算分配，随请求发送 `{event_id}_context_plan`
+    let plan_model = provider_config.models.first().cloned().unwrap_or_default();
+    let plan_budget = project_root.as_deref()
+        .map(project_config::max_context_tokens)
+        .unwrap_or_else(|| conversation::SummarizationConfig::default().max_tokens);
+    let mut planner = context_plan::ContextPlanner::new(&plan_model, plan_budget);
+    let mut system_planned = false;
+
     if let Some(ref root) = project_root {
         let root_clone = root.clone();
+        system_planned = true;
 
         // 1. Detect @codebase query or smart RAG trigger
         let mut codebase_query = None;
+        // v0.3.4: 自动触发的检索使用更小的预算，并在引用中标记
+        let auto_rag_config = auto_rag::load_config();
+        let mut auto_rag_decision = None;
         if let Some(last_msg) = messages.iter().filter(|m| m.role == "user").last() {
+             let symbol_state = app.try_state::<Arc<std::sync::Mutex<SymbolIndexState>>>();
+             let symbol_guard = symbol_state.as_ref().and_then(|s| s.lock().ok());
              match &last_msg.content {
                 core_traits::ai::Content::Text(text) => {
                      let lower_text = text.to_lowercase();
@@ -198,10 +294,10 @@ async fn ai_chat(
                             codebase_query = Some(if final_query.is_empty() { "overview of the project structure and main logic".to_string() } else { final_query });
                         }
                     }
-                    // Priority 2: Smart RAG detection (if enabled in settings)
-                    // Note: For now we enable by default, can be controlled via provider_config in future
-                    else if should_use_rag(&lower_text) {
-                        println!("[AI Chat] Smart RAG triggered for query: {}", text);
+                    // Priority 2: Auto RAG (heuristic, configurable)
+                    else if let Some(decision) = auto_rag::decide(text, &auto_rag_config, symbol_guard.as_deref()) {
+                        println!("[AI Chat] Auto RAG triggered for query: {}", text);
+                        auto_rag_decision = Some(decision);
                         codebase_query = Some(text.to_string());
                     }
                 }
@@ -222,41 +318,113 @@ async fn ai_chat(
                             codebase_query = Some(if final_query.is_empty() { "overview of the project structure and main logic".to_string() } else { final_query });
                         }
                     }
-                    // Priority 2: Smart RAG detection
-                    else if should_use_rag(&lower_text) {
-                        println!("[AI Chat] Smart RAG triggered for query: {}", combined_text);
+                    // Priority 2: Auto RAG
+                    else if let Some(decision) = auto_rag::decide(&combined_text, &auto_rag_config, symbol_guard.as_deref()) {
+                        println!("[AI Chat] Auto RAG triggered for query: {}", combined_text);
+                        auto_rag_decision = Some(decision);
                         codebase_query = Some(combined_text);
                     }
                 }
             };
         }
 
+        // v0.3.4: `@diff base...head` 将检索范围限定为两个引用之间的改动
+        let diff_scope = messages.iter()
+            .filter(|m| m.role == "user")
+            .last()
+            .and_then(|m| diff_rag::parse_diff_mention(&intelligence_router::extract_text_content(&m.content)));
+        if diff_scope.is_some() {
+            codebase_query = None;
+        }
+
+        // v0.3.4: 安全模式不加载索引，跳过检索
+        if safe_mode::is_enabled() {
+            codebase_query = None;
+        }
+        let diff_scope = diff_scope.filter(|_| !safe_mode::is_enabled());
+
+        // v0.3.4: strict 隐私级别下不自动注入任何项目内容
+        let privacy_level = privacy::level_for_session(session_id.as_deref());
+        if !privacy_level.allows_project_content() && (codebase_query.is_some() || diff_scope.is_some()) {
+            planner.dropped(context_plan::ContextSection::Rag, "Project context withheld by the session privacy level", 0);
+        }
+        let diff_scope = diff_scope.filter(|_| privacy_level.allows_project_content());
+        if !privacy_level.allows_project_content() {
+            codebase_query = None;
+        }
+
+        // v0.3.4: `@file:` / `@folder:` / `@symbol:` 定向提及在后端展开；有定向提及时不再自动检索
+        let mention_context = messages.iter()
+            .filter(|m| m.role == "user")
+            .last()
+            .filter(|_| privacy_level.allows_project_content())
+            .and_then(|m| {
+                let index_state = app.try_state::<Arc<std::sync::Mutex<SymbolIndexState>>>();
+                let index_guard = index_state.as_ref().and_then(|s| s.lock().ok());
+                mention_context::resolve(root, &intelligence_router::extract_text_content(&m.content), index_guard.as_deref())
+            });
+        if let Some(ref mentioned) = mention_context {
+            if auto_rag_decision.is_some() {
+                codebase_query = None;
+            }
+            let _ = app.emit(&format!("{}_mention_references", event_id), &mentioned.references);
+        }
+        let auto_rag_budget = auto_rag_decision.filter(|_| codebase_query.is_some()).map(|_| auto_rag_config.context_budget);
+        let auto_rag_max_references = auto_rag_config.max_references;
+
         // 2. RAG Context Building (Parallel)
         let app_handle = app.clone();
         let rag_service = state.rag_service.clone();
         let event_id_for_rag = event_id.clone();
         let root_for_rag = root.clone();
+        let provider_for_rerank = provider_config.clone();
         
         // Clone messages for summarization to avoid move
         let mut messages_for_summarize = messages.clone();
         
         // Define futures for parallel execution
         let rag_task = async move {
-            if let Some(query) = codebase_query {
+            if let Some((scope, query)) = diff_scope {
+                println!("[AI Chat] Diff RAG: {}..{} for query: {}", scope.base, scope.head, query);
+                let root_for_diff = root_for_rag.clone();
+                match tokio::task::spawn_blocking(move || diff_rag::build_diff_context(&root_for_diff, &scope, &query)).await {
+                    Ok(Ok(rag_result)) => {
+                        let _ = app_handle.emit(&format!("{}_references", event_id_for_rag), &rag_result.references);
+                        Some(rag_result.context)
+                    }
+                    Ok(Err(e)) => {
+                        eprintln!("[AI Chat] Diff RAG failed: {}", e);
+                        None
+                    }
+                    Err(e) => {
+                        eprintln!("[AI Chat] Diff RAG task failed: {}", e);
+                        None
+                    }
+                }
+            } else if let Some(query) = codebase_query {
                  println!("[AI Chat] Parallel RAG: Starting context build for query: {}", query);
 
                  // Note: initialization check is implicit in retrieve_context logic in Commercial impl
                  // or skipped in Community impl.
 
                  // Add timeout to prevent blocking indefinitely
-                 let retrieve_future = rag_service.retrieve_context(&query, &root_for_rag);
+                 // v0.3.4: 有 HNSW 索引时优先使用（见 vector_index）
+                 let retrieve_future = vector_index::retrieve_context(rag_service.as_ref(), &query, &root_for_rag);
                  let timeout_duration = std::time::Duration::from_secs(30);
 
                  match tokio::time::timeout(timeout_duration, retrieve_future).await {
                     Ok(Ok(rag_result)) => {
                         println!("[AI Chat] RAG context built successfully with {} references", rag_result.references.len());
-                        let _ = app_handle.emit(&format!("{}_references", event_id_for_rag), &rag_result.references);
-                        let _ = app_handle.emit("codebase-references", rag_result.references);
+                        // v0.3.4: 组装上下文前按相关性重排序
+                        let rag_result = rerank::rerank_result(&rerank::load_config(), &provider_for_rerank, &query, rag_result).await;
+                        if auto_rag_budget.is_some() {
+                            let references = auto_rag::mark_references(&rag_result.references, auto_rag_max_references);
+                            let _ = app_handle.emit(&format!("{}_references", event_id_for_rag), &references);
+                            let _ = app_handle.emit("codebase-references", references);
+                        } else {
+                            let _ = app_handle.emit(&format!("{}_references", event_id_for_rag), &rag_result.references);
+                            let _ = app_handle.emit("codebase-references", rag_result.references);
+                        }
                         Some(rag_result.context)
                     },
                     Ok(Err(e)) => {
@@ -290,12 +458,75 @@ async fn ai_chat(
         let (rag_context, updated_messages): (Option<String>, Vec<_>) = tokio::join!(rag_task, summarize_task);
         
         // Update messages with summarized version
+        if updated_messages.len() != messages.len() {
+            let before = conversation::token_counter::count_messages_tokens_for_model(&messages, &plan_model);
+            let after = conversation::token_counter::count_messages_tokens_for_model(&updated_messages, &plan_model);
+            planner.compressed(
+                context_plan::ContextSection::History,
+                format!("Conversation summarized: {} messages compacted to {}", messages.len(), updated_messages.len()),
+                before,
+                after,
+            );
+        }
         messages = updated_messages;
 
         // Insert Main System Prompt
-        let mut final_system_prompt = prompt_manager::get_main_system_prompt(&root);
+        // v0.3.4: 作用域为 monorepo 子包时，使用该包的上下文配置代替仓库级默认值
+        let mut final_system_prompt = prompt_manager::get_main_system_prompt_for_scope(&root, scope_path.as_deref());
+        planner.add(context_plan::ContextSection::System, &final_system_prompt);
+
+        // v0.3.4: 按最近对话涉及的语言附加语言规范
+        let recent_user_texts: Vec<String> = messages.iter()
+            .rev()
+            .filter(|m| m.role == "user")
+            .take(4)
+            .map(|m| intelligence_router::extract_text_content(&m.content))
+            .collect();
+        let recent_refs: Vec<&str> = recent_user_texts.iter().map(|t| t.as_str()).collect();
+        if let Some(addendum) = prompt_manager::languages::language_addendum(&root, &recent_refs, prompt_manager::languages::DEFAULT_ADDENDUM_BUDGET) {
+            planner.add(context_plan::ContextSection::System, &addendum);
+            final_system_prompt.push_str(&addendum);
+        }
+
+        // v0.3.4: 问题中出现的项目术语附加释义（术语表由 `build_glossary` 生成）
+        if privacy_level.allows_project_content() {
+            if let Some(section) = recent_user_texts.first().and_then(|text| glossary::prompt_section(&root, text)) {
+                planner.add(context_plan::ContextSection::System, &section);
+                final_system_prompt.push_str(&section);
+            }
+        }
+
+        // v0.3.4: 附加粘贴内容中解析到的项目代码片段
+        if let Some(paste_context) = session_id.as_deref().and_then(paste_enrichment::take_pending_context).filter(|_| privacy_level.allows_project_content()) {
+            planner.add(context_plan::ContextSection::Pinned, &paste_context);
+            final_system_prompt.push_str("\n\n");
+            final_system_prompt.push_str(&paste_context);
+        }
+
+        // v0.3.4: 定向提及的文件 / 目录 / 符号内容
+        if let Some(mentioned) = mention_context {
+            planner.add(context_plan::ContextSection::Pinned, &mentioned.context);
+            final_system_prompt.push_str("\n\n");
+            final_system_prompt.push_str(&mentioned.context);
+        }
+
+        // v0.3.4: 编辑器状态（前端通过 set_editor_context / set_active_selection 上报）：
+        // 展开 `@selection` / `@file` / `@clipboard`，消息指代选中的代码时注入选区（校验内容未变）
+        if privacy_level.allows_project_content() {
+            if let Some(last_user) = recent_user_texts.first() {
+                let editor = app.try_state::<editor_context::EditorContextState>().map(|s| s.snapshot()).unwrap_or_default();
+                let index_state = app.try_state::<Arc<std::sync::Mutex<SymbolIndexState>>>();
+                let index_guard = index_state.as_ref().and_then(|s| s.lock().ok());
+                if let Some(section) = editor_context::context_for_message(&root, &editor, last_user, index_guard.as_deref()) {
+                    planner.add(context_plan::ContextSection::Pinned, &section);
+                    final_system_prompt.push_str("\n\n");
+                    final_system_prompt.push_str(&section);
+                }
+            }
+        }
         
         // 注入工具定义兜底：确保模型即便没收到 tools 参数，也能通过提示词学会调用
+        let tools_start = final_system_prompt.len();
         final_system_prompt.push_str("\n\n# ADDITIONAL TOOLS AVAILABLE\n");
         final_system_prompt.push_str("You also have access to the following tool. You MUST use it by outputting a standard tool call JSON:\n");
         final_system_prompt.push_str(r#"
@@ -304,16 +535,26 @@ async fn ai_chat(
   parameters: { "command": "string", "working_dir": "string (optional)" }
   example: {"name": "bash", "arguments": {"command": "ls -la"}}
 "#);
+        planner.add(context_plan::ContextSection::Tools, &final_system_prompt[tools_start..]);
 
+        // v0.3.4: 项目上下文作为单独的 system 消息放在主提示词之后，上下文变化时主提示词仍能命中提示词缓存
+        let mut context_prompt = None;
         if let Some(context) = rag_context {
-             if !context.is_empty() {
+             let original_tokens = planner.count(&context);
+             if let Some(budget) = auto_rag_budget.filter(|_| !context.is_empty()) {
+                let rendered = auto_rag::render_context(&context, budget);
+                planner.add(context_plan::ContextSection::Rag, &rendered);
+                planner.compressed(context_plan::ContextSection::Rag, format!("Auto-retrieved context truncated to {} chars", budget), original_tokens, planner.count(&rendered));
+                context_prompt = Some(rendered.trim_start().to_string());
+             } else if !context.is_empty() {
                 let truncated_context = if context.len() > 12000 {
                     format!("{}... [Context Truncated]", &context[..12000])
                 } else {
                     context
                 };
-                final_system_prompt.push_str("\n\nProject Context:\n");
-                final_system_prompt.push_str(&truncated_context);
+                planner.add(context_plan::ContextSection::Rag, &truncated_context);
+                planner.compressed(context_plan::ContextSection::Rag, "Project context truncated to 12000 chars", original_tokens, planner.count(&truncated_context));
+                context_prompt = Some(format!("Project Context:\n{}", truncated_context));
              }
         }
 
@@ -347,6 +588,7 @@ async fn ai_chat(
 
         // Re-insert Summary if found
         if let Some(summary) = summary_message {
+            planner.add_message(context_plan::ContextSection::Summary, &summary);
             // Insert after the main system prompt
             if messages.len() > 0 {
                 messages.insert(1, summary);
@@ -354,6 +596,16 @@ async fn ai_chat(
                 messages.push(summary);
             }
         }
+
+        if let Some(context) = context_prompt.filter(|c| !c.trim().is_empty()) {
+            let position = messages.iter().take_while(|m| m.role == "system").count();
+            messages.insert(position, core_traits::ai::Message {
+                role: "system".to_string(),
+                content: core_traits::ai::Content::Text(context),
+                tool_calls: None,
+                tool_call_id: None,
+            });
+        }
     }
 
     ai_utils::sanitize_messages(&mut messages);
@@ -379,6 +631,8 @@ async fn ai_chat(
     let preprocess_result = if has_image {
         // 如果有图片，不使用本地模型
         Err("Image content detected, routing to cloud Vision LLM".to_string())
+    } else if safe_mode::is_enabled() {
+        Err("Safe mode: local model disabled".to_string())
     } else {
         local_model::local_model_preprocess(messages.clone()).await
     };
@@ -392,6 +646,16 @@ async fn ai_chat(
             println!("  - tool_calls: {:?}", result.tool_calls.iter().map(|t| &t.name).collect::<Vec<_>>());
             println!("  - route_reason: {}", result.route_reason);
 
+            // v0.3.4: 通知前端路由决策，用户改用云端 / 本地时据此反馈
+            if let Some(ref decision_id) = result.decision_id {
+                let _ = app.emit("local-model-route", json!({
+                    "type": "route-decision",
+                    "decision_id": decision_id,
+                    "should_use_local": result.should_use_local,
+                    "reason": result.route_reason
+                }));
+            }
+
             // 如果本地模型解析到工具调用，发送路由事件通知前端
             if result.has_tool_calls {
                 let _ = app.emit("local-model-route", json!({
@@ -636,6 +900,12 @@ async fn ai_chat(
         return Err("No user message to process".to_string());
     }
 
+    // v0.3.4: 会话详略预设（长度要求写入系统提示词，max_tokens 在请求构建层设置）
+    let verbosity_settings = verbosity::settings_for(session_id.as_deref());
+    if let Some(ref settings) = verbosity_settings {
+        verbosity::inject_instruction(&mut messages, settings);
+    }
+
     println!("[AI Chat] Final messages to send: {}", messages.len());
     for (i, msg) in messages.iter().enumerate() {
         let content_info = match &msg.content {
@@ -656,6 +926,14 @@ async fn ai_chat(
     let accumulated_reasoning = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
     let accumulated_content = std::sync::Arc::new(std::sync::Mutex::new(String::new()));
     let has_intercepted_tool = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let hard_cap = verbosity_settings.as_ref().and_then(|v| v.hard_cap_tokens);
+    let hard_capped = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let hard_capped_for_stream = hard_capped.clone();
+
+    // v0.3.4: 流结束后将组装的响应写入缓存，用于重新打开会话时回放；未结束的缓存标记为中断
+    let stream_tee = completion_cache::begin(&event_id, session_id.as_deref());
+    let assembled = std::sync::Arc::new(std::sync::Mutex::new(completion_cache::AssembledResponse::default()));
+    let assembled_for_stream = assembled.clone();
 
     // 为云端请求注入 bash 工具定义
     let mut tools = vec![
@@ -676,7 +954,28 @@ async fn ai_chat(
         })
     ];
 
-    state.ai_service.stream_chat(
+    // v0.3.4: 工具定义与对话消息计入上下文预算后发送分配说明
+    for tool in &tools {
+        planner.add(context_plan::ContextSection::Tools, &tool.to_string());
+    }
+    for message in &messages {
+        if message.role != "system" {
+            planner.add_message(context_plan::ContextSection::History, message);
+        } else if !system_planned {
+            planner.add_message(context_plan::ContextSection::System, message);
+        }
+    }
+    let _ = app.emit(&format!("{}_context_plan", event_id), planner.finish());
+
+    // v0.3.4: 取消、重试进度通道、详略、温度调度的会话覆盖与用量归属统一由请求选项设置
+    let options = request_options::RequestOptions {
+        cancel: Some(&cancel),
+        retry_channel: Some((app.clone(), event_id.clone())),
+        verbosity: verbosity_settings,
+        session_id,
+        usage_scope: usage_meter::UsageScope { project: project_root.clone(), agent_id: None },
+    };
+    let result = options.run(state.ai_service.stream_chat(
         &provider_config,
         messages,
         &event_id,
@@ -708,6 +1007,25 @@ async fn ai_chat(
                      current_content = accumulated_content.lock().unwrap().clone();
                  }
 
+                 // v0.3.4: 超过会话硬上限时在句子边界截断，并中止请求
+                 if let Some(cap) = hard_cap {
+                     if !hard_capped_for_stream.load(std::sync::atomic::Ordering::SeqCst) {
+                         if let Some(kept) = verbosity::enforce_hard_cap(&current_content, cap) {
+                             hard_capped_for_stream.store(true, std::sync::atomic::Ordering::SeqCst);
+                             println!("[AI Chat] Hard cap of {} tokens reached, keeping {} chars", cap, kept.len());
+                             if let Ok(mut response) = assembled_for_stream.lock() {
+                                 response.content = kept.to_string();
+                             }
+                             let event = events::StreamEvent::Truncated {
+                                 reason: format!("hard cap of {} tokens", cap),
+                                 content: kept.to_string(),
+                             };
+                             events::emit_event(&app_handle_for_stream, &event_id_clone, &event);
+                             cancellation::cancel(&event_id_clone);
+                         }
+                     }
+                 }
+
                  // 检测 XML 标签并转换
                  let combined = format!("{}{}", current_reasoning, current_content);
                  let already_intercepted = has_intercepted_tool.load(std::sync::atomic::Ordering::SeqCst);
@@ -737,7 +1055,7 @@ async fn ai_chat(
                                  println!("[AI Chat] INTERCEPTED XML: {} - {}", tool_name, cmd_str);
 
                                  // 发送标准工具调用事件给前端
-                                 let _ = app_handle_for_stream.emit(&event_id_clone, serde_json::json!({
+                                 let tool_call_event = serde_json::json!({
                                      "type": "tool_call",
                                      "tool_call": {
                                          "index": 0,
@@ -748,7 +1066,11 @@ async fn ai_chat(
                                              "arguments": serde_json::to_string(&args).unwrap_or_default()
                                          }
                                      }
-                                 }).to_string());
+                                 });
+                                 if let Ok(mut response) = assembled_for_stream.lock() {
+                                     response.merge_tool_call(&tool_call_event["tool_call"]);
+                                 }
+                                 let _ = app_handle_for_stream.emit(&event_id_clone, tool_call_event.to_string());
                              }
                          }
                      }
@@ -756,10 +1078,24 @@ async fn ai_chat(
 
                  // 如果已经拦截过工具，或者正在输出 XML 标签，则彻底静默后续所有块
                  // 这样可以防止 AI 在工具调用后输出重复的 XML 或者废话
-                 let is_xml_fragment = combined.contains("<tool_call>") || combined.contains("<arg_") || chunk.contains("tool_call");
-                 let should_suppress = already_intercepted || is_xml_fragment;
+                 // v0.3.4: 结构化的 tool_call 事件（社区版流式输出）直接转发
+                 let structured_tool_call = json_obj["type"] == "tool_call";
+                 let is_xml_fragment = !structured_tool_call && (combined.contains("<tool_call>") || combined.contains("<arg_") || chunk.contains("tool_call"));
+                 let should_suppress = already_intercepted || is_xml_fragment || hard_capped_for_stream.load(std::sync::atomic::Ordering::SeqCst);
                  
                  if !should_suppress {
+                     // 组装实际发送给前端的响应，流结束后写入缓存
+                     if let Ok(mut response) = assembled_for_stream.lock() {
+                         if structured_tool_call {
+                             response.merge_tool_call(&json_obj["tool_call"]);
+                         }
+                         let delta = &json_obj["choices"][0]["delta"];
+                         response.reasoning.push_str(delta["reasoning_content"].as_str().unwrap_or(""));
+                         response.content.push_str(delta["content"].as_str().unwrap_or(""));
+                         for call in delta["tool_calls"].as_array().into_iter().flatten() {
+                             response.merge_tool_call(call);
+                         }
+                     }
                      let _ = app_handle_for_stream.emit(&event_id_clone, chunk.clone());
                  }
 
@@ -770,16 +1106,37 @@ async fn ai_chat(
                  }
              }
         })
-    ).await
+    )).await;
+
+    // 硬上限截断通过取消令牌中止请求，视为正常结束
+    let truncated = hard_capped.load(std::sync::atomic::Ordering::SeqCst);
+    let result = if truncated { Ok(()) } else { result };
+    if let Some(tee) = stream_tee {
+        let response = assembled.lock().map(|r| r.clone()).unwrap_or_default();
+        tee.finish(&result, &response);
+    }
+    if truncated {
+        let _ = app.emit(&format!("{}_finish", event_id), "TRUNCATED");
+    } else if cancel.is_cancelled() {
+        println!("[AI Chat] Request cancelled: {}", event_id);
+        events::emit_event(&app, &event_id, &events::StreamEvent::Cancelled { reason: "Request cancelled by user".to_string() });
+        let _ = app.emit(&format!("{}_finish", event_id), "CANCELLED");
+    }
+    result
 }
 
 #[tauri::command]
 async fn ai_completion(
     state: tauri::State<'_, AppState>,
-    provider_config: core_traits::ai::AIProviderConfig,
+    mut provider_config: core_traits::ai::AIProviderConfig,
     messages: Vec<core_traits::ai::Message>,
+    project_root: Option<String>,
 ) -> Result<String, String> {
     println!("[AI Completion] Entry - provider: {}", provider_config.id);
+    idle_manager::touch();
+    if let Some(root) = project_root.as_deref() {
+        project_config::apply_model_route(root, project_config::ModelTask::Completion, &mut provider_config);
+    }
     let response = state.ai_service.chat(&provider_config, messages).await?;
     match response.content {
         core_traits::ai::Content::Text(t) => Ok(t),
@@ -801,6 +1158,8 @@ async fn create_window(app: tauri::AppHandle, label: String, title: String, url:
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    // v0.3.4: 安全模式（--safe-mode / IFAI_SAFE_MODE）
+    let safe_mode = safe_mode::init();
     let mut builder = tauri::Builder::default();
     
     // 初始化日志插件
@@ -854,23 +1213,54 @@ pub fn run() {
             .map_err(|e| format!("Failed to create ErrorParserState: {}", e))?;
         app.manage(std::sync::Mutex::new(error_parser));
 
+        // v0.3.4: 安全模式跳过索引加载和所有后台任务
+        if safe_mode::is_enabled() {
+            return Ok(());
+        }
+
         #[cfg(all(feature = "commercial", feature = "fastembed"))]
         {
             app.manage(ifainew_core::RagState::new());
         }
+
+        // v0.3.4: 空闲资源回收（向量模型、交叉编码器与 HNSW 索引在下次使用时懒加载）
+        idle_manager::register_reclaimer("embedding_models", Box::new(embedding::unload_models));
+        idle_manager::register_reclaimer("rerank_models", Box::new(rerank::unload_models));
+        idle_manager::register_reclaimer("hnsw_index", Box::new(vector_index::unload_indexes));
+        idle_manager::start_idle_monitor();
+
+        // v0.3.4: 用户开启自动检查后按间隔检查新版本（仅提示，不自动安装）
+        update_check::start_background_check(app_handle.clone());
+
+        // v0.3.4: 后台任务队列
+        job_queue::register_builtin_handlers();
+        job_queue::start_job_worker(app_handle.clone());
+
+        // v0.3.4: 清理过期的流式响应缓存
+        std::thread::spawn(completion_cache::prune);
+
+        // v0.3.4: 清理崩溃遗留的临时目录、未完成下载和失效锁文件
+        janitor::start_startup_cleanup();
         
         Ok(())
     });
 
-    builder
+    let builder = builder
         .plugin(tauri_plugin_window_state::Builder::default().build())
         .manage(TerminalManager::new())
         .manage(LspManager::new())
         .manage(Supervisor::new())
+        .manage(background_processes::BackgroundProcessRegistry::new())
+        .manage(editor_context::EditorContextState::default())
         .on_window_event(|window, event| {
             match event {
                 tauri::WindowEvent::CloseRequested { .. } => {
                     if window.label() == "main" {
+                        // v0.3.4: 保存运行中 Agent 的状态，下次可通过 resume_agent 继续
+                        #[cfg(feature = "commercial")]
+                        agent_system::persistence::persist_all();
+                        // v0.3.4: 结束托管的后台进程（开发服务器等）
+                        window.app_handle().state::<background_processes::BackgroundProcessRegistry>().kill_all();
                         window.app_handle().exit(0);
                     }
                 }
@@ -897,6 +1287,7 @@ pub fn run() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_os::init())
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init()) // v0.3.4: 系统通知
         .invoke_handler(tauri::generate_handler![
             greet,
             ai_chat,
@@ -909,30 +1300,57 @@ pub fn run() {
             terminal::write_pty,
             terminal::resize_pty,
             terminal::kill_pty,
+            terminal::list_pty_sessions,
+            terminal::attach_pty,
+            terminal::read_pty_history,
             search::search_in_files,
+            search::search_replace_in_files,
             git::get_git_statuses,
             git::get_git_statuses_incremental,
             git::get_git_statuses_pattern,
+            git::git_stage_files,
+            git::git_unstage,
+            git::git_commit,
+            git::git_diff,
+            git::git_log,
+            git::git_create_branch,
+            git::git_switch_branch,
+            git::git_create_worktree,
+            git::git_finish_agent_branch,
             lsp::start_lsp,
             lsp::send_lsp_message,
             lsp::kill_lsp,
+            lsp::get_diagnostics,
             commands::core_wrappers::init_rag_index,
             commands::core_wrappers::search_semantic,
             commands::core_wrappers::search_hybrid,
             commands::core_wrappers::build_context,
             commands::core_wrappers::agent_write_file,
+            commands::core_wrappers::agent_edit_file,
             commands::core_wrappers::agent_read_file,
             commands::core_wrappers::agent_list_dir,
             commands::core_wrappers::agent_delete_file,
             commands::core_wrappers::agent_batch_read,
             commands::core_wrappers::agent_scan_directory,
+            commands::core_wrappers::agent_grep,
             commands::prompt_commands::list_prompts,
             commands::prompt_commands::get_prompt,
             commands::prompt_commands::update_prompt,
             commands::prompt_commands::render_prompt_template,
+            prompt_manager::bundle::export_prompt_bundle,
+            prompt_manager::bundle::import_prompt_bundle,
+            prompt_manager::bundle::trust_prompt_bundle_signer,
+            prompt_watch::watch_project_prompts,
+            prompt_watch::unwatch_project_prompts,
             commands::agent_commands::launch_agent,
+            commands::agent_commands::launch_fix_agent,
             commands::agent_commands::list_running_agents,
             commands::agent_commands::approve_agent_action,
+            commands::agent_commands::approve_all_pending,
+            commands::agent_commands::list_pending_approvals,
+            commands::agent_commands::pause_agent,
+            commands::agent_commands::resume_agent,
+            commands::agent_commands::list_paused_agents,
             commands::bash_commands::execute_bash_command,
             performance::detect_gpu_info,
             performance::is_on_battery,
@@ -940,6 +1358,7 @@ pub fn run() {
             project_config::load_project_config,
             project_config::save_project_config,
             project_config::parse_project_config,
+            project_config::resolve_model_route,
             project_config::project_config_exists,
             project_config::delete_project_config,
             local_model::get_local_model_config,
@@ -951,8 +1370,29 @@ pub fn run() {
             local_model::start_download,
             local_model::cancel_download,
             local_model::local_model_preprocess,
+            intelligence_router::record_route_feedback,
+            intelligence_router::get_router_stats,
             local_model::local_code_completion,
             local_model::local_model_fim,
+            local_model::local_model_stream, // v0.3.4 新增：本地模型流式生成
+            local_model::cancel_local_generation, // v0.3.4 新增：取消本地生成
+            // v0.3.4 新增：本地多模型管理
+            model_registry::list_local_models,
+            model_registry::set_active_model,
+            model_registry::remove_local_model,
+            embedding::embed_texts,
+            vector_index::semantic_index_upsert,
+            vector_index::semantic_index_file,
+            vector_index::semantic_index_remove,
+            vector_index::semantic_index_search,
+            vector_index::rag_index_stats,
+            code_chunker::chunk_file,
+            rerank::get_rag_config,
+            rerank::set_rag_config,
+            ifai_ignore::check_ifaiignore,
+            checkpoints::list_checkpoints,
+            checkpoints::diff_checkpoint,
+            checkpoints::restore_checkpoint,
             file_cache::get_file_cache_stats,
             file_cache::clear_file_cache,
             file_cache::print_file_cache_stats,
@@ -960,6 +1400,7 @@ pub fn run() {
             token_counter::count_tokens,
             token_counter::count_tokens_batch,
             token_counter::estimate_tokens_cmd,
+            token_counter::count_context_tokens,
             // v0.2.6 新增：任务拆解文件存储
             commands::task_commands::save_task_breakdown,
             commands::task_commands::load_task_breakdown,
@@ -980,6 +1421,9 @@ pub fn run() {
             commands::symbol_commands::find_symbol_references,
             commands::symbol_commands::find_implementations,
             commands::symbol_commands::clear_symbol_index,
+            // v0.3.4 新增：调用图与文件依赖图
+            commands::graph_commands::get_call_graph,
+            commands::graph_commands::get_file_dependencies,
             // v0.2.8 新增：原子文件操作
             commands::atomic_commands::atomic_write_start,
             commands::atomic_commands::atomic_write_add_operation,
@@ -999,10 +1443,208 @@ pub fn run() {
             multimodal::multimodal_analyze_image,
             multimodal::multimodal_is_vision_supported,
             multimodal::read_file_as_base64,
+            multimodal::capture_window_screenshot,
+            multimodal::attach_image,
             // v0.3.3 新增：工具分类系统
             tool_classification::tool_classify,
-            tool_classification::tool_batch_classify
+            tool_classification::tool_batch_classify,
+            tool_classification::classification_stats,
+            tool_classification::reset_classification_stats,
+            // v0.3.4 新增：工作流录制与回放
+            commands::workflow_commands::start_workflow_recording,
+            commands::workflow_commands::stop_workflow_recording,
+            commands::workflow_commands::cancel_workflow_recording,
+            commands::workflow_commands::get_workflow_recording,
+            commands::workflow_commands::save_workflow,
+            commands::workflow_commands::load_workflow,
+            commands::workflow_commands::list_workflows,
+            commands::workflow_commands::delete_workflow,
+            commands::workflow_commands::run_workflow,
+            // v0.3.4 新增：Monorepo 子包上下文配置
+            workspace_profiles::list_workspace_profiles,
+            workspace_profiles::get_workspace_profile,
+            workspace_profiles::save_workspace_profile_override,
+            // v0.3.4 新增：失败请求记录与回放
+            failed_requests::list_failed_requests,
+            failed_requests::get_failed_request,
+            failed_requests::clear_failed_requests,
+            failed_requests::replay_failed_request,
+            // v0.3.4 新增：斜杠命令框架
+            slash_commands::list_slash_commands,
+            slash_commands::slash_command_help,
+            slash_commands::execute_slash_command,
+            // v0.3.4 新增：粘贴内容增强
+            paste_enrichment::enrich_pasted_text,
+            paste_enrichment::clear_pending_paste_context,
+            // v0.3.4 新增：限时快速回答模式
+            quick_answer::quick_answer_chat,
+            quick_answer::get_quick_answer_stats,
+            quick_answer::reset_quick_answer_stats,
+            prompt_cache::get_prompt_cache_stats,
+            prompt_cache::reset_prompt_cache_stats,
+            usage_meter::get_usage_summary,
+            usage_meter::export_usage_csv,
+            // v0.3.4 新增：对话级温度调度
+            temperature_schedule::get_temperature_schedule,
+            temperature_schedule::set_temperature_schedule,
+            temperature_schedule::set_session_temperature_override,
+            temperature_schedule::preview_scheduled_temperature,
+            // v0.3.4 新增：前端事件结构与版本
+            events::negotiate_event_schema,
+            // v0.3.4 新增：项目上下文导出
+            commands::export_commands::export_repo_context,
+            // v0.3.4 新增：文件系统瞬时错误重试
+            fs_retry::get_fs_retry_log,
+            // v0.3.4 新增：空闲资源回收
+            idle_manager::get_idle_metrics,
+            idle_manager::get_idle_config,
+            idle_manager::set_idle_config,
+            idle_manager::reclaim_idle_resources,
+            // v0.3.4 新增：Agent 分级日志与运行记录
+            agent_log::set_agent_log_verbosity,
+            agent_log::get_agent_transcript,
+            agent_log::delete_agent_transcript,
+            // v0.3.4 新增：单元测试生成
+            commands::testgen_commands::generate_tests,
+            // v0.3.4 新增：错误解释与修复建议
+            commands::error_commands::explain_error,
+            // v0.3.4 新增：提交前风险分析
+            commands::atomic_commands::atomic_write_analyze_risk,
+            // v0.3.4 新增：版本更新检查
+            update_check::check_for_updates,
+            update_check::get_update_config,
+            update_check::set_update_config,
+            update_check::skip_update_version,
+            // v0.3.4 新增：沙箱化 JS/TS 片段执行
+            js_sandbox::agent_eval_js,
+            // v0.3.4 新增：.ifai 目录初始化
+            project_init::init_ifai_project,
+            // v0.3.4 新增：最近项目列表
+            recent_projects::list_recent_projects,
+            recent_projects::record_recent_project,
+            recent_projects::remove_recent_project,
+            // v0.3.4 新增：基于 Git 引用差异的检索上下文
+            diff_rag::build_diff_rag_context,
+            // v0.3.4 新增：系统通知
+            notifications::get_notification_config,
+            notifications::set_notification_config,
+            notifications::send_test_notification,
+            // v0.3.4 新增：会话隐私级别
+            privacy::set_session_privacy_level,
+            privacy::get_session_privacy_level,
+            // v0.3.4 新增：编辑器选区上下文
+            selection_context::set_active_selection,
+            selection_context::clear_active_selection,
+            editor_context::set_editor_context,
+            editor_context::get_editor_context,
+            editor_context::clear_editor_context,
+            // v0.3.4 新增：后台任务队列
+            job_queue::list_jobs,
+            job_queue::enqueue_job,
+            job_queue::cancel_job,
+            job_queue::clear_finished_jobs,
+            // v0.3.4 新增：流式响应缓存
+            completion_cache::get_cached_stream,
+            completion_cache::list_cached_streams,
+            completion_cache::delete_cached_stream,
+            // v0.3.4 新增：内容寻址的大对象存储
+            blob_store::blob_put_file,
+            blob_store::blob_put_text,
+            blob_store::blob_read,
+            blob_store::blob_release,
+            blob_store::blob_gc,
+            // v0.3.4 新增：自适应 RAG 触发
+            auto_rag::get_auto_rag_config,
+            auto_rag::set_auto_rag_config,
+            auto_rag::preview_auto_rag,
+            // v0.3.4 新增：摘要模型策略
+            conversation::summarizer::get_summarizer_strategy,
+            conversation::summarizer::set_summarizer_strategy,
+            // v0.3.4 新增：会话合并
+            conversation::merge::merge_sessions,
+            conversation::export::export_conversation,
+            // v0.3.4 新增：工具调用能力探测
+            tool_capability::get_tool_support,
+            tool_capability::set_tool_support,
+            // v0.3.4 新增：遗留文件清理
+            janitor::run_cleanup_now,
+            // v0.3.4 新增：语言识别
+            language_map::detect_file_language,
+            // v0.3.4 新增：Provider 配置共享
+            provider_sharing::export_provider_configs,
+            provider_sharing::import_provider_configs,
+            // v0.3.4 新增：安全模式
+            safe_mode::get_safe_mode_status,
+            // v0.3.4 新增：Agent 运行产物
+            agent_artifacts::get_agent_artifacts,
+            // v0.3.4 新增：请求取消
+            cancellation::cancel_ai_request,
+            // v0.3.4 新增：AI 请求重试策略
+            ai_retry::get_ai_retry_config,
+            ai_retry::set_ai_retry_config,
+            // v0.3.4 新增：工具类别授权
+            tool_permissions::get_tool_permissions,
+            tool_permissions::revoke_permission,
+            // v0.3.4 新增：自动审批策略
+            approval_policy::get_approval_policy,
+            approval_policy::set_approval_policy,
+            command_safety::analyze_command_safety,
+            formatter::format_file,
+            formatter::format_project,
+            // v0.3.4 新增：后台进程管理
+            background_processes::list_background_processes,
+            background_processes::stop_background_process,
+            background_processes::get_process_logs,
+            // v0.3.4 新增：回复详略控制
+            verbosity::get_session_verbosity,
+            verbosity::set_session_verbosity,
+            // v0.3.4 新增：项目术语表
+            glossary::build_glossary
+        ]);
+
+    // v0.3.4: 安全模式下替换为只包含核心对话、文件与诊断命令的处理器
+    let builder = if safe_mode {
+        builder.invoke_handler(tauri::generate_handler![
+            greet,
+            ai_chat,
+            create_window,
+            file_walker::get_all_file_paths,
+            terminal::create_pty,
+            terminal::write_pty,
+            terminal::resize_pty,
+            terminal::kill_pty,
+            terminal::list_pty_sessions,
+            terminal::attach_pty,
+            terminal::read_pty_history,
+            search::search_in_files,
+            search::search_replace_in_files,
+            commands::core_wrappers::agent_write_file,
+            commands::core_wrappers::agent_read_file,
+            commands::core_wrappers::agent_list_dir,
+            commands::bash_commands::execute_bash_command,
+            project_config::load_project_config,
+            project_config::save_project_config,
+            project_config::project_config_exists,
+            project_config::delete_project_config,
+            token_counter::count_tokens,
+            token_counter::count_context_tokens,
+            local_model::get_system_info,
+            performance::detect_gpu_info,
+            failed_requests::list_failed_requests,
+            failed_requests::get_failed_request,
+            failed_requests::clear_failed_requests,
+            fs_retry::get_fs_retry_log,
+            recent_projects::list_recent_projects,
+            recent_projects::remove_recent_project,
+            janitor::run_cleanup_now,
+            safe_mode::get_safe_mode_status,
+            cancellation::cancel_ai_request
         ])
+    } else {
+        builder
+    };
+
+    builder
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
\ No newline at end of file
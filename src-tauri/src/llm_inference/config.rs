@@ -102,6 +102,62 @@ impl LlmInferenceConfig {
     }
 }
 
+// ============================================================================
+// Sampling Parameters
+// ============================================================================
+
+/// 采样参数
+///
+/// 相同的参数（含 `seed`）、模型与提示词保证得到相同的输出，
+/// 供测试与评估脚本复现本地生成结果。`temperature` 为 0 时使用贪心解码。
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SamplingParams {
+    /// 随机种子
+    pub seed: u32,
+
+    /// 温度参数（0.0 - 2.0，0 表示贪心）
+    pub temperature: f32,
+
+    /// Top-p 采样参数（0.0 - 1.0）
+    pub top_p: f32,
+
+    /// 重复惩罚（1.0 表示不惩罚）
+    pub repeat_penalty: f32,
+}
+
+impl Default for SamplingParams {
+    fn default() -> Self {
+        Self {
+            seed: 1234,
+            temperature: 0.0,
+            top_p: 1.0,
+            repeat_penalty: 1.0,
+        }
+    }
+}
+
+impl SamplingParams {
+    /// 是否使用贪心解码
+    pub fn is_greedy(&self) -> bool {
+        self.temperature <= 0.0
+    }
+
+    /// 验证参数范围
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=2.0).contains(&self.temperature) {
+            return Err(format!("温度参数超出范围 (0.0 - 2.0): {}", self.temperature));
+        }
+        if !(0.0..=1.0).contains(&self.top_p) || self.top_p == 0.0 {
+            return Err(format!("Top-p 参数超出范围 (0.0 - 1.0]: {}", self.top_p));
+        }
+        if !(1.0..=2.0).contains(&self.repeat_penalty) {
+            return Err(format!("重复惩罚超出范围 (1.0 - 2.0): {}", self.repeat_penalty));
+        }
+        Ok(())
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -135,4 +191,19 @@ mod tests {
         config.top_p = 1.5;
         assert!(config.validate().is_err());
     }
+
+    #[test]
+    fn test_sampling_params() {
+        let params = SamplingParams::default();
+        assert!(params.is_greedy());
+        assert!(params.validate().is_ok());
+
+        let params: SamplingParams = serde_json::from_str(r#"{ "seed": 7, "temperature": 0.8 }"#).unwrap();
+        assert_eq!(params.seed, 7);
+        assert!(!params.is_greedy());
+        assert_eq!(params.top_p, 1.0);
+
+        assert!(SamplingParams { top_p: 0.0, ..Default::default() }.validate().is_err());
+        assert!(SamplingParams { repeat_penalty: 0.5, ..Default::default() }.validate().is_err());
+    }
 }
@@ -0,0 +1,94 @@
+/*!
+Embedder - llama.cpp Text Embeddings
+====================================
+
+使用 GGUF 嵌入模型（如 bge-m3、nomic-embed）生成文本向量。
+
+与文本生成共用全局模型槽位：嵌入模型与生成模型不同时会在切换时重新加载。
+*/
+
+use crate::llm_inference::InferenceError;
+use std::path::Path;
+
+#[cfg(feature = "llm-inference")]
+use llama_cpp_2::{
+    context::params::LlamaContextParams,
+    llama_batch::LlamaBatch,
+    model::AddBos,
+};
+
+/// 单条文本的最大 token 数（超出部分截断）
+#[cfg(feature = "llm-inference")]
+const MAX_EMBED_TOKENS: usize = 512;
+
+/// L2 归一化，便于直接用点积计算余弦相似度
+pub fn normalize(vector: &mut [f32]) {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+}
+
+/// 使用指定的 GGUF 模型生成文本向量（已归一化）
+///
+/// 阻塞调用，异步环境中应在 `spawn_blocking` 内调用
+#[cfg(feature = "llm-inference")]
+pub fn embed_texts(model_path: &Path, texts: &[String]) -> Result<Vec<Vec<f32>>, InferenceError> {
+    use crate::llm_inference::model::{ensure_path_loaded, get_or_init_model};
+
+    let model_ref = get_or_init_model()?;
+    let mut model_guard = model_ref.lock()
+        .map_err(|_| InferenceError::InferenceFailed("获取模型锁失败".to_string()))?;
+    ensure_path_loaded(&mut model_guard, model_path)?;
+    let model = model_guard.as_ref().ok_or(InferenceError::ModelNotLoaded)?;
+
+    let ctx_params = LlamaContextParams::default()
+        .with_n_ctx(std::num::NonZeroU32::new(MAX_EMBED_TOKENS as u32))
+        .with_embeddings(true);
+    let mut ctx = model.model.new_context(&model.backend, ctx_params)
+        .map_err(|e| InferenceError::InferenceFailed(format!("创建上下文失败: {}", e)))?;
+
+    let mut vectors = Vec::with_capacity(texts.len());
+    for text in texts {
+        let mut tokens = model.model.str_to_token(text, AddBos::Always)
+            .map_err(|e| InferenceError::InferenceFailed(format!("分词失败: {}", e)))?;
+        tokens.truncate(MAX_EMBED_TOKENS);
+
+        let mut batch = LlamaBatch::new(MAX_EMBED_TOKENS, 1);
+        batch.add_sequence(&tokens, 0, false)
+            .map_err(|e| InferenceError::InferenceFailed(format!("添加 token 到批处理失败: {}", e)))?;
+
+        ctx.clear_kv_cache();
+        ctx.decode(&mut batch)
+            .map_err(|e| InferenceError::InferenceFailed(format!("解码失败: {}", e)))?;
+
+        let mut vector = ctx.embeddings_seq_ith(0)
+            .map_err(|e| InferenceError::InferenceFailed(format!("读取向量失败: {}", e)))?
+            .to_vec();
+        normalize(&mut vector);
+        vectors.push(vector);
+    }
+
+    println!("[Embedder] Embedded {} texts with {}", texts.len(), model_path.display());
+    Ok(vectors)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let mut vector = vec![3.0, 4.0];
+        normalize(&mut vector);
+        assert_eq!(vector, vec![0.6, 0.8]);
+
+        let mut zero = vec![0.0, 0.0];
+        normalize(&mut zero);
+        assert_eq!(zero, vec![0.0, 0.0]);
+    }
+}
@@ -7,7 +7,9 @@ Text Generator - llama.cpp Text Generation
 使用 llama-cpp-2 v0.1 库实现。
 */
 
-use crate::llm_inference::{InferenceError, model::Model};
+use crate::llm_inference::{InferenceError, SamplingParams, model::Model};
+use crate::model_registry::ModelTask;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 
 #[cfg(feature = "llm-inference")]
 use llama_cpp_2::{
@@ -17,17 +19,109 @@ use llama_cpp_2::{
     model::{AddBos, Special},
 };
 
+/// 取消代数：`cancel_generation` 递增，生成开始时记录，两者不同即视为已取消。
+/// 等待模型锁的生成同样会被取消，之后开始的生成不受影响
+static CANCEL_EPOCH: AtomicU64 = AtomicU64::new(0);
+
+/// 正在进行的生成数
+static ACTIVE_GENERATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// 取消所有进行中（含等待模型锁）的生成；返回当时是否有生成在进行
+pub fn cancel_generation() -> bool {
+    CANCEL_EPOCH.fetch_add(1, Ordering::SeqCst);
+    ACTIVE_GENERATIONS.load(Ordering::SeqCst) > 0
+}
+
+fn is_cancelled(epoch: u64) -> bool {
+    CANCEL_EPOCH.load(Ordering::SeqCst) != epoch
+}
+
+/// 生成期间计数
+struct ActiveGeneration;
+
+impl ActiveGeneration {
+    fn start() -> Self {
+        ACTIVE_GENERATIONS.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for ActiveGeneration {
+    fn drop(&mut self) {
+        ACTIVE_GENERATIONS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// 重复惩罚回看的 token 数
+#[cfg(feature = "llm-inference")]
+const REPEAT_LAST_N: i32 = 64;
+
+/// 增量 UTF-8 解码：缓存跨 token 的不完整多字节序列
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    /// 追加字节，返回已完整的文本；无效字节按 U+FFFD 替换
+    pub fn push(&mut self, bytes: &[u8]) -> String {
+        self.pending.extend_from_slice(bytes);
+        let mut output = String::new();
+        loop {
+            match std::str::from_utf8(&self.pending) {
+                Ok(text) => {
+                    output.push_str(text);
+                    self.pending.clear();
+                    return output;
+                }
+                Err(e) => {
+                    let valid = e.valid_up_to();
+                    output.push_str(std::str::from_utf8(&self.pending[..valid]).unwrap_or_default());
+                    match e.error_len() {
+                        // 序列未结束，等待后续字节
+                        None => {
+                            self.pending.drain(..valid);
+                            return output;
+                        }
+                        Some(len) => {
+                            output.push(char::REPLACEMENT_CHARACTER);
+                            self.pending.drain(..valid + len);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// 结束时输出剩余字节
+    pub fn finish(&mut self) -> String {
+        let rest = String::from_utf8_lossy(&self.pending).to_string();
+        self.pending.clear();
+        rest
+    }
+}
+
 /// 文本生成器
 pub struct TextGenerator {
     max_tokens: usize,
     seed: u32,
+    temperature: f32,
+    top_p: f32,
+    repeat_penalty: f32,
+    /// v0.3.4: GBNF 语法约束（根规则为 `root`），用于分类等固定格式的输出
+    grammar: Option<String>,
 }
 
 impl Default for TextGenerator {
     fn default() -> Self {
+        let sampling = SamplingParams::default();
         Self {
             max_tokens: 50,
-            seed: 1234,
+            seed: sampling.seed,
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            repeat_penalty: sampling.repeat_penalty,
+            grammar: None,
         }
     }
 }
@@ -50,12 +144,91 @@ impl TextGenerator {
         self
     }
 
+    /// 设置采样参数（种子、温度、top-p、重复惩罚）
+    pub fn with_sampling(mut self, sampling: &SamplingParams) -> Self {
+        self.seed = sampling.seed;
+        self.temperature = sampling.temperature;
+        self.top_p = sampling.top_p;
+        self.repeat_penalty = sampling.repeat_penalty;
+        self
+    }
+
+    /// 设置输出语法约束
+    pub fn with_grammar(mut self, grammar: &str) -> Self {
+        self.grammar = Some(grammar.to_string());
+        self
+    }
+
+    /// 当前采样参数
+    pub fn sampling(&self) -> SamplingParams {
+        SamplingParams {
+            seed: self.seed,
+            temperature: self.temperature,
+            top_p: self.top_p,
+            repeat_penalty: self.repeat_penalty,
+        }
+    }
+
+    /// 构建采样器链
+    ///
+    /// 每次生成都新建采样器，固定种子下输出可复现；语法约束位于链首
+    #[cfg(feature = "llm-inference")]
+    fn build_sampler(&self, model: &Model) -> Result<LlamaSampler, InferenceError> {
+        let mut chain = Vec::new();
+        if let Some(grammar) = &self.grammar {
+            let sampler = LlamaSampler::grammar(&model.model, grammar, "root")
+                .map_err(|e| InferenceError::InferenceFailed(format!("语法约束无效: {:?}", e)))?;
+            chain.push(sampler);
+        }
+        if self.repeat_penalty > 1.0 {
+            chain.push(LlamaSampler::penalties(REPEAT_LAST_N, self.repeat_penalty, 0.0, 0.0));
+        }
+        if self.sampling().is_greedy() {
+            chain.push(LlamaSampler::greedy());
+        } else {
+            if self.top_p < 1.0 {
+                chain.push(LlamaSampler::top_p(self.top_p, 1));
+            }
+            chain.push(LlamaSampler::temp(self.temperature));
+            chain.push(LlamaSampler::dist(self.seed));
+        }
+        Ok(LlamaSampler::chain_simple(chain))
+    }
+
     /// 生成文本补全
     #[cfg(feature = "llm-inference")]
     pub fn generate(&self, prompt: &str, model: &Model) -> Result<String, InferenceError> {
+        self.generate_stream(prompt, model, &mut |_| true)
+    }
+
+    /// 流式生成文本补全（调用之后的 `cancel_generation` 会中止生成）
+    #[cfg(feature = "llm-inference")]
+    pub fn generate_stream(
+        &self,
+        prompt: &str,
+        model: &Model,
+        on_token: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<String, InferenceError> {
+        self.generate_stream_until(prompt, model, on_token, CANCEL_EPOCH.load(Ordering::SeqCst))
+    }
+
+    /// 流式生成文本补全
+    ///
+    /// 每解码出完整的 UTF-8 文本片段即调用 `on_token`，返回 false 时提前停止；
+    /// 返回值为完整的生成文本。每个 token 之间检查取消代数，
+    /// 与 `epoch` 不同时返回 `InferenceError::Cancelled`
+    #[cfg(feature = "llm-inference")]
+    fn generate_stream_until(
+        &self,
+        prompt: &str,
+        model: &Model,
+        on_token: &mut dyn FnMut(&str) -> bool,
+        epoch: u64,
+    ) -> Result<String, InferenceError> {
         println!("[TextGenerator] Generating completion");
         println!("[TextGenerator]   Prompt length: {} chars", prompt.len());
         println!("[TextGenerator]   Max tokens: {}", self.max_tokens);
+        println!("[TextGenerator]   Sampling: {:?}", self.sampling());
 
         // 创建上下文参数，设置更大的上下文窗口
         let ctx_params = LlamaContextParams::default()
@@ -97,15 +270,18 @@ impl TextGenerator {
         let mut n_decode = 0;
 
         // 创建采样器
-        let mut sampler = LlamaSampler::chain_simple([
-            LlamaSampler::dist(self.seed),
-            LlamaSampler::greedy(),
-        ]);
+        let mut sampler = self.build_sampler(model)?;
 
         let mut result = String::new();
+        let mut decoder = Utf8Decoder::default();
 
         // 生成循环
         while n_decode < n_len {
+            if is_cancelled(epoch) {
+                println!("[TextGenerator] Cancelled after {} tokens", n_decode);
+                return Err(InferenceError::Cancelled);
+            }
+
             // 采样 token
             let token = sampler.sample(&ctx, batch.n_tokens() - 1);
             sampler.accept(token);
@@ -119,8 +295,13 @@ impl TextGenerator {
             let output_bytes = model.model.token_to_bytes(token, Special::Tokenize)
                 .map_err(|e| InferenceError::InferenceFailed(format!("token 转字节失败: {}", e)))?;
 
-            let output_string = String::from_utf8_lossy(&output_bytes);
+            // 多字节字符可能跨 token，凑齐后再输出
+            let output_string = decoder.push(&output_bytes);
             result.push_str(&output_string);
+            if !output_string.is_empty() && !on_token(&output_string) {
+                println!("[TextGenerator] Stopped by callback after {} tokens", n_decode + 1);
+                break;
+            }
 
             // 注释：移除换行符停止逻辑，让模型能够生成完整的工具调用格式
             // 工具调用场景需要模型生成多行内容（如 bash(command='git status')）
@@ -143,6 +324,12 @@ impl TextGenerator {
             }
         }
 
+        let rest = decoder.finish();
+        if !rest.is_empty() {
+            result.push_str(&rest);
+            on_token(&rest);
+        }
+
         println!("[TextGenerator] Generated {} tokens, {} chars", n_decode, result.len());
         Ok(result)
     }
@@ -153,24 +340,85 @@ impl TextGenerator {
 /// 使用全局模型实例生成文本补全。
 #[cfg(feature = "llm-inference")]
 pub fn generate_completion(prompt: &str, max_tokens: usize) -> Result<String, InferenceError> {
-    use crate::llm_inference::model::{get_or_init_model, ensure_model_loaded};
+    generate_completion_with(prompt, max_tokens, &SamplingParams::default())
+}
 
-    // 确保模型已加载
-    ensure_model_loaded()?;
+/// 便捷函数：使用指定采样参数生成文本补全
+#[cfg(feature = "llm-inference")]
+pub fn generate_completion_with(prompt: &str, max_tokens: usize, sampling: &SamplingParams) -> Result<String, InferenceError> {
+    generate_completion_stream(prompt, max_tokens, sampling, |_| true)
+}
+
+/// 便捷函数：使用指定任务选择的模型生成文本补全
+#[cfg(feature = "llm-inference")]
+pub fn generate_completion_for(
+    task: ModelTask,
+    prompt: &str,
+    max_tokens: usize,
+    sampling: &SamplingParams,
+) -> Result<String, InferenceError> {
+    generate_stream_for(task, prompt, max_tokens, sampling, None, &mut |_| true)
+}
 
-    // 获取模型实例
+/// 便捷函数：按 GBNF 语法约束输出（根规则为 `root`），使用指定任务选择的模型
+#[cfg(feature = "llm-inference")]
+pub fn generate_constrained_for(
+    task: ModelTask,
+    prompt: &str,
+    max_tokens: usize,
+    sampling: &SamplingParams,
+    grammar: &str,
+) -> Result<String, InferenceError> {
+    generate_stream_for(task, prompt, max_tokens, sampling, Some(grammar), &mut |_| true)
+}
+
+/// 便捷函数：流式生成文本补全
+///
+/// 阻塞调用，每个文本片段回调一次 `on_token`（返回 false 停止生成）；
+/// 异步环境中应在 `spawn_blocking` 内调用
+#[cfg(feature = "llm-inference")]
+pub fn generate_completion_stream(
+    prompt: &str,
+    max_tokens: usize,
+    sampling: &SamplingParams,
+    mut on_token: impl FnMut(&str) -> bool,
+) -> Result<String, InferenceError> {
+    generate_stream_for(ModelTask::Completion, prompt, max_tokens, sampling, None, &mut on_token)
+}
+
+#[cfg(feature = "llm-inference")]
+fn generate_stream_for(
+    task: ModelTask,
+    prompt: &str,
+    max_tokens: usize,
+    sampling: &SamplingParams,
+    grammar: Option<&str>,
+    on_token: &mut dyn FnMut(&str) -> bool,
+) -> Result<String, InferenceError> {
+    use crate::llm_inference::model::{get_or_init_model, ensure_task_model};
+
+    // 在等待模型锁之前记录取消代数，排队期间的取消同样生效
+    let epoch = CANCEL_EPOCH.load(Ordering::SeqCst);
+    let _active = ActiveGeneration::start();
+
+    // 获取模型实例，在同一把锁内确保加载了任务选择的模型
     let model_ref = get_or_init_model()?;
-    let model_guard = model_ref.lock()
+    let mut model_guard = model_ref.lock()
         .map_err(|_| InferenceError::InferenceFailed("获取模型锁失败".to_string()))?;
+    ensure_task_model(&mut model_guard, task)?;
 
     let model = model_guard.as_ref()
         .ok_or(InferenceError::ModelNotLoaded)?;
 
     // 创建生成器并生成
-    let generator = TextGenerator::new()
-        .with_max_tokens(max_tokens);
+    let mut generator = TextGenerator::new()
+        .with_max_tokens(max_tokens)
+        .with_sampling(sampling);
+    if let Some(grammar) = grammar {
+        generator = generator.with_grammar(grammar);
+    }
 
-    generator.generate(prompt, model)
+    generator.generate_stream_until(prompt, model, on_token, epoch)
 }
 
 // ============================================================================
@@ -196,5 +444,37 @@ mod tests {
         let generator = TextGenerator::default();
         assert_eq!(generator.max_tokens, 50);
         assert_eq!(generator.seed, 1234);
+        assert_eq!(generator.sampling(), SamplingParams::default());
+    }
+
+    #[test]
+    fn test_utf8_decoder_joins_split_chars() {
+        let bytes = "补全 ok".as_bytes();
+        let mut decoder = Utf8Decoder::default();
+        assert_eq!(decoder.push(&bytes[..2]), "");
+        assert_eq!(decoder.push(&bytes[2..4]), "补");
+        assert_eq!(decoder.push(&bytes[4..]), "全 ok");
+        assert_eq!(decoder.push(&[0xff, b'a']), "\u{fffd}a");
+        assert_eq!(decoder.push(&[0xe5]), "");
+        assert_eq!(decoder.finish(), "\u{fffd}");
+    }
+
+    #[test]
+    fn test_cancel_epoch() {
+        let epoch = CANCEL_EPOCH.load(Ordering::SeqCst);
+        assert!(!is_cancelled(epoch));
+        let active = ActiveGeneration::start();
+        assert!(cancel_generation());
+        assert!(is_cancelled(epoch));
+        // 取消之后开始的生成不受影响
+        assert!(!is_cancelled(CANCEL_EPOCH.load(Ordering::SeqCst)));
+        drop(active);
+    }
+
+    #[test]
+    fn test_with_sampling() {
+        let params = SamplingParams { seed: 9, temperature: 0.8, top_p: 0.95, repeat_penalty: 1.1 };
+        let generator = TextGenerator::new().with_sampling(&params);
+        assert_eq!(generator.sampling(), params);
     }
 }
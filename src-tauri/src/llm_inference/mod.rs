@@ -29,22 +29,35 @@ pub const VERSION: &str = "0.6.0-llama-cpp-2-implementation";
 pub mod model;
 pub mod generator;
 pub mod config;
+pub mod embedder;
 
 // 重新导出常用类型
 pub use model::{
     default_model_path,
     load_model,
     ensure_model_loaded,
+    is_loaded_model,
     unload_model,
     is_model_loaded,
 };
 
 pub use config::{
     LlmInferenceConfig,
+    SamplingParams,
 };
 
 // 重新导出文本生成函数
-pub use generator::generate_completion;
+pub use generator::{
+    generate_completion,
+    generate_completion_with,
+    generate_completion_for,
+    generate_constrained_for,
+    generate_completion_stream,
+    cancel_generation,
+};
+
+// 重新导出文本向量函数
+pub use embedder::embed_texts;
 
 // ============================================================================
 // Error Types
@@ -73,6 +86,9 @@ pub enum InferenceError {
 
     /// 不支持的格式
     UnsupportedFormat(String),
+
+    /// 生成被取消
+    Cancelled,
 }
 
 impl std::fmt::Display for InferenceError {
@@ -99,6 +115,9 @@ impl std::fmt::Display for InferenceError {
             InferenceError::UnsupportedFormat(msg) => {
                 write!(f, "不支持的格式: {}", msg)
             }
+            InferenceError::Cancelled => {
+                write!(f, "生成已取消")
+            }
         }
     }
 }
@@ -109,6 +128,26 @@ impl std::error::Error for InferenceError {}
 // Public Interface
 // ============================================================================
 
+/// 在阻塞线程池中运行推理任务（带超时）
+///
+/// 超时后取消进行中的生成（生成器在 token 之间检查取消标记），
+/// 推理线程随即退出并释放模型锁，不会一直占用模型。
+pub async fn run_with_timeout<T, F>(timeout: std::time::Duration, task: F) -> Result<T, InferenceError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, InferenceError> + Send + 'static,
+{
+    match tokio::time::timeout(timeout, tokio::task::spawn_blocking(task)).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(InferenceError::InferenceFailed(format!("任务调度失败: {}", e))),
+        Err(_) => {
+            println!("[LLM] Generation timed out after {:?}, cancelling", timeout);
+            cancel_generation();
+            Err(InferenceError::Timeout)
+        }
+    }
+}
+
 /// 生成文本补全（带超时）
 ///
 /// # 参数
@@ -118,17 +157,14 @@ impl std::error::Error for InferenceError {}
 ///
 /// # 返回
 /// - 成功时返回生成的文本
-/// - 失败时返回错误信息
-///
-/// # 注意
-/// 当前版本未实现超时功能，将直接调用 `generate_completion`。
-pub fn generate_completion_with_timeout(
+/// - 超时返回 `InferenceError::Timeout`，进行中的生成被取消
+pub async fn generate_completion_with_timeout(
     prompt: &str,
     max_tokens: usize,
-    _timeout_secs: u64,
+    timeout_secs: u64,
 ) -> Result<String, InferenceError> {
-    // 当前版本不实现超时，直接调用基础函数
-    generate_completion(prompt, max_tokens)
+    let prompt = prompt.to_string();
+    run_with_timeout(std::time::Duration::from_secs(timeout_secs), move || generate_completion(&prompt, max_tokens)).await
 }
 
 /// 检查 LLM 推理是否可用
@@ -159,6 +195,19 @@ mod tests {
         assert_eq!(format!("{}", err), "功能未实现: test");
     }
 
+    #[tokio::test]
+    async fn test_run_with_timeout() {
+        let fast = run_with_timeout(std::time::Duration::from_secs(5), || Ok(7)).await;
+        assert_eq!(fast.unwrap(), 7);
+
+        let slow = run_with_timeout(std::time::Duration::from_millis(20), || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            Ok(())
+        })
+        .await;
+        assert!(matches!(slow, Err(InferenceError::Timeout)));
+    }
+
     #[test]
     fn test_version() {
         assert_eq!(get_version(), "0.6.0-llama-cpp-2-implementation");
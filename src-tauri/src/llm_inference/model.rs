@@ -8,7 +8,8 @@ Model Loader - llama.cpp GGUF Model Loading
 */
 
 use crate::llm_inference::InferenceError;
-use std::path::PathBuf;
+use crate::model_registry::{active_model_path, ModelTask};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex, OnceLock};
 
 #[cfg(feature = "llm-inference")]
@@ -27,6 +28,8 @@ use std::pin::pin;
 pub struct Model {
     pub backend: LlamaBackend,
     pub model: LlamaModel,
+    /// 模型文件路径
+    pub path: PathBuf,
 }
 
 /// 模型实例占位（当 feature 未启用时）
@@ -99,35 +102,51 @@ pub fn load_model(model_path: &PathBuf) -> Result<Model, InferenceError> {
 
     println!("[LlmInference] Model loaded successfully");
 
-    Ok(Model { backend, model })
+    Ok(Model { backend, model, path: model_path.clone() })
 }
 
 /// 懒加载模型
 ///
-/// 如果全局模型未加载，则加载模型。
+/// 如果全局模型未加载，则加载补全任务选择的模型。
 pub fn ensure_model_loaded() -> Result<(), InferenceError> {
     let model_ref = get_or_init_model()?;
+    let mut model_guard = model_ref.lock()
+        .map_err(|_| InferenceError::InferenceFailed("获取模型锁失败".to_string()))?;
+    ensure_task_model(&mut model_guard, ModelTask::Completion)
+}
 
-    {
-        let mut model_guard = model_ref.lock()
-            .map_err(|_| InferenceError::InferenceFailed("获取模型锁失败".to_string()))?;
-
-        if model_guard.is_some() {
-            println!("[LlmInference] Model already loaded");
-            return Ok(());
-        }
-
-        // 加载默认模型
-        let model_path = default_model_path();
-        let model = load_model(&model_path)?;
+/// 在已持有的模型锁内确保加载了任务选择的模型
+///
+/// 已加载的是其他模型时卸载并重新加载（只有一个模型槽位）。
+#[cfg(feature = "llm-inference")]
+pub fn ensure_task_model(slot: &mut Option<Model>, task: ModelTask) -> Result<(), InferenceError> {
+    ensure_path_loaded(slot, &active_model_path(task))
+}
 
-        *model_guard = Some(model);
-        println!("[LlmInference] Model loaded and stored globally");
+/// 在已持有的模型锁内确保加载了指定的模型文件
+#[cfg(feature = "llm-inference")]
+pub fn ensure_path_loaded(slot: &mut Option<Model>, model_path: &Path) -> Result<(), InferenceError> {
+    // 每次推理 / 嵌入都经过这里，记录为活动以推迟空闲回收
+    crate::idle_manager::touch();
+    if slot.as_ref().is_some_and(|m| m.path == model_path) {
+        return Ok(());
     }
-
+    if let Some(previous) = slot.take() {
+        println!("[LlmInference] Switching model: {} -> {}", previous.path.display(), model_path.display());
+    }
+    *slot = Some(load_model(&model_path.to_path_buf())?);
+    println!("[LlmInference] Model loaded and stored globally");
     Ok(())
 }
 
+/// 模型文件是否为当前加载的模型
+pub fn is_loaded_model(path: &Path) -> bool {
+    get_or_init_model()
+        .ok()
+        .and_then(|model_ref| model_ref.lock().ok().map(|guard| guard.as_ref().is_some_and(|m| m.path == path)))
+        .unwrap_or(false)
+}
+
 /// 卸载模型
 pub fn unload_model() -> Result<(), InferenceError> {
     println!("[LlmInference] unload_model called");
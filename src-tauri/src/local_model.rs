@@ -19,7 +19,7 @@ IfAI Editor - Local Model Management
 - Windows: %USERPROFILE%\.ifai\models\
 */
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -57,13 +57,14 @@ pub struct LocalModelConfig {
 
 impl Default for LocalModelConfig {
     fn default() -> Self {
-        let model_path = Self::default_model_path();
+        // 补全任务当前选择的模型（未选择时为内置默认模型）
+        let model_path = crate::model_registry::active_model_path(crate::model_registry::ModelTask::Completion);
         let model_exists = model_path.exists();
 
         let enabled = model_exists;
 
         Self {
-            model_name: "qwen2.5-coder-0.5b-ifai-v3-Q4_K_M.gguf".to_string(),
+            model_name: model_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
             model_path,
             enabled,
             max_seq_length: 2048,
@@ -119,6 +120,18 @@ impl LocalModelConfig {
 
         let file_size = metadata.len();
 
+        if self.model_name != crate::model_registry::DEFAULT_MODEL_FILE {
+            // 用户选择的其他模型：以 GGUF 头部为准
+            let gguf = crate::model_registry::read_gguf_metadata(&self.model_path)?;
+            return Ok(ModelInfo {
+                path: self.model_path.to_string_lossy().to_string(),
+                size_mb: file_size as f64 / 1_000_000.0,
+                size_bytes: file_size,
+                format: format!("GGUF ({})", gguf.quantization.as_deref().unwrap_or("unknown")),
+                model: gguf.name.unwrap_or_else(|| self.model_name.clone()),
+            });
+        }
+
         // Q4_K_M 应该在 350-400MB 之间
         if file_size < 300_000_000 || file_size > 500_000_000 {
             return Err(format!(
@@ -170,13 +183,16 @@ pub struct ModelDownloadConfig {
     /// 下载 URL
     pub url: String,
 
+    /// 备用下载地址（前一个地址失败时依次尝试）
+    pub mirrors: Vec<String>,
+
     /// 文件名
     pub filename: String,
 
     /// 预期文件大小（字节）
     pub expected_size: u64,
 
-    /// SHA256 校验和（可选）
+    /// SHA256 校验和（可选；未配置时使用下载地址公布的摘要，见 `published_sha256`）
     pub checksum: Option<String>,
 }
 
@@ -187,6 +203,7 @@ impl Default for ModelDownloadConfig {
 
         Self {
             url,
+            mirrors: Vec::new(),
             filename: "qwen2.5-coder-0.5b-ifai-v3-Q4_K_M.gguf".to_string(),
             expected_size: 397_807_552, // 379.4MB（实际文件大小）
             checksum: None,
@@ -234,6 +251,8 @@ impl Default for DownloadState {
 pub enum DownloadStatus {
     NotStarted,
     Downloading,
+    /// 下载完成，正在校验 SHA256
+    Verifying,
     Completed,
     Failed(String),
     Cancelled,
@@ -242,14 +261,18 @@ pub enum DownloadStatus {
 /// 下载管理器（内部状态）
 struct DownloadManager {
     state: Arc<Mutex<DownloadState>>,
-    cancel_flag: Arc<AtomicBool>,
+    /// 下载任务在任务队列中的 ID（取消时通过队列通知）
+    job_id: Mutex<Option<String>>,
+    /// 正在下载的目标文件
+    output_path: Mutex<Option<PathBuf>>,
 }
 
 impl DownloadManager {
     fn new() -> Self {
         Self {
             state: Arc::new(Mutex::new(DownloadState::default())),
-            cancel_flag: Arc::new(AtomicBool::new(false)),
+            job_id: Mutex::new(None),
+            output_path: Mutex::new(None),
         }
     }
 
@@ -262,6 +285,21 @@ impl DownloadManager {
 static DOWNLOAD_MANAGER: once_cell::sync::Lazy<DownloadManager> =
     once_cell::sync::Lazy::new(DownloadManager::new);
 
+/// 下载中的临时文件后缀，完成后重命名为正式文件名
+pub const PARTIAL_DOWNLOAD_EXT: &str = "part";
+
+/// 下载过程中写入的临时文件路径
+pub fn partial_download_path(output_path: &Path) -> PathBuf {
+    let mut name = output_path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(format!(".{}", PARTIAL_DOWNLOAD_EXT));
+    output_path.with_file_name(name)
+}
+
+/// 是否有下载正在进行
+pub async fn is_downloading() -> bool {
+    matches!(DOWNLOAD_MANAGER.get_state().await.status, DownloadStatus::Downloading | DownloadStatus::Verifying)
+}
+
 // ============================================================================
 // Tauri Commands
 // ============================================================================
@@ -435,34 +473,9 @@ pub fn test_tool_parse(text: String) -> Vec<ParsedToolCall> {
         // 因为需要多行内容解析
     }
 
-    // 模式3: /命令 格式 (如 /explore, /read)
-    if text.starts_with('/') {
-        let cmd_pattern = regex::Regex::new(r"/(\w+)(?:\s+(.+))?$").unwrap();
-        if let Some(cap) = cmd_pattern.captures(&text) {
-            if let Some(cmd) = cap.get(1) {
-                let cmd_str = cmd.as_str();
-                let arg = cap.get(2).map(|m| m.as_str()).unwrap_or(".");
-                match cmd_str {
-                    "explore" | "scan" => {
-                        let mut args = HashMap::new();
-                        args.insert("rel_path".to_string(), arg.to_string());
-                        calls.push(ParsedToolCall {
-                            name: "agent_list_dir".to_string(),
-                            arguments: args,
-                        });
-                    }
-                    "read" => {
-                        let mut args = HashMap::new();
-                        args.insert("rel_path".to_string(), arg.to_string());
-                        calls.push(ParsedToolCall {
-                            name: "agent_read_file".to_string(),
-                            arguments: args,
-                        });
-                    }
-                    _ => {}
-                }
-            }
-        }
+    // 模式3: /命令 格式 (如 /explore, /read)，由斜杠命令注册表统一解析
+    if let Some((name, arguments)) = crate::slash_commands::to_tool_call(&text) {
+        calls.push(ParsedToolCall { name, arguments });
     }
 
     calls
@@ -478,10 +491,49 @@ pub async fn get_download_status() -> DownloadState {
     DOWNLOAD_MANAGER.get_state().await
 }
 
+/// 模型下载任务的类型（见 `job_queue`）
+pub const DOWNLOAD_JOB_KIND: &str = "model_download";
+
 /// 开始下载模型
+///
+/// `model_id` 为模型目录（`list_local_models`）中的条目，未提供时下载内置默认模型。
+/// 下载作为后台任务提交到任务队列，应用重启后从 `.part` 临时文件断点继续
 #[tauri::command]
-pub async fn start_download(app: AppHandle) -> Result<DownloadState, String> {
-    let config = ModelDownloadConfig::default();
+pub async fn start_download(model_id: Option<String>) -> Result<DownloadState, String> {
+    if is_downloading().await {
+        return Err("已有模型正在下载".to_string());
+    }
+    let config = match &model_id {
+        Some(id) => crate::model_registry::download_config(id)?,
+        None => ModelDownloadConfig::default(),
+    };
+
+    let job = crate::job_queue::enqueue(
+        DOWNLOAD_JOB_KIND,
+        serde_json::json!({ "model_id": model_id }),
+        crate::job_queue::JobPriority::High,
+        1,
+    )?;
+    *DOWNLOAD_MANAGER.job_id.lock().await = Some(job.id);
+
+    // 更新状态为下载中
+    {
+        let mut state = DOWNLOAD_MANAGER.state.lock().await;
+        state.status = DownloadStatus::Downloading;
+        state.progress = 0;
+        state.bytes_downloaded = 0;
+        state.total_bytes = config.expected_size;
+    }
+
+    Ok(DOWNLOAD_MANAGER.get_state().await)
+}
+
+/// 执行下载任务（任务队列处理器）：依次尝试主地址和备用地址，被取消时不再尝试
+pub async fn run_download_job(app: AppHandle, payload: serde_json::Value, cancel_flag: Arc<AtomicBool>) -> Result<serde_json::Value, String> {
+    let config = match payload["model_id"].as_str() {
+        Some(id) => crate::model_registry::download_config(id)?,
+        None => ModelDownloadConfig::default(),
+    };
     let model_dir = LocalModelConfig::model_dir();
 
     // 确保模型目录存在
@@ -489,69 +541,88 @@ pub async fn start_download(app: AppHandle) -> Result<DownloadState, String> {
         .map_err(|e| format!("无法创建模型目录: {}", e))?;
 
     let output_path = model_dir.join(&config.filename);
+    *DOWNLOAD_MANAGER.output_path.lock().await = Some(output_path.clone());
 
-    // 重置取消标志
-    DOWNLOAD_MANAGER.cancel_flag.store(false, Ordering::SeqCst);
-
-    // 更新状态为下载中
+    // 重启后恢复的任务同样更新为下载中
     {
         let mut state = DOWNLOAD_MANAGER.state.lock().await;
         state.status = DownloadStatus::Downloading;
-        state.progress = 0;
-        state.bytes_downloaded = 0;
         state.total_bytes = config.expected_size;
     }
 
-    // 启动下载任务
     let state = DOWNLOAD_MANAGER.state.clone();
-    let state_for_error = state.clone();
-    let cancel_flag = DOWNLOAD_MANAGER.cancel_flag.clone();
-
-    tokio::spawn(async move {
-        if let Err(e) = download_file(
-            &config.url,
+    let mut result = Err("没有可用的下载地址".to_string());
+    for url in std::iter::once(&config.url).chain(config.mirrors.iter()) {
+        result = download_file(
+            url,
             &output_path,
-            state,
-            cancel_flag,
+            state.clone(),
+            cancel_flag.clone(),
             config.expected_size,
-            app,
-        ).await
-        {
-            let mut s = state_for_error.lock().await;
-            s.status = DownloadStatus::Failed(e);
+            config.checksum.as_deref(),
+            app.clone(),
+        ).await;
+        match &result {
+            Err(e) if !cancel_flag.load(Ordering::SeqCst) => println!("[Download] {} 失败: {}", url, e),
+            _ => break,
         }
-    });
+    }
 
-    Ok(DOWNLOAD_MANAGER.get_state().await)
+    if let Err(e) = &result {
+        let cancelled = cancel_flag.load(Ordering::SeqCst);
+        if !cancelled {
+            crate::notifications::notify(&app, crate::notifications::NotificationTrigger::ModelDownloaded, "Model download failed", e);
+        }
+        let mut s = state.lock().await;
+        s.status = if cancelled { DownloadStatus::Cancelled } else { DownloadStatus::Failed(e.clone()) };
+    }
+    result.map(|_| serde_json::json!({ "file": config.filename }))
 }
 
 /// 取消下载
+///
+/// 通过任务队列取消下载任务。默认保留 `.part` 临时文件，再次 `start_download` 时从断点继续（即暂停）；
+/// `discard` 为 true 时删除临时文件。长期未继续的临时文件由 `janitor` 清理
 #[tauri::command]
-pub async fn cancel_download() -> Result<(), String> {
-    DOWNLOAD_MANAGER.cancel_flag.store(true, Ordering::SeqCst);
+pub async fn cancel_download(app: AppHandle, discard: Option<bool>) -> Result<(), String> {
+    if let Some(job_id) = DOWNLOAD_MANAGER.job_id.lock().await.take() {
+        if let Err(e) = crate::job_queue::cancel(&app, &job_id) {
+            println!("[Download] {}", e);
+        }
+    }
 
-    // 删除已下载的部分文件
-    let model_path = LocalModelConfig::default_model_path();
-    if model_path.exists() {
-        std::fs::remove_file(&model_path)
-            .map_err(|e| format!("无法删除部分文件: {}", e))?;
+    if discard.unwrap_or(false) {
+        let output_path = DOWNLOAD_MANAGER.output_path.lock().await.clone()
+            .unwrap_or_else(LocalModelConfig::default_model_path);
+        let partial_path = partial_download_path(&output_path);
+        if partial_path.exists() {
+            std::fs::remove_file(&partial_path)
+                .map_err(|e| format!("无法删除部分文件: {}", e))?;
+        }
     }
 
     {
         let mut state = DOWNLOAD_MANAGER.state.lock().await;
-        state.status = DownloadStatus::Cancelled;
+        if matches!(state.status, DownloadStatus::Downloading | DownloadStatus::Verifying) {
+            state.status = DownloadStatus::Cancelled;
+        }
     }
 
     Ok(())
 }
 
 /// 下载文件（内部函数）
+///
+/// 已有 `.part` 临时文件时通过 HTTP Range 从断点继续，服务器不支持 Range 时从头下载；
+/// 完成后按 `checksum`（未提供时按下载地址公布的摘要）校验 SHA256（`Verifying` 状态），
+/// 校验失败删除临时文件
 async fn download_file(
     url: &str,
     output_path: &PathBuf,
     state: Arc<Mutex<DownloadState>>,
     cancel_flag: Arc<AtomicBool>,
     total_size: u64,
+    checksum: Option<&str>,
     app: AppHandle,
 ) -> Result<(), String> {
     println!("[Download] 开始下载: {}", url);
@@ -561,17 +632,51 @@ async fn download_file(
         .build()
         .map_err(|e| format!("创建 HTTP 客户端失败: {}", e))?;
 
-    let response = client.get(url)
+    let published = match checksum {
+        Some(_) => None,
+        None => published_sha256(url).await,
+    };
+    let checksum = checksum.or(published.as_deref());
+    if checksum.is_none() {
+        println!("[Download] 未找到 SHA256 摘要，跳过校验: {}", url);
+    }
+
+    // 先写入 .part 临时文件，完成后再重命名，崩溃时不会留下残缺的模型文件
+    let partial_path = partial_download_path(output_path);
+    let mut resume_from = tokio::fs::metadata(&partial_path).await.map(|m| m.len()).unwrap_or(0);
+
+    let mut request = client.get(url);
+    if resume_from > 0 {
+        println!("[Download] 从断点继续: {} bytes", resume_from);
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-", resume_from));
+    }
+    let mut response = request
         .send()
         .await
         .map_err(|e| format!("请求失败: {}", e))?;
 
+    if response.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        // 临时文件与服务器上的文件不一致（如超出文件大小），从头下载
+        println!("[Download] 断点无效，从头下载");
+        resume_from = 0;
+        response = client.get(url)
+            .send()
+            .await
+            .map_err(|e| format!("请求失败: {}", e))?;
+    }
+
     if !response.status().is_success() {
         return Err(format!("HTTP 错误: {}", response.status()));
     }
 
-    // 获取实际文件大小
-    let total_bytes_from_server = response.content_length();
+    // 服务器忽略 Range 时返回 200 和完整文件
+    if resume_from > 0 && response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        println!("[Download] 服务器不支持断点续传，从头下载");
+        resume_from = 0;
+    }
+
+    // 获取实际文件大小（206 响应的 Content-Length 为剩余部分）
+    let total_bytes_from_server = response.content_length().map(|len| len + resume_from);
     let total_bytes = total_bytes_from_server.unwrap_or_else(|| {
         println!("[Download] 服务器未返回 Content-Length，使用配置的大小: {}MB", total_size / 1024 / 1024);
         total_size
@@ -581,11 +686,16 @@ async fn download_file(
         println!("[Download] 服务器返回文件大小: {}MB ({} bytes)", size / 1024 / 1024, size);
     }
 
-    let mut file = tokio::fs::File::create(output_path)
-        .await
-        .map_err(|e| format!("创建文件失败: {}", e))?;
+    let mut file = if resume_from > 0 {
+        tokio::fs::OpenOptions::new().append(true).open(&partial_path).await
+    } else {
+        tokio::fs::File::create(&partial_path).await
+    }
+    .map_err(|e| format!("创建文件失败: {}", e))?;
 
-    let mut downloaded: u64 = 0;
+    let mut downloaded: u64 = resume_from;
+    // 上一个镜像可能停在校验阶段
+    state.lock().await.status = DownloadStatus::Downloading;
     let mut start_time = Instant::now();
     let mut last_update_time = Instant::now();
     let mut last_log_time = Instant::now();
@@ -617,7 +727,7 @@ async fn download_file(
             };
 
             let speed = if start_time.elapsed().as_secs() > 0 {
-                downloaded / start_time.elapsed().as_secs()
+                (downloaded - resume_from) / start_time.elapsed().as_secs()
             } else {
                 0
             };
@@ -659,6 +769,42 @@ async fn download_file(
     }
 
     // 下载完成
+    tokio::io::AsyncWriteExt::flush(&mut file)
+        .await
+        .map_err(|e| format!("写入文件失败: {}", e))?;
+    drop(file);
+
+    if let Some(expected) = checksum {
+        {
+            let mut s = state.lock().await;
+            s.status = DownloadStatus::Verifying;
+            s.progress = 100;
+            s.speed = 0;
+            s.eta = 0;
+        }
+        let _ = app.emit("model-download-progress", &DownloadState {
+            status: DownloadStatus::Verifying,
+            progress: 100,
+            bytes_downloaded: downloaded,
+            total_bytes,
+            speed: 0,
+            eta: 0,
+        });
+
+        let path = partial_path.clone();
+        let actual = tokio::task::spawn_blocking(move || sha256_file(&path))
+            .await
+            .map_err(|e| format!("校验任务失败: {}", e))??;
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            let _ = tokio::fs::remove_file(&partial_path).await;
+            return Err(format!("SHA256 校验失败: 预期 {}，实际 {}", expected, actual));
+        }
+        println!("[Download] SHA256 校验通过");
+    }
+
+    tokio::fs::rename(&partial_path, output_path)
+        .await
+        .map_err(|e| format!("重命名下载文件失败: {}", e))?;
     println!("[Download] 下载完成: {} bytes", downloaded);
     {
         let mut s = state.lock().await;
@@ -667,6 +813,8 @@ async fn download_file(
         s.bytes_downloaded = total_bytes;
     }
 
+    crate::notifications::notify(&app, crate::notifications::NotificationTrigger::ModelDownloaded, "Model download finished", "The local model is ready to use.");
+
     // 发送完成事件
     let _ = app.emit("model-download-complete", &DownloadState {
         status: DownloadStatus::Completed,
@@ -680,6 +828,44 @@ async fn download_file(
     Ok(())
 }
 
+/// 下载地址公布的 SHA256
+///
+/// Hugging Face（及其镜像）的 `resolve` 地址在重定向响应的 `X-Linked-Etag` 中给出 LFS 文件的 SHA256，
+/// 其他地址返回 `None`
+async fn published_sha256(url: &str) -> Option<String> {
+    let client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(Duration::from_secs(15))
+        .build()
+        .ok()?;
+    let response = client.head(url).send().await.ok()?;
+    parse_linked_etag(response.headers().get("x-linked-etag")?.to_str().ok()?)
+}
+
+/// 解析 `X-Linked-Etag`（带引号的 64 位十六进制）；不是 SHA256 格式时返回 `None`
+fn parse_linked_etag(value: &str) -> Option<String> {
+    let digest = value.trim().trim_start_matches("W/").trim_matches('"').to_lowercase();
+    (digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit())).then_some(digest)
+}
+
+/// 计算文件的 SHA256（十六进制小写）
+fn sha256_file(path: &Path) -> Result<String, String> {
+    use sha2::{Digest, Sha256};
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path).map_err(|e| format!("无法读取下载文件: {}", e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let n = file.read(&mut buf).map_err(|e| format!("无法读取下载文件: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 // ============================================================================
 // Response Types
 // ============================================================================
@@ -718,6 +904,27 @@ pub struct PreprocessResult {
 
     /// 路由原因
     pub route_reason: String,
+
+    /// 路由决策 ID（v0.3.4），用户改用云端 / 本地时通过 `record_route_feedback` 反馈
+    #[serde(default)]
+    pub decision_id: Option<String>,
+}
+
+/// 本地生成的元数据（用于复现）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationMetadata {
+    pub sampling: crate::llm_inference::SamplingParams,
+    pub max_tokens: usize,
+    /// 模型文件名
+    pub model: String,
+    pub elapsed_ms: u64,
+}
+
+/// 本地代码补全结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalCompletionResult {
+    pub text: String,
+    pub metadata: GenerationMetadata,
 }
 
 /// 本地模型预处理 - 智能路由决策
@@ -746,6 +953,7 @@ pub async fn local_model_preprocess(
             tool_calls: vec![],
             local_response: None,
             route_reason: "模型文件不存在".to_string(),
+            decision_id: None,
         });
     }
 
@@ -756,47 +964,53 @@ pub async fn local_model_preprocess(
 
     let decision = router.decide_route(&messages).await;
     println!("[LocalModel] Route decision: {:?}", decision);
+    let features = router.route_features(&messages);
 
-    match decision {
+    let mut result = match decision {
         crate::intelligence_router::RouteDecision::Local { reason } => {
             // 使用本地模型
             println!("[LocalModel] ✅ Route: Local - {}", reason);
             
             // 🔥 针对 Windows 的安全性增强：
             // 如果本地模型未启用（Windows 默认），且无法直接解析出工具调用，则强制路由到云端
-            if !model_enabled {
-                let tool_calls = try_parse_tool_calls_from_messages(&messages).await;
-                if tool_calls.is_empty() {
-                    println!("[LocalModel] 🛡️ Windows Safety: Local model disabled and no explicit tools found, routing to Cloud");
-                    return Ok(PreprocessResult {
-                        should_use_local: false,
-                        has_tool_calls: false,
-                        tool_calls: vec![],
-                        local_response: None,
-                        route_reason: format!("{} (Windows 安全回退到云端)", reason),
-                    });
+            if !model_enabled && try_parse_tool_calls_from_messages(&messages).await.is_empty() {
+                println!("[LocalModel] 🛡️ Windows Safety: Local model disabled and no explicit tools found, routing to Cloud");
+                PreprocessResult {
+                    should_use_local: false,
+                    has_tool_calls: false,
+                    tool_calls: vec![],
+                    local_response: None,
+                    route_reason: format!("{} (Windows 安全回退到云端)", reason),
+                    decision_id: None,
                 }
+            } else {
+                process_with_local_model(messages, reason).await?
             }
-            
-            process_with_local_model(messages, reason).await
         }
         crate::intelligence_router::RouteDecision::Cloud { reason } => {
             // 转发云端
             println!("[LocalModel] ☁️ Route: Cloud - {}", reason);
-            Ok(PreprocessResult {
+            PreprocessResult {
                 should_use_local: false,
                 has_tool_calls: false,
                 tool_calls: vec![],
                 local_response: None,
                 route_reason: reason,
-            })
+                decision_id: None,
+            }
         }
         crate::intelligence_router::RouteDecision::Hybrid { reason } => {
             // 混合模式：先尝试解析，然后让本地模型推理
             println!("[LocalModel] 🔄 Route: Hybrid - {}", reason);
-            process_with_local_model(messages, reason).await
+            process_with_local_model(messages, reason).await?
         }
+    };
+
+    // v0.3.4: 记录最终采用的路由，供用户纠正后学习
+    if let Some(features) = features {
+        result.decision_id = crate::intelligence_router::record_route(features, result.should_use_local);
     }
+    Ok(result)
 }
 
 /// 使用本地模型处理（进行推理后再判断）
@@ -817,6 +1031,7 @@ async fn process_with_local_model(
             tool_calls: tool_calls.clone(),
             local_response: None,
             route_reason: format!("{} - 解析到 {} 个工具调用", reason, tool_calls.len()),
+            decision_id: None,
         });
     }
 
@@ -829,6 +1044,7 @@ async fn process_with_local_model(
         tool_calls: vec![],
         local_response: None,
         route_reason: format!("{} - 需要本地模型推理来判断", reason),
+        decision_id: None,
     })
 }
 
@@ -853,6 +1069,31 @@ async fn try_parse_tool_calls_from_messages(
     }
 }
 
+/// 构造 Qwen2.5-Coder 的 FIM Prompt
+///
+/// 格式: `<|fim_prefix|>{prefix}<|fim_suffix|>{suffix}<|fim_middle|>`，
+/// prefix 保留末尾 1000 字节、suffix 保留开头 500 字节（按字符边界截断）
+#[cfg(feature = "llm-inference")]
+fn fim_prompt(prefix: &str, suffix: &str) -> String {
+    let mut start = prefix.len().saturating_sub(1000);
+    while !prefix.is_char_boundary(start) {
+        start += 1;
+    }
+    let mut end = suffix.len().min(500);
+    while !suffix.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("<|fim_prefix|>{}<|fim_suffix|>{}<|fim_middle|>", &prefix[start..], &suffix[..end])
+}
+
+/// 补全类请求的推理超时（秒）；超时后生成被取消，前端回退到云端 API
+#[cfg(feature = "llm-inference")]
+const LOCAL_COMPLETION_TIMEOUT_SECS: u64 = 30;
+
+/// 流式生成的推理超时（秒）
+#[cfg(feature = "llm-inference")]
+const LOCAL_STREAM_TIMEOUT_SECS: u64 = 180;
+
 /// 本地模型 FIM (Fill-In-the-Middle) 代码补全
 #[tauri::command]
 pub async fn local_model_fim(
@@ -879,23 +1120,16 @@ pub async fn local_model_fim(
 
     #[cfg(feature = "llm-inference")]
     {
-        use crate::llm_inference::generate_completion;
-
-        // 构造 Qwen2.5-Coder 的 FIM Prompt 格式
-        // 格式: <|fim_prefix|>{prefix}<|fim_suffix|>{suffix}<|fim_middle|>
-        let prompt = format!("<|fim_prefix|>{}{}<|fim_suffix|>{}{}<|fim_middle|>", 
-            if prefix.len() > 1000 { &prefix[prefix.len()-1000..] } else { &prefix },
-            "", // Placeholder for potential middle content if needed
-            if suffix.len() > 500 { &suffix[..500] } else { &suffix },
-            ""
-        );
+        use crate::llm_inference::{generate_completion, run_with_timeout};
+
+        let prompt = fim_prompt(&prefix, &suffix);
 
         let max_tokens_val = max_tokens.unwrap_or(128);
 
-        // 使用 spawn_blocking
-        let result = tokio::task::spawn_blocking(move || {
+        // 在阻塞线程池中运行，超时后取消生成
+        let result = run_with_timeout(Duration::from_secs(LOCAL_COMPLETION_TIMEOUT_SECS), move || {
             generate_completion(&prompt, max_tokens_val)
-        }).await.map_err(|e| format!("任务调度失败: {}", e))?;
+        }).await;
 
         match result {
             Ok(text) => {
@@ -923,17 +1157,22 @@ pub async fn local_model_fim(
 ///
 /// 使用 llama.cpp 进行本地模型推理。
 /// 如果本地推理失败，返回错误让前端回退到云端 API。
+/// `sampling` 未提供时使用默认参数（贪心解码，固定种子），相同参数下输出可复现。
 #[tauri::command]
 pub async fn local_code_completion(
     prompt: String,
     max_tokens: Option<usize>,
-) -> Result<String, String> {
+    sampling: Option<crate::llm_inference::SamplingParams>,
+) -> Result<LocalCompletionResult, String> {
     use std::time::Instant;
 
     let start_time = Instant::now();
     println!("[LocalCompletion] Request received");
     println!("[LocalCompletion] Prompt length: {}", prompt.len());
 
+    let sampling = sampling.unwrap_or_default();
+    sampling.validate()?;
+
     // 检查模型是否可用
     let config = LocalModelConfig::default();
     if !config.model_path.exists() {
@@ -957,21 +1196,29 @@ pub async fn local_code_completion(
 
     #[cfg(feature = "llm-inference")]
     {
-        use crate::llm_inference::generate_completion;
+        use crate::llm_inference::{generate_completion_with, run_with_timeout};
 
         let max_tokens_val = max_tokens.unwrap_or(50);
 
-        // 使用 spawn_blocking 在专用线程池中运行同步推理任务
-        // 这样可以避免阻塞 tokio 的工作线程，从而保持 UI 响应
-        let result = tokio::task::spawn_blocking(move || {
-            generate_completion(&prompt, max_tokens_val)
-        }).await.map_err(|e| format!("任务调度失败: {}", e))?;
+        // 在专用线程池中运行同步推理任务，避免阻塞 tokio 的工作线程，从而保持 UI 响应；
+        // 超时后取消生成，避免失控的生成一直占用模型
+        let result = run_with_timeout(Duration::from_secs(LOCAL_COMPLETION_TIMEOUT_SECS), move || {
+            generate_completion_with(&prompt, max_tokens_val, &sampling)
+        }).await;
 
         match result {
             Ok(text) => {
                 let elapsed = start_time.elapsed();
                 println!("[LocalCompletion] ✓ Success: {} chars in {:?}", text.len(), elapsed);
-                Ok(text)
+                Ok(LocalCompletionResult {
+                    text,
+                    metadata: GenerationMetadata {
+                        sampling,
+                        max_tokens: max_tokens_val,
+                        model: config.model_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+                        elapsed_ms: elapsed.as_millis() as u64,
+                    },
+                })
             }
             Err(e) => {
                 let elapsed = start_time.elapsed();
@@ -982,6 +1229,169 @@ pub async fn local_code_completion(
     }
 }
 
+// ============================================================================
+// Streaming Generation
+// ============================================================================
+
+/// 流式输出在特殊标记处截止（模型有时会继续输出 `<|...|>` 标记）
+#[cfg(feature = "llm-inference")]
+const STREAM_STOP_MARKER: &str = "<|";
+
+/// `local-llm://{event_id}` 事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LocalStreamEvent {
+    Token { text: String },
+    Done { text: String, metadata: GenerationMetadata },
+    Cancelled { text: String },
+    Error { message: String },
+}
+
+/// 截止标记过滤：可能是标记开头的尾部字符暂缓输出
+#[derive(Debug, Default)]
+#[cfg(feature = "llm-inference")]
+struct StopFilter {
+    held: String,
+    stopped: bool,
+}
+
+#[cfg(feature = "llm-inference")]
+impl StopFilter {
+    /// 返回可以输出的文本
+    fn push(&mut self, text: &str) -> String {
+        if self.stopped {
+            return String::new();
+        }
+        self.held.push_str(text);
+        if let Some(pos) = self.held.find(STREAM_STOP_MARKER) {
+            self.stopped = true;
+            let out = self.held[..pos].to_string();
+            self.held.clear();
+            return out;
+        }
+        let keep = if self.held.ends_with('<') { 1 } else { 0 };
+        let out = self.held[..self.held.len() - keep].to_string();
+        self.held.drain(..self.held.len() - keep);
+        out
+    }
+
+    fn finish(&mut self) -> String {
+        std::mem::take(&mut self.held)
+    }
+}
+
+/// 本地模型流式生成
+///
+/// 生成的文本片段以 `local-llm://{event_id}` 事件逐个发送，结束时发送 `done`（或 `cancelled` / `error`）。
+/// 提供 `suffix` 时 `prompt` 作为前缀构造 FIM Prompt，用于流式代码补全。
+/// 可通过 `cancel_ai_request(event_id)` 或 `cancel_local_generation` 中止，超过时限时发送 `error`。
+#[tauri::command]
+pub async fn local_model_stream(
+    app: AppHandle,
+    event_id: String,
+    prompt: String,
+    suffix: Option<String>,
+    max_tokens: Option<usize>,
+    sampling: Option<crate::llm_inference::SamplingParams>,
+) -> Result<(), String> {
+    let sampling = sampling.unwrap_or_default();
+    sampling.validate()?;
+
+    let config = LocalModelConfig::default();
+    if !config.model_path.exists() {
+        return Err("本地模型文件不存在".to_string());
+    }
+
+    #[cfg(not(feature = "llm-inference"))]
+    {
+        let _ = (app, event_id, prompt, suffix, max_tokens);
+        return Err("本地推理功能未启用".to_string());
+    }
+
+    #[cfg(feature = "llm-inference")]
+    {
+        let channel = format!("local-llm://{}", event_id);
+        let prompt = match &suffix {
+            Some(suffix) => fim_prompt(&prompt, suffix),
+            None => prompt,
+        };
+        let max_tokens_val = max_tokens.unwrap_or(if suffix.is_some() { 128 } else { 512 });
+        let cancel = crate::cancellation::register(&event_id);
+        let start_time = Instant::now();
+        println!("[LocalStream] {} started (max_tokens: {}, fim: {})", event_id, max_tokens_val, suffix.is_some());
+
+        let emit_app = app.clone();
+        let emit_channel = channel.clone();
+        let timeout = Duration::from_secs(LOCAL_STREAM_TIMEOUT_SECS);
+        let result = crate::llm_inference::run_with_timeout(timeout, move || {
+            let mut filter = StopFilter::default();
+            let mut text = String::new();
+            let generated = crate::llm_inference::generate_completion_stream(&prompt, max_tokens_val, &sampling, |token| {
+                let out = filter.push(token);
+                if !out.is_empty() {
+                    text.push_str(&out);
+                    let _ = emit_app.emit(&emit_channel, &LocalStreamEvent::Token { text: out });
+                }
+                !filter.stopped && !cancel.is_cancelled()
+            });
+            if !filter.stopped {
+                let rest = filter.finish();
+                if !rest.is_empty() {
+                    text.push_str(&rest);
+                    let _ = emit_app.emit(&emit_channel, &LocalStreamEvent::Token { text: rest });
+                }
+            }
+            match generated {
+                Ok(_) => Ok((text, cancel.is_cancelled())),
+                Err(crate::llm_inference::InferenceError::Cancelled) => Ok((text, true)),
+                Err(e) => Err(e),
+            }
+        }).await;
+
+        let elapsed = start_time.elapsed();
+        let event = match result {
+            Ok((text, true)) => {
+                println!("[LocalStream] {} cancelled after {:?}", event_id, elapsed);
+                LocalStreamEvent::Cancelled { text }
+            }
+            Ok((text, false)) => {
+                println!("[LocalStream] ✓ {}: {} chars in {:?}", event_id, text.len(), elapsed);
+                LocalStreamEvent::Done {
+                    text,
+                    metadata: GenerationMetadata {
+                        sampling,
+                        max_tokens: max_tokens_val,
+                        model: config.model_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+                        elapsed_ms: elapsed.as_millis() as u64,
+                    },
+                }
+            }
+            Err(e) => {
+                println!("[LocalStream] ✗ {} failed after {:?}: {}", event_id, elapsed, e);
+                LocalStreamEvent::Error { message: format!("本地推理失败: {}", e) }
+            }
+        };
+        let _ = app.emit(&channel, &event);
+        Ok(())
+    }
+}
+
+/// 取消正在进行（含排队等待模型）的本地生成；返回是否有生成被取消
+#[tauri::command]
+pub fn cancel_local_generation() -> bool {
+    #[cfg(feature = "llm-inference")]
+    {
+        let cancelled = crate::llm_inference::cancel_generation();
+        println!("[LocalModel] Cancel requested (active generation: {})", cancelled);
+        cancelled
+    }
+
+    #[cfg(not(feature = "llm-inference"))]
+    {
+        false
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -997,6 +1407,40 @@ mod tests {
         assert!(path.to_string_lossy().contains("models"));
     }
 
+    #[test]
+    #[cfg(feature = "llm-inference")]
+    fn test_fim_prompt_truncation() {
+        let prompt = fim_prompt(&"a".repeat(1200), "éb");
+        assert!(prompt.starts_with(&format!("<|fim_prefix|>{}<|fim_suffix|>", "a".repeat(1000))));
+        assert!(prompt.ends_with("éb<|fim_middle|>"));
+        // 截断不落在多字节字符中间
+        let suffix = format!("{}é", "x".repeat(499));
+        assert!(fim_prompt("", &suffix).ends_with(&format!("{}<|fim_middle|>", "x".repeat(499))));
+    }
+
+    #[test]
+    #[cfg(feature = "llm-inference")]
+    fn test_stream_stop_filter() {
+        let mut filter = StopFilter::default();
+        assert_eq!(filter.push("fn main() {"), "fn main() {");
+        assert_eq!(filter.push(" a <"), " a ");
+        assert_eq!(filter.push(" b"), "< b");
+        assert_eq!(filter.push("}<"), "}");
+        assert_eq!(filter.push("|fim_pad|>more"), "");
+        assert!(filter.stopped);
+        assert_eq!(filter.push("ignored"), "");
+
+        let mut open = StopFilter::default();
+        assert_eq!(open.push("x <"), "x ");
+        assert_eq!(open.finish(), "<");
+    }
+
+    #[test]
+    fn test_partial_download_path() {
+        let path = PathBuf::from("/models/qwen.gguf");
+        assert_eq!(partial_download_path(&path), PathBuf::from("/models/qwen.gguf.part"));
+    }
+
     #[test]
     fn test_download_config() {
         let config = ModelDownloadConfig::default();
@@ -1005,6 +1449,24 @@ mod tests {
         assert_eq!(config.expected_size, 397_807_552); // 379.4MB
     }
 
+    #[test]
+    fn test_parse_linked_etag() {
+        let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(parse_linked_etag(&format!("\"{}\"", digest.to_uppercase())).as_deref(), Some(digest));
+        assert_eq!(parse_linked_etag(digest).as_deref(), Some(digest));
+        // 普通 git 文件的 ETag 是 40 位的 git blob SHA1
+        assert_eq!(parse_linked_etag("\"a9993e364706816aba3e25717850c26c9cd0d89d\""), None);
+        assert_eq!(parse_linked_etag("\"not-a-digest\""), None);
+    }
+
+    #[test]
+    fn test_sha256_file() {
+        let path = std::env::temp_dir().join(format!("ifai_sha256_{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "abc").unwrap();
+        assert_eq!(sha256_file(&path).unwrap(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let _ = std::fs::remove_file(&path);
+    }
+
     #[test]
     fn test_download_state_default() {
         let state = DownloadState::default();
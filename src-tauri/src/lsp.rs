@@ -1,6 +1,9 @@
 use tauri::{AppHandle, Emitter, command, State};
-use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::{Arc, OnceLock};
 use std::collections::HashMap;
+use std::path::Path;
 use tokio::process::{Command, ChildStdin};
 use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, AsyncBufReadExt};
 use tokio::sync::Mutex;
@@ -73,6 +76,10 @@ pub async fn start_lsp(
                                 let body_bytes: Vec<u8> = buffer.drain(0..len).collect();
                                 if let Ok(msg) = str::from_utf8(&body_bytes) {
                                     // println!("LSP < {}: {}", lang_id, msg); // Verbose log
+                                    if let Some(update) = parse_publish_diagnostics(msg, &lang_id) {
+                                        record_diagnostics(&update);
+                                        app_handle.emit("lsp://diagnostics", &update).unwrap_or(());
+                                    }
                                     app_handle.emit(&format!("lsp-msg-{}", lang_id), msg).unwrap_or(());
                                 }
                                 content_length = None;
@@ -116,6 +123,7 @@ pub async fn start_lsp(
             }
         }
         println!("LSP {} stdout closed", lang_id);
+        clear_diagnostics(&lang_id);
     });
 
     // Spawn stderr reader (for logging)
@@ -167,8 +175,261 @@ pub async fn kill_lsp(state: State<'_, LspManager>, language_id: String) -> Resu
     let mut processes = state.processes.lock().await;
     if let Some(stdin) = processes.remove(&language_id) {
         drop(stdin); // Close stdin
+        clear_diagnostics(&language_id);
         Ok(())
     } else {
         Ok(()) // Already dead
     }
 }
+
+// ============================================================================
+// 诊断汇总：解析 textDocument/publishDiagnostics，按文件保存
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+}
+
+impl DiagnosticSeverity {
+    /// LSP 中 1 = Error … 4 = Hint，缺省按 Error 处理
+    fn from_lsp(value: Option<u64>) -> Self {
+        match value {
+            Some(2) => DiagnosticSeverity::Warning,
+            Some(3) => DiagnosticSeverity::Information,
+            Some(4) => DiagnosticSeverity::Hint,
+            _ => DiagnosticSeverity::Error,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DiagnosticSeverity::Error => "error",
+            DiagnosticSeverity::Warning => "warning",
+            DiagnosticSeverity::Information => "info",
+            DiagnosticSeverity::Hint => "hint",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LspDiagnostic {
+    pub severity: DiagnosticSeverity,
+    /// 行列号从 1 开始（LSP 原始值从 0 开始）
+    pub line: u64,
+    pub column: u64,
+    pub end_line: u64,
+    pub end_column: u64,
+    pub message: String,
+    pub source: Option<String>,
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiagnostics {
+    pub path: String,
+    pub language_id: String,
+    pub version: Option<i64>,
+    /// 为空表示该文件的问题已全部消除
+    pub diagnostics: Vec<LspDiagnostic>,
+    pub updated_at: i64,
+}
+
+fn diagnostics_store() -> &'static std::sync::Mutex<HashMap<String, FileDiagnostics>> {
+    static STORE: OnceLock<std::sync::Mutex<HashMap<String, FileDiagnostics>>> = OnceLock::new();
+    STORE.get_or_init(|| std::sync::Mutex::new(HashMap::new()))
+}
+
+/// `file://` URI 转为本地路径（处理百分号编码与 Windows 盘符）
+fn uri_to_path(uri: &str) -> Option<String> {
+    let rest = uri.strip_prefix("file://")?;
+    let bytes = rest.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let Some(byte) = str::from_utf8(&bytes[i + 1..i + 3]).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    let mut path = String::from_utf8(decoded).ok()?;
+    // file:///C:/x -> C:/x
+    if path.len() >= 3 && path.starts_with('/') && path.as_bytes()[2] == b':' {
+        path.remove(0);
+    }
+    Some(path)
+}
+
+/// 存储用的键：尽量规范化，使编辑器与 Agent 传入的路径能对上
+fn diagnostics_key(path: &str) -> String {
+    let resolved = std::fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string());
+    resolved.trim_start_matches(r"\\?\").replace('\\', "/")
+}
+
+/// 解析 `textDocument/publishDiagnostics` 通知；其他消息返回 `None`
+pub fn parse_publish_diagnostics(msg: &str, language_id: &str) -> Option<FileDiagnostics> {
+    // 大部分消息不是诊断，先做廉价的字符串检查
+    if !msg.contains("textDocument/publishDiagnostics") {
+        return None;
+    }
+    let value: Value = serde_json::from_str(msg).ok()?;
+    if value["method"].as_str() != Some("textDocument/publishDiagnostics") {
+        return None;
+    }
+    let params = &value["params"];
+    let path = uri_to_path(params["uri"].as_str()?)?;
+    let position = |v: &Value| v.as_u64().unwrap_or(0) + 1;
+    let diagnostics = params["diagnostics"]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .map(|d| LspDiagnostic {
+                    severity: DiagnosticSeverity::from_lsp(d["severity"].as_u64()),
+                    line: position(&d["range"]["start"]["line"]),
+                    column: position(&d["range"]["start"]["character"]),
+                    end_line: position(&d["range"]["end"]["line"]),
+                    end_column: position(&d["range"]["end"]["character"]),
+                    message: d["message"].as_str().unwrap_or_default().to_string(),
+                    source: d["source"].as_str().map(String::from),
+                    code: match &d["code"] {
+                        Value::String(code) => Some(code.clone()),
+                        Value::Number(code) => Some(code.to_string()),
+                        _ => None,
+                    },
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(FileDiagnostics {
+        path,
+        language_id: language_id.to_string(),
+        version: params["version"].as_i64(),
+        diagnostics,
+        updated_at: chrono::Utc::now().timestamp(),
+    })
+}
+
+fn record_diagnostics(update: &FileDiagnostics) {
+    let Ok(mut store) = diagnostics_store().lock() else { return };
+    let key = diagnostics_key(&update.path);
+    if update.diagnostics.is_empty() {
+        store.remove(&key);
+    } else {
+        store.insert(key, update.clone());
+    }
+}
+
+/// 语言服务器退出后其诊断不再更新，一并清除
+fn clear_diagnostics(language_id: &str) {
+    if let Ok(mut store) = diagnostics_store().lock() {
+        store.retain(|_, file| file.language_id != language_id);
+    }
+}
+
+/// 指定文件（或目录下所有文件）的诊断；`None` 返回全部，按路径排序
+pub fn diagnostics_for(path: Option<&str>) -> Vec<FileDiagnostics> {
+    let Ok(store) = diagnostics_store().lock() else { return Vec::new() };
+    let key = path.map(diagnostics_key);
+    let mut files: Vec<FileDiagnostics> = store
+        .iter()
+        .filter(|(file_key, _)| match &key {
+            Some(key) => *file_key == key || file_key.starts_with(&format!("{}/", key.trim_end_matches('/'))),
+            None => true,
+        })
+        .map(|(_, file)| file.clone())
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// 供 Agent 阅读的文本：每个问题一行 `path:line:column severity [source code] message`
+pub fn format_diagnostics(files: &[FileDiagnostics], root: &Path) -> String {
+    let root_key = diagnostics_key(&root.to_string_lossy());
+    let mut lines = Vec::new();
+    for file in files {
+        let key = diagnostics_key(&file.path);
+        let display = key.strip_prefix(&format!("{}/", root_key)).unwrap_or(&file.path);
+        let mut diagnostics = file.diagnostics.clone();
+        diagnostics.sort_by_key(|d| (d.severity, d.line, d.column));
+        for d in diagnostics {
+            let origin = match (&d.source, &d.code) {
+                (Some(source), Some(code)) => format!(" [{} {}]", source, code),
+                (Some(source), None) => format!(" [{}]", source),
+                (None, Some(code)) => format!(" [{}]", code),
+                (None, None) => String::new(),
+            };
+            lines.push(format!("{}:{}:{} {}{} {}", display, d.line, d.column, d.severity.label(), origin, d.message));
+        }
+    }
+    lines.join("\n")
+}
+
+/// 获取语言服务器报告的诊断；`path` 可以是文件或目录，为空时返回所有文件
+#[command]
+pub fn get_diagnostics(path: Option<String>) -> Vec<FileDiagnostics> {
+    diagnostics_for(path.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_diagnostics_store() {
+        let dir = std::env::temp_dir().join(format!("ifai_lsp_{} dir", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        let file = dir.join("src").join("main.rs");
+        std::fs::write(&file, "fn main() {}\n").unwrap();
+        let uri = format!("file://{}", file.to_string_lossy().replace(' ', "%20"));
+
+        let publish = |diagnostics: Value| serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "version": 3, "diagnostics": diagnostics }
+        }).to_string();
+        let msg = publish(serde_json::json!([
+            { "range": { "start": { "line": 4, "character": 2 }, "end": { "line": 4, "character": 9 } },
+              "severity": 2, "source": "rustc", "message": "unused variable" },
+            { "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 1 } },
+              "severity": 1, "source": "rustc", "code": "E0308", "message": "mismatched types" }
+        ]));
+        assert!(parse_publish_diagnostics(r#"{"id":1,"result":null}"#, "rust").is_none());
+        let update = parse_publish_diagnostics(&msg, "rust").unwrap();
+        assert_eq!(update.path, file.to_string_lossy());
+        assert_eq!((update.version, update.diagnostics.len()), (Some(3), 2));
+        assert_eq!(update.diagnostics[0].severity, DiagnosticSeverity::Warning);
+        assert_eq!((update.diagnostics[0].line, update.diagnostics[0].column), (5, 3));
+
+        record_diagnostics(&update);
+        let path = file.to_string_lossy().to_string();
+        assert_eq!(diagnostics_for(Some(&path)).len(), 1);
+        let in_dir = diagnostics_for(Some(&dir.to_string_lossy()));
+        assert_eq!(in_dir.len(), 1);
+        assert_eq!(
+            format_diagnostics(&in_dir, &dir),
+            "src/main.rs:1:1 error [rustc E0308] mismatched types\nsrc/main.rs:5:3 warning [rustc] unused variable"
+        );
+
+        // 空列表表示问题已修复
+        record_diagnostics(&parse_publish_diagnostics(&publish(serde_json::json!([])), "rust").unwrap());
+        assert!(diagnostics_for(Some(&path)).is_empty());
+
+        record_diagnostics(&update);
+        clear_diagnostics("rust");
+        assert!(diagnostics_for(Some(&path)).is_empty());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,396 @@
+/*!
+Mention Context - 定向上下文提及
+================================
+
+解析用户消息中的定向提及，在后端展开为有长度上限的上下文块（不经过 RAG）：
+
+- `@file:src/foo.rs`：文件内容，可用 `#L10-20` 指定行范围
+- `@folder:src/utils`：目录结构（遵循 `.gitignore` / `.ifaiignore`）与部分文件内容
+- `@symbol:User::new`：符号定义（来自符号索引，匹配限定名或短名）
+
+每个提及对应的引用随 `{event_id}_mention_references` 事件发送给前端；
+无法解析的提及会在上下文中说明原因，模型可以据此提示用户。
+*/
+
+use ignore::WalkBuilder;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use crate::commands::symbol_commands::SymbolIndexState;
+
+/// 单条消息最多展开的提及数
+const MAX_MENTIONS: usize = 8;
+/// 全部提及合计的最大字符数
+const MAX_TOTAL_CHARS: usize = 48_000;
+/// 单个文件注入的最大行数
+const MAX_FILE_LINES: usize = 400;
+/// 目录列表的最大条目数与遍历深度
+const MAX_FOLDER_ENTRIES: usize = 200;
+const MAX_FOLDER_DEPTH: usize = 4;
+/// 目录提及附带内容的文件数与每个文件的行数
+const MAX_FOLDER_FILES: usize = 8;
+const MAX_FOLDER_FILE_LINES: usize = 120;
+/// 同名符号最多展开的定义数与每个定义的行数
+const MAX_SYMBOL_MATCHES: usize = 3;
+const MAX_SYMBOL_LINES: usize = 200;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Mention {
+    /// `lines` 为从 1 开始的闭区间
+    File { path: String, lines: Option<(usize, usize)> },
+    Folder { path: String },
+    Symbol { name: String },
+}
+
+impl Mention {
+    pub fn token(&self) -> String {
+        match self {
+            Mention::File { path, lines: Some((start, end)) } => format!("@file:{}#L{}-{}", path, start, end),
+            Mention::File { path, lines: None } => format!("@file:{}", path),
+            Mention::Folder { path } => format!("@folder:{}", path),
+            Mention::Symbol { name } => format!("@symbol:{}", name),
+        }
+    }
+}
+
+/// 提及对应的引用（`{event_id}_mention_references` 事件）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MentionReference {
+    /// 原始提及，如 `@symbol:User::new`
+    pub mention: String,
+    /// 相对项目根目录的路径
+    pub file_path: String,
+    pub line_start: usize,
+    pub line_end: usize,
+    /// 内容是否被截断
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MentionContext {
+    pub context: String,
+    pub references: Vec<MentionReference>,
+    /// 无法解析的提及及原因
+    pub unresolved: Vec<String>,
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+fn mention_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?i)(?:^|[\s(（])@(file|folder|symbol):([^\s,;，；、)）`]+)").unwrap())
+}
+
+fn line_range_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^(.+?)#L(\d+)(?:-L?(\d+))?$").unwrap())
+}
+
+/// 按出现顺序解析提及（去重，最多 `MAX_MENTIONS` 个）
+pub fn parse_mentions(message: &str) -> Vec<Mention> {
+    let mut mentions = Vec::new();
+    for caps in mention_pattern().captures_iter(message) {
+        // 句末标点不属于路径
+        let target = caps[2].trim_end_matches(['.', '?', '!', ':', '。', '？', '！', '：']);
+        if target.is_empty() {
+            continue;
+        }
+        let mention = match caps[1].to_lowercase().as_str() {
+            "file" => match line_range_pattern().captures(target) {
+                Some(range) => {
+                    let start: usize = range[2].parse().unwrap_or(1).max(1);
+                    let end = range.get(3).and_then(|m| m.as_str().parse().ok()).unwrap_or(start).max(start);
+                    Mention::File { path: range[1].to_string(), lines: Some((start, end)) }
+                }
+                None => Mention::File { path: target.to_string(), lines: None },
+            },
+            "folder" => Mention::Folder { path: target.trim_end_matches(['/', '\\']).to_string() },
+            _ => Mention::Symbol { name: target.to_string() },
+        };
+        if !mentions.contains(&mention) {
+            mentions.push(mention);
+        }
+        if mentions.len() >= MAX_MENTIONS {
+            break;
+        }
+    }
+    mentions
+}
+
+// ============================================================================
+// Resolution
+// ============================================================================
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).to_string_lossy().replace('\\', "/")
+}
+
+fn fence_language(path: &str) -> &str {
+    Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("")
+}
+
+/// 按行截取：返回 (内容, 实际结束行, 是否截断)
+fn slice_lines(content: &str, start: usize, end: usize, max_lines: usize) -> (String, usize, bool) {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = start.clamp(1, lines.len().max(1));
+    let requested_end = end.min(lines.len()).max(start);
+    let end = requested_end.min(start + max_lines - 1);
+    let text = lines.get(start - 1..end).map(|l| l.join("\n")).unwrap_or_default();
+    (text, end, end < requested_end)
+}
+
+struct Block {
+    text: String,
+    references: Vec<MentionReference>,
+}
+
+fn resolve_file(root: &Path, project_root: &str, path: &str, lines: Option<(usize, usize)>, token: &str) -> Result<Block, String> {
+    let target = crate::commands::core_wrappers::ensure_in_root(project_root, path, "mention")?;
+    if !target.is_file() {
+        return Err("file not found".to_string());
+    }
+    let content = std::fs::read_to_string(&target).map_err(|e| format!("failed to read file: {}", e))?;
+    let (start, end) = lines.unwrap_or((1, usize::MAX));
+    let (text, line_end, truncated) = slice_lines(&content, start, end, MAX_FILE_LINES);
+    let rel = relative(root, &target);
+
+    let mut header = format!("## File `{}`", rel);
+    if lines.is_some() || truncated {
+        header.push_str(&format!(" (lines {}-{} of {})", start.min(line_end), line_end, content.lines().count()));
+    }
+    let mut block = format!("{}\n```{}\n{}\n```", header, fence_language(&rel), text);
+    if truncated {
+        block.push_str(&format!("\n[Truncated after {} lines; mention `@file:{}#L{}-{}` for more]", MAX_FILE_LINES, rel, line_end + 1, line_end + MAX_FILE_LINES));
+    }
+    Ok(Block {
+        text: block,
+        references: vec![MentionReference { mention: token.to_string(), file_path: rel, line_start: start.min(line_end), line_end, truncated }],
+    })
+}
+
+fn resolve_folder(root: &Path, project_root: &str, path: &str, token: &str) -> Result<Block, String> {
+    let target = crate::commands::core_wrappers::ensure_in_root(project_root, path, "mention")?;
+    if !target.is_dir() {
+        return Err("folder not found".to_string());
+    }
+
+    let mut walker = WalkBuilder::new(&target);
+    walker.max_depth(Some(MAX_FOLDER_DEPTH)).sort_by_file_path(|a, b| a.cmp(b));
+    crate::ifai_ignore::apply_project(&mut walker, project_root);
+
+    let mut entries = Vec::new();
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut total = 0;
+    for entry in walker.build().filter_map(|e| e.ok()).filter(|e| e.path() != target) {
+        total += 1;
+        if entries.len() >= MAX_FOLDER_ENTRIES {
+            continue;
+        }
+        let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+        let rel = relative(&target, entry.path());
+        entries.push(if is_dir { format!("{}/", rel) } else { rel });
+        if !is_dir {
+            files.push(entry.into_path());
+        }
+    }
+
+    let rel = relative(root, &target);
+    let mut block = format!("## Folder `{}/`\n```\n{}\n```", rel, entries.join("\n"));
+    if total > entries.len() {
+        block.push_str(&format!("\n[{} more entries omitted]", total - entries.len()));
+    }
+
+    let mut references = Vec::new();
+    // 附带浅层的文本文件内容（先按深度、再按路径）
+    files.sort_by_key(|f| (f.components().count(), f.clone()));
+    for file in files.iter() {
+        if references.len() >= MAX_FOLDER_FILES {
+            break;
+        }
+        let Ok(content) = std::fs::read_to_string(file) else { continue };
+        let (text, line_end, truncated) = slice_lines(&content, 1, usize::MAX, MAX_FOLDER_FILE_LINES);
+        let file_rel = relative(root, file);
+        block.push_str(&format!("\n\n### `{}`\n```{}\n{}\n```", file_rel, fence_language(&file_rel), text));
+        if truncated {
+            block.push_str(&format!("\n[Truncated after {} lines]", MAX_FOLDER_FILE_LINES));
+        }
+        references.push(MentionReference { mention: token.to_string(), file_path: file_rel, line_start: 1, line_end, truncated });
+    }
+    Ok(Block { text: block, references })
+}
+
+fn resolve_symbol(root: &Path, name: &str, index: Option<&SymbolIndexState>, token: &str) -> Result<Block, String> {
+    let index = index.ok_or("symbol index is not available")?;
+    let short_name = !name.contains("::") && !name.contains('.');
+    let mut matches: Vec<(String, u32, Option<u32>, String)> = index
+        .indexed_files()
+        .filter(|fs| Path::new(&fs.path).starts_with(root))
+        .flat_map(|fs| {
+            fs.symbols.iter()
+                .filter(|s| s.qualified_name == name || (short_name && s.name == name))
+                .map(|s| (fs.path.clone(), s.line, s.end_line, format!("{} ({})", s.qualified_name, s.kind)))
+        })
+        .collect();
+    if matches.is_empty() {
+        return Err("symbol not found in the symbol index".to_string());
+    }
+    // 限定名完全匹配的定义优先
+    matches.sort_by_key(|(path, line, _, label)| (!label.starts_with(&format!("{} ", name)), path.clone(), *line));
+    let total = matches.len();
+
+    let mut sections = Vec::new();
+    let mut references = Vec::new();
+    for (path, line, end_line, label) in matches.into_iter().take(MAX_SYMBOL_MATCHES) {
+        let Ok(content) = std::fs::read_to_string(&path) else { continue };
+        let start = line as usize;
+        let end = end_line.map(|e| e as usize).unwrap_or(start);
+        let (text, line_end, truncated) = slice_lines(&content, start, end, MAX_SYMBOL_LINES);
+        let rel = relative(root, Path::new(&path));
+        let mut section = format!("## Symbol {} in `{}` (lines {}-{})\n```{}\n{}\n```", label, rel, start, line_end, fence_language(&rel), text);
+        if truncated {
+            section.push_str(&format!("\n[Truncated after {} lines]", MAX_SYMBOL_LINES));
+        }
+        sections.push(section);
+        references.push(MentionReference { mention: token.to_string(), file_path: rel, line_start: start, line_end, truncated });
+    }
+    if sections.is_empty() {
+        return Err("symbol source files could not be read".to_string());
+    }
+    let mut text = sections.join("\n\n");
+    if total > MAX_SYMBOL_MATCHES {
+        text.push_str(&format!("\n[{} more definitions named `{}` omitted]", total - MAX_SYMBOL_MATCHES, name));
+    }
+    Ok(Block { text, references })
+}
+
+/// 截断到剩余预算（按字符边界）
+fn fit(text: &str, budget: usize) -> (String, bool) {
+    if text.len() <= budget {
+        return (text.to_string(), false);
+    }
+    let mut end = budget;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    (format!("{}\n... [Mention context truncated]", &text[..end]), true)
+}
+
+/// 展开消息中的提及；没有提及时返回 None
+pub fn resolve(project_root: &str, message: &str, index: Option<&SymbolIndexState>) -> Option<MentionContext> {
+    let mentions = parse_mentions(message);
+    if mentions.is_empty() {
+        return None;
+    }
+    let root = std::fs::canonicalize(project_root).unwrap_or_else(|_| PathBuf::from(project_root));
+
+    let mut result = MentionContext::default();
+    let mut blocks = Vec::new();
+    let mut budget = MAX_TOTAL_CHARS;
+    for mention in &mentions {
+        let token = mention.token();
+        if budget == 0 {
+            result.unresolved.push(format!("{}: context budget exhausted", token));
+            continue;
+        }
+        let block = match mention {
+            Mention::File { path, lines } => resolve_file(&root, project_root, path, *lines, &token),
+            Mention::Folder { path } => resolve_folder(&root, project_root, path, &token),
+            Mention::Symbol { name } => resolve_symbol(&root, name, index, &token),
+        };
+        match block {
+            Ok(block) => {
+                let (text, truncated) = fit(&block.text, budget);
+                budget = budget.saturating_sub(text.len());
+                blocks.push(text);
+                result.references.extend(block.references.into_iter().map(|mut r| {
+                    r.truncated |= truncated;
+                    r
+                }));
+            }
+            Err(e) => result.unresolved.push(format!("{}: {}", token, e)),
+        }
+    }
+
+    let mut context = String::from("# Mentioned Context\nThe user explicitly referenced the following project content:");
+    for block in &blocks {
+        context.push_str("\n\n");
+        context.push_str(block);
+    }
+    if !result.unresolved.is_empty() {
+        context.push_str("\n\nCould not resolve: ");
+        context.push_str(&result.unresolved.join("; "));
+    }
+    println!("[MentionContext] Resolved {} of {} mentions ({} chars)", blocks.len(), mentions.len(), context.len());
+    result.context = context;
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::symbol_commands::{FileSymbols, Symbol};
+
+    #[test]
+    fn test_parse_mentions() {
+        let mentions = parse_mentions("Compare @file:src/foo.rs#L10-20 with @folder:src/utils/ and @symbol:User::new. Also @file:README.md?");
+        assert_eq!(mentions, vec![
+            Mention::File { path: "src/foo.rs".to_string(), lines: Some((10, 20)) },
+            Mention::Folder { path: "src/utils".to_string() },
+            Mention::Symbol { name: "User::new".to_string() },
+            Mention::File { path: "README.md".to_string(), lines: None },
+        ]);
+        assert_eq!(parse_mentions("（@file:a.rs#L5）"), vec![Mention::File { path: "a.rs".to_string(), lines: Some((5, 5)) }]);
+        // 邮箱与不带目标的提及不解析
+        assert!(parse_mentions("mail dev@file:x and @file alone").is_empty());
+    }
+
+    #[test]
+    fn test_resolve_mentions() {
+        let dir = std::env::temp_dir().join(format!("ifai_mentions_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src/utils")).unwrap();
+        let long: Vec<String> = (1..=500).map(|i| format!("line {}", i)).collect();
+        std::fs::write(dir.join("src/long.rs"), long.join("\n")).unwrap();
+        std::fs::write(dir.join("src/utils/math.rs"), "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").unwrap();
+        std::fs::write(dir.join("src/user.rs"), "struct User;\n\nimpl User {\n    fn new() -> Self {\n        User\n    }\n}\n").unwrap();
+        let root = std::fs::canonicalize(&dir).unwrap();
+        let project_root = root.to_string_lossy().to_string();
+
+        let mut index = SymbolIndexState::new();
+        index.index_file(FileSymbols {
+            path: root.join("src/user.rs").to_string_lossy().to_string(),
+            symbols: vec![Symbol { kind: "method".to_string(), name: "new".to_string(), line: 4, end_line: Some(6), parent: Some("User".to_string()), qualified_name: "User::new".to_string() }],
+            hash: String::new(),
+        });
+
+        let message = "see @file:src/long.rs and @file:src/long.rs#L498-510 @folder:src/utils @symbol:User::new @symbol:Missing @file:../outside.rs";
+        let result = resolve(&project_root, message, Some(&index)).unwrap();
+
+        assert!(result.context.contains("## File `src/long.rs` (lines 1-400 of 500)"));
+        assert!(result.context.contains("mention `@file:src/long.rs#L401-800` for more"));
+        assert!(result.context.contains("## File `src/long.rs` (lines 498-500 of 500)\n```rs\nline 498\nline 499\nline 500\n```"));
+        assert!(result.context.contains("## Folder `src/utils/`\n```\nmath.rs\n```"));
+        assert!(result.context.contains("## Symbol User::new (method) in `src/user.rs` (lines 4-6)\n```rs\n    fn new() -> Self {"));
+        assert_eq!(result.unresolved.len(), 2);
+        assert!(result.context.contains("@symbol:Missing: symbol not found"));
+        assert!(result.unresolved[1].starts_with("@file:../outside.rs"));
+
+        let refs: Vec<(&str, usize, usize, bool)> = result.references.iter().map(|r| (r.file_path.as_str(), r.line_start, r.line_end, r.truncated)).collect();
+        assert_eq!(refs, vec![
+            ("src/long.rs", 1, 400, true),
+            ("src/long.rs", 498, 500, false),
+            ("src/utils/math.rs", 1, 3, false),
+            ("src/user.rs", 4, 6, false),
+        ]);
+        assert!(resolve(&project_root, "no mentions here", None).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
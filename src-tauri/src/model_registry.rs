@@ -0,0 +1,599 @@
+/*!
+Model Registry - 本地 GGUF 模型管理
+===================================
+
+不再只认一个写死的模型文件：
+
+- 扫描 `~/.ifai/models/` 下的 `.gguf` 文件，读取 GGUF 头部元数据（架构、量化方式、上下文长度）
+- 可下载的模型目录：内置条目 + `~/.ifai/model_catalog.json`（同 id 覆盖内置条目），
+  每个条目可配置多个镜像地址，下载时依次尝试
+- 按任务选择模型：`completion`（补全、对话、摘要）与 `classification`（工具分类），
+  选择保存在 `~/.ifai/local_models.json`；选中的文件不存在时回退到默认模型
+- 删除模型时，若它正被某个任务使用，该任务恢复为默认模型
+
+推理层只有一个模型槽位，两个任务选择不同模型时会在切换时重新加载。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::local_model::{LocalModelConfig, ModelDownloadConfig};
+
+/// 内置的默认模型
+pub const DEFAULT_MODEL_FILE: &str = "qwen2.5-coder-0.5b-ifai-v3-Q4_K_M.gguf";
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+/// 字符串长度上限，防止损坏的文件导致巨量分配
+const MAX_GGUF_STRING: u64 = 1 << 20;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelTask {
+    Completion,
+    Classification,
+}
+
+const TASKS: [ModelTask; 2] = [ModelTask::Completion, ModelTask::Classification];
+
+// ============================================================================
+// GGUF Metadata
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct GgufMetadata {
+    pub version: u32,
+    pub tensor_count: u64,
+    pub architecture: Option<String>,
+    pub name: Option<String>,
+    /// 量化方式（如 `Q4_K_M`），来自 `general.file_type`，缺失时从文件名推断
+    pub quantization: Option<String>,
+    pub context_length: Option<u64>,
+}
+
+/// `general.file_type`（llama_ftype）对应的量化名称
+fn quantization_name(file_type: u32) -> Option<&'static str> {
+    Some(match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        30 => "IQ4_XS",
+        32 => "BF16",
+        _ => return None,
+    })
+}
+
+/// 从文件名推断量化方式（如 `model-Q4_K_M.gguf`）
+fn quantization_from_file_name(file_name: &str) -> Option<String> {
+    const KNOWN: [&str; 17] = [
+        "Q4_K_M", "Q4_K_S", "Q5_K_M", "Q5_K_S", "Q3_K_M", "Q3_K_S", "Q3_K_L", "Q2_K", "Q6_K",
+        "Q8_0", "Q4_0", "Q4_1", "Q5_0", "Q5_1", "BF16", "F16", "F32",
+    ];
+    let upper = file_name.to_uppercase();
+    KNOWN.iter().find(|q| upper.contains(*q)).map(|q| q.to_string())
+}
+
+enum GgufValue {
+    Uint(u64),
+    Str(String),
+    Other,
+}
+
+fn read_bytes<R: Read, const N: usize>(r: &mut R) -> std::io::Result<[u8; N]> {
+    let mut buf = [0u8; N];
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u32<R: Read>(r: &mut R) -> std::io::Result<u32> {
+    Ok(u32::from_le_bytes(read_bytes(r)?))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> std::io::Result<u64> {
+    Ok(u64::from_le_bytes(read_bytes(r)?))
+}
+
+fn read_string<R: Read>(r: &mut R) -> std::io::Result<String> {
+    let len = read_u64(r)?;
+    if len > MAX_GGUF_STRING {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "GGUF string too long"));
+    }
+    let mut buf = vec![0u8; len as usize];
+    r.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// 固定长度类型的字节数（字符串、数组返回 None）
+fn scalar_size(value_type: u32) -> Option<u64> {
+    match value_type {
+        0 | 1 | 7 => Some(1),
+        2 | 3 => Some(2),
+        4..=6 => Some(4),
+        10..=12 => Some(8),
+        _ => None,
+    }
+}
+
+fn read_value<R: Read + Seek>(r: &mut R, value_type: u32) -> std::io::Result<GgufValue> {
+    match value_type {
+        0 => Ok(GgufValue::Uint(read_bytes::<_, 1>(r)?[0] as u64)),
+        2 => Ok(GgufValue::Uint(u16::from_le_bytes(read_bytes(r)?) as u64)),
+        4 => Ok(GgufValue::Uint(read_u32(r)? as u64)),
+        10 => Ok(GgufValue::Uint(read_u64(r)?)),
+        8 => Ok(GgufValue::Str(read_string(r)?)),
+        9 => {
+            // 数组（如词表）只跳过，不读取内容
+            let item_type = read_u32(r)?;
+            let len = read_u64(r)?;
+            match scalar_size(item_type) {
+                Some(size) => {
+                    r.seek(SeekFrom::Current((size * len) as i64))?;
+                }
+                None if item_type == 8 => {
+                    for _ in 0..len {
+                        let n = read_u64(r)?;
+                        r.seek(SeekFrom::Current(n as i64))?;
+                    }
+                }
+                None => return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "unsupported GGUF array type")),
+            }
+            Ok(GgufValue::Other)
+        }
+        t => match scalar_size(t) {
+            Some(size) => {
+                r.seek(SeekFrom::Current(size as i64))?;
+                Ok(GgufValue::Other)
+            }
+            None => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unknown GGUF value type {}", t))),
+        },
+    }
+}
+
+/// 读取 GGUF 文件头部的元数据（只读取键值区，不加载张量）
+pub fn read_gguf_metadata(path: &Path) -> Result<GgufMetadata, String> {
+    let file = std::fs::File::open(path).map_err(|e| format!("无法打开模型文件: {}", e))?;
+    let mut r = BufReader::new(file);
+    let invalid = |e: std::io::Error| format!("无效的 GGUF 文件 {}: {}", path.display(), e);
+
+    if &read_bytes::<_, 4>(&mut r).map_err(invalid)? != GGUF_MAGIC {
+        return Err(format!("不是 GGUF 文件: {}", path.display()));
+    }
+    let version = read_u32(&mut r).map_err(invalid)?;
+    if version < 2 {
+        return Err(format!("不支持的 GGUF 版本: {}", version));
+    }
+    let tensor_count = read_u64(&mut r).map_err(invalid)?;
+    let kv_count = read_u64(&mut r).map_err(invalid)?;
+
+    let mut values = BTreeMap::new();
+    for _ in 0..kv_count {
+        let key = read_string(&mut r).map_err(invalid)?;
+        let value_type = read_u32(&mut r).map_err(invalid)?;
+        let value = read_value(&mut r, value_type).map_err(invalid)?;
+        if !matches!(value, GgufValue::Other) {
+            values.insert(key, value);
+        }
+    }
+
+    let string = |key: &str| match values.get(key) {
+        Some(GgufValue::Str(s)) => Some(s.clone()),
+        _ => None,
+    };
+    let uint = |key: &str| match values.get(key) {
+        Some(GgufValue::Uint(n)) => Some(*n),
+        _ => None,
+    };
+    let architecture = string("general.architecture");
+    let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    Ok(GgufMetadata {
+        version,
+        tensor_count,
+        context_length: architecture.as_deref().and_then(|arch| uint(&format!("{}.context_length", arch))),
+        name: string("general.name"),
+        quantization: uint("general.file_type")
+            .and_then(|t| quantization_name(t as u32).map(str::to_string))
+            .or_else(|| quantization_from_file_name(&file_name)),
+        architecture,
+    })
+}
+
+// ============================================================================
+// Catalog
+// ============================================================================
+
+/// 可下载的模型
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CatalogEntry {
+    pub id: String,
+    pub name: String,
+    pub file_name: String,
+    /// 参数规模（如 `0.5B`）
+    #[serde(default)]
+    pub parameters: String,
+    #[serde(default)]
+    pub quantization: String,
+    /// 预期文件大小（服务器未返回 Content-Length 时用于计算进度）
+    #[serde(default)]
+    pub size_bytes: u64,
+    /// 下载地址，按顺序尝试
+    pub mirrors: Vec<String>,
+    /// 下载完成后校验的 SHA256（十六进制）；未配置时使用下载地址公布的摘要（Hugging Face 的 `X-Linked-Etag`）
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+fn builtin_catalog() -> Vec<CatalogEntry> {
+    let default = ModelDownloadConfig::default();
+    vec![
+        CatalogEntry {
+            id: "ifai-coder-0.5b-v3".to_string(),
+            name: "Qwen2.5-Coder-0.5B-IfAI-v3".to_string(),
+            file_name: DEFAULT_MODEL_FILE.to_string(),
+            parameters: "0.5B".to_string(),
+            quantization: "Q4_K_M".to_string(),
+            size_bytes: default.expected_size,
+            mirrors: vec![default.url],
+            sha256: default.checksum,
+        },
+        CatalogEntry {
+            id: "qwen2.5-coder-0.5b-instruct".to_string(),
+            name: "Qwen2.5-Coder-0.5B-Instruct".to_string(),
+            file_name: "qwen2.5-coder-0.5b-instruct-q4_k_m.gguf".to_string(),
+            parameters: "0.5B".to_string(),
+            quantization: "Q4_K_M".to_string(),
+            size_bytes: 491_000_000,
+            mirrors: vec![
+                "https://huggingface.co/Qwen/Qwen2.5-Coder-0.5B-Instruct-GGUF/resolve/main/qwen2.5-coder-0.5b-instruct-q4_k_m.gguf".to_string(),
+                "https://hf-mirror.com/Qwen/Qwen2.5-Coder-0.5B-Instruct-GGUF/resolve/main/qwen2.5-coder-0.5b-instruct-q4_k_m.gguf".to_string(),
+            ],
+            sha256: None,
+        },
+        CatalogEntry {
+            id: "qwen2.5-coder-1.5b-instruct".to_string(),
+            name: "Qwen2.5-Coder-1.5B-Instruct".to_string(),
+            file_name: "qwen2.5-coder-1.5b-instruct-q4_k_m.gguf".to_string(),
+            parameters: "1.5B".to_string(),
+            quantization: "Q4_K_M".to_string(),
+            size_bytes: 1_120_000_000,
+            mirrors: vec![
+                "https://huggingface.co/Qwen/Qwen2.5-Coder-1.5B-Instruct-GGUF/resolve/main/qwen2.5-coder-1.5b-instruct-q4_k_m.gguf".to_string(),
+                "https://hf-mirror.com/Qwen/Qwen2.5-Coder-1.5B-Instruct-GGUF/resolve/main/qwen2.5-coder-1.5b-instruct-q4_k_m.gguf".to_string(),
+            ],
+            sha256: None,
+        },
+    ]
+}
+
+fn ifai_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join(".ifai")
+}
+
+/// 内置条目与用户目录合并：同 id 覆盖，其余追加
+fn merge_catalog(mut catalog: Vec<CatalogEntry>, custom: Vec<CatalogEntry>) -> Vec<CatalogEntry> {
+    for entry in custom {
+        match catalog.iter_mut().find(|e| e.id == entry.id) {
+            Some(existing) => *existing = entry,
+            None => catalog.push(entry),
+        }
+    }
+    catalog
+}
+
+pub fn load_catalog() -> Vec<CatalogEntry> {
+    let custom: Vec<CatalogEntry> = std::fs::read_to_string(ifai_dir().join("model_catalog.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    merge_catalog(builtin_catalog(), custom)
+}
+
+/// 目录中模型的下载配置
+pub fn download_config(model_id: &str) -> Result<ModelDownloadConfig, String> {
+    let entry = load_catalog()
+        .into_iter()
+        .find(|e| e.id == model_id)
+        .ok_or_else(|| format!("模型目录中没有 {}", model_id))?;
+    validate_file_name(&entry.file_name)?;
+    let mut mirrors = entry.mirrors.into_iter();
+    let url = mirrors.next().ok_or_else(|| format!("模型 {} 没有下载地址", model_id))?;
+    Ok(ModelDownloadConfig {
+        url,
+        mirrors: mirrors.collect(),
+        filename: entry.file_name,
+        expected_size: entry.size_bytes,
+        checksum: entry.sha256,
+    })
+}
+
+// ============================================================================
+// Active Models
+// ============================================================================
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ActiveModels {
+    #[serde(default)]
+    active: BTreeMap<ModelTask, String>,
+}
+
+fn active_models_path() -> PathBuf {
+    ifai_dir().join("local_models.json")
+}
+
+fn load_active() -> ActiveModels {
+    std::fs::read_to_string(active_models_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_active(active: &ActiveModels) -> Result<(), String> {
+    let path = active_models_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(active).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write local model selection: {}", e))
+}
+
+/// 模型文件名只能是模型目录下的 `.gguf` 文件
+pub(crate) fn validate_file_name(file_name: &str) -> Result<(), String> {
+    if file_name.is_empty() || file_name.contains(['/', '\\']) || file_name.starts_with('.') || !file_name.ends_with(".gguf") {
+        return Err(format!("无效的模型文件名: {}", file_name));
+    }
+    Ok(())
+}
+
+/// 任务选择的模型文件名；未选择或文件已不存在时为默认模型
+fn active_file_in(dir: &Path, active: &ActiveModels, task: ModelTask) -> String {
+    active
+        .active
+        .get(&task)
+        .filter(|name| dir.join(name).is_file())
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_MODEL_FILE.to_string())
+}
+
+/// 任务当前使用的模型路径
+pub fn active_model_path(task: ModelTask) -> PathBuf {
+    let dir = LocalModelConfig::model_dir();
+    let file = active_file_in(&dir, &load_active(), task);
+    dir.join(file)
+}
+
+// ============================================================================
+// Installed Models
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledModel {
+    pub file_name: String,
+    pub path: String,
+    pub size_bytes: u64,
+    /// 读取失败（文件损坏或不是 GGUF）时为空
+    pub metadata: Option<GgufMetadata>,
+    pub error: Option<String>,
+    /// 使用该模型的任务
+    pub active_for: Vec<ModelTask>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalModelList {
+    pub model_dir: String,
+    pub installed: Vec<InstalledModel>,
+    pub catalog: Vec<CatalogEntry>,
+    pub active: BTreeMap<ModelTask, String>,
+}
+
+/// 扫描模型目录（忽略下载中的 `.part` 文件）
+fn scan_models(dir: &Path, active: &ActiveModels) -> Vec<InstalledModel> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut models: Vec<InstalledModel> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "gguf"))
+        .map(|path| {
+            let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let (metadata, error) = match read_gguf_metadata(&path) {
+                Ok(metadata) => (Some(metadata), None),
+                Err(e) => (None, Some(e)),
+            };
+            InstalledModel {
+                size_bytes: std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                path: path.to_string_lossy().to_string(),
+                active_for: TASKS.into_iter().filter(|&t| active_file_in(dir, active, t) == file_name).collect(),
+                file_name,
+                metadata,
+                error,
+            }
+        })
+        .collect();
+    models.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    models
+}
+
+/// 文件正被推理层加载时先卸载（Windows 上映射中的文件无法删除）
+fn release_if_loaded(path: &Path) {
+    #[cfg(feature = "llm-inference")]
+    if crate::llm_inference::is_loaded_model(path) {
+        if let Err(e) = crate::llm_inference::unload_model() {
+            eprintln!("[ModelRegistry] Failed to unload model: {}", e);
+        }
+    }
+    #[cfg(not(feature = "llm-inference"))]
+    let _ = path;
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 已安装的模型、可下载的模型目录和各任务当前使用的模型
+#[tauri::command]
+pub fn list_local_models() -> LocalModelList {
+    let dir = LocalModelConfig::model_dir();
+    let active = load_active();
+    LocalModelList {
+        model_dir: dir.to_string_lossy().to_string(),
+        installed: scan_models(&dir, &active),
+        catalog: load_catalog(),
+        active: TASKS.into_iter().map(|t| (t, active_file_in(&dir, &active, t))).collect(),
+    }
+}
+
+/// 设置任务使用的模型（下次生成时加载）
+#[tauri::command]
+pub fn set_active_model(task: ModelTask, file_name: String) -> Result<(), String> {
+    validate_file_name(&file_name)?;
+    let path = LocalModelConfig::model_dir().join(&file_name);
+    if !path.is_file() {
+        return Err(format!("模型文件不存在: {}", path.display()));
+    }
+    read_gguf_metadata(&path)?;
+
+    let mut active = load_active();
+    active.active.insert(task, file_name.clone());
+    save_active(&active)?;
+    println!("[ModelRegistry] {:?} -> {}", task, file_name);
+    Ok(())
+}
+
+/// 删除已安装的模型；正在使用它的任务恢复为默认模型
+#[tauri::command]
+pub async fn remove_local_model(file_name: String) -> Result<(), String> {
+    validate_file_name(&file_name)?;
+    if crate::local_model::is_downloading().await {
+        return Err("模型下载中，请稍后再删除".to_string());
+    }
+    let path = LocalModelConfig::model_dir().join(&file_name);
+    if !path.is_file() {
+        return Err(format!("模型文件不存在: {}", path.display()));
+    }
+
+    // 等待进行中的生成结束后再释放模型
+    let loaded_path = path.clone();
+    tokio::task::spawn_blocking(move || release_if_loaded(&loaded_path))
+        .await
+        .map_err(|e| format!("任务调度失败: {}", e))?;
+    std::fs::remove_file(&path).map_err(|e| format!("无法删除模型文件: {}", e))?;
+
+    let mut active = load_active();
+    let before = active.active.len();
+    active.active.retain(|_, name| *name != file_name);
+    if active.active.len() != before {
+        save_active(&active)?;
+    }
+    println!("[ModelRegistry] Removed {}", file_name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn put_string(buf: &mut Vec<u8>, s: &str) {
+        buf.extend((s.len() as u64).to_le_bytes());
+        buf.extend(s.as_bytes());
+    }
+
+    fn put_kv_string(buf: &mut Vec<u8>, key: &str, value: &str) {
+        put_string(buf, key);
+        buf.extend(8u32.to_le_bytes());
+        put_string(buf, value);
+    }
+
+    fn put_kv_u32(buf: &mut Vec<u8>, key: &str, value: u32) {
+        put_string(buf, key);
+        buf.extend(4u32.to_le_bytes());
+        buf.extend(value.to_le_bytes());
+    }
+
+    #[test]
+    fn test_read_gguf_metadata() {
+        let mut buf = b"GGUF".to_vec();
+        buf.extend(3u32.to_le_bytes());
+        buf.extend(291u64.to_le_bytes());
+        buf.extend(6u64.to_le_bytes());
+        put_kv_string(&mut buf, "general.architecture", "qwen2");
+        put_kv_string(&mut buf, "general.name", "Qwen2.5 Coder 0.5B");
+        put_kv_u32(&mut buf, "general.file_type", 15);
+        // 字符串数组（词表）与浮点数被跳过
+        put_string(&mut buf, "tokenizer.ggml.tokens");
+        buf.extend(9u32.to_le_bytes());
+        buf.extend(8u32.to_le_bytes());
+        buf.extend(2u64.to_le_bytes());
+        put_string(&mut buf, "<|endoftext|>");
+        put_string(&mut buf, "fn");
+        put_string(&mut buf, "qwen2.rope.freq_base");
+        buf.extend(6u32.to_le_bytes());
+        buf.extend(1_000_000f32.to_le_bytes());
+        put_kv_u32(&mut buf, "qwen2.context_length", 32768);
+
+        let dir = std::env::temp_dir().join(format!("ifai_model_registry_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("custom-Q8_0.gguf");
+        std::fs::File::create(&path).unwrap().write_all(&buf).unwrap();
+
+        let metadata = read_gguf_metadata(&path).unwrap();
+        assert_eq!(metadata.version, 3);
+        assert_eq!(metadata.tensor_count, 291);
+        assert_eq!(metadata.architecture.as_deref(), Some("qwen2"));
+        assert_eq!(metadata.name.as_deref(), Some("Qwen2.5 Coder 0.5B"));
+        assert_eq!(metadata.quantization.as_deref(), Some("Q4_K_M"));
+        assert_eq!(metadata.context_length, Some(32768));
+
+        std::fs::write(dir.join("broken.gguf"), b"GGML").unwrap();
+        std::fs::write(dir.join("downloading.gguf.part"), b"GGUF").unwrap();
+        let active = ActiveModels { active: BTreeMap::from([(ModelTask::Classification, "custom-Q8_0.gguf".to_string())]) };
+        let models = scan_models(&dir, &active);
+        assert_eq!(models.iter().map(|m| m.file_name.as_str()).collect::<Vec<_>>(), ["broken.gguf", "custom-Q8_0.gguf"]);
+        assert!(models[0].metadata.is_none() && models[0].error.is_some());
+        assert_eq!(models[1].active_for, [ModelTask::Classification]);
+
+        // 未选择的任务使用默认模型；选中的文件被删除后同样回退
+        assert_eq!(active_file_in(&dir, &active, ModelTask::Completion), DEFAULT_MODEL_FILE);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(active_file_in(&dir, &active, ModelTask::Classification), DEFAULT_MODEL_FILE);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_catalog_merge_and_file_names() {
+        let custom = vec![
+            CatalogEntry { mirrors: vec!["https://mirror.example/ifai.gguf".into()], ..builtin_catalog()[0].clone() },
+            CatalogEntry {
+                id: "team-model".into(),
+                name: "Team".into(),
+                file_name: "team.gguf".into(),
+                parameters: String::new(),
+                quantization: String::new(),
+                size_bytes: 0,
+                mirrors: vec!["https://models.example/team.gguf".into()],
+                sha256: None,
+            },
+        ];
+        let catalog = merge_catalog(builtin_catalog(), custom);
+        assert_eq!(catalog.len(), builtin_catalog().len() + 1);
+        assert_eq!(catalog[0].mirrors, ["https://mirror.example/ifai.gguf"]);
+        assert_eq!(catalog.last().unwrap().id, "team-model");
+
+        assert!(validate_file_name("model.gguf").is_ok());
+        assert!(validate_file_name("../model.gguf").is_err());
+        assert!(validate_file_name("model.bin").is_err());
+        assert_eq!(quantization_from_file_name("qwen2.5-coder-1.5b-instruct-q4_k_m.gguf").as_deref(), Some("Q4_K_M"));
+    }
+}
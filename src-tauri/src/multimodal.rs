@@ -24,6 +24,13 @@ pub struct ImageContent {
     pub size: Option<usize>,
 }
 
+impl ImageContent {
+    /// data URL，可直接作为 `ContentPart::ImageUrl` 发送给各协议
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, self.data)
+    }
+}
+
 /// 视觉分析结果
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VisionAnalysisResult {
@@ -218,6 +225,312 @@ pub async fn read_file_as_base64(path: String) -> Result<String, String> {
     Ok(base64_string)
 }
 
+// ============================================================================
+// v0.3.4: 图片附件（读取本地文件并按需缩小）
+// ============================================================================
+
+/// 附件图片最长边的默认上限（像素），超过时等比缩小
+pub const MAX_IMAGE_DIMENSION: u32 = 1568;
+/// 附件原始文件大小上限
+const MAX_ATTACHMENT_BYTES: u64 = 20 * 1024 * 1024;
+
+/// 图片附件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageAttachment {
+    pub image: ImageContent,
+    /// `image` 的 data URL，用于消息的 `image_url` 内容
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+    /// 是否经过缩小或转码
+    pub resized: bool,
+}
+
+/// 各协议都接受的图片格式
+fn supported_mime(format: image::ImageFormat) -> Option<&'static str> {
+    match format {
+        image::ImageFormat::Png => Some("image/png"),
+        image::ImageFormat::Jpeg => Some("image/jpeg"),
+        image::ImageFormat::Gif => Some("image/gif"),
+        image::ImageFormat::WebP => Some("image/webp"),
+        _ => None,
+    }
+}
+
+/// 准备发送给模型的图片：尺寸未超限且格式受支持时保留原始字节，
+/// 否则缩小到 `max_dimension` 以内并转码（PNG 与带透明通道的图片转为 PNG，其余转为 JPEG）
+fn prepare_image(bytes: Vec<u8>, name: Option<String>, max_dimension: u32) -> Result<ImageAttachment, String> {
+    use base64::Engine;
+
+    let format = image::guess_format(&bytes).map_err(|_| "无法识别的图片格式".to_string())?;
+    let decoded = image::load_from_memory_with_format(&bytes, format)
+        .map_err(|e| format!("图片解码失败: {}", e))?;
+    let (width, height) = (decoded.width(), decoded.height());
+
+    let (data, mime_type, width, height, resized) = match supported_mime(format) {
+        Some(mime) if width.max(height) <= max_dimension => (bytes, mime, width, height, false),
+        _ => {
+            let img = if width.max(height) > max_dimension {
+                decoded.resize(max_dimension, max_dimension, image::imageops::FilterType::Triangle)
+            } else {
+                decoded
+            };
+            let mut out = std::io::Cursor::new(Vec::new());
+            let mime = if format == image::ImageFormat::Png || img.color().has_alpha() {
+                img.write_to(&mut out, image::ImageFormat::Png).map_err(|e| format!("图片编码失败: {}", e))?;
+                "image/png"
+            } else {
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, 85)
+                    .encode_image(&img.to_rgb8())
+                    .map_err(|e| format!("图片编码失败: {}", e))?;
+                "image/jpeg"
+            };
+            (out.into_inner(), mime, img.width(), img.height(), true)
+        }
+    };
+
+    let image = ImageContent {
+        data: base64::engine::general_purpose::STANDARD.encode(&data),
+        mime_type: mime_type.to_string(),
+        name,
+        size: Some(data.len()),
+    };
+    Ok(ImageAttachment { data_url: image.data_url(), image, width, height, resized })
+}
+
+/// v0.3.4: 读取本地图片作为消息附件，超过 `max_dimension`（默认 1568 像素）时等比缩小
+#[tauri::command]
+pub async fn attach_image(path: String, max_dimension: Option<u32>) -> Result<ImageAttachment, String> {
+    let path = std::path::PathBuf::from(path);
+    let size = std::fs::metadata(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+        .len();
+    if size > MAX_ATTACHMENT_BYTES {
+        return Err(format!("图片过大（{} MB），上限为 {} MB", size / 1024 / 1024, MAX_ATTACHMENT_BYTES / 1024 / 1024));
+    }
+
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string());
+    let max_dimension = max_dimension.unwrap_or(MAX_IMAGE_DIMENSION).max(1);
+    let attachment = tokio::task::spawn_blocking(move || {
+        let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        prepare_image(bytes, name, max_dimension)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    println!(
+        "[Multimodal] attach_image: {:?} {}x{} ({}, {} bytes, resized: {})",
+        attachment.image.name, attachment.width, attachment.height, attachment.image.mime_type,
+        attachment.image.size.unwrap_or_default(), attachment.resized
+    );
+    Ok(attachment)
+}
+
+// ============================================================================
+// v0.3.4: 窗口截图 + 视觉分析
+// ============================================================================
+
+/// 截图区域（相对于窗口左上角的逻辑像素）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureRegion {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// 截图结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowScreenshotResult {
+    pub image: ImageContent,
+    /// 提供了 prompt 与 provider 时的视觉分析结果
+    pub analysis: Option<VisionAnalysisResult>,
+}
+
+/// 屏幕上的物理像素矩形
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PhysicalRect {
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+}
+
+/// 根据窗口位置/缩放计算实际截图区域（物理像素），并裁剪到窗口范围内
+fn resolve_capture_rect(
+    window_pos: (i32, i32),
+    window_size: (u32, u32),
+    scale_factor: f64,
+    region: Option<&CaptureRegion>,
+) -> Result<PhysicalRect, String> {
+    let (wx, wy) = window_pos;
+    let (ww, wh) = window_size;
+    let Some(r) = region else {
+        return Ok(PhysicalRect { x: wx, y: wy, width: ww, height: wh });
+    };
+
+    let x = (r.x * scale_factor).round().max(0.0) as u32;
+    let y = (r.y * scale_factor).round().max(0.0) as u32;
+    if x >= ww || y >= wh {
+        return Err("截图区域超出窗口范围".to_string());
+    }
+    let width = ((r.width * scale_factor).round().max(0.0) as u32).min(ww - x);
+    let height = ((r.height * scale_factor).round().max(0.0) as u32).min(wh - y);
+    if width == 0 || height == 0 {
+        return Err("截图区域为空".to_string());
+    }
+
+    Ok(PhysicalRect { x: wx + x as i32, y: wy + y as i32, width, height })
+}
+
+/// 调用系统截图工具截取屏幕区域到 PNG 文件
+fn capture_screen_rect(rect: PhysicalRect, scale_factor: f64, output: &std::path::Path) -> Result<(), String> {
+    use std::process::Command;
+
+    let out = output.to_string_lossy().to_string();
+
+    #[cfg(target_os = "macos")]
+    let status = {
+        // screencapture 使用逻辑坐标（points）
+        let to_pt = |v: f64| (v / scale_factor).round() as i64;
+        let region = format!(
+            "{},{},{},{}",
+            to_pt(rect.x as f64), to_pt(rect.y as f64), to_pt(rect.width as f64), to_pt(rect.height as f64)
+        );
+        Command::new("screencapture").args(["-x", "-R", &region, &out]).status()
+    };
+
+    #[cfg(target_os = "windows")]
+    let status = {
+        let _ = scale_factor;
+        let script = format!(
+            "Add-Type -AssemblyName System.Drawing; \
+             $bmp = New-Object System.Drawing.Bitmap {w}, {h}; \
+             $g = [System.Drawing.Graphics]::FromImage($bmp); \
+             $g.CopyFromScreen({x}, {y}, 0, 0, $bmp.Size); \
+             $bmp.Save('{out}', [System.Drawing.Imaging.ImageFormat]::Png)",
+            x = rect.x, y = rect.y, w = rect.width, h = rect.height, out = out.replace('\'', "''")
+        );
+        Command::new("powershell").args(["-NoProfile", "-Command", &script]).status()
+    };
+
+    #[cfg(all(not(target_os = "macos"), not(target_os = "windows")))]
+    let status = {
+        let _ = scale_factor;
+        // Wayland 优先使用 grim，X11 回退到 ImageMagick import
+        if std::env::var("WAYLAND_DISPLAY").is_ok() {
+            let geometry = format!("{},{} {}x{}", rect.x, rect.y, rect.width, rect.height);
+            Command::new("grim").args(["-g", &geometry, &out]).status()
+        } else {
+            let crop = format!("{}x{}+{}+{}", rect.width, rect.height, rect.x, rect.y);
+            Command::new("import").args(["-window", "root", "-crop", &crop, &out]).status()
+        }
+    };
+
+    match status {
+        Ok(s) if s.success() && output.exists() => Ok(()),
+        Ok(s) => Err(format!("截图命令执行失败 (exit: {:?})", s.code())),
+        Err(e) => Err(format!("无法启动系统截图工具: {}", e)),
+    }
+}
+
+/// 使用支持视觉的 Provider 分析图片（OpenAI 兼容的 image_url 格式）
+async fn analyze_with_provider(
+    config: &crate::core_traits::ai::AIProviderConfig,
+    image: &ImageContent,
+    prompt: &str,
+) -> Result<VisionAnalysisResult, String> {
+    use crate::core_traits::ai::{Content, ContentPart, ImageUrl, Message};
+
+    let message = Message {
+        role: "user".to_string(),
+        content: Content::Parts(vec![
+            ContentPart::Text { text: prompt.to_string(), part_type: "text".to_string() },
+            ContentPart::ImageUrl {
+                image_url: ImageUrl { url: image.data_url() },
+            },
+        ]),
+        tool_calls: None,
+        tool_call_id: None,
+    };
+
+    let reply = crate::ai_utils::fetch_ai_completion(config, vec![message], None).await?;
+    let description = match reply.content {
+        Content::Text(text) => text,
+        Content::Parts(parts) => parts.into_iter()
+            .filter_map(|p| match p {
+                ContentPart::Text { text, .. } => Some(text),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    Ok(VisionAnalysisResult {
+        description,
+        code: None,
+        language: None,
+        confidence: None,
+    })
+}
+
+/// v0.3.4: 截取应用窗口（或窗口内区域），可选交给视觉模型分析
+///
+/// - `label`: 窗口标签，默认 "main"
+/// - `region`: 窗口内的区域（逻辑像素），为空时截取整个窗口
+/// - `prompt` + `provider_config`: 同时提供时发送给视觉模型；只提供 prompt 时返回错误
+#[tauri::command]
+pub async fn capture_window_screenshot(
+    app: tauri::AppHandle,
+    label: Option<String>,
+    region: Option<CaptureRegion>,
+    prompt: Option<String>,
+    provider_config: Option<crate::core_traits::ai::AIProviderConfig>,
+) -> Result<WindowScreenshotResult, String> {
+    use tauri::Manager;
+
+    // 请求了分析却没有可用的视觉 Provider 时直接报错，不返回模拟结果
+    let prompt = prompt.filter(|p| !p.trim().is_empty());
+    if prompt.is_some() && provider_config.is_none() {
+        return Err("Screenshot analysis requires a vision-capable provider config".to_string());
+    }
+
+    let label = label.unwrap_or_else(|| "main".to_string());
+    let window = app.get_webview_window(&label)
+        .ok_or_else(|| format!("Window '{}' not found", label))?;
+
+    let pos = window.outer_position().map_err(|e| e.to_string())?;
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+    let scale = window.scale_factor().map_err(|e| e.to_string())?;
+    let rect = resolve_capture_rect((pos.x, pos.y), (size.width, size.height), scale, region.as_ref())?;
+
+    println!("[Multimodal] capture_window_screenshot: {} {:?}", label, rect);
+
+    let output = std::env::temp_dir().join(format!("ifai-screenshot-{}.png", uuid::Uuid::new_v4()));
+    let output_clone = output.clone();
+    tokio::task::spawn_blocking(move || capture_screen_rect(rect, scale, &output_clone))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let bytes = std::fs::read(&output).map_err(|e| format!("Failed to read screenshot: {}", e));
+    let _ = std::fs::remove_file(&output);
+    let bytes = bytes?;
+
+    // 高分屏截图缩小到附件尺寸上限，避免超出视觉模型的输入限制
+    let name = Some(format!("{}-screenshot.png", label));
+    let image = tokio::task::spawn_blocking(move || prepare_image(bytes, name, MAX_IMAGE_DIMENSION))
+        .await
+        .map_err(|e| e.to_string())??
+        .image;
+
+    let analysis = match (prompt, provider_config) {
+        (Some(prompt), Some(config)) => Some(analyze_with_provider(&config, &image, &prompt).await?),
+        _ => None,
+    };
+
+    Ok(WindowScreenshotResult { image, analysis })
+}
+
 // ============================================================================
 // 测试
 // ============================================================================
@@ -241,6 +554,52 @@ mod tests {
         assert!(!MockMultimodalEngine::is_vision_supported());
     }
 
+    #[test]
+    fn test_resolve_capture_rect() {
+        // 整个窗口
+        let rect = resolve_capture_rect((100, 50), (800, 600), 2.0, None).unwrap();
+        assert_eq!(rect, PhysicalRect { x: 100, y: 50, width: 800, height: 600 });
+
+        // 逻辑像素区域按缩放换算，并裁剪到窗口内
+        let region = CaptureRegion { x: 10.0, y: 20.0, width: 1000.0, height: 100.0 };
+        let rect = resolve_capture_rect((100, 50), (800, 600), 2.0, Some(&region)).unwrap();
+        assert_eq!(rect, PhysicalRect { x: 120, y: 90, width: 780, height: 200 });
+
+        let outside = CaptureRegion { x: 500.0, y: 0.0, width: 10.0, height: 10.0 };
+        assert!(resolve_capture_rect((0, 0), (800, 600), 2.0, Some(&outside)).is_err());
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut out = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(width, height).write_to(&mut out, image::ImageFormat::Png).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn test_prepare_image() {
+        // 尺寸未超限：保留原始字节
+        let small = png(40, 20);
+        let attachment = prepare_image(small.clone(), Some("a.png".to_string()), MAX_IMAGE_DIMENSION).unwrap();
+        assert!(!attachment.resized);
+        assert_eq!((attachment.width, attachment.height), (40, 20));
+        assert_eq!(attachment.image.size, Some(small.len()));
+        assert!(attachment.data_url.starts_with("data:image/png;base64,"));
+
+        // 超限时等比缩小，PNG 仍输出 PNG
+        let attachment = prepare_image(png(400, 100), None, 200).unwrap();
+        assert!(attachment.resized);
+        assert_eq!((attachment.width, attachment.height), (200, 50));
+        assert_eq!(attachment.image.mime_type, "image/png");
+
+        // 其他格式缩小后转为 JPEG
+        let mut jpeg = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(100, 300).write_to(&mut jpeg, image::ImageFormat::Jpeg).unwrap();
+        let attachment = prepare_image(jpeg.into_inner(), None, 150).unwrap();
+        assert_eq!((attachment.width, attachment.height, attachment.image.mime_type.as_str()), (50, 150, "image/jpeg"));
+
+        assert!(prepare_image(b"not an image".to_vec(), None, 200).is_err());
+    }
+
     #[test]
     fn test_empty_image_data() {
         // 测试空图片数据会被拒绝
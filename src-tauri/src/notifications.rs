@@ -0,0 +1,184 @@
+/*!
+Notifications - 系统通知
+========================
+
+长时间运行的操作完成时发送系统原生通知（基于 tauri-plugin-notification）：
+
+- Agent 运行完成 / 失败
+- 窗口未聚焦时出现待审批的工具调用
+- 本地模型下载完成 / 失败
+- Agent 运行用量达到预算阈值（见 `agent_budget::BUDGET_WARNING_RATIO`）
+
+每种触发器可单独开关，配置保存在 `~/.ifai/notifications.json`。
+完成类通知默认只在窗口未聚焦时发送，避免打扰正在看界面的用户。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_notification::NotificationExt;
+
+/// 通知触发器
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationTrigger {
+    AgentCompleted,
+    AgentFailed,
+    ApprovalRequired,
+    ModelDownloaded,
+    BudgetThreshold,
+}
+
+/// 通知配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct NotificationConfig {
+    /// 总开关
+    pub enabled: bool,
+    pub agent_completed: bool,
+    pub agent_failed: bool,
+    pub approval_required: bool,
+    pub model_downloaded: bool,
+    pub budget_threshold: bool,
+    /// 仅在窗口未聚焦时通知（审批请求始终要求未聚焦）
+    pub only_when_unfocused: bool,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            agent_completed: true,
+            agent_failed: true,
+            approval_required: true,
+            model_downloaded: true,
+            budget_threshold: true,
+            only_when_unfocused: true,
+        }
+    }
+}
+
+impl NotificationConfig {
+    fn allows(&self, trigger: NotificationTrigger) -> bool {
+        self.enabled && match trigger {
+            NotificationTrigger::AgentCompleted => self.agent_completed,
+            NotificationTrigger::AgentFailed => self.agent_failed,
+            NotificationTrigger::ApprovalRequired => self.approval_required,
+            NotificationTrigger::ModelDownloaded => self.model_downloaded,
+            NotificationTrigger::BudgetThreshold => self.budget_threshold,
+        }
+    }
+
+    /// 结合窗口聚焦状态判断是否发送
+    fn should_notify(&self, trigger: NotificationTrigger, window_focused: bool) -> bool {
+        if !self.allows(trigger) {
+            return false;
+        }
+        let requires_unfocused = self.only_when_unfocused || trigger == NotificationTrigger::ApprovalRequired;
+        !(requires_unfocused && window_focused)
+    }
+}
+
+// ============================================================================
+// Config
+// ============================================================================
+
+fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("notifications.json")
+}
+
+fn load_config() -> NotificationConfig {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(config: &NotificationConfig) -> Result<(), String> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write notification config: {}", e))
+}
+
+// ============================================================================
+// Sending
+// ============================================================================
+
+fn any_window_focused(app: &AppHandle) -> bool {
+    app.webview_windows().values().any(|w| w.is_focused().unwrap_or(false))
+}
+
+fn show(app: &AppHandle, title: &str, body: &str) -> Result<(), String> {
+    app.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show()
+        .map_err(|e| format!("Failed to show notification: {}", e))
+}
+
+/// 按配置发送通知
+pub fn notify(app: &AppHandle, trigger: NotificationTrigger, title: &str, body: &str) {
+    let config = load_config();
+    if !config.should_notify(trigger, any_window_focused(app)) {
+        return;
+    }
+    println!("[Notifications] {:?}: {}", trigger, title);
+    if let Err(e) = show(app, title, body) {
+        eprintln!("[Notifications] {}", e);
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_notification_config() -> NotificationConfig {
+    load_config()
+}
+
+#[tauri::command]
+pub fn set_notification_config(config: NotificationConfig) -> Result<(), String> {
+    save_config(&config)
+}
+
+/// 发送测试通知（忽略配置，用于检查系统权限）
+#[tauri::command]
+pub fn send_test_notification(app: AppHandle) -> Result<(), String> {
+    show(&app, "IfAI", "Notifications are working.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_notify() {
+        let config = NotificationConfig::default();
+        assert!(config.should_notify(NotificationTrigger::AgentCompleted, false));
+        assert!(!config.should_notify(NotificationTrigger::AgentCompleted, true));
+
+        let always = NotificationConfig { only_when_unfocused: false, ..Default::default() };
+        assert!(always.should_notify(NotificationTrigger::AgentFailed, true));
+        assert!(!always.should_notify(NotificationTrigger::ApprovalRequired, true));
+
+        let muted = NotificationConfig { agent_failed: false, ..Default::default() };
+        assert!(!muted.should_notify(NotificationTrigger::AgentFailed, false));
+        let off = NotificationConfig { enabled: false, ..Default::default() };
+        assert!(!off.should_notify(NotificationTrigger::ModelDownloaded, false));
+    }
+
+    #[test]
+    fn test_partial_config_uses_defaults() {
+        let config: NotificationConfig = serde_json::from_str(r#"{ "agent_completed": false }"#).unwrap();
+        assert!(!config.agent_completed);
+        assert!(config.approval_required);
+    }
+}
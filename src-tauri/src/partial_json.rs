@@ -0,0 +1,288 @@
+/*!
+Partial JSON - 流式工具参数的增量 JSON 解析
+==========================================
+
+工具调用参数以 JSON 片段的形式逐块到达。`PartialJsonParser` 逐字符消费新片段
+（不重复扫描已处理内容），并随时给出当前可用的部分值：
+
+- 已完成的键值对原样保留
+- 正在接收的字符串值以已解码部分给出（支持转义与 `\uXXXX` 代理对）
+- 未完成的键、数字、`true/false/null` 暂不输出，保证每个键的值只会"增长"而不会跳变
+
+用于审批界面中文件写入等工具调用的渐进式预览。
+*/
+
+use serde_json::{Map, Value};
+
+/// 正在构建的容器
+#[derive(Debug, Clone)]
+enum Container {
+    /// 对象及其当前等待值的键
+    Object { map: Map<String, Value>, key: Option<String> },
+    Array(Vec<Value>),
+}
+
+/// 字符串转义状态
+#[derive(Debug, Clone, Default)]
+enum Escape {
+    #[default]
+    None,
+    Backslash,
+    Unicode(String),
+}
+
+/// 正在接收的字符串
+#[derive(Debug, Clone)]
+struct PartialString {
+    is_key: bool,
+    text: String,
+    escape: Escape,
+    /// 等待低位代理的高位代理
+    high_surrogate: Option<u32>,
+}
+
+/// 增量 JSON 解析器
+#[derive(Debug, Clone, Default)]
+pub struct PartialJsonParser {
+    stack: Vec<Container>,
+    string: Option<PartialString>,
+    /// 未结束的数字 / 字面量
+    scalar: String,
+    /// 已完成的顶层值
+    root: Option<Value>,
+    /// 遇到非法输入后停止解析，保留已解析部分
+    failed: bool,
+}
+
+impl PartialJsonParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 追加一个新片段
+    pub fn push(&mut self, chunk: &str) {
+        for c in chunk.chars() {
+            if self.failed {
+                return;
+            }
+            self.push_char(c);
+        }
+    }
+
+    /// 顶层值是否已完整结束
+    pub fn is_complete(&self) -> bool {
+        self.root.is_some()
+    }
+
+    /// 当前可用的部分值
+    pub fn snapshot(&self) -> Option<Value> {
+        if let Some(root) = &self.root {
+            return Some(root.clone());
+        }
+
+        let mut child = self.string.as_ref()
+            .filter(|s| !s.is_key)
+            .map(|s| Value::String(s.text.clone()));
+
+        for container in self.stack.iter().rev() {
+            let mut value = container.clone();
+            if let Some(v) = child.take() {
+                match &mut value {
+                    Container::Object { map, key: Some(k) } => { map.insert(k.clone(), v); }
+                    Container::Array(items) => items.push(v),
+                    Container::Object { .. } => {}
+                }
+            }
+            child = Some(into_value(value));
+        }
+        child
+    }
+
+    fn push_char(&mut self, c: char) {
+        if self.string.is_some() {
+            self.push_string_char(c);
+            return;
+        }
+
+        match c {
+            '"' => {
+                self.flush_scalar();
+                let is_key = matches!(self.stack.last(), Some(Container::Object { key: None, .. }));
+                self.string = Some(PartialString {
+                    is_key,
+                    text: String::new(),
+                    escape: Escape::None,
+                    high_surrogate: None,
+                });
+            }
+            '{' => {
+                self.flush_scalar();
+                self.stack.push(Container::Object { map: Map::new(), key: None });
+            }
+            '[' => {
+                self.flush_scalar();
+                self.stack.push(Container::Array(Vec::new()));
+            }
+            '}' | ']' => {
+                self.flush_scalar();
+                let closes = match self.stack.last() {
+                    Some(Container::Object { .. }) => c == '}',
+                    Some(Container::Array(_)) => c == ']',
+                    None => false,
+                };
+                if !closes {
+                    self.failed = true;
+                    return;
+                }
+                if let Some(container) = self.stack.pop() {
+                    self.complete_value(into_value(container));
+                }
+            }
+            ',' | ':' => self.flush_scalar(),
+            c if c.is_whitespace() => self.flush_scalar(),
+            c if c.is_ascii_alphanumeric() || matches!(c, '-' | '+' | '.') => self.scalar.push(c),
+            _ => self.failed = true,
+        }
+    }
+
+    fn push_string_char(&mut self, c: char) {
+        let Some(s) = self.string.as_mut() else { return };
+
+        match std::mem::take(&mut s.escape) {
+            Escape::None => match c {
+                '\\' => s.escape = Escape::Backslash,
+                '"' => {
+                    let done = self.string.take().unwrap();
+                    if done.is_key {
+                        if let Some(Container::Object { key, .. }) = self.stack.last_mut() {
+                            *key = Some(done.text);
+                        }
+                    } else {
+                        self.complete_value(Value::String(done.text));
+                    }
+                }
+                c => s.text.push(c),
+            },
+            Escape::Backslash => match c {
+                'n' => s.text.push('\n'),
+                'r' => s.text.push('\r'),
+                't' => s.text.push('\t'),
+                'b' => s.text.push('\u{8}'),
+                'f' => s.text.push('\u{c}'),
+                'u' => s.escape = Escape::Unicode(String::new()),
+                c => s.text.push(c),
+            },
+            Escape::Unicode(mut hex) => {
+                hex.push(c);
+                if hex.len() < 4 {
+                    s.escape = Escape::Unicode(hex);
+                    return;
+                }
+                let Ok(code) = u32::from_str_radix(&hex, 16) else {
+                    self.failed = true;
+                    return;
+                };
+                match (s.high_surrogate.take(), code) {
+                    (None, 0xD800..=0xDBFF) => s.high_surrogate = Some(code),
+                    (Some(high), 0xDC00..=0xDFFF) => {
+                        let combined = 0x10000 + ((high - 0xD800) << 10) + (code - 0xDC00);
+                        s.text.push(char::from_u32(combined).unwrap_or('\u{FFFD}'));
+                    }
+                    (_, code) => s.text.push(char::from_u32(code).unwrap_or('\u{FFFD}')),
+                }
+            }
+        }
+    }
+
+    /// 结束当前数字 / 字面量
+    fn flush_scalar(&mut self) {
+        if self.scalar.is_empty() {
+            return;
+        }
+        let token = std::mem::take(&mut self.scalar);
+        match serde_json::from_str::<Value>(&token) {
+            Ok(value) => self.complete_value(value),
+            Err(_) => self.failed = true,
+        }
+    }
+
+    /// 将一个完整的值挂到父容器上
+    fn complete_value(&mut self, value: Value) {
+        match self.stack.last_mut() {
+            None => self.root = Some(value),
+            Some(Container::Array(items)) => items.push(value),
+            Some(Container::Object { map, key }) => match key.take() {
+                Some(k) => { map.insert(k, value); }
+                None => self.failed = true,
+            },
+        }
+    }
+}
+
+fn into_value(container: Container) -> Value {
+    match container {
+        Container::Object { map, .. } => Value::Object(map),
+        Container::Array(items) => Value::Array(items),
+    }
+}
+
+/// 一次性解析部分 JSON（非增量场景的便捷函数）
+pub fn parse_partial(json_str: &str) -> Option<Value> {
+    let mut parser = PartialJsonParser::new();
+    parser.push(json_str);
+    parser.snapshot()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_partial_string_value() {
+        let value = parse_partial(r#"{"rel_path": "src/a.rs", "content": "fn main() {\n    println!(\"hi"#).unwrap();
+        assert_eq!(value["rel_path"], "src/a.rs");
+        assert_eq!(value["content"], "fn main() {\n    println!(\"hi");
+    }
+
+    #[test]
+    fn test_incomplete_key_and_scalar_are_hidden() {
+        assert_eq!(parse_partial(r#"{"a": "x", "con"#).unwrap(), json!({ "a": "x" }));
+        assert_eq!(parse_partial(r#"{"a": 12"#).unwrap(), json!({}));
+        assert_eq!(parse_partial(r#"{"a": tr"#).unwrap(), json!({}));
+        assert_eq!(parse_partial(r#"{"a": "x\"#).unwrap(), json!({ "a": "x" }));
+        assert_eq!(parse_partial(r#"{"a": "\u00"#).unwrap(), json!({ "a": "" }));
+    }
+
+    #[test]
+    fn test_nested_and_complete() {
+        let text = r#"{"edits": [{"line": 3, "text": "}]{\"x\""}, {"line": 4}], "ok": true, "n": null}"#;
+        let full: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(parse_partial(text).unwrap(), full);
+
+        let partial = parse_partial(r#"{"edits": [{"line": 3, "text": "a"}, {"line": 4, "text": "b"#).unwrap();
+        assert_eq!(partial, json!({ "edits": [{ "line": 3, "text": "a" }, { "line": 4, "text": "b" }] }));
+    }
+
+    #[test]
+    fn test_incremental_chunks_match_full_parse() {
+        let text = r#"{"content": "emoji 😀 \ud83d\ude00 and tab\t", "path": "x/y.rs"}"#;
+        let mut parser = PartialJsonParser::new();
+        let mut last_len = 0;
+        for c in text.chars() {
+            parser.push(&c.to_string());
+            // 内容只增长不回退
+            if let Some(content) = parser.snapshot().and_then(|v| v["content"].as_str().map(|s| s.chars().count())) {
+                assert!(content >= last_len);
+                last_len = content;
+            }
+        }
+        assert!(parser.is_complete());
+        assert_eq!(parser.snapshot().unwrap(), serde_json::from_str::<Value>(text).unwrap());
+        assert_eq!(parser.snapshot().unwrap()["content"], "emoji 😀 😀 and tab\t");
+    }
+}
@@ -0,0 +1,298 @@
+/*!
+Paste Enrichment - 粘贴内容增强
+================================
+
+对用户粘贴的大段文本进行分类与增强：
+- 分类复用 `tool_classification` 的分层规则（Layer 1 堆栈标记，Layer 2 日志 / 代码占比）
+- 自动使用合适的 Markdown 代码围栏包裹
+- 提取项目中真实存在的 文件:行号 引用，并附带代码片段
+- 生成的上下文按会话暂存，由该会话下一次 `ai_chat` 注入系统提示词
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use regex::Regex;
+
+use crate::tool_classification::{classify_paste, ClassificationLayer};
+pub use crate::tool_classification::PasteKind;
+
+/// 低于该长度的粘贴内容不做增强
+const MIN_ENRICH_CHARS: usize = 200;
+/// 最多解析的引用数量
+const MAX_REFERENCES: usize = 8;
+/// 引用片段上下各保留的行数
+const SNIPPET_CONTEXT_LINES: usize = 5;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 项目内已解析的文件引用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedReference {
+    /// 相对项目根目录的路径
+    pub path: String,
+    pub line: usize,
+    /// 带行号的代码片段
+    pub snippet: String,
+}
+
+/// 增强结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnrichedPaste {
+    pub kind: PasteKind,
+    /// 命中的分类层级
+    pub layer: ClassificationLayer,
+    /// 推测的语言（用于代码围栏）
+    pub language: Option<String>,
+    /// 已加围栏的粘贴内容
+    pub fenced: String,
+    pub references: Vec<ResolvedReference>,
+    /// 附加到下一条消息的上下文（Markdown）
+    pub context: String,
+}
+
+// ============================================================================
+// Enrichment
+// ============================================================================
+
+/// 使用足够长的反引号围栏包裹，避免与内容中的 ``` 冲突
+fn fence(text: &str, info: &str) -> String {
+    let mut ticks = 3;
+    while text.contains(&"`".repeat(ticks)) {
+        ticks += 1;
+    }
+    let bar = "`".repeat(ticks);
+    format!("{}{}\n{}\n{}", bar, info, text.trim_end(), bar)
+}
+
+/// 提取 `path:line` 与 Python `File "path", line N` 形式的引用
+fn extract_file_refs(text: &str) -> Vec<(String, usize)> {
+    let colon = Regex::new(r#"([A-Za-z0-9_./\\-]+\.[A-Za-z0-9]+):(\d+)"#).unwrap();
+    let python = Regex::new(r#"File "([^"]+)", line (\d+)"#).unwrap();
+
+    let mut refs: Vec<(String, usize)> = Vec::new();
+    for caps in python.captures_iter(text).chain(colon.captures_iter(text)) {
+        let path = caps[1].to_string();
+        if let Ok(line) = caps[2].parse::<usize>() {
+            if line > 0 && !refs.iter().any(|(p, l)| *p == path && *l == line) {
+                refs.push((path, line));
+            }
+        }
+    }
+    refs
+}
+
+/// 将引用路径解析为项目内真实存在的文件
+fn resolve_in_project(root: &Path, raw: &str) -> Option<(PathBuf, String)> {
+    let raw = raw.replace('\\', "/");
+    let candidate = Path::new(&raw);
+    let abs = if candidate.is_absolute() {
+        candidate.to_path_buf()
+    } else {
+        root.join(raw.trim_start_matches("./"))
+    };
+
+    let canonical_root = root.canonicalize().ok()?;
+    let canonical = abs.canonicalize().ok()?;
+    if !canonical.starts_with(&canonical_root) || !canonical.is_file() {
+        return None;
+    }
+    let rel = canonical.strip_prefix(&canonical_root).ok()?.to_string_lossy().replace('\\', "/");
+    Some((canonical, rel))
+}
+
+/// 读取带行号的代码片段
+fn read_snippet(path: &Path, line: usize) -> Option<String> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let lines: Vec<&str> = content.lines().collect();
+    if line == 0 || line > lines.len() {
+        return None;
+    }
+    let start = line.saturating_sub(SNIPPET_CONTEXT_LINES + 1);
+    let end = (line + SNIPPET_CONTEXT_LINES).min(lines.len());
+    Some(
+        (start..end)
+            .map(|i| format!("{}{:>5} | {}", if i + 1 == line { ">" } else { " " }, i + 1, lines[i]))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// 对粘贴内容进行分类、加围栏并解析项目内引用
+pub fn enrich_paste(text: &str, project_root: Option<&str>) -> EnrichedPaste {
+    let classification = classify_paste(text);
+    let (kind, language) = (classification.kind, classification.language);
+    let info = match kind {
+        PasteKind::Log => "log".to_string(),
+        PasteKind::Text => String::new(),
+        _ => language.clone().unwrap_or_default(),
+    };
+    let fenced = if kind == PasteKind::Text { text.to_string() } else { fence(text, &info) };
+
+    let mut references = Vec::new();
+    if let Some(root) = project_root {
+        let root = Path::new(root);
+        for (raw, line) in extract_file_refs(text) {
+            if references.len() >= MAX_REFERENCES {
+                break;
+            }
+            if let Some((abs, rel)) = resolve_in_project(root, &raw) {
+                if let Some(snippet) = read_snippet(&abs, line) {
+                    references.push(ResolvedReference { path: rel, line, snippet });
+                }
+            }
+        }
+    }
+
+    let mut context = String::new();
+    if !references.is_empty() {
+        context.push_str("# Referenced Code From Pasted Content\n");
+        for r in &references {
+            context.push_str(&format!("\n## {}:{}\n{}\n", r.path, r.line, fence(&r.snippet, "")));
+        }
+    }
+
+    EnrichedPaste { kind, layer: classification.layer, language, fenced, references, context }
+}
+
+// ============================================================================
+// Pending context
+// ============================================================================
+
+/// 按会话 id（即 `ai_chat` 的 `session_id`）暂存的粘贴上下文
+static PENDING_CONTEXT: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn pending() -> &'static Mutex<HashMap<String, String>> {
+    PENDING_CONTEXT.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 追加会话的待附加上下文
+fn push_pending_context(session_id: &str, context: &str) {
+    if let Ok(mut guard) = pending().lock() {
+        guard.entry(session_id.to_string())
+            .and_modify(|prev| {
+                prev.push('\n');
+                prev.push_str(context);
+            })
+            .or_insert_with(|| context.to_string());
+    }
+}
+
+/// 取出会话的待附加粘贴上下文（取出后清空）
+pub fn take_pending_context(session_id: &str) -> Option<String> {
+    pending().lock().ok()?.remove(session_id)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 增强粘贴内容；短文本原样返回
+///
+/// `attach` 为 true 且提供 `session_id` 时，解析到的引用片段会附加到该会话下一次
+/// `ai_chat` 的上下文中；其他会话不受影响。
+#[tauri::command]
+pub fn enrich_pasted_text(
+    text: String,
+    project_root: Option<String>,
+    session_id: Option<String>,
+    attach: Option<bool>,
+) -> EnrichedPaste {
+    if text.len() < MIN_ENRICH_CHARS {
+        return EnrichedPaste {
+            kind: PasteKind::Text,
+            layer: ClassificationLayer::Layer2,
+            language: None,
+            fenced: text,
+            references: Vec::new(),
+            context: String::new(),
+        };
+    }
+
+    let enriched = enrich_paste(&text, project_root.as_deref());
+    println!("[PasteEnrichment] {:?} paste, {} references", enriched.kind, enriched.references.len());
+
+    if let Some(session_id) = session_id.filter(|_| attach.unwrap_or(true) && !enriched.context.is_empty()) {
+        push_pending_context(&session_id, &enriched.context);
+    }
+    enriched
+}
+
+/// 丢弃会话尚未发送的粘贴上下文
+#[tauri::command]
+pub fn clear_pending_paste_context(session_id: String) {
+    let _ = take_pending_context(&session_id);
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_stack_trace() {
+        let text = "Traceback (most recent call last):\n  File \"app/main.py\", line 3, in <module>\nValueError: bad";
+        let result = classify_paste(text);
+        assert_eq!((result.kind, result.layer), (PasteKind::StackTrace, ClassificationLayer::Layer1));
+        assert_eq!(result.language.as_deref(), Some("python"));
+
+        let js = "TypeError: x is undefined\n    at foo (src/a.js:10:5)\n    at bar (src/b.js:2:1)";
+        assert_eq!(classify_paste(js).kind, PasteKind::StackTrace);
+    }
+
+    #[test]
+    fn test_classify_log_and_code() {
+        let log = "2024-01-01 10:00:00 INFO start\n2024-01-01 10:00:01 WARN slow\n2024-01-01 10:00:02 ERROR boom";
+        assert_eq!(classify_paste(log).kind, PasteKind::Log);
+
+        let code = "use std::fs;\n\nfn main() {\n    let mut x = 1;\n    x += 1;\n}\n";
+        let result = classify_paste(code);
+        assert_eq!((result.kind, result.layer), (PasteKind::Code, ClassificationLayer::Layer2));
+        assert_eq!(result.language.as_deref(), Some("rust"));
+
+        assert_eq!(classify_paste("just some words here\nand more words").kind, PasteKind::Text);
+    }
+
+    #[test]
+    fn test_fence_escapes_backticks() {
+        let fenced = fence("a ``` b", "md");
+        assert!(fenced.starts_with("````md\n"));
+        assert!(fenced.ends_with("\n````"));
+    }
+
+    #[test]
+    fn test_extract_and_resolve_refs() {
+        let dir = std::env::temp_dir().join(format!("ifai_paste_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "line1\nline2\nline3\n").unwrap();
+
+        let text = "error: oops\n --> src/lib.rs:2:5\n --> src/missing.rs:9:1\n";
+        let refs = extract_file_refs(text);
+        assert_eq!(refs, vec![("src/lib.rs".to_string(), 2), ("src/missing.rs".to_string(), 9)]);
+
+        let enriched = enrich_paste(text, Some(&dir.to_string_lossy()));
+        assert_eq!(enriched.references.len(), 1);
+        assert_eq!(enriched.references[0].path, "src/lib.rs");
+        assert!(enriched.references[0].snippet.contains(">    2 | line2"));
+        assert!(enriched.context.contains("src/lib.rs:2"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_pending_context_is_per_session() {
+        push_pending_context("paste-a", "ctx a1");
+        push_pending_context("paste-a", "ctx a2");
+        push_pending_context("paste-b", "ctx b");
+
+        assert_eq!(take_pending_context("paste-a").as_deref(), Some("ctx a1\nctx a2"));
+        assert_eq!(take_pending_context("paste-a"), None);
+        assert_eq!(take_pending_context("paste-b").as_deref(), Some("ctx b"));
+    }
+}
@@ -0,0 +1,164 @@
+/*!
+Privacy - 会话隐私级别
+======================
+
+控制每个会话可以自动纳入哪些上下文，避免敏感讨论在不知情时把仓库内容发送到云端模型：
+
+- `strict`：不自动注入任何文件内容（关闭 RAG / 差异检索 / 粘贴增强，Agent 只能使用不返回文件内容的工具）
+- `normal`：允许项目文件（默认）
+- `open`：额外允许文档 / 网络抓取
+
+级别按 `session_id` 设置；Agent 启动时绑定所属会话的级别，运行期间在工具执行前检查。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// 隐私级别（按宽松程度排序）
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum PrivacyLevel {
+    Strict,
+    #[default]
+    Normal,
+    Open,
+}
+
+impl PrivacyLevel {
+    /// 是否允许自动注入项目文件内容
+    pub fn allows_project_content(self) -> bool {
+        self >= PrivacyLevel::Normal
+    }
+
+    /// 是否允许抓取文档 / 网页
+    pub fn allows_network(self) -> bool {
+        self >= PrivacyLevel::Open
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PrivacyLevel::Strict => "strict",
+            PrivacyLevel::Normal => "normal",
+            PrivacyLevel::Open => "open",
+        }
+    }
+}
+
+/// 会话 ID（或 `agent_{id}`）-> 级别
+static LEVELS: OnceLock<Mutex<HashMap<String, PrivacyLevel>>> = OnceLock::new();
+
+fn levels() -> &'static Mutex<HashMap<String, PrivacyLevel>> {
+    LEVELS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn agent_key(agent_id: &str) -> String {
+    format!("agent_{}", agent_id)
+}
+
+/// 会话的隐私级别（未设置时为默认级别）
+pub fn level_for_session(session_id: Option<&str>) -> PrivacyLevel {
+    session_id
+        .and_then(|id| levels().lock().ok()?.get(id).copied())
+        .unwrap_or_default()
+}
+
+/// Agent 启动时绑定所属会话的级别
+pub fn bind_agent(agent_id: &str, session_id: Option<&str>) {
+    let level = level_for_session(session_id);
+    if let Ok(mut levels) = levels().lock() {
+        levels.insert(agent_key(agent_id), level);
+    }
+}
+
+pub fn level_for_agent(agent_id: &str) -> PrivacyLevel {
+    levels().lock().ok().and_then(|l| l.get(&agent_key(agent_id)).copied()).unwrap_or_default()
+}
+
+/// Agent 结束时释放绑定
+pub fn release_agent(agent_id: &str) {
+    if let Ok(mut levels) = levels().lock() {
+        levels.remove(&agent_key(agent_id));
+    }
+}
+
+/// strict 级别下允许的工具：结果不包含文件内容（目录结构、沙箱内的 JS 求值）
+const CONTENT_FREE_TOOLS: &[&str] = &["agent_list_dir", "agent_scan_directory", "agent_eval_js"];
+
+/// 工具所需的最低级别（白名单：未列出的工具至少需要 `normal`）
+fn required_level(tool_name: &str) -> PrivacyLevel {
+    match tool_name {
+        name if CONTENT_FREE_TOOLS.contains(&name) => PrivacyLevel::Strict,
+        name if name.contains("fetch") || name.contains("web_search") => PrivacyLevel::Open,
+        _ => PrivacyLevel::Normal,
+    }
+}
+
+/// 检查工具在当前级别下是否允许执行，不允许时返回给模型的说明
+pub fn check_tool(level: PrivacyLevel, tool_name: &str) -> Result<(), String> {
+    let required = required_level(tool_name);
+    if level >= required {
+        Ok(())
+    } else {
+        Err(format!(
+            "Tool {} is not allowed: this conversation uses the '{}' privacy level (requires '{}'). Continue without it.",
+            tool_name, level.as_str(), required.as_str()
+        ))
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 设置会话隐私级别
+#[tauri::command]
+pub fn set_session_privacy_level(session_id: String, level: PrivacyLevel) -> Result<(), String> {
+    println!("[Privacy] Session {} -> {:?}", session_id, level);
+    levels().lock().map_err(|e| e.to_string())?.insert(session_id, level);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_session_privacy_level(session_id: String) -> PrivacyLevel {
+    level_for_session(Some(&session_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level_ordering() {
+        assert!(!PrivacyLevel::Strict.allows_project_content());
+        assert!(PrivacyLevel::Normal.allows_project_content());
+        assert!(!PrivacyLevel::Normal.allows_network());
+        assert!(PrivacyLevel::Open.allows_network());
+        assert_eq!(serde_json::to_string(&PrivacyLevel::Strict).unwrap(), "\"strict\"");
+    }
+
+    #[test]
+    fn test_check_tool() {
+        assert!(check_tool(PrivacyLevel::Strict, "agent_read_file").is_err());
+        assert!(check_tool(PrivacyLevel::Strict, "agent_list_dir").is_ok());
+        // 会返回文件内容的工具与未知工具在 strict 下一律拒绝
+        for tool in ["agent_grep", "agent_edit_file", "agent_get_diagnostics", "agent_read_tool_output", "some_new_tool"] {
+            assert!(check_tool(PrivacyLevel::Strict, tool).is_err(), "{} should be denied", tool);
+            assert!(check_tool(PrivacyLevel::Normal, tool).is_ok());
+        }
+        assert!(check_tool(PrivacyLevel::Normal, "bash").is_ok());
+        assert!(check_tool(PrivacyLevel::Normal, "agent_fetch_url").is_err());
+        assert!(check_tool(PrivacyLevel::Open, "agent_fetch_url").is_ok());
+    }
+
+    #[test]
+    fn test_agent_binding() {
+        set_session_privacy_level("s-strict".to_string(), PrivacyLevel::Strict).unwrap();
+        bind_agent("a1", Some("s-strict"));
+        bind_agent("a2", None);
+        assert_eq!(level_for_agent("a1"), PrivacyLevel::Strict);
+        assert_eq!(level_for_agent("a2"), PrivacyLevel::Normal);
+        release_agent("a1");
+        assert_eq!(level_for_agent("a1"), PrivacyLevel::Normal);
+    }
+}
@@ -1,7 +1,9 @@
 use tauri::command;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::{Mutex, OnceLock};
 
 /// Project-level configuration from `.ifai/IFAI.md`
 ///
@@ -27,6 +29,33 @@ pub struct ProjectConfig {
     /// Custom instructions for LLM (user editable)
     pub custom_instructions: Option<String>,
 
+    /// Conversation compaction thresholds and summary model override
+    pub summarization: Option<crate::conversation::SummarizationConfig>,
+
+    /// Paths outside the project root that agent tools may access
+    pub path_allowlist: Option<Vec<String>>,
+
+    /// Extension -> language mapping and per-file language overrides
+    pub languages: Option<crate::language_map::LanguageMapConfig>,
+
+    /// Embedding provider for semantic search (fastembed / OpenAI-compatible / local GGUF)
+    pub embedding: Option<crate::embedding::EmbeddingConfig>,
+
+    /// Formatter settings (auto-format after agent writes, disabled formatters)
+    pub formatting: Option<crate::formatter::FormattingConfig>,
+
+    /// Default provider/model per task type; overrides `ai_provider_id` / `ai_model`
+    pub models: Option<ModelRouting>,
+
+    /// Agent tool auto-approval policy; takes precedence over `.ifai/approval_policy.json`
+    pub approval: Option<crate::approval_policy::ApprovalPolicy>,
+
+    /// Extra gitignore-style patterns excluded from indexing, scans and agent prompts
+    pub ignore: Option<Vec<String>>,
+
+    /// Maximum context tokens sent per request (defaults to `summarization.max_tokens`)
+    pub max_context_tokens: Option<usize>,
+
     /// Project creation timestamp
     pub created_at: Option<i64>,
 }
@@ -40,11 +69,91 @@ impl Default for ProjectConfig {
             enable_rag: None,
             custom_system_prompt: None,
             custom_instructions: None,
+            summarization: None,
+            path_allowlist: None,
+            languages: None,
+            embedding: None,
+            formatting: None,
+            models: None,
+            approval: None,
+            ignore: None,
+            max_context_tokens: None,
             created_at: Some(chrono::Utc::now().timestamp()),
         }
     }
 }
 
+/// Task types that can be routed to a different provider/model
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelTask {
+    Chat,
+    Agent,
+    Completion,
+}
+
+/// Provider/model for one task type; either field may be omitted
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ModelRoute {
+    pub provider_id: Option<String>,
+    pub model: Option<String>,
+}
+
+/// `models` section of IFAI.md
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ModelRouting {
+    pub chat: Option<ModelRoute>,
+    pub agent: Option<ModelRoute>,
+    pub completion: Option<ModelRoute>,
+}
+
+impl ProjectConfig {
+    /// Route for a task: the task's `models` entry, falling back to `ai_provider_id` / `ai_model`
+    pub fn model_route(&self, task: ModelTask) -> Option<ModelRoute> {
+        let routing = self.models.as_ref();
+        let specific = match task {
+            ModelTask::Chat => routing.and_then(|r| r.chat.clone()),
+            ModelTask::Agent => routing.and_then(|r| r.agent.clone()),
+            ModelTask::Completion => routing.and_then(|r| r.completion.clone()),
+        }
+        .unwrap_or_default();
+        let route = ModelRoute {
+            provider_id: specific.provider_id.or_else(|| self.ai_provider_id.clone()),
+            model: specific.model.or_else(|| self.ai_model.clone()),
+        };
+        (route.provider_id.is_some() || route.model.is_some()).then_some(route)
+    }
+}
+
+/// Apply the project's model route to a request's provider config
+///
+/// Provider credentials live in the frontend, so the backend can only switch the model:
+/// the routed model is moved to the front of `models` when the route names no provider
+/// or names this one. Returns the route so callers can report a provider mismatch.
+pub fn apply_model_route(project_root: &str, task: ModelTask, config: &mut crate::core_traits::ai::AIProviderConfig) -> Option<ModelRoute> {
+    let route = load_project_config_sync(project_root)?.model_route(task)?;
+    match (&route.provider_id, &route.model) {
+        (Some(provider), _) if provider != &config.id => {
+            println!("[ProjectConfig] {:?} is routed to provider {}, but the request uses {}", task, provider, config.id);
+        }
+        (_, Some(model)) => {
+            config.models.retain(|m| m != model);
+            config.models.insert(0, model.clone());
+            println!("[ProjectConfig] Using project model {} for {:?}", model, task);
+        }
+        _ => {}
+    }
+    Some(route)
+}
+
+/// Context budget for a request: `max_context_tokens`, else the summarization threshold
+pub fn max_context_tokens(project_root: &str) -> usize {
+    load_project_config_sync(project_root)
+        .and_then(|c| c.max_context_tokens)
+        .unwrap_or_else(|| crate::conversation::SummarizationConfig::load(project_root).max_tokens)
+}
+
 /// Get the path to `.ifai/IFAI.md` for a project root
 fn get_config_path(project_root: &str) -> Result<PathBuf, String> {
     let root = Path::new(project_root);
@@ -53,7 +162,7 @@ fn get_config_path(project_root: &str) -> Result<PathBuf, String> {
 }
 
 /// Get the default IFAI.md content
-fn get_default_content() -> String {
+pub(crate) fn get_default_content() -> String {
     r#"---
 # IFAI Project Configuration
 # You can edit these settings directly
@@ -70,6 +179,56 @@ default_language: zh-CN
 custom_instructions: |
   请使用中文回答所有问题，除非用户明确要求使用其他语言。
 
+# Conversation compaction (optional, for small-context models)
+# summarization:
+#   max_tokens: 24000
+#   max_messages: 40
+#   tail_size: 6
+#   summary_model: local
+
+# Paths outside the project root that agent tools may read/write (optional)
+# path_allowlist:
+#   - ../shared-config
+#   - /tmp/ifai-scratch
+
+# Language detection overrides (optional)
+# languages:
+#   extensions:
+#     inc: php
+#   files:
+#     Jenkinsfile: groovy
+
+# Code formatting (optional): rustfmt / prettier / black / gofmt
+# formatting:
+#   on_agent_write: true
+#   disabled: [prettier]
+
+# Provider/model per task type (optional)
+# models:
+#   chat:
+#     model: glm-4.6
+#   agent:
+#     provider_id: anthropic
+#     model: claude-sonnet-4-5
+#   completion:
+#     model: deepseek-chat
+
+# Agent tool auto-approval (optional, overrides .ifai/approval_policy.json)
+# approval:
+#   categories:
+#     read_files: auto
+#     write_files: ask
+#     run_commands: ask
+#   approval_timeout_secs: 300
+
+# Paths excluded from indexing and agent scans (gitignore syntax, optional)
+# ignore:
+#   - fixtures/
+#   - "*.snap"
+
+# Maximum context tokens per request (optional)
+# max_context_tokens: 64000
+
 ---
 
 # Project Notes
@@ -87,6 +246,13 @@ custom_instructions: |
 - `ai_provider_id`: AI 提供商 ID (可选)
 - `ai_model`: AI 模型名称 (可选)
 - `custom_instructions`: 自定义指令，会添加到系统提示中
+- `summarization`: 对话压缩配置（触发阈值、保留的最近消息数、摘要模型、`enabled: false` 关闭）
+- `path_allowlist`: 允许 Agent 工具访问的项目外路径（相对项目根目录或绝对路径）
+- `languages`: 扩展名到语言的映射（`extensions`）及按 glob 指定单个文件的语言（`files`）
+- `models`: 按任务类型（`chat` / `agent` / `completion`）指定 Provider 与模型
+- `approval`: Agent 工具调用的自动审批策略，优先于 `.ifai/approval_policy.json`
+- `ignore`: 额外排除的路径（gitignore 语法），与 `.ifaiignore` 一同生效
+- `max_context_tokens`: 单次请求的上下文 Token 上限
 
 ### 示例
 
@@ -123,7 +289,30 @@ fn parse_frontmatter(content: &str) -> Result<ProjectConfig, String> {
 /// Load project configuration synchronously (for internal use)
 ///
 /// This is a synchronous version for use in non-async contexts like prompt_manager
+///
+/// v0.3.4: 被 `prompt_watch` 监听的项目按修订号缓存解析结果，IFAI.md 变化后重新读取
 pub fn load_project_config_sync(project_root: &str) -> Option<ProjectConfig> {
+    static CACHE: OnceLock<Mutex<HashMap<String, (u64, Option<ProjectConfig>)>>> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+
+    // 先取修订号再读文件：读取期间的修改会让本次缓存随即失效
+    let revision = crate::prompt_watch::revision(project_root);
+    if let Some(revision) = revision {
+        if let Some((cached, config)) = cache.lock().ok().and_then(|map| map.get(project_root).cloned()) {
+            if cached == revision {
+                return config;
+            }
+        }
+    }
+
+    let config = read_project_config(project_root);
+    if let (Some(revision), Ok(mut map)) = (revision, cache.lock()) {
+        map.insert(project_root.to_string(), (revision, config.clone()));
+    }
+    config
+}
+
+fn read_project_config(project_root: &str) -> Option<ProjectConfig> {
     let config_path = get_config_path(project_root).ok()?;
 
     if !config_path.exists() {
@@ -205,6 +394,12 @@ pub async fn parse_project_config(content: String) -> Result<ProjectConfig, Stri
     parse_frontmatter(&content)
 }
 
+/// Resolve the provider/model configured for a task type (None when IFAI.md sets none)
+#[command]
+pub async fn resolve_model_route(project_root: String, task: ModelTask) -> Result<Option<ModelRoute>, String> {
+    Ok(load_project_config_sync(&project_root).and_then(|c| c.model_route(task)))
+}
+
 /// Check if `.ifai/IFAI.md` exists for a project
 #[command]
 pub async fn project_config_exists(project_root: String) -> Result<bool, String> {
@@ -266,6 +461,61 @@ custom_instructions: |
         assert_eq!(config.custom_instructions, Some("Please respond in English.".to_string()));
     }
 
+    #[test]
+    fn test_parse_summarization() {
+        let content = r#"---
+default_language: en-US
+summarization:
+  max_tokens: 24000
+  tail_size: 6
+  summary_model: local
+---
+"#;
+
+        let config = parse_frontmatter(content).unwrap();
+        let summarization = config.summarization.unwrap();
+        assert_eq!(summarization.max_tokens, 24000);
+        assert_eq!(summarization.tail_size, 6);
+        // 未填写的字段使用默认值
+        assert_eq!(summarization.max_messages, 100);
+        assert!(summarization.enabled);
+        assert_eq!(summarization.summary_model.as_deref(), Some("local"));
+    }
+
+    #[test]
+    fn test_parse_routing_and_policy() {
+        let content = r#"---
+ai_model: glm-4.6
+models:
+  agent:
+    provider_id: anthropic
+    model: claude-sonnet-4-5
+  completion:
+    model: deepseek-chat
+approval:
+  yolo: true
+ignore:
+  - fixtures/
+max_context_tokens: 64000
+---
+"#;
+
+        let config = parse_frontmatter(content).unwrap();
+        let agent = config.model_route(ModelTask::Agent).unwrap();
+        assert_eq!((agent.provider_id.as_deref(), agent.model.as_deref()), (Some("anthropic"), Some("claude-sonnet-4-5")));
+        assert_eq!(config.model_route(ModelTask::Completion).unwrap().model.as_deref(), Some("deepseek-chat"));
+        // 未配置的任务回退到 ai_model
+        assert_eq!(config.model_route(ModelTask::Chat).unwrap().model.as_deref(), Some("glm-4.6"));
+
+        let approval = config.approval.unwrap();
+        assert!(approval.yolo);
+        // 未填写的审批字段使用默认值
+        assert!(!approval.denied_commands.is_empty());
+        assert_eq!(config.ignore, Some(vec!["fixtures/".to_string()]));
+        assert_eq!(config.max_context_tokens, Some(64000));
+        assert_eq!(ProjectConfig::default().model_route(ModelTask::Chat), None);
+    }
+
     #[test]
     fn test_parse_no_frontmatter() {
         let content = r#"# Just markdown
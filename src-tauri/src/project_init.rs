@@ -0,0 +1,163 @@
+/*!
+Project Init - `.ifai/` 目录初始化
+==================================
+
+为新项目生成标准的 `.ifai/` 目录结构，附带带注释的默认配置，
+新用户无需阅读文档即可获得可用的配置：
+
+```text
+.ifai/
+├── IFAI.md              项目配置（YAML 头）与项目说明
+├── settings.json        项目设置
+├── prompts/             提示词覆盖（system / agents / languages）
+├── modes/               自定义对话模式
+└── scaffolds/           代码脚手架模板
+```
+
+已存在的文件不会被覆盖（除非显式指定 `overwrite`），初始化后项目会记录到最近项目列表。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+/// 初始化结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InitReport {
+    pub root: String,
+    /// 新建的文件（相对 `.ifai/`）
+    pub created: Vec<String>,
+    /// 已存在而跳过的文件
+    pub skipped: Vec<String>,
+}
+
+const SETTINGS_JSON: &str = r#"{
+  "//": "IfAI project settings. Keys starting with // are comments and are ignored.",
+  "agent": {
+    "//": "Maximum tool-call loops per agent run, and whether agent writes need approval",
+    "max_loops": 12,
+    "require_write_approval": true
+  },
+  "indexing": {
+    "//": "Glob patterns excluded from the symbol index and RAG, in addition to .gitignore",
+    "exclude": ["node_modules/**", "target/**", "dist/**", ".git/**"]
+  },
+  "context": {
+    "//": "Token budget for per-language prompt addenda (.ifai/prompts/languages)",
+    "language_addendum_budget": 600
+  }
+}
+"#;
+
+const PROMPTS_README: &str = r#"# Prompt Overrides
+
+Files here override the built-in prompts for this project only.
+
+- `system/main.md` — replaces the main system prompt (`system/main.override.md` takes precedence)
+- `agents/{agent-type}.md` — replaces an agent prompt, e.g. `agents/review.md`
+- `languages/{language}.md` — guidelines appended when the conversation focuses on that language,
+  e.g. `languages/rust.md`
+
+Prompt files may start with a YAML header:
+
+```markdown
+---
+name: review
+description: Project-specific review rules
+---
+Review the changes in {{TASK_DESCRIPTION}} ...
+```
+"#;
+
+const MODES_README: &str = r#"# Modes
+
+Each `*.md` file defines a chat mode: a named preset with its own instructions.
+
+```markdown
+---
+name: architect
+description: Discuss design before writing code
+---
+Focus on architecture trade-offs. Do not write code until the design is agreed.
+```
+"#;
+
+const SCAFFOLDS_README: &str = r#"# Scaffolds
+
+Each sub-directory is a code template the agent can copy when creating new files,
+e.g. `scaffolds/component/` with `{{name}}` placeholders in file names and contents.
+"#;
+
+/// 需要生成的文件：(相对 `.ifai/` 的路径, 内容)
+fn scaffold_files() -> Vec<(&'static str, String)> {
+    vec![
+        ("IFAI.md", crate::project_config::get_default_content()),
+        ("settings.json", SETTINGS_JSON.to_string()),
+        ("prompts/README.md", PROMPTS_README.to_string()),
+        ("modes/README.md", MODES_README.to_string()),
+        ("scaffolds/README.md", SCAFFOLDS_README.to_string()),
+    ]
+}
+
+/// 生成 `.ifai/` 目录结构
+pub fn scaffold_project(project_root: &str, overwrite: bool) -> Result<InitReport, String> {
+    let root = Path::new(project_root);
+    if !root.is_dir() {
+        return Err(format!("Project root does not exist: {}", project_root));
+    }
+    let ifai_dir = root.join(".ifai");
+
+    let mut report = InitReport { root: project_root.to_string(), ..Default::default() };
+    for dir in ["prompts/system", "prompts/agents", "prompts/languages", "modes", "scaffolds"] {
+        std::fs::create_dir_all(ifai_dir.join(dir))
+            .map_err(|e| format!("Failed to create .ifai directory: {}", e))?;
+    }
+
+    for (rel, content) in scaffold_files() {
+        let path = ifai_dir.join(rel);
+        if path.exists() && !overwrite {
+            report.skipped.push(rel.to_string());
+            continue;
+        }
+        std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", rel, e))?;
+        report.created.push(rel.to_string());
+    }
+
+    println!("[ProjectInit] Initialized {} ({} created, {} skipped)", project_root, report.created.len(), report.skipped.len());
+    Ok(report)
+}
+
+/// 初始化项目的 `.ifai/` 配置并记录到最近项目
+#[tauri::command]
+pub async fn init_ifai_project(app: AppHandle, root: String, overwrite: Option<bool>) -> Result<InitReport, String> {
+    let report = scaffold_project(&root, overwrite.unwrap_or(false))?;
+    if let Err(e) = crate::recent_projects::record_project(&app, &root) {
+        eprintln!("[ProjectInit] Failed to record recent project: {}", e);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scaffold_is_idempotent() {
+        let root = std::env::temp_dir().join(format!("ifai_init_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        let first = scaffold_project(&root_str, false).unwrap();
+        assert_eq!(first.created.len(), 5);
+        assert!(root.join(".ifai/prompts/languages").is_dir());
+        let settings: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(root.join(".ifai/settings.json")).unwrap()).unwrap();
+        assert_eq!(settings["agent"]["max_loops"], 12);
+
+        std::fs::write(root.join(".ifai/IFAI.md"), "custom").unwrap();
+        let second = scaffold_project(&root_str, false).unwrap();
+        assert!(second.created.is_empty());
+        assert_eq!(std::fs::read_to_string(root.join(".ifai/IFAI.md")).unwrap(), "custom");
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,214 @@
+/*!
+Prompt Cache - 提示词缓存
+=========================
+
+`ai_chat` 每轮都会发送很长的 system 提示词与项目上下文，相邻请求之间这部分基本不变：
+
+- Anthropic：为最后一个工具定义、第一个与最后一个 system 块加上 `cache_control` 断点；
+  主提示词与 RAG 上下文是两个 system 块，上下文变化时主提示词仍可命中
+- OpenAI / DeepSeek / Gemini：服务端自动缓存相同前缀，无需标注，稳定内容放在前面即可
+- 响应 usage 中的缓存命中 / 写入 Token 数按 Provider 累计，通过 `get_prompt_cache_stats` 查询
+*/
+
+use crate::core_traits::ai::AIProviderConfig;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+// ============================================================================
+// 请求标注
+// ============================================================================
+
+fn mark(block: &mut Value) {
+    if block.is_object() {
+        block["cache_control"] = json!({ "type": "ephemeral" });
+    }
+}
+
+/// 为 Anthropic 请求体加上缓存断点（最多 3 个，接口上限为 4 个）
+pub fn annotate_anthropic(body: &mut Value) {
+    if let Some(last_tool) = body.get_mut("tools").and_then(Value::as_array_mut).and_then(|tools| tools.last_mut()) {
+        mark(last_tool);
+    }
+    if let Some(system) = body.get_mut("system").and_then(Value::as_array_mut) {
+        let last = system.len().saturating_sub(1);
+        for index in [0, last] {
+            if let Some(block) = system.get_mut(index) {
+                mark(block);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// 用量统计
+// ============================================================================
+
+/// 单次请求的输入 Token 构成
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct CacheUsage {
+    /// 从缓存读取
+    pub cached_tokens: u64,
+    /// 写入缓存（仅 Anthropic 单独计费）
+    pub cache_write_tokens: u64,
+    /// 未命中缓存
+    pub uncached_tokens: u64,
+}
+
+impl CacheUsage {
+    /// Anthropic `usage`：`input_tokens` 不含缓存部分
+    pub fn from_anthropic(usage: &Value) -> Option<Self> {
+        Some(Self {
+            uncached_tokens: usage["input_tokens"].as_u64()?,
+            cached_tokens: usage["cache_read_input_tokens"].as_u64().unwrap_or(0),
+            cache_write_tokens: usage["cache_creation_input_tokens"].as_u64().unwrap_or(0),
+        })
+    }
+
+    /// OpenAI 兼容 `usage`：OpenAI 的 `prompt_tokens_details.cached_tokens`，DeepSeek 的 `prompt_cache_hit_tokens`
+    pub fn from_openai(usage: &Value) -> Option<Self> {
+        let prompt = usage["prompt_tokens"].as_u64()?;
+        let cached = usage["prompt_tokens_details"]["cached_tokens"]
+            .as_u64()
+            .or_else(|| usage["prompt_cache_hit_tokens"].as_u64())
+            .unwrap_or(0)
+            .min(prompt);
+        Some(Self { cached_tokens: cached, cache_write_tokens: 0, uncached_tokens: prompt - cached })
+    }
+
+    /// Gemini `usageMetadata`：`cachedContentTokenCount` 包含在 `promptTokenCount` 中
+    pub fn from_gemini(metadata: &Value) -> Option<Self> {
+        let prompt = metadata["promptTokenCount"].as_u64()?;
+        let cached = metadata["cachedContentTokenCount"].as_u64().unwrap_or(0).min(prompt);
+        Some(Self { cached_tokens: cached, cache_write_tokens: 0, uncached_tokens: prompt - cached })
+    }
+
+    /// 非流式响应体
+    pub fn from_response(config: &AIProviderConfig, response: &Value) -> Option<Self> {
+        if crate::anthropic_api::is_anthropic(config) {
+            Self::from_anthropic(&response["usage"])
+        } else if crate::gemini_api::is_gemini(config) {
+            Self::from_gemini(&response["usageMetadata"])
+        } else {
+            Self::from_openai(&response["usage"])
+        }
+    }
+
+    /// Anthropic 流式事件：输入用量只在 `message_start` 中给出
+    pub fn from_anthropic_event(event: &Value) -> Option<Self> {
+        (event["type"] == "message_start").then(|| Self::from_anthropic(&event["message"]["usage"])).flatten()
+    }
+}
+
+/// 单个 Provider 的累计统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptCacheStats {
+    pub requests: u64,
+    /// 至少读取了一部分缓存的请求数
+    pub hits: u64,
+    pub cached_tokens: u64,
+    pub cache_write_tokens: u64,
+    pub uncached_tokens: u64,
+    /// 输入 Token 中从缓存读取的比例
+    pub hit_rate: f64,
+}
+
+impl PromptCacheStats {
+    fn record(&mut self, usage: CacheUsage) {
+        self.requests += 1;
+        if usage.cached_tokens > 0 {
+            self.hits += 1;
+        }
+        self.cached_tokens += usage.cached_tokens;
+        self.cache_write_tokens += usage.cache_write_tokens;
+        self.uncached_tokens += usage.uncached_tokens;
+        let total = self.cached_tokens + self.cache_write_tokens + self.uncached_tokens;
+        self.hit_rate = if total == 0 { 0.0 } else { self.cached_tokens as f64 / total as f64 };
+    }
+}
+
+fn stats() -> &'static Mutex<HashMap<String, PromptCacheStats>> {
+    static STATS: OnceLock<Mutex<HashMap<String, PromptCacheStats>>> = OnceLock::new();
+    STATS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn provider_key(config: &AIProviderConfig) -> String {
+    [&config.id, &config.name, &config.base_url]
+        .into_iter()
+        .find(|s| !s.is_empty())
+        .cloned()
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// 记录一次请求的缓存用量；`usage` 为 None（接口未返回）时忽略
+pub fn record(config: &AIProviderConfig, usage: Option<CacheUsage>) {
+    let Some(usage) = usage else { return };
+    let key = provider_key(config);
+    println!(
+        "[PromptCache] {}: {} cached, {} written, {} uncached input tokens",
+        key, usage.cached_tokens, usage.cache_write_tokens, usage.uncached_tokens
+    );
+    if let Ok(mut map) = stats().lock() {
+        map.entry(key).or_default().record(usage);
+    }
+}
+
+/// 按 Provider 的提示词缓存统计
+#[tauri::command]
+pub fn get_prompt_cache_stats() -> HashMap<String, PromptCacheStats> {
+    stats().lock().map(|map| map.clone()).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn reset_prompt_cache_stats() {
+    if let Ok(mut map) = stats().lock() {
+        map.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_anthropic() {
+        let mut body = json!({
+            "system": [{ "type": "text", "text": "main" }, { "type": "text", "text": "context" }],
+            "tools": [{ "name": "a" }, { "name": "b" }],
+            "messages": [],
+        });
+        annotate_anthropic(&mut body);
+        assert!(body["tools"][0].get("cache_control").is_none());
+        assert_eq!(body["tools"][1]["cache_control"], json!({ "type": "ephemeral" }));
+        assert_eq!(body["system"][0]["cache_control"]["type"], "ephemeral");
+        assert_eq!(body["system"][1]["cache_control"]["type"], "ephemeral");
+
+        let mut bare = json!({ "messages": [] });
+        annotate_anthropic(&mut bare);
+        assert_eq!(bare, json!({ "messages": [] }));
+    }
+
+    #[test]
+    fn test_cache_usage_and_stats() {
+        let anthropic = CacheUsage::from_anthropic_event(&json!({
+            "type": "message_start",
+            "message": { "usage": { "input_tokens": 20, "cache_read_input_tokens": 900, "cache_creation_input_tokens": 80 } }
+        }));
+        assert_eq!(anthropic, Some(CacheUsage { cached_tokens: 900, cache_write_tokens: 80, uncached_tokens: 20 }));
+        assert_eq!(CacheUsage::from_anthropic_event(&json!({ "type": "message_delta", "usage": { "output_tokens": 5 } })), None);
+
+        let openai = CacheUsage::from_openai(&json!({ "prompt_tokens": 1000, "prompt_tokens_details": { "cached_tokens": 768 } }));
+        assert_eq!(openai, Some(CacheUsage { cached_tokens: 768, cache_write_tokens: 0, uncached_tokens: 232 }));
+        let deepseek = CacheUsage::from_openai(&json!({ "prompt_tokens": 100, "prompt_cache_hit_tokens": 64, "prompt_cache_miss_tokens": 36 }));
+        assert_eq!(deepseek.map(|u| u.cached_tokens), Some(64));
+        let gemini = CacheUsage::from_gemini(&json!({ "promptTokenCount": 50, "cachedContentTokenCount": 40 }));
+        assert_eq!(gemini.map(|u| u.uncached_tokens), Some(10));
+
+        let mut stats = PromptCacheStats::default();
+        stats.record(CacheUsage { cached_tokens: 0, cache_write_tokens: 100, uncached_tokens: 0 });
+        stats.record(CacheUsage { cached_tokens: 100, cache_write_tokens: 0, uncached_tokens: 0 });
+        assert_eq!((stats.requests, stats.hits), (2, 1));
+        assert!((stats.hit_rate - 0.5).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,546 @@
+/*!
+Prompt Bundles - 提示词包导入/导出
+==================================
+
+把项目的 `.ifai/prompts/` 打包为 zip，在团队之间共享提示词：
+
+- 包内容：`manifest.json`（名称、作者、每个文件的 SHA-256 与访问级别）、
+  `manifest.sig`（manifest 的 Ed25519 签名）与 `prompts/...`
+- 签名密钥首次导出时生成，保存在 `~/.ifai/prompt_bundles/signing_key.pk8`；
+  导入时校验签名与文件哈希，签名者不在信任列表中时需要显式允许
+- 访问级别：`private` 提示词不导出也不导入；已有的 `protected` 提示词与内置 `system/` 提示词不会被覆盖，改为重命名
+- 冲突处理：`skip` / `overwrite` / `rename`（`review.md` → `review-1.md`）
+- 导入来源可以是本地文件或 http(s) URL
+*/
+
+use base64::Engine;
+use ring::rand::SystemRandom;
+use ring::signature::{self, Ed25519KeyPair, KeyPair};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+use walkdir::WalkDir;
+
+use super::{storage, AccessTier, BuiltinPrompts};
+
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST_FILE: &str = "manifest.json";
+const SIGNATURE_FILE: &str = "manifest.sig";
+const PROMPTS_PREFIX: &str = "prompts/";
+/// 包大小上限（下载与解压）
+const MAX_BUNDLE_BYTES: usize = 10 * 1024 * 1024;
+
+// ============================================================================
+// 类型定义
+// ============================================================================
+
+/// 包内文件名 → 内容
+type BundleFiles = BTreeMap<String, Vec<u8>>;
+
+/// 包中的一个提示词文件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundleEntry {
+    /// 相对 `.ifai/prompts/` 的路径
+    pub path: String,
+    pub sha256: String,
+    #[serde(default)]
+    pub name: String,
+    pub access_tier: AccessTier,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub author: Option<String>,
+    pub created_at: String,
+    /// 签名公钥（base64）
+    pub public_key: String,
+    pub files: Vec<BundleEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    #[default]
+    Skip,
+    Overwrite,
+    Rename,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImportedPrompt {
+    /// 写入的路径（重命名后的路径）
+    pub path: String,
+    /// "created" | "overwritten" | "renamed" | "skipped"
+    pub action: String,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleImportReport {
+    pub name: String,
+    pub author: Option<String>,
+    /// 签名公钥指纹
+    pub signer: String,
+    pub trusted: bool,
+    pub prompts: Vec<ImportedPrompt>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleExportReport {
+    pub path: String,
+    pub signer: String,
+    pub files: Vec<String>,
+    /// 因 `private` 未导出的文件
+    pub skipped_private: Vec<String>,
+}
+
+// ============================================================================
+// 签名密钥与信任列表
+// ============================================================================
+
+fn bundle_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("prompt_bundles")
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// 公钥指纹（SHA-256 前 16 位）
+fn fingerprint(public_key: &[u8]) -> String {
+    sha256_hex(public_key)[..16].to_string()
+}
+
+/// 读取签名密钥，不存在时生成
+fn load_or_create_key(dir: &Path) -> Result<Ed25519KeyPair, String> {
+    let path = dir.join("signing_key.pk8");
+    let pkcs8 = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            let document = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).map_err(|_| "Failed to generate signing key".to_string())?;
+            std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+            std::fs::write(&path, document.as_ref()).map_err(|e| format!("Failed to save signing key: {}", e))?;
+            println!("[PromptBundle] Generated signing key {}", path.display());
+            document.as_ref().to_vec()
+        }
+    };
+    Ed25519KeyPair::from_pkcs8(&pkcs8).map_err(|_| format!("Invalid signing key: {}", path.display()))
+}
+
+fn trusted_keys_path(dir: &Path) -> PathBuf {
+    dir.join("trusted_keys.json")
+}
+
+/// 信任的签名者指纹（本机密钥始终信任）
+fn trusted_signers(dir: &Path) -> Vec<String> {
+    let mut signers: Vec<String> = std::fs::read_to_string(trusted_keys_path(dir))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    if let Ok(bytes) = std::fs::read(dir.join("signing_key.pk8")) {
+        if let Ok(key) = Ed25519KeyPair::from_pkcs8(&bytes) {
+            signers.push(fingerprint(key.public_key().as_ref()));
+        }
+    }
+    signers
+}
+
+// ============================================================================
+// 打包
+// ============================================================================
+
+/// 只接受 `.ifai/prompts/` 内的相对 `.md` 路径
+fn safe_rel_path(path: &str) -> Option<PathBuf> {
+    let rel = Path::new(path);
+    let normal = rel.components().all(|c| matches!(c, Component::Normal(_)));
+    (normal && rel.extension().is_some_and(|ext| ext == "md")).then(|| rel.to_path_buf())
+}
+
+/// 提示词的名称与访问级别；没有 front matter 的片段视为 public
+fn prompt_info(data: &[u8]) -> (String, AccessTier) {
+    let text = String::from_utf8_lossy(data);
+    storage::parse_front_matter(&text)
+        .map(|(meta, _)| (meta.name, meta.access_tier))
+        .unwrap_or((String::new(), AccessTier::Public))
+}
+
+/// 收集项目提示词，返回（路径 → 内容，跳过的 private 文件）
+fn collect_prompts(prompts_dir: &Path) -> (BTreeMap<String, Vec<u8>>, Vec<String>) {
+    let mut files = BTreeMap::new();
+    let mut skipped = Vec::new();
+    for entry in WalkDir::new(prompts_dir).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !entry.file_type().is_file() || path.extension().is_none_or(|ext| ext != "md") {
+            continue;
+        }
+        let Ok(rel) = path.strip_prefix(prompts_dir) else { continue };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        let Ok(data) = std::fs::read(path) else { continue };
+        if prompt_info(&data).1 == AccessTier::Private {
+            skipped.push(rel);
+        } else {
+            files.insert(rel, data);
+        }
+    }
+    (files, skipped)
+}
+
+/// 生成包内的全部条目（manifest、签名与提示词）
+fn build_bundle(
+    files: &BTreeMap<String, Vec<u8>>,
+    name: &str,
+    description: &str,
+    author: Option<String>,
+    key: &Ed25519KeyPair,
+) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let manifest = BundleManifest {
+        format_version: FORMAT_VERSION,
+        name: name.to_string(),
+        description: description.to_string(),
+        author,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        public_key: base64::engine::general_purpose::STANDARD.encode(key.public_key().as_ref()),
+        files: files
+            .iter()
+            .map(|(path, data)| {
+                let (name, access_tier) = prompt_info(data);
+                BundleEntry { path: path.clone(), sha256: sha256_hex(data), name, access_tier }
+            })
+            .collect(),
+    };
+    let manifest_bytes = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    let signature = base64::engine::general_purpose::STANDARD.encode(key.sign(&manifest_bytes).as_ref());
+
+    let mut entries: BTreeMap<String, Vec<u8>> = files.iter().map(|(path, data)| (format!("{}{}", PROMPTS_PREFIX, path), data.clone())).collect();
+    entries.insert(MANIFEST_FILE.to_string(), manifest_bytes);
+    entries.insert(SIGNATURE_FILE.to_string(), signature.into_bytes());
+    Ok(entries)
+}
+
+/// 校验签名与文件哈希，返回 manifest、提示词内容与签名者指纹
+fn open_bundle(mut entries: BundleFiles) -> Result<(BundleManifest, BundleFiles, String), String> {
+    let manifest_bytes = entries.remove(MANIFEST_FILE).ok_or("Not a prompt bundle: manifest.json is missing")?;
+    let manifest: BundleManifest = serde_json::from_slice(&manifest_bytes).map_err(|e| format!("Invalid bundle manifest: {}", e))?;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(format!("Bundle format version {} is newer than supported ({})", manifest.format_version, FORMAT_VERSION));
+    }
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let signature = entries.remove(SIGNATURE_FILE).ok_or("Bundle is not signed: manifest.sig is missing")?;
+    let signature = engine.decode(String::from_utf8_lossy(&signature).trim()).map_err(|_| "Invalid bundle signature encoding")?;
+    let public_key = engine.decode(&manifest.public_key).map_err(|_| "Invalid bundle public key")?;
+    signature::UnparsedPublicKey::new(&signature::ED25519, &public_key)
+        .verify(&manifest_bytes, &signature)
+        .map_err(|_| "Bundle signature does not match its manifest".to_string())?;
+
+    let mut prompts = BTreeMap::new();
+    for entry in &manifest.files {
+        if safe_rel_path(&entry.path).is_none() {
+            return Err(format!("Bundle contains an unsafe path: {}", entry.path));
+        }
+        let data = entries
+            .remove(&format!("{}{}", PROMPTS_PREFIX, entry.path))
+            .ok_or_else(|| format!("Bundle is missing {}", entry.path))?;
+        if sha256_hex(&data) != entry.sha256 {
+            return Err(format!("Checksum mismatch for {}", entry.path));
+        }
+        prompts.insert(entry.path.clone(), data);
+    }
+    if let Some(extra) = entries.keys().find(|name| name.starts_with(PROMPTS_PREFIX)) {
+        return Err(format!("Bundle contains a file not listed in its manifest: {}", extra));
+    }
+    Ok((manifest, prompts, fingerprint(&public_key)))
+}
+
+// ============================================================================
+// 安装
+// ============================================================================
+
+/// 受保护的目标：已有的 protected 提示词，或内置的 `system/` 提示词
+fn is_protected(prompts_dir: &Path, rel: &str) -> bool {
+    let existing = std::fs::read(prompts_dir.join(rel)).ok().map(|data| prompt_info(&data).1);
+    existing == Some(AccessTier::Protected)
+        || (rel.starts_with("system/") && (existing.is_some() || BuiltinPrompts::get(&rel.replace(".override.md", ".md")).is_some()))
+}
+
+/// `review.md` → `review-1.md`，直到不冲突
+fn unique_path(prompts_dir: &Path, rel: &str) -> String {
+    let stem = rel.strip_suffix(".md").unwrap_or(rel);
+    (1..)
+        .map(|i| format!("{}-{}.md", stem, i))
+        .find(|candidate| !prompts_dir.join(candidate).exists())
+        .unwrap_or_else(|| rel.to_string())
+}
+
+fn install(prompts_dir: &Path, manifest: &BundleManifest, prompts: &BTreeMap<String, Vec<u8>>, strategy: ConflictStrategy) -> Result<Vec<ImportedPrompt>, String> {
+    let skipped = |path: &str, reason: &str| ImportedPrompt { path: path.to_string(), action: "skipped".to_string(), reason: Some(reason.to_string()) };
+    let mut results = Vec::new();
+    for entry in &manifest.files {
+        let Some(data) = prompts.get(&entry.path) else { continue };
+        if entry.access_tier == AccessTier::Private || prompt_info(data).1 == AccessTier::Private {
+            results.push(skipped(&entry.path, "private prompts cannot be imported"));
+            continue;
+        }
+        if let Err(e) = storage::validate_prompt_content(&String::from_utf8_lossy(data)) {
+            results.push(skipped(&entry.path, &e));
+            continue;
+        }
+
+        let protected = is_protected(prompts_dir, &entry.path);
+        let exists = prompts_dir.join(&entry.path).exists() || protected;
+        let (target, action, reason) = match (exists, strategy) {
+            (false, _) => (entry.path.clone(), "created", None),
+            (true, ConflictStrategy::Skip) => {
+                results.push(skipped(&entry.path, "a prompt with this path already exists"));
+                continue;
+            }
+            (true, ConflictStrategy::Overwrite) if !protected => (entry.path.clone(), "overwritten", None),
+            (true, ConflictStrategy::Overwrite) => {
+                (unique_path(prompts_dir, &entry.path), "renamed", Some("the existing prompt is protected".to_string()))
+            }
+            (true, ConflictStrategy::Rename) => (unique_path(prompts_dir, &entry.path), "renamed", None),
+        };
+
+        let path = prompts_dir.join(&target);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        std::fs::write(&path, data).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        results.push(ImportedPrompt { path: target, action: action.to_string(), reason });
+    }
+    Ok(results)
+}
+
+// ============================================================================
+// zip 读写
+// ============================================================================
+
+fn write_zip(entries: &BTreeMap<String, Vec<u8>>) -> Result<Vec<u8>, String> {
+    let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for (name, data) in entries {
+        writer.start_file(name.as_str(), options).map_err(|e| e.to_string())?;
+        writer.write_all(data).map_err(|e| e.to_string())?;
+    }
+    writer.finish().map(|cursor| cursor.into_inner()).map_err(|e| e.to_string())
+}
+
+fn read_zip(bytes: &[u8]) -> Result<BTreeMap<String, Vec<u8>>, String> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).map_err(|e| format!("Invalid bundle archive: {}", e))?;
+    let mut entries = BTreeMap::new();
+    let mut total = 0;
+    for i in 0..archive.len() {
+        let file = archive.by_index(i).map_err(|e| format!("Invalid bundle archive: {}", e))?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_string();
+        let mut data = Vec::new();
+        file.take((MAX_BUNDLE_BYTES - total) as u64 + 1).read_to_end(&mut data).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+        total += data.len();
+        if total > MAX_BUNDLE_BYTES {
+            return Err(format!("Bundle is larger than {} MB when extracted", MAX_BUNDLE_BYTES / 1024 / 1024));
+        }
+        entries.insert(name, data);
+    }
+    Ok(entries)
+}
+
+async fn fetch_bundle(url: &str) -> Result<Vec<u8>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let bytes = client.get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download bundle: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Failed to download bundle: {}", e))?
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download bundle: {}", e))?;
+    if bytes.len() > MAX_BUNDLE_BYTES {
+        return Err(format!("Bundle is larger than {} MB", MAX_BUNDLE_BYTES / 1024 / 1024));
+    }
+    Ok(bytes.to_vec())
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 导出项目的 `.ifai/prompts/` 为签名的 zip 包
+#[tauri::command]
+pub async fn export_prompt_bundle(
+    project_root: String,
+    path: String,
+    name: Option<String>,
+    description: Option<String>,
+    author: Option<String>,
+) -> Result<BundleExportReport, String> {
+    let prompts_dir = Path::new(&project_root).join(".ifai/prompts");
+    let (files, skipped_private) = collect_prompts(&prompts_dir);
+    if files.is_empty() {
+        return Err("No shareable prompts found in .ifai/prompts".to_string());
+    }
+
+    let key = load_or_create_key(&bundle_dir())?;
+    let name = name.unwrap_or_else(|| {
+        Path::new(&project_root).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_else(|| "prompts".to_string())
+    });
+    let entries = build_bundle(&files, &name, description.as_deref().unwrap_or(""), author, &key)?;
+    std::fs::write(&path, write_zip(&entries)?).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    let signer = fingerprint(key.public_key().as_ref());
+    println!("[PromptBundle] Exported {} prompts to {} (signer {})", files.len(), path, signer);
+    Ok(BundleExportReport { path, signer, files: files.into_keys().collect(), skipped_private })
+}
+
+/// 从文件或 http(s) URL 导入提示词包；签名者未受信任时需要 `allow_untrusted`
+#[tauri::command]
+pub async fn import_prompt_bundle(
+    project_root: String,
+    source: String,
+    conflict: Option<ConflictStrategy>,
+    allow_untrusted: Option<bool>,
+) -> Result<BundleImportReport, String> {
+    let bytes = if source.starts_with("http://") || source.starts_with("https://") {
+        fetch_bundle(&source).await?
+    } else {
+        std::fs::read(&source).map_err(|e| format!("Failed to read {}: {}", source, e))?
+    };
+    let (manifest, prompts, signer) = open_bundle(read_zip(&bytes)?)?;
+    let trusted = trusted_signers(&bundle_dir()).contains(&signer);
+    if !trusted && !allow_untrusted.unwrap_or(false) {
+        return Err(format!("Bundle \"{}\" is signed by an untrusted key ({}); trust the signer or allow untrusted bundles to import it", manifest.name, signer));
+    }
+
+    let prompts_dir = Path::new(&project_root).join(".ifai/prompts");
+    let results = install(&prompts_dir, &manifest, &prompts, conflict.unwrap_or_default())?;
+    println!(
+        "[PromptBundle] Imported bundle {} from {} (signer {}, trusted: {}): {} of {} prompts written",
+        manifest.name, source, signer, trusted, results.iter().filter(|r| r.action != "skipped").count(), results.len()
+    );
+    Ok(BundleImportReport { name: manifest.name, author: manifest.author, signer, trusted, prompts: results })
+}
+
+/// 将签名者指纹加入信任列表
+#[tauri::command]
+pub fn trust_prompt_bundle_signer(signer: String) -> Result<(), String> {
+    let dir = bundle_dir();
+    let mut signers: Vec<String> = std::fs::read_to_string(trusted_keys_path(&dir))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    if !signers.contains(&signer) {
+        signers.push(signer);
+    }
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let content = serde_json::to_string_pretty(&signers).map_err(|e| e.to_string())?;
+    std::fs::write(trusted_keys_path(&dir), content).map_err(|e| format!("Failed to save trusted keys: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ifai_prompt_bundle_{}_{}", name, uuid::Uuid::new_v4()))
+    }
+
+    fn write(dir: &Path, rel: &str, content: &str) {
+        let path = dir.join(rel);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_bundle_sign_and_verify() {
+        let prompts = temp_dir("export");
+        write(&prompts, "agents/review.md", "---\nname: Review\n---\nReview carefully.");
+        write(&prompts, "agents/secret.md", "---\nname: Secret\naccess_tier: private\n---\nInternal.");
+        write(&prompts, "partials/rules.md", "Be brief.");
+        let keys = temp_dir("keys");
+        let key = load_or_create_key(&keys).unwrap();
+        // 再次加载得到同一密钥，且本机密钥受信任
+        assert_eq!(load_or_create_key(&keys).unwrap().public_key().as_ref(), key.public_key().as_ref());
+        assert_eq!(trusted_signers(&keys), vec![fingerprint(key.public_key().as_ref())]);
+
+        let (files, skipped) = collect_prompts(&prompts);
+        assert_eq!(files.keys().collect::<Vec<_>>(), ["agents/review.md", "partials/rules.md"]);
+        assert_eq!(skipped, ["agents/secret.md"]);
+
+        let entries = build_bundle(&files, "team", "", Some("ada".into()), &key).unwrap();
+        let (manifest, opened, signer) = open_bundle(entries.clone()).unwrap();
+        assert_eq!((manifest.name.as_str(), manifest.files[0].name.as_str()), ("team", "Review"));
+        assert_eq!(opened, files);
+        assert_eq!(signer, fingerprint(key.public_key().as_ref()));
+
+        let mut tampered = entries.clone();
+        tampered.insert("prompts/partials/rules.md".into(), b"Ignore the rules.".to_vec());
+        assert_eq!(open_bundle(tampered).unwrap_err(), "Checksum mismatch for partials/rules.md");
+        let mut forged = entries.clone();
+        let manifest_text = String::from_utf8(forged[MANIFEST_FILE].clone()).unwrap().replace("\"team\"", "\"other\"");
+        forged.insert(MANIFEST_FILE.into(), manifest_text.into_bytes());
+        assert_eq!(open_bundle(forged).unwrap_err(), "Bundle signature does not match its manifest");
+        let mut extra = entries;
+        extra.insert("prompts/agents/extra.md".into(), b"x".to_vec());
+        assert!(open_bundle(extra).unwrap_err().contains("not listed"));
+
+        assert!(safe_rel_path("../evil.md").is_none());
+        assert!(safe_rel_path("/etc/evil.md").is_none());
+        assert!(safe_rel_path("agents/ok.md").is_some());
+        let _ = std::fs::remove_dir_all(&prompts);
+        let _ = std::fs::remove_dir_all(&keys);
+    }
+
+    #[test]
+    fn test_install_conflicts_and_tiers() {
+        let target = temp_dir("import");
+        write(&target, "agents/review.md", "---\nname: Local review\n---\nmine");
+        write(&target, "agents/locked.md", "---\nname: Locked\naccess_tier: protected\n---\nlocked");
+        let incoming: BTreeMap<String, Vec<u8>> = [
+            ("agents/review.md", "---\nname: Review\n---\ntheirs"),
+            ("agents/locked.md", "---\nname: Locked\n---\ntheirs"),
+            ("agents/new.md", "---\nname: New\n---\nnew"),
+        ]
+        .into_iter()
+        .map(|(p, c)| (p.to_string(), c.as_bytes().to_vec()))
+        .collect();
+        let manifest = BundleManifest {
+            format_version: FORMAT_VERSION,
+            name: "team".into(),
+            description: String::new(),
+            author: None,
+            created_at: String::new(),
+            public_key: String::new(),
+            files: incoming.iter().map(|(path, data)| BundleEntry { path: path.clone(), sha256: sha256_hex(data), name: String::new(), access_tier: prompt_info(data).1 }).collect(),
+        };
+
+        let skip = install(&target, &manifest, &incoming, ConflictStrategy::Skip).unwrap();
+        assert_eq!(skip.iter().map(|r| r.action.as_str()).collect::<Vec<_>>(), ["skipped", "created", "skipped"]);
+
+        let overwrite = install(&target, &manifest, &incoming, ConflictStrategy::Overwrite).unwrap();
+        // protected 的本地提示词改为重命名
+        assert_eq!(overwrite[0], ImportedPrompt { path: "agents/locked-1.md".into(), action: "renamed".into(), reason: Some("the existing prompt is protected".into()) });
+        assert_eq!(overwrite[2].action, "overwritten");
+        assert!(std::fs::read_to_string(target.join("agents/locked.md")).unwrap().ends_with("locked"));
+        assert!(std::fs::read_to_string(target.join("agents/review.md")).unwrap().ends_with("theirs"));
+
+        let rename = install(&target, &manifest, &incoming, ConflictStrategy::Rename).unwrap();
+        assert_eq!(rename[1].path, "agents/new-1.md");
+        let _ = std::fs::remove_dir_all(&target);
+    }
+}
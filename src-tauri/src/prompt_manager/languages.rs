@@ -0,0 +1,150 @@
+//! v0.3.4 按语言自动附加的提示词补充
+//!
+//! `.ifai/prompts/languages/{language}.md` 存放语言相关的规范（如 Rust edition/惯用法、
+//! TypeScript 严格模式规则）。对话或 Agent 任务聚焦某种语言的文件时，
+//! 自动将对应补充追加到系统提示词中，总长度受 Token 预算限制。
+
+use std::collections::HashMap;
+use std::path::Path;
+use regex::Regex;
+
+use super::{storage, BuiltinPrompts};
+use crate::token_counter::estimate_tokens;
+
+/// 默认 Token 预算
+pub const DEFAULT_ADDENDUM_BUDGET: usize = 600;
+/// 最多附加的语言数
+const MAX_LANGUAGES: usize = 2;
+
+fn language_for_extension(ext: &str) -> Option<&'static str> {
+    match ext.to_lowercase().as_str() {
+        "rs" => Some("rust"),
+        "ts" | "tsx" | "mts" | "cts" => Some("typescript"),
+        "js" | "jsx" | "mjs" | "cjs" => Some("javascript"),
+        "py" => Some("python"),
+        "go" => Some("go"),
+        "java" => Some("java"),
+        _ => None,
+    }
+}
+
+/// 从文本中的文件路径与代码块标记识别语言，按出现次数排序
+pub fn detect_languages(texts: &[&str]) -> Vec<String> {
+    let path_re = Regex::new(r"[\w./\\-]+\.([A-Za-z]{1,4})\b").unwrap();
+    let fence_re = Regex::new(r"```([A-Za-z]+)").unwrap();
+
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    for text in texts {
+        for caps in path_re.captures_iter(text) {
+            if let Some(lang) = language_for_extension(&caps[1]) {
+                *counts.entry(lang).or_default() += 1;
+            }
+        }
+        for caps in fence_re.captures_iter(text) {
+            let lang = match caps[1].to_lowercase().as_str() {
+                "rust" => Some("rust"),
+                "typescript" | "tsx" => Some("typescript"),
+                "javascript" | "jsx" => Some("javascript"),
+                "python" => Some("python"),
+                "go" | "golang" => Some("go"),
+                "java" => Some("java"),
+                other => language_for_extension(other),
+            };
+            if let Some(lang) = lang {
+                *counts.entry(lang).or_default() += 1;
+            }
+        }
+    }
+
+    let mut ranked: Vec<(&str, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    ranked.into_iter().take(MAX_LANGUAGES).map(|(lang, _)| lang.to_string()).collect()
+}
+
+/// 加载语言补充：项目本地优先，其次内置
+fn load_addendum(project_root: &str, language: &str) -> Option<String> {
+    let name = format!("languages/{}.md", language);
+    let local_path = Path::new(project_root).join(".ifai/prompts").join(&name);
+
+    let raw = if local_path.exists() {
+        std::fs::read_to_string(&local_path).ok()?
+    } else {
+        let file = BuiltinPrompts::get(&name)?;
+        String::from_utf8_lossy(file.data.as_ref()).to_string()
+    };
+
+    // 允许不带元数据头的纯 Markdown
+    let content = storage::load_prompt_from_str(&raw, None)
+        .map(|t| t.content)
+        .unwrap_or(raw);
+    let content = content.trim().to_string();
+    (!content.is_empty()).then_some(content)
+}
+
+/// 在预算内截断
+fn fit_to_budget(content: &str, budget: usize) -> Option<String> {
+    if estimate_tokens(content) <= budget {
+        return Some(content.to_string());
+    }
+    let mut out = String::new();
+    for line in content.lines() {
+        if estimate_tokens(&out) + estimate_tokens(line) + 1 > budget {
+            break;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    (!out.trim().is_empty()).then_some(out)
+}
+
+/// 根据对话 / 任务文本选择语言补充，返回可直接追加到系统提示词的段落
+pub fn language_addendum(project_root: &str, texts: &[&str], budget: usize) -> Option<String> {
+    let mut remaining = budget;
+    let mut sections = Vec::new();
+
+    for language in detect_languages(texts) {
+        let Some(content) = load_addendum(project_root, &language) else { continue };
+        let Some(section) = fit_to_budget(&content, remaining) else { break };
+        remaining = remaining.saturating_sub(estimate_tokens(&section));
+        println!("[PromptManager] Adding {} language addendum", language);
+        sections.push(section);
+    }
+
+    if sections.is_empty() {
+        None
+    } else {
+        Some(format!("\n\n# Language-Specific Guidelines\n\n{}", sections.join("\n\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_languages() {
+        let texts = ["Fix the bug in src/lib.rs and src/main.rs", "also check ui/App.tsx\n```rust\nfn a() {}\n```"];
+        assert_eq!(detect_languages(&texts), vec!["rust", "typescript"]);
+        assert!(detect_languages(&["what is a closure?"]).is_empty());
+    }
+
+    #[test]
+    fn test_local_addendum_overrides_builtin() {
+        let root = std::env::temp_dir().join(format!("ifai_lang_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join(".ifai/prompts/languages")).unwrap();
+        std::fs::write(root.join(".ifai/prompts/languages/go.md"), "Always run gofmt.").unwrap();
+
+        let addendum = language_addendum(&root.to_string_lossy(), &["edit cmd/main.go"], DEFAULT_ADDENDUM_BUDGET).unwrap();
+        assert!(addendum.contains("Always run gofmt."));
+        assert!(language_addendum(&root.to_string_lossy(), &["edit cmd/main.go"], 0).is_none());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_fit_to_budget() {
+        let content = "line one\n".repeat(200);
+        let fitted = fit_to_budget(&content, 50).unwrap();
+        assert!(estimate_tokens(&fitted) <= 50);
+    }
+}
@@ -1,10 +1,15 @@
 use serde::{Deserialize, Serialize};
 use rust_embed::RustEmbed;
 use crate::project_config;
+use crate::workspace_profiles::{self, PackageProfile};
 
 pub mod storage;
 pub mod template;
 pub mod variables;
+// v0.3.4 新增：按语言自动附加的提示词补充
+pub mod languages;
+// v0.3.4 新增：签名的提示词包导入/导出
+pub mod bundle;
 
 #[derive(RustEmbed)]
 #[folder = "../.ifai/prompts/"]
@@ -21,11 +26,21 @@ pub enum AccessTier {
 }
 
 pub fn get_main_system_prompt(project_root: &str) -> String {
+    build_main_system_prompt(project_root, None)
+}
+
+/// v0.3.4: 作用域为 monorepo 子包时，使用该包的配置代替仓库级默认值
+pub fn get_main_system_prompt_for_scope(project_root: &str, scope_path: Option<&str>) -> String {
+    let profile = scope_path.and_then(|scope| workspace_profiles::profile_for_scope(project_root, scope));
+    build_main_system_prompt(project_root, profile.as_ref())
+}
+
+fn build_main_system_prompt(project_root: &str, profile: Option<&PackageProfile>) -> String {
     let variables = variables::collect_system_variables(project_root);
 
+    let local_root = std::path::Path::new(project_root).join(".ifai/prompts/system");
+    let override_path = local_root.join("main.override.md");
     let template = {
-        let local_root = std::path::Path::new(project_root).join(".ifai/prompts/system");
-        let override_path = local_root.join("main.override.md");
         let local_path = local_root.join("main.md");
 
         if override_path.exists() {
@@ -40,29 +55,38 @@ pub fn get_main_system_prompt(project_root: &str) -> String {
         }
     };
 
+    // v0.3.4: 展开片段引用与 `extends` 继承
+    let origin = if override_path.exists() { "system/main.override.md" } else { "system/main.md" };
     let mut prompt = match template {
-        Some(t) => template::render_template(&t.content, &variables).unwrap_or_else(|_| t.content),
+        Some(t) => template::render_prompt(Some(project_root), &t.raw_text, origin, &variables).unwrap_or_else(|e| {
+            eprintln!("[PromptManager] Failed to render {}: {:#}", origin, e);
+            t.content
+        }),
         None => "You are a helpful AI programming assistant.".to_string(),
     };
 
-    // 追加 IFAI.md 中的 custom_instructions
-    if let Some(ifai_config) = project_config::load_project_config_sync(project_root) {
-        println!("[PromptManager] Loaded IFAI.md config: {:?}", ifai_config.default_language);
-        if let Some(instructions) = ifai_config.custom_instructions {
-            if !instructions.trim().is_empty() {
-                println!("[PromptManager] Adding custom_instructions: {} chars", instructions.len());
-                prompt.push_str("\n\n# Project-Specific Instructions\n");
-                prompt.push_str(&instructions);
-            }
-        }
-    } else {
-        println!("[PromptManager] No IFAI.md config found or failed to parse");
+    // 追加 IFAI.md 中的 custom_instructions 与排除路径
+    let ifai_config = project_config::load_project_config_sync(project_root);
+    match &ifai_config {
+        Some(config) => println!("[PromptManager] Loaded IFAI.md config: {:?}", config.default_language),
+        None => println!("[PromptManager] No IFAI.md config found or failed to parse"),
     }
+    prompt.push_str(&project_sections(ifai_config.as_ref(), profile, "main"));
 
     prompt
 }
 
 pub fn get_agent_prompt(agent_type: &str, project_root: &str, task_description: &str) -> String {
+    build_agent_prompt(agent_type, project_root, task_description, None)
+}
+
+/// v0.3.4: 作用域为 monorepo 子包时，使用该包的配置代替仓库级默认值
+pub fn get_agent_prompt_for_scope(agent_type: &str, project_root: &str, task_description: &str, scope_path: Option<&str>) -> String {
+    let profile = scope_path.and_then(|scope| workspace_profiles::profile_for_scope(project_root, scope));
+    build_agent_prompt(agent_type, project_root, task_description, profile.as_ref())
+}
+
+fn build_agent_prompt(agent_type: &str, project_root: &str, task_description: &str, profile: Option<&PackageProfile>) -> String {
     let mut variables = variables::collect_system_variables(project_root);
 
     // v0.2.6: 检测提案上下文 [PROPOSAL:proposal_id]
@@ -102,24 +126,46 @@ pub fn get_agent_prompt(agent_type: &str, project_root: &str, task_description:
     };
 
     let mut prompt = match template {
-        Some(t) => template::render_template(&t.content, &variables).unwrap_or_else(|_| t.content),
+        Some(t) => template::render_prompt(Some(project_root), &t.raw_text, &template_name, &variables).unwrap_or_else(|e| {
+            eprintln!("[PromptManager] Failed to render {}: {:#}", template_name, e);
+            t.content
+        }),
         None => format!("You are a specialized {} agent. Task: {}", agent_type, clean_task),
     };
 
-    // 追加 IFAI.md 中的 custom_instructions (与 main prompt 相同的逻辑)
-    if let Some(ifai_config) = project_config::load_project_config_sync(project_root) {
-        if let Some(instructions) = ifai_config.custom_instructions {
-            if !instructions.trim().is_empty() {
-                println!("[PromptManager] Adding custom_instructions to agent {}: {} chars", agent_type, instructions.len());
-                prompt.push_str("\n\n# Project-Specific Instructions\n");
-                prompt.push_str(&instructions);
-            }
-        }
-    }
+    // 追加 IFAI.md 中的 custom_instructions 与排除路径 (与 main prompt 相同的逻辑)
+    let ifai_config = project_config::load_project_config_sync(project_root);
+    prompt.push_str(&project_sections(ifai_config.as_ref(), profile, agent_type));
 
     prompt
 }
 
+/// IFAI.md 中需要写入提示词的部分：custom_instructions 与 `ignore` 排除的路径
+///
+/// 有子包配置时，其 `prompt_addendum` 代替仓库级 custom_instructions，并附加该包的构建 / 测试命令与关键文件
+fn project_sections(config: Option<&project_config::ProjectConfig>, profile: Option<&PackageProfile>, target: &str) -> String {
+    let mut sections = String::new();
+    let instructions = profile
+        .and_then(|p| p.prompt_addendum.as_ref())
+        .or_else(|| config.and_then(|c| c.custom_instructions.as_ref()))
+        .filter(|i| !i.trim().is_empty());
+    if let Some(instructions) = instructions {
+        println!("[PromptManager] Adding custom_instructions to {}: {} chars", target, instructions.len());
+        sections.push_str("\n\n# Project-Specific Instructions\n");
+        sections.push_str(instructions);
+    }
+    if let Some(patterns) = config.and_then(|c| c.ignore.as_ref()).filter(|p| !p.is_empty()) {
+        sections.push_str("\n\n# Excluded Paths\nDo not read, search or modify paths matching these patterns unless the user asks explicitly:\n");
+        for pattern in patterns {
+            sections.push_str(&format!("- `{}`\n", pattern));
+        }
+    }
+    if let Some(profile) = profile {
+        sections.push_str(&workspace_profiles::profile_prompt_section(profile));
+    }
+    sections
+}
+
 /// v0.2.6: 提取提案上下文
 /// 检测并移除 [PROPOSAL:xxx] 格式的标记
 /// 返回：(清理后的任务描述, 提案ID)
@@ -155,6 +201,9 @@ pub struct PromptMetadata {
     pub variables: Vec<String>,
     #[serde(default)]
     pub tools: Vec<String>,
+    /// v0.3.4: 继承的父模板（相对 `.ifai/prompts/`）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
 }
 
 fn default_version() -> String {
@@ -1,18 +1,24 @@
 use handlebars::{Handlebars, handlebars_helper};
+use regex::Regex;
+use serde::Deserialize;
 use serde_json::json;
 use std::collections::HashMap;
-use anyhow::{Result, Context};
+use std::sync::OnceLock;
+use anyhow::{Result, Context, bail};
 
 // Define helpers using the macro
 handlebars_helper!(eq: |x: str, y: str| x == y);
 handlebars_helper!(ne: |x: str, y: str| x != y);
 
+/// 片段引用与继承的最大嵌套深度
+const MAX_INCLUDE_DEPTH: usize = 16;
+
 pub fn render_template(template_content: &str, variables: &HashMap<String, String>) -> Result<String> {
     let mut reg = Handlebars::new();
-    
+
     // Configure handlebars
     reg.set_strict_mode(false);
-    
+
     // Register helpers
     reg.register_helper("eq", Box::new(eq));
     reg.register_helper("ne", Box::new(ne));
@@ -22,4 +28,201 @@ pub fn render_template(template_content: &str, variables: &HashMap<String, Strin
 
     reg.render_template(template_content, &data)
         .context("Failed to render prompt template")
-}
\ No newline at end of file
+}
+
+// ============================================================================
+// v0.3.4: 片段引用（`{{> partials/xyz}}`）与继承（front matter `extends:`）
+//
+// - 名称相对 `.ifai/prompts/`，可省略 `.md`；项目文件（含 `.override.md`）优先于内置提示词
+// - 父模板用 `{{#> name}}默认内容{{/name}}` 标记可覆盖的区块，
+//   子模板用 `{{#*inline "name"}}...{{/inline}}` 覆盖；子模板的其余内容追加在父模板之后
+// - 引用链出现循环或超过深度时报错，错误信息包含完整的引用链
+// ============================================================================
+
+#[derive(Debug, Default, Deserialize)]
+struct Inheritance {
+    #[serde(default)]
+    extends: Option<String>,
+}
+
+fn partial_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\{\{>\s*([\w./-]+)\s*\}\}").unwrap())
+}
+
+fn inline_pattern() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"(?s)\{\{#\*inline\s+"([^"]+)"\s*\}\}.*?\{\{/inline\}\}"#).unwrap())
+}
+
+/// 统一名称：去掉前导 `./` 与 `.md` 后缀
+fn normalize_name(name: &str) -> String {
+    let name = name.trim().trim_start_matches("./");
+    name.strip_suffix(".md").unwrap_or(name).to_string()
+}
+
+/// 拆分 front matter；没有或无法识别时整段作为正文
+fn split_front_matter(text: &str) -> (Inheritance, &str) {
+    let trimmed = text.trim_start();
+    let Some(after_first) = trimmed.strip_prefix("---") else { return (Inheritance::default(), text) };
+    let Some(end) = after_first.find("\n---") else { return (Inheritance::default(), text) };
+    let meta = serde_yaml::from_str(&after_first[..end]).unwrap_or_default();
+    let body = &after_first[end + 4..];
+    (meta, body.strip_prefix('\n').unwrap_or(body))
+}
+
+/// 按名称加载模板原文；`skip_override` 时忽略 `.override.md`（覆盖文件继承自身的原版）
+fn load_source(project_root: Option<&str>, name: &str, skip_override: bool) -> Option<String> {
+    if let Some(root) = project_root {
+        let dir = std::path::Path::new(root).join(".ifai/prompts");
+        let candidates = [format!("{}.override.md", name), format!("{}.md", name)];
+        for candidate in &candidates[usize::from(skip_override)..] {
+            if let Ok(content) = std::fs::read_to_string(dir.join(candidate)) {
+                return Some(content);
+            }
+        }
+    }
+    let file = super::BuiltinPrompts::get(&format!("{}.md", name))?;
+    Some(String::from_utf8_lossy(file.data.as_ref()).into_owned())
+}
+
+fn chain(stack: &[String], next: &str) -> String {
+    stack.iter().map(String::as_str).chain([next]).collect::<Vec<_>>().join(" -> ")
+}
+
+/// 展开片段引用与继承，返回可直接交给 handlebars 的模板
+pub fn compose(project_root: Option<&str>, text: &str, origin: &str) -> Result<String> {
+    compose_inner(project_root, text, &mut vec![normalize_name(origin)])
+}
+
+fn compose_inner(project_root: Option<&str>, text: &str, stack: &mut Vec<String>) -> Result<String> {
+    let (meta, body) = split_front_matter(text);
+    let mut composed = expand_partials(project_root, body, stack)?;
+
+    if let Some(parent) = meta.extends.as_deref().map(normalize_name).filter(|p| !p.is_empty()) {
+        let parent_body = with_template(project_root, &parent, "extends", stack)?;
+        let names: Vec<String> = inline_pattern().captures_iter(&composed).map(|c| c[1].to_string()).collect();
+        let overrides: String = inline_pattern().find_iter(&composed).map(|m| m.as_str()).collect();
+        let rest = inline_pattern().replace_all(&composed, "").into_owned();
+        // 多级继承时子模板的覆盖优先：去掉父模板中同名的内联片段
+        let parent_body = inline_pattern().replace_all(&parent_body, |c: &regex::Captures| {
+            if names.iter().any(|n| n == &c[1]) { String::new() } else { c[0].to_string() }
+        });
+        composed = overrides + &parent_body;
+        if !rest.trim().is_empty() {
+            composed.push_str("\n\n");
+            composed.push_str(rest.trim());
+        }
+    }
+    Ok(composed)
+}
+
+fn expand_partials(project_root: Option<&str>, body: &str, stack: &mut Vec<String>) -> Result<String> {
+    let inline_names: Vec<String> = inline_pattern().captures_iter(body).map(|c| c[1].to_string()).collect();
+    let mut out = String::with_capacity(body.len());
+    let mut last = 0;
+    for caps in partial_pattern().captures_iter(body) {
+        // 同一模板中定义的内联片段交给 handlebars 处理
+        if inline_names.iter().any(|n| n == &caps[1]) {
+            continue;
+        }
+        let whole = caps.get(0).unwrap();
+        out.push_str(&body[last..whole.start()]);
+        let expanded = with_template(project_root, &normalize_name(&caps[1]), "partial", stack)?;
+        out.push_str(expanded.trim_end_matches('\n'));
+        last = whole.end();
+    }
+    out.push_str(&body[last..]);
+    Ok(out)
+}
+
+/// 在引用栈中加载并展开另一个模板
+fn with_template(project_root: Option<&str>, name: &str, kind: &str, stack: &mut Vec<String>) -> Result<String> {
+    if stack.iter().any(|s| s == name) {
+        bail!("Prompt template cycle: {}", chain(stack, name));
+    }
+    if stack.len() >= MAX_INCLUDE_DEPTH {
+        bail!("Prompt templates nested deeper than {} levels: {}", MAX_INCLUDE_DEPTH, chain(stack, name));
+    }
+    let skip_override = stack.iter().any(|s| s.strip_suffix(".override") == Some(name));
+    let Some(source) = load_source(project_root, name, skip_override) else {
+        bail!("Prompt {} `{}` not found in .ifai/prompts or builtin prompts (from {})", kind, name, chain(stack, name));
+    };
+    stack.push(name.to_string());
+    let result = compose_inner(project_root, &source, stack);
+    stack.pop();
+    result
+}
+
+/// 展开片段与继承后渲染；`text` 可以包含 front matter，`origin` 为模板自身的名称（用于循环检测与报错）
+pub fn render_prompt(project_root: Option<&str>, text: &str, origin: &str, variables: &HashMap<String, String>) -> Result<String> {
+    let composed = compose(project_root, text, origin)?;
+    render_template(&composed, variables).with_context(|| format!("Failed to render prompt `{}`", normalize_name(origin)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project(files: &[(&str, &str)]) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("ifai_prompt_template_{}", uuid::Uuid::new_v4()));
+        for (name, content) in files {
+            let path = dir.join(".ifai/prompts").join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_partials_and_extends() {
+        let dir = project(&[
+            ("partials/rules.md", "---\nname: Rules\n---\nBe brief, {{USER_NAME}}.\n"),
+            ("base.md", "---\nname: Base\n---\n# Intro\n{{#> intro}}Default intro{{/intro}}\n{{> partials/rules}}\n{{#> tone}}Neutral{{/tone}}\n"),
+            ("mid.md", "---\nname: Mid\nextends: base\n---\n{{#*inline \"intro\"}}Mid intro{{/inline}}{{#*inline \"tone\"}}Warm{{/inline}}"),
+        ]);
+        let root = dir.to_string_lossy().to_string();
+        let vars = HashMap::from([("USER_NAME".to_string(), "Ada".to_string())]);
+
+        let child = "---\nname: Review\nextends: base\n---\n{{#*inline \"intro\"}}You review code.{{/inline}}\nAlways cite files.\n";
+        let rendered = render_prompt(Some(&root), child, "agents/review.md", &vars).unwrap();
+        assert_eq!(rendered, "# Intro\nYou review code.\nBe brief, Ada.\nNeutral\n\n\nAlways cite files.");
+
+        // 多级继承：子模板覆盖中间模板的同名区块，其余区块沿用中间模板
+        let grandchild = child.replace("extends: base", "extends: mid.md");
+        let rendered = render_prompt(Some(&root), &grandchild, "agents/review.md", &vars).unwrap();
+        assert!(rendered.starts_with("# Intro\nYou review code.\nBe brief, Ada.\nWarm\n"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cycles_and_missing_partials() {
+        let dir = project(&[
+            ("partials/a.md", "A {{> partials/b}}"),
+            ("partials/b.md", "B {{> partials/a}}"),
+            ("system/main.override.md", "---\nname: Main\nextends: system/main\n---\nExtra rules."),
+            ("agents/loop.md", "---\nname: Loop\nextends: agents/loop\n---\nbody"),
+        ]);
+        let root = dir.to_string_lossy().to_string();
+
+        let err = compose(Some(&root), "{{> partials/a}}", "agents/x.md").unwrap_err().to_string();
+        assert_eq!(err, "Prompt template cycle: agents/x -> partials/a -> partials/b -> partials/a");
+        let err = compose(Some(&root), &std::fs::read_to_string(dir.join(".ifai/prompts/agents/loop.md")).unwrap(), "agents/loop").unwrap_err();
+        assert!(err.to_string().contains("agents/loop -> agents/loop"));
+        let err = compose(Some(&root), "{{> partials/nope}}", "main").unwrap_err();
+        assert!(err.to_string().contains("partial `partials/nope` not found"));
+
+        // 覆盖文件可以继承自身的原版
+        let source = std::fs::read_to_string(dir.join(".ifai/prompts/system/main.override.md")).unwrap();
+        let err = compose(Some(&root), &source, "system/main.override.md").unwrap_err();
+        assert!(err.to_string().contains("extends `system/main` not found"));
+        std::fs::write(dir.join(".ifai/prompts/system/main.md"), "Base rules.").unwrap();
+        assert_eq!(compose(Some(&root), &source, "system/main.override.md").unwrap(), "Base rules.\n\nExtra rules.");
+
+        // 内联片段不当作文件引用
+        let inline = "{{#*inline \"x\"}}hi{{/inline}}{{> x}}";
+        assert_eq!(render_prompt(None, inline, "t", &HashMap::new()).unwrap(), "hi");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,183 @@
+/*!
+Prompt Watch - 提示词与 IFAI.md 热重载
+======================================
+
+监听项目的 `.ifai/prompts/` 与 `.ifai/IFAI.md`：
+
+- 变化合并（300ms 防抖）后递增项目的修订号，并发送 `prompts-updated` 事件
+- 修订号用作缓存键：监听中的项目按修订号缓存 IFAI.md 的解析结果，变化后自动失效
+- 运行中的 Agent 在每轮循环开始时比较修订号，变化时重建 system 提示词并重新读取上下文上限
+- 未监听的项目没有修订号，所有读取照旧每次访问磁盘
+*/
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// 合并连续文件事件的等待时间
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// `prompts-updated` 事件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptsUpdatedEvent {
+    pub project_root: String,
+    /// 相对 `.ifai/` 的变化路径
+    pub paths: Vec<String>,
+    pub revision: u64,
+}
+
+struct ProjectWatch {
+    watcher: RecommendedWatcher,
+    revision: Arc<AtomicU64>,
+    /// `.ifai/prompts/` 是否已加入监听（目录可能在监听开始后才创建）
+    prompts_watched: bool,
+}
+
+static WATCHES: OnceLock<Mutex<HashMap<String, ProjectWatch>>> = OnceLock::new();
+
+fn watches() -> &'static Mutex<HashMap<String, ProjectWatch>> {
+    WATCHES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 项目的当前修订号；未监听时返回 None
+pub fn revision(project_root: &str) -> Option<u64> {
+    let map = watches().lock().ok()?;
+    map.get(project_root).map(|w| w.revision.load(Ordering::SeqCst))
+}
+
+/// 提示词或 IFAI.md 的变化，返回相对 `.ifai/` 的路径
+fn relevant_path(ifai_dir: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(ifai_dir).ok()?;
+    let is_prompt = rel.starts_with("prompts") && (rel == Path::new("prompts") || rel.extension().is_some_and(|ext| ext == "md") || rel.extension().is_none());
+    (is_prompt || rel == Path::new("IFAI.md")).then(|| rel.to_string_lossy().replace('\\', "/"))
+}
+
+/// 监听 `.ifai/prompts/`（存在时）；返回是否已在监听
+fn watch_prompts_dir(watch: &mut ProjectWatch, ifai_dir: &Path) -> bool {
+    if !ifai_dir.join("prompts").is_dir() {
+        // 目录被删除时监听随之失效，重新创建后需要再次加入
+        watch.prompts_watched = false;
+    } else if !watch.prompts_watched {
+        match watch.watcher.watch(&ifai_dir.join("prompts"), RecursiveMode::Recursive) {
+            Ok(()) => watch.prompts_watched = true,
+            Err(e) => eprintln!("[PromptWatch] Failed to watch {}: {}", ifai_dir.join("prompts").display(), e),
+        }
+    }
+    watch.prompts_watched
+}
+
+/// 开始监听项目；已在监听时返回 false
+pub fn watch(app: &AppHandle, project_root: &str) -> Result<bool, String> {
+    let mut map = watches().lock().map_err(|e| e.to_string())?;
+    if map.contains_key(project_root) {
+        return Ok(false);
+    }
+
+    let ifai_dir = Path::new(project_root).join(".ifai");
+    std::fs::create_dir_all(&ifai_dir).map_err(|e| format!("Failed to create {}: {}", ifai_dir.display(), e))?;
+
+    let (tx, rx) = mpsc::channel::<PathBuf>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+        Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+            for path in event.paths {
+                let _ = tx.send(path);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("[PromptWatch] Watch error: {}", e),
+    })
+    .map_err(|e| format!("Failed to create watcher: {}", e))?;
+    // `.ifai/` 本身只监听一层：IFAI.md 与 prompts 目录的创建
+    watcher
+        .watch(&ifai_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch {}: {}", ifai_dir.display(), e))?;
+
+    let revision = Arc::new(AtomicU64::new(0));
+    let mut watch = ProjectWatch { watcher, revision: revision.clone(), prompts_watched: false };
+    watch_prompts_dir(&mut watch, &ifai_dir);
+    map.insert(project_root.to_string(), watch);
+
+    let app = app.clone();
+    let root = project_root.to_string();
+    std::thread::spawn(move || {
+        // 监听取消后 watcher 被释放，发送端随之关闭，线程退出
+        while let Ok(first) = rx.recv() {
+            let mut changed = Vec::new();
+            let mut next = Some(first);
+            while let Some(path) = next {
+                if let Some(rel) = relevant_path(&ifai_dir, &path) {
+                    if !changed.contains(&rel) {
+                        changed.push(rel);
+                    }
+                }
+                next = rx.recv_timeout(DEBOUNCE).ok();
+            }
+            if changed.is_empty() {
+                continue;
+            }
+            if changed.iter().any(|p| p == "prompts") {
+                if let Ok(mut map) = watches().lock() {
+                    if let Some(watch) = map.get_mut(&root) {
+                        watch_prompts_dir(watch, &ifai_dir);
+                    }
+                }
+            }
+
+            let revision = revision.fetch_add(1, Ordering::SeqCst) + 1;
+            println!("[PromptWatch] {} changed (revision {}): {}", root, revision, changed.join(", "));
+            let _ = app.emit("prompts-updated", PromptsUpdatedEvent { project_root: root.clone(), paths: changed, revision });
+        }
+    });
+
+    println!("[PromptWatch] Watching prompts and IFAI.md in {}", project_root);
+    Ok(true)
+}
+
+/// 停止监听项目；未在监听时返回 false
+pub fn unwatch(project_root: &str) -> bool {
+    let removed = watches().lock().ok().and_then(|mut map| map.remove(project_root)).is_some();
+    if removed {
+        println!("[PromptWatch] Stopped watching {}", project_root);
+    }
+    removed
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 开始监听项目的提示词与 IFAI.md，返回当前修订号
+#[tauri::command]
+pub fn watch_project_prompts(app: AppHandle, project_root: String) -> Result<u64, String> {
+    watch(&app, &project_root)?;
+    Ok(revision(&project_root).unwrap_or_default())
+}
+
+#[tauri::command]
+pub fn unwatch_project_prompts(project_root: String) -> bool {
+    unwatch(&project_root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relevant_path() {
+        let ifai = Path::new("/repo/.ifai");
+        assert_eq!(relevant_path(ifai, Path::new("/repo/.ifai/IFAI.md")).as_deref(), Some("IFAI.md"));
+        assert_eq!(relevant_path(ifai, Path::new("/repo/.ifai/prompts/agents/review.md")).as_deref(), Some("prompts/agents/review.md"));
+        assert_eq!(relevant_path(ifai, Path::new("/repo/.ifai/prompts")).as_deref(), Some("prompts"));
+        // 目录的创建与删除同样视为变化
+        assert_eq!(relevant_path(ifai, Path::new("/repo/.ifai/prompts/partials")).as_deref(), Some("prompts/partials"));
+        assert_eq!(relevant_path(ifai, Path::new("/repo/.ifai/prompts/review.md.swp")), None);
+        assert_eq!(relevant_path(ifai, Path::new("/repo/.ifai/sessions/a.json")), None);
+        assert_eq!(relevant_path(ifai, Path::new("/repo/IFAI.md")), None);
+    }
+}
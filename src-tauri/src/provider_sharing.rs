@@ -0,0 +1,219 @@
+/*!
+Provider Sharing - Provider 配置导入/导出
+=========================================
+
+团队共享 Provider 配置（不含密钥）：
+
+- 导出：API Key 替换为 `${IFAI_<ID>_API_KEY}` 形式的占位符
+- 导入：逐条校验（id、协议、base_url、模型列表），无效条目跳过并报告；
+  与现有配置按 id 合并，已有的 API Key 不会被覆盖，
+  占位符会尝试从同名环境变量解析
+
+Provider 列表保存在前端，命令接收现有列表并返回合并后的列表。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::core_traits::ai::{AIProtocol, AIProviderConfig};
+
+const EXPORT_VERSION: u32 = 1;
+
+/// 导出文件格式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderBundle {
+    pub version: u32,
+    #[serde(default)]
+    pub exported_at: String,
+    pub providers: Vec<AIProviderConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProviderImportResult {
+    /// 合并后的完整列表
+    pub providers: Vec<AIProviderConfig>,
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    /// 校验失败而跳过的条目
+    pub errors: Vec<String>,
+}
+
+/// API Key 占位符，如 `${IFAI_DEEPSEEK_API_KEY}`
+pub fn key_placeholder(provider_id: &str) -> String {
+    let id: String = provider_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!("${{IFAI_{}_API_KEY}}", id)
+}
+
+/// 占位符对应的环境变量名；不是占位符时返回 `None`
+fn placeholder_var(value: &str) -> Option<&str> {
+    value.trim().strip_prefix("${")?.strip_suffix('}').filter(|v| !v.is_empty())
+}
+
+pub fn sanitize(providers: &[AIProviderConfig]) -> ProviderBundle {
+    ProviderBundle {
+        version: EXPORT_VERSION,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        providers: providers
+            .iter()
+            .map(|p| AIProviderConfig { api_key: key_placeholder(&p.id), ..p.clone() })
+            .collect(),
+    }
+}
+
+/// 按协议注册表（`AIProtocol`）和基本规则校验单个配置
+fn validate(provider: &serde_json::Value) -> Result<AIProviderConfig, String> {
+    let id = provider["id"].as_str().unwrap_or("").trim();
+    if id.is_empty() {
+        return Err("missing provider id".to_string());
+    }
+    if let Some(protocol) = provider.get("protocol").filter(|p| !p.is_null()) {
+        serde_json::from_value::<AIProtocol>(protocol.clone())
+            .map_err(|_| format!("{}: unsupported protocol {}", id, protocol))?;
+    }
+    let config: AIProviderConfig = serde_json::from_value(provider.clone())
+        .map_err(|e| format!("{}: invalid config: {}", id, e))?;
+
+    let url = reqwest::Url::parse(config.base_url.trim())
+        .map_err(|e| format!("{}: invalid base_url '{}': {}", id, config.base_url, e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("{}: base_url must use http or https", id));
+    }
+    if config.models.iter().all(|m| m.trim().is_empty()) {
+        return Err(format!("{}: no models configured", id));
+    }
+    Ok(config)
+}
+
+/// 合并导入的配置；已有 Provider 保留其 API Key
+pub fn merge(existing: Vec<AIProviderConfig>, imported: &[serde_json::Value]) -> ProviderImportResult {
+    let mut result = ProviderImportResult { providers: existing, ..Default::default() };
+    let mut seen = HashSet::new();
+
+    for entry in imported {
+        let mut config = match validate(entry) {
+            Ok(config) => config,
+            Err(e) => {
+                result.errors.push(e);
+                continue;
+            }
+        };
+        if !seen.insert(config.id.clone()) {
+            result.errors.push(format!("{}: duplicate provider id", config.id));
+            continue;
+        }
+
+        if let Some(var) = placeholder_var(&config.api_key) {
+            config.api_key = std::env::var(var).unwrap_or_default();
+        }
+        match result.providers.iter_mut().find(|p| p.id == config.id) {
+            Some(current) => {
+                if !current.api_key.trim().is_empty() {
+                    config.api_key = current.api_key.clone();
+                }
+                *current = config;
+                result.updated.push(current.id.clone());
+            }
+            None => {
+                result.added.push(config.id.clone());
+                result.providers.push(config);
+            }
+        }
+    }
+
+    result
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 导出 Provider 配置（API Key 替换为占位符）
+#[tauri::command]
+pub fn export_provider_configs(path: String, providers: Vec<AIProviderConfig>) -> Result<usize, String> {
+    let bundle = sanitize(&providers);
+    let content = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    println!("[ProviderSharing] Exported {} providers to {}", providers.len(), path);
+    Ok(providers.len())
+}
+
+/// 导入 Provider 配置并与现有列表合并
+#[tauri::command]
+pub fn import_provider_configs(path: String, existing: Vec<AIProviderConfig>) -> Result<ProviderImportResult, String> {
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let raw: serde_json::Value = serde_json::from_str(&content).map_err(|e| format!("Invalid JSON: {}", e))?;
+
+    // 兼容直接导出的数组
+    let entries = match &raw {
+        serde_json::Value::Array(items) => items.clone(),
+        _ => {
+            let version = raw["version"].as_u64().unwrap_or(0);
+            if version == 0 || version > EXPORT_VERSION as u64 {
+                return Err(format!("Unsupported provider bundle version: {}", raw["version"]));
+            }
+            raw["providers"].as_array().cloned().ok_or("Missing 'providers' array")?
+        }
+    };
+
+    let result = merge(existing, &entries);
+    println!(
+        "[ProviderSharing] Imported from {}: {} added, {} updated, {} rejected",
+        path,
+        result.added.len(),
+        result.updated.len(),
+        result.errors.len()
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn provider(id: &str, key: &str) -> AIProviderConfig {
+        AIProviderConfig {
+            id: id.to_string(),
+            name: id.to_string(),
+            api_key: key.to_string(),
+            base_url: "https://api.example.com/v1".to_string(),
+            models: vec!["model-a".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_strips_keys() {
+        let bundle = sanitize(&[provider("deep-seek", "sk-secret")]);
+        assert_eq!(bundle.providers[0].api_key, "${IFAI_DEEP_SEEK_API_KEY}");
+        assert!(!serde_json::to_string(&bundle).unwrap().contains("sk-secret"));
+    }
+
+    #[test]
+    fn test_import_keeps_existing_secrets_and_validates() {
+        let existing = vec![provider("openai", "sk-mine")];
+        let imported = vec![
+            json!({ "id": "openai", "name": "OpenAI Team", "api_key": "sk-other", "base_url": "https://proxy.example.com/v1", "models": ["gpt-4o"], "protocol": "openai" }),
+            json!({ "id": "claude", "name": "Claude", "api_key": "${IFAI_TEST_UNSET_API_KEY}", "base_url": "https://api.anthropic.com", "models": ["claude-sonnet"], "protocol": "anthropic" }),
+            json!({ "id": "bad-protocol", "base_url": "https://x.example.com", "models": ["m"], "protocol": "grpc" }),
+            json!({ "id": "bad-url", "base_url": "ftp://x.example.com", "models": ["m"] }),
+            json!({ "id": "no-models", "base_url": "https://x.example.com", "models": [] }),
+            json!({ "name": "no id" }),
+        ];
+
+        let result = merge(existing, &imported);
+        assert_eq!(result.updated, vec!["openai"]);
+        assert_eq!(result.added, vec!["claude"]);
+        assert_eq!(result.errors.len(), 4);
+
+        let openai = result.providers.iter().find(|p| p.id == "openai").unwrap();
+        assert_eq!(openai.api_key, "sk-mine");
+        assert_eq!(openai.base_url, "https://proxy.example.com/v1");
+        let claude = result.providers.iter().find(|p| p.id == "claude").unwrap();
+        assert_eq!(claude.api_key, "");
+        assert!(matches!(claude.protocol, AIProtocol::Anthropic));
+    }
+}
@@ -0,0 +1,414 @@
+/*!
+Quick Answer - 限时快速回答模式
+================================
+
+同时向快速模型（本地模型或廉价云端模型）和完整模型发起请求，完整模型流式输出：
+- 完整模型在超时时间内开始输出：取消快速回答，直接流式推送完整回答（`final_delta` 事件）
+- 超时：快速回答先完成则推送（`quick_answer` 事件），之后完整回答的 `final_delta` 开始替换它；
+  完整模型先开始输出则同样取消快速回答
+- 完整模型结束后推送 `final_answer`（完整内容）与 `done`
+- 两路请求的延迟、成功/失败与估算 Token 均计入使用统计（被取消的快速回答单独计数）
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::core_traits::ai::{AIProviderConfig, AIService, Message};
+use crate::intelligence_router::extract_text_content;
+use crate::token_counter::estimate_tokens;
+
+/// 完整模型的默认等待时间
+const DEFAULT_TIMEOUT_MS: u64 = 3000;
+/// 本地快速模型的最大生成 token 数
+const LOCAL_MAX_TOKENS: usize = 256;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 回答来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnswerTier {
+    Quick,
+    Full,
+}
+
+/// 单路回答结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TierAnswer {
+    pub tier: AnswerTier,
+    pub model: String,
+    pub content: Option<String>,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+}
+
+/// 快速回答模式的最终结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuickAnswerResult {
+    /// 仅在快速回答被展示时存在
+    pub quick: Option<TierAnswer>,
+    pub full: TierAnswer,
+    /// 完整回答是否替换了已展示的快速回答
+    pub replaced_quick: bool,
+}
+
+/// 单路使用统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TierUsage {
+    pub requests: u64,
+    pub successes: u64,
+    pub failures: u64,
+    pub total_latency_ms: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+/// 快速回答模式使用统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuickAnswerStats {
+    pub quick: TierUsage,
+    pub full: TierUsage,
+    /// 快速回答被展示的次数（完整模型超时）
+    pub quick_shown: u64,
+    /// 完整模型在超时内开始输出的次数
+    pub full_within_timeout: u64,
+    /// 完整模型先开始输出、快速回答被取消的次数
+    pub quick_cancelled: u64,
+}
+
+impl QuickAnswerStats {
+    fn record(&mut self, answer: &TierAnswer, prompt_tokens: usize) {
+        let usage = match answer.tier {
+            AnswerTier::Quick => &mut self.quick,
+            AnswerTier::Full => &mut self.full,
+        };
+        usage.requests += 1;
+        usage.total_latency_ms += answer.latency_ms;
+        usage.prompt_tokens += prompt_tokens as u64;
+        match &answer.content {
+            Some(content) => {
+                usage.successes += 1;
+                usage.completion_tokens += estimate_tokens(content) as u64;
+            }
+            None => usage.failures += 1,
+        }
+    }
+}
+
+static STATS: OnceLock<Mutex<QuickAnswerStats>> = OnceLock::new();
+
+fn stats() -> &'static Mutex<QuickAnswerStats> {
+    STATS.get_or_init(|| Mutex::new(QuickAnswerStats::default()))
+}
+
+fn record_usage(answer: &TierAnswer, prompt_tokens: usize) {
+    if let Ok(mut s) = stats().lock() {
+        s.record(answer, prompt_tokens);
+    }
+}
+
+// ============================================================================
+// Model calls
+// ============================================================================
+
+/// 将对话压平为本地模型可用的提示词
+fn flatten_prompt(messages: &[Message]) -> String {
+    let mut prompt = messages.iter()
+        .map(|m| format!("{}: {}", m.role, extract_text_content(&m.content)))
+        .collect::<Vec<_>>()
+        .join("\n");
+    prompt.push_str("\nassistant:");
+    prompt
+}
+
+fn provider_model(config: &AIProviderConfig) -> String {
+    config.models.first().cloned().unwrap_or_default()
+}
+
+async fn call_provider(tier: AnswerTier, config: AIProviderConfig, messages: Vec<Message>) -> TierAnswer {
+    let start = Instant::now();
+    let result = crate::ai_utils::fetch_ai_completion(&config, messages, None).await;
+    let (content, error) = match result {
+        Ok(msg) => (Some(extract_text_content(&msg.content)), None),
+        Err(e) => (None, Some(e)),
+    };
+    TierAnswer {
+        tier,
+        model: provider_model(&config),
+        content,
+        error,
+        latency_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// 流式数据块中的正文增量
+fn content_delta(chunk: &str) -> Option<String> {
+    let value: Value = serde_json::from_str(chunk).ok()?;
+    value["choices"][0]["delta"]["content"].as_str().filter(|s| !s.is_empty()).map(String::from)
+}
+
+/// 流式请求完整模型，正文增量实时发送到 `deltas`（请求结束时通道关闭）
+async fn stream_full(
+    ai_service: Arc<dyn AIService>,
+    config: AIProviderConfig,
+    messages: Vec<Message>,
+    event_id: String,
+    deltas: UnboundedSender<String>,
+) -> TierAnswer {
+    let start = Instant::now();
+    let buffer = Arc::new(Mutex::new(String::new()));
+    let sink = buffer.clone();
+    let callback = Box::new(move |chunk: String| {
+        if let Some(delta) = content_delta(&chunk) {
+            if let Ok(mut buffer) = sink.lock() {
+                buffer.push_str(&delta);
+            }
+            let _ = deltas.send(delta);
+        }
+    });
+    let result = ai_service.stream_chat(&config, messages, &event_id, None, callback).await;
+    let text = buffer.lock().map(|b| b.clone()).unwrap_or_default();
+    let (content, error) = match result {
+        Ok(()) if text.is_empty() => (None, Some("完整模型未返回内容".to_string())),
+        Ok(()) => (Some(text), None),
+        Err(e) => (None, Some(e)),
+    };
+    TierAnswer {
+        tier: AnswerTier::Full,
+        model: provider_model(&config),
+        content,
+        error,
+        latency_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// 本地模型生成快速回答；`cancel` 置位后在下一个 token 处停止，释放推理线程
+async fn call_local(messages: Vec<Message>, cancel: Arc<AtomicBool>) -> TierAnswer {
+    let start = Instant::now();
+    let prompt = flatten_prompt(&messages);
+
+    #[cfg(feature = "llm-inference")]
+    let result = tokio::task::spawn_blocking(move || {
+        let sampling = crate::llm_inference::SamplingParams::default();
+        crate::llm_inference::generate_completion_stream(&prompt, LOCAL_MAX_TOKENS, &sampling, |_| !cancel.load(Ordering::SeqCst))
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("任务调度失败: {}", e))
+    .and_then(|r| r);
+
+    #[cfg(not(feature = "llm-inference"))]
+    let result: Result<String, String> = {
+        let _ = (prompt, LOCAL_MAX_TOKENS, cancel);
+        Err("本地推理不可用，请配置快速模型 Provider".to_string())
+    };
+
+    let (content, error) = match result {
+        Ok(text) => (Some(text), None),
+        Err(e) => (None, Some(e)),
+    };
+    TierAnswer {
+        tier: AnswerTier::Quick,
+        model: "local".to_string(),
+        content,
+        error,
+        latency_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 限时快速回答
+///
+/// `fast_provider` 为空时使用本地模型（需要 `llm-inference` feature）。
+/// 事件通过 `event_id` 推送：`quick_answer`、`final_delta`、`final_answer`、`done`。
+#[tauri::command]
+pub async fn quick_answer_chat(
+    app: AppHandle,
+    provider_config: AIProviderConfig,
+    fast_provider: Option<AIProviderConfig>,
+    messages: Vec<Message>,
+    event_id: String,
+    timeout_ms: Option<u64>,
+) -> Result<QuickAnswerResult, String> {
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS));
+    let prompt_tokens: usize = messages.iter()
+        .map(|m| estimate_tokens(&extract_text_content(&m.content)))
+        .sum();
+
+    let ai_service = app.state::<crate::AppState>().ai_service.clone();
+    let (delta_tx, mut deltas) = tokio::sync::mpsc::unbounded_channel();
+    let full_task = tokio::spawn(stream_full(ai_service, provider_config, messages.clone(), event_id.clone(), delta_tx));
+    let cancel_quick = Arc::new(AtomicBool::new(false));
+    let mut quick_task = match fast_provider {
+        Some(config) => tokio::spawn(call_provider(AnswerTier::Quick, config, messages)),
+        None => tokio::spawn(call_local(messages, cancel_quick.clone())),
+    };
+
+    // 完整模型在超时内开始输出：直接使用（通道关闭说明完整模型已无输出地结束，同样等待快速回答）
+    let mut first_delta = tokio::select! {
+        delta = deltas.recv() => delta,
+        _ = tokio::time::sleep(timeout) => None,
+    };
+    let within_timeout = first_delta.is_some();
+
+    let mut quick_finished = false;
+    let mut shown_quick = None;
+    if first_delta.is_none() {
+        println!("[QuickAnswer] Full model silent after {}ms, racing quick answer", timeout.as_millis());
+        // 快速回答与完整模型的首个输出谁先到用谁
+        first_delta = tokio::select! {
+            Some(delta) = deltas.recv() => Some(delta),
+            quick = &mut quick_task => {
+                quick_finished = true;
+                let quick = quick.map_err(|e| format!("快速模型任务失败: {}", e))?;
+                record_usage(&quick, prompt_tokens);
+                if let Some(content) = &quick.content {
+                    if let Ok(mut s) = stats().lock() {
+                        s.quick_shown += 1;
+                    }
+                    let _ = app.emit(&event_id, json!({
+                        "type": "quick_answer",
+                        "content": content,
+                        "model": quick.model,
+                        "provisional": true
+                    }));
+                    shown_quick = Some(quick);
+                }
+                None
+            }
+        };
+    }
+
+    if !quick_finished {
+        // 完整模型先开始输出：停止快速模型（本地推理在下一个 token 处退出）
+        cancel_quick.store(true, Ordering::SeqCst);
+        quick_task.abort();
+    }
+    if let Ok(mut s) = stats().lock() {
+        if within_timeout {
+            s.full_within_timeout += 1;
+        }
+        if !quick_finished {
+            s.quick_cancelled += 1;
+        }
+    }
+
+    // 流式推送完整回答，直到完整模型结束
+    while let Some(delta) = match first_delta.take() {
+        Some(delta) => Some(delta),
+        None => deltas.recv().await,
+    } {
+        let _ = app.emit(&event_id, json!({
+            "type": "final_delta",
+            "content": delta,
+            "replaces_quick": shown_quick.is_some()
+        }));
+    }
+
+    let full = full_task.await.map_err(|e| format!("完整模型任务失败: {}", e))?;
+    record_usage(&full, prompt_tokens);
+    finish(&app, &event_id, shown_quick, full)
+}
+
+fn finish(
+    app: &AppHandle,
+    event_id: &str,
+    quick: Option<TierAnswer>,
+    full: TierAnswer,
+) -> Result<QuickAnswerResult, String> {
+    let replaced_quick = quick.is_some() && full.content.is_some();
+    match &full.content {
+        Some(content) => {
+            let _ = app.emit(event_id, json!({
+                "type": "final_answer",
+                "content": content,
+                "model": full.model,
+                "replaces_quick": replaced_quick
+            }));
+        }
+        None if quick.is_none() => {
+            return Err(full.error.clone().unwrap_or_else(|| "完整模型无响应".to_string()));
+        }
+        // 完整模型失败时保留已展示的快速回答
+        None => {}
+    }
+    let _ = app.emit(event_id, json!({"type": "done"}));
+
+    Ok(QuickAnswerResult { quick, full, replaced_quick })
+}
+
+/// 获取快速回答模式的使用统计
+#[tauri::command]
+pub fn get_quick_answer_stats() -> QuickAnswerStats {
+    stats().lock().map(|s| s.clone()).unwrap_or_default()
+}
+
+/// 重置快速回答模式的使用统计
+#[tauri::command]
+pub fn reset_quick_answer_stats() {
+    if let Ok(mut s) = stats().lock() {
+        *s = QuickAnswerStats::default();
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_traits::ai::Content;
+
+    fn answer(tier: AnswerTier, content: Option<&str>, latency_ms: u64) -> TierAnswer {
+        TierAnswer {
+            tier,
+            model: "m".to_string(),
+            content: content.map(|c| c.to_string()),
+            error: content.is_none().then(|| "err".to_string()),
+            latency_ms,
+        }
+    }
+
+    #[test]
+    fn test_stats_record_per_tier() {
+        let mut stats = QuickAnswerStats::default();
+        stats.record(&answer(AnswerTier::Quick, Some("hello world"), 100), 10);
+        stats.record(&answer(AnswerTier::Full, None, 5000), 10);
+
+        assert_eq!(stats.quick.requests, 1);
+        assert_eq!(stats.quick.successes, 1);
+        assert_eq!(stats.quick.total_latency_ms, 100);
+        assert!(stats.quick.completion_tokens > 0);
+        assert_eq!(stats.full.failures, 1);
+        assert_eq!(stats.full.prompt_tokens, 10);
+        assert_eq!(stats.full.completion_tokens, 0);
+    }
+
+    #[test]
+    fn test_content_delta() {
+        assert_eq!(content_delta(r#"{"choices":[{"index":0,"delta":{"content":"Hi"}}]}"#).as_deref(), Some("Hi"));
+        assert_eq!(content_delta(r#"{"choices":[{"index":0,"delta":{"reasoning_content":"hmm"}}]}"#), None);
+        assert_eq!(content_delta(r#"{"type":"tool_call"}"#), None);
+        assert_eq!(content_delta("not json"), None);
+    }
+
+    #[test]
+    fn test_flatten_prompt() {
+        let messages = vec![
+            Message { role: "system".to_string(), content: Content::Text("be brief".to_string()), ..Default::default() },
+            Message { role: "user".to_string(), content: Content::Text("hi".to_string()), ..Default::default() },
+        ];
+        assert_eq!(flatten_prompt(&messages), "system: be brief\nuser: hi\nassistant:");
+    }
+}
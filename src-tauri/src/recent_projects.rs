@@ -0,0 +1,307 @@
+/*!
+Recent Projects - 最近打开的项目
+================================
+
+在应用数据目录的 `recent_projects.json` 中记录最近打开的项目根目录，
+按最近打开时间排序，最多保留 `MAX_RECENT_PROJECTS` 个，并缓存每个项目的快速统计，
+供欢迎页展示项目卡片：
+
+- 符号索引新鲜度：上次索引时间、文件数，以及此后是否有文件被修改
+- 最近会话：`.ifai/agent_runs/` 中最新的运行记录
+- 待处理提案：`.ifai/proposals/` 中的提案数
+
+`list_recent_projects` 立即返回缓存，同时在后台刷新统计，完成后发送 `recent-projects:stats` 事件。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+use crate::events::emit_event;
+
+/// 最多保留的项目数
+const MAX_RECENT_PROJECTS: usize = 20;
+/// 判断索引是否过期时最多检查的文件数
+const MAX_STALE_CHECK_FILES: usize = 5000;
+
+/// 串行化注册表的读-改-写
+static REGISTRY_LOCK: Mutex<()> = Mutex::new(());
+
+/// 项目快速统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProjectStats {
+    /// 上次符号索引时间（Unix 秒）
+    pub index_updated_at: Option<i64>,
+    pub index_files: Option<usize>,
+    /// 索引之后是否有代码文件被修改
+    pub index_stale: bool,
+    /// 最近一次 Agent 运行
+    pub last_session_id: Option<String>,
+    pub last_session_at: Option<i64>,
+    pub pending_proposals: usize,
+    /// 统计刷新时间
+    pub refreshed_at: Option<i64>,
+}
+
+/// 最近项目条目
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecentProject {
+    pub root: String,
+    pub name: String,
+    /// 最近打开时间（Unix 秒）
+    pub last_opened: i64,
+    /// 项目目录是否仍存在
+    #[serde(default = "default_exists")]
+    pub exists: bool,
+    #[serde(default)]
+    pub stats: ProjectStats,
+}
+
+fn default_exists() -> bool {
+    true
+}
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+fn registry_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("recent_projects.json"))
+        .map_err(|e| format!("Failed to resolve app data dir: {}", e))
+}
+
+fn load_registry(path: &Path) -> Vec<RecentProject> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_registry(path: &Path, projects: &[RecentProject]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create app data dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(projects).map_err(|e| e.to_string())?;
+    std::fs::write(path, content).map_err(|e| format!("Failed to write recent projects: {}", e))
+}
+
+/// 在锁内读取、修改并保存注册表
+fn update_registry<T>(app: &AppHandle, f: impl FnOnce(&mut Vec<RecentProject>) -> T) -> Result<T, String> {
+    let _guard = REGISTRY_LOCK.lock().map_err(|e| format!("Lock error: {}", e))?;
+    let path = registry_path(app)?;
+    let mut projects = load_registry(&path);
+    let result = f(&mut projects);
+    save_registry(&path, &projects)?;
+    Ok(result)
+}
+
+fn normalize_root(root: &str) -> String {
+    root.trim_end_matches(['/', '\\']).to_string()
+}
+
+/// 将项目移到列表最前（不存在则新增），返回该条目
+fn upsert<'a>(projects: &'a mut Vec<RecentProject>, root: &str, now: i64) -> &'a mut RecentProject {
+    let root = normalize_root(root);
+    let existing = projects.iter().position(|p| p.root == root).map(|i| projects.remove(i));
+    let entry = match existing {
+        Some(mut entry) => {
+            entry.last_opened = now;
+            entry.exists = true;
+            entry
+        }
+        None => RecentProject {
+            name: Path::new(&root)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| root.clone()),
+            root,
+            last_opened: now,
+            exists: true,
+            stats: ProjectStats::default(),
+        },
+    };
+    projects.insert(0, entry);
+    projects.truncate(MAX_RECENT_PROJECTS);
+    &mut projects[0]
+}
+
+/// 记录一次项目打开
+pub fn record_project(app: &AppHandle, root: &str) -> Result<(), String> {
+    update_registry(app, |projects| {
+        upsert(projects, root, chrono::Utc::now().timestamp());
+    })
+}
+
+/// 记录符号索引完成
+pub fn record_index(app: &AppHandle, root: &str, files_indexed: usize) -> Result<(), String> {
+    update_registry(app, |projects| {
+        let now = chrono::Utc::now().timestamp();
+        let entry = upsert(projects, root, now);
+        entry.stats.index_updated_at = Some(now);
+        entry.stats.index_files = Some(files_indexed);
+        entry.stats.index_stale = false;
+    })
+}
+
+// ============================================================================
+// Stats
+// ============================================================================
+
+fn modified_secs(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64)
+}
+
+/// 索引之后是否有代码文件被修改
+fn index_is_stale(root: &Path, indexed_at: i64) -> bool {
+    ignore::WalkBuilder::new(root)
+        .hidden(true)
+        .git_ignore(true)
+        .build()
+        .flatten()
+        .filter(|e| e.file_type().map_or(false, |ft| ft.is_file()))
+        .take(MAX_STALE_CHECK_FILES)
+        .any(|e| modified_secs(e.path()).map_or(false, |t| t > indexed_at))
+}
+
+/// 最新的 Agent 运行记录：(运行 ID, 时间)
+fn last_session(root: &Path) -> Option<(String, i64)> {
+    std::fs::read_dir(root.join(".ifai").join("agent_runs"))
+        .ok()?
+        .flatten()
+        .filter(|e| e.path().extension().map_or(false, |ext| ext == "jsonl"))
+        .filter_map(|e| {
+            let id = e.path().file_stem()?.to_string_lossy().trim_start_matches("agent_").to_string();
+            Some((id, modified_secs(&e.path())?))
+        })
+        .max_by_key(|(_, t)| *t)
+}
+
+fn pending_proposals(root: &Path) -> usize {
+    std::fs::read_dir(root.join(".ifai").join("proposals"))
+        .map(|entries| entries.flatten().filter(|e| e.path().join("metadata.json").exists()).count())
+        .unwrap_or(0)
+}
+
+/// 重新计算项目统计（保留索引记录）
+fn compute_stats(root: &str, previous: &ProjectStats) -> ProjectStats {
+    let root = Path::new(root);
+    let (last_session_id, last_session_at) = match last_session(root) {
+        Some((id, at)) => (Some(id), Some(at)),
+        None => (None, None),
+    };
+    ProjectStats {
+        index_updated_at: previous.index_updated_at,
+        index_files: previous.index_files,
+        index_stale: previous.index_updated_at.map_or(false, |t| index_is_stale(root, t)),
+        last_session_id,
+        last_session_at,
+        pending_proposals: pending_proposals(root),
+        refreshed_at: Some(chrono::Utc::now().timestamp()),
+    }
+}
+
+/// 后台刷新所有项目的统计，完成后发送事件
+fn refresh_stats_in_background(app: AppHandle, projects: Vec<RecentProject>) {
+    tauri::async_runtime::spawn_blocking(move || {
+        let refreshed: Vec<(String, bool, ProjectStats)> = projects.iter()
+            .map(|p| {
+                let exists = Path::new(&p.root).is_dir();
+                let stats = if exists { compute_stats(&p.root, &p.stats) } else { p.stats.clone() };
+                (p.root.clone(), exists, stats)
+            })
+            .collect();
+
+        let result = update_registry(&app, |current| {
+            for project in current.iter_mut() {
+                if let Some((_, exists, stats)) = refreshed.iter().find(|(root, _, _)| *root == project.root) {
+                    project.exists = *exists;
+                    project.stats = stats.clone();
+                }
+            }
+            current.clone()
+        });
+        match result {
+            Ok(projects) => emit_event(&app, "recent-projects:stats", &projects),
+            Err(e) => eprintln!("[RecentProjects] Failed to refresh stats: {}", e),
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 列出最近项目（返回缓存统计，并在后台刷新）
+#[tauri::command]
+pub fn list_recent_projects(app: AppHandle, refresh: Option<bool>) -> Result<Vec<RecentProject>, String> {
+    let projects = {
+        let _guard = REGISTRY_LOCK.lock().map_err(|e| format!("Lock error: {}", e))?;
+        load_registry(&registry_path(&app)?)
+    };
+    if refresh.unwrap_or(true) && !projects.is_empty() {
+        refresh_stats_in_background(app, projects.clone());
+    }
+    Ok(projects)
+}
+
+/// 记录项目被打开
+#[tauri::command]
+pub fn record_recent_project(app: AppHandle, root: String) -> Result<(), String> {
+    record_project(&app, &root)
+}
+
+/// 从列表中移除项目（不删除项目文件）
+#[tauri::command]
+pub fn remove_recent_project(app: AppHandle, root: String) -> Result<(), String> {
+    let root = normalize_root(&root);
+    update_registry(&app, |projects| projects.retain(|p| p.root != root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert_moves_to_front_and_truncates() {
+        let mut projects = Vec::new();
+        for i in 0..25 {
+            upsert(&mut projects, &format!("/work/p{}", i), i);
+        }
+        assert_eq!(projects.len(), MAX_RECENT_PROJECTS);
+        assert_eq!(projects[0].root, "/work/p24");
+
+        projects[10].stats.pending_proposals = 3;
+        let root = projects[10].root.clone();
+        upsert(&mut projects, &format!("{}/", root), 100);
+        assert_eq!(projects[0].root, root);
+        assert_eq!(projects[0].last_opened, 100);
+        assert_eq!(projects[0].stats.pending_proposals, 3);
+        assert_eq!(projects.iter().filter(|p| p.root == root).count(), 1);
+    }
+
+    #[test]
+    fn test_compute_stats() {
+        let root = std::env::temp_dir().join(format!("ifai_recent_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join(".ifai/agent_runs")).unwrap();
+        std::fs::create_dir_all(root.join(".ifai/proposals/add-auth")).unwrap();
+        std::fs::create_dir_all(root.join(".ifai/proposals/empty")).unwrap();
+        std::fs::write(root.join(".ifai/proposals/add-auth/metadata.json"), "{}").unwrap();
+        std::fs::write(root.join(".ifai/agent_runs/agent_run-1.jsonl"), "").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+
+        let previous = ProjectStats { index_updated_at: Some(0), index_files: Some(1), ..Default::default() };
+        let stats = compute_stats(&root.to_string_lossy(), &previous);
+        assert_eq!(stats.pending_proposals, 1);
+        assert_eq!(stats.last_session_id.as_deref(), Some("run-1"));
+        assert!(stats.index_stale);
+        assert_eq!(stats.index_files, Some(1));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,51 @@
+/*!
+Request Options - 请求选项
+==========================
+
+单次 AI 请求的上下文设置，由 `RequestOptions::run` 一次性建立：
+
+- `cancel`: 取消令牌，取消时丢弃请求 future
+- `retry_channel`: 重试进度（`retrying` 事件）发送的通道
+- `verbosity`: 会话详略设置，请求构建层据此设置 `max_tokens`
+- `session_id`: 温度调度的会话级覆盖
+- `usage_scope`: 用量记录的项目 / Agent 归属
+
+各项通过 task-local 传递给 `ai_utils` 的请求构建层，社区版与商业版共用同一路径。
+*/
+
+use std::future::Future;
+use tauri::AppHandle;
+
+use crate::cancellation::CancelGuard;
+use crate::usage_meter::UsageScope;
+use crate::verbosity::VerbositySettings;
+
+#[derive(Default)]
+pub struct RequestOptions<'a> {
+    pub cancel: Option<&'a CancelGuard>,
+    pub retry_channel: Option<(AppHandle, String)>,
+    pub verbosity: Option<VerbositySettings>,
+    pub session_id: Option<String>,
+    pub usage_scope: UsageScope,
+}
+
+impl RequestOptions<'_> {
+    /// 在这些设置下执行请求
+    pub async fn run<T>(self, fut: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+        let retry_channel = self.retry_channel;
+        let scoped = crate::verbosity::with_settings(
+            self.verbosity,
+            crate::temperature_schedule::with_session(self.session_id, crate::usage_meter::with_scope(self.usage_scope, fut)),
+        );
+        let scoped = async move {
+            match retry_channel {
+                Some((app, channel)) => crate::ai_retry::with_channel(app, channel, scoped).await,
+                None => scoped.await,
+            }
+        };
+        match self.cancel {
+            Some(cancel) => cancel.run(scoped).await,
+            None => scoped.await,
+        }
+    }
+}
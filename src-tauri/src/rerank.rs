@@ -0,0 +1,326 @@
+/*!
+Rerank - 检索结果重排序
+=======================
+
+混合检索（向量 top-k + grep）返回的片段未必都与问题相关。组装上下文前先对
+候选片段重新打分，只保留最相关的 `top_k` 个：
+
+- `cross_encoder`：本地 ONNX 交叉编码器（fastembed，需 `rag` feature）
+- `llm`：让对话模型（或 `llm_model` 指定的模型）为每个片段打 0-10 分
+- `none`：保持原始顺序（默认）
+
+打分失败时保留原始结果，不影响对话。配置保存在 `~/.ifai/rag.json`。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::core_traits::ai::{AIProviderConfig, Content, Message};
+use crate::core_traits::rag::{RagReference, RagResult};
+
+/// LLM 打分时每个片段的最大字符数
+const LLM_SNIPPET_CHARS: usize = 800;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RerankerKind {
+    #[default]
+    None,
+    CrossEncoder,
+    Llm,
+}
+
+impl RerankerKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::CrossEncoder => "cross_encoder",
+            Self::Llm => "llm",
+        }
+    }
+}
+
+/// 检索配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RagConfig {
+    pub reranker: RerankerKind,
+    /// 交叉编码器模型：`bge-reranker-base`、`bge-reranker-v2-m3`（多语言）、`jina-reranker-v2-base-multilingual`
+    pub cross_encoder_model: String,
+    /// LLM 打分使用的模型，默认与对话相同
+    pub llm_model: Option<String>,
+    /// 重排后保留的片段数
+    pub top_k: usize,
+    /// 重排后上下文的最大字符数
+    pub max_context_chars: usize,
+}
+
+impl Default for RagConfig {
+    fn default() -> Self {
+        Self {
+            reranker: RerankerKind::None,
+            cross_encoder_model: "bge-reranker-base".to_string(),
+            llm_model: None,
+            top_k: 10,
+            max_context_chars: 12000,
+        }
+    }
+}
+
+// ============================================================================
+// Scoring
+// ============================================================================
+
+/// 已加载的交叉编码器（按模型名缓存）
+#[cfg(feature = "fastembed")]
+static CROSS_ENCODERS: std::sync::OnceLock<std::sync::Mutex<std::collections::HashMap<String, std::sync::Arc<std::sync::Mutex<fastembed::TextRerank>>>>> =
+    std::sync::OnceLock::new();
+
+/// 释放已加载的交叉编码器，下次重排时重新加载（由 `idle_manager` 调用）
+pub fn unload_models() -> bool {
+    #[cfg(feature = "fastembed")]
+    {
+        let Some(models) = CROSS_ENCODERS.get() else { return false };
+        let Ok(mut models) = models.lock() else { return false };
+        let loaded = !models.is_empty();
+        models.clear();
+        loaded
+    }
+    #[cfg(not(feature = "fastembed"))]
+    {
+        false
+    }
+}
+
+#[cfg(feature = "fastembed")]
+fn cross_encoder_scores(model_name: &str, query: &str, documents: &[String]) -> Result<Vec<f32>, String> {
+    use fastembed::{RerankInitOptions, RerankerModel, TextRerank};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    let model = match model_name {
+        "bge-reranker-base" => RerankerModel::BGERerankerBase,
+        "bge-reranker-v2-m3" => RerankerModel::BGERerankerV2M3,
+        "jina-reranker-v1-turbo-en" => RerankerModel::JINARerankerV1TurboEn,
+        "jina-reranker-v2-base-multilingual" => RerankerModel::JINARerankerV2BaseMultiligual,
+        _ => return Err(format!("Unsupported cross-encoder model: {}", model_name)),
+    };
+    let instance = {
+        let mut models = CROSS_ENCODERS.get_or_init(|| Mutex::new(HashMap::new())).lock().map_err(|e| e.to_string())?;
+        match models.get(model_name) {
+            Some(instance) => instance.clone(),
+            None => {
+                println!("[Rerank] Loading cross-encoder {}", model_name);
+                let reranker = TextRerank::try_new(RerankInitOptions::new(model))
+                    .map_err(|e| format!("Failed to load cross-encoder {}: {}", model_name, e))?;
+                let instance = Arc::new(Mutex::new(reranker));
+                models.insert(model_name.to_string(), instance.clone());
+                instance
+            }
+        }
+    };
+
+    let count = documents.len();
+    let documents: Vec<&str> = documents.iter().map(|d| d.as_str()).collect();
+    let results = instance.lock().map_err(|e| e.to_string())?
+        .rerank(query, documents, false, None)
+        .map_err(|e| format!("Cross-encoder failed: {}", e))?;
+    let mut scores = vec![f32::MIN; count];
+    for result in results {
+        if let Some(score) = scores.get_mut(result.index) {
+            *score = result.score;
+        }
+    }
+    Ok(scores)
+}
+
+#[cfg(not(feature = "fastembed"))]
+fn cross_encoder_scores(_model_name: &str, _query: &str, _documents: &[String]) -> Result<Vec<f32>, String> {
+    Err("Cross-encoder reranking requires the rag feature".to_string())
+}
+
+fn llm_prompt(query: &str, references: &[RagReference]) -> String {
+    let mut prompt = format!(
+        "Rate how relevant each code snippet is to the question, from 0 (unrelated) to 10 (directly answers it).\n\nQuestion: {}\n\n",
+        query
+    );
+    for (i, reference) in references.iter().enumerate() {
+        let snippet: String = reference.content.chars().take(LLM_SNIPPET_CHARS).collect();
+        prompt.push_str(&format!("[{}] {}:{}\n```\n{}\n```\n\n", i, reference.file_path, reference.line_start, snippet));
+    }
+    prompt.push_str(&format!(
+        "Respond with only a JSON object with one score per snippet in order: {{\"scores\": [..{} numbers..]}}",
+        references.len()
+    ));
+    prompt
+}
+
+fn parse_llm_scores(response: &str, expected: usize) -> Result<Vec<f32>, String> {
+    let value = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if end > start => serde_json::from_str::<serde_json::Value>(&response[start..=end]).ok(),
+        _ => None,
+    };
+    let scores: Vec<f32> = value
+        .as_ref()
+        .and_then(|v| v["scores"].as_array())
+        .ok_or_else(|| "Reranker response has no scores".to_string())?
+        .iter()
+        .map(|s| s.as_f64().unwrap_or(0.0) as f32)
+        .collect();
+    if scores.len() != expected {
+        return Err(format!("Reranker returned {} scores for {} snippets", scores.len(), expected));
+    }
+    Ok(scores)
+}
+
+async fn llm_scores(config: &RagConfig, provider: &AIProviderConfig, query: &str, references: &[RagReference]) -> Result<Vec<f32>, String> {
+    let mut provider = provider.clone();
+    if let Some(model) = &config.llm_model {
+        provider.models = vec![model.clone()];
+    }
+    let messages = vec![Message {
+        role: "user".to_string(),
+        content: Content::Text(llm_prompt(query, references)),
+        tool_calls: None,
+        tool_call_id: None,
+    }];
+    let reply = crate::ai_utils::fetch_ai_completion(&provider, messages, None).await?;
+    parse_llm_scores(&crate::intelligence_router::extract_text_content(&reply.content), references.len())
+}
+
+// ============================================================================
+// Context assembly
+// ============================================================================
+
+/// 按分数降序保留前 `top_k` 个片段并重新组装上下文
+fn assemble(config: &RagConfig, references: Vec<RagReference>, scores: &[f32]) -> RagResult {
+    let mut ranked: Vec<(f32, RagReference)> = scores.iter().copied().zip(references).collect();
+    ranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let total = ranked.len();
+
+    let mut context = format!("<codebase_context reranked_by=\"{}\">\n", config.reranker.as_str());
+    let mut references = Vec::new();
+    for (score, reference) in ranked.into_iter().take(config.top_k) {
+        let rendered = format!(
+            "<chunk path=\"{}\" line_start=\"{}\" score=\"{:.2}\">\n{}\n</chunk>\n",
+            reference.file_path, reference.line_start, score, reference.content
+        );
+        if context.len() + rendered.len() > config.max_context_chars && !references.is_empty() {
+            break;
+        }
+        context.push_str(&rendered);
+        references.push(reference);
+    }
+    if total > references.len() {
+        context.push_str(&format!("<!-- {} less relevant chunks omitted -->\n", total - references.len()));
+    }
+    context.push_str("</codebase_context>");
+    RagResult { context, references }
+}
+
+/// 对检索结果重排序；未启用或打分失败时原样返回
+pub async fn rerank_result(config: &RagConfig, provider: &AIProviderConfig, query: &str, result: RagResult) -> RagResult {
+    if result.references.len() < 2 {
+        return result;
+    }
+    let scores = match config.reranker {
+        RerankerKind::CrossEncoder => {
+            let model = config.cross_encoder_model.clone();
+            let query = query.to_string();
+            let documents: Vec<String> = result.references.iter().map(|r| format!("{}\n{}", r.file_path, r.content)).collect();
+            tokio::task::spawn_blocking(move || cross_encoder_scores(&model, &query, &documents))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|r| r)
+        }
+        RerankerKind::Llm => llm_scores(config, provider, query, &result.references).await,
+        RerankerKind::None => return result,
+    };
+    match scores {
+        Ok(scores) if scores.len() == result.references.len() => {
+            println!("[Rerank] Reranked {} chunks with {}", scores.len(), config.reranker.as_str());
+            assemble(config, result.references, &scores)
+        }
+        Ok(_) => result,
+        Err(e) => {
+            eprintln!("[Rerank] Reranking failed, keeping retrieval order: {}", e);
+            result
+        }
+    }
+}
+
+// ============================================================================
+// Config
+// ============================================================================
+
+fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("rag.json")
+}
+
+pub fn load_config() -> RagConfig {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(config: &RagConfig) -> Result<(), String> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write RAG config: {}", e))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_rag_config() -> RagConfig {
+    load_config()
+}
+
+#[tauri::command]
+pub fn set_rag_config(config: RagConfig) -> Result<(), String> {
+    if config.top_k == 0 || config.max_context_chars == 0 {
+        return Err("top_k and max_context_chars must be greater than 0".to_string());
+    }
+    save_config(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reference(path: &str, content: &str) -> RagReference {
+        RagReference { file_path: path.to_string(), line_start: 1, content: content.to_string() }
+    }
+
+    #[test]
+    fn test_parse_llm_scores() {
+        assert_eq!(parse_llm_scores("Sure: {\"scores\": [3, 9.5, 0]}", 3).unwrap(), vec![3.0, 9.5, 0.0]);
+        assert!(parse_llm_scores("{\"scores\": [3]}", 2).is_err());
+        assert!(parse_llm_scores("no json", 1).is_err());
+    }
+
+    #[test]
+    fn test_assemble_orders_and_limits() {
+        let config = RagConfig { reranker: RerankerKind::Llm, top_k: 2, ..Default::default() };
+        let references = vec![reference("a.rs", "fn a() {}"), reference("b.rs", "fn b() {}"), reference("c.rs", "fn c() {}")];
+        let result = assemble(&config, references, &[1.0, 8.0, 5.0]);
+        assert_eq!(result.references.iter().map(|r| r.file_path.as_str()).collect::<Vec<_>>(), vec!["b.rs", "c.rs"]);
+        assert!(result.context.starts_with("<codebase_context reranked_by=\"llm\">\n<chunk path=\"b.rs\" line_start=\"1\" score=\"8.00\">"));
+        assert!(result.context.contains("<!-- 1 less relevant chunks omitted -->"));
+
+        // 超出字符预算时至少保留最相关的一个
+        let tight = RagConfig { max_context_chars: 10, ..config };
+        let result = assemble(&tight, vec![reference("a.rs", "x"), reference("b.rs", "y")], &[0.0, 1.0]);
+        assert_eq!(result.references.len(), 1);
+        assert_eq!(result.references[0].file_path, "b.rs");
+    }
+}
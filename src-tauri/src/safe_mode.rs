@@ -0,0 +1,96 @@
+/*!
+Safe Mode - 安全模式启动
+========================
+
+启动卡死时的恢复手段：以 `--safe-mode` 参数或 `IFAI_SAFE_MODE=1` 环境变量启动，
+跳过所有可选子系统，只注册核心的对话、文件与诊断命令：
+
+- 不加载 RAG / 符号索引，对话不注入检索上下文
+- 不加载本地模型（对话跳过本地模型预处理）
+- 不启动后台任务：任务队列、更新检查、空闲资源回收、缓存与遗留文件清理
+
+前端通过 `get_safe_mode_status` 判断是否处于安全模式并提示用户。
+*/
+
+use serde::Serialize;
+use std::sync::OnceLock;
+
+pub const SAFE_MODE_FLAG: &str = "--safe-mode";
+pub const SAFE_MODE_ENV: &str = "IFAI_SAFE_MODE";
+
+/// 安全模式下跳过的子系统
+const SKIPPED_SUBSYSTEMS: &[&str] = &[
+    "rag_index",
+    "symbol_index",
+    "local_model",
+    "job_queue",
+    "update_check",
+    "idle_monitor",
+    "startup_cleanup",
+];
+
+/// 启用时记录触发原因
+static SAFE_MODE: OnceLock<Option<String>> = OnceLock::new();
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SafeModeStatus {
+    pub enabled: bool,
+    pub reason: Option<String>,
+    pub skipped: Vec<String>,
+}
+
+fn env_enabled(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes" | "on")
+}
+
+/// 根据命令行参数和环境变量判断是否启用，返回触发原因
+pub fn detect(mut args: impl Iterator<Item = String>, env: Option<String>) -> Option<String> {
+    if args.any(|arg| arg == SAFE_MODE_FLAG) {
+        return Some(format!("{} flag", SAFE_MODE_FLAG));
+    }
+    env.filter(|v| env_enabled(v)).map(|_| format!("{} environment variable", SAFE_MODE_ENV))
+}
+
+/// 启动时调用一次
+pub fn init() -> bool {
+    let reason = SAFE_MODE.get_or_init(|| detect(std::env::args().skip(1), std::env::var(SAFE_MODE_ENV).ok()));
+    if let Some(reason) = reason {
+        println!("[SafeMode] Enabled via {}; skipping: {}", reason, SKIPPED_SUBSYSTEMS.join(", "));
+    }
+    reason.is_some()
+}
+
+pub fn is_enabled() -> bool {
+    SAFE_MODE.get().is_some_and(|reason| reason.is_some())
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_safe_mode_status() -> SafeModeStatus {
+    let reason = SAFE_MODE.get().cloned().flatten();
+    SafeModeStatus {
+        enabled: reason.is_some(),
+        skipped: if reason.is_some() { SKIPPED_SUBSYSTEMS.iter().map(|s| s.to_string()).collect() } else { Vec::new() },
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter().map(|s| s.to_string()).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn test_detect() {
+        assert!(detect(args(&["--safe-mode"]), None).unwrap().contains("flag"));
+        assert!(detect(args(&[]), Some("TRUE".to_string())).unwrap().contains(SAFE_MODE_ENV));
+        assert_eq!(detect(args(&["--verbose"]), Some("0".to_string())), None);
+        assert_eq!(detect(args(&[]), None), None);
+    }
+}
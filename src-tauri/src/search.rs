@@ -1,68 +1,552 @@
-use serde::Serialize;
-use std::sync::{Arc, Mutex};
-use grep::regex::RegexMatcher;
-use grep::searcher::Searcher;
-use grep::searcher::sinks::UTF8;
+use serde::{Deserialize, Serialize};
+use grep::regex::RegexMatcherBuilder;
+use grep::searcher::{Searcher, SearcherBuilder, Sink, SinkContext, SinkContextKind, SinkMatch};
+use ignore::overrides::OverrideBuilder;
 use ignore::WalkBuilder;
-use tauri::command;
+use tauri::{command, AppHandle, Emitter};
+
+use crate::commands::atomic_commands::{FileOperationRequest, FileOperationType};
+
+/// 默认最多返回的匹配数
+const DEFAULT_MAX_RESULTS: usize = 1000;
+/// 上下文行数上限
+const MAX_CONTEXT_LINES: usize = 10;
+/// 流式搜索每批至少包含的匹配数（批次在文件边界发送）
+const STREAM_BATCH_SIZE: usize = 200;
+/// 搜索替换默认最多修改的文件数
+const DEFAULT_MAX_REPLACE_FILES: usize = 200;
+/// 超过该大小的文件不参与替换
+const MAX_REPLACE_FILE_BYTES: u64 = 2 * 1024 * 1024;
 
 #[derive(Serialize, Clone, Debug)]
 pub struct MatchResult {
     pub path: String,
     pub line_number: u64,
     pub content: String,
+    /// 匹配行之前的上下文（`context_lines` 为 0 时为空）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context_before: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub context_after: Vec<String>,
+}
+
+/// `search_in_files` 的可选过滤条件
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// 按字面量搜索（默认按正则）
+    pub literal: bool,
+    pub whole_word: bool,
+    /// 默认 1000
+    pub max_results: Option<usize>,
+    /// 每个匹配前后附带的行数，最多 10
+    pub context_lines: usize,
+    /// 设置后通过 `search:results` 事件分批发送结果
+    pub search_id: Option<String>,
+}
+
+/// `search:results` 事件负载
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchResultBatch {
+    pub search_id: String,
+    pub matches: Vec<MatchResult>,
+    /// 最后一批
+    pub done: bool,
+    /// 目前为止的匹配总数
+    pub total: usize,
+    /// 达到 `max_results`，结果被截断
+    pub truncated: bool,
 }
 
+/// 项目内搜索
+///
+/// 未指定 `options.search_id` 时一次返回全部结果；指定时结果通过 `search:results` 事件分批发送，
+/// 返回值为空，界面可以边搜索边展示。`case_sensitive` 默认为 true。
 #[command]
-pub async fn search_in_files(root_path: String, query: String) -> Result<Vec<MatchResult>, String> {
-    grep_search(&root_path, &query).map_err(|e| e.to_string())
+pub async fn search_in_files(
+    app: AppHandle,
+    root_path: String,
+    query: String,
+    case_sensitive: Option<bool>,
+    options: Option<SearchOptions>,
+) -> Result<Vec<MatchResult>, String> {
+    let options = options.unwrap_or_default();
+    let grep_options = GrepOptions {
+        include: options.include,
+        exclude: options.exclude,
+        case_insensitive: !case_sensitive.unwrap_or(true),
+        max_results: options.max_results.filter(|n| *n > 0).unwrap_or(DEFAULT_MAX_RESULTS),
+        whole_word: options.whole_word,
+        context_lines: options.context_lines.min(MAX_CONTEXT_LINES),
+    };
+    let query = if options.literal { regex::escape(&query) } else { query };
+
+    let Some(search_id) = options.search_id else {
+        return tokio::task::spawn_blocking(move || grep_search_with(&root_path, &query, &grep_options))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string());
+    };
+
+    let max_results = grep_options.max_results;
+    tokio::task::spawn_blocking(move || {
+        let mut total = 0;
+        let emit = |matches: Vec<MatchResult>, total: usize, done: bool| {
+            let _ = app.emit("search:results", SearchResultBatch {
+                search_id: search_id.clone(),
+                matches,
+                done,
+                total,
+                truncated: done && total >= max_results,
+            });
+        };
+        let result = grep_search_streaming(&root_path, &query, &grep_options, |batch| {
+            total += batch.len();
+            emit(batch, total, false);
+        });
+        match result {
+            Ok(rest) => {
+                total += rest.len();
+                emit(rest, total, true);
+                println!("[Search] {} finished with {} matches", search_id, total);
+                Ok(Vec::new())
+            }
+            Err(e) => {
+                emit(Vec::new(), total, true);
+                Err(e.to_string())
+            }
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 搜索选项：文件过滤与结果上限
+#[derive(Debug, Clone)]
+pub struct GrepOptions {
+    /// 只搜索匹配的文件（gitignore 风格 glob，如 `*.rs`、`src/**`）
+    pub include: Vec<String>,
+    /// 跳过匹配的文件
+    pub exclude: Vec<String>,
+    pub case_insensitive: bool,
+    pub max_results: usize,
+    /// 只匹配完整单词
+    pub whole_word: bool,
+    /// 匹配前后附带的上下文行数
+    pub context_lines: usize,
+}
+
+impl Default for GrepOptions {
+    fn default() -> Self {
+        Self {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            case_insensitive: false,
+            max_results: DEFAULT_MAX_RESULTS,
+            whole_word: false,
+            context_lines: 0,
+        }
+    }
 }
 
 pub fn grep_search(root_path: &str, query: &str) -> anyhow::Result<Vec<MatchResult>> {
-    let matches = Arc::new(Mutex::new(Vec::new()));
-    let matches_clone = matches.clone();
+    grep_search_with(root_path, query, &GrepOptions::default())
+}
+
+/// 遍历项目文件（遵循 .gitignore 与 include / exclude 过滤）
+fn build_walker(root_path: &str, include: &[String], exclude: &[String]) -> anyhow::Result<ignore::Walk> {
+    let mut overrides = OverrideBuilder::new(root_path);
+    for glob in include {
+        overrides.add(glob)?;
+    }
+    for glob in exclude {
+        overrides.add(&format!("!{}", glob))?;
+    }
 
     // Use ignore::WalkBuilder to respect .gitignore
-    let walker = WalkBuilder::new(root_path).build();
+    Ok(WalkBuilder::new(root_path).overrides(overrides.build()?).build())
+}
+
+pub fn grep_search_with(root_path: &str, query: &str, options: &GrepOptions) -> anyhow::Result<Vec<MatchResult>> {
+    let mut matches = Vec::new();
+    let rest = grep_search_streaming(root_path, query, options, |batch| matches.extend(batch))?;
+    matches.extend(rest);
+    Ok(matches)
+}
 
-    let matcher = RegexMatcher::new(query)?;
-    
+/// 搜索并在累计超过 `STREAM_BATCH_SIZE` 条时把已完成文件的匹配交给 `on_batch`；返回最后未发送的部分
+pub fn grep_search_streaming(
+    root_path: &str,
+    query: &str,
+    options: &GrepOptions,
+    mut on_batch: impl FnMut(Vec<MatchResult>),
+) -> anyhow::Result<Vec<MatchResult>> {
+    let walker = build_walker(root_path, &options.include, &options.exclude)?;
+    let matcher = RegexMatcherBuilder::new()
+        .case_insensitive(options.case_insensitive)
+        .word(options.whole_word)
+        .build(query)?;
+    let mut searcher = SearcherBuilder::new()
+        .before_context(options.context_lines)
+        .after_context(options.context_lines)
+        .build();
+
+    let mut pending = Vec::new();
+    let mut found = 0;
     for result in walker {
         match result {
             Ok(entry) => {
-                if !entry.file_type().map_or(false, |ft| ft.is_file()) {
+                if !entry.file_type().is_some_and(|ft| ft.is_file()) {
                     continue;
                 }
-                let path = entry.path();
-                
-                let matches_in_file = matches_clone.clone();
-                let path_string = path.to_string_lossy().to_string();
-                
-                let _ = Searcher::new().search_path(
-                    &matcher,
-                    path,
-                    UTF8(|ln, line| {
-                        let mut m = matches_in_file.lock().unwrap();
-                        if m.len() >= 1000 {
-                            return Ok(false); 
-                        }
-                        m.push(MatchResult {
-                            path: path_string.clone(),
-                            line_number: ln,
-                            content: line.to_string(),
-                        });
-                        Ok(true)
-                    }),
-                );
-                
-                if matches_clone.lock().unwrap().len() >= 1000 {
+                let path_string = entry.path().to_string_lossy().to_string();
+                let mut sink = CollectSink {
+                    path: &path_string,
+                    matches: Vec::new(),
+                    before: Vec::new(),
+                    limit: options.max_results - found,
+                };
+                let _ = searcher.search_path(&matcher, entry.path(), &mut sink);
+
+                found += sink.matches.len();
+                pending.extend(sink.matches);
+                if found >= options.max_results {
                     break;
                 }
+                if pending.len() >= STREAM_BATCH_SIZE {
+                    on_batch(std::mem::take(&mut pending));
+                }
             }
             Err(err) => eprintln!("Error walking directory: {}", err),
         }
     }
 
-    let result = matches.lock().unwrap().clone();
-    Ok(result)
+    Ok(pending)
+}
+
+/// 收集单个文件的匹配及上下文
+struct CollectSink<'a> {
+    path: &'a str,
+    matches: Vec<MatchResult>,
+    /// 等待下一个匹配的前置上下文
+    before: Vec<String>,
+    limit: usize,
+}
+
+impl Sink for CollectSink<'_> {
+    type Error = std::io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, Self::Error> {
+        if self.matches.len() >= self.limit {
+            return Ok(false);
+        }
+        self.matches.push(MatchResult {
+            path: self.path.to_string(),
+            line_number: mat.line_number().unwrap_or(0),
+            content: String::from_utf8_lossy(mat.bytes()).to_string(),
+            context_before: std::mem::take(&mut self.before),
+            context_after: Vec::new(),
+        });
+        // 达到上限后继续到下一个匹配为止，以便收集最后一个匹配的后置上下文
+        Ok(true)
+    }
+
+    fn context(&mut self, _searcher: &Searcher, context: &SinkContext<'_>) -> Result<bool, Self::Error> {
+        let line = String::from_utf8_lossy(context.bytes()).trim_end_matches(['\n', '\r']).to_string();
+        match context.kind() {
+            SinkContextKind::Before => self.before.push(line),
+            SinkContextKind::After => {
+                if let Some(last) = self.matches.last_mut() {
+                    last.context_after.push(line);
+                }
+            }
+            SinkContextKind::Other => {}
+        }
+        Ok(true)
+    }
+}
+
+// ============================================================================
+// 搜索替换
+// ============================================================================
+
+/// 搜索替换选项
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ReplaceOptions {
+    /// 按正则匹配，替换文本中可用 `$1` / `${name}` 引用捕获组；否则按字面量匹配与替换
+    pub is_regex: bool,
+    pub case_insensitive: bool,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    /// 最多修改的文件数，默认 200
+    pub max_files: Option<usize>,
+}
+
+/// 一处修改涉及的完整行（替换前后）
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ReplaceHunk {
+    /// 起始行号（从 1 开始）
+    pub line_number: usize,
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FileReplacePreview {
+    pub path: String,
+    pub replacements: usize,
+    pub hunks: Vec<ReplaceHunk>,
+    /// 可直接加入原子写入会话的更新操作（带原始内容用于冲突检测）
+    pub operation: FileOperationRequest,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchReplacePreview {
+    pub files: Vec<FileReplacePreview>,
+    pub total_replacements: usize,
+    /// 达到文件数上限，仍有文件未列出
+    pub truncated: bool,
+}
+
+/// 预览搜索替换：返回每个文件的修改片段，不写入磁盘。
+///
+/// 确认后将各文件的 `operation` 通过 `atomic_write_add_operation` 加入会话并提交。
+#[command]
+pub async fn search_replace_in_files(
+    root_path: String,
+    query: String,
+    replacement: String,
+    options: Option<ReplaceOptions>,
+) -> Result<SearchReplacePreview, String> {
+    let options = options.unwrap_or_default();
+    tokio::task::spawn_blocking(move || search_replace_preview(&root_path, &query, &replacement, &options))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+pub fn search_replace_preview(
+    root_path: &str,
+    query: &str,
+    replacement: &str,
+    options: &ReplaceOptions,
+) -> anyhow::Result<SearchReplacePreview> {
+    if query.is_empty() {
+        anyhow::bail!("Search query is empty");
+    }
+    let pattern = if options.is_regex { query.to_string() } else { regex::escape(query) };
+    let regex = regex::RegexBuilder::new(&pattern).case_insensitive(options.case_insensitive).build()?;
+    let max_files = options.max_files.filter(|n| *n > 0).unwrap_or(DEFAULT_MAX_REPLACE_FILES);
+
+    let mut files = Vec::new();
+    let mut truncated = false;
+    for entry in build_walker(root_path, &options.include, &options.exclude)? {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                eprintln!("Error walking directory: {}", err);
+                continue;
+            }
+        };
+        if !entry.file_type().is_some_and(|ft| ft.is_file())
+            || entry.metadata().map_or(true, |m| m.len() > MAX_REPLACE_FILE_BYTES)
+        {
+            continue;
+        }
+        // 跳过二进制或非 UTF-8 文件
+        let Ok(content) = std::fs::read_to_string(entry.path()) else { continue };
+        let Some((new_content, replacements, hunks)) = replace_in_text(&regex, &content, replacement, options.is_regex) else {
+            continue;
+        };
+        if files.len() >= max_files {
+            truncated = true;
+            break;
+        }
+        let path = entry.path().to_string_lossy().to_string();
+        files.push(FileReplacePreview {
+            path: path.clone(),
+            replacements,
+            hunks,
+            operation: FileOperationRequest {
+                path,
+                op_type: FileOperationType::Update,
+                content: Some(new_content),
+                original_content: Some(content),
+            },
+        });
+    }
+
+    let total_replacements = files.iter().map(|f| f.replacements).sum();
+    println!("[Search] Replace preview for {:?}: {} replacements in {} files", query, total_replacements, files.len());
+    Ok(SearchReplacePreview { files, total_replacements, truncated })
+}
+
+/// 替换文本中的所有匹配，返回新内容、替换次数与按行合并的修改片段；无匹配时返回 `None`
+fn replace_in_text(regex: &regex::Regex, text: &str, replacement: &str, expand: bool) -> Option<(String, usize, Vec<ReplaceHunk>)> {
+    let mut new_text = String::with_capacity(text.len());
+    let mut hunks = Vec::new();
+    let mut count = 0;
+    let mut last = 0;
+    // 当前片段：(起始字节, 结束字节, 替换后的内容)
+    let mut hunk: Option<(usize, usize, String)> = None;
+
+    for caps in regex.captures_iter(text) {
+        let m = caps.get(0)?;
+        // 忽略空匹配（如 `x*`），避免在每个位置插入
+        if m.is_empty() {
+            continue;
+        }
+        let mut replaced = String::new();
+        if expand {
+            caps.expand(replacement, &mut replaced);
+        } else {
+            replaced.push_str(replacement);
+        }
+
+        let line_start = text[..m.start()].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = text[m.end()..].find('\n').map_or(text.len(), |i| m.end() + i);
+        match hunk.as_mut() {
+            // 与上一处修改在同一行（或相邻跨行匹配重叠）时合并
+            Some((_, end, after)) if line_start <= *end => {
+                after.push_str(&text[last..m.start()]);
+                after.push_str(&replaced);
+                *end = line_end;
+            }
+            _ => {
+                if let Some(done) = hunk.take() {
+                    hunks.push(finish_hunk(text, done, last));
+                }
+                hunk = Some((line_start, line_end, format!("{}{}", &text[line_start..m.start()], replaced)));
+            }
+        }
+
+        new_text.push_str(&text[last..m.start()]);
+        new_text.push_str(&replaced);
+        last = m.end();
+        count += 1;
+    }
+
+    let done = hunk?;
+    hunks.push(finish_hunk(text, done, last));
+    new_text.push_str(&text[last..]);
+    Some((new_text, count, hunks))
+}
+
+fn finish_hunk(text: &str, (start, end, mut after): (usize, usize, String), last: usize) -> ReplaceHunk {
+    after.push_str(&text[last..end]);
+    ReplaceHunk {
+        line_number: text[..start].matches('\n').count() + 1,
+        before: text[start..end].to_string(),
+        after,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grep_with_globs_and_limit() {
+        let root = std::env::temp_dir().join(format!("ifai_search_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}\nfn helper() {}\n").unwrap();
+        std::fs::write(root.join("src/app.ts"), "function main() {}\n").unwrap();
+        std::fs::write(root.join("notes.md"), "FN main\n").unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        let options = GrepOptions { include: vec!["*.rs".into()], ..Default::default() };
+        let matches = grep_search_with(&root_str, r"fn \w+", &options).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|m| m.path.ends_with("main.rs")));
+
+        let options = GrepOptions { exclude: vec!["*.ts".into()], case_insensitive: true, max_results: 1, ..Default::default() };
+        let matches = grep_search_with(&root_str, "fn main", &options).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert!(!matches[0].path.ends_with("app.ts"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_grep_word_context_and_batches() {
+        let root = std::env::temp_dir().join(format!("ifai_search_ctx_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("a.txt"), "one\ntwo\nfoo\nthree\nfoobar\nfour\n").unwrap();
+        let many: String = (0..STREAM_BATCH_SIZE + 5).map(|i| format!("foo {}\n", i)).collect();
+        std::fs::write(root.join("b.txt"), many).unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        let options = GrepOptions { include: vec!["a.txt".into()], whole_word: true, context_lines: 1, ..Default::default() };
+        let matches = grep_search_with(&root_str, "foo", &options).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line_number, 3);
+        assert_eq!((matches[0].context_before.clone(), matches[0].context_after.clone()), (vec!["two".to_string()], vec!["three".to_string()]));
+
+        let options = GrepOptions { include: vec!["a.txt".into()], ..Default::default() };
+        assert_eq!(grep_search_with(&root_str, "foo", &options).unwrap().len(), 2);
+
+        // 超过批次大小的文件先通过回调发送，剩余部分作为返回值
+        let mut batches = Vec::new();
+        let rest = grep_search_streaming(&root_str, "foo", &GrepOptions::default(), |batch| batches.push(batch.len())).unwrap();
+        assert_eq!(batches.iter().sum::<usize>() + rest.len(), STREAM_BATCH_SIZE + 5 + 2);
+        assert!(!batches.is_empty());
+
+        let options = GrepOptions { max_results: 3, context_lines: 1, ..Default::default() };
+        let matches = grep_search_with(&root_str, "foo", &options).unwrap();
+        assert_eq!(matches.len(), 3);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_search_replace_preview() {
+        let root = std::env::temp_dir().join(format!("ifai_replace_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let main = "fn old_name() {}\nfn main() { old_name(); old_name(); }\n// untouched\n";
+        std::fs::write(root.join("src/main.rs"), main).unwrap();
+        std::fs::write(root.join("src/app.ts"), "old_name.call(a.b)\n").unwrap();
+        std::fs::write(root.join("notes.md"), "nothing here\n").unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        // 字面量：`.` 不作为通配符，`$1` 原样写入
+        let options = ReplaceOptions { include: vec!["*.ts".into()], ..Default::default() };
+        let preview = search_replace_preview(&root_str, "a.b", "$1", &options).unwrap();
+        assert_eq!(preview.files.len(), 1);
+        assert_eq!(preview.files[0].operation.content.as_deref(), Some("old_name.call($1)\n"));
+
+        // 正则 + 捕获组：同一行的多处匹配合并为一个片段
+        let options = ReplaceOptions { is_regex: true, ..Default::default() };
+        let preview = search_replace_preview(&root_str, r"old_(\w+)\(\)", "new_$1()", &options).unwrap();
+        assert_eq!((preview.files.len(), preview.total_replacements), (1, 3));
+        let file = &preview.files[0];
+        assert!(file.path.ends_with("main.rs"));
+        assert_eq!(file.hunks, vec![
+            ReplaceHunk { line_number: 1, before: "fn old_name() {}".into(), after: "fn new_name() {}".into() },
+            ReplaceHunk {
+                line_number: 2,
+                before: "fn main() { old_name(); old_name(); }".into(),
+                after: "fn main() { new_name(); new_name(); }".into(),
+            },
+        ]);
+        assert_eq!(file.operation.original_content.as_deref(), Some(main));
+        assert_eq!(
+            file.operation.content.as_deref(),
+            Some("fn new_name() {}\nfn main() { new_name(); new_name(); }\n// untouched\n")
+        );
+        assert!(std::fs::read_to_string(root.join("src/main.rs")).unwrap() == main);
+
+        // 跨行匹配与文件数上限
+        let options = ReplaceOptions { is_regex: true, case_insensitive: true, max_files: Some(1), ..Default::default() };
+        let preview = search_replace_preview(&root_str, r"\{\}\nFN", "{}\n\nfn", &options).unwrap();
+        assert_eq!(preview.files[0].hunks[0].before, "fn old_name() {}\nfn main() { old_name(); old_name(); }");
+        assert!(!preview.truncated);
+        let preview = search_replace_preview(&root_str, "old_name", "x", &options).unwrap();
+        assert!(preview.truncated);
+        assert!(search_replace_preview(&root_str, "", "x", &options).is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
 }
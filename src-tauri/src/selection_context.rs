@@ -0,0 +1,212 @@
+/*!
+Selection Context - 编辑器选区上下文
+====================================
+
+支持“解释选中的代码”这类对话：
+
+- 选区统一保存在 `EditorContextState` 中（前端通过 `set_editor_context` 上报，
+  或调用 `set_active_selection(path, range, text_hash)`：后端读取文件并校验哈希后写入同一状态，
+  `text_hash` 为选中文本的 MD5 十六进制）
+- 消息没有显式 `@selection`，但提到“这段代码 / this code / 选中”等时，重新读取文件，
+  内容未变才注入选区及其所在符号（来自符号索引）；注入由 `editor_context::context_for_message` 统一完成
+- 选区在被替换、清除或编辑器状态超过 `SELECTION_TTL_SECS` 未更新后失效
+*/
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::commands::symbol_commands::{Symbol, SymbolIndexState};
+use crate::editor_context::{EditorContext, EditorContextState, EditorSelection};
+
+/// 选区有效期
+const SELECTION_TTL_SECS: i64 = 30 * 60;
+/// 注入的选区最大行数
+const MAX_SELECTION_LINES: usize = 300;
+
+/// 选区范围：行号从 1 开始（含结束行），列为字符偏移（从 0 开始，可选）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SelectionRange {
+    pub start_line: usize,
+    pub end_line: usize,
+    #[serde(default)]
+    pub start_column: Option<usize>,
+    #[serde(default)]
+    pub end_column: Option<usize>,
+}
+
+pub fn hash_text(text: &str) -> String {
+    format!("{:x}", md5::compute(text))
+}
+
+/// 按范围截取文本
+fn extract_range(content: &str, range: &SelectionRange) -> Option<String> {
+    if range.start_line == 0 || range.end_line < range.start_line {
+        return None;
+    }
+    let lines: Vec<&str> = content.lines().collect();
+    if range.end_line > lines.len() {
+        return None;
+    }
+    let mut selected: Vec<String> = lines[range.start_line - 1..range.end_line].iter().map(|l| l.to_string()).collect();
+    if let Some(end) = range.end_column {
+        let last = selected.last_mut()?;
+        *last = last.chars().take(end).collect();
+    }
+    if let Some(start) = range.start_column {
+        let first = selected.first_mut()?;
+        *first = first.chars().skip(start).collect();
+    }
+    Some(selected.join("\n"))
+}
+
+/// 消息是否在指代选中的代码
+pub fn references_selection(message: &str) -> bool {
+    let re = Regex::new(
+        r"(?i)\b(this|these|selected|highlighted) (code|function|method|snippet|lines?|block|class)\b|\bselection\b|这段|这几行|选中|这个函数|这个方法|这里的代码",
+    ).unwrap();
+    re.is_match(message)
+}
+
+/// 覆盖选区的最小符号
+fn enclosing_symbol<'a>(symbols: &'a [Symbol], range: &SelectionRange) -> Option<&'a Symbol> {
+    symbols.iter()
+        .filter(|s| s.line as usize <= range.start_line && s.end_line.is_some_and(|e| e as usize >= range.end_line))
+        .min_by_key(|s| s.end_line.unwrap_or(s.line) - s.line)
+}
+
+fn language_for(path: &str) -> &str {
+    match std::path::Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("") {
+        "rs" => "rust",
+        "ts" | "tsx" => "typescript",
+        other => other,
+    }
+}
+
+/// 校验并渲染当前选区，内容已变化或过期时返回错误
+fn render_selection(path: &str, selection: &EditorSelection, set_at: i64, index: Option<&SymbolIndexState>, now: i64) -> Result<String, String> {
+    if now - set_at > SELECTION_TTL_SECS {
+        return Err("selection expired".to_string());
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    let text = extract_range(&content, &selection.range).ok_or("selection range out of bounds")?;
+    if text != selection.text {
+        return Err("file changed since selection".to_string());
+    }
+
+    let symbol = match index.and_then(|idx| idx.file_symbols(path)) {
+        Some(fs) => enclosing_symbol(&fs.symbols, &selection.range).map(|s| format!("{} ({})", s.qualified_name, s.kind)),
+        None => {
+            // 未索引的文件直接解析
+            let line = selection.range.start_line - 1;
+            crate::symbol_engine::extract_symbols_from_source(&content, language_for(path))
+                .into_iter()
+                .filter(|s| s.range.start_line <= line && selection.range.end_line - 1 <= s.range.end_line)
+                .min_by_key(|s| s.range.end_line - s.range.start_line)
+                .map(|s| format!("{} ({})", s.name, s.kind))
+        }
+    };
+
+    let body: String = text.lines().take(MAX_SELECTION_LINES).collect::<Vec<_>>().join("\n");
+    let mut out = format!(
+        "# Selected Code\nThe user is referring to this selection in `{}` (lines {}-{})",
+        path, selection.range.start_line, selection.range.end_line
+    );
+    if let Some(symbol) = symbol {
+        out.push_str(&format!(", inside {}", symbol));
+    }
+    out.push_str(&format!(":\n```{}\n{}\n```", language_for(path), body));
+    Ok(out)
+}
+
+/// 消息指代编辑器状态中的选区时返回要注入的上下文
+pub fn context_for_message(message: &str, editor: &EditorContext, index: Option<&SymbolIndexState>) -> Option<String> {
+    if !references_selection(message) {
+        return None;
+    }
+    let selection = editor.selection.as_ref()?;
+    let path = editor.file_path.as_deref()?;
+    match render_selection(path, selection, editor.updated_at, index, chrono::Utc::now().timestamp()) {
+        Ok(context) => {
+            println!("[SelectionContext] Injecting selection from {}", path);
+            Some(context)
+        }
+        Err(e) => {
+            println!("[SelectionContext] Skipping selection: {}", e);
+            None
+        }
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 按位置与哈希记录当前编辑器选区：读取文件中的选中文本，校验后写入编辑器状态
+#[tauri::command]
+pub fn set_active_selection(
+    state: State<'_, EditorContextState>,
+    path: String,
+    range: SelectionRange,
+    text_hash: String,
+) -> Result<(), String> {
+    if range.start_line == 0 || range.end_line < range.start_line {
+        return Err(format!("Invalid selection range: {}-{}", range.start_line, range.end_line));
+    }
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let text = extract_range(&content, &range).ok_or("Selection range out of bounds")?;
+    if hash_text(&text) != text_hash.to_lowercase() {
+        return Err("Selection does not match the file on disk".to_string());
+    }
+    state.update(|editor| {
+        editor.file_path = Some(path);
+        editor.selection = Some(EditorSelection { text, range });
+    })
+}
+
+#[tauri::command]
+pub fn clear_active_selection(state: State<'_, EditorContextState>) -> Result<(), String> {
+    state.update(|editor| editor.selection = None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(start_line: usize, end_line: usize) -> SelectionRange {
+        SelectionRange { start_line, end_line, start_column: None, end_column: None }
+    }
+
+    #[test]
+    fn test_extract_range_with_columns() {
+        let content = "fn a() {\n    let x = 1;\n    x + 1\n}\n";
+        assert_eq!(extract_range(content, &range(2, 3)).unwrap(), "    let x = 1;\n    x + 1");
+        let partial = SelectionRange { start_line: 2, end_line: 2, start_column: Some(8), end_column: Some(13) };
+        assert_eq!(extract_range(content, &partial).unwrap(), "x = 1");
+        assert!(extract_range(content, &range(3, 9)).is_none());
+    }
+
+    #[test]
+    fn test_references_selection() {
+        assert!(references_selection("Can you explain this code?"));
+        assert!(references_selection("这段代码有什么问题"));
+        assert!(!references_selection("How do I configure the build?"));
+    }
+
+    #[test]
+    fn test_render_selection_checks_content() {
+        let path = std::env::temp_dir().join(format!("ifai_sel_{}.rs", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").unwrap();
+        let file = path.to_string_lossy().to_string();
+        let selection = EditorSelection { text: "    a + b".to_string(), range: range(2, 2) };
+
+        let context = render_selection(&file, &selection, 100, None, 100).unwrap();
+        assert!(context.contains("inside add (function_item)"));
+        assert!(context.contains("    a + b"));
+
+        std::fs::write(&path, "pub fn add(a: i32, b: i32) -> i32 {\n    a - b\n}\n").unwrap();
+        assert!(render_selection(&file, &selection, 100, None, 100).is_err());
+        assert!(render_selection(&file, &selection, 100, None, 100 + SELECTION_TTL_SECS + 1).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,531 @@
+/*!
+Slash Command Framework - 聊天斜杠命令
+======================================
+
+统一管理聊天中的斜杠命令（/read、/explore、/test、/commit 以及 `.ifai/commands/` 中的自定义命令）：
+- 注册表：内置命令 + 项目自定义命令
+- 参数解析：支持引号包裹的参数
+- 帮助文本生成
+- 执行路径：
+  - Direct：确定性命令（如 /read）直接在后端执行，不经过 LLM
+  - Prompt：展开为提示词后交给 LLM
+  - Agent：启动对应类型的 Agent
+
+自定义命令文件格式（`.ifai/commands/review-pr.md`）：
+
+```markdown
+---
+name: review-pr
+description: Review the current branch
+usage: /review-pr <base>
+agent: review
+---
+Review the changes between {{arg1}} and HEAD. Extra notes: {{args}}
+```
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::tool_classification::ToolCategory;
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 命令执行方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlashExecution {
+    /// 后端直接执行确定性工具
+    Direct,
+    /// 展开为提示词交给 LLM（`{{args}}`、`{{arg1}}`... 为参数占位符）
+    Prompt { template: String },
+    /// 启动 Agent，参数作为任务描述
+    Agent { agent_type: String, template: Option<String> },
+}
+
+/// 斜杠命令定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlashCommandSpec {
+    /// 命令名（不含 `/`）
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub description: String,
+    pub usage: String,
+    pub category: ToolCategory,
+    /// 对应的工具名（用于工具分类 Layer 1）
+    pub tool: Option<String>,
+    /// 是否至少需要一个参数
+    pub requires_args: bool,
+    pub execution: SlashExecution,
+    /// "builtin" 或自定义命令文件路径
+    pub source: String,
+}
+
+/// 解析后的斜杠命令
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParsedSlashCommand {
+    pub name: String,
+    /// 命令名之后的原始参数文本
+    pub raw_args: String,
+    /// 按空白切分（支持引号）的参数
+    pub args: Vec<String>,
+}
+
+/// 命令执行结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SlashCommandOutcome {
+    /// 已在后端执行完成，`output` 可直接展示
+    Output { command: String, output: String },
+    /// 需要交给 LLM 的提示词
+    Prompt { command: String, prompt: String },
+    /// 需要启动的 Agent
+    Agent { command: String, agent_type: String, task: String },
+}
+
+/// 自定义命令文件的 front matter
+#[derive(Debug, Clone, Deserialize)]
+struct CustomCommandMeta {
+    name: Option<String>,
+    description: Option<String>,
+    usage: Option<String>,
+    agent: Option<String>,
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+/// 按空白切分参数，支持单/双引号
+fn split_args(raw: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let mut has_token = false;
+
+    for c in raw.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                has_token = true;
+            }
+            None if c.is_whitespace() => {
+                if has_token {
+                    args.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            None => {
+                current.push(c);
+                has_token = true;
+            }
+        }
+    }
+    if has_token {
+        args.push(current);
+    }
+    args
+}
+
+/// 解析 `/name args...` 格式的输入
+pub fn parse_slash_command(input: &str) -> Option<ParsedSlashCommand> {
+    let input = input.trim();
+    let body = input.strip_prefix('/')?;
+    let (name, rest) = match body.find(char::is_whitespace) {
+        Some(idx) => (&body[..idx], body[idx..].trim()),
+        None => (body, ""),
+    };
+
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_') {
+        return None;
+    }
+
+    Some(ParsedSlashCommand {
+        name: name.to_lowercase(),
+        raw_args: rest.to_string(),
+        args: split_args(rest),
+    })
+}
+
+/// 用参数填充模板：`{{args}}` 为完整参数，`{{arg1}}`、`{{arg2}}`... 为位置参数
+fn render_args(template: &str, parsed: &ParsedSlashCommand) -> String {
+    let mut out = template.replace("{{args}}", &parsed.raw_args);
+    for (i, arg) in parsed.args.iter().enumerate() {
+        out = out.replace(&format!("{{{{arg{}}}}}", i + 1), arg);
+    }
+    out
+}
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+/// 斜杠命令注册表
+#[derive(Debug, Clone)]
+pub struct SlashCommandRegistry {
+    commands: Vec<SlashCommandSpec>,
+}
+
+impl SlashCommandSpec {
+    /// 创建内置命令（无别名、无对应工具、参数可选），其余字段通过链式方法设置
+    fn builtin(name: &str, description: &str, usage: &str, category: ToolCategory, execution: SlashExecution) -> Self {
+        Self {
+            name: name.to_string(),
+            aliases: Vec::new(),
+            description: description.to_string(),
+            usage: usage.to_string(),
+            category,
+            tool: None,
+            requires_args: false,
+            execution,
+            source: "builtin".to_string(),
+        }
+    }
+
+    fn aliases(mut self, aliases: &[&str]) -> Self {
+        self.aliases = aliases.iter().map(|a| a.to_string()).collect();
+        self
+    }
+
+    fn tool(mut self, tool: &str) -> Self {
+        self.tool = Some(tool.to_string());
+        self
+    }
+
+    fn requires_args(mut self) -> Self {
+        self.requires_args = true;
+        self
+    }
+}
+
+impl SlashCommandRegistry {
+    /// 仅包含内置命令
+    pub fn builtin() -> Self {
+        let commands = vec![
+            SlashCommandSpec::builtin("read", "Read a file", "/read <path>",
+                ToolCategory::FileOperations, SlashExecution::Direct)
+                .tool("agent_read_file")
+                .requires_args(),
+            SlashCommandSpec::builtin("explore", "List a directory", "/explore [path]",
+                ToolCategory::FileOperations, SlashExecution::Direct)
+                .aliases(&["scan"])
+                .tool("agent_list_dir"),
+            SlashCommandSpec::builtin("list", "List a directory", "/list [path]",
+                ToolCategory::FileOperations, SlashExecution::Direct)
+                .tool("agent_list_dir"),
+            SlashCommandSpec::builtin("search", "Search the project for a pattern", "/search <pattern>",
+                ToolCategory::SearchOperations, SlashExecution::Direct)
+                .aliases(&["find"])
+                .tool("agent_search")
+                .requires_args(),
+            SlashCommandSpec::builtin("test", "Write or run tests for a target", "/test [target]",
+                ToolCategory::CodeGeneration,
+                SlashExecution::Agent {
+                    agent_type: "test".to_string(),
+                    template: Some("Write and run tests for: {{args}}".to_string()),
+                }),
+            SlashCommandSpec::builtin("commit", "Draft a commit message for the staged changes", "/commit [notes]",
+                ToolCategory::TerminalCommands,
+                SlashExecution::Prompt {
+                    template: "Run `git diff --cached` to inspect the staged changes and draft a concise commit message. Notes from the user: {{args}}".to_string(),
+                }),
+            SlashCommandSpec::builtin("help", "Show available slash commands", "/help [command]",
+                ToolCategory::AiChat, SlashExecution::Direct)
+                .tool("help"),
+        ];
+        Self { commands }
+    }
+
+    /// 内置命令 + 项目 `.ifai/commands/*.md` 中的自定义命令（同名时自定义命令覆盖内置）
+    pub fn for_project(project_root: &str) -> Self {
+        let mut registry = Self::builtin();
+        let dir = Path::new(project_root).join(".ifai").join("commands");
+        if let Ok(entries) = std::fs::read_dir(&dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "md") {
+                    match std::fs::read_to_string(&path) {
+                        Ok(content) => {
+                            let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                            if let Some(spec) = parse_custom_command(&stem, &content, &path.to_string_lossy()) {
+                                registry.register(spec);
+                            } else {
+                                eprintln!("[SlashCommands] Invalid custom command file: {:?}", path);
+                            }
+                        }
+                        Err(e) => eprintln!("[SlashCommands] Failed to read {:?}: {}", path, e),
+                    }
+                }
+            }
+        }
+        registry
+    }
+
+    /// 注册命令（同名替换）
+    pub fn register(&mut self, spec: SlashCommandSpec) {
+        self.commands.retain(|c| c.name != spec.name);
+        self.commands.push(spec);
+    }
+
+    /// 按名称或别名查找
+    pub fn find(&self, name: &str) -> Option<&SlashCommandSpec> {
+        let name = name.trim_start_matches('/').to_lowercase();
+        self.commands.iter().find(|c| c.name == name || c.aliases.contains(&name))
+    }
+
+    pub fn commands(&self) -> &[SlashCommandSpec] {
+        &self.commands
+    }
+
+    /// 生成帮助文本
+    pub fn help_text(&self, command: Option<&str>) -> String {
+        if let Some(name) = command.filter(|c| !c.is_empty()) {
+            return match self.find(name) {
+                Some(spec) => {
+                    let mut text = format!("{}\n  {}", spec.usage, spec.description);
+                    if !spec.aliases.is_empty() {
+                        text.push_str(&format!("\n  Aliases: {}", spec.aliases.iter().map(|a| format!("/{}", a)).collect::<Vec<_>>().join(", ")));
+                    }
+                    text
+                }
+                None => format!("Unknown command: /{}", name.trim_start_matches('/')),
+            };
+        }
+
+        let width = self.commands.iter().map(|c| c.usage.len()).max().unwrap_or(0);
+        let mut lines = vec!["Available commands:".to_string()];
+        for spec in &self.commands {
+            lines.push(format!("  {:width$}  {}", spec.usage, spec.description, width = width));
+        }
+        lines.join("\n")
+    }
+}
+
+/// 解析自定义命令文件
+fn parse_custom_command(stem: &str, content: &str, source: &str) -> Option<SlashCommandSpec> {
+    let trimmed = content.trim_start();
+    let (meta, body) = match trimmed.strip_prefix("---") {
+        Some(rest) => {
+            let end = rest.find("---")?;
+            let meta: CustomCommandMeta = serde_yaml::from_str(rest[..end].trim()).ok()?;
+            (Some(meta), rest[end + 3..].trim().to_string())
+        }
+        None => (None, trimmed.trim().to_string()),
+    };
+
+    let name = meta.as_ref().and_then(|m| m.name.clone()).unwrap_or_else(|| stem.to_string()).to_lowercase();
+    if name.is_empty() || body.is_empty() {
+        return None;
+    }
+
+    let execution = match meta.as_ref().and_then(|m| m.agent.clone()) {
+        Some(agent_type) => SlashExecution::Agent { agent_type, template: Some(body.clone()) },
+        None => SlashExecution::Prompt { template: body.clone() },
+    };
+
+    Some(SlashCommandSpec {
+        usage: meta.as_ref().and_then(|m| m.usage.clone()).unwrap_or_else(|| format!("/{}", name)),
+        description: meta.as_ref().and_then(|m| m.description.clone()).unwrap_or_else(|| "Custom command".to_string()),
+        aliases: meta.map(|m| m.aliases).unwrap_or_default(),
+        category: ToolCategory::AiChat,
+        tool: None,
+        requires_args: body.contains("{{arg1}}"),
+        execution,
+        source: source.to_string(),
+        name,
+    })
+}
+
+// ============================================================================
+// Execution
+// ============================================================================
+
+/// 执行斜杠命令
+///
+/// Direct 命令直接返回结果；Prompt / Agent 命令返回展开后的内容，由调用方交给 LLM 或 Agent。
+pub async fn execute(registry: &SlashCommandRegistry, project_root: &str, input: &str) -> Result<SlashCommandOutcome, String> {
+    let parsed = parse_slash_command(input).ok_or("Not a slash command")?;
+    let spec = registry.find(&parsed.name)
+        .ok_or_else(|| format!("Unknown command: /{}. Type /help to list commands.", parsed.name))?;
+
+    if spec.requires_args && parsed.args.is_empty() {
+        return Err(format!("Missing arguments. Usage: {}", spec.usage));
+    }
+
+    let command = spec.name.clone();
+    println!("[SlashCommands] Executing /{} ({:?})", command, parsed.args);
+
+    match &spec.execution {
+        SlashExecution::Direct => {
+            let output = match spec.name.as_str() {
+                "help" => registry.help_text(parsed.args.first().map(|s| s.as_str())),
+                "search" => {
+                    let root = project_root.to_string();
+                    let query = parsed.raw_args.clone();
+                    let matches = tokio::task::spawn_blocking(move || crate::search::grep_search(&root, &query))
+                        .await
+                        .map_err(|e| e.to_string())?
+                        .map_err(|e| e.to_string())?;
+                    matches.iter()
+                        .take(100)
+                        .map(|m| format!("{}:{}: {}", m.path, m.line_number, m.content.trim_end()))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                }
+                _ => {
+                    let tool = spec.tool.clone().ok_or_else(|| format!("/{} has no direct executor", command))?;
+                    let rel_path = parsed.args.first().cloned().unwrap_or_else(|| ".".to_string());
+                    crate::execute_local_tool(&tool, &serde_json::json!({ "rel_path": rel_path }), project_root).await
+                }
+            };
+            Ok(SlashCommandOutcome::Output { command, output })
+        }
+        SlashExecution::Prompt { template } => Ok(SlashCommandOutcome::Prompt {
+            command,
+            prompt: render_args(template, &parsed),
+        }),
+        SlashExecution::Agent { agent_type, template } => Ok(SlashCommandOutcome::Agent {
+            command,
+            agent_type: agent_type.clone(),
+            task: template.as_ref().map(|t| render_args(t, &parsed)).unwrap_or_else(|| parsed.raw_args.clone()),
+        }),
+    }
+}
+
+/// 将斜杠命令映射为工具调用（供本地模型预处理使用）
+///
+/// 仅对 Direct 且对应单一工具的命令返回 `(tool, args)`。
+pub fn to_tool_call(input: &str) -> Option<(String, HashMap<String, String>)> {
+    let parsed = parse_slash_command(input)?;
+    let registry = SlashCommandRegistry::builtin();
+    let spec = registry.find(&parsed.name)?;
+    let tool = spec.tool.clone()?;
+    if spec.execution != SlashExecution::Direct || !tool.starts_with("agent_") || tool == "agent_search" {
+        return None;
+    }
+
+    let mut args = HashMap::new();
+    args.insert("rel_path".to_string(), parsed.args.first().cloned().unwrap_or_else(|| ".".to_string()));
+    Some((tool, args))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 列出可用的斜杠命令
+#[tauri::command]
+pub async fn list_slash_commands(project_root: Option<String>) -> Result<Vec<SlashCommandSpec>, String> {
+    let registry = match project_root {
+        Some(root) => SlashCommandRegistry::for_project(&root),
+        None => SlashCommandRegistry::builtin(),
+    };
+    Ok(registry.commands().to_vec())
+}
+
+/// 获取帮助文本
+#[tauri::command]
+pub async fn slash_command_help(project_root: Option<String>, command: Option<String>) -> Result<String, String> {
+    let registry = match project_root {
+        Some(root) => SlashCommandRegistry::for_project(&root),
+        None => SlashCommandRegistry::builtin(),
+    };
+    Ok(registry.help_text(command.as_deref()))
+}
+
+/// 执行斜杠命令
+#[tauri::command]
+pub async fn execute_slash_command(project_root: String, input: String) -> Result<SlashCommandOutcome, String> {
+    let registry = SlashCommandRegistry::for_project(&project_root);
+    execute(&registry, &project_root, &input).await
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_slash_command() {
+        let parsed = parse_slash_command("/read \"src/my file.rs\" extra").unwrap();
+        assert_eq!(parsed.name, "read");
+        assert_eq!(parsed.args, vec!["src/my file.rs", "extra"]);
+        assert_eq!(parsed.raw_args, "\"src/my file.rs\" extra");
+
+        let parsed = parse_slash_command("/HELP").unwrap();
+        assert_eq!(parsed.name, "help");
+        assert!(parsed.args.is_empty());
+
+        assert!(parse_slash_command("no slash").is_none());
+        assert!(parse_slash_command("/").is_none());
+        assert!(parse_slash_command("/usr/bin/env").is_none());
+    }
+
+    #[test]
+    fn test_registry_aliases_and_help() {
+        let registry = SlashCommandRegistry::builtin();
+        assert_eq!(registry.find("scan").unwrap().name, "explore");
+        assert_eq!(registry.find("/find").unwrap().name, "search");
+        assert!(registry.find("unknown").is_none());
+
+        let help = registry.help_text(None);
+        assert!(help.contains("/read <path>"));
+        assert!(registry.help_text(Some("scan")).contains("/explore [path]"));
+    }
+
+    #[test]
+    fn test_parse_custom_command() {
+        let content = "---\nname: review-pr\ndescription: Review branch\nagent: review\n---\nReview {{arg1}} vs HEAD. {{args}}";
+        let spec = parse_custom_command("file", content, "x.md").unwrap();
+        assert_eq!(spec.name, "review-pr");
+        assert!(spec.requires_args);
+
+        let parsed = parse_slash_command("/review-pr main quickly").unwrap();
+        match &spec.execution {
+            SlashExecution::Agent { agent_type, template } => {
+                assert_eq!(agent_type, "review");
+                assert_eq!(render_args(template.as_ref().unwrap(), &parsed), "Review main vs HEAD. main quickly");
+            }
+            other => panic!("unexpected execution: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_to_tool_call() {
+        let (tool, args) = to_tool_call("/explore src").unwrap();
+        assert_eq!(tool, "agent_list_dir");
+        assert_eq!(args.get("rel_path").unwrap(), "src");
+
+        let (tool, args) = to_tool_call("/read").unwrap();
+        assert_eq!(tool, "agent_read_file");
+        assert_eq!(args.get("rel_path").unwrap(), ".");
+
+        assert!(to_tool_call("/commit").is_none());
+        assert!(to_tool_call("/search foo").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_execute_prompt_and_errors() {
+        let registry = SlashCommandRegistry::builtin();
+        match execute(&registry, ".", "/commit fix typo").await.unwrap() {
+            SlashCommandOutcome::Prompt { command, prompt } => {
+                assert_eq!(command, "commit");
+                assert!(prompt.contains("fix typo"));
+            }
+            other => panic!("unexpected outcome: {:?}", other),
+        }
+
+        assert!(execute(&registry, ".", "/read").await.is_err());
+        assert!(execute(&registry, ".", "/nope").await.is_err());
+    }
+}
@@ -0,0 +1,174 @@
+/*!
+Symbol Accuracy - 符号提取准确率评测
+===================================
+
+对 `tests/v0.2.8/fixtures` 下的 Rust / TypeScript / Python 样例项目运行符号提取引擎，
+与各项目的 `symbols.golden.json` 逐条比对（按 文件 + 行号 + 类型 + 名称 匹配），
+按语言输出 precision / recall 以及漏报、误报清单。
+
+黄金文件记录的是“应当提取”的符号，而不是引擎当前的输出；`baseline` 为当前达到的
+准确率，评测结果低于基线即失败。改进提取引擎后同步提高基线。
+*/
+
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::symbol_engine::extract_symbols_from_source;
+
+const GOLDEN_FILE: &str = "symbols.golden.json";
+const FIXTURES: [&str; 3] = ["symbol_project", "symbol_project_ts", "symbol_project_py"];
+
+#[derive(Debug, Deserialize)]
+struct Baseline {
+    precision: f64,
+    recall: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoldenFile {
+    language: String,
+    baseline: Baseline,
+    symbols: Vec<GoldenSymbol>,
+}
+
+/// 行号从 1 开始，与编辑器一致
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+struct GoldenSymbol {
+    file: String,
+    line: usize,
+    kind: String,
+    name: String,
+}
+
+impl std::fmt::Display for GoldenSymbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{} {} {}", self.file, self.line, self.kind, self.name)
+    }
+}
+
+#[derive(Debug)]
+struct AccuracyReport {
+    language: String,
+    expected: usize,
+    matched: usize,
+    missing: Vec<GoldenSymbol>,
+    unexpected: Vec<GoldenSymbol>,
+}
+
+impl AccuracyReport {
+    /// 没有提取出任何符号时记为 1.0（没有误报）
+    fn precision(&self) -> f64 {
+        ratio(self.matched, self.matched + self.unexpected.len())
+    }
+
+    fn recall(&self) -> f64 {
+        ratio(self.matched, self.expected)
+    }
+
+    fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "[SymbolAccuracy] {:<10} precision {:.3}  recall {:.3}  ({}/{} matched, {} unexpected)",
+            self.language, self.precision(), self.recall(), self.matched, self.expected, self.unexpected.len()
+        )];
+        lines.extend(self.missing.iter().map(|s| format!("  - missing    {}", s)));
+        lines.extend(self.unexpected.iter().map(|s| format!("  + unexpected {}", s)));
+        lines.join("\n")
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 { 1.0 } else { numerator as f64 / denominator as f64 }
+}
+
+fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests/v0.2.8/fixtures")
+}
+
+fn language_for(path: &Path) -> Option<&'static str> {
+    match path.extension()?.to_str()? {
+        "rs" => Some("rust"),
+        "ts" => Some("typescript"),
+        "tsx" => Some("tsx"),
+        "py" => Some("python"),
+        _ => None,
+    }
+}
+
+/// 提取项目中所有源文件的符号（按路径排序，保证结果稳定）
+fn extract_project(root: &Path) -> BTreeSet<GoldenSymbol> {
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(root)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| p.is_file() && language_for(p).is_some())
+        .collect();
+    files.sort();
+
+    let mut symbols = BTreeSet::new();
+    for path in files {
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        let file = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        for symbol in extract_symbols_from_source(&content, language_for(&path).unwrap_or_default()) {
+            symbols.insert(GoldenSymbol {
+                file: file.clone(),
+                line: symbol.range.start_line + 1,
+                kind: symbol.kind,
+                name: symbol.name,
+            });
+        }
+    }
+    symbols
+}
+
+fn evaluate(root: &Path) -> Result<(AccuracyReport, Baseline), String> {
+    let golden_path = root.join(GOLDEN_FILE);
+    let content = std::fs::read_to_string(&golden_path).map_err(|e| format!("Failed to read {}: {}", golden_path.display(), e))?;
+    let golden: GoldenFile = serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", golden_path.display(), e))?;
+
+    let expected: BTreeSet<GoldenSymbol> = golden.symbols.into_iter().collect();
+    let extracted = extract_project(root);
+    let report = AccuracyReport {
+        language: golden.language,
+        expected: expected.len(),
+        matched: expected.intersection(&extracted).count(),
+        missing: expected.difference(&extracted).cloned().collect(),
+        unexpected: extracted.difference(&expected).cloned().collect(),
+    };
+    Ok((report, golden.baseline))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_accuracy_against_golden() {
+        let mut regressions = Vec::new();
+        for fixture in FIXTURES {
+            let (report, baseline) = evaluate(&fixtures_dir().join(fixture)).unwrap();
+            println!("{}", report.summary());
+            // 浮点比较留出舍入余量
+            if report.precision() + 1e-9 < baseline.precision || report.recall() + 1e-9 < baseline.recall {
+                regressions.push(format!(
+                    "{}: precision {:.3} (baseline {:.3}), recall {:.3} (baseline {:.3})",
+                    report.language, report.precision(), baseline.precision, report.recall(), baseline.recall
+                ));
+            }
+        }
+        assert!(regressions.is_empty(), "Symbol accuracy regressed:\n{}", regressions.join("\n"));
+    }
+
+    #[test]
+    fn test_report_metrics() {
+        let symbol = |line| GoldenSymbol { file: "a.rs".into(), line, kind: "function_item".into(), name: "f".into() };
+        let report = AccuracyReport { language: "rust".into(), expected: 4, matched: 3, missing: vec![symbol(1)], unexpected: vec![symbol(9)] };
+        assert_eq!(report.precision(), 0.75);
+        assert_eq!(report.recall(), 0.75);
+        assert!(report.summary().contains("  - missing    a.rs:1 function_item f"));
+
+        let empty = AccuracyReport { language: "python".into(), expected: 2, matched: 0, missing: vec![], unexpected: vec![] };
+        assert_eq!(empty.precision(), 1.0);
+        assert_eq!(empty.recall(), 0.0);
+    }
+}
@@ -16,6 +16,22 @@ pub struct SymbolRange {
     pub end_col: usize,
 }
 
+/// Rust / TypeScript 的符号节点类型
+const RUST_TS_KINDS: &[&str] = &[
+    "struct_item", "enum_item", "trait_item", "function_item", "impl_item",
+    "class_declaration", "method_definition", "function_declaration", "interface_declaration",
+];
+const GO_KINDS: &[&str] = &["function_declaration", "method_declaration", "type_spec"];
+const JAVA_KINDS: &[&str] = &[
+    "class_declaration", "interface_declaration", "enum_declaration", "record_declaration",
+    "method_declaration", "constructor_declaration",
+];
+const C_KINDS: &[&str] = &["function_definition", "struct_specifier", "union_specifier", "enum_specifier", "type_definition"];
+const CPP_KINDS: &[&str] = &[
+    "function_definition", "class_specifier", "struct_specifier", "union_specifier", "enum_specifier",
+    "type_definition", "namespace_definition",
+];
+
 pub struct SymbolEngine {
     parser: Parser,
 }
@@ -29,9 +45,13 @@ impl SymbolEngine {
 
     /// 根据语言标识提取符号
     pub fn extract_symbols(&mut self, content: &str, language_id: &str) -> Vec<Symbol> {
-        let lang = match language_id {
-            "rust" => tree_sitter_rust::LANGUAGE.into(),
-            "typescript" | "tsx" => tree_sitter_typescript::LANGUAGE_TSX.into(),
+        let (lang, kinds): (Language, &[&str]) = match language_id {
+            "rust" => (tree_sitter_rust::LANGUAGE.into(), RUST_TS_KINDS),
+            "typescript" | "tsx" => (tree_sitter_typescript::LANGUAGE_TSX.into(), RUST_TS_KINDS),
+            "go" => (tree_sitter_go::LANGUAGE.into(), GO_KINDS),
+            "java" => (tree_sitter_java::LANGUAGE.into(), JAVA_KINDS),
+            "c" => (tree_sitter_c::LANGUAGE.into(), C_KINDS),
+            "cpp" => (tree_sitter_cpp::LANGUAGE.into(), CPP_KINDS),
             _ => return Vec::new(),
         };
 
@@ -40,46 +60,124 @@ impl SymbolEngine {
         let root_node = tree.root_node();
 
         let mut symbols = Vec::new();
-        self.traverse(root_node, content, &mut symbols);
+        self.traverse(root_node, content, kinds, &mut symbols);
         symbols
     }
 
-    fn traverse(&self, node: tree_sitter::Node, source: &str, symbols: &mut Vec<Symbol>) {
+    fn traverse(&self, node: tree_sitter::Node, source: &str, kinds: &[&str], symbols: &mut Vec<Symbol>) {
         let kind = node.kind();
         
         // 识别核心符号类型
-        match kind {
-            "struct_item" | "enum_item" | "trait_item" | "function_item" | "impl_item" |
-            "class_declaration" | "method_definition" | "function_declaration" | "interface_declaration" => {
-                if let Some(name_node) = node.child_by_field_name("name") {
-                    let name = &source[name_node.start_byte()..name_node.end_byte()];
-                    let range = node.range();
-                    
-                    symbols.push(Symbol {
-                        name: name.to_string(),
-                        kind: kind.to_string(),
-                        range: SymbolRange {
-                            start_line: range.start_point.row,
-                            start_col: range.start_point.column,
-                            end_line: range.end_point.row,
-                            end_col: range.end_point.column,
-                        },
-                    });
-                }
+        if kinds.contains(&kind) {
+            if let Some(name_node) = name_node(node) {
+                let name = &source[name_node.start_byte()..name_node.end_byte()];
+                let range = node.range();
+
+                symbols.push(Symbol {
+                    name: name.to_string(),
+                    kind: kind.to_string(),
+                    range: SymbolRange {
+                        start_line: range.start_point.row,
+                        start_col: range.start_point.column,
+                        end_line: range.end_point.row,
+                        end_col: range.end_point.column,
+                    },
+                });
             }
-            _ => {}
         }
 
         // 递归遍历子节点
         let mut cursor = node.walk();
         for child in node.children(&mut cursor) {
-            self.traverse(child, source, symbols);
+            self.traverse(child, source, kinds, symbols);
         }
     }
 }
 
+/// 符号名所在节点
+fn name_node(node: tree_sitter::Node) -> Option<tree_sitter::Node> {
+    match node.kind() {
+        // C/C++ 的函数名和 typedef 名在声明符中：`int *foo(void)`、`Foo &Foo::get()`
+        "function_definition" | "type_definition" => {
+            let mut declarator = node.child_by_field_name("declarator")?;
+            loop {
+                if let Some(inner) = declarator.child_by_field_name("declarator") {
+                    declarator = inner;
+                } else if declarator.kind() == "reference_declarator" {
+                    declarator = declarator.named_child(0)?;
+                } else {
+                    break;
+                }
+            }
+            // `ns::Foo::bar` 取最后一段
+            while let Some(name) = declarator.child_by_field_name("name") {
+                declarator = name;
+            }
+            Some(declarator)
+        }
+        // 只有带定义体的才是定义，`struct foo *p;` 只是使用
+        "struct_specifier" | "class_specifier" | "union_specifier" | "enum_specifier" if node.child_by_field_name("body").is_none() => None,
+        _ => node.child_by_field_name("name"),
+    }
+}
+
 /// 对外暴露的便捷函数
 pub fn extract_symbols_from_source(content: &str, language_id: &str) -> Vec<Symbol> {
     let mut engine = SymbolEngine::new();
     engine.extract_symbols(content, language_id)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(content: &str, language: &str) -> Vec<(String, String, usize)> {
+        extract_symbols_from_source(content, language)
+            .into_iter()
+            .map(|s| (s.kind, s.name, s.range.start_line))
+            .collect()
+    }
+
+    fn pairs(content: &str, language: &str) -> Vec<(String, String)> {
+        names(content, language).into_iter().map(|(k, n, _)| (k, n)).collect()
+    }
+
+    fn expected(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items.iter().map(|(k, n)| (k.to_string(), n.to_string())).collect()
+    }
+
+    #[test]
+    fn test_go_symbols() {
+        let source = "package store\n\ntype Store struct {\n\titems map[string]int\n}\n\ntype Reader interface {\n\tGet(key string) int\n}\n\nfunc (s *Store) Get(key string) int {\n\treturn s.items[key]\n}\n\nfunc New() *Store {\n\treturn &Store{}\n}\n";
+        assert_eq!(pairs(source, "go"), expected(&[
+            ("type_spec", "Store"), ("type_spec", "Reader"), ("method_declaration", "Get"), ("function_declaration", "New"),
+        ]));
+        assert_eq!(names(source, "go")[2].2, 10);
+    }
+
+    #[test]
+    fn test_java_symbols() {
+        let source = "package app;\n\npublic class UserService implements Service {\n    public UserService() {}\n\n    public User find(long id) {\n        return null;\n    }\n\n    enum Role { ADMIN }\n}\n\ninterface Service {}\n\nrecord User(long id) {}\n";
+        assert_eq!(pairs(source, "java"), expected(&[
+            ("class_declaration", "UserService"), ("constructor_declaration", "UserService"), ("method_declaration", "find"),
+            ("enum_declaration", "Role"), ("interface_declaration", "Service"), ("record_declaration", "User"),
+        ]));
+    }
+
+    #[test]
+    fn test_c_symbols() {
+        let source = "#include <stdio.h>\n\nstruct point {\n    int x, y;\n};\n\ntypedef struct {\n    int size;\n} buffer_t;\n\nstatic char *read_line(struct point *p) {\n    return 0;\n}\n\nint main(void) {\n    return 0;\n}\n";
+        assert_eq!(pairs(source, "c"), expected(&[
+            ("struct_specifier", "point"), ("type_definition", "buffer_t"), ("function_definition", "read_line"), ("function_definition", "main"),
+        ]));
+    }
+
+    #[test]
+    fn test_cpp_symbols() {
+        let source = "namespace net {\nclass Client {\npublic:\n    int send(const char *data);\n    Client &self() { return *this; }\n};\n\nint Client::send(const char *data) {\n    return 0;\n}\n}\n\ntemplate <typename T>\nT max_of(T a, T b) { return a > b ? a : b; }\n";
+        assert_eq!(pairs(source, "cpp"), expected(&[
+            ("namespace_definition", "net"), ("class_specifier", "Client"), ("function_definition", "self"),
+            ("function_definition", "send"), ("function_definition", "max_of"),
+        ]));
+    }
+}
@@ -0,0 +1,416 @@
+/*!
+Symbol References - 跨文件引用解析
+==================================
+
+在符号索引建立定义表之后，扫描每个文件中的标识符，按以下顺序解析到定义：
+
+1. 同一文件中的定义
+2. `use` / `import` 引入的名称（含别名），优先匹配导入路径对应的文件
+3. 命名空间限定（`ns.Name`、`module::Name`、通配导入 `use x::*` / `from x import *`）
+4. 成员调用（`.name(` / `Type::name`）在整个项目中只有一个定义时
+
+注释、字符串和导入语句本身不计为引用；无法确定目标的标识符不记录，宁缺毋滥。
+*/
+
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// 参与解析的定义
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefinitionSite {
+    pub path: String,
+    /// 从 1 开始
+    pub line: u32,
+}
+
+impl DefinitionSite {
+    pub fn location(&self) -> String {
+        format!("{}:{}", self.path, self.line)
+    }
+}
+
+/// 解析到定义的一处引用
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedReference {
+    /// 定义位置 `path:line`
+    pub definition: String,
+    /// 引用所在行（从 1 开始）
+    pub line: u32,
+}
+
+/// 文件中的一条导入
+#[derive(Debug, Clone, PartialEq)]
+struct Import {
+    /// 文件中使用的名称（别名或原名）
+    local: String,
+    /// 被导入的名称；`*` 表示通配导入，空字符串表示导入整个模块
+    name: String,
+    /// 模块路径（`crate::storage`、`./services/user`、`app.models`）
+    module: String,
+}
+
+struct Patterns {
+    identifier: Regex,
+    rust_use: Regex,
+    ts_import: Regex,
+    py_from: Regex,
+    py_import: Regex,
+    double_quoted: Regex,
+    single_quoted: Regex,
+    template: Regex,
+    rust_char: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        identifier: Regex::new(r"[A-Za-z_][A-Za-z0-9_]*").unwrap(),
+        rust_use: Regex::new(r"(?s)\buse\s+([^;]+);").unwrap(),
+        ts_import: Regex::new(r#"(?s)\bimport\s+(?:type\s+)?([^;'"]+?)\s+from\s+['"]([^'"]+)['"]"#).unwrap(),
+        py_from: Regex::new(r"(?m)^\s*from\s+([\w.]+)\s+import\s+(?:\(([^)]*)\)|([^\n#]+))").unwrap(),
+        py_import: Regex::new(r"(?m)^\s*import\s+([^\n#]+)").unwrap(),
+        double_quoted: Regex::new(r#""(?:\\.|[^"\\])*""#).unwrap(),
+        single_quoted: Regex::new(r"'(?:\\.|[^'\\])*'").unwrap(),
+        template: Regex::new(r"`(?:\\.|[^`\\])*`").unwrap(),
+        rust_char: Regex::new(r"'(?:\\.|[^'\\])'").unwrap(),
+    })
+}
+
+// ============================================================================
+// Imports
+// ============================================================================
+
+/// 按顶层逗号拆分（忽略花括号内的逗号）
+fn split_top_level(text: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0i32, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts.into_iter().map(|p| p.trim()).filter(|p| !p.is_empty()).collect()
+}
+
+/// 展开 Rust `use` 树：`a::{b::C, D as E, self, *}`
+fn expand_rust_use(prefix: &str, tree: &str, out: &mut Vec<Import>) {
+    let tree = tree.trim();
+    if let Some(open) = tree.find('{') {
+        let base = tree[..open].trim().trim_end_matches("::");
+        let inner = tree[open + 1..].trim_end();
+        let inner = inner.strip_suffix('}').unwrap_or(inner);
+        let prefix = [prefix, base].iter().filter(|s| !s.is_empty()).cloned().collect::<Vec<_>>().join("::");
+        for part in split_top_level(inner) {
+            expand_rust_use(&prefix, part, out);
+        }
+        return;
+    }
+    let (path, alias) = match tree.split_once(" as ") {
+        Some((path, alias)) => (path.trim(), Some(alias.trim())),
+        None => (tree, None),
+    };
+    let full = [prefix, path].iter().filter(|s| !s.is_empty()).cloned().collect::<Vec<_>>().join("::");
+    let (module, name) = match full.rsplit_once("::") {
+        Some((module, name)) => (module.to_string(), name.to_string()),
+        None => (String::new(), full.clone()),
+    };
+    match name.as_str() {
+        "*" => out.push(Import { local: "*".to_string(), name: "*".to_string(), module }),
+        // `use a::b::{self}` 引入模块 `b`
+        "self" => {
+            let local = alias.map(String::from).unwrap_or_else(|| module.rsplit("::").next().unwrap_or("").to_string());
+            out.push(Import { local, name: String::new(), module });
+        }
+        _ => {
+            let local = alias.unwrap_or(&name).to_string();
+            out.push(Import { local, name, module });
+        }
+    }
+}
+
+fn parse_imports(content: &str, language: &str) -> Vec<Import> {
+    let p = patterns();
+    let mut imports = Vec::new();
+    match language {
+        "rust" => {
+            for caps in p.rust_use.captures_iter(content) {
+                let tree = caps[1].trim();
+                let tree = tree.strip_prefix("pub ").unwrap_or(tree);
+                expand_rust_use("", tree, &mut imports);
+            }
+        }
+        "typescript" | "javascript" | "tsx" => {
+            for caps in p.ts_import.captures_iter(content) {
+                let module = caps[2].to_string();
+                for part in split_top_level(&caps[1]) {
+                    if let Some(ns) = part.strip_prefix("* as ") {
+                        imports.push(Import { local: ns.trim().to_string(), name: String::new(), module: module.clone() });
+                    } else if part.starts_with('{') {
+                        for named in part.trim_matches(|c| c == '{' || c == '}').split(',') {
+                            let named = named.trim().trim_start_matches("type ").trim();
+                            if named.is_empty() {
+                                continue;
+                            }
+                            let (name, local) = named.split_once(" as ").map(|(n, l)| (n.trim(), l.trim())).unwrap_or((named, named));
+                            imports.push(Import { local: local.to_string(), name: name.to_string(), module: module.clone() });
+                        }
+                    } else {
+                        // 默认导入：名称由导出方决定，按本地名匹配
+                        imports.push(Import { local: part.to_string(), name: part.to_string(), module: module.clone() });
+                    }
+                }
+            }
+        }
+        "python" => {
+            for caps in p.py_from.captures_iter(content) {
+                let module = caps[1].to_string();
+                let names = caps.get(2).or(caps.get(3)).map(|m| m.as_str()).unwrap_or("");
+                for named in names.split(',') {
+                    let named = named.trim();
+                    if named.is_empty() {
+                        continue;
+                    }
+                    let (name, local) = named.split_once(" as ").map(|(n, l)| (n.trim(), l.trim())).unwrap_or((named, named));
+                    imports.push(Import { local: local.to_string(), name: name.to_string(), module: module.clone() });
+                }
+            }
+            for caps in p.py_import.captures_iter(content) {
+                for module in caps[1].split(',') {
+                    let module = module.trim();
+                    let (module, local) = module.split_once(" as ").map(|(m, l)| (m.trim(), l.trim())).unwrap_or((module, module));
+                    if !module.is_empty() {
+                        imports.push(Import { local: local.to_string(), name: String::new(), module: module.to_string() });
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    imports
+}
+
+/// 模块路径最后一段（`crate::storage` -> `storage`，`./services/user.ts` -> `user`）
+fn module_leaf(module: &str) -> &str {
+    let leaf = module.rsplit([':', '/', '.']).find(|s| !s.is_empty()).unwrap_or(module);
+    // `./user.ts` 这类带扩展名的路径
+    match module.rsplit('/').next().and_then(|last| last.split_once('.')) {
+        Some((stem, ext)) if !stem.is_empty() && matches!(ext, "ts" | "tsx" | "js" | "jsx" | "mjs" | "py" | "rs") => stem,
+        _ => leaf,
+    }
+}
+
+/// 定义所在文件是否对应该模块（文件名或 `mod.rs` / `index.ts` / `__init__.py` 所在目录名）
+fn module_matches(module: &str, def_path: &str) -> bool {
+    let leaf = module_leaf(module);
+    if leaf.is_empty() {
+        return false;
+    }
+    let path = Path::new(def_path);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    if stem == leaf {
+        return true;
+    }
+    matches!(stem, "mod" | "index" | "__init__" | "lib")
+        && path.parent().and_then(|p| p.file_name()).and_then(|s| s.to_str()) == Some(leaf)
+}
+
+// ============================================================================
+// Resolution
+// ============================================================================
+
+/// 导入语句占据的行（从 0 开始，含跨行的导入）
+fn import_lines(content: &str, language: &str) -> HashSet<usize> {
+    let p = patterns();
+    let regexes: Vec<&Regex> = match language {
+        "rust" => vec![&p.rust_use],
+        "python" => vec![&p.py_from, &p.py_import],
+        "typescript" | "javascript" | "tsx" => vec![&p.ts_import],
+        _ => Vec::new(),
+    };
+    let line_of = |offset: usize| content[..offset].matches('\n').count();
+    regexes.iter()
+        .flat_map(|re| re.find_iter(content))
+        .flat_map(|m| line_of(m.start())..=line_of(m.end().saturating_sub(1).max(m.start())))
+        .collect()
+}
+
+/// 去掉字符串和行注释，保留位置无关的代码部分
+fn code_part(line: &str, language: &str) -> String {
+    let p = patterns();
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("/*") || trimmed.starts_with('*') {
+        return String::new();
+    }
+    let mut code = p.double_quoted.replace_all(line, "\"\"").to_string();
+    code = match language {
+        "rust" => p.rust_char.replace_all(&code, "''").to_string(),
+        _ => p.single_quoted.replace_all(&code, "''").to_string(),
+    };
+    if language != "rust" && language != "python" {
+        code = p.template.replace_all(&code, "``").to_string();
+    }
+    let comment = if language == "python" { "#" } else { "//" };
+    if let Some(pos) = code.find(comment) {
+        code.truncate(pos);
+    }
+    code
+}
+
+/// 标识符前的限定符：`ns.name` / `Type::name` 中的 `ns` / `Type`；成员调用 `.name` 返回空字符串
+fn qualifier(code: &str, start: usize) -> Option<&str> {
+    let before = &code[..start];
+    let head = before.strip_suffix("::").or_else(|| before.strip_suffix('.'))?;
+    let ident_start = head.rfind(|c: char| !(c.is_alphanumeric() || c == '_')).map(|i| i + 1).unwrap_or(0);
+    Some(&head[ident_start..])
+}
+
+/// 解析文件中的引用（`path` 与定义表中的路径格式一致）
+pub fn resolve_references(
+    path: &str,
+    content: &str,
+    language: &str,
+    definitions: &HashMap<String, Vec<DefinitionSite>>,
+) -> Vec<ResolvedReference> {
+    let imports = parse_imports(content, language);
+    let by_local: HashMap<&str, &Import> = imports.iter().filter(|i| i.local != "*").map(|i| (i.local.as_str(), i)).collect();
+    let globs: Vec<&str> = imports.iter().filter(|i| i.name == "*").map(|i| i.module.as_str()).collect();
+
+    let skipped = import_lines(content, language);
+
+    let mut seen = HashSet::new();
+    let mut resolved = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        if skipped.contains(&index) {
+            continue;
+        }
+        let line_no = index as u32 + 1;
+        let code = code_part(line, language);
+        for m in patterns().identifier.find_iter(&code) {
+            // 数字字面量的后缀（`1u32`、`0x1F`）不是标识符
+            if code[..m.start()].chars().last().is_some_and(|c| c.is_ascii_digit()) {
+                continue;
+            }
+            let qualifier = qualifier(&code, m.start());
+            let import = if qualifier.is_none() { by_local.get(m.as_str()).copied() } else { None };
+            let name = import.map(|i| i.name.as_str()).filter(|n| !n.is_empty()).unwrap_or(m.as_str());
+            let Some(candidates) = definitions.get(name) else { continue };
+            // 定义所在行本身不是引用
+            if candidates.iter().any(|d| d.path == path && d.line == line_no) {
+                continue;
+            }
+
+            let local: Vec<&DefinitionSite> = candidates.iter().filter(|d| d.path == path).collect();
+            let targets: Vec<&DefinitionSite> = if qualifier.is_none() && !local.is_empty() {
+                local
+            } else if let Some(import) = import {
+                let matched: Vec<&DefinitionSite> = candidates.iter().filter(|d| module_matches(&import.module, &d.path)).collect();
+                if matched.is_empty() { candidates.iter().collect() } else { matched }
+            } else if let Some(namespace) = qualifier.and_then(|q| by_local.get(q)) {
+                // `ns.name` / `module::name`：命名空间对应的模块（Rust 中 `use crate::storage;` 导入的名称本身就是模块）
+                let module = if namespace.name.is_empty() { namespace.module.clone() } else { format!("{}::{}", namespace.module, namespace.name) };
+                candidates.iter().filter(|d| module_matches(&module, &d.path)).collect()
+            } else if let Some(q) = qualifier.filter(|q| !q.is_empty() && !matches!(*q, "self" | "Self" | "this" | "super" | "crate")) {
+                // `storage::Store` 等未导入的路径：按路径最后一段匹配文件，无法匹配时退回唯一定义
+                let matched: Vec<&DefinitionSite> = candidates.iter().filter(|d| module_matches(q, &d.path)).collect();
+                if !matched.is_empty() { matched } else if candidates.len() == 1 { candidates.iter().collect() } else { Vec::new() }
+            } else if qualifier.is_some() {
+                // 成员调用：项目中只有一个同名定义时才解析
+                if candidates.len() == 1 { candidates.iter().collect() } else { Vec::new() }
+            } else {
+                candidates.iter().filter(|d| globs.iter().any(|g| module_matches(g, &d.path))).collect()
+            };
+
+            for target in targets {
+                let definition = target.location();
+                if seen.insert((definition.clone(), line_no)) {
+                    resolved.push(ResolvedReference { definition, line: line_no });
+                }
+            }
+        }
+    }
+    resolved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definitions(sites: &[(&str, &str, u32)]) -> HashMap<String, Vec<DefinitionSite>> {
+        let mut map: HashMap<String, Vec<DefinitionSite>> = HashMap::new();
+        for (name, path, line) in sites {
+            map.entry(name.to_string()).or_default().push(DefinitionSite { path: path.to_string(), line: *line });
+        }
+        map
+    }
+
+    fn lines(refs: &[ResolvedReference], definition: &str) -> Vec<u32> {
+        refs.iter().filter(|r| r.definition == definition).map(|r| r.line).collect()
+    }
+
+    #[test]
+    fn test_parse_rust_use_tree() {
+        let imports = parse_imports("use crate::storage::{Store, cache::Cache as Lru, self};\nuse super::*;\n", "rust");
+        let locals: Vec<(&str, &str, &str)> = imports.iter().map(|i| (i.local.as_str(), i.name.as_str(), i.module.as_str())).collect();
+        assert_eq!(locals, vec![
+            ("Store", "Store", "crate::storage"),
+            ("Lru", "Cache", "crate::storage::cache"),
+            ("storage", "", "crate::storage"),
+            ("*", "*", "super"),
+        ]);
+    }
+
+    #[test]
+    fn test_rust_references() {
+        let defs = definitions(&[
+            ("Store", "/p/src/storage.rs", 3),
+            ("Store", "/p/src/legacy.rs", 1),
+            ("helper", "/p/src/lib.rs", 10),
+            ("save", "/p/src/storage.rs", 8),
+        ]);
+        let source = "use crate::storage::Store;\n\n// Store is mentioned in a comment\nfn run(s: Store) {\n    let msg = \"Store\";\n    helper();\n    s.save();\n}\n\nfn helper() {}\n\nfn helper2() { helper() }\n";
+        let refs = resolve_references("/p/src/lib.rs", source, "rust", &defs);
+        // 导入路径指向 storage.rs，不会解析到 legacy.rs 中的同名结构体
+        assert_eq!(lines(&refs, "/p/src/storage.rs:3"), vec![4]);
+        assert!(lines(&refs, "/p/src/legacy.rs:1").is_empty());
+        assert_eq!(lines(&refs, "/p/src/lib.rs:10"), vec![6, 12]);
+        assert_eq!(lines(&refs, "/p/src/storage.rs:8"), vec![7]);
+    }
+
+    #[test]
+    fn test_typescript_and_python_references() {
+        let defs = definitions(&[
+            ("UserService", "/p/src/services/userService.ts", 4),
+            ("Avatar", "/p/src/components/Avatar.tsx", 2),
+            ("User", "/p/app/models.py", 1),
+            ("User", "/p/other/models_old.py", 1),
+        ]);
+        let ts = "import { UserService as Users } from './services/userService';\nimport * as UI from './components/Avatar';\n\nconst svc = new Users();\nrender(UI.Avatar, 'UserService');\n";
+        let refs = resolve_references("/p/src/app.ts", ts, "typescript", &defs);
+        assert_eq!(lines(&refs, "/p/src/services/userService.ts:4"), vec![4]);
+        assert_eq!(lines(&refs, "/p/src/components/Avatar.tsx:2"), vec![5]);
+
+        let py = "from app.models import (\n    User,\n)\n\ndef load():  # User\n    return User()\n";
+        let refs = resolve_references("/p/app/service.py", py, "python", &defs);
+        assert_eq!(lines(&refs, "/p/app/models.py:1"), vec![6]);
+        assert!(lines(&refs, "/p/other/models_old.py:1").is_empty());
+    }
+
+    #[test]
+    fn test_module_matches() {
+        assert!(module_matches("crate::storage", "/p/src/storage.rs"));
+        assert!(module_matches("crate::storage", "/p/src/storage/mod.rs"));
+        assert!(module_matches("./services/userService", "/p/src/services/userService.ts"));
+        assert!(module_matches("../components", "/p/src/components/index.ts"));
+        assert!(module_matches("app.models", "/p/app/models.py"));
+        assert!(!module_matches("app.models", "/p/app/views.py"));
+    }
+}
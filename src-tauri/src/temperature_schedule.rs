@@ -0,0 +1,255 @@
+/*!
+Temperature Schedule - 对话级温度调度
+=====================================
+
+根据每条用户消息的分类结果自动设置请求的 `temperature`：
+- 探索性的早期轮次（问答、分析）使用较高温度
+- 代码生成 / 文件操作 / 终端命令等精修轮次使用较低温度
+
+在请求构建层（`ai_utils`）统一应用，所有 Provider 均生效。
+全局调度可配置，单个会话可通过 `session_id` 覆盖（固定温度或关闭调度）。
+请求体中已显式给出 `temperature` 时不做修改。
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+
+use crate::core_traits::ai::Message;
+use crate::intelligence_router::extract_text_content;
+use crate::tool_classification::{classify_tool_fast, ToolCategory};
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 温度调度配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemperatureSchedule {
+    pub enabled: bool,
+    /// 各分类的基础温度（键为 snake_case 分类名）
+    pub category_temperatures: HashMap<ToolCategory, f32>,
+    /// 视为"探索阶段"的用户轮次数
+    pub exploratory_turns: usize,
+    /// 探索阶段对非代码类分类的温度加成
+    pub exploratory_boost: f32,
+}
+
+impl Default for TemperatureSchedule {
+    fn default() -> Self {
+        let category_temperatures = HashMap::from([
+            (ToolCategory::AiChat, 0.7),
+            (ToolCategory::NoToolNeeded, 0.7),
+            (ToolCategory::CodeAnalysis, 0.4),
+            (ToolCategory::SearchOperations, 0.2),
+            (ToolCategory::CodeGeneration, 0.2),
+            (ToolCategory::FileOperations, 0.1),
+            (ToolCategory::TerminalCommands, 0.1),
+        ]);
+        Self {
+            enabled: true,
+            category_temperatures,
+            exploratory_turns: 2,
+            exploratory_boost: 0.2,
+        }
+    }
+}
+
+/// 会话级覆盖
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionTemperatureOverride {
+    /// 固定温度（优先级最高）
+    pub temperature: Option<f32>,
+    /// 为该会话关闭自动调度
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// 调度结果（便于前端展示）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScheduledTemperature {
+    pub temperature: f32,
+    pub category: Option<ToolCategory>,
+    /// "session_override" | "exploratory" | "category"
+    pub reason: String,
+}
+
+// ============================================================================
+// State
+// ============================================================================
+
+static SCHEDULE: OnceLock<Mutex<TemperatureSchedule>> = OnceLock::new();
+static SESSION_OVERRIDES: OnceLock<Mutex<HashMap<String, SessionTemperatureOverride>>> = OnceLock::new();
+
+tokio::task_local! {
+    /// 当前请求所属的会话（由 `with_session` 设置）
+    static CURRENT_SESSION: String;
+}
+
+fn schedule() -> &'static Mutex<TemperatureSchedule> {
+    SCHEDULE.get_or_init(|| Mutex::new(TemperatureSchedule::default()))
+}
+
+fn session_overrides() -> &'static Mutex<HashMap<String, SessionTemperatureOverride>> {
+    SESSION_OVERRIDES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 在指定会话上下文中执行请求，使会话覆盖生效
+pub async fn with_session<F: Future>(session_id: Option<String>, fut: F) -> F::Output {
+    match session_id {
+        Some(id) => CURRENT_SESSION.scope(id, fut).await,
+        None => fut.await,
+    }
+}
+
+// ============================================================================
+// Scheduling
+// ============================================================================
+
+fn is_refinement(category: ToolCategory) -> bool {
+    matches!(
+        category,
+        ToolCategory::CodeGeneration | ToolCategory::FileOperations | ToolCategory::TerminalCommands
+    )
+}
+
+/// 根据对话计算温度
+pub fn compute_temperature(
+    schedule: &TemperatureSchedule,
+    session: Option<&SessionTemperatureOverride>,
+    messages: &[Message],
+) -> Option<ScheduledTemperature> {
+    if let Some(t) = session.and_then(|s| s.temperature) {
+        return Some(ScheduledTemperature { temperature: t, category: None, reason: "session_override".to_string() });
+    }
+    if !schedule.enabled || session.map_or(false, |s| s.disabled) {
+        return None;
+    }
+
+    let user_messages: Vec<&Message> = messages.iter().filter(|m| m.role == "user").collect();
+    let last = user_messages.last()?;
+    let category = classify_tool_fast(&extract_text_content(&last.content)).category;
+    let base = *schedule.category_temperatures.get(&category)?;
+
+    if user_messages.len() <= schedule.exploratory_turns && !is_refinement(category) {
+        return Some(ScheduledTemperature {
+            temperature: (base + schedule.exploratory_boost).min(1.0),
+            category: Some(category),
+            reason: "exploratory".to_string(),
+        });
+    }
+    Some(ScheduledTemperature { temperature: base, category: Some(category), reason: "category".to_string() })
+}
+
+/// 在请求体中应用温度调度（请求构建层调用）
+pub fn apply_to_request(request_body: &mut Value, messages: &[Message]) {
+    if request_body.get("temperature").is_some() {
+        return;
+    }
+    let Ok(schedule) = schedule().lock().map(|s| s.clone()) else { return };
+    let session = CURRENT_SESSION
+        .try_with(|id| id.clone())
+        .ok()
+        .and_then(|id| session_overrides().lock().ok()?.get(&id).cloned());
+
+    if let Some(scheduled) = compute_temperature(&schedule, session.as_ref(), messages) {
+        println!("[TemperatureSchedule] temperature={} ({}, {:?})", scheduled.temperature, scheduled.reason, scheduled.category);
+        request_body["temperature"] = serde_json::json!(scheduled.temperature);
+    }
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 获取全局温度调度配置
+#[tauri::command]
+pub fn get_temperature_schedule() -> TemperatureSchedule {
+    schedule().lock().map(|s| s.clone()).unwrap_or_default()
+}
+
+/// 更新全局温度调度配置
+#[tauri::command]
+pub fn set_temperature_schedule(config: TemperatureSchedule) -> Result<(), String> {
+    if config.category_temperatures.values().any(|t| !(0.0..=2.0).contains(t)) {
+        return Err("temperature must be within 0.0 - 2.0".to_string());
+    }
+    *schedule().lock().map_err(|e| e.to_string())? = config;
+    Ok(())
+}
+
+/// 设置（或清除，`config` 为空时）会话级覆盖
+#[tauri::command]
+pub fn set_session_temperature_override(
+    session_id: String,
+    config: Option<SessionTemperatureOverride>,
+) -> Result<(), String> {
+    let mut overrides = session_overrides().lock().map_err(|e| e.to_string())?;
+    match config {
+        Some(c) => { overrides.insert(session_id, c); }
+        None => { overrides.remove(&session_id); }
+    }
+    Ok(())
+}
+
+/// 预览给定对话将使用的温度
+#[tauri::command]
+pub fn preview_scheduled_temperature(
+    messages: Vec<Message>,
+    session_id: Option<String>,
+) -> Option<ScheduledTemperature> {
+    let schedule = get_temperature_schedule();
+    let session = session_id.and_then(|id| session_overrides().lock().ok()?.get(&id).cloned());
+    compute_temperature(&schedule, session.as_ref(), &messages)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core_traits::ai::Content;
+
+    fn user(text: &str) -> Message {
+        Message { role: "user".to_string(), content: Content::Text(text.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_exploratory_turn_is_boosted() {
+        let schedule = TemperatureSchedule::default();
+        let result = compute_temperature(&schedule, None, &[user("什么是闭包？")]).unwrap();
+        assert_eq!(result.category, Some(ToolCategory::AiChat));
+        assert_eq!(result.reason, "exploratory");
+        assert!((result.temperature - 0.9).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_refinement_turn_uses_low_temperature() {
+        let schedule = TemperatureSchedule::default();
+        let messages = [user("a"), user("b"), user("/read src/main.rs")];
+        let result = compute_temperature(&schedule, None, &messages).unwrap();
+        assert!(result.temperature <= 0.2);
+        assert_eq!(result.reason, "category");
+    }
+
+    #[test]
+    fn test_session_override() {
+        let schedule = TemperatureSchedule::default();
+        let fixed = SessionTemperatureOverride { temperature: Some(0.05), disabled: false };
+        assert_eq!(compute_temperature(&schedule, Some(&fixed), &[user("hi")]).unwrap().temperature, 0.05);
+
+        let off = SessionTemperatureOverride { temperature: None, disabled: true };
+        assert!(compute_temperature(&schedule, Some(&off), &[user("hi")]).is_none());
+    }
+
+    #[test]
+    fn test_explicit_temperature_is_kept() {
+        let mut body = serde_json::json!({ "temperature": 1.2 });
+        apply_to_request(&mut body, &[user("hi")]);
+        assert_eq!(body["temperature"], 1.2);
+    }
+}
@@ -4,10 +4,103 @@ use std::io::{Read, Write};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::path::PathBuf;
+use serde::Serialize;
+
+/// v0.3.4: 每个会话在 Rust 侧保留的输出（字节），webview 刷新后可重新挂载并回放
+const MAX_SCROLLBACK_BYTES: usize = 512 * 1024;
+/// `read_pty_history` 未指定行数时返回的行数
+const DEFAULT_HISTORY_LINES: usize = 200;
+
+/// 会话的原始输出（含 ANSI 控制序列），超出上限时丢弃最早的部分
+#[derive(Default)]
+pub struct Scrollback {
+    buffer: String,
+    exited: bool,
+}
+
+impl Scrollback {
+    pub fn push(&mut self, output: &str) {
+        self.buffer.push_str(output);
+        if self.buffer.len() > MAX_SCROLLBACK_BYTES {
+            let mut cut = self.buffer.len() - MAX_SCROLLBACK_BYTES;
+            while !self.buffer.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.buffer.drain(..cut);
+        }
+    }
+
+    /// 去掉控制序列后的最后 `lines` 行，供 AI 作为“当前终端内容”使用
+    pub fn tail_lines(&self, lines: usize) -> String {
+        let text = strip_ansi(&self.buffer);
+        let all: Vec<&str> = text.lines()
+            // 进度条等用 \r 覆盖的行只保留最后一次绘制的内容
+            .map(|line| line.rsplit('\r').find(|segment| !segment.is_empty()).unwrap_or(""))
+            .collect();
+        let end = all.iter().rposition(|line| !line.trim().is_empty()).map_or(0, |i| i + 1);
+        all[end.saturating_sub(lines)..end].join("\n")
+    }
+}
+
+/// 去掉 ANSI 控制序列（CSI、OSC、字符集选择与两字节转义）
+fn strip_ansi(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI：参数后以 0x40..=0x7E 结束
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\u{40}'..='\u{7e}').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC：以 BEL 或 ESC \ 结束
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' || (c == '\u{1b}' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // 字符集选择等三字节序列（如 ESC ( B）
+            Some('(' | ')' | '*' | '+' | '#' | '%') => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    out
+}
 
 pub struct TerminalSession {
     pub master: Box<dyn MasterPty + Send>,
     pub writer: Box<dyn Write + Send>,
+    pub scrollback: Arc<Mutex<Scrollback>>,
+    pub info: PtySessionInfo,
+}
+
+/// 可重新挂载的终端会话
+#[derive(Debug, Clone, Serialize)]
+pub struct PtySessionInfo {
+    pub pty_id: u32,
+    pub cwd: Option<String>,
+    pub cols: u16,
+    pub rows: u16,
+    pub created_at: i64,
+    pub exited: bool,
+}
+
+/// 重新挂载时返回的会话信息与完整输出
+#[derive(Debug, Clone, Serialize)]
+pub struct PtyAttachment {
+    pub session: PtySessionInfo,
+    pub scrollback: String,
 }
 
 // Store PTY sessions
@@ -42,7 +135,7 @@ pub async fn create_pty(app_handle: AppHandle, manager: tauri::State<'_, Termina
 
     let mut command = CommandBuilder::new(program);
 
-    if let Some(dir) = cwd {
+    if let Some(dir) = cwd.clone() {
         command.cwd(PathBuf::from(dir));
     } else {
         // Fallback to app data dir or home dir
@@ -64,6 +157,8 @@ pub async fn create_pty(app_handle: AppHandle, manager: tauri::State<'_, Termina
     let writer = pty_pair.master.take_writer().map_err(|e| e.to_string())?;
     
     let event_name = format!("pty-output-{}", pty_id);
+    let scrollback = Arc::new(Mutex::new(Scrollback::default()));
+    let reader_scrollback = scrollback.clone();
 
     // Spawn a thread to read PTY output and emit to frontend
     async_runtime::spawn(async move {
@@ -72,16 +167,26 @@ pub async fn create_pty(app_handle: AppHandle, manager: tauri::State<'_, Termina
             match reader.read(&mut buf) {
                 Ok(0) => {
                     // EOF, child process exited
+                    if let Ok(mut scrollback) = reader_scrollback.lock() {
+                        scrollback.exited = true;
+                    }
                     let _ = app_handle.emit(&format!("pty-exit-{}", pty_id), pty_id);
                     break;
                 },
                 Ok(bytes_read) => {
                     let output = String::from_utf8_lossy(&buf[..bytes_read]);
+                    // v0.3.4: 先写入 scrollback，再推送到前端
+                    if let Ok(mut scrollback) = reader_scrollback.lock() {
+                        scrollback.push(&output);
+                    }
                     let _ = app_handle.emit(&event_name, output.to_string());
                 },
                 Err(e) => {
                     // Error reading from PTY, child process might have exited
                     eprintln!("Error reading from PTY: {}", e);
+                    if let Ok(mut scrollback) = reader_scrollback.lock() {
+                        scrollback.exited = true;
+                    }
                     let _ = app_handle.emit(&format!("pty-error-{}", pty_id), e.to_string());
                     break;
                 },
@@ -93,6 +198,15 @@ pub async fn create_pty(app_handle: AppHandle, manager: tauri::State<'_, Termina
     manager.pty_sessions.lock().unwrap().insert(pty_id, TerminalSession {
         master: pty_pair.master,
         writer,
+        scrollback,
+        info: PtySessionInfo {
+            pty_id,
+            cwd,
+            cols,
+            rows,
+            created_at: chrono::Utc::now().timestamp(),
+            exited: false,
+        },
     });
 
     Ok(pty_id)
@@ -114,6 +228,8 @@ pub async fn resize_pty(manager: tauri::State<'_, TerminalManager>, pty_id: u32,
     let mut sessions = manager.pty_sessions.lock().unwrap();
     if let Some(session) = sessions.get_mut(&pty_id) {
         session.master.resize(PtySize { cols, rows, pixel_width: 0, pixel_height: 0 }).map_err(|e| e.to_string())?;
+        session.info.cols = cols;
+        session.info.rows = rows;
         Ok(())
     } else {
         Err(format!("PTY session {} not found", pty_id))
@@ -130,4 +246,63 @@ pub async fn kill_pty(manager: tauri::State<'_, TerminalManager>, pty_id: u32) -
     } else {
         Err(format!("PTY session {} not found", pty_id))
     }
-}
\ No newline at end of file
+}
+
+impl TerminalSession {
+    fn current_info(&self) -> PtySessionInfo {
+        let exited = self.scrollback.lock().map(|s| s.exited).unwrap_or(false);
+        PtySessionInfo { exited, ..self.info.clone() }
+    }
+}
+
+/// v0.3.4: 列出仍由后端持有的终端会话（webview 刷新后据此重新挂载）
+#[command]
+pub async fn list_pty_sessions(manager: tauri::State<'_, TerminalManager>) -> Result<Vec<PtySessionInfo>, String> {
+    let sessions = manager.pty_sessions.lock().unwrap();
+    let mut list: Vec<PtySessionInfo> = sessions.values().map(|session| session.current_info()).collect();
+    list.sort_by_key(|info| info.pty_id);
+    Ok(list)
+}
+
+/// v0.3.4: 重新挂载会话：返回保留的完整输出（含控制序列）用于回放，之后继续监听 `pty-output-{id}`
+#[command]
+pub async fn attach_pty(manager: tauri::State<'_, TerminalManager>, pty_id: u32) -> Result<PtyAttachment, String> {
+    let sessions = manager.pty_sessions.lock().unwrap();
+    let session = sessions.get(&pty_id).ok_or_else(|| format!("PTY session {} not found", pty_id))?;
+    let scrollback = session.scrollback.lock().map(|s| s.buffer.clone()).unwrap_or_default();
+    Ok(PtyAttachment { session: session.current_info(), scrollback })
+}
+
+/// v0.3.4: 读取终端最近的输出（纯文本），可作为“当前终端内容”提供给 AI
+#[command]
+pub async fn read_pty_history(manager: tauri::State<'_, TerminalManager>, session_id: u32, lines: Option<usize>) -> Result<String, String> {
+    let sessions = manager.pty_sessions.lock().unwrap();
+    let session = sessions.get(&session_id).ok_or_else(|| format!("PTY session {} not found", session_id))?;
+    let scrollback = session.scrollback.lock().map_err(|e| e.to_string())?;
+    Ok(scrollback.tail_lines(lines.unwrap_or(DEFAULT_HISTORY_LINES)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\u{1b}[1;32mok\u{1b}[0m done"), "ok done");
+        assert_eq!(strip_ansi("\u{1b}]0;user@host: ~\u{7}$ ls"), "$ ls");
+        assert_eq!(strip_ansi("\u{1b}]2;title\u{1b}\\prompt\u{1b}(B"), "prompt");
+    }
+
+    #[test]
+    fn test_scrollback_tail_and_limit() {
+        let mut scrollback = Scrollback::default();
+        scrollback.push("$ cargo build\r\n");
+        scrollback.push("Compiling 1/3\rCompiling 3/3\r\n\u{1b}[31merror\u{1b}[0m: oops\r\n$ \r\n\r\n");
+        assert_eq!(scrollback.tail_lines(2), "error: oops\n$ ");
+        assert_eq!(scrollback.tail_lines(10), "$ cargo build\nCompiling 3/3\nerror: oops\n$ ");
+
+        scrollback.push(&"é".repeat(MAX_SCROLLBACK_BYTES));
+        assert!(scrollback.buffer.len() <= MAX_SCROLLBACK_BYTES);
+        assert!(scrollback.buffer.ends_with('é'));
+    }
+}
@@ -1,7 +1,10 @@
 // Token 计数模块 - v0.2.6 新增
 // 支持 tiktoken（云端模型）
+// v0.3.4: 按模型选择分词方式（OpenAI 使用真实 BPE，Qwen / GLM 使用近似）
 
-use tiktoken_rs::{cl100k_base, o200k_base, p50k_base, get_bpe_from_model};
+use std::sync::OnceLock;
+use tiktoken_rs::{cl100k_base, o200k_base, get_bpe_from_model, CoreBPE};
+use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
 
 /// Token 计数结果
 #[derive(serde::Serialize, serde::Deserialize, Debug)]
@@ -10,22 +13,109 @@ pub struct TokenCountResult {
     pub encoding: String,
 }
 
-/// 为 OpenAI 模型计数 Token
-pub fn count_tokens_openai(text: &str, model: &str) -> usize {
-    // 根据模型选择编码器
-    let bpe = get_bpe_from_model(model);
+/// 按模型选择的分词方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizerKind {
+    /// OpenAI o200k_base（gpt-4o / o1 / o3 等）
+    O200k,
+    /// OpenAI cl100k_base，也用于未知模型的近似
+    Cl100k,
+    /// Qwen 系列近似：非中文部分按 cl100k，中文约 1.4 字 / Token
+    Qwen,
+    /// GLM 系列近似：非中文部分按 cl100k，中文约 1.6 字 / Token
+    Glm,
+}
 
-    match bpe {
-        Ok(bpe) => {
-            bpe.encode_with_special_tokens(text).len()
+impl TokenizerKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TokenizerKind::O200k => "o200k_base",
+            TokenizerKind::Cl100k => "cl100k_base",
+            TokenizerKind::Qwen => "qwen (approx)",
+            TokenizerKind::Glm => "glm (approx)",
         }
-        Err(_) => {
+    }
+}
+
+/// 根据模型名选择分词方式（支持 `provider/model` 形式）
+pub fn tokenizer_for_model(model: &str) -> TokenizerKind {
+    let lower = model.to_lowercase();
+    let name = lower.rsplit('/').next().unwrap_or(&lower);
+    if name.contains("qwen") {
+        TokenizerKind::Qwen
+    } else if name.starts_with("glm") || name.contains("chatglm") {
+        TokenizerKind::Glm
+    } else if get_tokenizer(name) == Some(Tokenizer::O200kBase) {
+        TokenizerKind::O200k
+    } else {
+        TokenizerKind::Cl100k
+    }
+}
+
+static CL100K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+static O200K: OnceLock<Option<CoreBPE>> = OnceLock::new();
+
+/// 缓存的编码器（加载失败时为 None，调用方回退到字符估算）
+fn cached_bpe(kind: TokenizerKind) -> Option<&'static CoreBPE> {
+    match kind {
+        TokenizerKind::O200k => O200K.get_or_init(|| o200k_base().ok()).as_ref(),
+        _ => CL100K.get_or_init(|| cl100k_base().ok()).as_ref(),
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    let cp = c as u32;
+    (0x4E00..=0x9FFF).contains(&cp) || // CJK 统一汉字
+    (0x3400..=0x4DBF).contains(&cp) || // CJK 扩展 A
+    (0x20000..=0x2A6DF).contains(&cp) // CJK 扩展 B
+}
+
+/// 中文按字数比例近似，其余部分按 cl100k 计数
+fn count_cjk_approx(text: &str, chars_per_token: f64) -> usize {
+    let cjk = text.chars().filter(|c| is_cjk(*c)).count();
+    let rest: String = text.chars().map(|c| if is_cjk(c) { ' ' } else { c }).collect();
+    let rest_tokens = match cached_bpe(TokenizerKind::Cl100k) {
+        Some(bpe) if !rest.trim().is_empty() => bpe.encode_ordinary(rest.trim()).len(),
+        Some(_) => 0,
+        None => estimate_tokens(&rest),
+    };
+    rest_tokens + (cjk as f64 / chars_per_token).ceil() as usize
+}
+
+/// 使用指定的分词方式计数
+pub fn count_with(text: &str, kind: TokenizerKind) -> usize {
+    match kind {
+        TokenizerKind::Qwen => count_cjk_approx(text, 1.4),
+        TokenizerKind::Glm => count_cjk_approx(text, 1.6),
+        _ => match cached_bpe(kind) {
+            Some(bpe) => bpe.encode_with_special_tokens(text).len(),
+            None => estimate_tokens(text),
+        },
+    }
+}
+
+/// 按模型计数 Token，同时返回使用的编码
+pub fn count_tokens_for_model(text: &str, model: &str) -> TokenCountResult {
+    let kind = tokenizer_for_model(model);
+    let count = match kind {
+        TokenizerKind::Qwen | TokenizerKind::Glm => count_with(text, kind),
+        _ => count_tokens_openai(text, model),
+    };
+    TokenCountResult { count, encoding: kind.name().to_string() }
+}
+
+/// 为 OpenAI 模型计数 Token
+pub fn count_tokens_openai(text: &str, model: &str) -> usize {
+    // 常用编码使用缓存的编码器，避免每次重新加载词表
+    match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => count_with(text, TokenizerKind::O200k),
+        Some(Tokenizer::Cl100kBase) | None => count_with(text, TokenizerKind::Cl100k),
+        // 旧模型（p50k / r50k 等）按模型名构建编码器
+        Some(_) => match get_bpe_from_model(model) {
+            Ok(bpe) => bpe.encode_with_special_tokens(text).len(),
             // 🔥 安全回退：不再使用 unwrap()，如果 cl100k_base 也失败，使用字符估算
-            match cl100k_base() {
-                Ok(fallback) => fallback.encode_with_special_tokens(text).len(),
-                Err(_) => estimate_tokens(text)
-            }
-        }
+            Err(_) => count_with(text, TokenizerKind::Cl100k),
+        },
     }
 }
 
@@ -35,12 +125,7 @@ pub fn estimate_tokens(text: &str) -> usize {
     // 英文大约 4 字符 = 1 Token
     // 中文大约 1.5-2 字符 = 1 Token
     // 这里使用混合估算
-    let chinese_chars = text.chars().filter(|c| {
-        let cp = *c as u32;
-        (0x4E00..=0x9FFF).contains(&cp) || // CJK 统一汉字
-        (0x3400..=0x4DBF).contains(&cp) || // CJK 扩展 A
-        (0x20000..=0x2A6DF).contains(&cp) // CJK 扩展 B
-    }).count();
+    let chinese_chars = text.chars().filter(|c| is_cjk(*c)).count();
 
     let other_chars = text.len() - chinese_chars;
 
@@ -49,27 +134,9 @@ pub fn estimate_tokens(text: &str) -> usize {
 
 /// 批量计数多个文本片段的 Token
 pub fn count_tokens_batch_internal(texts: &[String], model: &str) -> Vec<usize> {
-    let bpe = get_bpe_from_model(model);
-
-    match bpe {
-        Ok(bpe) => {
-            texts.iter()
-                .map(|text| bpe.encode_with_special_tokens(text).len())
-                .collect()
-        }
-        Err(_) => {
-            // 🔥 安全回退：不再使用 unwrap()
-            let fallback_bpe = cl100k_base().ok();
-            texts.iter()
-                .map(|text| {
-                    match &fallback_bpe {
-                        Some(f) => f.encode_with_special_tokens(text).len(),
-                        None => estimate_tokens(text)
-                    }
-                })
-                .collect()
-        }
-    }
+    texts.iter()
+        .map(|text| count_tokens_for_model(text, model).count)
+        .collect()
 }
 
 // ============== Tauri 命令 ==============
@@ -84,7 +151,7 @@ pub fn count_tokens_batch_internal(texts: &[String], model: &str) -> Vec<usize>
 /// 返回 Token 数量
 #[tauri::command]
 pub fn count_tokens(text: String, model: String) -> usize {
-    count_tokens_openai(&text, &model)
+    count_tokens_for_model(&text, &model).count
 }
 
 /// 批量计数多个文本的 Token 数量
@@ -100,6 +167,21 @@ pub fn count_tokens_batch(texts: Vec<String>, model: String) -> Vec<usize> {
     count_tokens_batch_internal(&texts, &model)
 }
 
+/// 计数即将发送的对话上下文（按 `provider_config.models[0]` 选择分词方式）
+///
+/// 前端在发送前调用，用于实时显示上下文占用
+#[tauri::command]
+pub fn count_context_tokens(
+    messages: Vec<crate::core_traits::ai::Message>,
+    provider_config: crate::core_traits::ai::AIProviderConfig,
+) -> TokenCountResult {
+    let model = provider_config.models.first().map(|m| m.as_str()).unwrap_or("");
+    TokenCountResult {
+        count: crate::conversation::token_counter::count_messages_tokens_for_model(&messages, model),
+        encoding: tokenizer_for_model(model).name().to_string(),
+    }
+}
+
 /// 快速估算 Token 数量（不使用 tiktoken，基于字符数）
 ///
 /// # 参数
@@ -140,6 +222,27 @@ mod tests {
         println!("'{}' estimated {} tokens", text, estimate);
     }
 
+    #[test]
+    fn test_tokenizer_for_model() {
+        assert_eq!(tokenizer_for_model("gpt-4o-mini"), TokenizerKind::O200k);
+        assert_eq!(tokenizer_for_model("openai/gpt-4"), TokenizerKind::Cl100k);
+        assert_eq!(tokenizer_for_model("Qwen2.5-Coder-7B-Instruct"), TokenizerKind::Qwen);
+        assert_eq!(tokenizer_for_model("glm-4.6"), TokenizerKind::Glm);
+        assert_eq!(tokenizer_for_model("unknown-model"), TokenizerKind::Cl100k);
+    }
+
+    #[test]
+    fn test_count_tokens_for_model() {
+        let english = "fn main() { println!(\"hello\"); }";
+        assert_eq!(count_tokens_for_model(english, "qwen-max").count, count_tokens_for_model(english, "gpt-4").count);
+
+        let chinese = "你好世界你好世界你好世界你好世界";
+        let qwen = count_tokens_for_model(chinese, "qwen-max");
+        assert_eq!(qwen.count, 12);
+        assert_eq!(qwen.encoding, "qwen (approx)");
+        assert_eq!(count_tokens_for_model(chinese, "glm-4").count, 10);
+    }
+
     #[test]
     fn test_batch_count() {
         let texts = vec![
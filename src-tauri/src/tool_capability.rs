@@ -0,0 +1,375 @@
+/*!
+Tool Capability - 模型工具调用能力探测
+======================================
+
+部分模型不支持 function calling，带 `tools` 请求时会返回 400，或直接忽略工具。
+
+- 内置注册表：已知不支持工具调用的模型（按名称片段匹配）
+- 运行时探测：请求因工具参数被拒绝时记录该模型，之后自动降级
+- 降级方案：提示词工具协议，工具描述写入系统提示词，模型以
+  ```` ```tool_call ```` 代码块输出调用，再解析为标准 `ToolCall`
+
+探测结果按 `base_url#model` 保存在 `~/.ifai/tool_capabilities.json`。
+*/
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use crate::core_traits::ai::{AIProviderConfig, Content, FunctionCall, Message, ToolCall};
+
+/// 已知不支持 function calling 的模型（小写名称片段）
+const NO_TOOL_MODELS: &[&str] = &[
+    "deepseek-reasoner",
+    "o1-mini",
+    "o1-preview",
+    "gemma",
+    "phi3",
+    "phi-3",
+    "llama2",
+    "codellama",
+    "deepseek-coder",
+    "starcoder",
+];
+
+/// 工具支持情况的来源
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CapabilitySource {
+    /// 默认认为支持
+    Default,
+    /// 内置注册表
+    Registry,
+    /// 运行时探测或用户手动设置
+    Detected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ToolSupport {
+    pub model: String,
+    pub supported: bool,
+    pub source: CapabilitySource,
+}
+
+static CAPABILITIES: OnceLock<Mutex<HashMap<String, bool>>> = OnceLock::new();
+
+fn capabilities() -> &'static Mutex<HashMap<String, bool>> {
+    CAPABILITIES.get_or_init(|| Mutex::new(load_capabilities()))
+}
+
+fn model_of(config: &AIProviderConfig) -> &str {
+    config.models.first().map(|m| m.as_str()).unwrap_or("")
+}
+
+fn cache_key(config: &AIProviderConfig) -> String {
+    format!("{}#{}", config.base_url.trim_end_matches('/'), model_of(config))
+}
+
+/// 注册表中是否标记为不支持工具
+pub fn in_registry(model: &str) -> bool {
+    let model = model.to_lowercase();
+    let name = model.rsplit('/').next().unwrap_or(&model);
+    NO_TOOL_MODELS.iter().any(|pattern| name.contains(pattern))
+}
+
+/// 查询模型的工具支持情况（探测结果优先于注册表）
+pub fn tool_support(config: &AIProviderConfig) -> ToolSupport {
+    let model = model_of(config).to_string();
+    let detected = capabilities().lock().ok().and_then(|map| map.get(&cache_key(config)).copied());
+    match detected {
+        Some(supported) => ToolSupport { model, supported, source: CapabilitySource::Detected },
+        None if in_registry(&model) => ToolSupport { model, supported: false, source: CapabilitySource::Registry },
+        None => ToolSupport { model, supported: true, source: CapabilitySource::Default },
+    }
+}
+
+pub fn supports_tools(config: &AIProviderConfig) -> bool {
+    tool_support(config).supported
+}
+
+/// 记录探测结果；`None` 清除记录，回到注册表判断
+pub fn record(config: &AIProviderConfig, supported: Option<bool>) -> Result<(), String> {
+    let mut map = capabilities().lock().map_err(|e| e.to_string())?;
+    match supported {
+        Some(value) => map.insert(cache_key(config), value),
+        None => map.remove(&cache_key(config)),
+    };
+    save_capabilities(&map)
+}
+
+/// 请求因工具参数被拒绝后调用，之后该模型走提示词协议
+pub fn mark_unsupported(config: &AIProviderConfig) {
+    println!("[ToolCapability] {} does not support tools, switching to prompt protocol", cache_key(config));
+    if let Err(e) = record(config, Some(false)) {
+        eprintln!("[ToolCapability] Failed to save capability: {}", e);
+    }
+}
+
+/// 判断错误是否由模型不支持工具调用导致
+pub fn is_tool_unsupported_error(status: u16, body: &str) -> bool {
+    if !matches!(status, 400 | 404 | 422) {
+        return false;
+    }
+    let body = body.to_lowercase();
+    let mentions_tools = body.contains("tool") || body.contains("function");
+    let rejected = ["not support", "unsupported", "not available", "not enabled", "unrecognized", "unknown field", "requires --enable-auto-tool-choice"]
+        .iter()
+        .any(|phrase| body.contains(phrase));
+    mentions_tools && rejected
+}
+
+// ============================================================================
+// Prompt Protocol
+// ============================================================================
+
+fn tool_call_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"(?s)```tool_call[ \t]*\r?\n(.*?)```").unwrap())
+}
+
+/// 生成提示词工具协议说明（工具格式为 OpenAI function schema）
+pub fn protocol_prompt(tools: &[Value]) -> String {
+    let tool_list = tools.iter()
+        .map(|tool| {
+            let function = tool.get("function").unwrap_or(tool);
+            format!(
+                "- {}: {}\n  parameters: {}",
+                function["name"].as_str().unwrap_or("unknown"),
+                function["description"].as_str().unwrap_or(""),
+                function.get("parameters").map(|p| p.to_string()).unwrap_or_else(|| "{}".to_string())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "## Tools\n\
+         You can call the following tools:\n{}\n\n\
+         To call a tool, reply with one fenced block per call, exactly in this format:\n\
+         ```tool_call\n{{\"name\": \"tool_name\", \"arguments\": {{\"key\": \"value\"}}}}\n```\n\
+         Stop after the tool blocks and wait for the results, which will be sent back as user messages starting with \"Tool result\".\n\
+         If no tool is needed, answer normally without any tool_call block.",
+        tool_list
+    )
+}
+
+/// 将消息转换为不依赖原生工具字段的形式：
+/// 工具说明并入系统提示词，历史中的工具调用和结果改写为纯文本
+pub fn to_prompt_messages(messages: &[Message], tools: &[Value]) -> Vec<Message> {
+    let protocol = protocol_prompt(tools);
+    let mut converted = Vec::with_capacity(messages.len() + 1);
+
+    for msg in messages {
+        match msg.role.as_str() {
+            "tool" => converted.push(Message {
+                role: "user".to_string(),
+                content: Content::Text(format!(
+                    "Tool result ({}):\n{}",
+                    msg.tool_call_id.as_deref().unwrap_or("unknown"),
+                    crate::intelligence_router::extract_text_content(&msg.content)
+                )),
+                tool_calls: None,
+                tool_call_id: None,
+            }),
+            "assistant" if msg.tool_calls.as_ref().is_some_and(|calls| !calls.is_empty()) => {
+                let mut text = crate::intelligence_router::extract_text_content(&msg.content);
+                for call in msg.tool_calls.iter().flatten() {
+                    let arguments: Value = serde_json::from_str(&call.function.arguments).unwrap_or(Value::Object(Default::default()));
+                    let block = serde_json::json!({ "name": call.function.name, "arguments": arguments });
+                    text.push_str(&format!("\n```tool_call\n{}\n```", block));
+                }
+                converted.push(Message {
+                    role: "assistant".to_string(),
+                    content: Content::Text(text.trim_start().to_string()),
+                    tool_calls: None,
+                    tool_call_id: None,
+                });
+            }
+            _ => converted.push(msg.clone()),
+        }
+    }
+
+    match converted.iter_mut().find(|m| m.role == "system") {
+        Some(system) => {
+            let text = crate::intelligence_router::extract_text_content(&system.content);
+            system.content = Content::Text(format!("{}\n\n{}", text, protocol));
+        }
+        None => converted.insert(0, Message {
+            role: "system".to_string(),
+            content: Content::Text(protocol),
+            tool_calls: None,
+            tool_call_id: None,
+        }),
+    }
+    converted
+}
+
+/// 从回复中解析 ```` ```tool_call ```` 代码块，返回去掉代码块后的正文和工具调用
+pub fn parse_tool_blocks(text: &str) -> (String, Vec<ToolCall>) {
+    let pattern = tool_call_pattern();
+    let mut calls = Vec::new();
+
+    for caps in pattern.captures_iter(text) {
+        let Ok(block) = serde_json::from_str::<Value>(caps[1].trim()) else {
+            eprintln!("[ToolCapability] Skipping malformed tool_call block");
+            continue;
+        };
+        let Some(name) = block["name"].as_str().filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        let arguments = match block.get("arguments") {
+            Some(Value::String(raw)) => raw.clone(),
+            Some(args) => args.to_string(),
+            None => "{}".to_string(),
+        };
+        calls.push(ToolCall {
+            id: format!("prompt_{}", uuid::Uuid::new_v4()),
+            r#type: "function".to_string(),
+            function: FunctionCall { name: name.to_string(), arguments },
+        });
+    }
+
+    if calls.is_empty() {
+        return (text.to_string(), calls);
+    }
+    (pattern.replace_all(text, "").trim().to_string(), calls)
+}
+
+/// 将提示词协议的回复转换为带 `tool_calls` 的标准消息
+pub fn into_tool_message(mut message: Message) -> Message {
+    let Content::Text(text) = &message.content else {
+        return message;
+    };
+    let (content, calls) = parse_tool_blocks(text);
+    if !calls.is_empty() {
+        message.content = Content::Text(content);
+        message.tool_calls = Some(calls);
+    }
+    message
+}
+
+// ============================================================================
+// Persistence
+// ============================================================================
+
+fn capabilities_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("tool_capabilities.json")
+}
+
+fn load_capabilities() -> HashMap<String, bool> {
+    std::fs::read_to_string(capabilities_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_capabilities(map: &HashMap<String, bool>) -> Result<(), String> {
+    let path = capabilities_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(map).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write tool capabilities: {}", e))
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_tool_support(provider_config: AIProviderConfig) -> ToolSupport {
+    tool_support(&provider_config)
+}
+
+/// 手动设置模型是否支持工具；`supported` 为空时清除记录
+#[tauri::command]
+pub fn set_tool_support(provider_config: AIProviderConfig, supported: Option<bool>) -> Result<ToolSupport, String> {
+    if provider_config.models.is_empty() {
+        return Err("Provider has no model configured".to_string());
+    }
+    record(&provider_config, supported)?;
+    Ok(tool_support(&provider_config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(role: &str, content: &str) -> Message {
+        Message { role: role.to_string(), content: Content::Text(content.to_string()), tool_calls: None, tool_call_id: None }
+    }
+
+    fn read_file_tool() -> Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": "agent_read_file",
+                "description": "Read a file",
+                "parameters": { "type": "object", "properties": { "rel_path": { "type": "string" } } }
+            }
+        })
+    }
+
+    #[test]
+    fn test_registry_and_error_detection() {
+        assert!(in_registry("deepseek-reasoner"));
+        assert!(in_registry("ollama/gemma2:9b"));
+        assert!(!in_registry("gpt-4o"));
+
+        assert!(is_tool_unsupported_error(400, r#"{"error":"registry.ollama.ai/library/gemma:2b does not support tools"}"#));
+        assert!(is_tool_unsupported_error(400, "tools is not supported with this model"));
+        assert!(!is_tool_unsupported_error(400, "invalid api key"));
+        assert!(!is_tool_unsupported_error(500, "tools not supported"));
+    }
+
+    #[test]
+    fn test_parse_tool_blocks() {
+        let reply = "Let me look.\n```tool_call\n{\"name\": \"agent_read_file\", \"arguments\": {\"rel_path\": \"src/main.rs\"}}\n```\n```tool_call\nnot json\n```";
+        let (content, calls) = parse_tool_blocks(reply);
+        assert_eq!(content, "Let me look.");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].function.name, "agent_read_file");
+        assert!(calls[0].id.starts_with("prompt_"));
+        let args: Value = serde_json::from_str(&calls[0].function.arguments).unwrap();
+        assert_eq!(args["rel_path"], "src/main.rs");
+
+        let (content, calls) = parse_tool_blocks("```rust\nfn main() {}\n```");
+        assert!(calls.is_empty());
+        assert_eq!(content, "```rust\nfn main() {}\n```");
+    }
+
+    #[test]
+    fn test_to_prompt_messages() {
+        let mut assistant = text("assistant", "");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            r#type: "function".to_string(),
+            function: FunctionCall { name: "agent_read_file".to_string(), arguments: r#"{"rel_path":"a.rs"}"#.to_string() },
+        }]);
+        let mut result = text("tool", "fn a() {}");
+        result.tool_call_id = Some("call_1".to_string());
+
+        let converted = to_prompt_messages(&[text("system", "You are helpful."), text("user", "read a.rs"), assistant, result], &[read_file_tool()]);
+        assert_eq!(converted.len(), 4);
+        assert!(converted.iter().all(|m| m.tool_calls.is_none() && m.role != "tool"));
+
+        let system = crate::intelligence_router::extract_text_content(&converted[0].content);
+        assert!(system.starts_with("You are helpful."));
+        assert!(system.contains("- agent_read_file: Read a file"));
+
+        let (_, calls) = parse_tool_blocks(&crate::intelligence_router::extract_text_content(&converted[2].content));
+        assert_eq!(calls[0].function.name, "agent_read_file");
+        assert_eq!(converted[3].role, "user");
+        assert!(crate::intelligence_router::extract_text_content(&converted[3].content).starts_with("Tool result (call_1)"));
+
+        let converted = to_prompt_messages(&[text("user", "hi")], &[read_file_tool()]);
+        assert_eq!(converted[0].role, "system");
+    }
+}
@@ -6,46 +6,35 @@ Layer 1: Exact Match Classification
 1. 斜杠命令：/read, /explore, /list 等
 2. Agent 函数调用：agent_xxx() 格式
 3. 纯命令：ls, git status, npm run 等
+4. 粘贴内容：堆栈标记（Traceback、panicked at 等）
 
 目标延迟：<1ms
 目标准确率：100%
 */
 
-use super::types::{ClassificationResult, ClassificationLayer, ToolCategory};
+use super::types::{ClassificationResult, ClassificationLayer, PasteClassification, PasteKind, ToolCategory};
 use std::collections::HashMap;
 
 // ============================================================================
 // Slash Commands
 // ============================================================================
 
-/// 斜杠命令映射
-const SLASH_COMMANDS: &[(&str, ToolCategory, &str)] = &[
-    ("/read", ToolCategory::FileOperations, "agent_read_file"),
-    ("/explore", ToolCategory::FileOperations, "agent_list_dir"),
-    ("/list", ToolCategory::FileOperations, "agent_list_dir"),
-    ("/scan", ToolCategory::FileOperations, "agent_list_dir"),
-    ("/search", ToolCategory::SearchOperations, "agent_search"),
-    ("/find", ToolCategory::SearchOperations, "agent_search"),
-    ("/help", ToolCategory::AiChat, "help"),
-];
-
 /// 处理斜杠命令
+///
+/// 命令定义统一来自 `slash_commands` 注册表（内置命令）。只有对应单一工具的命令
+/// （/read、/explore、/search、/help 等）是精确匹配；/test、/commit 这类展开为
+/// 提示词或 Agent 任务的命令没有确定的工具，仍交给后续层分类。
 fn classify_slash_command(input: &str) -> Option<ClassificationResult> {
-    // 提取命令和参数
-    let parts: Vec<&str> = input.splitn(2, ' ').collect();
-    let command = parts[0];
-
-    for &(cmd, category, tool) in SLASH_COMMANDS {
-        if command == cmd {
-            return Some(ClassificationResult::layer1(
-                category,
-                Some(tool.to_string()),
-                "slash_command",
-            ));
-        }
-    }
-
-    None
+    let parsed = crate::slash_commands::parse_slash_command(input)?;
+    let registry = crate::slash_commands::SlashCommandRegistry::builtin();
+    let spec = registry.find(&parsed.name)?;
+    let tool = spec.tool.clone()?;
+
+    Some(ClassificationResult::layer1(
+        spec.category,
+        Some(tool),
+        "slash_command",
+    ))
 }
 
 // ============================================================================
@@ -57,10 +46,12 @@ const AGENT_FUNCTIONS: &[(&str, ToolCategory)] = &[
     ("agent_read_file", ToolCategory::FileOperations),
     ("agent_list_dir", ToolCategory::FileOperations),
     ("agent_write_file", ToolCategory::FileOperations),
+    ("agent_edit_file", ToolCategory::FileOperations),
     ("agent_create_file", ToolCategory::FileOperations),
     ("agent_delete_file", ToolCategory::FileOperations),
     ("agent_rename_file", ToolCategory::FileOperations),
     ("agent_search", ToolCategory::SearchOperations),
+    ("agent_grep", ToolCategory::SearchOperations),
     ("agent_find_references", ToolCategory::SearchOperations),
     ("agent_find_definition", ToolCategory::SearchOperations),
 ];
@@ -227,6 +218,36 @@ pub fn classify(input: &str) -> Option<ClassificationResult> {
     None
 }
 
+// ============================================================================
+// Pasted Content
+// ============================================================================
+
+/// 精确的堆栈标记及其语言
+const STACK_TRACE_MARKERS: &[(&str, &str)] = &[
+    ("Traceback (most recent call last)", "python"),
+    ("panicked at", "rust"),
+    ("stack backtrace:", "rust"),
+    ("Exception in thread", "java"),
+    ("goroutine ", "go"),
+];
+
+/// Layer 1 粘贴内容分类：识别堆栈
+pub fn classify_paste(text: &str) -> Option<PasteClassification> {
+    for (marker, lang) in STACK_TRACE_MARKERS {
+        if text.contains(marker) {
+            return Some(PasteClassification::layer1(PasteKind::StackTrace, Some(lang), "stack_marker"));
+        }
+    }
+
+    // JS/Java 风格的 "    at foo (file.js:1:2)" 连续出现
+    let at_lines = text.lines().filter(|l| l.trim_start().starts_with("at ")).count();
+    if at_lines >= 2 {
+        let lang = if text.contains(".java:") { "java" } else { "javascript" };
+        return Some(PasteClassification::layer1(PasteKind::StackTrace, Some(lang), "stack_frames"));
+    }
+    None
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -264,6 +285,19 @@ mod tests {
         assert_eq!(result.category, ToolCategory::SearchOperations);
     }
 
+    #[test]
+    fn test_slash_command_aliases() {
+        assert_eq!(classify("/scan src").unwrap().tool, Some("agent_list_dir".to_string()));
+        assert_eq!(classify("/find useState").unwrap().tool, Some("agent_search".to_string()));
+    }
+
+    #[test]
+    fn test_slash_command_without_tool_not_exact() {
+        // /test、/commit 展开为 Agent 任务或提示词，不属于精确匹配
+        assert!(classify("/test src/lib.rs").is_none());
+        assert!(classify("/commit fix typo").is_none());
+    }
+
     // Agent Function Tests
     #[test]
     fn test_agent_function_read_file() {
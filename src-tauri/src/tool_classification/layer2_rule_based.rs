@@ -9,12 +9,14 @@ Layer 2: Rule-Based Classification
 4. 代码分析关键词
 5. 搜索操作关键词
 6. AI 对话关键词
+7. 粘贴内容：日志行 / 代码特征占比
 
 目标延迟：<5ms
 目标准确率：90%+
 */
 
-use super::types::{ClassificationResult, ClassificationLayer, ToolCategory};
+use super::types::{ClassificationResult, ClassificationLayer, PasteClassification, PasteKind, ToolCategory};
+use regex::Regex;
 
 // ============================================================================
 // Rule Definitions
@@ -261,6 +263,16 @@ pub fn classify(input: &str) -> Option<ClassificationResult> {
         return None; // 延迟到 Layer3 进行语义分析
     }
 
+    apply_rules(input)
+}
+
+/// 不做长度与复杂度检查的最佳猜测（v0.3.4: Layer 3 超时或不可用时的回退）
+pub fn best_guess(input: &str) -> Option<ClassificationResult> {
+    apply_rules(input)
+}
+
+/// 按优先级依次尝试各条规则
+fn apply_rules(input: &str) -> Option<ClassificationResult> {
     // 首先检查明确的问句格式（最高优先级）
     let has_question_mark = input.contains('?') || input.contains('？');
     let is_question_format = CHAT_KEYWORDS_CN.iter().any(|kw| input.starts_with(kw))
@@ -305,6 +317,69 @@ pub fn classify(input: &str) -> Option<ClassificationResult> {
     rule_ai_chat(input)
 }
 
+// ============================================================================
+// Pasted Content
+// ============================================================================
+
+/// 判定为日志所需的日志行占比
+const LOG_LINE_THRESHOLD: f32 = 0.5;
+/// 判定为代码所需的代码行占比
+const CODE_LINE_THRESHOLD: f32 = 0.3;
+
+/// 日志行占比
+fn log_line_ratio(lines: &[&str]) -> f32 {
+    let re = Regex::new(
+        r"(?i)^\s*(\[?\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}|\[?\d{2}:\d{2}:\d{2}|\[?(trace|debug|info|warn|warning|error|fatal)\b)"
+    ).unwrap();
+    let matched = lines.iter().filter(|l| re.is_match(l)).count();
+    matched as f32 / lines.len().max(1) as f32
+}
+
+/// 代码特征占比
+fn code_line_ratio(lines: &[&str]) -> f32 {
+    let re = Regex::new(
+        r"(^\s*(fn|pub|impl|use|let|const|function|import|export|class|def|return|if|for|while|struct|interface|package|#include)\b)|[{};]\s*$|=>|::"
+    ).unwrap();
+    let non_empty: Vec<_> = lines.iter().filter(|l| !l.trim().is_empty()).collect();
+    let matched = non_empty.iter().filter(|l| re.is_match(l)).count();
+    matched as f32 / non_empty.len().max(1) as f32
+}
+
+/// 根据关键词推测代码语言
+fn guess_code_language(text: &str) -> Option<&'static str> {
+    const RULES: &[(&[&str], &str)] = &[
+        (&["fn ", "let mut ", "impl ", "pub struct", "use std::"], "rust"),
+        (&["def ", "import ", "self.", "elif "], "python"),
+        (&["interface ", ": string", ": number", "export type"], "typescript"),
+        (&["function ", "const ", "=> {", "require("], "javascript"),
+        (&["package main", "func ", ":= "], "go"),
+        (&["public class", "private ", "System.out"], "java"),
+        (&["#include", "std::", "int main("], "cpp"),
+    ];
+    RULES.iter()
+        .map(|(keywords, lang)| (keywords.iter().filter(|k| text.contains(*k)).count(), *lang))
+        .filter(|(score, _)| *score > 0)
+        .max_by_key(|(score, _)| *score)
+        .map(|(_, lang)| lang)
+}
+
+/// Layer 2 粘贴内容分类：按日志行与代码特征占比打分
+pub fn classify_paste(text: &str) -> Option<PasteClassification> {
+    let lines: Vec<&str> = text.lines().collect();
+
+    let log_ratio = log_line_ratio(&lines);
+    if log_ratio >= LOG_LINE_THRESHOLD {
+        return Some(PasteClassification::layer2(PasteKind::Log, None, log_ratio, "log_lines"));
+    }
+
+    let code_ratio = code_line_ratio(&lines);
+    if code_ratio >= CODE_LINE_THRESHOLD {
+        let confidence = (0.5 + code_ratio / 2.0).min(1.0);
+        return Some(PasteClassification::layer2(PasteKind::Code, guess_code_language(text), confidence, "code_lines"));
+    }
+    None
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
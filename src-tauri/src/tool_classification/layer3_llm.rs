@@ -5,17 +5,29 @@ Layer 3: LLM Classification
 工具分类 Layer 3 实现
 
 商业版：使用 ifainew-core 私有库的 LLM 推理
-社区版：使用关键词回退 (Mock)
+社区版：启用 llm-inference 时使用本地模型（Qwen 0.5B），输出由 GBNF 语法约束为一个类别名
+未启用推理、推理失败或超出延迟预算时：回退到 Layer 2 的最佳猜测，再回退到关键词规则 (Mock)
 
 目标延迟：<300ms
 目标准确率：85%+
 */
 
 use super::types::{ClassificationResult, ClassificationLayer, ToolCategory};
+use crate::llm_inference::SamplingParams;
 
 // 条件导入：仅当启用 llm-inference feature 时可用
 #[cfg(feature = "llm-inference")]
-use crate::llm_inference::generate_completion;
+use crate::model_registry::ModelTask;
+#[cfg(feature = "llm-inference")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "llm-inference")]
+use std::time::Duration;
+
+// 社区版：带语法约束的本地推理
+#[cfg(all(feature = "llm-inference", not(feature = "commercial")))]
+use crate::llm_inference::generate_constrained_for;
+#[cfg(all(feature = "llm-inference", feature = "commercial"))]
+use crate::llm_inference::generate_completion_for;
 
 // 商业版：导入私有库 ifainew-core
 #[cfg(feature = "commercial")]
@@ -28,10 +40,149 @@ use ifainew_core::tool_classification::{
 // Fallback Logic (社区版 Mock)
 // ============================================================================
 
-/// 社区版回退分类（使用 Mock 模块）
+/// 回退结果的置信度上限
+const FALLBACK_CONFIDENCE: f32 = 0.6;
+
+/// 回退分类：优先使用 Layer 2 规则的最佳猜测，没有匹配时使用 Mock 关键词回退
 fn fallback_classify(input: &str) -> ClassificationResult {
-    // 委托给 mock 模块的统一回退逻辑
-    super::mock::classify_layer3_mock(input)
+    match super::layer2_rule_based::best_guess(input) {
+        Some(guess) => ClassificationResult {
+            layer: ClassificationLayer::Layer3,
+            category: guess.category,
+            tool: None,
+            confidence: guess.confidence.min(FALLBACK_CONFIDENCE),
+            match_type: "fallback".to_string(),
+        },
+        // 委托给 mock 模块的统一回退逻辑
+        None => super::mock::classify_layer3_mock(input),
+    }
+}
+
+// ============================================================================
+// Local Inference (v0.3.4)
+// ============================================================================
+
+/// Layer 3 推理的延迟预算（含等待模型锁与首次加载模型）
+#[cfg(feature = "llm-inference")]
+const LATENCY_BUDGET: Duration = Duration::from_millis(300);
+
+/// 类别名最多需要的 token 数
+#[cfg(all(feature = "llm-inference", not(feature = "commercial")))]
+const MAX_CATEGORY_TOKENS: usize = 8;
+
+/// 语法约束下本地模型结果的置信度（约束输出不提供概率）
+#[cfg(all(feature = "llm-inference", not(feature = "commercial")))]
+const LLM_CONFIDENCE: f32 = 0.8;
+
+#[cfg(all(feature = "llm-inference", not(feature = "commercial")))]
+const CATEGORIES: [ToolCategory; 7] = [
+    ToolCategory::FileOperations,
+    ToolCategory::CodeGeneration,
+    ToolCategory::CodeAnalysis,
+    ToolCategory::TerminalCommands,
+    ToolCategory::AiChat,
+    ToolCategory::SearchOperations,
+    ToolCategory::NoToolNeeded,
+];
+
+/// GBNF 语法：输出只能是一个类别名
+#[cfg(all(feature = "llm-inference", not(feature = "commercial")))]
+fn category_grammar() -> String {
+    let names: Vec<String> = CATEGORIES.iter().map(|c| format!("\"{}\"", c.display_name())).collect();
+    format!("root ::= {}", names.join(" | "))
+}
+
+/// Qwen 对话格式的分类提示词
+#[cfg(all(feature = "llm-inference", not(feature = "commercial")))]
+fn build_prompt(input: &str) -> String {
+    let categories: String = CATEGORIES
+        .iter()
+        .map(|c| format!("- {}: {}\n", c.display_name(), c.description()))
+        .collect();
+    format!(
+        "<|im_start|>system\nClassify the user's request for a code editor into exactly one category. Answer with the category name only.\n{}<|im_end|>\n<|im_start|>user\n{}<|im_end|>\n<|im_start|>assistant\n",
+        categories, input
+    )
+}
+
+#[cfg(all(feature = "llm-inference", not(feature = "commercial")))]
+fn parse_category(output: &str) -> Option<ToolCategory> {
+    let output = output.trim();
+    CATEGORIES.into_iter().find(|c| c.display_name() == output)
+}
+
+/// 同一时间只运行一个 Layer 3 推理，避免超时的推理在后台堆积
+#[cfg(feature = "llm-inference")]
+static IN_FLIGHT: AtomicBool = AtomicBool::new(false);
+
+/// Layer 3 未给出结果的原因
+#[cfg(feature = "llm-inference")]
+#[derive(Debug, PartialEq)]
+enum Layer3Miss {
+    /// 上一次推理仍在进行
+    Busy,
+    Timeout,
+    Failed(String),
+}
+
+#[cfg(feature = "llm-inference")]
+impl std::fmt::Display for Layer3Miss {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layer3Miss::Busy => write!(f, "busy with a previous classification"),
+            Layer3Miss::Timeout => write!(f, "exceeded the {}ms latency budget", LATENCY_BUDGET.as_millis()),
+            Layer3Miss::Failed(e) => write!(f, "failed: {}", e),
+        }
+    }
+}
+
+/// 在延迟预算内运行推理；超时后推理在后台线程结束并释放模型锁，不取消其他生成
+#[cfg(feature = "llm-inference")]
+fn run_with_budget<F>(budget: Duration, task: F) -> Result<ClassificationResult, Layer3Miss>
+where
+    F: FnOnce() -> Result<ClassificationResult, String> + Send + 'static,
+{
+    if IN_FLIGHT.swap(true, Ordering::SeqCst) {
+        return Err(Layer3Miss::Busy);
+    }
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let result = task();
+        IN_FLIGHT.store(false, Ordering::SeqCst);
+        let _ = tx.send(result);
+    });
+    match rx.recv_timeout(budget) {
+        Ok(result) => result.map_err(Layer3Miss::Failed),
+        Err(_) => Err(Layer3Miss::Timeout),
+    }
+}
+
+/// 社区版：本地模型推理，输出约束为类别名
+#[cfg(all(feature = "llm-inference", not(feature = "commercial")))]
+fn infer(input: String, sampling: SamplingParams) -> Result<ClassificationResult, String> {
+    let output = generate_constrained_for(ModelTask::Classification, &build_prompt(&input), MAX_CATEGORY_TOKENS, &sampling, &category_grammar())
+        .map_err(|e| e.to_string())?;
+    let category = parse_category(&output).ok_or_else(|| format!("unexpected output {:?}", output))?;
+    Ok(ClassificationResult::layer3(category, LLM_CONFIDENCE))
+}
+
+/// 商业版：使用 ifainew-core 的 LLM 分类
+#[cfg(all(feature = "llm-inference", feature = "commercial"))]
+fn infer(input: String, sampling: SamplingParams) -> Result<ClassificationResult, String> {
+    let llm_generate = |prompt: &str, max_tokens: usize| -> Result<String, Box<dyn std::error::Error>> {
+        // 调用本地的 llama.cpp 推理（使用分类任务选择的模型）
+        generate_completion_for(ModelTask::Classification, prompt, max_tokens, &sampling).map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
+    };
+
+    let core_result = core_classify_with_llm(&input, llm_generate).map_err(|_| "ifainew-core classification failed".to_string())?;
+    // 转换类型
+    Ok(ClassificationResult {
+        layer: ClassificationLayer::Layer3,
+        category: convert_core_category(core_result.category),
+        tool: None,
+        confidence: core_result.confidence,
+        match_type: core_result.match_type,
+    })
 }
 
 // ============================================================================
@@ -56,37 +207,33 @@ fn convert_core_category(category: CoreToolCategory) -> ToolCategory {
 // Public API
 // ============================================================================
 
-/// Layer 3 分类入口 - 商业版（使用 ifainew-core 私有库）
-#[cfg(all(feature = "llm-inference", feature = "commercial"))]
+/// Layer 3 分类入口（默认采样参数）
 pub fn classify(input: &str) -> ClassificationResult {
-    // 商业版：使用 ifainew-core 的 LLM 分类
-    let llm_generate = |prompt: &str, max_tokens: usize| -> Result<String, Box<dyn std::error::Error>> {
-        // 调用本地的 llama.cpp 推理
-        generate_completion(prompt, max_tokens).map_err(|e| Box::new(e) as Box<dyn std::error::Error>)
-    };
+    classify_with(input, &SamplingParams::default())
+}
 
-    match core_classify_with_llm(input, llm_generate) {
-        Ok(core_result) => {
-            // 转换类型
-            ClassificationResult {
-                layer: ClassificationLayer::Layer3,
-                category: convert_core_category(core_result.category),
-                tool: None,
-                confidence: core_result.confidence,
-                match_type: core_result.match_type,
+/// Layer 3 分类入口 - 本地推理
+///
+/// 采样参数透传给本地推理，固定参数下分类结果可复现；
+/// 超出延迟预算或推理失败时使用 Layer 2 的最佳猜测
+#[cfg(feature = "llm-inference")]
+pub fn classify_with(input: &str, sampling: &SamplingParams) -> ClassificationResult {
+    let (owned, sampling) = (input.to_string(), *sampling);
+    match run_with_budget(LATENCY_BUDGET, move || infer(owned, sampling)) {
+        Ok(result) => result,
+        Err(miss) => {
+            println!("[ToolClassification] Layer 3 {}, using fallback", miss);
+            if miss == Layer3Miss::Timeout {
+                super::stats::record_timeout();
             }
-        }
-        Err(_) => {
-            // 私有库失败，使用回退
             fallback_classify(input)
         }
     }
 }
 
-/// Layer 3 分类入口 - 社区版（只使用 Mock 回退）
-#[cfg(not(all(feature = "llm-inference", feature = "commercial")))]
-pub fn classify(input: &str) -> ClassificationResult {
-    // 社区版：直接使用 Mock 回退逻辑
+/// Layer 3 分类入口 - 未启用本地推理（只使用回退）
+#[cfg(not(feature = "llm-inference"))]
+pub fn classify_with(input: &str, _sampling: &SamplingParams) -> ClassificationResult {
     // 不包含任何 LLM 推理核心代码
     fallback_classify(input)
 }
@@ -124,12 +271,49 @@ mod tests {
     }
 
     #[test]
+    #[cfg(not(feature = "llm-inference"))]
     fn test_classify_match_type_is_fallback() {
         let result = classify("任何输入");
-        // 社区版应该使用 fallback
+        // 未启用本地推理时应该使用 fallback
         assert_eq!(result.match_type, "fallback");
     }
 
+    #[test]
+    fn test_fallback_uses_layer2_best_guess() {
+        // 超过 Layer 2 长度限制的输入仍按规则猜测类别，置信度受限
+        let result = fallback_classify("please search the whole repository for usages of this helper");
+        assert_eq!(result.category, ToolCategory::SearchOperations);
+        assert_eq!(result.layer, ClassificationLayer::Layer3);
+        assert_eq!(result.match_type, "fallback");
+        assert!(result.confidence <= FALLBACK_CONFIDENCE);
+    }
+
+    #[test]
+    #[cfg(all(feature = "llm-inference", not(feature = "commercial")))]
+    fn test_constrained_output() {
+        let grammar = category_grammar();
+        assert!(grammar.starts_with("root ::= \"file_operations\" | "));
+        assert!(grammar.ends_with("\"no_tool_needed\""));
+        assert!(build_prompt("整理依赖").ends_with("<|im_start|>user\n整理依赖<|im_end|>\n<|im_start|>assistant\n"));
+        assert_eq!(parse_category(" code_analysis\n"), Some(ToolCategory::CodeAnalysis));
+        assert_eq!(parse_category("code"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "llm-inference")]
+    fn test_latency_budget() {
+        let slow = || {
+            std::thread::sleep(Duration::from_millis(200));
+            Ok(ClassificationResult::layer3(ToolCategory::AiChat, 0.8))
+        };
+        assert_eq!(run_with_budget(Duration::from_millis(20), slow).unwrap_err(), Layer3Miss::Timeout);
+        // 超时的推理结束前不再启动新的推理
+        assert_eq!(run_with_budget(Duration::from_millis(20), slow).unwrap_err(), Layer3Miss::Busy);
+        std::thread::sleep(Duration::from_millis(300));
+        let fast = run_with_budget(Duration::from_millis(500), || Ok(ClassificationResult::layer3(ToolCategory::CodeAnalysis, 0.8)));
+        assert_eq!(fast.unwrap().category, ToolCategory::CodeAnalysis);
+    }
+
     #[test]
     #[cfg(feature = "commercial")]
     fn test_convert_core_category() {
@@ -20,6 +20,9 @@ mod layer3_llm;
 // 社区版 Mock 实现
 mod mock;
 
+// v0.3.4 新增：按层命中率与延迟统计
+mod stats;
+
 pub mod types;
 
 // 重新导出主要类型
@@ -27,6 +30,8 @@ pub use types::{
     ToolCategory,
     ClassificationResult,
     ClassificationLayer,
+    PasteKind,
+    PasteClassification,
 };
 
 // 重新导出版本信息
@@ -40,6 +45,8 @@ pub fn get_edition_info_command() -> String {
 
 use std::collections::HashMap;
 
+use crate::llm_inference::SamplingParams;
+
 // ============================================================================
 // Public API
 // ============================================================================
@@ -53,6 +60,13 @@ use std::collections::HashMap;
  * 3. Layer 3: LLM 推理（Qwen 0.5B 本地分类）
  */
 pub fn classify_tool(input: &str) -> ClassificationResult {
+    classify_tool_with(input, &SamplingParams::default())
+}
+
+/**
+ * 工具分类（指定 Layer 3 推理的采样参数）
+ */
+pub fn classify_tool_with(input: &str, sampling: &SamplingParams) -> ClassificationResult {
     let input = input.trim();
 
     // 空输入处理
@@ -66,6 +80,14 @@ pub fn classify_tool(input: &str) -> ClassificationResult {
         };
     }
 
+    let start = Instant::now();
+    let result = classify_layers(input, sampling);
+    stats::record(&result, start.elapsed());
+    result
+}
+
+/// 依次尝试三层分类
+fn classify_layers(input: &str, sampling: &SamplingParams) -> ClassificationResult {
     // Layer 1: 精确匹配
     if let Some(result) = layer1_exact_match::classify(input) {
         return result;
@@ -77,7 +99,24 @@ pub fn classify_tool(input: &str) -> ClassificationResult {
     }
 
     // Layer 3: LLM 推理
-    layer3_llm::classify(input)
+    layer3_llm::classify_with(input, sampling)
+}
+
+/**
+ * 快速分类（不调用 LLM）
+ *
+ * Layer 1 → Layer 2 → Mock 启发式，适合请求构建等热路径
+ */
+pub fn classify_tool_fast(input: &str) -> ClassificationResult {
+    let input = input.trim();
+
+    if let Some(result) = layer1_exact_match::classify(input) {
+        return result;
+    }
+    if let Some(result) = layer2_rule_based::classify(input) {
+        return result;
+    }
+    mock::classify_layer3_mock(input)
 }
 
 /**
@@ -87,6 +126,22 @@ pub fn classify_tools(inputs: &[&str]) -> Vec<ClassificationResult> {
     inputs.iter().map(|input| classify_tool(input)).collect()
 }
 
+/**
+ * 粘贴内容分类（v0.3.4）
+ *
+ * 复用前两层：Layer 1 识别堆栈标记，Layer 2 按日志行 / 代码特征占比打分，
+ * 均未命中时视为普通文本。不调用 LLM。
+ */
+pub fn classify_paste(text: &str) -> PasteClassification {
+    if let Some(result) = layer1_exact_match::classify_paste(text) {
+        return result;
+    }
+    if let Some(result) = layer2_rule_based::classify_paste(text) {
+        return result;
+    }
+    PasteClassification::layer2(PasteKind::Text, None, 0.5, "plain_text")
+}
+
 // ============================================================================
 // Tauri Commands
 // ============================================================================
@@ -95,16 +150,23 @@ use std::time::Instant;
 use crate::tool_classification::types::{ClassifyToolResponse, BatchClassifyResponse};
 
 /// Tauri 命令：工具分类
+///
+/// `sampling` 用于 Layer 3 推理；结果经过 Layer 3 时在响应中附带实际使用的参数
 #[tauri::command]
-pub fn tool_classify(input: String) -> ClassifyToolResponse {
+pub fn tool_classify(input: String, sampling: Option<SamplingParams>) -> Result<ClassifyToolResponse, String> {
+    let sampling = sampling.unwrap_or_default();
+    sampling.validate()?;
+
     let start = Instant::now();
-    let result = classify_tool(&input);
+    let result = classify_tool_with(&input, &sampling);
     let latency_ms = start.elapsed().as_millis() as u64;
+    let sampling = (result.layer == ClassificationLayer::Layer3).then_some(sampling);
 
-    ClassifyToolResponse {
+    Ok(ClassifyToolResponse {
         result,
         latency_ms,
-    }
+        sampling,
+    })
 }
 
 /// Tauri 命令：批量工具分类
@@ -123,6 +185,18 @@ pub fn tool_batch_classify(inputs: Vec<String>) -> BatchClassifyResponse {
     }
 }
 
+/// Tauri 命令：各层分类命中率与平均延迟
+#[tauri::command]
+pub fn classification_stats() -> stats::ClassificationStats {
+    stats::snapshot()
+}
+
+/// Tauri 命令：清空分类统计
+#[tauri::command]
+pub fn reset_classification_stats() {
+    stats::reset();
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -0,0 +1,119 @@
+/*!
+Classification Stats - 分类统计
+===============================
+
+按层统计命中次数与平均延迟（v0.3.4）：
+
+- Layer 3 区分本地模型给出的结果与回退结果（超时、推理失败或未启用推理）
+- 通过 `classification_stats` 命令查询，用于评估 Layer 1/2 的规则覆盖率与 Layer 3 的延迟预算
+*/
+
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+use super::types::{ClassificationLayer, ClassificationResult};
+
+/// Layer 3 本地模型结果的匹配类型
+pub const LLM_MATCH_TYPE: &str = "llm_classification";
+
+/// 单层统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LayerStats {
+    pub hits: u64,
+    /// 占全部分类的比例
+    pub hit_rate: f64,
+    pub avg_latency_ms: f64,
+    #[serde(skip)]
+    total_latency_ms: f64,
+}
+
+impl LayerStats {
+    fn record(&mut self, latency: Duration) {
+        self.hits += 1;
+        self.total_latency_ms += latency.as_secs_f64() * 1000.0;
+        self.avg_latency_ms = self.total_latency_ms / self.hits as f64;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClassificationStats {
+    pub total: u64,
+    pub layer1: LayerStats,
+    pub layer2: LayerStats,
+    /// Layer 3 由本地模型给出的结果
+    pub layer3_llm: LayerStats,
+    /// Layer 3 回退到 Layer 2 最佳猜测或关键词规则的结果
+    pub layer3_fallback: LayerStats,
+    /// 超出延迟预算的次数（包含在 `layer3_fallback` 中）
+    pub layer3_timeouts: u64,
+}
+
+impl ClassificationStats {
+    fn record(&mut self, result: &ClassificationResult, latency: Duration) {
+        self.total += 1;
+        match result.layer {
+            ClassificationLayer::Layer1 => self.layer1.record(latency),
+            ClassificationLayer::Layer2 => self.layer2.record(latency),
+            ClassificationLayer::Layer3 if result.match_type == LLM_MATCH_TYPE => self.layer3_llm.record(latency),
+            ClassificationLayer::Layer3 => self.layer3_fallback.record(latency),
+        }
+        let total = self.total as f64;
+        for layer in [&mut self.layer1, &mut self.layer2, &mut self.layer3_llm, &mut self.layer3_fallback] {
+            layer.hit_rate = layer.hits as f64 / total;
+        }
+    }
+}
+
+fn stats() -> &'static Mutex<ClassificationStats> {
+    static STATS: OnceLock<Mutex<ClassificationStats>> = OnceLock::new();
+    STATS.get_or_init(|| Mutex::new(ClassificationStats::default()))
+}
+
+/// 记录一次分类结果
+pub fn record(result: &ClassificationResult, latency: Duration) {
+    if let Ok(mut stats) = stats().lock() {
+        stats.record(result, latency);
+    }
+}
+
+/// 记录一次 Layer 3 超时
+pub fn record_timeout() {
+    if let Ok(mut stats) = stats().lock() {
+        stats.layer3_timeouts += 1;
+    }
+}
+
+pub fn snapshot() -> ClassificationStats {
+    stats().lock().map(|stats| stats.clone()).unwrap_or_default()
+}
+
+pub fn reset() {
+    if let Ok(mut stats) = stats().lock() {
+        *stats = ClassificationStats::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool_classification::types::ToolCategory;
+
+    #[test]
+    fn test_hit_rates_and_latency() {
+        let mut stats = ClassificationStats::default();
+        stats.record(&ClassificationResult::layer1(ToolCategory::TerminalCommands, None, "pure_command"), Duration::from_millis(1));
+        stats.record(&ClassificationResult::layer2(ToolCategory::FileOperations, 0.9, "keyword_file_operations"), Duration::from_millis(3));
+        stats.record(&ClassificationResult::layer3(ToolCategory::CodeAnalysis, 0.8), Duration::from_millis(120));
+        stats.record(&ClassificationResult::layer3(ToolCategory::CodeAnalysis, 0.8), Duration::from_millis(180));
+        let mut fallback = ClassificationResult::layer3(ToolCategory::AiChat, 0.55);
+        fallback.match_type = "fallback".to_string();
+        stats.record(&fallback, Duration::from_millis(300));
+
+        assert_eq!(stats.total, 5);
+        assert_eq!((stats.layer3_llm.hits, stats.layer3_fallback.hits), (2, 1));
+        assert!((stats.layer3_llm.avg_latency_ms - 150.0).abs() < 1e-9);
+        assert!((stats.layer1.hit_rate - 0.2).abs() < 1e-9);
+        assert!((stats.layer3_llm.hit_rate - 0.4).abs() < 1e-9);
+    }
+}
@@ -178,6 +178,63 @@ impl ClassificationResult {
     }
 }
 
+// ============================================================================
+// Pasted Content
+// ============================================================================
+
+/// 粘贴内容类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PasteKind {
+    StackTrace,
+    Log,
+    Code,
+    Text,
+}
+
+/// 粘贴内容分类结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PasteClassification {
+    /// 分类层级
+    pub layer: ClassificationLayer,
+
+    /// 内容类型
+    pub kind: PasteKind,
+
+    /// 推测的语言（用于代码围栏）
+    pub language: Option<String>,
+
+    /// 置信度 (0.0 - 1.0)
+    pub confidence: f32,
+
+    /// 匹配类型
+    pub match_type: String,
+}
+
+impl PasteClassification {
+    /// 创建 Layer 1 结果
+    pub fn layer1(kind: PasteKind, language: Option<&str>, match_type: &str) -> Self {
+        Self {
+            layer: ClassificationLayer::Layer1,
+            kind,
+            language: language.map(|l| l.to_string()),
+            confidence: 1.0,
+            match_type: match_type.to_string(),
+        }
+    }
+
+    /// 创建 Layer 2 结果
+    pub fn layer2(kind: PasteKind, language: Option<&str>, confidence: f32, match_type: &str) -> Self {
+        Self {
+            layer: ClassificationLayer::Layer2,
+            kind,
+            language: language.map(|l| l.to_string()),
+            confidence,
+            match_type: match_type.to_string(),
+        }
+    }
+}
+
 // ============================================================================
 // Tauri Command Types
 // ============================================================================
@@ -193,6 +250,9 @@ pub struct ClassifyToolRequest {
 pub struct ClassifyToolResponse {
     pub result: ClassificationResult,
     pub latency_ms: u64,
+    /// Layer 3 推理使用的采样参数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<crate::llm_inference::SamplingParams>,
 }
 
 /// 批量分类请求
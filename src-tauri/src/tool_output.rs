@@ -0,0 +1,246 @@
+/*!
+Tool Output - 长工具输出摘要
+============================
+
+扫描结果 JSON、测试日志等超长工具输出原样进入 Agent 历史会迅速耗尽上下文。
+
+- 完整输出照常通过 `ToolResult` 事件发送并写入运行记录（`.ifai/agent_runs/`）
+- 超过阈值时，模型可见的历史中只插入结构化摘要：规模、JSON 键统计、错误摘录、首尾若干行
+- 摘要附带句柄，模型可调用 `agent_read_tool_output` 分段读取完整内容；
+  单行 JSON（如扫描结果）先格式化为多行再保存，每页同时受行数与字符数限制，超长的单行按字符分段
+- `agent_read_tool_output` 自身的结果不再摘要（每页已在 `MAX_PAGE_CHARS` 以内）
+- 完整输出在运行结束时释放
+*/
+
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+/// 超过该字符数的输出会被摘要
+pub const SUMMARY_THRESHOLD_CHARS: usize = 8_000;
+/// 摘要中保留的首尾行数
+const EDGE_LINES: usize = 15;
+/// 摘要中最多列出的错误行
+const MAX_ERROR_EXCERPTS: usize = 10;
+/// 单行最大字符数
+const MAX_LINE_CHARS: usize = 200;
+/// `agent_read_tool_output` 默认每次返回的行数
+pub const DEFAULT_READ_LINES: usize = 200;
+/// `agent_read_tool_output` 每次最多返回的行数
+const MAX_READ_LINES: usize = 500;
+/// `agent_read_tool_output` 每页最多返回的字符数（低于摘要阈值）
+const MAX_PAGE_CHARS: usize = 6_000;
+
+/// 句柄（`{channel}/{tool_call_id}`）-> 完整输出
+static FULL_OUTPUTS: OnceLock<Mutex<HashMap<String, String>>> = OnceLock::new();
+
+fn full_outputs() -> &'static Mutex<HashMap<String, String>> {
+    FULL_OUTPUTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn truncate_line(line: &str) -> String {
+    if line.chars().count() > MAX_LINE_CHARS {
+        format!("{}…", line.chars().take(MAX_LINE_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// JSON 顶层结构统计
+fn describe_json(value: &Value) -> Option<String> {
+    let describe = |v: &Value| match v {
+        Value::Array(a) => format!("array ({} items)", a.len()),
+        Value::Object(o) => format!("object ({} keys)", o.len()),
+        Value::String(s) if s.chars().count() > 60 => format!("string ({} chars)", s.chars().count()),
+        other => other.to_string(),
+    };
+    match value {
+        Value::Object(map) => Some(
+            map.iter()
+                .map(|(k, v)| format!("  {}: {}", k, describe(v)))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        Value::Array(items) => Some(format!("  top-level array ({} items)", items.len())),
+        _ => None,
+    }
+}
+
+/// 生成结构化摘要
+pub fn summarize(tool_name: &str, output: &str, handle: &str) -> String {
+    let lines: Vec<&str> = output.lines().collect();
+    let mut out = format!(
+        "[Output of {} summarized: {} chars, {} lines. Full output handle: \"{}\" — call agent_read_tool_output with this handle (and optional start_line / max_lines) to read it.]\n",
+        tool_name, output.len(), lines.len(), handle
+    );
+
+    if let Some(structure) = serde_json::from_str::<Value>(output).ok().as_ref().and_then(describe_json) {
+        out.push_str("\nJSON structure:\n");
+        out.push_str(&structure);
+        out.push('\n');
+    }
+
+    let error_re = Regex::new(r"(?i)\b(error|failed|failure|panicked|exception|traceback)\b").unwrap();
+    let warning_re = Regex::new(r"(?i)\bwarn(ing)?\b").unwrap();
+    let errors: Vec<(usize, &str)> = lines.iter().enumerate().filter(|(_, l)| error_re.is_match(l)).map(|(i, l)| (i, *l)).collect();
+    let warnings = lines.iter().filter(|l| warning_re.is_match(l)).count();
+    out.push_str(&format!("\nError lines: {}, warning lines: {}\n", errors.len(), warnings));
+    if !errors.is_empty() {
+        out.push_str("Error excerpts:\n");
+        for (i, line) in errors.iter().take(MAX_ERROR_EXCERPTS) {
+            out.push_str(&format!("  L{}: {}\n", i + 1, truncate_line(line.trim())));
+        }
+    }
+
+    let head = lines.len().min(EDGE_LINES);
+    out.push_str("\nFirst lines:\n");
+    for line in &lines[..head] {
+        out.push_str(&truncate_line(line));
+        out.push('\n');
+    }
+    if lines.len() > head {
+        let tail_start = lines.len().saturating_sub(EDGE_LINES).max(head);
+        if tail_start > head {
+            out.push_str(&format!("... ({} lines omitted) ...\n", tail_start - head));
+        }
+        out.push_str("Last lines:\n");
+        for line in &lines[tail_start..] {
+            out.push_str(&truncate_line(line));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// 返回写入模型历史的内容：未超阈值原样返回，否则保存完整输出并返回摘要
+///
+/// `agent_read_tool_output` 的结果不摘要，否则读取分页会再次被摘要
+pub fn condense(channel: &str, tool_call_id: &str, tool_name: &str, output: &str) -> String {
+    if output.len() <= SUMMARY_THRESHOLD_CHARS || tool_name == "agent_read_tool_output" {
+        return output.to_string();
+    }
+    let handle = format!("{}/{}", channel, tool_call_id);
+    println!("[ToolOutput] Summarizing {} output ({} chars) as {}", tool_name, output.len(), handle);
+    // 单行 JSON 格式化为多行，便于按行分页
+    let stored = match serde_json::from_str::<Value>(output) {
+        Ok(value) if output.trim().lines().count() == 1 => serde_json::to_string_pretty(&value).unwrap_or_else(|_| output.to_string()),
+        _ => output.to_string(),
+    };
+    let summary = summarize(tool_name, &stored, &handle);
+    if let Ok(mut outputs) = full_outputs().lock() {
+        outputs.insert(handle, stored);
+    }
+    summary
+}
+
+/// 按行读取完整输出（行号从 1 开始）
+///
+/// 每页最多 `MAX_READ_LINES` 行、`MAX_PAGE_CHARS` 个字符；超出字符上限的行留到下一页，
+/// 单行本身超出上限时只返回该行的前一段并注明省略的字符数
+pub fn read_full_output(handle: &str, start_line: usize, max_lines: usize) -> Result<String, String> {
+    let outputs = full_outputs().lock().map_err(|e| format!("Lock error: {}", e))?;
+    let output = outputs.get(handle).ok_or_else(|| format!("Unknown or expired tool output handle: {}", handle))?;
+    let lines: Vec<&str> = output.lines().collect();
+    let start = start_line.max(1);
+    if start > lines.len() {
+        return Err(format!("start_line {} is past the end of the output ({} lines)", start, lines.len()));
+    }
+    let max_lines = max_lines.clamp(1, MAX_READ_LINES);
+
+    let mut page: Vec<String> = Vec::new();
+    let mut chars = 0;
+    for line in lines.iter().skip(start - 1).take(max_lines) {
+        let len = line.chars().count();
+        if page.is_empty() && len > MAX_PAGE_CHARS {
+            let kept: String = line.chars().take(MAX_PAGE_CHARS).collect();
+            page.push(format!("{}… [{} more chars on this line]", kept, len - MAX_PAGE_CHARS));
+            break;
+        }
+        if chars + len > MAX_PAGE_CHARS {
+            break;
+        }
+        chars += len + 1;
+        page.push(line.to_string());
+    }
+    let end = start - 1 + page.len();
+    let mut out = format!("[Lines {}-{} of {}", start, end, lines.len());
+    if end < lines.len() {
+        out.push_str(&format!("; continue with start_line {}", end + 1));
+    }
+    out.push_str("]\n");
+    out.push_str(&page.join("\n"));
+    Ok(out)
+}
+
+/// 释放某次运行保存的完整输出
+pub fn clear_run(channel: &str) {
+    if let Ok(mut outputs) = full_outputs().lock() {
+        let prefix = format!("{}/", channel);
+        outputs.retain(|handle, _| !handle.starts_with(&prefix));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_output_unchanged() {
+        assert_eq!(condense("agent_t0", "call_0", "bash", "ok"), "ok");
+    }
+
+    #[test]
+    fn test_log_summary_and_read_back() {
+        let mut log = String::new();
+        for i in 0..2000 {
+            log.push_str(&format!("test case_{} ... ok\n", i));
+        }
+        log.push_str("error[E0308]: mismatched types\ntest result: FAILED. 1999 passed; 1 failed\n");
+
+        let summary = condense("agent_t1", "call_1", "bash", &log);
+        assert!(summary.len() < SUMMARY_THRESHOLD_CHARS);
+        assert!(summary.contains("handle: \"agent_t1/call_1\""));
+        assert!(summary.contains("L2001: error[E0308]: mismatched types"));
+        assert!(summary.contains("test result: FAILED"));
+        assert!(summary.contains("test case_0 ... ok"));
+
+        let page = read_full_output("agent_t1/call_1", 1000, 2).unwrap();
+        assert_eq!(page, "[Lines 1000-1001 of 2002; continue with start_line 1002]\ntest case_999 ... ok\ntest case_1000 ... ok");
+        // 页大小受行数与字符数限制
+        let page = read_full_output("agent_t1/call_1", 1, 100_000).unwrap();
+        assert!(page.len() <= MAX_PAGE_CHARS + 100);
+        assert!(page.starts_with("[Lines 1-"));
+
+        clear_run("agent_t1");
+        assert!(read_full_output("agent_t1/call_1", 1, 10).is_err());
+    }
+
+    #[test]
+    fn test_single_line_json_is_paged() {
+        let files: Vec<String> = (0..2000).map(|i| format!("src/file_{}.rs", i)).collect();
+        let scan = serde_json::json!({ "files": files }).to_string();
+        let summary = condense("agent_t2", "call_2", "agent_scan_directory", &scan);
+        assert!(summary.contains("files: array (2000 items)"));
+
+        let page = read_full_output("agent_t2/call_2", 3, 2).unwrap();
+        assert_eq!(page, "[Lines 3-4 of 2004; continue with start_line 5]\n    \"src/file_0.rs\",\n    \"src/file_1.rs\",");
+
+        // 无法格式化的超长单行按字符截断
+        let long_line = "x".repeat(20_000);
+        condense("agent_t2", "call_3", "bash", &long_line);
+        let page = read_full_output("agent_t2/call_3", 1, 10).unwrap();
+        assert!(page.starts_with("[Lines 1-1 of 1]\n"));
+        assert!(page.ends_with("[14000 more chars on this line]"));
+        clear_run("agent_t2");
+    }
+
+    #[test]
+    fn test_json_structure() {
+        let files: Vec<String> = (0..1000).map(|i| format!("src/file_{}.rs", i)).collect();
+        let scan = serde_json::json!({ "files": files, "stats": { "totalFiles": 1000 }, "root": "." });
+        let summary = summarize("agent_scan_directory", &scan.to_string(), "h");
+        assert!(summary.contains("files: array (1000 items)"));
+        assert!(summary.contains("stats: object (1 keys)"));
+    }
+}
@@ -0,0 +1,179 @@
+/*!
+Tool Permissions - 按工具类别的项目级授权
+=========================================
+
+Agent 的工具调用不再逐次审批，而是按类别征求一次同意：
+
+- 类别：读取文件、写入文件、执行命令、网络抓取（其余工具仍逐次审批）
+- 某类别在项目中首次使用时发送 `permission_request` 事件，用户对这次调用的审批结果
+  即记录为该类别的决定
+- 之后同类别的调用按记录自动批准或拒绝，直到通过 `revoke_permission` 撤销
+
+决定保存在 `.ifai/permissions.json`。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum PermissionCategory {
+    ReadFiles,
+    WriteFiles,
+    RunCommands,
+    NetworkFetch,
+}
+
+const CATEGORIES: [PermissionCategory; 4] = [
+    PermissionCategory::ReadFiles,
+    PermissionCategory::WriteFiles,
+    PermissionCategory::RunCommands,
+    PermissionCategory::NetworkFetch,
+];
+
+impl PermissionCategory {
+    pub fn label(self) -> &'static str {
+        match self {
+            PermissionCategory::ReadFiles => "read files",
+            PermissionCategory::WriteFiles => "write files",
+            PermissionCategory::RunCommands => "run commands",
+            PermissionCategory::NetworkFetch => "fetch from the network",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionDecision {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PermissionRecord {
+    pub decision: PermissionDecision,
+    /// 触发首次授权的工具
+    pub tool: String,
+    pub decided_at: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PermissionFile {
+    #[serde(default)]
+    categories: BTreeMap<PermissionCategory, PermissionRecord>,
+}
+
+/// 前端设置页展示用（未决定的类别 `record` 为空）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionEntry {
+    pub category: PermissionCategory,
+    pub record: Option<PermissionRecord>,
+}
+
+/// 工具所属类别；不属于任何类别的工具仍逐次审批
+pub fn category_for_tool(tool_name: &str) -> Option<PermissionCategory> {
+    match tool_name {
+        "agent_read_file" | "agent_batch_read" | "agent_list_dir" | "agent_scan_directory" | "agent_grep" | "agent_get_diagnostics" => {
+            Some(PermissionCategory::ReadFiles)
+        }
+        "agent_write_file" | "agent_edit_file" | "agent_create_file" | "agent_delete_file" | "agent_rename_file" => {
+            Some(PermissionCategory::WriteFiles)
+        }
+        "bash" | "agent_run_shell_command" | "agent_execute_command" | "agent_run_command" => Some(PermissionCategory::RunCommands),
+        name if name.contains("fetch") || name.contains("web_search") => Some(PermissionCategory::NetworkFetch),
+        _ => None,
+    }
+}
+
+fn permissions_path(project_root: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("permissions.json")
+}
+
+fn load(project_root: &str) -> PermissionFile {
+    std::fs::read_to_string(permissions_path(project_root))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save(project_root: &str, file: &PermissionFile) -> Result<(), String> {
+    let path = permissions_path(project_root);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create .ifai dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(file).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write permissions: {}", e))
+}
+
+/// 已记录的决定；`None` 表示该类别尚未征求同意
+pub fn decision_for(project_root: &str, category: PermissionCategory) -> Option<PermissionDecision> {
+    load(project_root).categories.get(&category).map(|r| r.decision)
+}
+
+pub fn record(project_root: &str, category: PermissionCategory, decision: PermissionDecision, tool: &str) -> Result<(), String> {
+    let mut file = load(project_root);
+    file.categories.insert(category, PermissionRecord {
+        decision,
+        tool: tool.to_string(),
+        decided_at: chrono::Utc::now().timestamp(),
+    });
+    println!("[ToolPermissions] {:?} -> {:?} (first used by {})", category, decision, tool);
+    save(project_root, &file)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_tool_permissions(project_root: String) -> Vec<PermissionEntry> {
+    let file = load(&project_root);
+    CATEGORIES
+        .iter()
+        .map(|&category| PermissionEntry { category, record: file.categories.get(&category).cloned() })
+        .collect()
+}
+
+/// 撤销类别的授权，下次使用时重新征求同意；返回该类别此前是否有记录
+#[tauri::command]
+pub fn revoke_permission(project_root: String, category: PermissionCategory) -> Result<bool, String> {
+    let mut file = load(&project_root);
+    let existed = file.categories.remove(&category).is_some();
+    if existed {
+        save(&project_root, &file)?;
+        println!("[ToolPermissions] Revoked {:?}", category);
+    }
+    Ok(existed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_revoke() {
+        let dir = std::env::temp_dir().join(format!("ifai_permissions_{}", uuid::Uuid::new_v4()));
+        let root = dir.to_string_lossy().to_string();
+
+        assert_eq!(category_for_tool("agent_edit_file"), Some(PermissionCategory::WriteFiles));
+        assert_eq!(category_for_tool("bash"), Some(PermissionCategory::RunCommands));
+        assert_eq!(category_for_tool("agent_run_command"), Some(PermissionCategory::RunCommands));
+        assert_eq!(category_for_tool("agent_emit_artifact"), None);
+
+        assert_eq!(decision_for(&root, PermissionCategory::ReadFiles), None);
+        record(&root, PermissionCategory::ReadFiles, PermissionDecision::Allow, "agent_read_file").unwrap();
+        record(&root, PermissionCategory::RunCommands, PermissionDecision::Deny, "bash").unwrap();
+        assert_eq!(decision_for(&root, PermissionCategory::ReadFiles), Some(PermissionDecision::Allow));
+        assert_eq!(decision_for(&root, PermissionCategory::RunCommands), Some(PermissionDecision::Deny));
+
+        let entries = get_tool_permissions(root.clone());
+        assert_eq!(entries.len(), 4);
+        assert!(entries.iter().any(|e| e.category == PermissionCategory::WriteFiles && e.record.is_none()));
+
+        assert!(revoke_permission(root.clone(), PermissionCategory::RunCommands).unwrap());
+        assert!(!revoke_permission(root.clone(), PermissionCategory::RunCommands).unwrap());
+        assert_eq!(decision_for(&root, PermissionCategory::RunCommands), None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,319 @@
+/*!
+Update Check - 版本更新检查
+============================
+
+查询发布端点（GitHub Releases 或自建服务），比较版本并展示发布说明。
+只做检查与提示，不自动下载安装。
+
+- 配置保存在 `~/.ifai/update.json`
+- 自建端点返回 JSON：`{ "version": "0.3.5", "notes": "...", "url": "...", "pub_date": "..." }`
+- 发现新版本时发送 `update:available` 事件
+- 启动时的自动检查需在配置中开启（`enabled`，默认关闭）；手动检查不受影响
+- 版本按 SemVer 优先级比较（`beta.10` 高于 `beta.2`）
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::events::emit_event;
+
+const USER_AGENT: &str = "ifai-update-check";
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 更新检查配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpdateConfig {
+    /// 启动时自动检查（默认关闭，启动时不主动联网）
+    pub enabled: bool,
+    /// 是否包含预发布版本
+    pub include_prerelease: bool,
+    /// GitHub 仓库（owner/repo）
+    pub github_repo: String,
+    /// 自建端点，设置后优先于 GitHub
+    pub custom_url: Option<String>,
+    /// 自动检查间隔（小时）
+    pub check_interval_hours: u64,
+    /// 用户选择跳过的版本
+    pub skipped_version: Option<String>,
+    /// 上次检查时间（Unix 秒）
+    pub last_checked: Option<i64>,
+}
+
+impl Default for UpdateConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            include_prerelease: false,
+            github_repo: "peterfei/ifai".to_string(),
+            custom_url: None,
+            check_interval_hours: 24,
+            skipped_version: None,
+            last_checked: None,
+        }
+    }
+}
+
+/// 发布信息
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReleaseInfo {
+    pub version: String,
+    pub notes: String,
+    pub url: Option<String>,
+    pub published_at: Option<String>,
+    pub prerelease: bool,
+}
+
+/// 检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCheckResult {
+    pub current_version: String,
+    pub update_available: bool,
+    pub latest: Option<ReleaseInfo>,
+    pub checked_at: i64,
+}
+
+// ============================================================================
+// Config
+// ============================================================================
+
+fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ifai")
+        .join("update.json")
+}
+
+fn load_config() -> UpdateConfig {
+    std::fs::read_to_string(config_path())
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_config(config: &UpdateConfig) -> Result<(), String> {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create config dir: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to write update config: {}", e))
+}
+
+// ============================================================================
+// Versions
+// ============================================================================
+
+/// 解析 `v1.2.3-beta.1` 形式的版本号（缺省的次版本号、修订号补 0）
+fn parse_version(version: &str) -> Option<semver::Version> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    if let Ok(parsed) = semver::Version::parse(version) {
+        return Some(parsed);
+    }
+    let split = version.find(['-', '+']).unwrap_or(version.len());
+    let (core, suffix) = version.split_at(split);
+    let mut parts: Vec<&str> = core.split('.').collect();
+    if parts.len() >= 3 {
+        return None;
+    }
+    parts.resize(3, "0");
+    semver::Version::parse(&format!("{}{}", parts.join("."), suffix)).ok()
+}
+
+/// 比较版本号（SemVer 优先级：正式版高于同号的预发布版，预发布标识按数字比较；无法解析的版本排在最前）
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    match (parse_version(a), parse_version(b)) {
+        (Some(a), Some(b)) => a.cmp_precedence(&b),
+        (Some(_), None) => Ordering::Greater,
+        (None, Some(_)) => Ordering::Less,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+// ============================================================================
+// Fetching
+// ============================================================================
+
+/// 解析 GitHub Releases 列表，返回符合通道的最新发布
+fn parse_github_releases(value: &Value, include_prerelease: bool) -> Option<ReleaseInfo> {
+    value.as_array()?
+        .iter()
+        .filter(|r| !r["draft"].as_bool().unwrap_or(false))
+        .filter(|r| include_prerelease || !r["prerelease"].as_bool().unwrap_or(false))
+        .filter_map(|r| {
+            Some(ReleaseInfo {
+                version: r["tag_name"].as_str()?.trim_start_matches(['v', 'V']).to_string(),
+                notes: r["body"].as_str().unwrap_or_default().to_string(),
+                url: r["html_url"].as_str().map(String::from),
+                published_at: r["published_at"].as_str().map(String::from),
+                prerelease: r["prerelease"].as_bool().unwrap_or(false),
+            })
+        })
+        .max_by(|a, b| compare_versions(&a.version, &b.version))
+}
+
+/// 解析自建端点返回
+fn parse_custom_release(value: &Value) -> Option<ReleaseInfo> {
+    let version = value["version"].as_str()?.trim_start_matches(['v', 'V']).to_string();
+    Some(ReleaseInfo {
+        prerelease: parse_version(&version).is_some_and(|v| !v.pre.is_empty()),
+        version,
+        notes: value["notes"].as_str().unwrap_or_default().to_string(),
+        url: value["url"].as_str().map(String::from),
+        published_at: value["pub_date"].as_str().map(String::from),
+    })
+}
+
+async fn fetch_latest(config: &UpdateConfig) -> Result<Option<ReleaseInfo>, String> {
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent(USER_AGENT)
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let url = match &config.custom_url {
+        Some(url) => url.clone(),
+        None => format!("https://api.github.com/repos/{}/releases?per_page=20", config.github_repo),
+    };
+    let value: Value = client.get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Update check failed: {}", e))?
+        .error_for_status()
+        .map_err(|e| format!("Update check failed: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Invalid release response: {}", e))?;
+
+    Ok(match &config.custom_url {
+        Some(_) => parse_custom_release(&value).filter(|r| config.include_prerelease || !r.prerelease),
+        None => parse_github_releases(&value, config.include_prerelease),
+    })
+}
+
+async fn run_check(app: &AppHandle) -> Result<UpdateCheckResult, String> {
+    let mut config = load_config();
+    let current_version = app.package_info().version.to_string();
+    let latest = fetch_latest(&config).await?;
+    let checked_at = chrono::Utc::now().timestamp();
+
+    let update_available = latest.as_ref()
+        .map(|r| compare_versions(&r.version, &current_version) == Ordering::Greater)
+        .unwrap_or(false);
+
+    config.last_checked = Some(checked_at);
+    let _ = save_config(&config);
+
+    let result = UpdateCheckResult { current_version, update_available, latest, checked_at };
+    let skipped = result.latest.as_ref().map(|r| Some(&r.version) == config.skipped_version.as_ref()).unwrap_or(false);
+    if update_available && !skipped {
+        println!("[UpdateCheck] New version available: {:?}", result.latest.as_ref().map(|r| &r.version));
+        emit_event(app, "update:available", &result);
+    }
+    Ok(result)
+}
+
+/// 启动时按间隔自动检查（仅在用户开启 `enabled` 后）
+pub fn start_background_check(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let config = load_config();
+        let due = config.last_checked
+            .map(|t| chrono::Utc::now().timestamp() - t >= (config.check_interval_hours * 3600) as i64)
+            .unwrap_or(true);
+        if !config.enabled || !due {
+            return;
+        }
+        if let Err(e) = run_check(&app).await {
+            eprintln!("[UpdateCheck] {}", e);
+        }
+    });
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// 检查更新（不下载安装）
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> Result<UpdateCheckResult, String> {
+    run_check(&app).await
+}
+
+#[tauri::command]
+pub fn get_update_config() -> UpdateConfig {
+    load_config()
+}
+
+#[tauri::command]
+pub fn set_update_config(config: UpdateConfig) -> Result<(), String> {
+    save_config(&config)
+}
+
+/// 跳过某个版本的提示
+#[tauri::command]
+pub fn skip_update_version(version: String) -> Result<(), String> {
+    let mut config = load_config();
+    config.skipped_version = Some(version);
+    save_config(&config)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_compare_versions() {
+        assert_eq!(compare_versions("0.3.4", "0.3.3"), Ordering::Greater);
+        assert_eq!(compare_versions("v0.3.10", "0.3.9"), Ordering::Greater);
+        assert_eq!(compare_versions("0.4.0-beta.1", "0.4.0"), Ordering::Less);
+        assert_eq!(compare_versions("0.4.0-beta.2", "0.4.0-beta.1"), Ordering::Greater);
+        assert_eq!(compare_versions("0.4.0-beta.10", "0.4.0-beta.2"), Ordering::Greater);
+        assert_eq!(compare_versions("0.4.0-rc.1", "0.4.0-beta.10"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0-beta.1", "1.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("0.3.5+build.7", "0.3.5"), Ordering::Equal);
+        assert_eq!(compare_versions("0.3.5", "nightly"), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_parse_github_releases() {
+        let releases = json!([
+            { "tag_name": "v0.4.0-beta.1", "prerelease": true, "draft": false, "body": "beta" },
+            { "tag_name": "v0.3.5", "prerelease": false, "draft": false, "body": "fixes", "html_url": "https://example.com/r" },
+            { "tag_name": "v0.3.4", "prerelease": false, "draft": false, "body": "" },
+            { "tag_name": "v0.5.0", "prerelease": false, "draft": true, "body": "" }
+        ]);
+        let stable = parse_github_releases(&releases, false).unwrap();
+        assert_eq!(stable.version, "0.3.5");
+        assert_eq!(stable.notes, "fixes");
+        assert_eq!(parse_github_releases(&releases, true).unwrap().version, "0.4.0-beta.1");
+    }
+
+    #[test]
+    fn test_parse_custom_release() {
+        let release = parse_custom_release(&json!({ "version": "v0.3.6", "notes": "n", "pub_date": "2026-01-01" })).unwrap();
+        assert_eq!(release.version, "0.3.6");
+        assert!(!release.prerelease);
+        assert!(parse_custom_release(&json!({})).is_none());
+        assert!(parse_custom_release(&json!({ "version": "0.4.0-rc.1" })).unwrap().prerelease);
+    }
+
+    #[test]
+    fn test_auto_check_is_opt_in() {
+        assert!(!UpdateConfig::default().enabled);
+        let config: UpdateConfig = serde_json::from_str(r#"{ "include_prerelease": true }"#).unwrap();
+        assert!(!config.enabled);
+    }
+}
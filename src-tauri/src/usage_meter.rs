@@ -0,0 +1,556 @@
+/*!
+Usage Meter - 用量计量与费用估算
+================================
+
+每次 Provider 请求的 Token 用量、Provider、模型、耗时与估算费用追加写入
+`~/.ifai/usage/YYYY-MM.jsonl`（每行一条记录，按月分文件）：
+
+- 用量取自接口返回的 usage；流式接口未返回时按分词器估算并标记 `estimated`
+- 所属项目 / Agent 由调用方通过 `with_scope` 设置（ai_chat、Agent runner），未设置时为空
+- 费用按内置单价表估算（美元 / 百万 Token，缓存读取按输入单价的 10% 计），
+  `~/.ifai/usage/prices.json` 可覆盖或补充单价；未知模型的费用为空
+- `get_usage_summary(period)` 按天、项目、模型、Provider 汇总，`export_usage_csv` 导出原始记录
+*/
+
+use chrono::{Datelike, Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::future::Future;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use crate::agent_budget::TokenUsage;
+use crate::core_traits::ai::{AIProviderConfig, Message};
+use crate::prompt_cache::CacheUsage;
+
+/// 缓存读取相对输入单价的比例
+const CACHED_INPUT_PRICE_RATIO: f64 = 0.1;
+
+/// 内置单价（美元 / 百万 Token）：(模型名片段, 输入, 输出)，按顺序取第一个匹配项，具体型号在前
+const BUILTIN_PRICES: &[(&str, f64, f64)] = &[
+    ("claude-opus-4-5", 5.0, 25.0),
+    ("claude-opus", 15.0, 75.0),
+    ("claude-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-haiku-4", 1.0, 5.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("gpt-5-nano", 0.05, 0.4),
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("o4-mini", 1.1, 4.4),
+    ("o3-mini", 1.1, 4.4),
+    ("o3", 2.0, 8.0),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("gemini-2.5-flash-lite", 0.1, 0.4),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.0-flash", 0.1, 0.4),
+    ("deepseek-reasoner", 0.55, 2.19),
+    ("deepseek-chat", 0.27, 1.1),
+];
+
+// ============================================================================
+// Types
+// ============================================================================
+
+/// 一次 Provider 请求的用量记录（jsonl 中的一行）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageRecord {
+    /// Unix 毫秒时间戳
+    pub timestamp: i64,
+    /// 请求来源（"chat", "completion", "agent"）
+    pub source: String,
+    pub provider: String,
+    pub model: String,
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// 输入中从提示词缓存读取的部分
+    #[serde(default)]
+    pub cached_tokens: u64,
+    pub latency_ms: u64,
+    /// 接口未返回 usage，按分词器估算
+    #[serde(default)]
+    pub estimated: bool,
+    /// 估算费用（美元），未知模型为空
+    #[serde(default)]
+    pub cost_usd: Option<f64>,
+}
+
+/// 请求的归属（项目 / Agent）
+#[derive(Debug, Clone, Default)]
+pub struct UsageScope {
+    pub project: Option<String>,
+    pub agent_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+struct ModelPrice {
+    input: f64,
+    output: f64,
+}
+
+/// 汇总数据
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cached_tokens: u64,
+    pub cost_usd: f64,
+    /// 无法估算费用的请求数（未知模型）
+    pub unpriced_requests: u64,
+    pub estimated_requests: u64,
+    pub total_latency_ms: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, record: &UsageRecord) {
+        self.requests += 1;
+        self.prompt_tokens += record.prompt_tokens;
+        self.completion_tokens += record.completion_tokens;
+        self.cached_tokens += record.cached_tokens;
+        match record.cost_usd {
+            Some(cost) => self.cost_usd += cost,
+            None => self.unpriced_requests += 1,
+        }
+        if record.estimated {
+            self.estimated_requests += 1;
+        }
+        self.total_latency_ms += record.latency_ms;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageBucket {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub period: String,
+    /// 统计起点（Unix 毫秒），`all` 时为空
+    pub since: Option<i64>,
+    pub totals: UsageTotals,
+    /// 按本地日期（YYYY-MM-DD）升序
+    pub by_day: Vec<UsageBucket>,
+    /// 以下按费用降序
+    pub by_project: Vec<UsageBucket>,
+    pub by_model: Vec<UsageBucket>,
+    pub by_provider: Vec<UsageBucket>,
+}
+
+// ============================================================================
+// Scope
+// ============================================================================
+
+tokio::task_local! {
+    static SCOPE: UsageScope;
+}
+
+/// 在指定归属下执行请求，期间的用量记录带上项目 / Agent
+pub async fn with_scope<F: Future>(scope: UsageScope, fut: F) -> F::Output {
+    SCOPE.scope(scope, fut).await
+}
+
+fn current_scope() -> UsageScope {
+    SCOPE.try_with(|scope| scope.clone()).unwrap_or_default()
+}
+
+// ============================================================================
+// Metering
+// ============================================================================
+
+/// 单次请求的计量：记录开始时间，累积接口返回的用量，结束时写入
+pub struct RequestMeter {
+    source: &'static str,
+    started: Instant,
+    usage: Option<TokenUsage>,
+    cache: Option<CacheUsage>,
+}
+
+impl RequestMeter {
+    pub fn start(source: &'static str) -> Self {
+        Self { source, started: Instant::now(), usage: None, cache: None }
+    }
+
+    /// 非流式响应体
+    pub fn response(&mut self, config: &AIProviderConfig, response: &Value) {
+        if crate::anthropic_api::is_anthropic(config) {
+            let usage = &response["usage"];
+            if let Some(input) = usage["input_tokens"].as_u64() {
+                let cached = usage["cache_read_input_tokens"].as_u64().unwrap_or(0) + usage["cache_creation_input_tokens"].as_u64().unwrap_or(0);
+                self.usage = Some(TokenUsage { prompt_tokens: input + cached, completion_tokens: usage["output_tokens"].as_u64().unwrap_or(0) });
+            }
+        } else if crate::gemini_api::is_gemini(config) {
+            self.usage = TokenUsage::from_gemini(response).or(self.usage);
+        } else {
+            self.usage = TokenUsage::from_openai(&response["usage"]).or(self.usage);
+        }
+        self.cache = CacheUsage::from_response(config, response).or(self.cache);
+    }
+
+    /// OpenAI 兼容流式数据块中的 `usage`
+    pub fn openai_usage(&mut self, usage: &Value) {
+        self.usage = TokenUsage::from_openai(usage).or(self.usage);
+        self.cache = CacheUsage::from_openai(usage).or(self.cache);
+    }
+
+    /// Anthropic 流式事件
+    pub fn anthropic_event(&mut self, event: &Value) {
+        let mut usage = self.usage.unwrap_or_default();
+        if usage.merge_anthropic_event(event) {
+            self.usage = Some(usage);
+        }
+        self.cache = CacheUsage::from_anthropic_event(event).or(self.cache);
+    }
+
+    /// Gemini 流式数据块（`usageMetadata` 为累计值）
+    pub fn gemini_chunk(&mut self, chunk: &Value) {
+        self.usage = TokenUsage::from_gemini(chunk).or(self.usage);
+        self.cache = CacheUsage::from_gemini(&chunk["usageMetadata"]).or(self.cache);
+    }
+
+    /// 请求结束：写入提示词缓存统计与用量记录；接口未返回用量时使用 `estimate`（返回 None 则不记录）
+    pub fn finish(self, config: &AIProviderConfig, estimate: impl FnOnce() -> Option<TokenUsage>) {
+        crate::prompt_cache::record(config, self.cache);
+        let (usage, estimated) = match self.usage {
+            Some(usage) => (usage, false),
+            None => match estimate() {
+                Some(usage) => (usage, true),
+                None => return,
+            },
+        };
+        let scope = current_scope();
+        let model = config.models.first().cloned().unwrap_or_default();
+        let cached_tokens = self.cache.map(|c| c.cached_tokens).unwrap_or(0).min(usage.prompt_tokens);
+        let record = UsageRecord {
+            timestamp: chrono::Utc::now().timestamp_millis(),
+            source: self.source.to_string(),
+            provider: provider_name(config),
+            cost_usd: estimate_cost(&model, usage.prompt_tokens, cached_tokens, usage.completion_tokens),
+            model,
+            project: scope.project,
+            agent_id: scope.agent_id,
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            cached_tokens,
+            latency_ms: self.started.elapsed().as_millis() as u64,
+            estimated,
+        };
+        if let Err(e) = append(&usage_dir(), &record) {
+            eprintln!("[UsageMeter] Failed to record usage: {}", e);
+        }
+    }
+}
+
+/// 按模型分词器估算一次请求的用量
+pub fn estimate(config: &AIProviderConfig, messages: &[Message], completion: &str) -> TokenUsage {
+    let kind = crate::token_counter::tokenizer_for_model(config.models.first().map(String::as_str).unwrap_or(""));
+    let prompt: usize = messages
+        .iter()
+        .map(|m| crate::token_counter::count_with(&crate::intelligence_router::extract_text_content(&m.content), kind))
+        .sum();
+    TokenUsage { prompt_tokens: prompt as u64, completion_tokens: crate::token_counter::count_with(completion, kind) as u64 }
+}
+
+fn provider_name(config: &AIProviderConfig) -> String {
+    [&config.name, &config.id, &config.base_url]
+        .into_iter()
+        .find(|s| !s.is_empty())
+        .cloned()
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// ============================================================================
+// Pricing
+// ============================================================================
+
+fn price_overrides() -> HashMap<String, ModelPrice> {
+    std::fs::read_to_string(usage_dir().join("prices.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn price_for(model: &str, overrides: &HashMap<String, ModelPrice>) -> Option<ModelPrice> {
+    let lower = model.to_lowercase();
+    let name = lower.rsplit('/').next().unwrap_or(&lower);
+    // 覆盖项取最长匹配
+    let custom = overrides
+        .iter()
+        .filter(|(key, _)| name.contains(&key.to_lowercase()))
+        .max_by_key(|(key, _)| key.len())
+        .map(|(_, price)| *price);
+    custom.or_else(|| {
+        BUILTIN_PRICES
+            .iter()
+            .find(|(key, _, _)| name.contains(key))
+            .map(|(_, input, output)| ModelPrice { input: *input, output: *output })
+    })
+}
+
+fn cost_with(price: ModelPrice, prompt_tokens: u64, cached_tokens: u64, completion_tokens: u64) -> f64 {
+    let uncached = prompt_tokens.saturating_sub(cached_tokens) as f64;
+    (uncached * price.input + cached_tokens as f64 * price.input * CACHED_INPUT_PRICE_RATIO + completion_tokens as f64 * price.output)
+        / 1_000_000.0
+}
+
+fn estimate_cost(model: &str, prompt_tokens: u64, cached_tokens: u64, completion_tokens: u64) -> Option<f64> {
+    price_for(model, &price_overrides()).map(|price| cost_with(price, prompt_tokens, cached_tokens, completion_tokens))
+}
+
+// ============================================================================
+// Storage
+// ============================================================================
+
+fn usage_dir() -> PathBuf {
+    dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join(".ifai").join("usage")
+}
+
+fn write_lock() -> &'static Mutex<()> {
+    static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+    LOCK.get_or_init(|| Mutex::new(()))
+}
+
+fn month_file(dir: &Path, timestamp: i64) -> PathBuf {
+    let month = Local.timestamp_millis_opt(timestamp).single().unwrap_or_else(Local::now).format("%Y-%m");
+    dir.join(format!("{}.jsonl", month))
+}
+
+fn append(dir: &Path, record: &UsageRecord) -> Result<(), String> {
+    let _guard = write_lock().lock().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create usage dir: {}", e))?;
+    let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(month_file(dir, record.timestamp))
+        .map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+/// 读取 `since` 之后的记录（跳过无法解析的行）
+fn load_records(dir: &Path, since: Option<i64>) -> Vec<UsageRecord> {
+    let first_month = since.map(|ts| month_file(dir, ts));
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new() };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "jsonl"))
+        .filter(|p| first_month.as_ref().is_none_or(|first| p >= first))
+        .collect();
+    files.sort();
+
+    files
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter_map(|line| serde_json::from_str::<UsageRecord>(line).ok())
+                .collect::<Vec<_>>()
+        })
+        .filter(|record| since.is_none_or(|since| record.timestamp >= since))
+        .collect()
+}
+
+// ============================================================================
+// Summary
+// ============================================================================
+
+/// 统计起点：today（本地零点）、week（7 天）、month（本月 1 日）、all
+fn period_start(period: &str, now: chrono::DateTime<Local>) -> Result<Option<i64>, String> {
+    let midnight = |date: chrono::NaiveDate| {
+        Local.from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default()).earliest().map(|t| t.timestamp_millis())
+    };
+    match period {
+        "today" => Ok(midnight(now.date_naive())),
+        "week" => Ok(midnight(now.date_naive() - chrono::Duration::days(6))),
+        "month" => Ok(now.date_naive().with_day(1).and_then(midnight)),
+        "all" => Ok(None),
+        other => Err(format!("Unknown period '{}': expected today, week, month or all", other)),
+    }
+}
+
+fn buckets(records: &[UsageRecord], key: impl Fn(&UsageRecord) -> String) -> BTreeMap<String, UsageTotals> {
+    let mut map: BTreeMap<String, UsageTotals> = BTreeMap::new();
+    for record in records {
+        map.entry(key(record)).or_default().add(record);
+    }
+    map
+}
+
+fn by_cost(map: BTreeMap<String, UsageTotals>) -> Vec<UsageBucket> {
+    let mut list: Vec<UsageBucket> = map.into_iter().map(|(key, totals)| UsageBucket { key, totals }).collect();
+    list.sort_by(|a, b| b.totals.cost_usd.total_cmp(&a.totals.cost_usd).then(b.totals.requests.cmp(&a.totals.requests)));
+    list
+}
+
+fn summarize(period: &str, since: Option<i64>, records: &[UsageRecord]) -> UsageSummary {
+    let mut totals = UsageTotals::default();
+    for record in records {
+        totals.add(record);
+    }
+    let day = |record: &UsageRecord| {
+        Local.timestamp_millis_opt(record.timestamp).single().map(|t| t.format("%Y-%m-%d").to_string()).unwrap_or_default()
+    };
+    UsageSummary {
+        period: period.to_string(),
+        since,
+        totals,
+        by_day: buckets(records, day).into_iter().map(|(key, totals)| UsageBucket { key, totals }).collect(),
+        by_project: by_cost(buckets(records, |r| r.project.clone().unwrap_or_else(|| "(none)".to_string()))),
+        by_model: by_cost(buckets(records, |r| r.model.clone())),
+        by_provider: by_cost(buckets(records, |r| r.provider.clone())),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn to_csv(records: &[UsageRecord]) -> String {
+    let mut csv = String::from("timestamp,source,provider,model,project,agent_id,prompt_tokens,completion_tokens,cached_tokens,latency_ms,estimated,cost_usd\n");
+    for r in records {
+        let time = Local.timestamp_millis_opt(r.timestamp).single().map(|t| t.to_rfc3339()).unwrap_or_default();
+        let fields = [
+            time,
+            csv_field(&r.source),
+            csv_field(&r.provider),
+            csv_field(&r.model),
+            csv_field(r.project.as_deref().unwrap_or("")),
+            csv_field(r.agent_id.as_deref().unwrap_or("")),
+            r.prompt_tokens.to_string(),
+            r.completion_tokens.to_string(),
+            r.cached_tokens.to_string(),
+            r.latency_ms.to_string(),
+            r.estimated.to_string(),
+            r.cost_usd.map(|c| format!("{:.6}", c)).unwrap_or_default(),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+// ============================================================================
+// Commands
+// ============================================================================
+
+/// 用量汇总；`period` 为 today / week / month / all（默认 week），可按项目过滤
+#[tauri::command]
+pub async fn get_usage_summary(period: Option<String>, project: Option<String>) -> Result<UsageSummary, String> {
+    let period = period.unwrap_or_else(|| "week".to_string());
+    let since = period_start(&period, Local::now())?;
+    tokio::task::spawn_blocking(move || {
+        let mut records = load_records(&usage_dir(), since);
+        if let Some(project) = &project {
+            records.retain(|r| r.project.as_deref() == Some(project.as_str()));
+        }
+        summarize(&period, since, &records)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 导出用量记录为 CSV，返回导出的记录数
+#[tauri::command]
+pub async fn export_usage_csv(path: String, period: Option<String>) -> Result<usize, String> {
+    let since = period_start(period.as_deref().unwrap_or("all"), Local::now())?;
+    tokio::task::spawn_blocking(move || {
+        let records = load_records(&usage_dir(), since);
+        std::fs::write(&path, to_csv(&records)).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+        Ok(records.len())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: i64, project: Option<&str>, model: &str, cost: Option<f64>) -> UsageRecord {
+        UsageRecord {
+            timestamp,
+            source: "chat".to_string(),
+            provider: "OpenAI".to_string(),
+            model: model.to_string(),
+            project: project.map(str::to_string),
+            agent_id: None,
+            prompt_tokens: 1000,
+            completion_tokens: 100,
+            cached_tokens: 0,
+            latency_ms: 500,
+            estimated: false,
+            cost_usd: cost,
+        }
+    }
+
+    #[test]
+    fn test_pricing() {
+        let overrides = HashMap::from([("my-model".to_string(), ModelPrice { input: 1.0, output: 2.0 })]);
+        let sonnet = price_for("anthropic/claude-sonnet-4-5", &overrides).unwrap();
+        assert_eq!((sonnet.input, sonnet.output), (3.0, 15.0));
+        assert_eq!(price_for("gpt-4o-mini-2024-07-18", &overrides).map(|p| p.input), Some(0.15));
+        assert_eq!(price_for("My-Model-v2", &overrides).map(|p| p.output), Some(2.0));
+        assert!(price_for("qwen2.5-coder:7b", &overrides).is_none());
+
+        // 1M 输入（其中一半命中缓存）+ 1M 输出
+        let cost = cost_with(ModelPrice { input: 2.0, output: 8.0 }, 1_000_000, 500_000, 1_000_000);
+        assert!((cost - (1.0 + 0.1 + 8.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_store_summary_and_csv() {
+        let dir = std::env::temp_dir().join(format!("ifai-usage-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let now = Local::now();
+        let today = now.timestamp_millis();
+        let old = (now - chrono::Duration::days(40)).timestamp_millis();
+        append(&dir, &record(old, Some("/p/a"), "gpt-4o", Some(0.5))).unwrap();
+        append(&dir, &record(today, Some("/p/a"), "gpt-4o", Some(0.25))).unwrap();
+        append(&dir, &record(today, Some("/p/b, \"x\""), "local", None)).unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        assert_eq!(load_records(&dir, None).len(), 3);
+        let since = period_start("week", now).unwrap();
+        let records = load_records(&dir, since);
+        assert_eq!(records.len(), 2);
+
+        let summary = summarize("week", since, &records);
+        assert_eq!(summary.totals.requests, 2);
+        assert_eq!(summary.totals.unpriced_requests, 1);
+        assert!((summary.totals.cost_usd - 0.25).abs() < 1e-9);
+        assert_eq!(summary.by_day.len(), 1);
+        assert_eq!(summary.by_project[0].key, "/p/a");
+        assert_eq!(summary.by_model.iter().map(|b| b.key.as_str()).collect::<Vec<_>>(), vec!["gpt-4o", "local"]);
+
+        let csv = to_csv(&records);
+        assert!(csv.starts_with("timestamp,source,provider,model,project"));
+        assert!(csv.contains(",\"/p/b, \"\"x\"\"\","));
+        assert!(csv.lines().nth(1).unwrap().ends_with(",false,0.250000"));
+        assert!(period_start("year", now).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
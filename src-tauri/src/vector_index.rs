@@ -0,0 +1,700 @@
+/*!
+Vector Index - 近似最近邻向量索引（HNSW）
+=========================================
+
+语义搜索的暴力余弦扫描在 5 万个分块以上会明显变慢，这里提供 HNSW 图索引：
+
+- 向量写入前归一化，距离为 `1 - 点积`
+- 层级由分块 ID 的哈希决定，同样的输入得到同样的图，便于复现
+- 删除只做标记（图结构保留用于遍历），重复写入同一 ID 视为更新
+- 存活分块少于 `FLAT_SCAN_THRESHOLD` 时直接精确扫描，结果与暴力搜索一致
+
+索引保存在 `.ifai/index.hnsw`，与核心 RAG 的 `.ifai/index.bin` 并列；
+向量由项目配置的向量模型计算（见 `embedding`），模型变化时索引自动重建。
+`@codebase` 检索（`retrieve_context`）优先使用该索引，项目没有索引时回退到核心 RAG。
+*/
+
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::core_traits::rag::{RagReference, RagResult, RagService};
+
+/// 索引文件格式版本，不兼容时重建
+const INDEX_VERSION: u32 = 2;
+/// 每层的邻居数上限（第 0 层为两倍）
+const MAX_NEIGHBORS: usize = 16;
+/// 建图时的候选集大小
+const EF_CONSTRUCTION: usize = 100;
+/// 搜索时的最小候选集大小
+const EF_SEARCH: usize = 64;
+/// 存活分块少于该数量时精确扫描
+const FLAT_SCAN_THRESHOLD: usize = 1000;
+/// 检索上下文时返回的分块数
+const RETRIEVE_TOP_K: usize = 20;
+
+/// 已加载的项目索引
+static INDEXES: OnceLock<Mutex<HashMap<String, HnswIndex>>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: u32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance.total_cmp(&other.distance).then(self.node.cmp(&other.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Node {
+    id: String,
+    vector: Vec<f32>,
+    /// 展示用的标签（如分块所属符号）
+    label: Option<String>,
+    /// `neighbors[level]` 为该层的邻居
+    neighbors: Vec<Vec<u32>>,
+    deleted: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HnswIndex {
+    version: u32,
+    /// 生成向量的模型（`EmbeddingProvider::model_id`）
+    model_id: String,
+    dimension: usize,
+    nodes: Vec<Node>,
+    entry_point: Option<u32>,
+    #[serde(skip)]
+    ids: HashMap<String, u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SemanticHit {
+    pub id: String,
+    /// 余弦相似度
+    pub score: f32,
+    pub label: Option<String>,
+}
+
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|v| *v /= norm);
+    }
+    vector
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+/// 按 ID 哈希确定层级，期望分布与随机层级相同（`P(level >= l) = M^-l`）
+fn level_for(id: &str) -> usize {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    id.hash(&mut hasher);
+    let unit = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+    let level = -(1.0 - unit).ln() / (MAX_NEIGHBORS as f64).ln();
+    level.floor() as usize
+}
+
+fn max_neighbors(level: usize) -> usize {
+    if level == 0 { MAX_NEIGHBORS * 2 } else { MAX_NEIGHBORS }
+}
+
+impl HnswIndex {
+    pub fn new(model_id: &str, dimension: usize) -> Self {
+        Self { version: INDEX_VERSION, model_id: model_id.to_string(), dimension, nodes: Vec::new(), entry_point: None, ids: HashMap::new() }
+    }
+
+    pub fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    /// 存活的分块数
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn vector(&self, node: u32) -> &[f32] {
+        &self.nodes[node as usize].vector
+    }
+
+    fn top_level(&self) -> usize {
+        self.entry_point.map(|e| self.nodes[e as usize].neighbors.len() - 1).unwrap_or(0)
+    }
+
+    /// 写入分块；同一 ID 再次写入时替换旧向量
+    pub fn insert(&mut self, id: &str, vector: Vec<f32>) -> Result<(), String> {
+        self.insert_labeled(id, None, vector)
+    }
+
+    pub fn insert_labeled(&mut self, id: &str, label: Option<String>, vector: Vec<f32>) -> Result<(), String> {
+        if vector.len() != self.dimension {
+            return Err(format!("Vector dimension {} does not match index dimension {}", vector.len(), self.dimension));
+        }
+        self.remove(id);
+
+        let vector = normalized(vector);
+        let level = level_for(id);
+        let node = self.nodes.len() as u32;
+        self.nodes.push(Node { id: id.to_string(), vector, label, neighbors: vec![Vec::new(); level + 1], deleted: false });
+        self.ids.insert(id.to_string(), node);
+
+        let Some(mut entry) = self.entry_point else {
+            self.entry_point = Some(node);
+            return Ok(());
+        };
+        let top_level = self.top_level();
+        let query = self.nodes[node as usize].vector.clone();
+
+        for layer in (level + 1..=top_level).rev() {
+            entry = self.greedy_closest(&query, entry, layer);
+        }
+        let mut entry_points = vec![entry];
+        for layer in (0..=level.min(top_level)).rev() {
+            let candidates = self.search_layer(&query, &entry_points, EF_CONSTRUCTION, layer);
+            let selected = self.select_neighbors(&candidates, max_neighbors(layer));
+            self.nodes[node as usize].neighbors[layer] = selected.clone();
+            for neighbor in selected {
+                self.connect(neighbor, node, layer);
+            }
+            entry_points = candidates.iter().map(|c| c.node).collect();
+        }
+
+        if level > top_level {
+            self.entry_point = Some(node);
+        }
+        Ok(())
+    }
+
+    /// 标记删除，返回分块是否存在
+    pub fn remove(&mut self, id: &str) -> bool {
+        match self.ids.remove(id) {
+            Some(node) => {
+                self.nodes[node as usize].deleted = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 删除 ID 以 `prefix` 开头的分块（如某个文件的全部分块），返回删除数量
+    pub fn remove_prefix(&mut self, prefix: &str) -> usize {
+        let ids: Vec<String> = self.ids.keys().filter(|id| id.starts_with(prefix)).cloned().collect();
+        ids.iter().filter(|id| self.remove(id)).count()
+    }
+
+    /// 返回与查询最相似的 `top_k` 个分块（按相似度降序）
+    pub fn search(&self, query: Vec<f32>, top_k: usize) -> Vec<SemanticHit> {
+        if top_k == 0 || query.len() != self.dimension || self.is_empty() {
+            return Vec::new();
+        }
+        let query = normalized(query);
+        let candidates: Vec<Candidate> = if self.len() < FLAT_SCAN_THRESHOLD {
+            self.ids.values().map(|&node| Candidate { distance: distance(&query, self.vector(node)), node }).collect()
+        } else {
+            let Some(mut entry) = self.entry_point else { return Vec::new() };
+            for layer in (1..=self.top_level()).rev() {
+                entry = self.greedy_closest(&query, entry, layer);
+            }
+            // 已删除节点仍参与遍历，候选集按删除比例放大
+            let deleted = self.nodes.len() - self.len();
+            let ef = (EF_SEARCH.max(top_k) * self.nodes.len()).div_ceil(self.nodes.len() - deleted);
+            self.search_layer(&query, &[entry], ef, 0)
+        };
+
+        let mut live: Vec<Candidate> = candidates.into_iter().filter(|c| !self.nodes[c.node as usize].deleted).collect();
+        live.sort();
+        live.truncate(top_k);
+        live.into_iter()
+            .map(|c| {
+                let node = &self.nodes[c.node as usize];
+                SemanticHit { id: node.id.clone(), score: 1.0 - c.distance, label: node.label.clone() }
+            })
+            .collect()
+    }
+
+    fn greedy_closest(&self, query: &[f32], mut entry: u32, layer: usize) -> u32 {
+        let mut best = distance(query, self.vector(entry));
+        loop {
+            let mut improved = false;
+            for &neighbor in &self.nodes[entry as usize].neighbors[layer] {
+                let d = distance(query, self.vector(neighbor));
+                if d < best {
+                    best = d;
+                    entry = neighbor;
+                    improved = true;
+                }
+            }
+            if !improved {
+                return entry;
+            }
+        }
+    }
+
+    /// 单层上的束搜索，返回最近的 `ef` 个节点（按距离升序）
+    fn search_layer(&self, query: &[f32], entry_points: &[u32], ef: usize, layer: usize) -> Vec<Candidate> {
+        let mut visited: HashSet<u32> = entry_points.iter().copied().collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> = BinaryHeap::new();
+        let mut results: BinaryHeap<Candidate> = BinaryHeap::new();
+        for &node in entry_points {
+            let candidate = Candidate { distance: distance(query, self.vector(node)), node };
+            candidates.push(Reverse(candidate));
+            results.push(candidate);
+        }
+        while results.len() > ef {
+            results.pop();
+        }
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            if results.len() >= ef && results.peek().is_some_and(|worst| current.distance > worst.distance) {
+                break;
+            }
+            for &neighbor in &self.nodes[current.node as usize].neighbors[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let candidate = Candidate { distance: distance(query, self.vector(neighbor)), node: neighbor };
+                if results.len() < ef || results.peek().is_some_and(|worst| candidate.distance < worst.distance) {
+                    candidates.push(Reverse(candidate));
+                    results.push(candidate);
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+        results.into_sorted_vec()
+    }
+
+    /// 启发式选邻：跳过离已选邻居比离目标更近的候选，保持图在各方向上的连通性
+    fn select_neighbors(&self, candidates: &[Candidate], limit: usize) -> Vec<u32> {
+        let mut selected: Vec<u32> = Vec::with_capacity(limit);
+        let mut pruned = Vec::new();
+        for candidate in candidates {
+            if selected.len() >= limit {
+                break;
+            }
+            let dominated = selected.iter().any(|&s| distance(self.vector(s), self.vector(candidate.node)) < candidate.distance);
+            if dominated { pruned.push(candidate.node) } else { selected.push(candidate.node) }
+        }
+        // 邻居不足时用被跳过的候选补齐
+        selected.extend(pruned.into_iter().take(limit.saturating_sub(selected.len())));
+        selected
+    }
+
+    fn connect(&mut self, from: u32, to: u32, layer: usize) {
+        self.nodes[from as usize].neighbors[layer].push(to);
+        if self.nodes[from as usize].neighbors[layer].len() <= max_neighbors(layer) {
+            return;
+        }
+        let base = self.vector(from);
+        let mut candidates: Vec<Candidate> = self.nodes[from as usize].neighbors[layer]
+            .iter()
+            .map(|&n| Candidate { distance: distance(base, self.vector(n)), node: n })
+            .collect();
+        candidates.sort();
+        let selected = self.select_neighbors(&candidates, max_neighbors(layer));
+        self.nodes[from as usize].neighbors[layer] = selected;
+    }
+
+    pub fn stats(&self) -> IndexStats {
+        let live: Vec<&Node> = self.ids.values().map(|&n| &self.nodes[n as usize]).collect();
+        let edges: usize = live.iter().map(|n| n.neighbors[0].len()).sum();
+        IndexStats {
+            model_id: self.model_id.clone(),
+            dimension: self.dimension,
+            chunks: live.len(),
+            deleted: self.nodes.len() - live.len(),
+            levels: if self.entry_point.is_some() { self.top_level() + 1 } else { 0 },
+            avg_degree: if live.is_empty() { 0.0 } else { edges as f64 / live.len() as f64 },
+        }
+    }
+
+    // ========================================================================
+    // Persistence
+    // ========================================================================
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        bincode::serde::encode_to_vec(self, bincode::config::standard()).map_err(|e| format!("Failed to encode index: {}", e))
+    }
+
+    /// 版本不兼容时返回 `None`
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let (mut index, _): (HnswIndex, usize) = bincode::serde::decode_from_slice(bytes, bincode::config::standard()).ok()?;
+        if index.version != INDEX_VERSION {
+            return None;
+        }
+        index.ids = index.nodes.iter().enumerate().filter(|(_, n)| !n.deleted).map(|(i, n)| (n.id.clone(), i as u32)).collect();
+        Some(index)
+    }
+
+    /// 删除比例过高时重建，回收已删除节点占用的空间
+    fn compacted(&self) -> Self {
+        let mut index = Self::new(&self.model_id, self.dimension);
+        let mut live: Vec<u32> = self.ids.values().copied().collect();
+        live.sort();
+        for node in live {
+            let node = &self.nodes[node as usize];
+            let _ = index.insert_labeled(&node.id, node.label.clone(), node.vector.clone());
+        }
+        index
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IndexStats {
+    pub model_id: String,
+    pub dimension: usize,
+    pub chunks: usize,
+    /// 已标记删除、尚未回收的节点
+    pub deleted: usize,
+    pub levels: usize,
+    /// 第 0 层的平均邻居数
+    pub avg_degree: f64,
+}
+
+fn index_path(project_root: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("index.hnsw")
+}
+
+fn load_index(project_root: &str) -> Option<HnswIndex> {
+    let bytes = std::fs::read(index_path(project_root)).ok()?;
+    let index = HnswIndex::from_bytes(&bytes);
+    if index.is_none() {
+        println!("[VectorIndex] Ignoring incompatible index for {}", project_root);
+    }
+    index
+}
+
+fn save_index(project_root: &str, index: &HnswIndex) -> Result<(), String> {
+    let path = index_path(project_root);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create .ifai directory: {}", e))?;
+    }
+    let bytes = index.to_bytes()?;
+    // 先写临时文件再重命名，避免中断时留下损坏的索引
+    let tmp = path.with_extension("hnsw.tmp");
+    std::fs::write(&tmp, bytes).map_err(|e| format!("Failed to write index: {}", e))?;
+    std::fs::rename(&tmp, &path).map_err(|e| format!("Failed to write index: {}", e))
+}
+
+/// 释放内存中的索引（已保存在磁盘上，下次使用时重新加载；由 `idle_manager` 调用）
+pub fn unload_indexes() -> bool {
+    let Some(indexes) = INDEXES.get() else { return false };
+    let Ok(mut indexes) = indexes.lock() else { return false };
+    let loaded = !indexes.is_empty();
+    indexes.clear();
+    loaded
+}
+
+/// 在已加载（或从磁盘加载）的项目索引上执行操作
+fn with_index<T>(project_root: &str, f: impl FnOnce(&mut Option<HnswIndex>) -> T) -> Result<T, String> {
+    let mut indexes = INDEXES.get_or_init(|| Mutex::new(HashMap::new())).lock().map_err(|e| e.to_string())?;
+    let mut slot = indexes.remove(project_root).or_else(|| load_index(project_root));
+    let result = f(&mut slot);
+    if let Some(index) = slot {
+        indexes.insert(project_root.to_string(), index);
+    }
+    Ok(result)
+}
+
+// ============================================================================
+// 上下文检索
+// ============================================================================
+
+/// 解析分块 ID（`path:start-end`，见 `code_chunker`）
+fn parse_chunk_id(id: &str) -> Option<(&str, usize, usize)> {
+    let (path, range) = id.rsplit_once(':')?;
+    let (start, end) = range.split_once('-')?;
+    Some((path, start.parse().ok()?, end.parse().ok()?))
+}
+
+/// 读取命中分块的当前内容并组装上下文；文件已删除或变短的分块跳过
+fn build_rag_result(project_root: &str, hits: &[SemanticHit]) -> RagResult {
+    let mut files: HashMap<&str, Option<String>> = HashMap::new();
+    let mut context = String::from("<codebase_context source=\"hnsw\">\n");
+    let mut references = Vec::new();
+    for hit in hits {
+        let Some((path, start, end)) = parse_chunk_id(&hit.id) else { continue };
+        let start = start.max(1);
+        if end < start {
+            continue;
+        }
+        let file = files.entry(path).or_insert_with(|| std::fs::read_to_string(Path::new(project_root).join(path)).ok());
+        let Some(content) = file.as_deref() else { continue };
+        let lines: Vec<&str> = content.lines().skip(start - 1).take(end - start + 1).collect();
+        if lines.is_empty() {
+            continue;
+        }
+        let content = lines.join("\n");
+        context.push_str(&format!(
+            "<chunk path=\"{}\" line_start=\"{}\" score=\"{:.2}\">\n{}\n</chunk>\n",
+            path, start, hit.score, content
+        ));
+        references.push(RagReference { file_path: path.to_string(), line_start: start, content });
+    }
+    context.push_str("</codebase_context>");
+    RagResult { context, references }
+}
+
+/// 用 HNSW 索引检索上下文；项目没有索引或向量模型已变化时返回 `None`
+async fn retrieve_from_index(project_root: &str, query: &str) -> Result<Option<RagResult>, String> {
+    // 先确认索引存在，避免没有索引时加载向量模型
+    let Some(index_model) = with_index(project_root, |slot| slot.as_ref().map(|index| index.model_id().to_string()))? else {
+        return Ok(None);
+    };
+    let provider = crate::embedding::provider_for_project(project_root)?;
+    if provider.model_id() != index_model {
+        println!("[VectorIndex] Index built with {}, skipping until rebuilt", index_model);
+        return Ok(None);
+    }
+    let vector = provider.embed(vec![query.to_string()]).await?.into_iter().next().unwrap_or_default();
+    let hits = with_index(project_root, |slot| slot.as_ref().map(|index| index.search(vector, RETRIEVE_TOP_K)).unwrap_or_default())?;
+    if hits.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(build_rag_result(project_root, &hits)))
+}
+
+/// 检索 `@codebase` 上下文：有 HNSW 索引时使用索引，否则（或索引检索失败时）交给核心 RAG
+pub async fn retrieve_context(rag: &dyn RagService, query: &str, project_root: &str) -> Result<RagResult, String> {
+    match retrieve_from_index(project_root, query).await {
+        Ok(Some(result)) if !result.references.is_empty() => {
+            println!("[VectorIndex] Retrieved {} chunks from HNSW index", result.references.len());
+            return Ok(result);
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("[VectorIndex] HNSW retrieval failed, falling back to core RAG: {}", e),
+    }
+    rag.retrieve_context(query, project_root).await
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexChunk {
+    pub id: String,
+    pub text: String,
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RagIndexStats {
+    /// 没有 HNSW 索引时为 `None`
+    pub ann: Option<IndexStats>,
+    pub ann_file_bytes: Option<u64>,
+    /// 核心 RAG 的扁平索引（`.ifai/index.bin`）大小
+    pub flat_index_bytes: Option<u64>,
+}
+
+/// 计算向量并写入索引；`replace_prefix` 指定时先删除该前缀下的旧分块
+async fn upsert_chunks(project_root: String, chunks: Vec<IndexChunk>, replace_prefix: Option<String>) -> Result<IndexStats, String> {
+    let provider = crate::embedding::provider_for_project(&project_root)?;
+    let model_id = provider.model_id();
+    let texts = chunks.iter().map(|c| c.text.clone()).collect();
+    let vectors = if chunks.is_empty() { Vec::new() } else { provider.embed(texts).await? };
+
+    tokio::task::spawn_blocking(move || {
+        with_index(&project_root, |slot| {
+            if slot.as_ref().is_some_and(|index| index.model_id() != model_id) {
+                println!("[VectorIndex] Embedding model changed to {}, rebuilding index", model_id);
+                *slot = None;
+            }
+            if let (Some(index), Some(prefix)) = (slot.as_mut(), &replace_prefix) {
+                index.remove_prefix(prefix);
+            }
+            for (chunk, vector) in chunks.into_iter().zip(vectors) {
+                let index = slot.get_or_insert_with(|| HnswIndex::new(&model_id, vector.len()));
+                index.insert_labeled(&chunk.id, chunk.label, vector)?;
+            }
+            let Some(index) = slot.as_ref() else { return Ok(HnswIndex::new(&model_id, 0).stats()) };
+            save_index(&project_root, index)?;
+            Ok(index.stats())
+        })?
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// 写入或更新分块；向量模型变化时丢弃旧索引
+#[tauri::command]
+pub async fn semantic_index_upsert(project_root: String, chunks: Vec<IndexChunk>) -> Result<IndexStats, String> {
+    upsert_chunks(project_root, chunks, None).await
+}
+
+/// 按语法结构切分文件并重新索引（替换该文件原有的分块）
+#[tauri::command]
+pub async fn semantic_index_file(project_root: String, path: String) -> Result<IndexStats, String> {
+    let rel_path = path.replace('\\', "/");
+    if crate::ifai_ignore::IfaiIgnore::load(&project_root).is_ignored(&rel_path, false) {
+        // 被 `.ifaiignore` 排除的文件只清理已有分块
+        return upsert_chunks(project_root, Vec::new(), Some(format!("{}:", rel_path))).await;
+    }
+    let code_chunks = crate::code_chunker::chunk_file(project_root.clone(), path, None).await?;
+    let Some(file_path) = code_chunks.first().map(|c| c.file_path.clone()) else {
+        return upsert_chunks(project_root, Vec::new(), None).await;
+    };
+    let chunks = code_chunks
+        .iter()
+        .map(|c| IndexChunk { id: c.id(), text: c.embedding_text(), label: c.symbol.as_ref().map(|s| s.qualified_name.clone()) })
+        .collect();
+    upsert_chunks(project_root, chunks, Some(format!("{}:", file_path))).await
+}
+
+/// 删除分块，返回实际删除的数量
+#[tauri::command]
+pub async fn semantic_index_remove(project_root: String, ids: Vec<String>) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || {
+        with_index(&project_root, |slot| {
+            let Some(index) = slot.as_mut() else { return Ok(0) };
+            let removed = ids.iter().filter(|id| index.remove(id)).count();
+            if index.nodes.len() > 2 * index.len() {
+                *index = index.compacted();
+            }
+            save_index(&project_root, index)?;
+            Ok(removed)
+        })?
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn semantic_index_search(project_root: String, query: String, top_k: Option<usize>) -> Result<Vec<SemanticHit>, String> {
+    let provider = crate::embedding::provider_for_project(&project_root)?;
+    let model_id = provider.model_id();
+    let vector = provider.embed(vec![query]).await?.into_iter().next().unwrap_or_default();
+    with_index(&project_root, |slot| match slot {
+        Some(index) if index.model_id() == model_id => Ok(index.search(vector, top_k.unwrap_or(10))),
+        Some(_) => Err("Embedding model changed; rebuild the semantic index".to_string()),
+        None => Ok(Vec::new()),
+    })?
+}
+
+#[tauri::command]
+pub async fn rag_index_stats(project_root: String) -> Result<RagIndexStats, String> {
+    let file_size = |path: PathBuf| std::fs::metadata(path).ok().map(|m| m.len());
+    let ann = with_index(&project_root, |slot| slot.as_ref().map(|index| index.stats()))?;
+    Ok(RagIndexStats {
+        ann,
+        ann_file_bytes: file_size(index_path(&project_root)),
+        flat_index_bytes: file_size(Path::new(&project_root).join(".ifai").join("index.bin")),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 确定性的伪随机向量
+    fn vectors(count: usize, dimension: usize) -> Vec<Vec<f32>> {
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        (0..count)
+            .map(|_| {
+                (0..dimension)
+                    .map(|_| {
+                        state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / (1u64 << 31) as f32) - 0.5
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn exact(index: &HnswIndex, query: &[f32], top_k: usize) -> Vec<String> {
+        let query = normalized(query.to_vec());
+        let mut all: Vec<(f32, String)> = index.ids.iter().map(|(id, &n)| (distance(&query, index.vector(n)), id.clone())).collect();
+        all.sort_by(|a, b| a.0.total_cmp(&b.0));
+        all.into_iter().take(top_k).map(|(_, id)| id).collect()
+    }
+
+    #[test]
+    fn test_build_rag_result_reads_current_lines() {
+        let dir = std::env::temp_dir().join(format!("ifai_hnsw_ctx_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "fn a() {}\nfn b() {}\nfn c() {}\n").unwrap();
+        let root = dir.to_string_lossy().to_string();
+        let hit = |id: &str| SemanticHit { id: id.to_string(), score: 0.9, label: None };
+
+        let result = build_rag_result(&root, &[hit("src/lib.rs:2-3"), hit("src/gone.rs:1-2"), hit("src/lib.rs:10-12")]);
+        assert_eq!(result.references.len(), 1);
+        assert_eq!(result.references[0].line_start, 2);
+        assert_eq!(result.references[0].content, "fn b() {}\nfn c() {}");
+        assert!(result.context.contains("<chunk path=\"src/lib.rs\" line_start=\"2\""));
+        assert_eq!(parse_chunk_id("a:b/c.rs:1-4"), Some(("a:b/c.rs", 1, 4)));
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_hnsw_recall() {
+        let data = vectors(1500, 16);
+        let mut index = HnswIndex::new("test", 16);
+        for (i, vector) in data.iter().enumerate() {
+            index.insert(&format!("chunk-{}", i), vector.clone()).unwrap();
+        }
+        assert!(index.len() >= FLAT_SCAN_THRESHOLD);
+        assert!(index.stats().levels > 1);
+
+        let queries = vectors(50, 16);
+        let mut found = 0;
+        for query in &queries {
+            let expected: HashSet<String> = exact(&index, query, 10).into_iter().collect();
+            found += index.search(query.clone(), 10).iter().filter(|hit| expected.contains(&hit.id)).count();
+        }
+        let recall = found as f64 / (queries.len() * 10) as f64;
+        assert!(recall >= 0.9, "recall@10 = {}", recall);
+    }
+
+    #[test]
+    fn test_update_remove_and_persist() {
+        let mut index = HnswIndex::new("test", 3);
+        index.insert("a", vec![1.0, 0.0, 0.0]).unwrap();
+        index.insert("b", vec![0.0, 1.0, 0.0]).unwrap();
+        index.insert_labeled("a", Some("Store::get".into()), vec![0.0, 0.0, 1.0]).unwrap();
+        assert!(index.insert("c", vec![1.0]).is_err());
+        assert_eq!(index.len(), 2);
+        let top = &index.search(vec![0.0, 0.0, 2.0], 1)[0];
+        assert_eq!((top.id.as_str(), top.label.as_deref()), ("a", Some("Store::get")));
+
+        assert!(index.remove("b"));
+        assert!(!index.remove("b"));
+        let hits = index.search(vec![0.0, 1.0, 0.0], 5);
+        assert_eq!(hits.iter().map(|h| h.id.as_str()).collect::<Vec<_>>(), vec!["a"]);
+
+        let restored = HnswIndex::from_bytes(&index.to_bytes().unwrap()).unwrap();
+        assert_eq!(restored.stats(), index.stats());
+        assert_eq!(restored.stats().deleted, 2);
+        assert_eq!(restored.compacted().stats().deleted, 0);
+        assert_eq!(restored.search(vec![0.0, 0.0, 1.0], 1), index.search(vec![0.0, 0.0, 1.0], 1));
+
+        let mut files = HnswIndex::new("test", 2);
+        for id in ["src/a.rs:1-5", "src/a.rs:6-9", "src/ab.rs:1-3"] {
+            files.insert(id, vec![1.0, 1.0]).unwrap();
+        }
+        assert_eq!(files.remove_prefix("src/a.rs:"), 2);
+        assert_eq!(files.search(vec![1.0, 1.0], 5).len(), 1);
+    }
+}
@@ -0,0 +1,238 @@
+/*!
+Verbosity - 回复长度与详略控制
+=============================
+
+会话级的详略预设，由后端强制执行而不是只靠前端提示：
+
+- `concise` / `normal` / `detailed`：向系统提示词追加对应的长度要求，并设置请求的 `max_tokens`
+- 硬上限（`hard_cap_tokens`）：流式输出超过上限时在句子边界截断，发送 `truncated` 事件并中止请求
+
+`max_tokens` 在请求构建层（`ai_utils`）应用，会话上下文由 `with_settings` 设置。
+*/
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+
+use crate::core_traits::ai::{Content, Message};
+use crate::token_counter::estimate_tokens;
+
+/// 硬上限的最小值（过小会截断几乎所有回复）
+const MIN_HARD_CAP_TOKENS: usize = 32;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerbosityPreset {
+    Concise,
+    #[default]
+    Normal,
+    Detailed,
+}
+
+impl VerbosityPreset {
+    /// 追加到系统提示词的要求；`normal` 不追加
+    pub fn instruction(self) -> Option<&'static str> {
+        match self {
+            Self::Concise => Some(
+                "Response length: be concise. Answer in a few sentences or a short list, \
+                 skip background explanations and restating the question, and only include code that is required.",
+            ),
+            Self::Normal => None,
+            Self::Detailed => Some(
+                "Response length: be thorough. Explain your reasoning step by step, cover edge cases and \
+                 alternatives, and include complete code examples where they help.",
+            ),
+        }
+    }
+
+    /// 请求的 `max_tokens`；`normal` 使用 Provider 默认值
+    pub fn max_tokens(self) -> Option<u32> {
+        match self {
+            Self::Concise => Some(1024),
+            Self::Normal => None,
+            Self::Detailed => Some(8192),
+        }
+    }
+}
+
+/// 会话级设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct VerbositySettings {
+    #[serde(default)]
+    pub preset: VerbosityPreset,
+    /// 流式输出的硬上限（估算 Token 数）
+    #[serde(default)]
+    pub hard_cap_tokens: Option<usize>,
+}
+
+// ============================================================================
+// State
+// ============================================================================
+
+static SESSION_SETTINGS: OnceLock<Mutex<HashMap<String, VerbositySettings>>> = OnceLock::new();
+
+tokio::task_local! {
+    /// 当前请求的设置（由 `with_settings` 设置）
+    static CURRENT_SETTINGS: VerbositySettings;
+}
+
+fn session_settings() -> &'static Mutex<HashMap<String, VerbositySettings>> {
+    SESSION_SETTINGS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// 会话的设置；未设置时为空
+pub fn settings_for(session_id: Option<&str>) -> Option<VerbositySettings> {
+    let id = session_id?;
+    session_settings().lock().ok()?.get(id).cloned()
+}
+
+/// 在指定设置的上下文中执行请求，使 `max_tokens` 生效
+pub async fn with_settings<F: Future>(settings: Option<VerbositySettings>, fut: F) -> F::Output {
+    match settings {
+        Some(settings) => CURRENT_SETTINGS.scope(settings, fut).await,
+        None => fut.await,
+    }
+}
+
+// ============================================================================
+// Enforcement
+// ============================================================================
+
+/// 将预设的长度要求追加到系统提示词（没有系统消息时插入一条）
+pub fn inject_instruction(messages: &mut Vec<Message>, settings: &VerbositySettings) {
+    let Some(instruction) = settings.preset.instruction() else { return };
+    match messages.first_mut() {
+        Some(first) if first.role == "system" => {
+            if let Content::Text(text) = &mut first.content {
+                text.push_str("\n\n");
+                text.push_str(instruction);
+                return;
+            }
+            messages.insert(1, system_message(instruction));
+        }
+        _ => messages.insert(0, system_message(instruction)),
+    }
+}
+
+fn system_message(text: &str) -> Message {
+    Message {
+        role: "system".to_string(),
+        content: Content::Text(text.to_string()),
+        tool_calls: None,
+        tool_call_id: None,
+    }
+}
+
+/// 在请求体中设置 `max_tokens`（请求构建层调用）
+pub fn apply_to_request(request_body: &mut Value) {
+    let Ok(Some(max_tokens)) = CURRENT_SETTINGS.try_with(|s| s.preset.max_tokens()) else { return };
+    println!("[Verbosity] max_tokens={}", max_tokens);
+    request_body["max_tokens"] = serde_json::json!(max_tokens);
+}
+
+/// 超过硬上限时返回截断后保留的文本（在最后一个句子边界处截断）
+pub fn enforce_hard_cap(text: &str, cap_tokens: usize) -> Option<&str> {
+    if estimate_tokens(text) <= cap_tokens {
+        return None;
+    }
+    // 二分查找不超过上限的最长前缀
+    let boundaries: Vec<usize> = text.char_indices().map(|(i, _)| i).chain(std::iter::once(text.len())).collect();
+    let (mut lo, mut hi) = (0, boundaries.len() - 1);
+    while lo < hi {
+        let mid = (lo + hi).div_ceil(2);
+        if estimate_tokens(&text[..boundaries[mid]]) <= cap_tokens {
+            lo = mid;
+        } else {
+            hi = mid - 1;
+        }
+    }
+    let prefix = &text[..boundaries[lo]];
+    Some(&prefix[..sentence_end(prefix)])
+}
+
+/// 最后一个完整句子的结束位置；没有句子边界时退回到最后一个换行或空白
+fn sentence_end(text: &str) -> usize {
+    let mut end = None;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next = chars.peek().map(|&(_, n)| n);
+        let is_end = match c {
+            '。' | '！' | '？' | '\n' => true,
+            '.' | '!' | '?' => !matches!(next, Some(n) if !n.is_whitespace()),
+            _ => false,
+        };
+        if is_end {
+            end = Some(i + c.len_utf8());
+        }
+    }
+    end.or_else(|| text.rfind(char::is_whitespace)).unwrap_or(text.len())
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_session_verbosity(session_id: String) -> VerbositySettings {
+    settings_for(Some(&session_id)).unwrap_or_default()
+}
+
+/// 设置（或清除，`settings` 为空时）会话的详略设置
+#[tauri::command]
+pub fn set_session_verbosity(session_id: String, settings: Option<VerbositySettings>) -> Result<(), String> {
+    if let Some(cap) = settings.as_ref().and_then(|s| s.hard_cap_tokens) {
+        if cap < MIN_HARD_CAP_TOKENS {
+            return Err(format!("hard_cap_tokens must be at least {}", MIN_HARD_CAP_TOKENS));
+        }
+    }
+    let mut map = session_settings().lock().map_err(|e| e.to_string())?;
+    match settings {
+        Some(s) => { map.insert(session_id, s); }
+        None => { map.remove(&session_id); }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject_instruction() {
+        let concise = VerbositySettings { preset: VerbosityPreset::Concise, hard_cap_tokens: None };
+        let mut messages = vec![system_message("You are IfAI"), Message { role: "user".into(), content: Content::Text("hi".into()), ..Default::default() }];
+        inject_instruction(&mut messages, &concise);
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(&messages[0].content, Content::Text(t) if t.starts_with("You are IfAI\n\nResponse length: be concise")));
+
+        let mut no_system = vec![Message { role: "user".into(), content: Content::Text("hi".into()), ..Default::default() }];
+        inject_instruction(&mut no_system, &VerbositySettings::default());
+        assert_eq!(no_system.len(), 1);
+        inject_instruction(&mut no_system, &VerbositySettings { preset: VerbosityPreset::Detailed, hard_cap_tokens: None });
+        assert_eq!(no_system[0].role, "system");
+    }
+
+    #[tokio::test]
+    async fn test_max_tokens_applied_in_scope() {
+        let mut body = serde_json::json!({ "model": "gpt-4o" });
+        apply_to_request(&mut body);
+        assert!(body.get("max_tokens").is_none());
+
+        let concise = VerbositySettings { preset: VerbosityPreset::Concise, hard_cap_tokens: None };
+        with_settings(Some(concise), async { apply_to_request(&mut body) }).await;
+        assert_eq!(body["max_tokens"], 1024);
+    }
+
+    #[test]
+    fn test_hard_cap_truncates_at_sentence() {
+        let text = "First sentence here. Second one follows! Third is cut mid way because it is long";
+        assert_eq!(enforce_hard_cap(text, 100), None);
+        assert_eq!(enforce_hard_cap(text, 12), Some("First sentence here. Second one follows!"));
+        assert_eq!(enforce_hard_cap(text, 6), Some("First sentence here."));
+        // 版本号中的点不算句子结束
+        assert_eq!(enforce_hard_cap("Use v1.2 of the crate and then upgrade later", 5), Some("Use v1.2 of the crate"));
+        assert_eq!(enforce_hard_cap("第一句话说完了。第二句话还没有说完就被截断了", 10), Some("第一句话说完了。"));
+    }
+}
@@ -0,0 +1,184 @@
+/*!
+Web Fetch - 网页抓取
+====================
+
+Agent 工具 `agent_fetch_url` 与需要在线文档的功能（如 `explain_error`）共用的抓取路径：
+- 仅允许 http / https
+- 统一超时与正文长度上限
+- HTML 经 html5ever 分词转为纯文本，跳过 script / style / nav / header / footer
+
+隐私级别与工具权限由调用方检查（Agent 运行器对 `agent_fetch_url` 走 `privacy::check_tool`）。
+*/
+
+use std::cell::{Cell, RefCell};
+use std::time::Duration;
+
+use html5ever::tendril::StrTendril;
+use html5ever::tokenizer::{
+    BufferQueue, Tag, TagKind, Token, TokenSink, TokenSinkResult, Tokenizer, TokenizerOpts,
+};
+use html5ever::tokenizer::states::RawKind;
+
+// ============================================================================
+// Constants
+// ============================================================================
+
+/// 请求超时
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Agent 工具返回的默认正文上限（字符）
+pub const DEFAULT_MAX_CHARS: usize = 20_000;
+
+/// 不计入正文的元素
+const SKIPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "nav", "header", "footer"];
+
+/// 不构成词边界的行内元素
+const INLINE_ELEMENTS: &[&str] = &["a", "abbr", "b", "code", "em", "i", "kbd", "small", "span", "strong", "sub", "sup", "var"];
+
+// ============================================================================
+// Fetch
+// ============================================================================
+
+/// 抓取 URL 并返回纯文本正文（最多 `max_chars` 个字符）
+pub async fn fetch_text(url: &str, max_chars: usize) -> Result<String, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("Invalid URL '{}': {}", url, e))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("Unsupported URL scheme '{}'", parsed.scheme()));
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+    let response = client
+        .get(parsed)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+
+    let is_html = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_none_or(|v| v.contains("html"));
+    let body = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read {}: {}", url, e))?;
+
+    println!("[WebFetch] Fetched {} ({} bytes)", url, body.len());
+    let text = if is_html { html_to_text(&body) } else { body.trim().to_string() };
+    Ok(text.chars().take(max_chars).collect())
+}
+
+// ============================================================================
+// HTML → Text
+// ============================================================================
+
+/// 收集正文文本的分词器输出
+#[derive(Default)]
+struct TextSink {
+    text: RefCell<String>,
+    /// 当前所在的被跳过元素层数
+    skip_depth: Cell<usize>,
+    /// 下一个字符前是否需要补一个空格（连续空白折叠为一个）
+    pending_space: Cell<bool>,
+}
+
+impl TextSink {
+    fn push_text(&self, chunk: &str) {
+        if self.skip_depth.get() > 0 {
+            return;
+        }
+        let mut text = self.text.borrow_mut();
+        for c in chunk.chars() {
+            if c.is_whitespace() {
+                self.pending_space.set(true);
+                continue;
+            }
+            if self.pending_space.replace(false) && !text.is_empty() {
+                text.push(' ');
+            }
+            text.push(c);
+        }
+    }
+
+    fn handle_tag(&self, tag: &Tag) -> TokenSinkResult<()> {
+        let name: &str = &tag.name;
+        let skipped = SKIPPED_ELEMENTS.contains(&name);
+        match tag.kind {
+            TagKind::StartTag => {
+                if skipped && !tag.self_closing {
+                    self.skip_depth.set(self.skip_depth.get() + 1);
+                }
+                // 块级标签视为词边界
+                if !INLINE_ELEMENTS.contains(&name) {
+                    self.push_text(" ");
+                }
+                match name {
+                    "script" => TokenSinkResult::RawData(RawKind::ScriptData),
+                    "style" | "noscript" => TokenSinkResult::RawData(RawKind::Rawtext),
+                    _ => TokenSinkResult::Continue,
+                }
+            }
+            TagKind::EndTag => {
+                if skipped {
+                    self.skip_depth.set(self.skip_depth.get().saturating_sub(1));
+                }
+                if !INLINE_ELEMENTS.contains(&name) {
+                    self.push_text(" ");
+                }
+                TokenSinkResult::Continue
+            }
+        }
+    }
+}
+
+impl TokenSink for TextSink {
+    type Handle = ();
+
+    fn process_token(&self, token: Token, _line_number: u64) -> TokenSinkResult<()> {
+        match token {
+            Token::TagToken(tag) => self.handle_tag(&tag),
+            Token::CharacterTokens(chars) => {
+                self.push_text(&chars);
+                TokenSinkResult::Continue
+            }
+            _ => TokenSinkResult::Continue,
+        }
+    }
+}
+
+/// 将 HTML 转为空白折叠后的纯文本
+pub fn html_to_text(html: &str) -> String {
+    let input = BufferQueue::default();
+    input.push_back(StrTendril::from_slice(html));
+    let tokenizer = Tokenizer::new(TextSink::default(), TokenizerOpts::default());
+    let _ = tokenizer.feed(&input);
+    tokenizer.end();
+    tokenizer.sink.text.into_inner()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = r#"<html><head><style>p { color: red; }</style><script>if (a < b) { x = "</p>"; }</script></head>
+<body><nav><a href="/">Home</a></nav><h1>Error code E0308</h1>
+<p>Expected   type <code>u32</code>,&nbsp;found <code>&amp;str</code>.</p><footer>© Rust</footer></body></html>"#;
+        assert_eq!(html_to_text(html), "Error code E0308 Expected type u32, found &str.");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_text_rejects_non_http() {
+        assert!(fetch_text("file:///etc/passwd", 100).await.is_err());
+        assert!(fetch_text("not a url", 100).await.is_err());
+    }
+}
@@ -0,0 +1,418 @@
+use tauri::command;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::fs;
+
+/// Per-package context profile for monorepos
+///
+/// Packages are detected from cargo workspace members, `pnpm-workspace.yaml`
+/// and `package.json` workspaces. User overrides live in `.ifai/profiles.json`,
+/// keyed by the package path relative to the project root.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PackageProfile {
+    /// Package name (crate name / npm package name)
+    pub name: String,
+
+    /// Package directory relative to the project root (uses `/`)
+    pub path: String,
+
+    /// Workspace kind: "cargo", "pnpm" or "npm"
+    pub kind: String,
+
+    /// Command used to build only this package
+    pub build_command: Option<String>,
+
+    /// Command used to test only this package
+    pub test_command: Option<String>,
+
+    /// Files worth reading first when working in this package
+    pub key_files: Vec<String>,
+
+    /// Instructions used instead of the repo-wide `custom_instructions` of IFAI.md
+    pub prompt_addendum: Option<String>,
+}
+
+/// User-editable override for a detected package (all fields optional)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PackageProfileOverride {
+    pub build_command: Option<String>,
+    pub test_command: Option<String>,
+    pub key_files: Option<Vec<String>>,
+    pub prompt_addendum: Option<String>,
+}
+
+/// Get the path to `.ifai/profiles.json`
+fn get_overrides_path(project_root: &str) -> PathBuf {
+    Path::new(project_root).join(".ifai").join("profiles.json")
+}
+
+fn load_overrides(project_root: &str) -> HashMap<String, PackageProfileOverride> {
+    fs::read_to_string(get_overrides_path(project_root))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// Normalize a relative path to forward slashes without leading `./` or trailing `/`
+fn normalize_rel(path: &str) -> String {
+    path.replace('\\', "/")
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+// ============================================================================
+// Workspace detection
+// ============================================================================
+
+/// Extract `members = [...]` from the `[workspace]` table of a Cargo.toml
+fn parse_cargo_workspace_members(content: &str) -> Vec<String> {
+    let mut in_workspace = false;
+    let mut collecting = false;
+    let mut buffer = String::new();
+
+    for line in content.lines() {
+        let trimmed = line.split('#').next().unwrap_or("").trim();
+        if !collecting && trimmed.starts_with('[') {
+            in_workspace = trimmed == "[workspace]";
+            continue;
+        }
+        if in_workspace && !collecting && trimmed.starts_with("members") {
+            if let Some(idx) = trimmed.find('[') {
+                buffer.push_str(&trimmed[idx + 1..]);
+                collecting = true;
+            }
+        } else if collecting {
+            buffer.push_str(trimmed);
+        }
+        if collecting && buffer.contains(']') {
+            break;
+        }
+    }
+
+    let list = buffer.split(']').next().unwrap_or("");
+    list.split(',')
+        .map(|s| s.trim().trim_matches('"').trim_matches('\'').to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Read the `name = "..."` key of the `[package]` table of a Cargo package
+fn read_cargo_package_name(dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    parse_cargo_package_name(&content)
+}
+
+fn parse_cargo_package_name(content: &str) -> Option<String> {
+    let mut in_package = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_package = trimmed.split('#').next().unwrap_or("").trim() == "[package]";
+            continue;
+        }
+        if !in_package {
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once('=') else { continue };
+        if key.trim() != "name" {
+            continue;
+        }
+        let value = value.trim();
+        let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        let end = value[1..].find(quote)?;
+        return Some(value[1..1 + end].to_string());
+    }
+    None
+}
+
+/// Read the `name` field from a package.json
+fn read_npm_package_name(dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(dir.join("package.json")).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&content).ok()?;
+    json.get("name").and_then(|v| v.as_str()).map(|s| s.to_string())
+}
+
+/// Expand workspace member globs (e.g. `crates/*`) into directories
+fn expand_member_globs(root: &Path, patterns: &[String], marker: &str) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for pattern in patterns {
+        if pattern.starts_with('!') {
+            continue;
+        }
+        let full = root.join(pattern).to_string_lossy().to_string();
+        if let Ok(paths) = glob::glob(&full) {
+            for path in paths.flatten() {
+                if path.is_dir() && path.join(marker).exists() && !dirs.contains(&path) {
+                    dirs.push(path);
+                }
+            }
+        }
+    }
+    dirs
+}
+
+fn existing_key_files(dir: &Path, rel: &str, candidates: &[&str]) -> Vec<String> {
+    candidates.iter()
+        .filter(|f| dir.join(f).exists())
+        .map(|f| if rel.is_empty() { f.to_string() } else { format!("{}/{}", rel, f) })
+        .collect()
+}
+
+fn relative_to(root: &Path, dir: &Path) -> String {
+    normalize_rel(&dir.strip_prefix(root).unwrap_or(dir).to_string_lossy())
+}
+
+/// Detect all workspace packages under the project root (without overrides)
+pub fn detect_packages(project_root: &str) -> Vec<PackageProfile> {
+    let root = Path::new(project_root);
+    let mut packages = Vec::new();
+
+    // Cargo workspace
+    if let Ok(content) = fs::read_to_string(root.join("Cargo.toml")) {
+        let members = parse_cargo_workspace_members(&content);
+        for dir in expand_member_globs(root, &members, "Cargo.toml") {
+            let rel = relative_to(root, &dir);
+            let name = read_cargo_package_name(&dir).unwrap_or_else(|| rel.clone());
+            packages.push(PackageProfile {
+                build_command: Some(format!("cargo build -p {}", name)),
+                test_command: Some(format!("cargo test -p {}", name)),
+                key_files: existing_key_files(&dir, &rel, &["Cargo.toml", "src/lib.rs", "src/main.rs", "README.md"]),
+                name,
+                path: rel,
+                kind: "cargo".to_string(),
+                prompt_addendum: None,
+            });
+        }
+    }
+
+    // pnpm / npm workspaces
+    let mut js_patterns: Vec<String> = Vec::new();
+    let mut js_kind = "npm";
+    if let Ok(content) = fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        if let Ok(yaml) = serde_yaml::from_str::<serde_yaml::Value>(&content) {
+            if let Some(list) = yaml.get("packages").and_then(|v| v.as_sequence()) {
+                js_patterns.extend(list.iter().filter_map(|v| v.as_str().map(|s| s.to_string())));
+                js_kind = "pnpm";
+            }
+        }
+    }
+    if js_patterns.is_empty() {
+        if let Ok(content) = fs::read_to_string(root.join("package.json")) {
+            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
+                let workspaces = json.get("workspaces");
+                let list = workspaces
+                    .and_then(|w| w.as_array())
+                    .or_else(|| workspaces.and_then(|w| w.get("packages")).and_then(|p| p.as_array()));
+                if let Some(list) = list {
+                    js_patterns.extend(list.iter().filter_map(|v| v.as_str().map(|s| s.to_string())));
+                }
+            }
+        }
+    }
+    for dir in expand_member_globs(root, &js_patterns, "package.json") {
+        let rel = relative_to(root, &dir);
+        let name = read_npm_package_name(&dir).unwrap_or_else(|| rel.clone());
+        let (build, test) = if js_kind == "pnpm" {
+            (format!("pnpm --filter {} build", name), format!("pnpm --filter {} test", name))
+        } else {
+            (format!("npm run build --workspace {}", rel), format!("npm test --workspace {}", rel))
+        };
+        packages.push(PackageProfile {
+            build_command: Some(build),
+            test_command: Some(test),
+            key_files: existing_key_files(&dir, &rel, &["package.json", "src/index.ts", "src/index.js", "README.md"]),
+            name,
+            path: rel,
+            kind: js_kind.to_string(),
+            prompt_addendum: None,
+        });
+    }
+
+    packages
+}
+
+/// Detect packages and apply `.ifai/profiles.json` overrides
+pub fn load_profiles(project_root: &str) -> Vec<PackageProfile> {
+    let overrides = load_overrides(project_root);
+    detect_packages(project_root)
+        .into_iter()
+        .map(|mut profile| {
+            if let Some(o) = overrides.get(&profile.path) {
+                if o.build_command.is_some() { profile.build_command = o.build_command.clone(); }
+                if o.test_command.is_some() { profile.test_command = o.test_command.clone(); }
+                if let Some(files) = &o.key_files { profile.key_files = files.clone(); }
+                if o.prompt_addendum.is_some() { profile.prompt_addendum = o.prompt_addendum.clone(); }
+            }
+            profile
+        })
+        .collect()
+}
+
+/// Pick the most specific package containing `scope_path` (absolute or relative)
+pub fn resolve_profile(profiles: &[PackageProfile], project_root: &str, scope_path: &str) -> Option<PackageProfile> {
+    let scope = Path::new(scope_path);
+    let rel = if scope.is_absolute() {
+        relative_to(Path::new(project_root), scope)
+    } else {
+        normalize_rel(scope_path)
+    };
+
+    profiles.iter()
+        .filter(|p| !p.path.is_empty() && (rel == p.path || rel.starts_with(&format!("{}/", p.path))))
+        .max_by_key(|p| p.path.len())
+        .cloned()
+}
+
+/// Render a profile as a system prompt section
+///
+/// The `prompt_addendum` is not part of this section: it replaces the repo-wide
+/// custom instructions (see `prompt_manager`).
+pub fn profile_prompt_section(profile: &PackageProfile) -> String {
+    let mut section = format!(
+        "\n\n# Package Context\nThis task is scoped to the `{}` package at `{}` ({} workspace).\n",
+        profile.name, profile.path, profile.kind
+    );
+    if let Some(cmd) = &profile.build_command {
+        section.push_str(&format!("- Build only this package with: `{}`\n", cmd));
+    }
+    if let Some(cmd) = &profile.test_command {
+        section.push_str(&format!("- Test only this package with: `{}`\n", cmd));
+    }
+    if !profile.key_files.is_empty() {
+        section.push_str(&format!("- Key files: {}\n", profile.key_files.join(", ")));
+    }
+    section
+}
+
+/// Profile of the package containing `scope_path`, if any
+pub fn profile_for_scope(project_root: &str, scope_path: &str) -> Option<PackageProfile> {
+    let profiles = load_profiles(project_root);
+    let profile = resolve_profile(&profiles, project_root, scope_path)?;
+    println!("[WorkspaceProfiles] Applying profile '{}' for scope {}", profile.name, scope_path);
+    Some(profile)
+}
+
+// ============================================================================
+// Tauri Commands
+// ============================================================================
+
+/// List detected workspace packages with overrides applied
+#[command]
+pub async fn list_workspace_profiles(project_root: String) -> Result<Vec<PackageProfile>, String> {
+    Ok(load_profiles(&project_root))
+}
+
+/// Get the profile that applies to a file or directory
+#[command]
+pub async fn get_workspace_profile(project_root: String, scope_path: String) -> Result<Option<PackageProfile>, String> {
+    let profiles = load_profiles(&project_root);
+    Ok(resolve_profile(&profiles, &project_root, &scope_path))
+}
+
+/// Save (or clear, when `profile_override` is None) the override for a package path
+#[command]
+pub async fn save_workspace_profile_override(
+    project_root: String,
+    package_path: String,
+    profile_override: Option<PackageProfileOverride>,
+) -> Result<(), String> {
+    let mut overrides = load_overrides(&project_root);
+    let key = normalize_rel(&package_path);
+    match profile_override {
+        Some(o) => { overrides.insert(key, o); }
+        None => { overrides.remove(&key); }
+    }
+
+    let path = get_overrides_path(&project_root);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create .ifai directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&overrides)
+        .map_err(|e| format!("Failed to serialize profiles: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to write profiles.json: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(path: &str) -> PackageProfile {
+        PackageProfile {
+            name: path.to_string(),
+            path: path.to_string(),
+            kind: "cargo".to_string(),
+            build_command: None,
+            test_command: None,
+            key_files: vec![],
+            prompt_addendum: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_cargo_workspace_members() {
+        let content = r#"
+[package]
+name = "root"
+
+[workspace]
+members = [
+    "crates/*", # all crates
+    "tools/cli",
+]
+"#;
+        assert_eq!(parse_cargo_workspace_members(content), vec!["crates/*", "tools/cli"]);
+        assert!(parse_cargo_workspace_members("[package]\nname = \"x\"").is_empty());
+    }
+
+    #[test]
+    fn test_parse_cargo_package_name() {
+        let content = r#"
+[workspace]
+name = "not-a-package"
+
+[package] # the crate
+name-suffix = "wrong"
+name = 'my-crate'  # comment
+version = "0.1.0"
+
+[dependencies]
+name = "dep"
+"#;
+        assert_eq!(parse_cargo_package_name(content).as_deref(), Some("my-crate"));
+        assert_eq!(parse_cargo_package_name("[dependencies]\nname = \"dep\"\n"), None);
+        assert_eq!(parse_cargo_package_name("[package]\nname.workspace = true\n"), None);
+    }
+
+    #[test]
+    fn test_resolve_profile_longest_prefix() {
+        let profiles = vec![profile("packages/app"), profile("packages/app/plugins/x"), profile("packages/lib")];
+        let hit = resolve_profile(&profiles, "/repo", "/repo/packages/app/plugins/x/src/main.ts").unwrap();
+        assert_eq!(hit.path, "packages/app/plugins/x");
+        let hit = resolve_profile(&profiles, "/repo", "packages/app/src").unwrap();
+        assert_eq!(hit.path, "packages/app");
+        assert!(resolve_profile(&profiles, "/repo", "packages/application").is_none());
+    }
+
+    #[test]
+    fn test_detect_cargo_workspace() {
+        let dir = std::env::temp_dir().join(format!("ifai_profiles_{}", chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)));
+        fs::create_dir_all(dir.join("crates/core/src")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[workspace]\nmembers = [\"crates/*\"]\n").unwrap();
+        fs::write(dir.join("crates/core/Cargo.toml"), "[package]\nname = \"my-core\"\n").unwrap();
+        fs::write(dir.join("crates/core/src/lib.rs"), "").unwrap();
+
+        let root = dir.to_string_lossy().to_string();
+        let packages = detect_packages(&root);
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].name, "my-core");
+        assert_eq!(packages[0].path, "crates/core");
+        assert_eq!(packages[0].test_command.as_deref(), Some("cargo test -p my-core"));
+        assert!(packages[0].key_files.contains(&"crates/core/src/lib.rs".to_string()));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,5 +1,6 @@
 import React, { useState, useEffect, useCallback, useMemo, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import { useFileStore } from '../../stores/fileStore';
 import { useLayoutStore } from '../../stores/layoutStore';
 import { Loader2, Search, X, ChevronDown, CaseSensitive, Regex, RotateCcw, Clock } from 'lucide-react';
@@ -14,6 +15,14 @@ interface SearchResult {
   content: string;
 }
 
+interface SearchResultBatch {
+  search_id: string;
+  matches: SearchResult[];
+  done: boolean;
+  total: number;
+  truncated: boolean;
+}
+
 interface SearchHistory {
   query: string;
   timestamp: number;
@@ -94,6 +103,8 @@ export const SearchPanel = () => {
   const [searchHistory, setSearchHistory] = useState<SearchHistory[]>([]);
   const [historyPosition, setHistoryPosition] = useState({ top: 0, left: 0 });
   const optionsRef = useRef<HTMLDivElement>(null);
+  // 当前流式搜索的 id，旧搜索的批次直接丢弃
+  const searchIdRef = useRef<string | null>(null);
   const historyRef = useRef<HTMLDivElement>(null);
   const historyButtonRef = useRef<HTMLButtonElement>(null);
 
@@ -164,29 +175,50 @@ export const SearchPanel = () => {
 
   const performSearch = async (searchQuery: string) => {
     if (!searchQuery.trim() || !rootPath) {
+      searchIdRef.current = null;
       setResults([]);
       return;
     }
 
     setIsSearching(true);
+    setResults([]);
     console.log('[Search] Searching for:', searchQuery, 'options:', options);
+    const searchId = uuidv4();
+    searchIdRef.current = searchId;
+
+    // 结果通过 search:results 事件分批到达，大仓库中也能边搜边显示
+    let unlisten: UnlistenFn | null = null;
+    unlisten = await listen<SearchResultBatch>('search:results', (event) => {
+      const batch = event.payload;
+      if (batch.search_id !== searchId) return;
+      if (searchIdRef.current === searchId && batch.matches.length > 0) {
+        setResults(prev => [...prev, ...batch.matches]);
+      }
+      if (batch.done) {
+        console.log('[Search] Found matches:', batch.total, batch.truncated ? '(truncated)' : '');
+        unlisten?.();
+        if (searchIdRef.current === searchId) setIsSearching(false);
+      }
+    });
+
     try {
-      const matches = await invoke<SearchResult[]>('search_in_files', {
+      await invoke('search_in_files', {
         rootPath,
         query: searchQuery,
-        caseSensitive: options.caseSensitive
+        caseSensitive: options.caseSensitive,
+        options: { literal: !options.useRegex, search_id: searchId }
       });
-      console.log('[Search] Found matches:', matches.length);
-      setResults(matches);
       // Always save to history, even if no results
       saveToHistory(searchQuery);
     } catch (err) {
       console.error('[Search] Search failed:', err);
-      setResults([]);
+      unlisten?.();
+      if (searchIdRef.current === searchId) {
+        setResults([]);
+        setIsSearching(false);
+      }
       // Still save to history even on error
       saveToHistory(searchQuery);
-    } finally {
-      setIsSearching(false);
     }
   };
 
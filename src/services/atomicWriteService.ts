@@ -28,6 +28,17 @@ export interface AtomicWriteResult {
     applied_files: string[];
     conflicts: string[];
     errors: string[];
+    /** 提交失败后恢复到提交前状态的文件 */
+    rolled_back?: string[];
+    /** 提交前的风险评估（高风险且未确认时不提交） */
+    risk?: RiskReport;
+}
+
+export interface RiskReport {
+    score: number;
+    level: 'low' | 'medium' | 'high';
+    reasons: { kind: string; path: string; detail: string; weight: number }[];
+    llm_review?: string;
 }
 
 export interface AtomicSession {
@@ -97,14 +108,25 @@ class AtomicWriteService {
     /**
      * 提交原子写入会话
      */
-    async commit(sessionId: string): Promise<AtomicWriteResult> {
+    async commit(sessionId: string, acknowledgeRisk = false): Promise<AtomicWriteResult> {
         try {
             const result = await invoke<AtomicWriteResult>('atomic_write_commit', {
-                sessionId
+                sessionId,
+                acknowledgeRisk
             });
 
             console.log('[AtomicWrite] Commit result:', result);
 
+            // 高风险修改需要确认后再提交（会话仍保留）
+            if (!result.success && !acknowledgeRisk && result.risk?.level === 'high' && result.applied_files.length === 0) {
+                const reasons = result.risk.reasons.map(r => `- ${r.path}: ${r.detail}`).join('\n');
+                if (confirm(`该修改风险较高（${result.risk.score}/100）：\n${reasons}\n\n仍要应用吗？`)) {
+                    return this.commit(sessionId, true);
+                }
+                toast.error('已取消高风险修改');
+                return result;
+            }
+
             if (result.success) {
                 toast.success(`已应用 ${result.applied_files.length} 个文件变更`);
             } else {
@@ -114,6 +136,9 @@ class AtomicWriteService {
                 if (result.errors.length > 0) {
                     toast.error(`写入失败: ${result.errors.join(', ')}`);
                 }
+                if (result.rolled_back?.length) {
+                    toast(`已回滚 ${result.rolled_back.length} 个文件`);
+                }
             }
 
             return result;
@@ -0,0 +1,35 @@
+pub const DEFAULT_CAPACITY: usize = 64;
+
+pub type StoreResult<T> = Result<T, StoreError>;
+
+#[derive(Debug)]
+pub enum StoreError {
+    NotFound(String),
+    Full,
+}
+
+pub struct Store<T> {
+    items: Vec<T>,
+    capacity: usize,
+}
+
+impl<T: Clone> Store<T> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { items: Vec::with_capacity(capacity), capacity }
+    }
+
+    pub fn push(&mut self, item: T) -> StoreResult<()> {
+        if self.items.len() >= self.capacity {
+            return Err(StoreError::Full);
+        }
+        self.items.push(item);
+        Ok(())
+    }
+}
+
+pub fn describe(err: &StoreError) -> String {
+    match err {
+        StoreError::NotFound(key) => format!("missing: {}", key),
+        StoreError::Full => "store is full".to_string(),
+    }
+}
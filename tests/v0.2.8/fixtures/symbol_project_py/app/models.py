@@ -0,0 +1,16 @@
+from dataclasses import dataclass
+
+
+@dataclass
+class Config:
+    path: str
+    retries: int = 3
+
+
+class User:
+    def __init__(self, user_id: int, name: str):
+        self.user_id = user_id
+        self.name = name
+
+    def display_name(self) -> str:
+        return f"{self.name} (#{self.user_id})"
@@ -0,0 +1,10 @@
+from .models import Config, User
+
+
+def load_users(config: Config) -> list[User]:
+    with open(config.path) as f:
+        return [User(i, line.strip()) for i, line in enumerate(f)]
+
+
+async def refresh(config: Config) -> int:
+    return len(load_users(config))
@@ -0,0 +1,16 @@
+interface AvatarProps {
+  url: string;
+  size?: number;
+}
+
+export function Avatar({ url, size = 32 }: AvatarProps) {
+  return <img src={url} width={size} height={size} />;
+}
+
+export class AvatarCache {
+  private urls: string[] = [];
+
+  remember(url: string) {
+    this.urls.push(url);
+  }
+}
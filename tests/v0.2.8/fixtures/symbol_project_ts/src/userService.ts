@@ -0,0 +1,29 @@
+export interface User {
+  id: number;
+  name: string;
+}
+
+export type UserId = number;
+
+export enum Role {
+  Admin,
+  Member,
+}
+
+export class UserService {
+  private users = new Map<UserId, User>();
+
+  constructor(private readonly prefix: string) {}
+
+  getUser(id: UserId): User | undefined {
+    return this.users.get(id);
+  }
+
+  async save(user: User): Promise<void> {
+    this.users.set(user.id, user);
+  }
+}
+
+export function formatUser(user: User): string {
+  return `${user.id}:${user.name}`;
+}